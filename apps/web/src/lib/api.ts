@@ -4,6 +4,20 @@ const base = import.meta?.env?.VITE_BACKEND_URL || ""; // Backend URL, defaults
 
 type HttpMethod = "GET" | "POST" | "PUT" | "DELETE";
 
+// Error responses carry a stable `code` (e.g. TIER_LIMIT_EXCEEDED) to branch on
+export class ApiError extends Error {
+  constructor(
+    message: string,
+    public status: number,
+    public code?: string,
+    public details?: unknown,
+    // Quote it when reporting the error, it finds the request in the server logs
+    public requestId?: string
+  ) {
+    super(message);
+  }
+}
+
 async function request<T>(
   path: string,
   options: { method?: HttpMethod; body?: any } = {}
@@ -22,11 +36,17 @@ async function request<T>(
 
   if (!res.ok) {
     let msg = `${res.status} ${res.statusText}`;
+    let code: string | undefined;
+    let details: unknown;
+    let requestId = res.headers.get("x-request-id") ?? undefined;
     try {
       const data = await res.json();
       msg = data.message || msg;
+      code = data.code;
+      details = data.details;
+      requestId = data.request_id ?? requestId;
     } catch {}
-    throw new Error(msg);
+    throw new ApiError(msg, res.status, code, details, requestId);
   }
   return (await res.json()) as T;
 }
@@ -0,0 +1,9 @@
+-- Revert: Soft delete for expense entries
+BEGIN;
+
+DROP INDEX IF EXISTS idx_expense_entries_group_active;
+
+ALTER TABLE expense_entries
+DROP COLUMN deleted_at;
+
+COMMIT;
@@ -0,0 +1,11 @@
+-- Soft delete for expense entries
+BEGIN;
+
+ALTER TABLE expense_entries
+ADD COLUMN deleted_at TIMESTAMPTZ;
+
+CREATE INDEX idx_expense_entries_group_active
+ON expense_entries (group_uid, created_at)
+WHERE deleted_at IS NULL;
+
+COMMIT;
@@ -0,0 +1,6 @@
+-- Revert: Income entries
+BEGIN;
+
+DROP TABLE IF EXISTS income_entries;
+
+COMMIT;
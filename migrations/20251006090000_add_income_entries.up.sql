@@ -0,0 +1,17 @@
+-- Income entries (salary, transfers, etc.)
+BEGIN;
+
+CREATE TABLE IF NOT EXISTS income_entries (
+  uid UUID PRIMARY KEY,
+  source VARCHAR NOT NULL,
+  amount NUMERIC(12,2) NOT NULL,
+  created_by VARCHAR NOT NULL, -- freeform user identifier (e.g. email or chat name)
+  group_uid UUID NOT NULL REFERENCES expense_groups(uid),
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  CONSTRAINT ck_income_amount_non_negative CHECK (amount >= 0)
+);
+
+CREATE INDEX IF NOT EXISTS idx_income_group_created_at ON income_entries(group_uid, created_at DESC);
+
+COMMIT;
@@ -0,0 +1,7 @@
+-- Revert: Recurring expenses
+BEGIN;
+
+DROP TABLE IF EXISTS recurring_expenses;
+DROP TYPE IF EXISTS recurring_cadence;
+
+COMMIT;
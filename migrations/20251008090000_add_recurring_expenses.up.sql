@@ -0,0 +1,34 @@
+-- Recurring expenses (rent, subscriptions, ...) materialized by the scheduler
+BEGIN;
+
+DO $$
+BEGIN
+  IF NOT EXISTS (SELECT 1 FROM pg_type WHERE typname = 'recurring_cadence') THEN
+    CREATE TYPE recurring_cadence AS ENUM ('monthly', 'weekly');
+  END IF;
+END$$;
+
+CREATE TABLE IF NOT EXISTS recurring_expenses (
+  uid UUID PRIMARY KEY,
+  group_uid UUID NOT NULL REFERENCES expense_groups(uid),
+  product VARCHAR NOT NULL,
+  price NUMERIC(12,2) NOT NULL,
+  category_uid UUID NULL,
+  cadence recurring_cadence NOT NULL,
+  -- day of month (1-31) for monthly, ISO weekday (1 = Monday .. 7 = Sunday) for weekly
+  run_day SMALLINT NOT NULL,
+  active BOOLEAN NOT NULL DEFAULT TRUE,
+  last_run_on DATE NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  CONSTRAINT ck_recurring_price_non_negative CHECK (price >= 0),
+  CONSTRAINT ck_recurring_run_day CHECK (
+    (cadence = 'monthly' AND run_day BETWEEN 1 AND 31) OR
+    (cadence = 'weekly' AND run_day BETWEEN 1 AND 7)
+  )
+);
+
+CREATE INDEX IF NOT EXISTS idx_recurring_group_uid ON recurring_expenses(group_uid);
+CREATE INDEX IF NOT EXISTS idx_recurring_active ON recurring_expenses(active);
+
+COMMIT;
@@ -0,0 +1,6 @@
+BEGIN;
+
+DROP INDEX IF EXISTS idx_expense_entries_product_trgm;
+DROP INDEX IF EXISTS idx_expense_entries_product_fts;
+
+COMMIT;
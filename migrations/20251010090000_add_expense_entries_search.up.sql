@@ -0,0 +1,14 @@
+-- Full-text and fuzzy search on expense products
+BEGIN;
+
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE INDEX idx_expense_entries_product_fts
+ON expense_entries USING GIN (to_tsvector('simple', product))
+WHERE deleted_at IS NULL;
+
+CREATE INDEX idx_expense_entries_product_trgm
+ON expense_entries USING GIN (product gin_trgm_ops)
+WHERE deleted_at IS NULL;
+
+COMMIT;
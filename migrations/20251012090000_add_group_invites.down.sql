@@ -0,0 +1,6 @@
+BEGIN;
+
+DROP INDEX IF EXISTS idx_group_invites_group_uid;
+DROP TABLE IF EXISTS group_invites;
+
+COMMIT;
@@ -0,0 +1,20 @@
+-- Invite links for sharing a group with other users
+BEGIN;
+
+CREATE TABLE IF NOT EXISTS group_invites (
+  uid UUID PRIMARY KEY,
+  group_uid UUID NOT NULL REFERENCES expense_groups(uid),
+  token VARCHAR NOT NULL,
+  role VARCHAR NOT NULL DEFAULT 'member',
+  email VARCHAR, -- when set, only the user with this email can accept
+  invited_by UUID NOT NULL REFERENCES users(uid),
+  expires_at TIMESTAMPTZ NOT NULL,
+  accepted_by UUID REFERENCES users(uid),
+  accepted_at TIMESTAMPTZ,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  CONSTRAINT uq_group_invites_token UNIQUE (token)
+);
+
+CREATE INDEX IF NOT EXISTS idx_group_invites_group_uid ON group_invites(group_uid);
+
+COMMIT;
@@ -0,0 +1,8 @@
+BEGIN;
+
+DROP INDEX IF EXISTS idx_expense_entries_created_by_user_uid;
+
+ALTER TABLE expense_entries
+DROP COLUMN IF EXISTS created_by_user_uid;
+
+COMMIT;
@@ -0,0 +1,10 @@
+-- Link expense entries to the user who created them (null when the sender can't be resolved)
+BEGIN;
+
+ALTER TABLE expense_entries
+ADD COLUMN created_by_user_uid UUID REFERENCES users(uid);
+
+CREATE INDEX IF NOT EXISTS idx_expense_entries_created_by_user_uid
+ON expense_entries (group_uid, created_by_user_uid);
+
+COMMIT;
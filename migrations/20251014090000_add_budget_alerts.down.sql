@@ -0,0 +1,6 @@
+BEGIN;
+
+DROP TABLE IF EXISTS budget_alerts;
+DROP TABLE IF EXISTS budget_alert_settings;
+
+COMMIT;
@@ -0,0 +1,23 @@
+-- Chat alerts when spending in a category crosses its budget thresholds
+BEGIN;
+
+-- Groups without a row use the defaults below
+CREATE TABLE IF NOT EXISTS budget_alert_settings (
+  group_uid UUID PRIMARY KEY REFERENCES expense_groups(uid),
+  enabled BOOLEAN NOT NULL DEFAULT TRUE,
+  warning_percent SMALLINT NOT NULL DEFAULT 80,
+  notify_exceeded BOOLEAN NOT NULL DEFAULT TRUE,
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  CONSTRAINT ck_budget_alert_settings_warning_percent CHECK (warning_percent BETWEEN 1 AND 99)
+);
+
+-- One row per alert already sent, so each threshold fires once per budget period
+CREATE TABLE IF NOT EXISTS budget_alerts (
+  budget_uid UUID NOT NULL REFERENCES budgets(uid) ON DELETE CASCADE,
+  period_start DATE NOT NULL,
+  threshold SMALLINT NOT NULL,
+  sent_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  PRIMARY KEY (budget_uid, period_start, threshold)
+);
+
+COMMIT;
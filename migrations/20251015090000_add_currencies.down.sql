@@ -0,0 +1,14 @@
+BEGIN;
+
+DROP TABLE IF EXISTS currency_rates;
+
+ALTER TABLE budgets
+DROP COLUMN IF EXISTS currency;
+
+ALTER TABLE expense_entries
+DROP COLUMN IF EXISTS currency;
+
+ALTER TABLE expense_groups
+DROP COLUMN IF EXISTS currency;
+
+COMMIT;
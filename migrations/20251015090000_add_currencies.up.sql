@@ -0,0 +1,24 @@
+-- Per-group default currency, per-row currency on expenses and budgets,
+-- and a static exchange rate table used to normalize reports
+BEGIN;
+
+ALTER TABLE expense_groups
+ADD COLUMN IF NOT EXISTS currency VARCHAR(3) NOT NULL DEFAULT 'IDR';
+
+ALTER TABLE expense_entries
+ADD COLUMN IF NOT EXISTS currency VARCHAR(3) NOT NULL DEFAULT 'IDR';
+
+ALTER TABLE budgets
+ADD COLUMN IF NOT EXISTS currency VARCHAR(3) NOT NULL DEFAULT 'IDR';
+
+-- 1 unit of `base` is worth `rate` units of `quote`; the inverse pair is derived when missing
+CREATE TABLE IF NOT EXISTS currency_rates (
+  base VARCHAR(3) NOT NULL,
+  quote VARCHAR(3) NOT NULL,
+  rate NUMERIC(20,10) NOT NULL,
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  PRIMARY KEY (base, quote),
+  CONSTRAINT ck_currency_rates_rate_positive CHECK (rate > 0)
+);
+
+COMMIT;
@@ -0,0 +1,11 @@
+BEGIN;
+
+ALTER TABLE exchange_rates
+DROP COLUMN IF EXISTS source;
+
+ALTER TABLE exchange_rates
+RENAME CONSTRAINT ck_exchange_rates_rate_positive TO ck_currency_rates_rate_positive;
+
+ALTER TABLE IF EXISTS exchange_rates RENAME TO currency_rates;
+
+COMMIT;
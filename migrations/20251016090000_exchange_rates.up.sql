@@ -0,0 +1,12 @@
+-- Rates are now synced daily from an external provider; `source` records where each row came from
+BEGIN;
+
+ALTER TABLE IF EXISTS currency_rates RENAME TO exchange_rates;
+
+ALTER TABLE exchange_rates
+RENAME CONSTRAINT ck_currency_rates_rate_positive TO ck_exchange_rates_rate_positive;
+
+ALTER TABLE exchange_rates
+ADD COLUMN IF NOT EXISTS source VARCHAR(32) NOT NULL DEFAULT 'manual';
+
+COMMIT;
@@ -0,0 +1,16 @@
+-- Web sessions: long-lived refresh tokens (stored hashed) that mint short-lived access tokens
+BEGIN;
+
+CREATE TABLE IF NOT EXISTS refresh_tokens (
+  uid UUID PRIMARY KEY,
+  user_uid UUID NOT NULL REFERENCES users(uid) ON DELETE CASCADE,
+  token_hash VARCHAR(64) NOT NULL,
+  expires_at TIMESTAMPTZ NOT NULL,
+  revoked_at TIMESTAMPTZ,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  CONSTRAINT uq_refresh_tokens_token_hash UNIQUE (token_hash)
+);
+
+CREATE INDEX IF NOT EXISTS idx_refresh_tokens_user_uid ON refresh_tokens(user_uid);
+
+COMMIT;
@@ -0,0 +1,16 @@
+-- Single-use, time-limited tokens emailed by /auth/forgot-password (stored hashed)
+BEGIN;
+
+CREATE TABLE IF NOT EXISTS password_reset_tokens (
+  uid UUID PRIMARY KEY,
+  user_uid UUID NOT NULL REFERENCES users(uid) ON DELETE CASCADE,
+  token_hash VARCHAR(64) NOT NULL,
+  expires_at TIMESTAMPTZ NOT NULL,
+  used_at TIMESTAMPTZ,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  CONSTRAINT uq_password_reset_tokens_token_hash UNIQUE (token_hash)
+);
+
+CREATE INDEX IF NOT EXISTS idx_password_reset_tokens_user_uid ON password_reset_tokens(user_uid);
+
+COMMIT;
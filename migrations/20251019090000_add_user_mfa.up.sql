@@ -0,0 +1,22 @@
+-- Optional TOTP two-factor authentication. The secret has to stay readable to compute codes,
+-- recovery codes are single use and stored hashed
+BEGIN;
+
+CREATE TABLE IF NOT EXISTS user_mfa (
+  user_uid UUID PRIMARY KEY REFERENCES users(uid) ON DELETE CASCADE,
+  secret VARCHAR(64) NOT NULL,
+  enabled_at TIMESTAMPTZ,
+  last_used_step BIGINT,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE TABLE IF NOT EXISTS user_mfa_recovery_codes (
+  uid UUID PRIMARY KEY,
+  user_uid UUID NOT NULL REFERENCES users(uid) ON DELETE CASCADE,
+  code_hash VARCHAR(64) NOT NULL,
+  used_at TIMESTAMPTZ,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  CONSTRAINT uq_user_mfa_recovery_codes_user_code UNIQUE (user_uid, code_hash)
+);
+
+COMMIT;
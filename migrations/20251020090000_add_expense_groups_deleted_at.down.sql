@@ -0,0 +1,10 @@
+-- Revert: Soft delete for expense groups
+BEGIN;
+
+DROP INDEX IF EXISTS idx_expense_entries_deleted_at;
+DROP INDEX IF EXISTS idx_expense_groups_deleted_at;
+
+ALTER TABLE expense_groups
+DROP COLUMN deleted_at;
+
+COMMIT;
@@ -0,0 +1,15 @@
+-- Soft delete for expense groups, purged after the owner's tier retention period
+BEGIN;
+
+ALTER TABLE expense_groups
+ADD COLUMN deleted_at TIMESTAMPTZ;
+
+CREATE INDEX idx_expense_groups_deleted_at
+ON expense_groups (deleted_at)
+WHERE deleted_at IS NOT NULL;
+
+CREATE INDEX idx_expense_entries_deleted_at
+ON expense_entries (group_uid, deleted_at)
+WHERE deleted_at IS NOT NULL;
+
+COMMIT;
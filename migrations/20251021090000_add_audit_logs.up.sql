@@ -0,0 +1,22 @@
+-- Who changed what in a group, written by route handlers and chat commands.
+-- Chat senders without a linked account are only known by name
+BEGIN;
+
+CREATE TABLE IF NOT EXISTS audit_logs (
+  uid UUID PRIMARY KEY,
+  group_uid UUID NOT NULL REFERENCES expense_groups(uid) ON DELETE CASCADE,
+  actor_user_uid UUID REFERENCES users(uid) ON DELETE SET NULL,
+  actor_name VARCHAR(255),
+  source VARCHAR(32) NOT NULL,
+  entity_type VARCHAR(32) NOT NULL,
+  entity_uid UUID NOT NULL,
+  action VARCHAR(16) NOT NULL,
+  before JSONB,
+  after JSONB,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX idx_audit_logs_group_created_at
+ON audit_logs (group_uid, created_at DESC);
+
+COMMIT;
@@ -0,0 +1,22 @@
+-- Daily and weekly chat digests, scheduled per group (hours are UTC)
+BEGIN;
+
+-- Groups without a row get no digests
+CREATE TABLE IF NOT EXISTS notification_settings (
+  group_uid UUID PRIMARY KEY REFERENCES expense_groups(uid),
+  daily_enabled BOOLEAN NOT NULL DEFAULT FALSE,
+  daily_hour SMALLINT NOT NULL DEFAULT 21,
+  weekly_enabled BOOLEAN NOT NULL DEFAULT FALSE,
+  -- ISO weekday, 1 is Monday
+  weekly_day SMALLINT NOT NULL DEFAULT 1,
+  weekly_hour SMALLINT NOT NULL DEFAULT 9,
+  -- Keeps each digest to one message per day
+  last_daily_sent_on DATE,
+  last_weekly_sent_on DATE,
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  CONSTRAINT ck_notification_settings_daily_hour CHECK (daily_hour BETWEEN 0 AND 23),
+  CONSTRAINT ck_notification_settings_weekly_day CHECK (weekly_day BETWEEN 1 AND 7),
+  CONSTRAINT ck_notification_settings_weekly_hour CHECK (weekly_hour BETWEEN 0 AND 23)
+);
+
+COMMIT;
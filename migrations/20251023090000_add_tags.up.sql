@@ -0,0 +1,21 @@
+-- Free-form labels on expense entries, next to their single category
+BEGIN;
+
+-- Names are stored normalized (lowercase, without the leading #)
+CREATE TABLE IF NOT EXISTS tags (
+  uid UUID PRIMARY KEY,
+  group_uid UUID NOT NULL REFERENCES expense_groups(uid),
+  name VARCHAR(50) NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  CONSTRAINT uq_tags_group_name UNIQUE (group_uid, name)
+);
+
+CREATE TABLE IF NOT EXISTS expense_tags (
+  expense_uid UUID NOT NULL REFERENCES expense_entries(uid) ON DELETE CASCADE,
+  tag_uid UUID NOT NULL REFERENCES tags(uid) ON DELETE CASCADE,
+  PRIMARY KEY (expense_uid, tag_uid)
+);
+
+CREATE INDEX IF NOT EXISTS idx_expense_tags_tag_uid ON expense_tags(tag_uid);
+
+COMMIT;
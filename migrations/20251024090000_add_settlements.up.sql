@@ -0,0 +1,20 @@
+-- Repayments between members of a group, confirmed by the member who received the money
+BEGIN;
+
+CREATE TABLE IF NOT EXISTS settlements (
+  uid UUID PRIMARY KEY,
+  group_uid UUID NOT NULL REFERENCES expense_groups(uid),
+  from_user_uid UUID NOT NULL REFERENCES users(uid),
+  to_user_uid UUID NOT NULL REFERENCES users(uid),
+  amount NUMERIC(12,2) NOT NULL,
+  currency VARCHAR(3) NOT NULL,
+  note TEXT,
+  confirmed_at TIMESTAMPTZ,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  CONSTRAINT ck_settlements_amount_positive CHECK (amount > 0),
+  CONSTRAINT ck_settlements_distinct_members CHECK (from_user_uid <> to_user_uid)
+);
+
+CREATE INDEX IF NOT EXISTS idx_settlements_group_created_at ON settlements(group_uid, created_at DESC);
+
+COMMIT;
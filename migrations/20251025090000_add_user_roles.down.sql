@@ -0,0 +1,6 @@
+BEGIN;
+
+ALTER TABLE users DROP CONSTRAINT IF EXISTS ck_users_role;
+ALTER TABLE users DROP COLUMN IF EXISTS role;
+
+COMMIT;
@@ -0,0 +1,11 @@
+-- Platform-wide role of a user, unrelated to their roles inside groups.
+-- Admins are promoted by hand: UPDATE users SET role = 'admin' WHERE email = '...';
+BEGIN;
+
+ALTER TABLE users
+ADD COLUMN IF NOT EXISTS role VARCHAR(20) NOT NULL DEFAULT 'user';
+
+ALTER TABLE users
+ADD CONSTRAINT ck_users_role CHECK (role IN ('user', 'admin'));
+
+COMMIT;
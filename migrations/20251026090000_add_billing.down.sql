@@ -0,0 +1,10 @@
+BEGIN;
+
+DROP TABLE IF EXISTS billing_events;
+DROP INDEX IF EXISTS uq_subscriptions_provider_subscription;
+ALTER TABLE subscriptions
+DROP COLUMN IF EXISTS provider_subscription_id,
+DROP COLUMN IF EXISTS provider_customer_id,
+DROP COLUMN IF EXISTS provider;
+
+COMMIT;
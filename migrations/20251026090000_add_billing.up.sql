@@ -0,0 +1,22 @@
+-- Link subscriptions to the payment provider and remember processed webhook events
+BEGIN;
+
+ALTER TABLE subscriptions
+ADD COLUMN IF NOT EXISTS provider VARCHAR(20),
+ADD COLUMN IF NOT EXISTS provider_customer_id VARCHAR,
+ADD COLUMN IF NOT EXISTS provider_subscription_id VARCHAR;
+
+CREATE UNIQUE INDEX IF NOT EXISTS uq_subscriptions_provider_subscription
+ON subscriptions(provider, provider_subscription_id)
+WHERE provider_subscription_id IS NOT NULL;
+
+-- Providers retry webhooks, an event is applied once
+CREATE TABLE IF NOT EXISTS billing_events (
+  provider VARCHAR(20) NOT NULL,
+  event_id VARCHAR NOT NULL,
+  event_type VARCHAR NOT NULL,
+  received_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  PRIMARY KEY (provider, event_id)
+);
+
+COMMIT;
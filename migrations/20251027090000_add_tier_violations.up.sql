@@ -0,0 +1,20 @@
+-- Resources a user keeps beyond the limits of their tier, e.g. after a downgrade to free
+BEGIN;
+
+CREATE TABLE IF NOT EXISTS tier_violations (
+  uid UUID PRIMARY KEY,
+  user_uid UUID NOT NULL REFERENCES users(uid) ON DELETE CASCADE,
+  -- NULL for limits on the user as a whole (groups)
+  group_uid UUID REFERENCES expense_groups(uid),
+  resource_type VARCHAR(50) NOT NULL,
+  current_count INTEGER NOT NULL,
+  limit_count INTEGER NOT NULL,
+  detected_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS uq_tier_violations_resource
+ON tier_violations(user_uid, resource_type, COALESCE(group_uid, '00000000-0000-0000-0000-000000000000'));
+
+CREATE INDEX IF NOT EXISTS idx_tier_violations_group_uid ON tier_violations(group_uid);
+
+COMMIT;
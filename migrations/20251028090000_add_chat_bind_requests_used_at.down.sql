@@ -0,0 +1,7 @@
+-- Revert: Chat bind requests can be accepted only once
+BEGIN;
+
+ALTER TABLE chat_bind_requests
+DROP COLUMN used_at;
+
+COMMIT;
@@ -0,0 +1,7 @@
+-- Chat bind requests can be accepted only once
+BEGIN;
+
+ALTER TABLE chat_bind_requests
+ADD COLUMN used_at TIMESTAMPTZ;
+
+COMMIT;
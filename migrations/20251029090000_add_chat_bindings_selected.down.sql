@@ -0,0 +1,19 @@
+-- Revert: A chat can be bound to several groups, commands go to the selected one
+BEGIN;
+
+-- Only the selected binding of each chat stays active
+UPDATE chat_bindings
+SET status = 'revoked', revoked_at = now()
+WHERE status = 'active' AND NOT selected;
+
+DROP INDEX IF EXISTS chat_bindings_one_selected_per_chat;
+DROP INDEX IF EXISTS chat_bindings_one_active_per_group;
+
+CREATE UNIQUE INDEX chat_bindings_one_active_per_chat
+ON chat_bindings(platform, p_uid)
+WHERE status = 'active';
+
+ALTER TABLE chat_bindings
+DROP COLUMN selected;
+
+COMMIT;
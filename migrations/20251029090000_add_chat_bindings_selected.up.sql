@@ -0,0 +1,20 @@
+-- A chat can be bound to several groups, commands go to the selected one
+BEGIN;
+
+ALTER TABLE chat_bindings
+ADD COLUMN selected BOOLEAN NOT NULL DEFAULT false;
+
+-- Until now a chat had at most one active binding
+UPDATE chat_bindings SET selected = true WHERE status = 'active';
+
+DROP INDEX IF EXISTS chat_bindings_one_active_per_chat;
+
+CREATE UNIQUE INDEX chat_bindings_one_active_per_group
+ON chat_bindings(platform, p_uid, group_uid)
+WHERE status = 'active';
+
+CREATE UNIQUE INDEX chat_bindings_one_selected_per_chat
+ON chat_bindings(platform, p_uid)
+WHERE status = 'active' AND selected;
+
+COMMIT;
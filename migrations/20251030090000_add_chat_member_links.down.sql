@@ -0,0 +1,6 @@
+BEGIN;
+
+DROP TABLE IF EXISTS chat_link_codes;
+DROP TABLE IF EXISTS chat_member_links;
+
+COMMIT;
@@ -0,0 +1,26 @@
+-- Chat platform users linked to their accounts, so group chat entries are attributed to them
+BEGIN;
+
+CREATE TABLE IF NOT EXISTS chat_member_links (
+  uid UUID PRIMARY KEY,
+  platform chat_platform NOT NULL,
+  p_user_id VARCHAR NOT NULL,
+  user_uid UUID NOT NULL REFERENCES users(uid) ON DELETE CASCADE,
+  linked_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  CONSTRAINT uq_chat_member_links_platform_user UNIQUE (platform, p_user_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_chat_member_links_user_uid ON chat_member_links(user_uid);
+
+-- Single-use, short-lived codes sent with /link-me (stored hashed)
+CREATE TABLE IF NOT EXISTS chat_link_codes (
+  uid UUID PRIMARY KEY,
+  user_uid UUID NOT NULL REFERENCES users(uid) ON DELETE CASCADE,
+  code_hash VARCHAR(64) NOT NULL,
+  expires_at TIMESTAMPTZ NOT NULL,
+  used_at TIMESTAMPTZ,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  CONSTRAINT uq_chat_link_codes_code_hash UNIQUE (code_hash)
+);
+
+COMMIT;
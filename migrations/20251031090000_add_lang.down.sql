@@ -0,0 +1,10 @@
+-- Revert: Language of the replies and emails
+BEGIN;
+
+ALTER TABLE expense_groups
+DROP COLUMN IF EXISTS lang;
+
+ALTER TABLE users
+DROP COLUMN IF EXISTS lang;
+
+COMMIT;
@@ -0,0 +1,10 @@
+-- Language of the replies and emails, NULL uses the default (Indonesian)
+BEGIN;
+
+ALTER TABLE users
+ADD COLUMN lang VARCHAR(8);
+
+ALTER TABLE expense_groups
+ADD COLUMN lang VARCHAR(8);
+
+COMMIT;
@@ -0,0 +1,8 @@
+BEGIN;
+
+DROP TABLE IF EXISTS receipts;
+
+ALTER TABLE expense_entries
+DROP COLUMN IF EXISTS note;
+
+COMMIT;
@@ -0,0 +1,21 @@
+-- Free text notes on expense entries and a receipt file per entry
+BEGIN;
+
+ALTER TABLE expense_entries
+ADD COLUMN note TEXT;
+
+-- `storage_key` locates the file in the backend named by `storage` (local or s3)
+CREATE TABLE IF NOT EXISTS receipts (
+  uid UUID PRIMARY KEY,
+  expense_uid UUID NOT NULL REFERENCES expense_entries(uid) ON DELETE CASCADE,
+  storage VARCHAR(20) NOT NULL,
+  storage_key TEXT NOT NULL,
+  file_name TEXT,
+  content_type VARCHAR(100) NOT NULL,
+  size_bytes BIGINT NOT NULL,
+  uploaded_by_user_uid UUID REFERENCES users(uid) ON DELETE SET NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  CONSTRAINT uq_receipts_expense_uid UNIQUE (expense_uid)
+);
+
+COMMIT;
@@ -0,0 +1,11 @@
+BEGIN;
+
+ALTER TABLE chat_bindings
+DROP COLUMN IF EXISTS envelope_uid;
+
+ALTER TABLE expense_entries
+DROP COLUMN IF EXISTS envelope_uid;
+
+DROP TABLE IF EXISTS envelopes;
+
+COMMIT;
@@ -0,0 +1,31 @@
+-- Envelopes: named, time-boxed spending buckets (e.g. a trip) with their own budget,
+-- kept apart from the group's regular monthly cycle
+BEGIN;
+
+-- `end_date` is inclusive; `budget` is optional and expressed in `currency`
+CREATE TABLE IF NOT EXISTS envelopes (
+  uid UUID PRIMARY KEY,
+  group_uid UUID NOT NULL REFERENCES expense_groups(uid),
+  name VARCHAR(100) NOT NULL,
+  start_date DATE NOT NULL,
+  end_date DATE NOT NULL,
+  budget NUMERIC(12,2),
+  currency VARCHAR(3) NOT NULL DEFAULT 'IDR',
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  CONSTRAINT uq_envelopes_group_name UNIQUE (group_uid, name),
+  CONSTRAINT ck_envelopes_date_range CHECK (start_date <= end_date),
+  CONSTRAINT ck_envelopes_budget_non_negative CHECK (budget IS NULL OR budget >= 0)
+);
+
+ALTER TABLE expense_entries
+ADD COLUMN envelope_uid UUID REFERENCES envelopes(uid) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_expense_entries_envelope_uid
+ON expense_entries(envelope_uid) WHERE envelope_uid IS NOT NULL;
+
+-- Envelope new chat expenses go to, switched with /trip
+ALTER TABLE chat_bindings
+ADD COLUMN envelope_uid UUID REFERENCES envelopes(uid) ON DELETE SET NULL;
+
+COMMIT;
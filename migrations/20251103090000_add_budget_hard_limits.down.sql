@@ -0,0 +1,12 @@
+BEGIN;
+
+DROP TABLE IF EXISTS pending_chat_expenses;
+
+ALTER TABLE budget_alert_settings
+DROP CONSTRAINT IF EXISTS ck_budget_alert_settings_hard_limit_action,
+DROP COLUMN IF EXISTS hard_limit_action;
+
+ALTER TABLE budgets
+DROP COLUMN IF EXISTS hard_limit;
+
+COMMIT;
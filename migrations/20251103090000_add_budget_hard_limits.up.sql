@@ -0,0 +1,22 @@
+-- Hard budget limits: spending past a budget is flagged or held back when recorded,
+-- not only reported afterwards
+BEGIN;
+
+ALTER TABLE budgets
+ADD COLUMN hard_limit BOOLEAN NOT NULL DEFAULT false;
+
+-- 'warn' records the expense with a warning, 'confirm' waits for /confirm in chat
+ALTER TABLE budget_alert_settings
+ADD COLUMN hard_limit_action VARCHAR(10) NOT NULL DEFAULT 'warn',
+ADD CONSTRAINT ck_budget_alert_settings_hard_limit_action CHECK (hard_limit_action IN ('warn', 'confirm'));
+
+-- Chat expenses held back by a hard limit, one per sender and chat binding
+CREATE TABLE IF NOT EXISTS pending_chat_expenses (
+  binding_id UUID NOT NULL REFERENCES chat_bindings(id) ON DELETE CASCADE,
+  p_user_id VARCHAR NOT NULL,
+  raw_message TEXT NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  PRIMARY KEY (binding_id, p_user_id)
+);
+
+COMMIT;
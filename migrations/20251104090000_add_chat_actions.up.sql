@@ -0,0 +1,18 @@
+-- Journal of the changes made from a chat, so /undo can revert the most recent one.
+-- Rows only live for the undo window, older ones are pruned as new ones are written
+BEGIN;
+
+-- `action` holds what is needed to revert the change, see repos/chat_action.rs
+CREATE TABLE IF NOT EXISTS chat_actions (
+  uid UUID PRIMARY KEY,
+  binding_id UUID NOT NULL REFERENCES chat_bindings(id) ON DELETE CASCADE,
+  p_user_id VARCHAR NOT NULL,
+  action JSONB NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  undone_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_chat_actions_binding_created_at
+ON chat_actions (binding_id, created_at DESC);
+
+COMMIT;
@@ -0,0 +1,14 @@
+-- Step-by-step chat entry: what a sender answered so far in an ongoing conversation
+-- (e.g. /expense without arguments), one per sender and chat binding
+BEGIN;
+
+-- `state` holds the flow and its answers, see repos/chat_conversation.rs
+CREATE TABLE IF NOT EXISTS chat_conversations (
+  binding_id UUID NOT NULL REFERENCES chat_bindings(id) ON DELETE CASCADE,
+  p_user_id VARCHAR NOT NULL,
+  state JSONB NOT NULL,
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  PRIMARY KEY (binding_id, p_user_id)
+);
+
+COMMIT;
@@ -0,0 +1,44 @@
+-- Product names used in each group, learned from the expenses recorded with them, to
+-- suggest names while typing and to categorize chat entries sent without a category
+BEGIN;
+
+-- `name` is the normalized name (trimmed, single spaces, lowercase), `display_name` the
+-- form it was last recorded with. `typical_price` averages the prices in `currency`
+CREATE TABLE IF NOT EXISTS products (
+  group_uid UUID NOT NULL REFERENCES expense_groups(uid) ON DELETE CASCADE,
+  name VARCHAR NOT NULL,
+  display_name VARCHAR NOT NULL,
+  category_uid UUID REFERENCES categories(uid) ON DELETE SET NULL,
+  typical_price NUMERIC(12,2) NOT NULL,
+  currency VARCHAR(3) NOT NULL,
+  use_count INTEGER NOT NULL DEFAULT 1,
+  last_used_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  PRIMARY KEY (group_uid, name)
+);
+
+-- Learn from the entries recorded so far: the latest entry of a name gives its display
+-- name and currency, the latest categorized one its category
+WITH entries AS (
+  SELECT e.*, lower(regexp_replace(trim(e.product), '\s+', ' ', 'g')) AS normalized
+  FROM expense_entries e
+  WHERE e.deleted_at IS NULL AND trim(e.product) <> ''
+)
+INSERT INTO products (group_uid, name, display_name, category_uid, typical_price, currency, use_count, last_used_at)
+SELECT DISTINCT ON (e.group_uid, e.normalized)
+  e.group_uid,
+  e.normalized,
+  regexp_replace(trim(e.product), '\s+', ' ', 'g'),
+  (
+    SELECT c.category_uid FROM entries c
+    WHERE c.group_uid = e.group_uid AND c.normalized = e.normalized AND c.category_uid IS NOT NULL
+    ORDER BY c.created_at DESC LIMIT 1
+  ),
+  round(AVG(e.price) OVER (PARTITION BY e.group_uid, e.normalized, e.currency), 2),
+  e.currency,
+  COUNT(*) OVER (PARTITION BY e.group_uid, e.normalized),
+  e.created_at
+FROM entries e
+ORDER BY e.group_uid, e.normalized, e.created_at DESC
+ON CONFLICT (group_uid, name) DO NOTHING;
+
+COMMIT;
@@ -0,0 +1,7 @@
+-- Revert: Archived expense groups
+BEGIN;
+
+ALTER TABLE expense_groups
+DROP COLUMN archived_at;
+
+COMMIT;
@@ -0,0 +1,8 @@
+-- Archived expense groups: still readable, but closed to new entries and chat commands,
+-- and left out of the tier's group limit
+BEGIN;
+
+ALTER TABLE expense_groups
+ADD COLUMN archived_at TIMESTAMPTZ;
+
+COMMIT;
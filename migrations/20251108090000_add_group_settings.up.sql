@@ -0,0 +1,11 @@
+-- Group preferences without a column of their own, see `GroupSettings`
+BEGIN;
+
+-- Keys missing from `settings` take their default, groups without a row use the defaults
+CREATE TABLE IF NOT EXISTS group_settings (
+  group_uid UUID PRIMARY KEY REFERENCES expense_groups(uid),
+  settings JSONB NOT NULL DEFAULT '{}'::jsonb,
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+COMMIT;
@@ -0,0 +1,23 @@
+-- Background jobs run by the worker pool, see `JobWorker`
+BEGIN;
+
+-- `running` jobs whose worker died are claimed again once `locked_at` is stale
+CREATE TABLE IF NOT EXISTS jobs (
+  uid UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+  kind VARCHAR(64) NOT NULL,
+  payload JSONB NOT NULL,
+  status VARCHAR(16) NOT NULL DEFAULT 'queued',
+  attempts INT NOT NULL DEFAULT 0,
+  max_attempts INT NOT NULL DEFAULT 5,
+  run_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  locked_at TIMESTAMPTZ,
+  last_error TEXT,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  CONSTRAINT ck_jobs_status CHECK (status IN ('queued', 'running', 'succeeded', 'dead'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_jobs_due ON jobs (run_at) WHERE status IN ('queued', 'running');
+CREATE INDEX IF NOT EXISTS idx_jobs_status_created ON jobs (status, created_at DESC);
+
+COMMIT;
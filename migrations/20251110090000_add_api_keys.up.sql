@@ -0,0 +1,21 @@
+-- Per-user API keys for scripts and integrations, stored hashed like refresh tokens
+BEGIN;
+
+CREATE TABLE IF NOT EXISTS api_keys (
+  uid UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+  user_uid UUID NOT NULL REFERENCES users(uid) ON DELETE CASCADE,
+  name VARCHAR(100) NOT NULL,
+  key_hash VARCHAR(64) NOT NULL,
+  -- First characters of the key, to tell keys apart without storing them
+  key_prefix VARCHAR(16) NOT NULL,
+  scope VARCHAR(16) NOT NULL DEFAULT 'read_only',
+  last_used_at TIMESTAMPTZ,
+  revoked_at TIMESTAMPTZ,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  CONSTRAINT uq_api_keys_key_hash UNIQUE (key_hash),
+  CONSTRAINT ck_api_keys_scope CHECK (scope IN ('read_only', 'read_write'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_api_keys_user_uid ON api_keys(user_uid);
+
+COMMIT;
@@ -0,0 +1,35 @@
+-- Outbound webhooks of a group and the log of their deliveries
+BEGIN;
+
+CREATE TABLE IF NOT EXISTS webhooks (
+  uid UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+  group_uid UUID NOT NULL REFERENCES expense_groups(uid),
+  url TEXT NOT NULL,
+  -- Signs the payloads, kept readable since it is needed for every delivery
+  secret VARCHAR(64) NOT NULL,
+  events TEXT[] NOT NULL,
+  active BOOLEAN NOT NULL DEFAULT TRUE,
+  created_by UUID REFERENCES users(uid) ON DELETE SET NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS idx_webhooks_group_uid ON webhooks(group_uid);
+
+CREATE TABLE IF NOT EXISTS webhook_deliveries (
+  uid UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+  webhook_uid UUID NOT NULL REFERENCES webhooks(uid) ON DELETE CASCADE,
+  event VARCHAR(64) NOT NULL,
+  payload JSONB NOT NULL,
+  status VARCHAR(16) NOT NULL DEFAULT 'pending',
+  attempts INT NOT NULL DEFAULT 0,
+  response_status INT,
+  last_error TEXT,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  delivered_at TIMESTAMPTZ,
+  CONSTRAINT ck_webhook_deliveries_status CHECK (status IN ('pending', 'succeeded', 'failed'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_webhook_created ON webhook_deliveries(webhook_uid, created_at DESC);
+
+COMMIT;
@@ -0,0 +1,10 @@
+BEGIN;
+
+DROP TABLE IF EXISTS slack_workspaces;
+
+-- Enum values cannot be dropped, the value stays but nothing uses it anymore
+DELETE FROM chat_member_links WHERE platform = 'slack';
+DELETE FROM chat_bind_requests WHERE platform = 'slack';
+DELETE FROM chat_bindings WHERE platform = 'slack';
+
+COMMIT;
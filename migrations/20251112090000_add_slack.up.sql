@@ -0,0 +1,13 @@
+-- Slack workspaces as chats: a workspace is bound as a whole, with the team id as p_uid
+BEGIN;
+
+ALTER TYPE chat_platform ADD VALUE IF NOT EXISTS 'slack';
+
+-- The channel a workspace last used the bot in, where messages that answer no command go
+CREATE TABLE IF NOT EXISTS slack_workspaces (
+  team_id VARCHAR PRIMARY KEY,
+  channel_id VARCHAR NOT NULL,
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+COMMIT;
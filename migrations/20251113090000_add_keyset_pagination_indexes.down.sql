@@ -0,0 +1,13 @@
+BEGIN;
+
+DROP INDEX IF EXISTS idx_audit_logs_group_created_at_uid;
+
+CREATE INDEX IF NOT EXISTS idx_audit_logs_group_created_at
+ON audit_logs (group_uid, created_at DESC);
+
+DROP INDEX IF EXISTS idx_expense_entries_group_created_at_uid;
+
+CREATE INDEX IF NOT EXISTS idx_entries_group_created_at
+ON expense_entries (group_uid, created_at DESC);
+
+COMMIT;
@@ -0,0 +1,15 @@
+-- Keyset pagination (`?after=` on the listings) walks a group's rows by (created_at, uid).
+-- The new indexes replace the (group_uid, created_at DESC) ones, btrees are scanned both ways
+BEGIN;
+
+DROP INDEX IF EXISTS idx_entries_group_created_at;
+
+CREATE INDEX IF NOT EXISTS idx_expense_entries_group_created_at_uid
+ON expense_entries (group_uid, created_at, uid);
+
+DROP INDEX IF EXISTS idx_audit_logs_group_created_at;
+
+CREATE INDEX IF NOT EXISTS idx_audit_logs_group_created_at_uid
+ON audit_logs (group_uid, created_at, uid);
+
+COMMIT;
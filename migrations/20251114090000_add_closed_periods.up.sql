@@ -0,0 +1,20 @@
+-- Closed billing cycles of a group, expense entries created inside them can't be edited or
+-- deleted until an owner reopens the period. The cycle's range is kept as it was when it was
+-- closed, changing the group's start_over_date later does not move it
+BEGIN;
+
+CREATE TABLE IF NOT EXISTS closed_periods (
+  group_uid UUID NOT NULL REFERENCES expense_groups(uid) ON DELETE CASCADE,
+  period_year INT NOT NULL,
+  period_month INT NOT NULL CHECK (period_month BETWEEN 1 AND 12),
+  starts_at TIMESTAMPTZ NOT NULL,
+  ends_at TIMESTAMPTZ NOT NULL,
+  closed_by UUID REFERENCES users(uid) ON DELETE SET NULL,
+  closed_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  PRIMARY KEY (group_uid, period_year, period_month)
+);
+
+CREATE INDEX IF NOT EXISTS idx_closed_periods_group_range
+ON closed_periods (group_uid, starts_at, ends_at);
+
+COMMIT;
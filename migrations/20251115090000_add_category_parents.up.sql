@@ -0,0 +1,14 @@
+-- Categories nest one level deep, e.g. "Kopi" under "Makanan". Spending in a subcategory rolls
+-- up into its parent in reports; deleting the parent moves its children back to the top level
+BEGIN;
+
+ALTER TABLE categories
+  ADD COLUMN IF NOT EXISTS parent_uid UUID REFERENCES categories(uid) ON DELETE SET NULL;
+
+ALTER TABLE categories
+  ADD CONSTRAINT chk_categories_parent_not_self CHECK (parent_uid <> uid);
+
+CREATE INDEX IF NOT EXISTS idx_categories_parent_uid
+ON categories (parent_uid) WHERE parent_uid IS NOT NULL;
+
+COMMIT;
@@ -0,0 +1,7 @@
+BEGIN;
+
+ALTER TABLE categories DROP CONSTRAINT IF EXISTS chk_categories_color_hex;
+ALTER TABLE categories DROP COLUMN IF EXISTS color;
+ALTER TABLE categories DROP COLUMN IF EXISTS icon;
+
+COMMIT;
@@ -0,0 +1,30 @@
+-- Icon (an emoji or a short code like :coffee:) and #rrggbb color each category is drawn with.
+-- Existing categories named like one of the templates in src/utils/category_style.rs get its
+-- style, the rest the fallback
+BEGIN;
+
+ALTER TABLE categories
+  ADD COLUMN IF NOT EXISTS icon VARCHAR(32) NOT NULL DEFAULT '🏷️',
+  ADD COLUMN IF NOT EXISTS color VARCHAR(7) NOT NULL DEFAULT '#64748b';
+
+ALTER TABLE categories
+  ADD CONSTRAINT chk_categories_color_hex CHECK (color ~ '^#[0-9a-f]{6}$');
+
+UPDATE categories c SET icon = t.icon, color = t.color
+FROM (VALUES
+  ('food', '🍔', '#f97316'), ('makanan', '🍔', '#f97316'), ('makan', '🍔', '#f97316'),
+  ('drinks', '☕', '#92400e'), ('minuman', '☕', '#92400e'), ('coffee', '☕', '#92400e'), ('kopi', '☕', '#92400e'),
+  ('groceries', '🛒', '#16a34a'), ('belanja bulanan', '🛒', '#16a34a'), ('sembako', '🛒', '#16a34a'),
+  ('transport', '🚗', '#2563eb'), ('transportation', '🚗', '#2563eb'), ('transportasi', '🚗', '#2563eb'),
+  ('shopping', '🛍️', '#db2777'), ('belanja', '🛍️', '#db2777'),
+  ('entertainment', '🎬', '#7c3aed'), ('hiburan', '🎬', '#7c3aed'),
+  ('bills', '💡', '#eab308'), ('utilities', '💡', '#eab308'), ('tagihan', '💡', '#eab308'),
+  ('health', '💊', '#dc2626'), ('kesehatan', '💊', '#dc2626'),
+  ('education', '📚', '#0891b2'), ('pendidikan', '📚', '#0891b2'),
+  ('home', '🏠', '#65a30d'), ('housing', '🏠', '#65a30d'), ('rumah', '🏠', '#65a30d'),
+  ('travel', '✈️', '#0ea5e9'), ('liburan', '✈️', '#0ea5e9'),
+  ('gifts', '🎁', '#e11d48'), ('hadiah', '🎁', '#e11d48')
+) AS t(name, icon, color)
+WHERE lower(trim(c.name)) = t.name;
+
+COMMIT;
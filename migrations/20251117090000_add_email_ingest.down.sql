@@ -0,0 +1,6 @@
+BEGIN;
+
+DROP TABLE IF EXISTS expense_drafts;
+DROP TABLE IF EXISTS email_ingest_addresses;
+
+COMMIT;
@@ -0,0 +1,30 @@
+-- Forwarded e-receipts: each group can have an address `<token>@EMAIL_INGEST_DOMAIN`, emails
+-- sent to it become drafts a member confirms into an expense entry or discards
+BEGIN;
+
+CREATE TABLE IF NOT EXISTS email_ingest_addresses (
+  group_uid UUID PRIMARY KEY REFERENCES expense_groups(uid) ON DELETE CASCADE,
+  -- Kept readable since the address is shown to members, rotating it replaces the token
+  token VARCHAR(64) NOT NULL UNIQUE,
+  created_by UUID REFERENCES users(uid) ON DELETE SET NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE TABLE IF NOT EXISTS expense_drafts (
+  uid UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+  group_uid UUID NOT NULL REFERENCES expense_groups(uid) ON DELETE CASCADE,
+  source VARCHAR(16) NOT NULL DEFAULT 'email',
+  -- Whatever could be read from the receipt, each may be missing
+  merchant VARCHAR(255),
+  price NUMERIC(12,2),
+  currency VARCHAR(3),
+  spent_at TIMESTAMPTZ NOT NULL,
+  sender TEXT NOT NULL,
+  subject TEXT NOT NULL,
+  body TEXT NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS idx_expense_drafts_group_created ON expense_drafts(group_uid, created_at DESC);
+
+COMMIT;
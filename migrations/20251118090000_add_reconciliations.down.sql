@@ -0,0 +1,6 @@
+BEGIN;
+
+DROP TABLE IF EXISTS reconciliation_rows;
+DROP TABLE IF EXISTS reconciliations;
+
+COMMIT;
@@ -0,0 +1,36 @@
+-- Bank statements uploaded for reconciliation, each row matched with the expense entry it paid
+-- for, or left unmatched until an entry is created from it
+BEGIN;
+
+CREATE TABLE IF NOT EXISTS reconciliations (
+  uid UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+  group_uid UUID NOT NULL REFERENCES expense_groups(uid) ON DELETE CASCADE,
+  file_name TEXT,
+  format VARCHAR(8) NOT NULL,
+  currency VARCHAR(3) NOT NULL,
+  -- How many days apart a row and an entry may be dated and still match
+  window_days SMALLINT NOT NULL,
+  -- Credits and rows that could not be read
+  skipped_rows INT NOT NULL DEFAULT 0,
+  created_by UUID REFERENCES users(uid) ON DELETE SET NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  CONSTRAINT chk_reconciliations_format CHECK (format IN ('csv', 'ofx'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_reconciliations_group_created ON reconciliations(group_uid, created_at DESC);
+
+CREATE TABLE IF NOT EXISTS reconciliation_rows (
+  uid UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+  reconciliation_uid UUID NOT NULL REFERENCES reconciliations(uid) ON DELETE CASCADE,
+  line INT NOT NULL,
+  posted_on DATE NOT NULL,
+  amount NUMERIC(12,2) NOT NULL,
+  description VARCHAR(255) NOT NULL,
+  -- The matched entry, or the one created from the row
+  entry_uid UUID REFERENCES expense_entries(uid) ON DELETE SET NULL,
+  created_entry BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE INDEX IF NOT EXISTS idx_reconciliation_rows_reconciliation ON reconciliation_rows(reconciliation_uid, line);
+
+COMMIT;
@@ -0,0 +1,23 @@
+-- A session outlives the rotation of its refresh token: every token issued from one sign-in
+-- shares its session_uid, along with the device (user agent) and IP address that signed in
+BEGIN;
+
+ALTER TABLE refresh_tokens
+  ADD COLUMN IF NOT EXISTS session_uid UUID,
+  ADD COLUMN IF NOT EXISTS user_agent VARCHAR(512),
+  ADD COLUMN IF NOT EXISTS ip_address VARCHAR(45),
+  ADD COLUMN IF NOT EXISTS signed_in_at TIMESTAMPTZ;
+
+-- Earlier tokens cannot be traced back to their sign-in, each counts as a session of its own
+UPDATE refresh_tokens
+SET session_uid = uid, signed_in_at = created_at
+WHERE session_uid IS NULL;
+
+ALTER TABLE refresh_tokens
+  ALTER COLUMN session_uid SET NOT NULL,
+  ALTER COLUMN signed_in_at SET NOT NULL,
+  ALTER COLUMN signed_in_at SET DEFAULT now();
+
+CREATE INDEX IF NOT EXISTS idx_refresh_tokens_session_uid ON refresh_tokens(session_uid);
+
+COMMIT;
@@ -0,0 +1,10 @@
+-- Run `encrypt_pii --decrypt` first, encrypted values cannot be looked up without their hash
+BEGIN;
+
+DROP INDEX IF EXISTS uq_chat_member_links_platform_user_hash;
+ALTER TABLE chat_member_links DROP COLUMN IF EXISTS p_user_id_hash;
+
+DROP INDEX IF EXISTS uq_users_email_hash;
+ALTER TABLE users DROP COLUMN IF EXISTS email_hash;
+
+COMMIT;
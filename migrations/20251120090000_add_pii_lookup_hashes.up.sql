@@ -0,0 +1,13 @@
+-- Emails and chat platform user ids may be stored encrypted (see src/utils/pii.rs). Encrypted
+-- values differ on every write, so they are looked up and kept unique by a keyed hash instead.
+-- Both hashes stay NULL until PII_ENCRYPTION_KEY is set and `encrypt_pii` has run
+BEGIN;
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS email_hash VARCHAR(64);
+CREATE UNIQUE INDEX IF NOT EXISTS uq_users_email_hash ON users(email_hash);
+
+ALTER TABLE chat_member_links ADD COLUMN IF NOT EXISTS p_user_id_hash VARCHAR(64);
+CREATE UNIQUE INDEX IF NOT EXISTS uq_chat_member_links_platform_user_hash
+ON chat_member_links(platform, p_user_id_hash);
+
+COMMIT;
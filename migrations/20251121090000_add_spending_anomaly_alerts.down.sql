@@ -0,0 +1,5 @@
+BEGIN;
+
+DROP TABLE IF EXISTS spending_anomaly_alerts;
+
+COMMIT;
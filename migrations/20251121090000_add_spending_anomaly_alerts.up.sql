@@ -0,0 +1,19 @@
+-- One row per unusual spending alert already sent: an expense alerts once, a category's total
+-- for a day alerts once per day (entry_uid NULL)
+BEGIN;
+
+CREATE TABLE IF NOT EXISTS spending_anomaly_alerts (
+  group_uid UUID NOT NULL REFERENCES expense_groups(uid) ON DELETE CASCADE,
+  category_uid UUID NOT NULL REFERENCES categories(uid) ON DELETE CASCADE,
+  day DATE NOT NULL,
+  entry_uid UUID REFERENCES expense_entries(uid) ON DELETE CASCADE,
+  sent_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS uq_spending_anomaly_alerts_entry
+ON spending_anomaly_alerts(entry_uid) WHERE entry_uid IS NOT NULL;
+
+CREATE UNIQUE INDEX IF NOT EXISTS uq_spending_anomaly_alerts_day
+ON spending_anomaly_alerts(group_uid, category_uid, day) WHERE entry_uid IS NULL;
+
+COMMIT;
@@ -0,0 +1,6 @@
+BEGIN;
+
+DROP TABLE IF EXISTS budget_plan_items;
+DROP TABLE IF EXISTS budget_plans;
+
+COMMIT;
@@ -0,0 +1,25 @@
+-- Budget plans: named sets of budgets a group can try against past cycles and apply to
+-- the next one
+BEGIN;
+
+CREATE TABLE IF NOT EXISTS budget_plans (
+  uid UUID PRIMARY KEY,
+  group_uid UUID NOT NULL REFERENCES expense_groups(uid) ON DELETE CASCADE,
+  name VARCHAR(100) NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS uq_budget_plans_group_name
+ON budget_plans(group_uid, lower(name));
+
+-- `amount` is in the group's currency
+CREATE TABLE IF NOT EXISTS budget_plan_items (
+  plan_uid UUID NOT NULL REFERENCES budget_plans(uid) ON DELETE CASCADE,
+  category_uid UUID NOT NULL REFERENCES categories(uid) ON DELETE CASCADE,
+  amount NUMERIC(12,2) NOT NULL,
+  PRIMARY KEY (plan_uid, category_uid),
+  CONSTRAINT ck_budget_plan_items_amount_non_negative CHECK (amount >= 0)
+);
+
+COMMIT;
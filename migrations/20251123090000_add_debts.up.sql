@@ -0,0 +1,28 @@
+-- Debts: money a group lent to or borrowed from someone outside it, settled outside the app
+BEGIN;
+
+-- `direction` is 'lent' (owed to the group) or 'borrowed' (owed by the group);
+-- `reminded_at` is set once the due date reminder went out and cleared when the date changes
+CREATE TABLE IF NOT EXISTS debts (
+  uid UUID PRIMARY KEY,
+  group_uid UUID NOT NULL REFERENCES expense_groups(uid) ON DELETE CASCADE,
+  direction VARCHAR(10) NOT NULL,
+  person VARCHAR(100) NOT NULL,
+  amount NUMERIC(12,2) NOT NULL,
+  currency VARCHAR(3) NOT NULL DEFAULT 'IDR',
+  due_date DATE,
+  note TEXT,
+  settled_at TIMESTAMPTZ,
+  reminded_at TIMESTAMPTZ,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  CONSTRAINT ck_debts_direction CHECK (direction IN ('lent', 'borrowed')),
+  CONSTRAINT ck_debts_amount_positive CHECK (amount > 0)
+);
+
+CREATE INDEX IF NOT EXISTS idx_debts_group_uid ON debts(group_uid);
+
+CREATE INDEX IF NOT EXISTS idx_debts_open_due_date
+ON debts(due_date) WHERE settled_at IS NULL AND reminded_at IS NULL;
+
+COMMIT;
@@ -0,0 +1,12 @@
+BEGIN;
+
+ALTER TABLE budgets
+DROP CONSTRAINT IF EXISTS ck_budgets_pinned_monthly,
+DROP CONSTRAINT IF EXISTS ck_budgets_custom_range,
+DROP COLUMN IF EXISTS range_end,
+DROP COLUMN IF EXISTS range_start,
+DROP COLUMN IF EXISTS period_type;
+
+DROP TYPE IF EXISTS budget_period_type;
+
+COMMIT;
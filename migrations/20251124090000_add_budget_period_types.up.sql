@@ -0,0 +1,23 @@
+-- Budgets tracked per week, quarter, year or a custom range instead of only per cycle
+BEGIN;
+
+DO $$
+BEGIN
+  IF NOT EXISTS (SELECT 1 FROM pg_type WHERE typname = 'budget_period_type') THEN
+    CREATE TYPE budget_period_type AS ENUM ('weekly', 'monthly', 'quarterly', 'yearly', 'custom');
+  END IF;
+END$$;
+
+-- period_year/period_month pin a monthly budget to one cycle, range_start/range_end
+-- ([start, end)) are the range of a custom budget
+ALTER TABLE budgets
+ADD COLUMN period_type budget_period_type NOT NULL DEFAULT 'monthly',
+ADD COLUMN range_start DATE NULL,
+ADD COLUMN range_end DATE NULL,
+ADD CONSTRAINT ck_budgets_custom_range CHECK (
+  (period_type = 'custom' AND range_start IS NOT NULL AND range_end IS NOT NULL AND range_end > range_start) OR
+  (period_type <> 'custom' AND range_start IS NULL AND range_end IS NULL)
+),
+ADD CONSTRAINT ck_budgets_pinned_monthly CHECK (period_month IS NULL OR period_type = 'monthly');
+
+COMMIT;
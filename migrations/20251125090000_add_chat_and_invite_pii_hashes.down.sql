@@ -0,0 +1,15 @@
+-- Run `encrypt_pii --decrypt` first, encrypted values cannot be looked up without their hash
+BEGIN;
+
+DROP INDEX IF EXISTS idx_group_invites_email_hash;
+ALTER TABLE group_invites DROP COLUMN IF EXISTS email_hash;
+
+DROP INDEX IF EXISTS idx_bind_req_platform_puid_hash;
+ALTER TABLE chat_bind_requests DROP COLUMN IF EXISTS p_uid_hash;
+
+DROP INDEX IF EXISTS chat_bindings_one_selected_per_chat_hash;
+DROP INDEX IF EXISTS chat_bindings_one_active_per_group_hash;
+DROP INDEX IF EXISTS idx_chat_bindings_platform_puid_hash_status;
+ALTER TABLE chat_bindings DROP COLUMN IF EXISTS p_uid_hash;
+
+COMMIT;
@@ -0,0 +1,22 @@
+-- Chat ids of bindings and bind requests and the emails of group invites may be stored
+-- encrypted as well (see src/utils/pii.rs), so they are looked up and kept unique by a keyed
+-- hash next to them. The hashes stay NULL until PII_ENCRYPTION_KEY is set and `encrypt_pii` ran
+BEGIN;
+
+ALTER TABLE chat_bindings ADD COLUMN IF NOT EXISTS p_uid_hash VARCHAR(64);
+CREATE INDEX IF NOT EXISTS idx_chat_bindings_platform_puid_hash_status
+ON chat_bindings(platform, p_uid_hash, status);
+CREATE UNIQUE INDEX IF NOT EXISTS chat_bindings_one_active_per_group_hash
+ON chat_bindings(platform, p_uid_hash, group_uid)
+WHERE status = 'active';
+CREATE UNIQUE INDEX IF NOT EXISTS chat_bindings_one_selected_per_chat_hash
+ON chat_bindings(platform, p_uid_hash)
+WHERE status = 'active' AND selected;
+
+ALTER TABLE chat_bind_requests ADD COLUMN IF NOT EXISTS p_uid_hash VARCHAR(64);
+CREATE INDEX IF NOT EXISTS idx_bind_req_platform_puid_hash ON chat_bind_requests(platform, p_uid_hash);
+
+ALTER TABLE group_invites ADD COLUMN IF NOT EXISTS email_hash VARCHAR(64);
+CREATE INDEX IF NOT EXISTS idx_group_invites_email_hash ON group_invites(email_hash);
+
+COMMIT;
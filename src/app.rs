@@ -1,49 +1,132 @@
-use crate::openapi::ApiDoc;
-use axum::{Router, routing::get};
+use crate::openapi::{self, ApiDoc};
+use axum::Router;
 use utoipa::OpenApi;
+use utoipa_axum::{router::OpenApiRouter, routes};
 use utoipa_swagger_ui::SwaggerUi;
 
-use crate::{routes, types::AppState};
-use axum::middleware;
+use crate::{
+    config::{ANY_ORIGIN, HttpConfig},
+    routes,
+    types::AppState,
+};
+use axum::{
+    extract::DefaultBodyLimit,
+    http::{HeaderName, HeaderValue},
+    middleware,
+};
 use tower_http::cors::{Any, CorsLayer};
 
+/*
+ Every API route. Routers are built with `routes!`, which also documents each route from
+ its handler's `#[utoipa::path]`, so the OpenAPI doc can't miss a served route.
+*/
+pub fn api_router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::with_openapi(ApiDoc::openapi())
+        .routes(routes!(routes::health::health))
+        .routes(routes!(routes::version::version))
+        .routes(routes!(routes::metrics::metrics))
+        .merge(routes::chat_bindings::router())
+        .merge(routes::chat_links::router())
+        .merge(routes::chat_relay::router())
+        .merge(routes::expense_entry::router())
+        .merge(routes::income_entry::router())
+        .merge(routes::recurring_expenses::router())
+        .merge(routes::chat_bind_requests::router())
+        .merge(routes::budgets::router())
+        .merge(routes::budget_plans::router())
+        .merge(routes::currencies::router())
+        .merge(routes::categories::router())
+        .merge(routes::categories_aliases::router())
+        .merge(routes::users::router())
+        .merge(routes::overview::router())
+        .merge(routes::api_keys::router())
+        .merge(routes::sessions::router())
+        .merge(routes::expense_groups::router())
+        .merge(routes::group_members::router())
+        .merge(routes::group_invites::router())
+        .merge(routes::group_settings::router())
+        .merge(routes::periods::router())
+        .merge(routes::notification_settings::router())
+        .merge(routes::stats::router())
+        .merge(routes::tags::router())
+        .merge(routes::products::router())
+        .merge(routes::envelopes::router())
+        .merge(routes::settlements::router())
+        .merge(routes::debts::router())
+        .merge(routes::activity::router())
+        .merge(routes::webhooks::router())
+        .merge(routes::email_ingest::router())
+        .merge(routes::reconciliations::router())
+        .merge(routes::admin::router())
+        .merge(routes::billing::router())
+}
+
 pub fn build_router(app_state: AppState) -> Router {
+    build_router_with_config(app_state, &HttpConfig::default())
+}
+
+pub fn build_router_with_config(app_state: AppState, http: &HttpConfig) -> Router {
     let auth_state = app_state.clone();
+    let rate_limit_state = app_state.clone();
 
     // Configure CORS
     let mut cors = CorsLayer::new()
         .allow_methods(Any)
-        .allow_headers(Any);
-
-    // Add allowed origins
-    let mut origins = vec![
-        "http://localhost:3000".parse().unwrap(),
-        "http://localhost:5173".parse().unwrap(), // Vite dev server
-    ];
+        .allow_headers(Any)
+        .expose_headers([HeaderName::from_static(
+            crate::middleware::request_id::REQUEST_ID_HEADER,
+        )]);
 
-    if let Ok(origin) = app_state.front_end_url.parse() {
-        origins.push(origin);
+    if http.cors_allowed_origins.iter().any(|origin| origin == ANY_ORIGIN) {
+        cors = cors.allow_origin(Any);
+    } else {
+        // Add allowed origins, the web app's own one is always allowed
+        let origins: Vec<HeaderValue> = http
+            .cors_allowed_origins
+            .iter()
+            .chain(std::iter::once(&app_state.front_end_url))
+            .filter_map(|origin| origin.trim_end_matches('/').parse().ok())
+            .collect();
+        cors = cors.allow_origin(origins);
     }
 
-    cors = cors.allow_origin(origins);
-
-    Router::new()
-        // .merge("/group-members", routes::group_members::router())
-        .route("/health", get(routes::health::health))
-        .route("/version", get(routes::version::version))
-        .merge(routes::chat_bindings::router())
-        .merge(routes::expense_entry::router())
-        .merge(routes::chat_bind_requests::router())
-        .merge(routes::budgets::router())
-        .merge(routes::categories::router())
-        .merge(routes::users::router())
-        .merge(routes::expense_groups::router())
-        .merge(SwaggerUi::new("/docs").url("/api-doc/openapi.json", ApiDoc::openapi()))
+    let (router, api) = api_router().split_for_parts();
+    router
+        .merge(SwaggerUi::new("/docs").url("/api-doc/openapi.json", openapi::complete(api)))
         .with_state(app_state)
+        // Runs after auth (layers wrap the ones added before them) so it can key on the user
+        .layer(middleware::from_fn_with_state(
+            rate_limit_state,
+            crate::middleware::rate_limit::rate_limit_middleware,
+        ))
         .layer(middleware::from_fn_with_state(
             auth_state,
             crate::auth::auth_middleware,
         ))
+        // `/metrics` is public to the auth middleware, its own token is checked before it
+        .layer(middleware::from_fn_with_state(
+            http.metrics_token.clone(),
+            crate::middleware::metrics_auth::metrics_auth_middleware,
+        ))
+        // Runs before the rate limiter, which keys unauthenticated requests on the client IP
+        .layer(middleware::from_fn_with_state(
+            http.trust_proxy,
+            crate::middleware::client_ip::client_ip_middleware,
+        ))
+        .layer(DefaultBodyLimit::max(http.max_body_bytes))
+        .layer(middleware::from_fn_with_state(
+            http.request_timeout,
+            crate::middleware::timeout::timeout_middleware,
+        ))
+        .layer(middleware::from_fn(
+            crate::middleware::request_metrics::request_metrics_middleware,
+        ))
         .layer(cors)
+        .layer(middleware::from_fn(
+            crate::middleware::security_headers::security_headers_middleware,
+        ))
+        .layer(middleware::from_fn(
+            crate::middleware::request_id::request_id_middleware,
+        ))
         .layer(tower_http::trace::TraceLayer::new_for_http())
 }
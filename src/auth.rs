@@ -4,21 +4,28 @@ use axum::extract::State;
 use axum::http::header::AUTHORIZATION;
 use axum::{
     body::Body,
-    http::{Request, StatusCode},
+    http::Request,
     middleware::Next,
     response::Response,
 };
 use hmac::{Hmac, Mac};
 use http_body_util::BodyExt as _; // for collect()
-use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation, decode, encode};
 use serde::{Deserialize, Serialize};
 use sha2::Sha256;
 use tracing::info;
 use uuid::Uuid;
 
+use crate::auth::jwt::JwtKeys;
+use crate::error::{AppError, ErrorCode};
+use crate::repos::api_key::{ApiKey, ApiKeyRepo, ApiKeyScope};
+use crate::repos::refresh_token::{RefreshToken, RefreshTokenRepo};
 use crate::types::AppState;
 
+pub mod admin_guard;
 pub mod group_guard;
+pub mod jwt;
+pub mod membership_cache;
+pub mod totp;
 
 #[derive(Clone, Debug)]
 pub enum AuthSource {
@@ -31,42 +38,152 @@ pub struct AuthContext {
     pub source: AuthSource,
     pub user_uid: Uuid,
     pub group_uid: Option<Uuid>,
+    // Set when the request was authenticated with an API key instead of a JWT
+    pub api_key_uid: Option<Uuid>,
+    // Session whose access token authenticated the request, see `/users/me/sessions`
+    pub session_uid: Option<Uuid>,
 }
 
+// Access tokens are short-lived, clients renew them with the refresh token
+pub const ACCESS_TOKEN_TTL_SECONDS: u64 = 60 * 15;
+pub const REFRESH_TOKEN_TTL_DAYS: i64 = 30;
+
+pub const API_KEY_HEADER: &str = "X-Api-Key";
+pub const RELAY_SIGNATURE_HEADER: &str = "X-Relay-Signature";
+
+// `aud` of the access tokens of the web app. Relay requests are signed with
+// CHAT_RELAY_SECRET instead, so no JWT keys are shared with the relays
+pub const WEB_AUDIENCE: &str = "web";
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String,
     pub typ: String,
+    pub aud: String,
     pub exp: usize,
+    // Refresh token (session) the access token was minted from, checked for revocation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
+}
+
+pub fn encode_web_jwt(user_uid: Uuid, keys: &JwtKeys, ttl_seconds: u64) -> anyhow::Result<String> {
+    encode_claims(user_uid, None, keys, ttl_seconds)
+}
+
+pub fn encode_session_jwt(
+    user_uid: Uuid,
+    session_uid: Uuid,
+    keys: &JwtKeys,
+    ttl_seconds: u64,
+) -> anyhow::Result<String> {
+    encode_claims(user_uid, Some(session_uid), keys, ttl_seconds)
 }
 
-pub fn encode_web_jwt(user_uid: Uuid, secret: &str, ttl_seconds: u64) -> anyhow::Result<String> {
+fn encode_claims(
+    user_uid: Uuid,
+    session_uid: Option<Uuid>,
+    keys: &JwtKeys,
+    ttl_seconds: u64,
+) -> anyhow::Result<String> {
     let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
-    let claims = Claims {
+    keys.encode(&Claims {
         sub: user_uid.to_string(),
         typ: "web".to_string(),
+        aud: WEB_AUDIENCE.to_string(),
         exp: (now + ttl_seconds) as usize,
+        sid: session_uid.map(|uid| uid.to_string()),
+    })
+}
+
+/*
+ Account, credential and admin routes need a login: a leaked key must not be able to change
+ the password or email, manage keys or sessions, or reach the admin routes
+*/
+pub fn reject_api_key_auth(auth: &AuthContext) -> Result<(), AppError> {
+    if auth.api_key_uid.is_some() {
+        return Err(AppError::coded(
+            ErrorCode::ApiKeyNotAllowed,
+            "API keys cannot be used here, sign in instead",
+        ));
+    }
+    Ok(())
+}
+
+// Checks a relay signature, `sha256=<hex>` where hex is the HMAC-SHA256 of the raw body
+pub fn verify_relay_signature(secret: &str, signature: &str, body: &[u8]) -> bool {
+    let Some(presented) = signature
+        .strip_prefix("sha256=")
+        .and_then(|v| hex::decode(v).ok())
+    else {
+        return false;
     };
-    let token = encode(
-        &Header::new(Algorithm::HS256),
-        &claims,
-        &EncodingKey::from_secret(secret.as_bytes()),
-    )?;
-    Ok(token)
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else {
+        return false;
+    };
+    mac.update(body);
+    mac.verify_slice(&presented).is_ok()
 }
 
 fn is_public_path(path: &str) -> bool {
     matches!(
         path,
-        "/health" | "/version" | "/auth/login" | "/auth/register" | "/api-doc/openapi.json"
+        "/health"
+            | "/version"
+            | "/metrics"
+            | "/auth/login"
+            | "/auth/register"
+            | "/auth/refresh"
+            | "/auth/logout"
+            | "/auth/forgot-password"
+            | "/auth/reset-password"
+            | "/billing/webhook"
+            | "/chat-relay"
+            | "/ingest/email"
+            | "/api-doc/openapi.json"
     ) || path.starts_with("/docs")
 }
 
+// The refresh token the access token was minted from, None once it is revoked or expired
+async fn active_session(state: &AppState, sid: &str) -> Result<Option<RefreshToken>, AppError> {
+    let Ok(refresh_token_uid) = Uuid::parse_str(sid) else {
+        return Ok(None);
+    };
+    let mut tx = state
+        .db_pool
+        .begin()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for checking session"))?;
+    let session = RefreshTokenRepo::get(&mut tx, refresh_token_uid).await;
+    tx.commit()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "committing transaction for checking session"))?;
+    Ok(session
+        .ok()
+        .filter(|session| session.is_active(chrono::Utc::now())))
+}
+
+// Active key matching `key`, recording that it was used
+async fn find_api_key(state: &AppState, key: &str) -> Result<Option<ApiKey>, AppError> {
+    let mut tx = state
+        .db_pool
+        .begin()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for checking api key"))?;
+    let api_key = ApiKeyRepo::find_active_by_key(&mut tx, key).await?;
+    if let Some(api_key) = &api_key {
+        ApiKeyRepo::touch(&mut tx, api_key.uid).await?;
+    }
+    tx.commit()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "committing transaction for checking api key"))?;
+    Ok(api_key)
+}
+
 pub async fn auth_middleware(
     State(state): State<AppState>,
     mut req: Request<Body>,
     next: Next,
-) -> Result<Response, StatusCode> {
+) -> Result<Response, AppError> {
     let path = req.uri().path();
     info!(
         "Auth middleware checking path: {} {:?}",
@@ -83,35 +200,68 @@ pub async fn auth_middleware(
     if let Some(authz) = req.headers().get(AUTHORIZATION) {
         if let Ok(val) = authz.to_str() {
             if let Some(token) = val.strip_prefix("Bearer ") {
-                let mut validation = Validation::new(Algorithm::HS256);
-                validation.validate_exp = true;
-                match decode::<Claims>(
-                    token,
-                    &DecodingKey::from_secret(state.jwt_secret.as_bytes()),
-                    &validation,
-                ) {
-                    Ok(data) if data.claims.typ == "web" => {
-                        if let Ok(user_uid) = Uuid::parse_str(&data.claims.sub) {
+                match state.jwt_keys.decode::<Claims>(token, WEB_AUDIENCE) {
+                    Ok(claims) if claims.typ == "web" => {
+                        // Tokens without a `sid` predate sessions and stay valid until they expire
+                        let session_uid = match &claims.sid {
+                            Some(sid) => match active_session(&state, sid).await? {
+                                Some(session) => Some(session.session_uid),
+                                None => {
+                                    return Err(AppError::Unauthorized("Session ended".into()));
+                                }
+                            },
+                            None => None,
+                        };
+                        if let Ok(user_uid) = Uuid::parse_str(&claims.sub) {
                             req.extensions_mut().insert(AuthContext {
                                 source: AuthSource::Web,
                                 user_uid,
                                 group_uid: None,
+                                api_key_uid: None,
+                                session_uid,
                             });
                             return Ok(next.run(req).await);
                         }
                     }
                     _ => {
-                        return Err(StatusCode::UNAUTHORIZED);
+                        return Err(AppError::Unauthorized("Invalid or expired token".into()));
                     }
                 }
             }
         }
     }
 
-    // 2) Try Chat relay signature
+    // 2) Try API key (scripts and integrations)
+    if let Some(key) = req.headers().get(API_KEY_HEADER) {
+        let key = key
+            .to_str()
+            .map_err(|_| AppError::Unauthorized("Invalid API key".into()))?;
+        let api_key = find_api_key(&state, key)
+            .await?
+            .ok_or_else(|| AppError::Unauthorized("Invalid API key".into()))?;
+        if api_key.scope() == ApiKeyScope::ReadOnly && !req.method().is_safe() {
+            return Err(AppError::coded(
+                ErrorCode::ApiKeyScopeRequired,
+                "This API key is read-only",
+            )
+            .with_details(
+                serde_json::json!({ "required_scope": ApiKeyScope::ReadWrite.as_str() }),
+            ));
+        }
+        req.extensions_mut().insert(AuthContext {
+            source: AuthSource::Web,
+            user_uid: api_key.user_uid,
+            group_uid: None,
+            api_key_uid: Some(api_key.uid),
+            session_uid: None,
+        });
+        return Ok(next.run(req).await);
+    }
+
+    // 3) Try Chat relay signature
     let sig_hdr = req
         .headers()
-        .get("X-Relay-Signature")
+        .get(RELAY_SIGNATURE_HEADER)
         .and_then(|v| v.to_str().ok())
         .map(|s| s.to_string());
     let binding_hdr = req
@@ -125,43 +275,34 @@ pub async fn auth_middleware(
         let bytes = body
             .collect()
             .await
-            .map_err(|_| StatusCode::BAD_REQUEST)?
+            .map_err(|_| AppError::BadRequest("Unreadable request body".into()))?
             .to_bytes();
         let mut req2 = Request::from_parts(parts, Body::from(bytes.clone()));
 
-        // Expect format: sha256=<hex>
-        let calc = {
-            let mut mac = Hmac::<Sha256>::new_from_slice(state.chat_relay_secret.as_bytes())
-                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-            mac.update(&bytes);
-            let tag = mac.finalize().into_bytes();
-            hex::encode(tag)
-        };
-        let presented = sig_hdr.strip_prefix("sha256=").unwrap_or("");
-        if presented != calc {
-            return Err(StatusCode::UNAUTHORIZED);
+        if !verify_relay_signature(&state.chat_relay_secret, &sig_hdr, &bytes) {
+            return Err(AppError::Unauthorized("Invalid relay signature".into()));
         }
 
         // Load binding and ensure active
         let binding_id = match Uuid::parse_str(&binding_hdr) {
             Ok(id) => id,
-            Err(_) => return Err(StatusCode::BAD_REQUEST),
+            Err(_) => return Err(AppError::BadRequest("Invalid chat binding id".into())),
         };
 
         let mut tx = state
             .db_pool
             .begin()
             .await
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            .map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for loading chat binding"))?;
         let binding = crate::repos::chat_binding::ChatBindingRepo::get(&mut tx, binding_id)
             .await
-            .map_err(|_| StatusCode::UNAUTHORIZED)?;
+            .map_err(|_| AppError::Unauthorized("Unknown chat binding".into()))?;
         tx.commit()
             .await
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            .map_err(|e| AppError::from_sqlx_error(e, "committing transaction for loading chat binding"))?;
 
         if binding.status != "active" || binding.revoked_at.is_some() {
-            return Err(StatusCode::UNAUTHORIZED);
+            return Err(AppError::Unauthorized("Chat binding is not active".into()));
         }
 
         // Attach group-scoped context attributed to the user who bound it
@@ -169,10 +310,31 @@ pub async fn auth_middleware(
             source: AuthSource::Chat,
             user_uid: binding.bound_by,
             group_uid: Some(binding.group_uid),
+            api_key_uid: None,
+            session_uid: None,
         });
         return Ok(next.run(req2).await);
     }
 
-    Err(StatusCode::UNAUTHORIZED)
+    Err(AppError::Unauthorized("Missing credentials".into()))
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_relay_signature() {
+        let body = br#"{"platform":"whatsapp","p_uid":"6281234567890","text":"/report"}"#;
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"relay-secret").unwrap();
+        mac.update(body);
+        let signature = format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+
+        assert!(verify_relay_signature("relay-secret", &signature, body));
+        assert!(!verify_relay_signature("other-secret", &signature, body));
+        assert!(!verify_relay_signature("relay-secret", &signature, b"{}"));
+        assert!(!verify_relay_signature("relay-secret", &signature[7..], body));
+        assert!(!verify_relay_signature("relay-secret", "sha256=zz", body));
+    }
+}
@@ -0,0 +1,41 @@
+use axum::{extract::FromRequestParts, http::request::Parts};
+
+use crate::{
+    auth::{AuthContext, AuthSource, reject_api_key_auth},
+    error::AppError,
+    repos::user::{UserRepo, UserRole},
+    types::AppState,
+};
+
+/*
+ Extractor for the platform admin routes: a handler taking `AdminGuard` only runs for web
+ sessions of users whose role is `admin`. Chat tokens are always rejected, they are scoped
+ to a single group, and so are API keys, even those of an admin.
+*/
+pub struct AdminGuard(pub AuthContext);
+
+impl FromRequestParts<AppState> for AdminGuard {
+    type Rejection = AppError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let auth = parts
+            .extensions
+            .get::<AuthContext>()
+            .cloned()
+            .ok_or_else(|| AppError::Unauthorized("Missing authentication".into()))?;
+        if !matches!(auth.source, AuthSource::Web) {
+            return Err(AppError::Unauthorized("Requires admin role".into()));
+        }
+        reject_api_key_auth(&auth)?;
+        let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for checking admin role"))?;
+        let role = UserRepo::get_role(&mut tx, auth.user_uid).await?;
+        tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for checking admin role"))?;
+        if role != UserRole::Admin {
+            return Err(AppError::Unauthorized("Requires admin role".into()));
+        }
+        Ok(Self(auth))
+    }
+}
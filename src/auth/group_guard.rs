@@ -1,31 +1,101 @@
-use sqlx::{Pool, Postgres};
 use uuid::Uuid;
 
 use crate::{
-    auth::{AuthContext, AuthSource},
-    error::AppError,
-    repos::{base::BaseRepo, expense_group::ExpenseGroupRepo},
+    auth::{AuthContext, AuthSource, membership_cache::MembershipCache},
+    error::{AppError, DatabaseError, ErrorCode},
+    repos::{
+        expense_group::ExpenseGroupRepo,
+        expense_group_member::{GroupMemberRepo, GroupRole},
+    },
 };
 
+/*
+ The guards run their queries in the handler's transaction, so a request holds a single
+ connection. Roles are looked up in `cache` first.
+*/
+
+// Any member of the group (owner, admin or member) may pass
 pub async fn group_guard(
     auth: &AuthContext,
     group_uid: Uuid,
-    pool: &Pool<Postgres>,
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    cache: &MembershipCache,
 ) -> Result<(), AppError> {
     if matches!(auth.source, AuthSource::Chat) && auth.group_uid != Some(group_uid) {
-        return Err(AppError::Unauthorized("Group scope mismatch".into()));
+        return Err(AppError::coded(
+            ErrorCode::NotGroupMember,
+            "Group scope mismatch",
+        ));
     }
     Ok(if matches!(auth.source, AuthSource::Web) {
-        let mut tx = pool
-            .begin()
-            .await
-            .map_err(|e| AppError::from_sqlx_error(e, ExpenseGroupRepo::get_table_name()))?;
-        let group = ExpenseGroupRepo::get(&mut tx, group_uid).await?;
-        if auth.user_uid != group.owner {
-            tx.commit()
-                .await
-                .map_err(|e| AppError::from_sqlx_error(e, ExpenseGroupRepo::get_table_name()))?;
-            return Err(AppError::Unauthorized("Not the owner of the group".into()));
-        }
+        group_role(auth, group_uid, tx, cache).await?;
     })
 }
+
+/*
+ Like `group_guard`, for adding entries: archived groups stay readable but reject them
+ until they are unarchived
+*/
+pub async fn group_write_guard(
+    auth: &AuthContext,
+    group_uid: Uuid,
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    cache: &MembershipCache,
+) -> Result<(), AppError> {
+    group_guard(auth, group_uid, tx, cache).await?;
+    if ExpenseGroupRepo::is_archived(tx, group_uid).await? {
+        return Err(AppError::coded(
+            ErrorCode::GroupArchived,
+            "The group is archived, unarchive it to add entries",
+        ));
+    }
+    Ok(())
+}
+
+// Passes only when the caller's role in the group is at least `min_role`
+pub async fn group_role_guard(
+    auth: &AuthContext,
+    group_uid: Uuid,
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    cache: &MembershipCache,
+    min_role: GroupRole,
+) -> Result<GroupRole, AppError> {
+    if matches!(auth.source, AuthSource::Chat) && auth.group_uid != Some(group_uid) {
+        return Err(AppError::coded(
+            ErrorCode::NotGroupMember,
+            "Group scope mismatch",
+        ));
+    }
+    let role = group_role(auth, group_uid, tx, cache).await?;
+    if role < min_role {
+        return Err(AppError::coded(
+            ErrorCode::GroupRoleRequired,
+            format!("Requires {} role in the group", min_role.as_str()),
+        )
+        .with_details(serde_json::json!({ "required_role": min_role.as_str() })));
+    }
+    Ok(role)
+}
+
+// Resolves the caller's role, failing when they are not part of the group at all
+pub async fn group_role(
+    auth: &AuthContext,
+    group_uid: Uuid,
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    cache: &MembershipCache,
+) -> Result<GroupRole, AppError> {
+    if let Some(role) = cache.get(auth.user_uid, group_uid) {
+        return Ok(role);
+    }
+    let role = match GroupMemberRepo::find_role(tx, group_uid, auth.user_uid).await {
+        Ok(role) => role,
+        Err(DatabaseError::NotFound(_)) => {
+            return Err(AppError::coded(ErrorCode::GroupNotFound, "Group not found"));
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let role = role
+        .ok_or_else(|| AppError::coded(ErrorCode::NotGroupMember, "Not a member of the group"))?;
+    cache.insert(auth.user_uid, group_uid, role);
+    Ok(role)
+}
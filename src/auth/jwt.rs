@@ -0,0 +1,183 @@
+use anyhow::Context;
+use jsonwebtoken::{
+    Algorithm, DecodingKey, EncodingKey, Header, Validation, decode, decode_header, encode,
+    errors::{Error, ErrorKind},
+};
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::config::Config;
+
+// A secret tokens are signed with, named in their `kid` header. Only JWT_SECRET has no name
+#[derive(Clone)]
+pub struct JwtKey {
+    pub kid: Option<String>,
+    secret: String,
+}
+
+impl JwtKey {
+    pub fn new(kid: Option<String>, secret: impl Into<String>) -> Self {
+        Self {
+            kid,
+            secret: secret.into(),
+        }
+    }
+}
+
+/*
+ * The active signing keys. New tokens are signed with the first one, tokens signed with any of
+ * them are accepted. Rotating the secret therefore signs no one out: the new key goes first in
+ * JWT_KEYS and the old one is dropped once the access tokens it signed have expired.
+ */
+#[derive(Clone)]
+pub struct JwtKeys {
+    keys: Vec<JwtKey>,
+}
+
+impl JwtKeys {
+    pub fn new(keys: Vec<JwtKey>) -> Self {
+        Self { keys }
+    }
+
+    // A single key without `kid`, like JWT_SECRET on its own
+    pub fn from_secret(secret: &str) -> Self {
+        Self::new(vec![JwtKey::new(None, secret)])
+    }
+
+    // JWT_KEYS, newest first, then JWT_SECRET for the tokens signed before keys were named
+    pub fn from_config(config: &Config) -> Self {
+        let named = config
+            .jwt_keys
+            .iter()
+            .map(|(kid, secret)| JwtKey::new(Some(kid.clone()), secret.clone()));
+        let legacy = config
+            .jwt_secret
+            .iter()
+            .map(|secret| JwtKey::new(None, secret.clone()));
+        Self::new(named.chain(legacy).collect())
+    }
+
+    pub fn encode<T: Serialize>(&self, claims: &T) -> anyhow::Result<String> {
+        let key = self.keys.first().context("No JWT signing key configured")?;
+        let mut header = Header::new(Algorithm::HS256);
+        header.kid = key.kid.clone();
+        let token = encode(
+            &header,
+            claims,
+            &EncodingKey::from_secret(key.secret.as_bytes()),
+        )?;
+        Ok(token)
+    }
+
+    // Checks the signature with the key named by the token, its expiry and its `aud` claim
+    pub fn decode<T: DeserializeOwned>(&self, token: &str, audience: &str) -> Result<T, Error> {
+        let header = decode_header(token)?;
+        let key = self
+            .keys
+            .iter()
+            .find(|key| key.kid == header.kid)
+            .ok_or_else(|| Error::from(ErrorKind::InvalidToken))?;
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.set_audience(&[audience]);
+        validation.set_required_spec_claims(&["exp", "aud"]);
+        let data = decode::<T>(
+            token,
+            &DecodingKey::from_secret(key.secret.as_bytes()),
+            &validation,
+        )?;
+        Ok(data.claims)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct TestClaims {
+        sub: String,
+        aud: String,
+        exp: usize,
+    }
+
+    fn claims(aud: &str) -> TestClaims {
+        TestClaims {
+            sub: "user".to_string(),
+            aud: aud.to_string(),
+            exp: 4_000_000_000,
+        }
+    }
+
+    #[test]
+    fn test_rotation() {
+        let old = JwtKeys::new(vec![JwtKey::new(Some("2025-08".to_string()), "old-secret")]);
+        let old_token = old.encode(&claims("web")).unwrap();
+        assert_eq!(
+            decode_header(&old_token).unwrap().kid.as_deref(),
+            Some("2025-08")
+        );
+
+        let rotated = JwtKeys::new(vec![
+            JwtKey::new(Some("2025-11".to_string()), "new-secret"),
+            JwtKey::new(Some("2025-08".to_string()), "old-secret"),
+        ]);
+        let new_token = rotated.encode(&claims("web")).unwrap();
+        assert_eq!(
+            decode_header(&new_token).unwrap().kid.as_deref(),
+            Some("2025-11")
+        );
+        for token in [&old_token, &new_token] {
+            assert_eq!(
+                rotated.decode::<TestClaims>(token, "web").unwrap(),
+                claims("web")
+            );
+        }
+
+        // Once the old key is dropped its tokens are rejected
+        let retired = JwtKeys::new(vec![JwtKey::new(Some("2025-11".to_string()), "new-secret")]);
+        assert!(retired.decode::<TestClaims>(&old_token, "web").is_err());
+        assert!(retired.decode::<TestClaims>(&new_token, "web").is_ok());
+    }
+
+    #[test]
+    fn test_legacy_secret() {
+        let legacy = JwtKeys::from_secret("secret");
+        let token = legacy.encode(&claims("web")).unwrap();
+        assert_eq!(decode_header(&token).unwrap().kid, None);
+
+        let keys = JwtKeys::new(vec![
+            JwtKey::new(Some("2025-11".to_string()), "new-secret"),
+            JwtKey::new(None, "secret"),
+        ]);
+        assert!(keys.decode::<TestClaims>(&token, "web").is_ok());
+        // A named key does not stand in for a missing `kid`
+        let named = JwtKeys::new(vec![JwtKey::new(Some("2025-11".to_string()), "secret")]);
+        assert!(named.decode::<TestClaims>(&token, "web").is_err());
+    }
+
+    #[test]
+    fn test_audience() {
+        let keys = JwtKeys::from_secret("secret");
+        let token = keys.encode(&claims("chat-relay")).unwrap();
+        assert!(keys.decode::<TestClaims>(&token, "web").is_err());
+        assert!(keys.decode::<TestClaims>(&token, "chat-relay").is_ok());
+
+        #[derive(Serialize)]
+        struct NoAudience {
+            sub: String,
+            exp: usize,
+        }
+        let token = keys
+            .encode(&NoAudience {
+                sub: "user".to_string(),
+                exp: 4_000_000_000,
+            })
+            .unwrap();
+        assert!(keys.decode::<TestClaims>(&token, "web").is_err());
+    }
+
+    #[test]
+    fn test_no_keys() {
+        assert!(JwtKeys::new(Vec::new()).encode(&claims("web")).is_err());
+    }
+}
@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use uuid::Uuid;
+
+use crate::repos::expense_group_member::GroupRole;
+
+// Changes made by another instance are picked up after at most this long
+const MEMBERSHIP_CACHE_TTL: Duration = Duration::from_secs(30);
+// Above this many entries, expired ones are dropped
+const MAX_CACHED_MEMBERSHIPS: usize = 10_000;
+
+/*
+ * Roles of users in groups, keyed by (user_uid, group_uid), so the group guards do not query
+ * the database on every request. Only roles that were found are cached: a user who is not a
+ * member keeps getting checked against the database. Handlers changing a membership, or
+ * deleting a group, invalidate it right away; the TTL covers the other instances.
+ */
+#[derive(Debug, Default)]
+pub struct MembershipCache {
+    roles: Mutex<HashMap<(Uuid, Uuid), (GroupRole, Instant)>>,
+}
+
+impl MembershipCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, user_uid: Uuid, group_uid: Uuid) -> Option<GroupRole> {
+        self.get_at(user_uid, group_uid, Instant::now())
+    }
+
+    fn get_at(&self, user_uid: Uuid, group_uid: Uuid, now: Instant) -> Option<GroupRole> {
+        let roles = self.roles.lock().unwrap();
+        roles
+            .get(&(user_uid, group_uid))
+            .filter(|(_, cached_at)| now.duration_since(*cached_at) < MEMBERSHIP_CACHE_TTL)
+            .map(|(role, _)| *role)
+    }
+
+    pub fn insert(&self, user_uid: Uuid, group_uid: Uuid, role: GroupRole) {
+        self.insert_at(user_uid, group_uid, role, Instant::now());
+    }
+
+    fn insert_at(&self, user_uid: Uuid, group_uid: Uuid, role: GroupRole, now: Instant) {
+        let mut roles = self.roles.lock().unwrap();
+        if roles.len() >= MAX_CACHED_MEMBERSHIPS {
+            roles.retain(|_, (_, cached_at)| now.duration_since(*cached_at) < MEMBERSHIP_CACHE_TTL);
+        }
+        roles.insert((user_uid, group_uid), (role, now));
+    }
+
+    // After the user joined, left or changed role in the group
+    pub fn invalidate(&self, user_uid: Uuid, group_uid: Uuid) {
+        self.roles.lock().unwrap().remove(&(user_uid, group_uid));
+    }
+
+    // After the group itself changed, e.g. it was deleted
+    pub fn invalidate_group(&self, group_uid: Uuid) {
+        self.roles
+            .lock()
+            .unwrap()
+            .retain(|(_, cached_group_uid), _| *cached_group_uid != group_uid);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cached_role_expires() {
+        let cache = MembershipCache::new();
+        let (user_uid, group_uid) = (Uuid::new_v4(), Uuid::new_v4());
+        let now = Instant::now();
+        cache.insert_at(user_uid, group_uid, GroupRole::Admin, now);
+
+        assert_eq!(
+            cache.get_at(user_uid, group_uid, now),
+            Some(GroupRole::Admin)
+        );
+        assert_eq!(cache.get_at(group_uid, user_uid, now), None);
+        assert_eq!(
+            cache.get_at(user_uid, group_uid, now + MEMBERSHIP_CACHE_TTL),
+            None
+        );
+    }
+
+    #[test]
+    fn test_invalidate() {
+        let cache = MembershipCache::new();
+        let (alice, bob, group_uid) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let other_group_uid = Uuid::new_v4();
+        cache.insert(alice, group_uid, GroupRole::Owner);
+        cache.insert(bob, group_uid, GroupRole::Member);
+        cache.insert(bob, other_group_uid, GroupRole::Member);
+
+        cache.invalidate(bob, group_uid);
+        assert_eq!(cache.get(bob, group_uid), None);
+        assert_eq!(cache.get(alice, group_uid), Some(GroupRole::Owner));
+
+        cache.invalidate_group(group_uid);
+        assert_eq!(cache.get(alice, group_uid), None);
+        assert_eq!(cache.get(bob, other_group_uid), Some(GroupRole::Member));
+    }
+}
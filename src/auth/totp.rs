@@ -0,0 +1,192 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+
+// RFC 6238 defaults, which every authenticator app supports
+const STEP_SECONDS: u64 = 30;
+const DIGITS: u32 = 6;
+const SECRET_BYTES: usize = 20;
+// Accept the previous and next code as well to tolerate clock drift
+const ALLOWED_DRIFT_STEPS: u64 = 1;
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+// Base32 secret (RFC 4648, unpadded), the format authenticator apps expect
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; SECRET_BYTES];
+    OsRng.fill_bytes(&mut bytes);
+    base32_encode(&bytes)
+}
+
+pub fn otpauth_uri(issuer: &str, account: &str, secret: &str) -> String {
+    let issuer = encode_uri_component(issuer);
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        issuer,
+        encode_uri_component(account),
+        secret,
+        issuer,
+        DIGITS,
+        STEP_SECONDS
+    )
+}
+
+pub fn current_step(unix_time: u64) -> u64 {
+    unix_time / STEP_SECONDS
+}
+
+pub fn code_at_step(secret: &str, step: u64) -> Option<String> {
+    let key = base32_decode(secret)?;
+    let mut mac = Hmac::<Sha1>::new_from_slice(&key).ok()?;
+    mac.update(&step.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    // Dynamic truncation, RFC 4226 section 5.3
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+    Some(format!(
+        "{:0width$}",
+        binary % 10u32.pow(DIGITS),
+        width = DIGITS as usize
+    ))
+}
+
+/*
+ * Returns the time step the code belongs to. Callers store it and pass it back as
+ * `last_used_step` so a code cannot be replayed within its validity window.
+ */
+pub fn verify_code(
+    secret: &str,
+    code: &str,
+    unix_time: u64,
+    last_used_step: Option<u64>,
+) -> Option<u64> {
+    let code = code.trim().replace(' ', "");
+    if code.len() != DIGITS as usize || !code.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let now = current_step(unix_time);
+    (now.saturating_sub(ALLOWED_DRIFT_STEPS)..=now + ALLOWED_DRIFT_STEPS)
+        .filter(|step| last_used_step.is_none_or(|last| *step > last))
+        .find(|step| code_at_step(secret, *step).is_some_and(|expected| expected == code))
+}
+
+fn base32_encode(bytes: &[u8]) -> String {
+    let mut output = String::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for byte in bytes {
+        buffer = (buffer << 8) | *byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            output.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        output.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    output
+}
+
+fn base32_decode(input: &str) -> Option<Vec<u8>> {
+    let mut output = Vec::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in input.chars().filter(|c| *c != '=' && !c.is_whitespace()) {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|a| *a as char == c.to_ascii_uppercase())?;
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            output.push(((buffer >> bits) & 0xff) as u8);
+        }
+    }
+    Some(output)
+}
+
+fn encode_uri_component(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Secret "12345678901234567890" from RFC 6238 appendix B
+    const RFC_SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    #[test]
+    fn test_base32_roundtrip() {
+        assert_eq!(base32_encode(b"12345678901234567890"), RFC_SECRET);
+        assert_eq!(
+            base32_decode(RFC_SECRET).unwrap(),
+            b"12345678901234567890".to_vec()
+        );
+        assert_eq!(base32_encode(b"f"), "MY");
+        assert_eq!(base32_decode("my======").unwrap(), b"f".to_vec());
+        assert!(base32_decode("not base32!").is_none());
+        assert_eq!(generate_secret().len(), 32);
+    }
+
+    #[test]
+    fn test_code_at_step() {
+        // RFC 6238 SHA1 vectors, truncated to 6 digits
+        assert_eq!(
+            code_at_step(RFC_SECRET, current_step(59)).unwrap(),
+            "287082"
+        );
+        assert_eq!(
+            code_at_step(RFC_SECRET, current_step(1111111109)).unwrap(),
+            "081804"
+        );
+        assert_eq!(
+            code_at_step(RFC_SECRET, current_step(2000000000)).unwrap(),
+            "279037"
+        );
+    }
+
+    #[test]
+    fn test_verify_code() {
+        let now = 1111111109;
+        let step = current_step(now);
+        assert_eq!(verify_code(RFC_SECRET, "081804", now, None), Some(step));
+        assert_eq!(verify_code(RFC_SECRET, "081 804", now, None), Some(step));
+        // Previous window is still accepted
+        assert_eq!(
+            verify_code(RFC_SECRET, "081804", now + STEP_SECONDS, None),
+            Some(step)
+        );
+        assert_eq!(
+            verify_code(RFC_SECRET, "081804", now + 3 * STEP_SECONDS, None),
+            None
+        );
+        // Replays are rejected
+        assert_eq!(verify_code(RFC_SECRET, "081804", now, Some(step)), None);
+        assert_eq!(verify_code(RFC_SECRET, "000000", now, None), None);
+        assert_eq!(verify_code(RFC_SECRET, "abcdef", now, None), None);
+    }
+
+    #[test]
+    fn test_otpauth_uri() {
+        assert_eq!(
+            otpauth_uri("Expense Tracker", "a@b.com", "ABC"),
+            "otpauth://totp/Expense%20Tracker:a%40b.com?secret=ABC&issuer=Expense%20Tracker&algorithm=SHA1&digits=6&period=30"
+        );
+    }
+}
@@ -0,0 +1,395 @@
+/*
+ Backups of a whole deployment, for moving it to another server or rolling it back, without
+ needing pg_dump of the same Postgres version.
+
+ An archive is gzip-compressed JSON: the format version, the last migration applied to the
+ database, and every table with its columns and rows. Tables are listed parents first, so a
+ restore can insert them in order without breaking foreign keys. The whole archive is held in
+ memory, which is fine for the size of a self-hosted deployment.
+*/
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Read;
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Utc};
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+
+// Bumped when the layout of the archive changes, older archives stay readable
+pub const FORMAT_VERSION: u32 = 1;
+
+// Owned by sqlx, a restored database keeps its own migration history
+const EXCLUDED_TABLES: &[&str] = &["_sqlx_migrations"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Archive {
+    pub format_version: u32,
+    pub created_at: DateTime<Utc>,
+    // Version of the last migration applied to the source database, 0 when there was none
+    pub schema_version: i64,
+    pub tables: Vec<TableDump>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TableDump {
+    pub name: String,
+    pub columns: Vec<Column>,
+    // One JSON object per row, keyed by column name
+    pub rows: Vec<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct Column {
+    pub name: String,
+    // As Postgres prints it, e.g. `numeric(12,2)` or `timestamp with time zone`
+    pub data_type: String,
+    pub nullable: bool,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct RestoreSummary {
+    pub tables: usize,
+    pub rows: u64,
+}
+
+impl Archive {
+    pub fn row_count(&self) -> usize {
+        self.tables.iter().map(|table| table.rows.len()).sum()
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        serde_json::to_writer(&mut encoder, self).context("Failed to write backup archive")?;
+        Ok(encoder.finish()?)
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let mut json = Vec::new();
+        GzDecoder::new(bytes)
+            .read_to_end(&mut json)
+            .context("Not a gzip-compressed backup archive")?;
+        let archive: Self =
+            serde_json::from_slice(&json).context("Failed to read backup archive")?;
+        if archive.format_version > FORMAT_VERSION {
+            bail!(
+                "Backup archive format {} is newer than this release supports ({}), upgrade first",
+                archive.format_version,
+                FORMAT_VERSION
+            );
+        }
+        Ok(archive)
+    }
+}
+
+pub const FILE_PREFIX: &str = "expense-tracker-backup-";
+pub const FILE_SUFFIX: &str = ".json.gz";
+
+// Default file name of an archive taken at `created_at`
+pub fn file_name(created_at: DateTime<Utc>) -> String {
+    format!(
+        "{}{}{}",
+        FILE_PREFIX,
+        created_at.format("%Y%m%dT%H%M%SZ"),
+        FILE_SUFFIX
+    )
+}
+
+/*
+ Orders `tables` so every table comes after the tables its foreign keys point to. `references`
+ holds (table, referenced table) pairs; references to itself or to tables not listed are
+ ignored. Tables left in a cycle go last, by name.
+*/
+pub fn dependency_order(tables: &[String], references: &[(String, String)]) -> Vec<String> {
+    let mut parents: BTreeMap<&str, BTreeSet<&str>> = tables
+        .iter()
+        .map(|table| (table.as_str(), BTreeSet::new()))
+        .collect();
+    for (table, referenced) in references {
+        if table == referenced || !parents.contains_key(referenced.as_str()) {
+            continue;
+        }
+        if let Some(table_parents) = parents.get_mut(table.as_str()) {
+            table_parents.insert(referenced.as_str());
+        }
+    }
+
+    let mut ordered: Vec<String> = Vec::with_capacity(tables.len());
+    while !parents.is_empty() {
+        let ready: Vec<&str> = parents
+            .iter()
+            .filter(|(_, table_parents)| table_parents.is_empty())
+            .map(|(table, _)| *table)
+            .collect();
+        if ready.is_empty() {
+            ordered.extend(parents.keys().map(|table| table.to_string()));
+            break;
+        }
+        for table in ready {
+            parents.remove(table);
+            for table_parents in parents.values_mut() {
+                table_parents.remove(table);
+            }
+            ordered.push(table.to_string());
+        }
+    }
+    ordered
+}
+
+fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+async fn schema_version(tx: &mut sqlx::Transaction<'_, sqlx::Postgres>) -> Result<i64> {
+    let version = sqlx::query_scalar::<_, i64>(
+        "SELECT COALESCE(MAX(version), 0) FROM _sqlx_migrations WHERE success",
+    )
+    .fetch_one(tx.as_mut())
+    .await
+    .context("Failed to read the applied migrations")?;
+    Ok(version)
+}
+
+// Tables of the `public` schema, parents first
+async fn list_tables(tx: &mut sqlx::Transaction<'_, sqlx::Postgres>) -> Result<Vec<String>> {
+    let tables = sqlx::query_scalar::<_, String>(
+        "SELECT tablename::text FROM pg_tables WHERE schemaname = 'public' AND tablename <> ALL($1) ORDER BY tablename",
+    )
+    .bind(EXCLUDED_TABLES)
+    .fetch_all(tx.as_mut())
+    .await
+    .context("Failed to list tables")?;
+    let references = sqlx::query_as::<_, (String, String)>(
+        "SELECT c.relname::text, p.relname::text FROM pg_constraint con \
+        JOIN pg_class c ON c.oid = con.conrelid JOIN pg_class p ON p.oid = con.confrelid \
+        WHERE con.contype = 'f' AND con.connamespace = 'public'::regnamespace",
+    )
+    .fetch_all(tx.as_mut())
+    .await
+    .context("Failed to list foreign keys")?;
+    Ok(dependency_order(&tables, &references))
+}
+
+// Generated columns are left out, Postgres computes them again on restore
+async fn list_columns(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    table: &str,
+) -> Result<Vec<Column>> {
+    let columns = sqlx::query_as::<_, Column>(
+        "SELECT a.attname::text AS name, format_type(a.atttypid, a.atttypmod) AS data_type, NOT a.attnotnull AS nullable \
+        FROM pg_attribute a JOIN pg_class c ON c.oid = a.attrelid \
+        WHERE c.relname = $1 AND c.relnamespace = 'public'::regnamespace AND a.attnum > 0 AND NOT a.attisdropped AND a.attgenerated = '' \
+        ORDER BY a.attnum",
+    )
+    .bind(table)
+    .fetch_all(tx.as_mut())
+    .await
+    .with_context(|| format!("Failed to list the columns of {}", table))?;
+    Ok(columns)
+}
+
+// Reads every table in one snapshot, rows written meanwhile are either all in or all out
+pub async fn dump(db_pool: &PgPool) -> Result<Archive> {
+    let mut tx = db_pool.begin().await?;
+    sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ, READ ONLY")
+        .execute(tx.as_mut())
+        .await?;
+    let schema_version = schema_version(&mut tx).await?;
+
+    let mut tables = Vec::new();
+    for name in list_tables(&mut tx).await? {
+        let columns = list_columns(&mut tx, &name).await?;
+        // Numbers are written as strings so numerics don't go through f64 on the way
+        let query = format!(
+            "SELECT (SELECT jsonb_object_agg(key, CASE WHEN jsonb_typeof(value) = 'number' THEN to_jsonb(value #>> '{{}}') ELSE value END) \
+            FROM jsonb_each(to_jsonb(t))) FROM {} t",
+            quote_ident(&name)
+        );
+        let rows = sqlx::query_scalar::<_, serde_json::Value>(&query)
+            .fetch_all(tx.as_mut())
+            .await
+            .with_context(|| format!("Failed to read {}", name))?;
+        tables.push(TableDump {
+            name,
+            columns,
+            rows,
+        });
+    }
+    tx.commit().await?;
+
+    Ok(Archive {
+        format_version: FORMAT_VERSION,
+        created_at: Utc::now(),
+        schema_version,
+        tables,
+    })
+}
+
+/*
+ Loads an archive into a database migrated at least as far as the one it was taken from.
+ Columns added by later migrations get their defaults. Unless `replace` is set the database must
+ be empty; with it every table is truncated first. Either everything is restored or nothing.
+*/
+pub async fn restore(db_pool: &PgPool, archive: &Archive, replace: bool) -> Result<RestoreSummary> {
+    let mut tx = db_pool.begin().await?;
+    let current_version = schema_version(&mut tx).await?;
+    if archive.schema_version > current_version {
+        bail!(
+            "The backup was taken at migration {}, this database is at {}; run the migrations first",
+            archive.schema_version,
+            current_version
+        );
+    }
+
+    let tables = list_tables(&mut tx).await?;
+    for table in &archive.tables {
+        if !tables.contains(&table.name) {
+            bail!("Table {} of the backup does not exist", table.name);
+        }
+        let columns = list_columns(&mut tx, &table.name).await?;
+        for column in &table.columns {
+            if !columns.iter().any(|existing| existing.name == column.name) {
+                bail!(
+                    "Column {}.{} of the backup does not exist",
+                    table.name,
+                    column.name
+                );
+            }
+        }
+    }
+
+    if replace {
+        if !tables.is_empty() {
+            let names: Vec<String> = tables.iter().map(|table| quote_ident(table)).collect();
+            sqlx::query(&format!("TRUNCATE {} CASCADE", names.join(", ")))
+                .execute(tx.as_mut())
+                .await
+                .context("Failed to empty the database")?;
+        }
+    } else {
+        for table in &tables {
+            let query = format!("SELECT EXISTS (SELECT 1 FROM {})", quote_ident(table));
+            let has_rows = sqlx::query_scalar::<_, bool>(&query)
+                .fetch_one(tx.as_mut())
+                .await?;
+            if has_rows {
+                bail!(
+                    "The database already has data in {}, restore with replace to overwrite it",
+                    table
+                );
+            }
+        }
+    }
+
+    // In the order of the target database, in case foreign keys were added since
+    let mut summary = RestoreSummary::default();
+    for name in &tables {
+        let Some(table) = archive.tables.iter().find(|table| &table.name == name) else {
+            continue;
+        };
+        summary.tables += 1;
+        if table.rows.is_empty() || table.columns.is_empty() {
+            continue;
+        }
+        let columns: Vec<String> = table
+            .columns
+            .iter()
+            .map(|column| quote_ident(&column.name))
+            .collect();
+        let columns = columns.join(", ");
+        // One statement per table, so rows referencing rows of the same table need no ordering
+        let query = format!(
+            "INSERT INTO {table} ({columns}) SELECT {columns} FROM jsonb_populate_recordset(NULL::{table}, $1)",
+            table = quote_ident(name)
+        );
+        let result = sqlx::query(&query)
+            .bind(serde_json::Value::Array(table.rows.clone()))
+            .execute(tx.as_mut())
+            .await
+            .with_context(|| format!("Failed to restore {}", name))?;
+        summary.rows += result.rows_affected();
+    }
+    tx.commit().await?;
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn names(tables: &[&str]) -> Vec<String> {
+        tables.iter().map(|table| table.to_string()).collect()
+    }
+
+    fn references(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(table, referenced)| (table.to_string(), referenced.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_dependency_order() {
+        let tables = names(&["categories", "expense_entries", "expense_groups", "users"]);
+        let order = dependency_order(
+            &tables,
+            &references(&[
+                ("expense_entries", "categories"),
+                ("expense_entries", "expense_groups"),
+                ("categories", "expense_groups"),
+                ("categories", "categories"),
+                ("expense_groups", "users"),
+                ("expense_entries", "_sqlx_migrations"),
+            ]),
+        );
+        assert_eq!(
+            order,
+            names(&["users", "expense_groups", "categories", "expense_entries"])
+        );
+
+        let order = dependency_order(
+            &names(&["a", "b", "c"]),
+            &references(&[("a", "b"), ("b", "a")]),
+        );
+        assert_eq!(order, names(&["c", "a", "b"]));
+    }
+
+    #[test]
+    fn test_archive_round_trip() {
+        let archive = Archive {
+            format_version: FORMAT_VERSION,
+            created_at: Utc.with_ymd_and_hms(2025, 11, 18, 9, 0, 0).unwrap(),
+            schema_version: 20251118090000,
+            tables: vec![TableDump {
+                name: "users".to_string(),
+                columns: vec![Column {
+                    name: "email".to_string(),
+                    data_type: "character varying(255)".to_string(),
+                    nullable: false,
+                }],
+                rows: vec![serde_json::json!({ "email": "budi@example.com" })],
+            }],
+        };
+        let decoded = Archive::decode(&archive.encode().unwrap()).unwrap();
+        assert_eq!(decoded.schema_version, archive.schema_version);
+        assert_eq!(decoded.tables[0].columns, archive.tables[0].columns);
+        assert_eq!(decoded.tables[0].rows, archive.tables[0].rows);
+        assert_eq!(decoded.row_count(), 1);
+
+        let newer = Archive {
+            format_version: FORMAT_VERSION + 1,
+            ..archive
+        };
+        assert!(Archive::decode(&newer.encode().unwrap()).is_err());
+        assert!(Archive::decode(b"{}").is_err());
+    }
+
+    #[test]
+    fn test_file_name() {
+        assert_eq!(
+            file_name(Utc.with_ymd_and_hms(2025, 11, 18, 2, 0, 5).unwrap()),
+            "expense-tracker-backup-20251118T020005Z.json.gz"
+        );
+    }
+}
@@ -0,0 +1,96 @@
+pub mod stripe;
+
+use async_trait::async_trait;
+use axum::http::HeaderMap;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::{config::Config, types::SubscriptionTier};
+
+#[derive(Debug, thiserror::Error)]
+pub enum WebhookError {
+    #[error("Invalid webhook signature")]
+    InvalidSignature,
+    #[error("Invalid webhook payload: {0}")]
+    InvalidPayload(String),
+}
+
+// A webhook event translated out of the provider's own vocabulary
+#[derive(Debug, Clone, PartialEq)]
+pub struct BillingEvent {
+    /// Provider event id, used to apply retried deliveries once
+    pub id: String,
+    pub kind: BillingEventKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BillingEventKind {
+    // The user paid for a tier, `subscription_id` identifies it in later events
+    CheckoutCompleted {
+        user_uid: Uuid,
+        tier: SubscriptionTier,
+        customer_id: String,
+        subscription_id: String,
+    },
+    // A renewal went through, the subscription runs until `period_end`
+    InvoicePaid {
+        subscription_id: String,
+        period_start: DateTime<Utc>,
+        period_end: DateTime<Utc>,
+    },
+    // The user cancelled (or resumed) a subscription that stays active until the period ends
+    CancellationChanged {
+        subscription_id: String,
+        cancel_at_period_end: bool,
+    },
+    SubscriptionEnded {
+        subscription_id: String,
+    },
+}
+
+pub struct CheckoutRequest<'a> {
+    pub user_uid: Uuid,
+    pub email: &'a str,
+    pub tier: &'a SubscriptionTier,
+    pub success_url: &'a str,
+    pub cancel_url: &'a str,
+}
+
+pub struct CheckoutSession {
+    pub id: String,
+    /// Hosted payment page the user is redirected to
+    pub url: String,
+}
+
+#[async_trait]
+pub trait PaymentProvider {
+    // Stored in `subscriptions.provider` and `billing_events.provider`
+    fn name(&self) -> &'static str;
+
+    async fn create_checkout_session(
+        &self,
+        request: CheckoutRequest<'_>,
+    ) -> Result<CheckoutSession, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Verifies the signature and parses the event, Ok(None) for events we do not act on
+    fn parse_webhook(
+        &self,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> Result<Option<BillingEvent>, WebhookError>;
+}
+
+// Billing is optional: without a configured provider the billing routes answer 400
+pub fn payment_provider_from_config(
+    config: &Config,
+) -> Option<Box<dyn PaymentProvider + Send + Sync>> {
+    match stripe::StripeProvider::from_config(config) {
+        Some(provider) => Some(Box::new(provider)),
+        None => {
+            tracing::warn!(
+                "STRIPE_SECRET_KEY or STRIPE_WEBHOOK_SECRET not set, billing is disabled"
+            );
+            None
+        }
+    }
+}
@@ -0,0 +1,423 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use axum::http::HeaderMap;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::{config::Config, types::SubscriptionTier};
+
+use super::{
+    BillingEvent, BillingEventKind, CheckoutRequest, CheckoutSession, PaymentProvider, WebhookError,
+};
+
+const STRIPE_CHECKOUT_URL: &str = "https://api.stripe.com/v1/checkout/sessions";
+// Stripe's own libraries reject signatures older than five minutes
+const SIGNATURE_TOLERANCE_SECS: i64 = 300;
+
+#[derive(Clone)]
+pub struct StripeProvider {
+    secret_key: String,
+    webhook_secret: String,
+    // Stripe price id per tier, from STRIPE_PRICE_IDS="personal=price_123,family=price_456"
+    price_ids: Vec<(SubscriptionTier, String)>,
+    client: reqwest::Client,
+}
+
+impl StripeProvider {
+    pub fn from_config(config: &Config) -> Option<Self> {
+        Some(Self {
+            secret_key: config.stripe_secret_key.clone()?,
+            webhook_secret: config.stripe_webhook_secret.clone()?,
+            price_ids: parse_price_ids(config.stripe_price_ids.as_deref().unwrap_or_default()),
+            client: reqwest::Client::new(),
+        })
+    }
+
+    fn price_id(&self, tier: &SubscriptionTier) -> Option<&str> {
+        self.price_ids
+            .iter()
+            .find(|(t, _)| t == tier)
+            .map(|(_, price)| price.as_str())
+    }
+}
+
+fn parse_price_ids(value: &str) -> Vec<(SubscriptionTier, String)> {
+    value
+        .split(',')
+        .filter_map(|pair| {
+            let (tier, price) = pair.split_once('=')?;
+            let tier = SubscriptionTier::parse(tier)?;
+            let price = price.trim();
+            (!price.is_empty()).then(|| (tier, price.to_string()))
+        })
+        .collect()
+}
+
+/*
+ `Stripe-Signature` looks like `t=1700000000,v1=<hex>,v1=<hex>`: an HMAC-SHA256 of
+ `{t}.{body}` with the endpoint secret. Several v1 values show up while a secret is rolled.
+*/
+fn verify_signature(secret: &str, header: &str, body: &[u8], now: i64) -> bool {
+    let mut timestamp = None;
+    let mut signatures = Vec::new();
+    for part in header.split(',') {
+        match part.trim().split_once('=') {
+            Some(("t", value)) => timestamp = value.parse::<i64>().ok(),
+            Some(("v1", value)) => signatures.push(value),
+            _ => {}
+        }
+    }
+    let Some(timestamp) = timestamp else {
+        return false;
+    };
+    if (now - timestamp).abs() > SIGNATURE_TOLERANCE_SECS {
+        return false;
+    }
+    signatures.into_iter().any(|signature| {
+        let Ok(expected) = hex::decode(signature) else {
+            return false;
+        };
+        let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else {
+            return false;
+        };
+        mac.update(timestamp.to_string().as_bytes());
+        mac.update(b".");
+        mac.update(body);
+        mac.verify_slice(&expected).is_ok()
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct StripeEvent {
+    id: String,
+    #[serde(rename = "type")]
+    kind: String,
+    data: StripeEventData,
+}
+
+#[derive(Debug, Deserialize)]
+struct StripeEventData {
+    object: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct CheckoutSessionObject {
+    client_reference_id: Option<String>,
+    customer: Option<String>,
+    subscription: Option<String>,
+    #[serde(default)]
+    metadata: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct InvoiceObject {
+    subscription: Option<String>,
+    lines: InvoiceLines,
+}
+
+#[derive(Debug, Deserialize)]
+struct InvoiceLines {
+    data: Vec<InvoiceLine>,
+}
+
+#[derive(Debug, Deserialize)]
+struct InvoiceLine {
+    period: InvoicePeriod,
+}
+
+#[derive(Debug, Deserialize)]
+struct InvoicePeriod {
+    start: i64,
+    end: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct SubscriptionObject {
+    id: String,
+    #[serde(default)]
+    cancel_at_period_end: bool,
+}
+
+fn object<T: serde::de::DeserializeOwned>(value: serde_json::Value) -> Result<T, WebhookError> {
+    serde_json::from_value(value).map_err(|e| WebhookError::InvalidPayload(e.to_string()))
+}
+
+fn timestamp(secs: i64) -> Result<DateTime<Utc>, WebhookError> {
+    DateTime::from_timestamp(secs, 0)
+        .ok_or_else(|| WebhookError::InvalidPayload(format!("Invalid timestamp: {}", secs)))
+}
+
+fn parse_event(body: &[u8]) -> Result<Option<BillingEvent>, WebhookError> {
+    let event: StripeEvent =
+        serde_json::from_slice(body).map_err(|e| WebhookError::InvalidPayload(e.to_string()))?;
+    let kind = match event.kind.as_str() {
+        "checkout.session.completed" => {
+            let session: CheckoutSessionObject = object(event.data.object)?;
+            let user_uid = session
+                .client_reference_id
+                .as_deref()
+                .and_then(|uid| Uuid::parse_str(uid).ok())
+                .ok_or_else(|| {
+                    WebhookError::InvalidPayload("Missing client_reference_id".to_string())
+                })?;
+            let tier = session
+                .metadata
+                .get("tier")
+                .and_then(|tier| SubscriptionTier::parse(tier))
+                .ok_or_else(|| WebhookError::InvalidPayload("Missing tier metadata".to_string()))?;
+            let (Some(customer_id), Some(subscription_id)) =
+                (session.customer, session.subscription)
+            else {
+                // One-off payments have no subscription to track
+                return Ok(None);
+            };
+            BillingEventKind::CheckoutCompleted {
+                user_uid,
+                tier,
+                customer_id,
+                subscription_id,
+            }
+        }
+        "invoice.paid" => {
+            let invoice: InvoiceObject = object(event.data.object)?;
+            let Some(subscription_id) = invoice.subscription else {
+                return Ok(None);
+            };
+            let periods = invoice.lines.data.iter().map(|line| &line.period);
+            let (Some(start), Some(end)) = (
+                periods.clone().map(|period| period.start).min(),
+                periods.map(|period| period.end).max(),
+            ) else {
+                return Err(WebhookError::InvalidPayload(
+                    "Invoice has no lines".to_string(),
+                ));
+            };
+            BillingEventKind::InvoicePaid {
+                subscription_id,
+                period_start: timestamp(start)?,
+                period_end: timestamp(end)?,
+            }
+        }
+        "customer.subscription.updated" => {
+            let subscription: SubscriptionObject = object(event.data.object)?;
+            BillingEventKind::CancellationChanged {
+                subscription_id: subscription.id,
+                cancel_at_period_end: subscription.cancel_at_period_end,
+            }
+        }
+        "customer.subscription.deleted" => {
+            let subscription: SubscriptionObject = object(event.data.object)?;
+            BillingEventKind::SubscriptionEnded {
+                subscription_id: subscription.id,
+            }
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(BillingEvent { id: event.id, kind }))
+}
+
+#[derive(Debug, Deserialize)]
+struct CheckoutSessionResponse {
+    id: String,
+    url: String,
+}
+
+#[async_trait]
+impl PaymentProvider for StripeProvider {
+    fn name(&self) -> &'static str {
+        "stripe"
+    }
+
+    async fn create_checkout_session(
+        &self,
+        request: CheckoutRequest<'_>,
+    ) -> Result<CheckoutSession, Box<dyn std::error::Error + Send + Sync>> {
+        let price_id = self.price_id(request.tier).ok_or_else(|| {
+            format!(
+                "No Stripe price configured for tier {}",
+                request.tier.as_str()
+            )
+        })?;
+        let user_uid = request.user_uid.to_string();
+        let form = [
+            ("mode", "subscription"),
+            ("line_items[0][price]", price_id),
+            ("line_items[0][quantity]", "1"),
+            ("success_url", request.success_url),
+            ("cancel_url", request.cancel_url),
+            ("customer_email", request.email),
+            ("client_reference_id", user_uid.as_str()),
+            ("metadata[tier]", request.tier.as_str()),
+        ];
+        let response = self
+            .client
+            .post(STRIPE_CHECKOUT_URL)
+            .bearer_auth(&self.secret_key)
+            .form(&form)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!("Stripe returned {}: {}", status, body).into());
+        }
+        let session: CheckoutSessionResponse = response.json().await?;
+        Ok(CheckoutSession {
+            id: session.id,
+            url: session.url,
+        })
+    }
+
+    fn parse_webhook(
+        &self,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> Result<Option<BillingEvent>, WebhookError> {
+        let header = headers
+            .get("Stripe-Signature")
+            .and_then(|v| v.to_str().ok())
+            .ok_or(WebhookError::InvalidSignature)?;
+        if !verify_signature(&self.webhook_secret, header, body, Utc::now().timestamp()) {
+            return Err(WebhookError::InvalidSignature);
+        }
+        parse_event(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(format!("{}.", timestamp).as_bytes());
+        mac.update(body);
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        let body = br#"{"id":"evt_1"}"#;
+        let now = 1_700_000_000;
+        let signature = sign("whsec_test", now, body);
+
+        let header = format!("t={},v1={}", now, signature);
+        assert!(verify_signature("whsec_test", &header, body, now + 10));
+        // Rolled secrets send one signature per secret
+        let header = format!("t={},v1=00ff,v1={}", now, signature);
+        assert!(verify_signature("whsec_test", &header, body, now));
+
+        assert!(!verify_signature("whsec_other", &header, body, now));
+        assert!(!verify_signature(
+            "whsec_test",
+            &header,
+            br#"{"id":"evt_2"}"#,
+            now
+        ));
+        assert!(!verify_signature("whsec_test", &header, body, now + 301));
+        assert!(!verify_signature(
+            "whsec_test",
+            &format!("v1={}", signature),
+            body,
+            now
+        ));
+    }
+
+    #[test]
+    fn test_parse_price_ids() {
+        let prices = parse_price_ids("personal=price_1, family = price_2,gold=price_3,team=");
+        assert_eq!(
+            prices,
+            vec![
+                (SubscriptionTier::Personal, "price_1".to_string()),
+                (SubscriptionTier::Family, "price_2".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_checkout_completed() {
+        let user_uid = Uuid::new_v4();
+        let body = format!(
+            r#"{{
+                "id": "evt_1",
+                "type": "checkout.session.completed",
+                "data": {{ "object": {{
+                    "client_reference_id": "{}",
+                    "customer": "cus_1",
+                    "subscription": "sub_1",
+                    "metadata": {{ "tier": "family" }}
+                }} }}
+            }}"#,
+            user_uid
+        );
+        let event = parse_event(body.as_bytes()).unwrap().unwrap();
+        assert_eq!(event.id, "evt_1");
+        assert_eq!(
+            event.kind,
+            BillingEventKind::CheckoutCompleted {
+                user_uid,
+                tier: SubscriptionTier::Family,
+                customer_id: "cus_1".to_string(),
+                subscription_id: "sub_1".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_invoice_paid() {
+        let body = br#"{
+            "id": "evt_2",
+            "type": "invoice.paid",
+            "data": { "object": {
+                "subscription": "sub_1",
+                "lines": { "data": [ { "period": { "start": 1700000000, "end": 1702592000 } } ] }
+            } }
+        }"#;
+        let event = parse_event(body).unwrap().unwrap();
+        assert_eq!(
+            event.kind,
+            BillingEventKind::InvoicePaid {
+                subscription_id: "sub_1".to_string(),
+                period_start: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+                period_end: DateTime::from_timestamp(1_702_592_000, 0).unwrap(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_subscription_events() {
+        let body = br#"{
+            "id": "evt_3",
+            "type": "customer.subscription.updated",
+            "data": { "object": { "id": "sub_1", "cancel_at_period_end": true } }
+        }"#;
+        assert_eq!(
+            parse_event(body).unwrap().unwrap().kind,
+            BillingEventKind::CancellationChanged {
+                subscription_id: "sub_1".to_string(),
+                cancel_at_period_end: true,
+            }
+        );
+
+        let body = br#"{
+            "id": "evt_4",
+            "type": "customer.subscription.deleted",
+            "data": { "object": { "id": "sub_1" } }
+        }"#;
+        assert_eq!(
+            parse_event(body).unwrap().unwrap().kind,
+            BillingEventKind::SubscriptionEnded {
+                subscription_id: "sub_1".to_string(),
+            }
+        );
+
+        let body = br#"{ "id": "evt_5", "type": "customer.created", "data": { "object": {} } }"#;
+        assert!(parse_event(body).unwrap().is_none());
+        assert!(parse_event(b"not json").is_err());
+    }
+}
@@ -0,0 +1,65 @@
+use anyhow::{Context, Result, bail};
+use chrono::Utc;
+use expense_tracker::{backup, db};
+
+const USAGE: &str = "Usage:
+  backup dump [FILE]               Write an archive of the database, named after the time by default
+  backup restore FILE [--replace]  Load an archive into a migrated database, --replace empties it first";
+
+/*
+ Backs up or restores the database of DATABASE_URL, e.g. to move a deployment:
+ `cargo run --bin backup -- dump` on the old server, then run the migrations and
+ `cargo run --bin backup -- restore <file>` on the new one.
+*/
+#[tokio::main]
+async fn main() -> Result<()> {
+    dotenv::dotenv().ok();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let db_url = std::env::var("DATABASE_URL").context("DATABASE_URL is not set")?;
+
+    match args.as_slice() {
+        ["dump"] | ["dump", _] => {
+            let pool = db::make_db_pool(&db_url).await?;
+            let archive = backup::dump(&pool).await?;
+            let path = match args.get(1) {
+                Some(path) => path.to_string(),
+                None => backup::file_name(archive.created_at),
+            };
+            std::fs::write(&path, archive.encode()?)
+                .with_context(|| format!("Failed to write {}", path))?;
+            println!(
+                "Wrote {} rows of {} tables to {}",
+                archive.row_count(),
+                archive.tables.len(),
+                path
+            );
+        }
+        ["restore", path] | ["restore", path, "--replace"] => {
+            let replace = args.len() == 3;
+            let bytes = std::fs::read(path).with_context(|| format!("Failed to read {}", path))?;
+            let archive = backup::Archive::decode(&bytes)?;
+            println!(
+                "Restoring {} rows of {} tables, backed up {} ago",
+                archive.row_count(),
+                archive.tables.len(),
+                format_age(Utc::now() - archive.created_at)
+            );
+            let pool = db::make_db_pool(&db_url).await?;
+            let summary = backup::restore(&pool, &archive, replace).await?;
+            println!(
+                "Restored {} rows of {} tables",
+                summary.rows, summary.tables
+            );
+        }
+        _ => bail!("{}", USAGE),
+    }
+    Ok(())
+}
+
+fn format_age(age: chrono::Duration) -> String {
+    match age.num_hours() {
+        hours if hours < 48 => format!("{} hours", hours.max(0)),
+        hours => format!("{} days", hours / 24),
+    }
+}
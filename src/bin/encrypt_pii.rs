@@ -0,0 +1,106 @@
+use anyhow::{Context, Result, bail};
+use expense_tracker::{
+    config::pii_encryption_key_from_env,
+    db,
+    utils::pii::{self, PiiCipher},
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+const USAGE: &str = "Usage:
+  encrypt_pii            Encrypt the emails and chat ids still stored in plaintext
+  encrypt_pii --decrypt  Turn them back into plaintext, e.g. before retiring the key";
+
+// Rows rewritten per transaction
+const BATCH_SIZE: i64 = 500;
+
+// (table, primary key, column, lookup hash column) of the encrypted columns, see `utils::pii`.
+// NULL values, like invites without an email, never match the LIKE and are left alone
+const COLUMNS: &[(&str, &str, &str, &str)] = &[
+    ("users", "uid", "email", "email_hash"),
+    ("chat_member_links", "uid", "p_user_id", "p_user_id_hash"),
+    ("chat_bindings", "id", "p_uid", "p_uid_hash"),
+    ("chat_bind_requests", "id", "p_uid", "p_uid_hash"),
+    ("group_invites", "uid", "email", "email_hash"),
+];
+
+/*
+ Backfill for PII_ENCRYPTION_KEY: once the server runs with the key, new values are written
+ encrypted, `cargo run --bin encrypt_pii` then encrypts the rows written before and fills in
+ their lookup hashes. Rows already done are skipped, so an interrupted run can be started again.
+*/
+#[tokio::main]
+async fn main() -> Result<()> {
+    dotenv::dotenv().ok();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let decrypt = match args.as_slice() {
+        [] => false,
+        ["--decrypt"] => true,
+        _ => bail!("{}", USAGE),
+    };
+    let db_url = std::env::var("DATABASE_URL").context("DATABASE_URL is not set")?;
+    let key = pii_encryption_key_from_env()?.context("PII_ENCRYPTION_KEY is not set")?;
+    pii::install(PiiCipher::new(&key));
+
+    let pool = db::make_db_pool(&db_url).await?;
+    for (table, key, column, hash_column) in COLUMNS {
+        let rows = rewrite(&pool, table, key, column, hash_column, decrypt).await?;
+        println!(
+            "{} {} rows of {}.{}",
+            if decrypt { "Decrypted" } else { "Encrypted" },
+            rows,
+            table,
+            column
+        );
+    }
+    Ok(())
+}
+
+// A batch per transaction, the rows rewritten no longer match and the next batch moves on
+async fn rewrite(
+    pool: &PgPool,
+    table: &str,
+    key: &str,
+    column: &str,
+    hash_column: &str,
+    decrypt: bool,
+) -> Result<u64> {
+    let pending = if decrypt { "LIKE" } else { "NOT LIKE" };
+    let select = format!(
+        "SELECT {key}, {column} FROM {table} WHERE {column} {pending} $1 LIMIT $2 FOR UPDATE"
+    );
+    let update = format!("UPDATE {table} SET {column} = $2, {hash_column} = $3 WHERE {key} = $1");
+    let mut total = 0;
+    loop {
+        let mut tx = pool.begin().await?;
+        let rows = sqlx::query_as::<_, (Uuid, String)>(&select)
+            .bind(format!("{}%", pii::PREFIX))
+            .bind(BATCH_SIZE)
+            .fetch_all(tx.as_mut())
+            .await
+            .with_context(|| format!("Failed to read {}.{}", table, column))?;
+        if rows.is_empty() {
+            return Ok(total);
+        }
+        for (uid, value) in &rows {
+            let (value, hash) = if decrypt {
+                let value = pii::decrypt(value).with_context(|| {
+                    format!("Failed to decrypt {}.{} of {}", table, column, uid)
+                })?;
+                (value, None)
+            } else {
+                (pii::encrypt(value), pii::lookup_hash(value))
+            };
+            sqlx::query(&update)
+                .bind(uid)
+                .bind(value)
+                .bind(hash)
+                .execute(tx.as_mut())
+                .await
+                .with_context(|| format!("Failed to rewrite {}.{} of {}", table, column, uid))?;
+        }
+        tx.commit().await?;
+        total += rows.len() as u64;
+    }
+}
@@ -0,0 +1,9 @@
+use anyhow::Result;
+use expense_tracker::openapi;
+
+// Prints the OpenAPI doc served at `/api-doc/openapi.json`, to regenerate API clients without
+// running the server: `cargo run --bin openapi > openapi.json`
+fn main() -> Result<()> {
+    println!("{}", openapi::build().to_pretty_json()?);
+    Ok(())
+}
@@ -0,0 +1,316 @@
+/*
+ Typed HTTP client for the REST API, compiled with the `client` feature. Requests and
+ responses are the server's own route and repo types, so a payload change breaks callers
+ at compile time instead of at runtime.
+
+ let mut client = ApiClient::new("http://localhost:3000");
+ let login = client.login(&LoginUserPayload { .. }).await?;
+ client.set_token(Some(login.tokens.token));
+ let groups = client.list_groups().await?;
+*/
+use reqwest::{Method, RequestBuilder, StatusCode};
+use serde::de::DeserializeOwned;
+use uuid::Uuid;
+
+use crate::{
+    error::ErrorBody,
+    repos::{
+        category::Category, expense_entry::ExpenseEntry, expense_group::ExpenseGroup,
+        product::Product, tag::Tag, user::UserRead,
+    },
+    routes::{
+        categories::{
+            CreateCategoryPayload, DeleteCategoryQuery, DeleteCategoryResponse,
+            UpdateCategoryPayload,
+        },
+        expense_entry::{
+            CreateExpenseEntryPayload, ListExpenseEntriesQuery, UpdateExpenseEntryPayload,
+        },
+        expense_groups::CreateExpenseGroupPayload,
+        group_settings::{GroupSettingsResponse, UpdateGroupSettingsPayload},
+        products::SuggestProductsQuery,
+        stats::{GroupStats, StatsQuery},
+        tags::TagPayload,
+        users::{
+            CreateUserPayload, LoginResponse, LoginUserPayload, RefreshTokenPayload, SessionTokens,
+        },
+    },
+    types::{DeleteResponse, PaginatedResponse},
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    #[error("request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    // An error response of the API, see `ErrorCode`
+    #[error("{status}: {}", .body.message)]
+    Api { status: StatusCode, body: ErrorBody },
+    // An error response without an `ErrorBody`, e.g. from a proxy in front of the API
+    #[error("{0}")]
+    Status(StatusCode),
+}
+
+#[derive(Debug, Clone)]
+pub struct ApiClient {
+    http: reqwest::Client,
+    base_url: String,
+    token: Option<String>,
+}
+
+impl ApiClient {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            token: None,
+        }
+    }
+
+    // Access token sent as `Authorization: Bearer`, e.g. from `login` or `refresh`
+    pub fn set_token(&mut self, token: Option<String>) {
+        self.token = token;
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let builder = self
+            .http
+            .request(method, format!("{}{}", self.base_url, path));
+        match &self.token {
+            Some(token) => builder.bearer_auth(token),
+            None => builder,
+        }
+    }
+
+    async fn send<T: DeserializeOwned>(builder: RequestBuilder) -> Result<T, ClientError> {
+        let response = Self::check(builder.send().await?).await?;
+        Ok(response.json().await?)
+    }
+
+    // For endpoints answering with an empty body
+    async fn send_empty(builder: RequestBuilder) -> Result<(), ClientError> {
+        Self::check(builder.send().await?).await?;
+        Ok(())
+    }
+
+    async fn check(response: reqwest::Response) -> Result<reqwest::Response, ClientError> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        match response.json::<ErrorBody>().await {
+            Ok(body) => Err(ClientError::Api { status, body }),
+            Err(_) => Err(ClientError::Status(status)),
+        }
+    }
+
+    // Auth
+
+    pub async fn register(&self, payload: &CreateUserPayload) -> Result<UserRead, ClientError> {
+        Self::send(self.request(Method::POST, "/auth/register").json(payload)).await
+    }
+
+    pub async fn login(&self, payload: &LoginUserPayload) -> Result<LoginResponse, ClientError> {
+        Self::send(self.request(Method::POST, "/auth/login").json(payload)).await
+    }
+
+    pub async fn refresh(&self, refresh_token: &str) -> Result<SessionTokens, ClientError> {
+        let payload = RefreshTokenPayload {
+            refresh_token: refresh_token.to_string(),
+        };
+        Self::send(self.request(Method::POST, "/auth/refresh").json(&payload)).await
+    }
+
+    pub async fn logout(&self, refresh_token: &str) -> Result<(), ClientError> {
+        let payload = RefreshTokenPayload {
+            refresh_token: refresh_token.to_string(),
+        };
+        Self::send_empty(self.request(Method::POST, "/auth/logout").json(&payload)).await
+    }
+
+    pub async fn me(&self) -> Result<UserRead, ClientError> {
+        Self::send(self.request(Method::GET, "/users/me")).await
+    }
+
+    // Expense groups
+
+    pub async fn list_groups(&self) -> Result<Vec<ExpenseGroup>, ClientError> {
+        Self::send(self.request(Method::GET, "/expense-groups")).await
+    }
+
+    pub async fn get_group(&self, uid: Uuid) -> Result<ExpenseGroup, ClientError> {
+        Self::send(self.request(Method::GET, &format!("/expense-groups/{uid}"))).await
+    }
+
+    pub async fn create_group(
+        &self,
+        payload: &CreateExpenseGroupPayload,
+    ) -> Result<ExpenseGroup, ClientError> {
+        Self::send(self.request(Method::POST, "/expense-groups").json(payload)).await
+    }
+
+    pub async fn delete_group(&self, uid: Uuid) -> Result<DeleteResponse, ClientError> {
+        Self::send(self.request(Method::DELETE, &format!("/expense-groups/{uid}"))).await
+    }
+
+    pub async fn archive_group(&self, uid: Uuid) -> Result<ExpenseGroup, ClientError> {
+        Self::send(self.request(Method::POST, &format!("/expense-groups/{uid}/archive"))).await
+    }
+
+    pub async fn unarchive_group(&self, uid: Uuid) -> Result<ExpenseGroup, ClientError> {
+        Self::send(self.request(Method::POST, &format!("/expense-groups/{uid}/unarchive"))).await
+    }
+
+    pub async fn group_settings(&self, uid: Uuid) -> Result<GroupSettingsResponse, ClientError> {
+        Self::send(self.request(Method::GET, &format!("/expense-groups/{uid}/settings"))).await
+    }
+
+    pub async fn update_group_settings(
+        &self,
+        uid: Uuid,
+        payload: &UpdateGroupSettingsPayload,
+    ) -> Result<GroupSettingsResponse, ClientError> {
+        Self::send(
+            self.request(Method::PUT, &format!("/expense-groups/{uid}/settings"))
+                .json(payload),
+        )
+        .await
+    }
+
+    // Categories
+
+    pub async fn list_categories(&self, group_uid: Uuid) -> Result<Vec<Category>, ClientError> {
+        Self::send(self.request(Method::GET, &format!("/groups/{group_uid}/categories"))).await
+    }
+
+    pub async fn get_category(&self, uid: Uuid) -> Result<Category, ClientError> {
+        Self::send(self.request(Method::GET, &format!("/categories/{uid}"))).await
+    }
+
+    pub async fn create_category(
+        &self,
+        payload: &CreateCategoryPayload,
+    ) -> Result<Category, ClientError> {
+        Self::send(self.request(Method::POST, "/categories").json(payload)).await
+    }
+
+    pub async fn update_category(
+        &self,
+        uid: Uuid,
+        payload: &UpdateCategoryPayload,
+    ) -> Result<Category, ClientError> {
+        Self::send(
+            self.request(Method::PUT, &format!("/categories/{uid}"))
+                .json(payload),
+        )
+        .await
+    }
+
+    pub async fn delete_category(
+        &self,
+        uid: Uuid,
+        query: &DeleteCategoryQuery,
+    ) -> Result<DeleteCategoryResponse, ClientError> {
+        Self::send(
+            self.request(Method::DELETE, &format!("/categories/{uid}"))
+                .query(query),
+        )
+        .await
+    }
+
+    // Expense entries
+
+    pub async fn list_expense_entries(
+        &self,
+        group_uid: Uuid,
+        query: &ListExpenseEntriesQuery,
+    ) -> Result<PaginatedResponse<ExpenseEntry>, ClientError> {
+        Self::send(
+            self.request(Method::GET, &format!("/groups/{group_uid}/expense-entries"))
+                .query(query),
+        )
+        .await
+    }
+
+    pub async fn get_expense_entry(&self, uid: Uuid) -> Result<ExpenseEntry, ClientError> {
+        Self::send(self.request(Method::GET, &format!("/expense-entries/{uid}"))).await
+    }
+
+    // Warnings the server adds to the created entry (tier or budget limits) are dropped
+    pub async fn create_expense_entry(
+        &self,
+        payload: &CreateExpenseEntryPayload,
+    ) -> Result<ExpenseEntry, ClientError> {
+        Self::send(self.request(Method::POST, "/expense-entries").json(payload)).await
+    }
+
+    pub async fn update_expense_entry(
+        &self,
+        uid: Uuid,
+        payload: &UpdateExpenseEntryPayload,
+    ) -> Result<ExpenseEntry, ClientError> {
+        Self::send(
+            self.request(Method::PUT, &format!("/expense-entries/{uid}"))
+                .json(payload),
+        )
+        .await
+    }
+
+    pub async fn delete_expense_entry(&self, uid: Uuid) -> Result<(), ClientError> {
+        Self::send_empty(self.request(Method::DELETE, &format!("/expense-entries/{uid}"))).await
+    }
+
+    pub async fn group_stats(
+        &self,
+        group_uid: Uuid,
+        query: &StatsQuery,
+    ) -> Result<GroupStats, ClientError> {
+        Self::send(
+            self.request(Method::GET, &format!("/groups/{group_uid}/stats"))
+                .query(query),
+        )
+        .await
+    }
+
+    // Tags and products
+
+    pub async fn list_tags(&self, group_uid: Uuid) -> Result<Vec<Tag>, ClientError> {
+        Self::send(self.request(Method::GET, &format!("/groups/{group_uid}/tags"))).await
+    }
+
+    pub async fn create_tag(&self, group_uid: Uuid, name: &str) -> Result<Tag, ClientError> {
+        let payload = TagPayload {
+            name: name.to_string(),
+        };
+        Self::send(
+            self.request(Method::POST, &format!("/groups/{group_uid}/tags"))
+                .json(&payload),
+        )
+        .await
+    }
+
+    pub async fn suggest_products(
+        &self,
+        group_uid: Uuid,
+        query: &SuggestProductsQuery,
+    ) -> Result<Vec<Product>, ClientError> {
+        Self::send(
+            self.request(
+                Method::GET,
+                &format!("/groups/{group_uid}/products/suggest"),
+            )
+            .query(query),
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base_url_trailing_slash() {
+        let client = ApiClient::new("http://localhost:3000/");
+        assert_eq!(client.base_url, "http://localhost:3000");
+    }
+}
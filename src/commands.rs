@@ -1,10 +1,28 @@
 pub mod base;
 pub mod budget;
 pub mod budget_edit;
+pub mod callback;
+pub mod cancel;
 pub mod category;
+pub mod category_delete;
 pub mod category_edit;
+pub mod confirm;
+pub mod debt;
+pub mod dispatcher;
 pub mod expense;
+pub mod expense_delete;
 pub mod expense_edit;
+pub mod feed;
 pub mod help;
+pub mod income;
+pub mod lang;
+pub mod link_me;
+pub mod logout;
+pub mod recurring;
 pub mod history;
 pub mod report;
+pub mod search;
+pub mod settle;
+pub mod switch;
+pub mod trip;
+pub mod undo;
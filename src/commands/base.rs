@@ -1,3 +1,8 @@
+use uuid::Uuid;
+
+use crate::commands::callback::CallbackAction;
+use crate::repos::chat_binding::ChatBinding;
+
 #[async_trait::async_trait(?Send)]
 pub trait Command {
     fn get_command() -> &'static str;
@@ -5,4 +10,130 @@ pub trait Command {
     fn get_instruction_text_key() -> &'static str {
         "MESSENGER__INSTRUCTION_UNKNOWN_COMMAND"
     }
+
+    // Format help appended to the error message when the command fails
+    fn get_help_text_key() -> Option<&'static str> {
+        None
+    }
+}
+
+// The platform user who sent a chat message
+#[derive(Debug, Clone)]
+pub struct ChatSender {
+    pub p_user_id: String,
+    pub name: String,
+    // Account linked with /link-me, set by the dispatcher when it is a member of the group
+    pub linked_user_uid: Option<Uuid>,
+}
+
+impl ChatSender {
+    /*
+     In a private chat the sender is the user who bound it. Senders in group chats are
+     known by the account they linked with /link-me, or only by name without one.
+    */
+    pub fn resolve_user_uid(&self, binding: &ChatBinding) -> Option<Uuid> {
+        if self.p_user_id == binding.p_uid {
+            return Some(binding.bound_by);
+        }
+        self.linked_user_uid
+    }
+
+    // Only the user who bound the chat may change its bindings or edit and delete group data
+    pub fn is_binder(&self, binding: &ChatBinding) -> bool {
+        self.resolve_user_uid(binding) == Some(binding.bound_by)
+    }
+}
+
+// A button under a reply, pressing it sends the encoded action back to the dispatcher
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatButton {
+    pub label: String,
+    pub data: String,
+}
+
+impl ChatButton {
+    pub fn new(label: impl Into<String>, action: &CallbackAction) -> Self {
+        Self {
+            label: label.into(),
+            data: action.encode(),
+        }
+    }
+}
+
+// Text sent back to the chat, messengers without buttons only send the text
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChatReply {
+    pub text: String,
+    // Rows of buttons
+    pub buttons: Vec<Vec<ChatButton>>,
+}
+
+impl ChatReply {
+    pub fn with_buttons(mut self, buttons: Vec<Vec<ChatButton>>) -> Self {
+        self.buttons = buttons;
+        self
+    }
+}
+
+impl From<String> for ChatReply {
+    fn from(text: String) -> Self {
+        Self {
+            text,
+            buttons: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn group_chat_binding() -> ChatBinding {
+        ChatBinding {
+            id: Uuid::new_v4(),
+            group_uid: Uuid::new_v4(),
+            platform: "telegram".to_string(),
+            p_uid: "-100123".to_string(),
+            status: "active".to_string(),
+            bound_by: Uuid::new_v4(),
+            bound_at: Utc::now(),
+            revoked_at: None,
+            selected: true,
+        }
+    }
+
+    fn sender(p_user_id: &str, linked_user_uid: Option<Uuid>) -> ChatSender {
+        ChatSender {
+            p_user_id: p_user_id.to_string(),
+            name: "Budi".to_string(),
+            linked_user_uid,
+        }
+    }
+
+    #[test]
+    fn test_resolve_user_uid_private_chat() {
+        let binding = ChatBinding {
+            p_uid: "123".to_string(),
+            ..group_chat_binding()
+        };
+        assert_eq!(
+            sender("123", None).resolve_user_uid(&binding),
+            Some(binding.bound_by)
+        );
+        assert!(sender("123", None).is_binder(&binding));
+    }
+
+    #[test]
+    fn test_resolve_user_uid_group_chat() {
+        let binding = group_chat_binding();
+        let member = Uuid::new_v4();
+        assert_eq!(sender("456", None).resolve_user_uid(&binding), None);
+        assert_eq!(
+            sender("456", Some(member)).resolve_user_uid(&binding),
+            Some(member)
+        );
+        assert!(!sender("456", Some(member)).is_binder(&binding));
+        assert!(sender("456", Some(binding.bound_by)).is_binder(&binding));
+    }
 }
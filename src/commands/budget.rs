@@ -1,21 +1,40 @@
 use std::collections::HashMap;
 
 use anyhow::Result;
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
 
 use crate::{
-    commands::base::Command,
+    commands::base::{ChatSender, Command},
     lang::Lang,
     repos::{
-        budget::{BudgetRepo, CreateBudgetDbPayload, UpdateBudgetDbPayload},
+        audit_log::{AuditActor, AuditChange, AuditEntity, AuditRepo},
+        budget::{
+            Budget, BudgetPeriodType, BudgetRepo, CreateBudgetDbPayload, UpdateBudgetDbPayload,
+        },
         category::CategoryRepo,
         chat_binding::ChatBinding,
     },
 };
 
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
 #[derive(Debug)]
 pub struct BudgetCommandEntry {
     pub category: String,
-    pub amount: f64,
+    pub amount: Decimal,
+    pub period_type: BudgetPeriodType,
+    // Start and exclusive end of a custom budget
+    pub range: Option<(NaiveDate, NaiveDate)>,
+}
+
+impl BudgetCommandEntry {
+    // The recurring budget (or custom range) of the category this entry sets the amount of
+    fn matches(&self, budget: &Budget) -> bool {
+        budget.period_type() == self.period_type
+            && budget.period_month.is_none()
+            && self.range == budget.range_start.zip(budget.range_end)
+    }
 }
 
 #[derive(Debug)]
@@ -37,14 +56,19 @@ impl BudgetCommand {
          or
         2. create new budget
         /budget
-        [category name]=[amount]
-        [category name]=[amount]
+        [category name]=[amount] [optional period]
+        [category name]=[amount] [optional period]
         ...
 
+        The period is monthly by default, or mingguan/weekly, kuartalan/quarterly,
+        tahunan/yearly, or a custom range as [start date] [end date] (end exclusive)
+
         Example:
         /budget
         Makanan=50000
         Transportasi=30000
+        Asuransi=2400000 tahunan
+        Liburan=5000000 2025-12-01 2026-01-01
 
         or
         /budget Makanan=50000
@@ -76,9 +100,9 @@ impl BudgetCommand {
                 continue;
             }
 
-            // Parse format: "CategoryName=amount"
+            // Parse format: "CategoryName=amount [period]"
             let parts: Vec<&str> = line.split("=").map(|s| s.trim()).collect();
-            if parts.len() != 2 {
+            if parts.len() != 2 || parts[1].is_empty() {
                 return Err(anyhow::anyhow!(
                     "Invalid format: {}. Expected 'CategoryName=amount'",
                     line
@@ -90,12 +114,19 @@ impl BudgetCommand {
                 return Err(anyhow::anyhow!("Category name cannot be empty"));
             }
 
-            let amount_str = parts[1];
-            let amount: f64 = amount_str.parse().map_err(|_| {
+            let mut words = parts[1].split_whitespace();
+            let amount_str = words.next().unwrap_or_default();
+            let amount: Decimal = amount_str.parse().map_err(|_| {
                 anyhow::anyhow!("Invalid amount: {}. Must be a number", amount_str)
             })?;
+            let (period_type, range) = Self::parse_period(&words.collect::<Vec<_>>())?;
 
-            entries.push(BudgetCommandEntry { category, amount });
+            entries.push(BudgetCommandEntry {
+                category,
+                amount,
+                period_type,
+                range,
+            });
         }
 
         if entries.is_empty() {
@@ -107,6 +138,66 @@ impl BudgetCommand {
         })
     }
 
+    // Nothing for monthly, a period name, or the two dates of a custom range
+    fn parse_period(words: &[&str]) -> Result<(BudgetPeriodType, Option<(NaiveDate, NaiveDate)>)> {
+        match words {
+            [] => Ok((BudgetPeriodType::Monthly, None)),
+            [period] => match BudgetPeriodType::parse(period) {
+                Some(BudgetPeriodType::Custom) | None => {
+                    Err(anyhow::anyhow!("Invalid budget period: {}", period))
+                }
+                Some(period_type) => Ok((period_type, None)),
+            },
+            [start, end] => {
+                let parse_date = |date: &str| {
+                    NaiveDate::parse_from_str(date, DATE_FORMAT)
+                        .map_err(|_| anyhow::anyhow!("Invalid date format: {}", date))
+                };
+                let (start, end) = (parse_date(start)?, parse_date(end)?);
+                if end <= start {
+                    return Err(anyhow::anyhow!(
+                        "The budget range must end after {}",
+                        start.format(DATE_FORMAT)
+                    ));
+                }
+                Ok((BudgetPeriodType::Custom, Some((start, end))))
+            }
+            _ => Err(anyhow::anyhow!(
+                "Invalid budget period: {}",
+                words.join(" ")
+            )),
+        }
+    }
+
+    // Short label of the period a budget covers, e.g. "tahunan" or "2025-12-01 - 2026-01-01"
+    fn period_text(budget: &Budget, lang: &Lang) -> String {
+        match budget.period_type() {
+            BudgetPeriodType::Weekly => lang.get("MESSENGER__BUDGET_PERIOD_WEEKLY"),
+            BudgetPeriodType::Monthly => lang.get("MESSENGER__BUDGET_PERIOD_MONTHLY"),
+            BudgetPeriodType::Quarterly => lang.get("MESSENGER__BUDGET_PERIOD_QUARTERLY"),
+            BudgetPeriodType::Yearly => lang.get("MESSENGER__BUDGET_PERIOD_YEARLY"),
+            BudgetPeriodType::Custom => lang.get_with_vars(
+                "MESSENGER__BUDGET_PERIOD_CUSTOM",
+                HashMap::from([
+                    (
+                        "start_date".to_string(),
+                        budget
+                            .range_start
+                            .map(|date| date.format(DATE_FORMAT).to_string())
+                            .unwrap_or_default(),
+                    ),
+                    (
+                        "end_date".to_string(),
+                        budget
+                            .range_end
+                            .map(|date| date.format(DATE_FORMAT).to_string())
+                            .unwrap_or_default(),
+                    ),
+                ]),
+            ),
+        }
+    }
+
     /*
         Output format:
 
@@ -114,8 +205,8 @@ impl BudgetCommand {
         1. get list response:
 
         Budget:
-        1. [category name]: [amount]
-        2. [category name]: [amount]
+        1. [category name]: [amount] ([period])
+        2. [category name]: [amount] ([period])
         3. ...
 
         Total: X budgets
@@ -123,8 +214,8 @@ impl BudgetCommand {
         Example:
 
         Budget:
-        1. Makanan: 50000
-        2. Transportasi: 30000
+        1. Makanan: 50000 (bulanan)
+        2. Asuransi: 2400000 (tahunan)
         Total: 2 budgets
 
         Untuk menambah budget, gunakan perintah
@@ -133,12 +224,13 @@ impl BudgetCommand {
         /budget Makanan=50000
 
         2. create new budget response:
-        Budget untuk [category name] sebesar [amount] berhasil ditambahkan.
+        Budget untuk [category name] (bulanan) sebesar [amount] berhasil ditambahkan.
     */
 
     pub async fn run(
         raw_message: &str,
         binding: &ChatBinding,
+        sender: &ChatSender,
         tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
         lang: &Lang,
     ) -> Result<String> {
@@ -147,7 +239,8 @@ impl BudgetCommand {
         match &command.action {
             BudgetAction::List => Self::get_list(binding, tx, lang).await,
             BudgetAction::Create(entries) => {
-                Self::create_budgets(entries, binding, tx, lang).await
+                let actor = AuditActor::from_chat(binding, sender);
+                Self::create_budgets(entries, binding, &actor, tx, lang).await
             }
         }
     }
@@ -183,10 +276,11 @@ impl BudgetCommand {
                 .unwrap_or_else(|| "Unknown".to_string());
 
             response.push_str(&format!(
-                "{}. {}: {}\n",
+                "{}. {}: {} ({})\n",
                 index + 1,
                 category_name,
-                budget.amount
+                budget.amount.normalize(),
+                Self::period_text(budget, lang)
             ));
         }
 
@@ -199,6 +293,7 @@ impl BudgetCommand {
     async fn create_budgets(
         entries: &[BudgetCommandEntry],
         binding: &ChatBinding,
+        actor: &AuditActor,
         tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
         lang: &Lang,
     ) -> Result<String> {
@@ -209,44 +304,77 @@ impl BudgetCommand {
             let category = CategoryRepo::find_by_name_or_alias(tx, binding.group_uid, &entry.category).await?
                 .ok_or_else(|| anyhow::anyhow!("Category '{}' not found", entry.category))?;
 
-            // Check if budget exists
-            let existing_budget = BudgetRepo::get_by_group_and_category(tx, binding.group_uid, category.uid).await?;
+            // Check if the category already has a budget over this period
+            let existing_budget = BudgetRepo::list_by_group(tx, binding.group_uid)
+                .await?
+                .into_iter()
+                .find(|budget| budget.category_uid == category.uid && entry.matches(budget));
 
             let result = if let Some(budget) = existing_budget {
                 // Update existing budget
-                BudgetRepo::update(
+                let updated = BudgetRepo::update(
                     tx,
                     budget.uid,
                     UpdateBudgetDbPayload {
                         amount: Some(entry.amount),
+                        currency: None,
+                        period_type: None,
                         period_year: None,
                         period_month: None,
+                        range_start: None,
+                        range_end: None,
+                        hard_limit: None,
                     },
                 ).await?;
+                AuditRepo::record(
+                    tx,
+                    actor,
+                    AuditEntity::Budget,
+                    binding.group_uid,
+                    updated.uid,
+                    AuditChange::update(&budget, &updated),
+                )
+                .await?;
                 lang.get_with_vars(
                     "MESSENGER__BUDGET_UPDATED",
                     HashMap::from([
                         ("category".to_string(), category.name.clone()),
                         ("amount".to_string(), entry.amount.to_string()),
+                        ("period".to_string(), Self::period_text(&updated, lang)),
                     ]),
                 )
             } else {
                 // Create new budget
-                BudgetRepo::create(
+                let created = BudgetRepo::create(
                     tx,
                     CreateBudgetDbPayload {
                         group_uid: binding.group_uid,
                         category_uid: category.uid,
                         amount: entry.amount,
+                        currency: None,
+                        period_type: entry.period_type,
                         period_year: None,
                         period_month: None,
+                        range_start: entry.range.map(|(start, _)| start),
+                        range_end: entry.range.map(|(_, end)| end),
+                        hard_limit: false,
                     },
                 ).await?;
+                AuditRepo::record(
+                    tx,
+                    actor,
+                    AuditEntity::Budget,
+                    binding.group_uid,
+                    created.uid,
+                    AuditChange::create(&created),
+                )
+                .await?;
                 lang.get_with_vars(
                     "MESSENGER__BUDGET_CREATED",
                     HashMap::from([
                         ("category".to_string(), category.name.clone()),
                         ("amount".to_string(), entry.amount.to_string()),
+                        ("period".to_string(), Self::period_text(&created, lang)),
                     ]),
                 )
             };
@@ -266,11 +394,16 @@ impl Command for BudgetCommand {
     fn get_instruction_text_key() -> &'static str {
         "MESSENGER__BUDGET_SHORT_INSTRUCTION"
     }
+
+    fn get_help_text_key() -> Option<&'static str> {
+        Some("MESSENGER__BUDGET_HELP")
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rust_decimal_macros::dec;
 
     #[test]
     fn test_parse_command_list() {
@@ -290,7 +423,7 @@ mod tests {
             BudgetAction::Create(entries) => {
                 assert_eq!(entries.len(), 1);
                 assert_eq!(entries[0].category, "Makanan");
-                assert_eq!(entries[0].amount, 50000.0);
+                assert_eq!(entries[0].amount, dec!(50000));
             }
             _ => panic!("Expected Create action"),
         }
@@ -304,14 +437,47 @@ mod tests {
             BudgetAction::Create(entries) => {
                 assert_eq!(entries.len(), 2);
                 assert_eq!(entries[0].category, "Makanan");
-                assert_eq!(entries[0].amount, 50000.0);
+                assert_eq!(entries[0].amount, dec!(50000));
                 assert_eq!(entries[1].category, "Transportasi");
-                assert_eq!(entries[1].amount, 30000.0);
+                assert_eq!(entries[1].amount, dec!(30000));
             }
             _ => panic!("Expected Create action"),
         }
     }
 
+    #[test]
+    fn test_parse_command_create_with_period() {
+        let input = "/budget\nAsuransi=2400000 tahunan\nListrik = 300000 Quarterly\nLiburan=5000000 2025-12-01 2026-01-01";
+        let command = BudgetCommand::parse_command(input).unwrap();
+        match &command.action {
+            BudgetAction::Create(entries) => {
+                assert_eq!(entries.len(), 3);
+                assert_eq!(entries[0].amount, dec!(2400000));
+                assert_eq!(entries[0].period_type, BudgetPeriodType::Yearly);
+                assert_eq!(entries[0].range, None);
+                assert_eq!(entries[1].category, "Listrik");
+                assert_eq!(entries[1].period_type, BudgetPeriodType::Quarterly);
+                assert_eq!(entries[2].period_type, BudgetPeriodType::Custom);
+                assert_eq!(
+                    entries[2].range,
+                    NaiveDate::from_ymd_opt(2025, 12, 1).zip(NaiveDate::from_ymd_opt(2026, 1, 1))
+                );
+            }
+            _ => panic!("Expected Create action"),
+        }
+    }
+
+    #[test]
+    fn test_parse_command_invalid_period() {
+        assert!(BudgetCommand::parse_command("/budget Makanan=50000 harian").is_err());
+        assert!(BudgetCommand::parse_command("/budget Makanan=50000 custom").is_err());
+        assert!(
+            BudgetCommand::parse_command("/budget Liburan=5000000 2026-01-01 2025-12-01").is_err()
+        );
+        assert!(BudgetCommand::parse_command("/budget Liburan=5000000 2025-12-01").is_err());
+        assert!(BudgetCommand::parse_command("/budget Makanan=").is_err());
+    }
+
     #[test]
     fn test_parse_command_invalid_format() {
         let input = "/budget invalid format";
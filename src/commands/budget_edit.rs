@@ -1,12 +1,14 @@
 use std::collections::HashMap;
 
 use anyhow::Result;
+use rust_decimal::Decimal;
 use uuid::Uuid;
 
 use crate::{
-    commands::base::Command,
+    commands::base::{ChatSender, Command},
     lang::Lang,
     repos::{
+        audit_log::{AuditActor, AuditChange, AuditEntity, AuditRepo},
         budget::{BudgetRepo, UpdateBudgetDbPayload},
         category::CategoryRepo,
         chat_binding::ChatBinding,
@@ -17,7 +19,7 @@ use crate::{
 pub struct BudgetEditCommandEntry {
     pub id: Uuid,
     pub category: String,
-    pub amount: f64,
+    pub amount: Decimal,
 }
 
 #[derive(Debug)]
@@ -76,7 +78,7 @@ impl BudgetEditCommand {
             }
 
             let amount_str = parts[1];
-            let amount: f64 = amount_str.parse().map_err(|_| {
+            let amount: Decimal = amount_str.parse().map_err(|_| {
                 anyhow::anyhow!("Invalid amount: {}. Must be a number", amount_str)
             })?;
 
@@ -107,10 +109,12 @@ impl BudgetEditCommand {
     pub async fn run(
         raw_message: &str,
         binding: &ChatBinding,
+        sender: &ChatSender,
         tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
         lang: &Lang,
     ) -> Result<String> {
         let entries = Self::parse_command(raw_message)?;
+        let actor = AuditActor::from_chat(binding, sender);
 
         let mut response = String::new();
         response.push_str(&lang.get("MESSENGER__BUDGET_EDIT_SUCCESS_HEADER"));
@@ -131,16 +135,30 @@ impl BudgetEditCommand {
             }
 
             // Update the budget amount
-            BudgetRepo::update(
+            let updated = BudgetRepo::update(
                 tx,
                 *id,
                 UpdateBudgetDbPayload {
                     amount: Some(entry.amount),
+                    currency: None,
+                    period_type: None,
                     period_year: None,
                     period_month: None,
+                    range_start: None,
+                    range_end: None,
+                    hard_limit: None,
                 },
             )
             .await?;
+            AuditRepo::record(
+                tx,
+                &actor,
+                AuditEntity::Budget,
+                binding.group_uid,
+                updated.uid,
+                AuditChange::update(&budget, &updated),
+            )
+            .await?;
 
             response.push_str(&lang.get_with_vars(
                 "MESSENGER__BUDGET_EDIT_SUCCESS_ENTRY",
@@ -164,11 +182,16 @@ impl Command for BudgetEditCommand {
     fn get_instruction_text_key() -> &'static str {
         "MESSENGER__BUDGET_EDIT_SHORT_INSTRUCTION"
     }
+
+    fn get_help_text_key() -> Option<&'static str> {
+        Some("MESSENGER__BUDGET_EDIT_HELP")
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rust_decimal_macros::dec;
 
     #[test]
     fn test_parse_command() {
@@ -189,14 +212,14 @@ Transportasi=30000
             "123e4567-e89b-12d3-a456-426614174000"
         );
         assert_eq!(entries[0].category, "Makanan");
-        assert_eq!(entries[0].amount, 50000.0);
+        assert_eq!(entries[0].amount, dec!(50000));
 
         assert_eq!(
             entries[1].id.to_string(),
             "44444444-4444-4444-4444-000000000001"
         );
         assert_eq!(entries[1].category, "Transportasi");
-        assert_eq!(entries[1].amount, 30000.0);
+        assert_eq!(entries[1].amount, dec!(30000));
     }
 
     #[test]
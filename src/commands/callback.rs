@@ -0,0 +1,245 @@
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+use crate::commands::history::HistoryFilter;
+
+// Telegram drops buttons whose callback data is longer than this
+pub const MAX_CALLBACK_DATA_LENGTH: usize = 64;
+
+const DATE_FORMAT: &str = "%Y%m%d";
+
+/*
+ What a button under a chat reply does when pressed. Encoded as short `:` separated strings,
+ UUIDs as unpadded base64 (22 characters) so two of them still fit in the callback data:
+
+ c:[entry]:[category]     set the category of an entry
+ d                        delete the entries listed in the confirmation message
+ x                        cancel the deletion
+ s:[settlement]           confirm a settlement recorded with /settle
+ h:[start]:[end]:[page]   show a page of /history, dates as YYYYMMDD
+ h:[start]:[end]:[page]:[filters]
+                          same for a filtered /history, filters as `,` separated c[category],
+                          m[member], g[min price] and l[max price]
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub enum CallbackAction {
+    SetCategory {
+        entry_uid: Uuid,
+        category_uid: Uuid,
+    },
+    ConfirmDelete,
+    CancelDelete,
+    ConfirmSettlement {
+        settlement_uid: Uuid,
+    },
+    HistoryPage {
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+        filter: HistoryFilter,
+        page: u32,
+    },
+}
+
+impl CallbackAction {
+    pub fn encode(&self) -> String {
+        match self {
+            Self::SetCategory {
+                entry_uid,
+                category_uid,
+            } => format!("c:{}:{}", encode_uid(entry_uid), encode_uid(category_uid)),
+            Self::ConfirmDelete => "d".to_string(),
+            Self::CancelDelete => "x".to_string(),
+            Self::ConfirmSettlement { settlement_uid } => {
+                format!("s:{}", encode_uid(settlement_uid))
+            }
+            Self::HistoryPage {
+                start_date,
+                end_date,
+                filter,
+                page,
+            } => {
+                let mut data = format!(
+                    "h:{}:{}:{}",
+                    start_date.format(DATE_FORMAT),
+                    end_date.format(DATE_FORMAT),
+                    page
+                );
+                if *filter != HistoryFilter::default() {
+                    data.push(':');
+                    data.push_str(&encode_history_filter(filter));
+                }
+                data
+            }
+        }
+    }
+
+    // Returns None for data we did not create, e.g. buttons of an older version
+    pub fn decode(data: &str) -> Option<Self> {
+        match data.split(':').collect::<Vec<_>>().as_slice() {
+            ["c", entry_uid, category_uid] => Some(Self::SetCategory {
+                entry_uid: decode_uid(entry_uid)?,
+                category_uid: decode_uid(category_uid)?,
+            }),
+            ["d"] => Some(Self::ConfirmDelete),
+            ["x"] => Some(Self::CancelDelete),
+            ["s", settlement_uid] => Some(Self::ConfirmSettlement {
+                settlement_uid: decode_uid(settlement_uid)?,
+            }),
+            ["h", start_date, end_date, page] => Some(Self::HistoryPage {
+                start_date: NaiveDate::parse_from_str(start_date, DATE_FORMAT).ok()?,
+                end_date: NaiveDate::parse_from_str(end_date, DATE_FORMAT).ok()?,
+                filter: HistoryFilter::default(),
+                page: page.parse().ok()?,
+            }),
+            ["h", start_date, end_date, page, filter] => Some(Self::HistoryPage {
+                start_date: NaiveDate::parse_from_str(start_date, DATE_FORMAT).ok()?,
+                end_date: NaiveDate::parse_from_str(end_date, DATE_FORMAT).ok()?,
+                filter: decode_history_filter(filter)?,
+                page: page.parse().ok()?,
+            }),
+            _ => None,
+        }
+    }
+}
+
+fn encode_uid(uid: &Uuid) -> String {
+    URL_SAFE_NO_PAD.encode(uid.as_bytes())
+}
+
+fn decode_uid(input: &str) -> Option<Uuid> {
+    let bytes = URL_SAFE_NO_PAD.decode(input).ok()?;
+    Uuid::from_slice(&bytes).ok()
+}
+
+fn encode_history_filter(filter: &HistoryFilter) -> String {
+    let mut parts = Vec::new();
+    if let Some(category_uid) = &filter.category_uid {
+        parts.push(format!("c{}", encode_uid(category_uid)));
+    }
+    if let Some(member) = &filter.member {
+        parts.push(format!("m{member}"));
+    }
+    if let Some(min_price) = filter.min_price {
+        parts.push(format!("g{}", min_price.normalize()));
+    }
+    if let Some(max_price) = filter.max_price {
+        parts.push(format!("l{}", max_price.normalize()));
+    }
+    parts.join(",")
+}
+
+fn decode_history_filter(input: &str) -> Option<HistoryFilter> {
+    let mut filter = HistoryFilter::default();
+    for part in input.split(',') {
+        let (kind, value) = part.split_at_checked(1)?;
+        match kind {
+            "c" => filter.category_uid = Some(decode_uid(value)?),
+            "m" if !value.is_empty() => filter.member = Some(value.to_string()),
+            "g" => filter.min_price = Some(value.parse::<Decimal>().ok()?),
+            "l" => filter.max_price = Some(value.parse::<Decimal>().ok()?),
+            _ => return None,
+        }
+    }
+    Some(filter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode_set_category() {
+        let action = CallbackAction::SetCategory {
+            entry_uid: Uuid::new_v4(),
+            category_uid: Uuid::new_v4(),
+        };
+        let data = action.encode();
+        assert!(data.len() <= MAX_CALLBACK_DATA_LENGTH);
+        assert_eq!(CallbackAction::decode(&data), Some(action));
+    }
+
+    #[test]
+    fn test_encode_decode_confirm_settlement() {
+        let action = CallbackAction::ConfirmSettlement {
+            settlement_uid: Uuid::new_v4(),
+        };
+        let data = action.encode();
+        assert!(data.starts_with("s:"));
+        assert_eq!(CallbackAction::decode(&data), Some(action));
+        assert_eq!(CallbackAction::decode("s:nope"), None);
+    }
+
+    #[test]
+    fn test_encode_decode_history_page() {
+        let action = CallbackAction::HistoryPage {
+            start_date: NaiveDate::from_ymd_opt(2025, 9, 1).unwrap(),
+            end_date: NaiveDate::from_ymd_opt(2025, 9, 3).unwrap(),
+            filter: HistoryFilter::default(),
+            page: 2,
+        };
+        assert_eq!(action.encode(), "h:20250901:20250903:2");
+        assert_eq!(CallbackAction::decode(&action.encode()), Some(action));
+    }
+
+    #[test]
+    fn test_encode_decode_filtered_history_page() {
+        let action = CallbackAction::HistoryPage {
+            start_date: NaiveDate::from_ymd_opt(2025, 9, 1).unwrap(),
+            end_date: NaiveDate::from_ymd_opt(2025, 9, 3).unwrap(),
+            filter: HistoryFilter {
+                category_uid: Some(Uuid::new_v4()),
+                member: Some("budi".to_string()),
+                min_price: Some(Decimal::new(5000000, 2)),
+                max_price: None,
+            },
+            page: 1,
+        };
+        let data = action.encode();
+        assert!(data.len() <= MAX_CALLBACK_DATA_LENGTH);
+        assert!(data.ends_with(",mbudi,g50000"));
+        // Prices compare by value, 50000.00 comes back as 50000
+        assert_eq!(CallbackAction::decode(&data), Some(action));
+    }
+
+    #[test]
+    fn test_encode_oversized_history_page() {
+        // Every filter at once no longer fits, /history then leaves the page buttons out
+        let action = CallbackAction::HistoryPage {
+            start_date: NaiveDate::from_ymd_opt(2025, 9, 1).unwrap(),
+            end_date: NaiveDate::from_ymd_opt(2025, 9, 3).unwrap(),
+            filter: HistoryFilter {
+                category_uid: Some(Uuid::new_v4()),
+                member: Some("budi".to_string()),
+                min_price: Some(Decimal::from(50000)),
+                max_price: Some(Decimal::from(100000)),
+            },
+            page: 1,
+        };
+        let data = action.encode();
+        assert!(data.ends_with(",mbudi,g50000,l100000"));
+        assert!(data.len() > MAX_CALLBACK_DATA_LENGTH);
+    }
+
+    #[test]
+    fn test_decode_delete() {
+        assert_eq!(
+            CallbackAction::decode("d"),
+            Some(CallbackAction::ConfirmDelete)
+        );
+        assert_eq!(
+            CallbackAction::decode("x"),
+            Some(CallbackAction::CancelDelete)
+        );
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        assert_eq!(CallbackAction::decode(""), None);
+        assert_eq!(CallbackAction::decode("c:abc:def"), None);
+        assert_eq!(CallbackAction::decode("h:20250901:20250903"), None);
+        assert_eq!(CallbackAction::decode("h:20250901:20250903:1:z1"), None);
+        assert_eq!(CallbackAction::decode("d:extra"), None);
+    }
+}
@@ -0,0 +1,64 @@
+use anyhow::Result;
+
+use crate::{
+    commands::base::{ChatSender, Command},
+    lang::Lang,
+    repos::{chat_binding::ChatBinding, chat_conversation::ChatConversationRepo},
+};
+
+#[derive(Debug)]
+pub struct CancelCommand;
+
+impl CancelCommand {
+    /*
+        Should be in format:
+        /cancel
+    */
+    fn parse_command(input: &str) -> Result<Self> {
+        let input = input.trim();
+
+        if input != Self::get_command() {
+            return Err(anyhow::anyhow!("Invalid format: expected only /cancel"));
+        }
+
+        Ok(Self {})
+    }
+
+    // Stops the sender's ongoing conversation in this chat, nothing from it is recorded
+    pub async fn run(
+        raw_message: &str,
+        binding: &ChatBinding,
+        sender: &ChatSender,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        lang: &Lang,
+    ) -> Result<String> {
+        let _command = Self::parse_command(raw_message)?;
+
+        if ChatConversationRepo::delete(tx, binding.id, &sender.p_user_id).await? {
+            Ok(lang.get("MESSENGER__CANCEL_DONE"))
+        } else {
+            Ok(lang.get("MESSENGER__CANCEL_NOTHING"))
+        }
+    }
+}
+
+impl Command for CancelCommand {
+    fn get_command() -> &'static str {
+        "/cancel"
+    }
+
+    fn get_instruction_text_key() -> &'static str {
+        "MESSENGER__CANCEL_SHORT_INSTRUCTION"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_command() {
+        assert!(CancelCommand::parse_command("/cancel").is_ok());
+        assert!(CancelCommand::parse_command("/cancel all").is_err());
+    }
+}
@@ -3,11 +3,16 @@ use std::collections::HashMap;
 use anyhow::Result;
 
 use crate::{
-    commands::base::Command,
+    commands::{
+        base::{ChatSender, Command},
+        undo::UndoCommand,
+    },
     lang::Lang,
     repos::{
-        category::{CategoryRepo, CreateCategoryDbPayload},
+        audit_log::{AuditActor, AuditChange, AuditEntity, AuditRepo},
+        category::{Category, CategoryRepo, CreateCategoryDbPayload},
         category_alias::{CategoryAliasRepo, CreateCategoryAliasDbPayload},
+        chat_action::ChatAction,
         chat_binding::ChatBinding,
     },
 };
@@ -16,6 +21,8 @@ use crate::{
 pub struct CategoryCommandEntry {
     pub name: String,
     pub aliases: Vec<String>,
+    // Name of the top-level category to nest under
+    pub parent: Option<String>,
 }
 
 #[derive(Debug)]
@@ -50,6 +57,8 @@ impl CategoryCommand {
         or
         /category Makanan=makan, food
 
+        A subcategory names its parent first:
+        /category Makanan > Kopi=coffee
 
     */
     fn parse_command(input: &str) -> Result<Self> {
@@ -87,7 +96,16 @@ impl CategoryCommand {
                 ));
             }
 
-            let name = parts[0].to_string();
+            let (parent, name) = match parts[0].split_once('>') {
+                Some((parent, name)) => {
+                    let parent = parent.trim();
+                    if parent.is_empty() {
+                        return Err(anyhow::anyhow!("Parent category name cannot be empty"));
+                    }
+                    (Some(parent.to_string()), name.trim().to_string())
+                }
+                None => (None, parts[0].to_string()),
+            };
             if name.is_empty() {
                 return Err(anyhow::anyhow!("Category name cannot be empty"));
             }
@@ -103,7 +121,11 @@ impl CategoryCommand {
                     .collect()
             };
 
-            entries.push(CategoryCommandEntry { name, aliases });
+            entries.push(CategoryCommandEntry {
+                name,
+                aliases,
+                parent,
+            });
         }
 
         if entries.is_empty() {
@@ -122,8 +144,8 @@ impl CategoryCommand {
         1. get list response:
 
         Kategori:
-        1. [category name]: ([alias1, alias2, ...])
-        2. [category name]: ([alias1, alias2, ...])
+        1. [icon] [category name]: ([alias1, alias2, ...])
+        2. [icon] [category name]: ([alias1, alias2, ...])
         3. ...
 
 
@@ -132,10 +154,11 @@ impl CategoryCommand {
         Example:
 
         Kategori:
-        1. Makanan: (makan, food)
-        2. Transportasi: (transport, travel)
-        3. Hiburan: (fun, entertainment)
-        Total: 3 categories
+        1. 🍔 Makanan: (makan, food)
+           ↳ ☕ Kopi (coffee)
+        2. 🚗 Transportasi: (transport, travel)
+        3. 🎬 Hiburan: (fun, entertainment)
+        Total: 4 categories
 
         Untuk menambah kategori, gunakan perintah
         /category [nama kategori]=>[alias1, alias2, ...]
@@ -149,6 +172,7 @@ impl CategoryCommand {
     pub async fn run(
         raw_message: &str,
         binding: &ChatBinding,
+        sender: &ChatSender,
         tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
         lang: &Lang,
     ) -> Result<String> {
@@ -157,7 +181,7 @@ impl CategoryCommand {
         match &command.action {
             CategoryAction::List => Self::get_list(binding, tx, lang).await,
             CategoryAction::Create(entries) => {
-                Self::create_categories(entries, binding, tx, lang).await
+                Self::create_categories(entries, binding, sender, tx, lang).await
             }
         }
     }
@@ -186,29 +210,7 @@ impl CategoryCommand {
                 .push(alias.alias);
         }
 
-        // Format the response
-        let mut response = "Kategori:\n".to_string();
-
-        for (index, category) in categories.iter().enumerate() {
-            let category_aliases = aliases_by_category
-                .get(&category.uid)
-                .map(|aliases| aliases.join(", "))
-                .unwrap_or_else(|| "".to_string());
-
-            let aliases_str = if category_aliases.is_empty() {
-                "".to_string()
-            } else {
-                format!(" ({})", category_aliases)
-            };
-
-            response.push_str(&format!(
-                "{}. {}{}\n",
-                index + 1,
-                category.name,
-                aliases_str
-            ));
-        }
-
+        let mut response = format_list(&categories, &aliases_by_category);
         response.push_str(&format!("\nTotal: {} categories", categories.len()));
         response.push_str(&lang.get("MESSENGER__CATEGORY_LIST_FOOTER"));
 
@@ -218,12 +220,28 @@ impl CategoryCommand {
     async fn create_categories(
         entries: &[CategoryCommandEntry],
         binding: &ChatBinding,
+        sender: &ChatSender,
         tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
         lang: &Lang,
     ) -> Result<String> {
+        let actor = &AuditActor::from_chat(binding, sender);
         let mut results = Vec::new();
+        let mut category_uids = Vec::new();
+        // Parents may also be added earlier in the same message
+        let mut existing = CategoryRepo::list_by_group(tx, binding.group_uid).await?;
 
         for entry in entries {
+            let parent_uid = match &entry.parent {
+                Some(parent) => Some(
+                    existing
+                        .iter()
+                        .find(|category| category.name.eq_ignore_ascii_case(parent))
+                        .map(|category| category.uid)
+                        .ok_or_else(|| anyhow::anyhow!("Parent category '{}' not found", parent))?,
+                ),
+                None => None,
+            };
+
             // Create the category
             let category = CategoryRepo::create(
                 tx,
@@ -231,13 +249,27 @@ impl CategoryCommand {
                     group_uid: binding.group_uid,
                     name: entry.name.clone(),
                     description: None,
+                    parent_uid,
+                    icon: None,
+                    color: None,
                 },
             )
             .await?;
+            AuditRepo::record(
+                tx,
+                actor,
+                AuditEntity::Category,
+                binding.group_uid,
+                category.uid,
+                AuditChange::create(&category),
+            )
+            .await?;
+            category_uids.push(category.uid);
+            existing.push(category.clone());
 
             // Create aliases
             for alias in &entry.aliases {
-                CategoryAliasRepo::create(
+                let created = CategoryAliasRepo::create(
                     tx,
                     CreateCategoryAliasDbPayload {
                         group_uid: binding.group_uid,
@@ -246,6 +278,15 @@ impl CategoryCommand {
                     },
                 )
                 .await?;
+                AuditRepo::record(
+                    tx,
+                    actor,
+                    AuditEntity::CategoryAlias,
+                    binding.group_uid,
+                    created.alias_uid,
+                    AuditChange::create(&created),
+                )
+                .await?;
             }
 
             let aliases_str = if entry.aliases.is_empty() {
@@ -262,11 +303,64 @@ impl CategoryCommand {
                 ]),
             ));
         }
+        UndoCommand::journal(
+            binding,
+            sender,
+            tx,
+            &ChatAction::CategoryCreate { category_uids },
+        )
+        .await?;
 
         Ok(results.join("\n"))
     }
 }
 
+/*
+ "Kategori:" followed by the top-level categories, numbered and with their icons, each
+ with its subcategories indented under it. Subcategories whose parent is not in
+ `categories` are listed as top-level ones.
+*/
+fn format_list(
+    categories: &[Category],
+    aliases_by_category: &HashMap<uuid::Uuid, Vec<String>>,
+) -> String {
+    let aliases_str = |category: &Category| match aliases_by_category.get(&category.uid) {
+        Some(aliases) if !aliases.is_empty() => format!(" ({})", aliases.join(", ")),
+        _ => "".to_string(),
+    };
+    let is_top_level = |category: &Category| match category.parent_uid {
+        Some(parent_uid) => !categories.iter().any(|parent| parent.uid == parent_uid),
+        None => true,
+    };
+
+    let mut response = "Kategori:\n".to_string();
+    for (index, category) in categories
+        .iter()
+        .filter(|category| is_top_level(category))
+        .enumerate()
+    {
+        response.push_str(&format!(
+            "{}. {} {}{}\n",
+            index + 1,
+            category.icon,
+            category.name,
+            aliases_str(category)
+        ));
+        for child in categories
+            .iter()
+            .filter(|child| child.parent_uid == Some(category.uid))
+        {
+            response.push_str(&format!(
+                "   ↳ {} {}{}\n",
+                child.icon,
+                child.name,
+                aliases_str(child)
+            ));
+        }
+    }
+    response
+}
+
 impl Command for CategoryCommand {
     fn get_command() -> &'static str {
         "/category"
@@ -275,6 +369,10 @@ impl Command for CategoryCommand {
     fn get_instruction_text_key() -> &'static str {
         "MESSENGER__CATEGORY_SHORT_INSTRUCTION"
     }
+
+    fn get_help_text_key() -> Option<&'static str> {
+        Some("MESSENGER__CATEGORY_HELP")
+    }
 }
 
 #[cfg(test)]
@@ -321,6 +419,52 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_command_create_subcategory() {
+        let input = "/category\nMakanan = makan\nMakanan > Kopi = coffee";
+        let command = CategoryCommand::parse_command(input).unwrap();
+        match &command.action {
+            CategoryAction::Create(entries) => {
+                assert_eq!(entries.len(), 2);
+                assert_eq!(entries[0].parent, None);
+                assert_eq!(entries[1].name, "Kopi");
+                assert_eq!(entries[1].parent.as_deref(), Some("Makanan"));
+                assert_eq!(entries[1].aliases, vec!["coffee"]);
+            }
+            _ => panic!("Expected Create action"),
+        }
+
+        assert!(CategoryCommand::parse_command("/category > Kopi = coffee").is_err());
+        assert!(CategoryCommand::parse_command("/category Makanan > = coffee").is_err());
+    }
+
+    #[test]
+    fn test_format_list_indents_subcategories() {
+        let category = |name: &str, icon: &str, parent_uid: Option<uuid::Uuid>| Category {
+            uid: uuid::Uuid::new_v4(),
+            group_uid: uuid::Uuid::nil(),
+            name: name.to_string(),
+            description: None,
+            parent_uid,
+            icon: icon.to_string(),
+            color: "#64748b".to_string(),
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        };
+        let food = category("Makanan", "🍔", None);
+        let coffee = category("Kopi", "☕", Some(food.uid));
+        let transport = category("Transportasi", "🚗", None);
+        let aliases = HashMap::from([
+            (food.uid, vec!["makan".to_string()]),
+            (coffee.uid, vec!["coffee".to_string()]),
+        ]);
+
+        assert_eq!(
+            format_list(&[coffee, transport, food], &aliases),
+            "Kategori:\n1. 🚗 Transportasi\n2. 🍔 Makanan (makan)\n   ↳ ☕ Kopi (coffee)\n"
+        );
+    }
+
     #[test]
     fn test_parse_command_invalid_format() {
         let input = "/category invalid format";
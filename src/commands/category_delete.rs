@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use crate::{
+    commands::base::{ChatSender, Command},
+    lang::Lang,
+    repos::{
+        audit_log::{AuditActor, AuditChange, AuditEntity, AuditRepo},
+        category::CategoryRepo,
+        chat_binding::ChatBinding,
+    },
+};
+
+#[derive(Debug)]
+pub struct CategoryDeleteCommand {
+    pub category: String,
+    pub reassign_to: Option<String>,
+}
+
+impl CategoryDeleteCommand {
+    /*
+        Expected format:
+        /category-delete [category]
+        /category-delete [category] > [target category]
+
+        Both sides accept a category name or alias. Without a target the
+        expenses of the deleted category become uncategorized.
+
+        Examples:
+        /category-delete Jajan
+        /category-delete Jajan > Makanan
+    */
+    fn parse_command(input: &str) -> Result<Self> {
+        let input = input.trim();
+
+        // Should start with /category-delete
+        let input = if input.starts_with(Self::get_command()) {
+            input[Self::get_command().len()..].trim()
+        } else {
+            input
+        };
+
+        let (category, reassign_to) = match input.split_once('>') {
+            Some((category, target)) => (category.trim(), Some(target.trim())),
+            None => (input, None),
+        };
+
+        if category.is_empty() {
+            return Err(anyhow::anyhow!("No category given"));
+        }
+        if reassign_to.is_some_and(|target| target.is_empty()) {
+            return Err(anyhow::anyhow!("Empty target category"));
+        }
+
+        Ok(Self {
+            category: category.to_string(),
+            reassign_to: reassign_to.map(|target| target.to_string()),
+        })
+    }
+
+    /*
+        Output format (can be found on lang/id.json):
+        🗑️ Kategori Jajan berhasil dihapus. 3 pengeluaran dipindahkan ke Makanan.
+    */
+    pub async fn run(
+        raw_message: &str,
+        binding: &ChatBinding,
+        sender: &ChatSender,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        lang: &Lang,
+    ) -> Result<String> {
+        let command = Self::parse_command(raw_message)?;
+
+        let category =
+            CategoryRepo::find_by_name_or_alias(tx, binding.group_uid, &command.category)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("Category '{}' not found", command.category))?;
+
+        let target = match &command.reassign_to {
+            Some(name) => {
+                let target = CategoryRepo::find_by_name_or_alias(tx, binding.group_uid, name)
+                    .await?
+                    .ok_or_else(|| anyhow::anyhow!("Category '{}' not found", name))?;
+                if target.uid == category.uid {
+                    return Err(anyhow::anyhow!(
+                        "Cannot reassign expenses to the deleted category"
+                    ));
+                }
+                Some(target)
+            }
+            None => None,
+        };
+
+        let moved =
+            CategoryRepo::delete_reassigning(tx, category.uid, target.as_ref().map(|t| t.uid))
+                .await?;
+        AuditRepo::record(
+            tx,
+            &AuditActor::from_chat(binding, sender),
+            AuditEntity::Category,
+            binding.group_uid,
+            category.uid,
+            AuditChange::delete(&category),
+        )
+        .await?;
+
+        Ok(match target {
+            Some(target) => lang.get_with_vars(
+                "MESSENGER__CATEGORY_DELETE_REASSIGNED",
+                HashMap::from([
+                    ("name".to_string(), category.name),
+                    ("count".to_string(), moved.to_string()),
+                    ("target".to_string(), target.name),
+                ]),
+            ),
+            None => lang.get_with_vars(
+                "MESSENGER__CATEGORY_DELETE_UNCATEGORIZED",
+                HashMap::from([
+                    ("name".to_string(), category.name),
+                    ("count".to_string(), moved.to_string()),
+                ]),
+            ),
+        })
+    }
+}
+
+impl Command for CategoryDeleteCommand {
+    fn get_command() -> &'static str {
+        "/category-delete"
+    }
+
+    fn get_instruction_text_key() -> &'static str {
+        "MESSENGER__CATEGORY_DELETE_SHORT_INSTRUCTION"
+    }
+
+    fn get_help_text_key() -> Option<&'static str> {
+        Some("MESSENGER__CATEGORY_DELETE_HELP")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_command() {
+        let command = CategoryDeleteCommand::parse_command("/category-delete Jajan").unwrap();
+        assert_eq!(command.category, "Jajan");
+        assert!(command.reassign_to.is_none());
+
+        let command =
+            CategoryDeleteCommand::parse_command("/category-delete Jajan Sore > Makanan").unwrap();
+        assert_eq!(command.category, "Jajan Sore");
+        assert_eq!(command.reassign_to.as_deref(), Some("Makanan"));
+    }
+
+    #[test]
+    fn test_parse_command_invalid() {
+        assert!(CategoryDeleteCommand::parse_command("/category-delete").is_err());
+        assert!(CategoryDeleteCommand::parse_command("/category-delete > Makanan").is_err());
+        assert!(CategoryDeleteCommand::parse_command("/category-delete Jajan >").is_err());
+    }
+}
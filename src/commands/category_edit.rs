@@ -4,15 +4,34 @@ use anyhow::Result;
 use uuid::Uuid;
 
 use crate::{
-    commands::base::Command,
+    commands::base::{ChatSender, Command},
+    error::DatabaseError,
     lang::Lang,
     repos::{
-        category::{CategoryRepo, UpdateCategoryDbPayload},
+        audit_log::{AuditActor, AuditChange, AuditEntity, AuditRepo},
+        category::{Category, CategoryRepo, UpdateCategoryDbPayload},
         category_alias::{CategoryAliasRepo, CreateCategoryAliasDbPayload},
         chat_binding::ChatBinding,
     },
 };
 
+// Reply for an edit that lost the race against someone else changing the same category
+fn edit_conflict_error(error: DatabaseError, lang: &Lang) -> anyhow::Error {
+    let DatabaseError::ConcurrentModification(current) = &error else {
+        return error.into();
+    };
+    let Ok(current) = serde_json::from_value::<Category>(current.clone()) else {
+        return error.into();
+    };
+    anyhow::anyhow!(lang.get_with_vars(
+        "MESSENGER__CATEGORY_EDIT_CONFLICT",
+        HashMap::from([
+            ("id".to_string(), current.uid.to_string()),
+            ("name".to_string(), current.name),
+        ]),
+    ))
+}
+
 #[derive(Debug)]
 pub struct CategoryEditCommandEntry {
     pub id: Uuid,
@@ -113,10 +132,12 @@ impl CategoryEditCommand {
     pub async fn run(
         raw_message: &str,
         binding: &ChatBinding,
+        sender: &ChatSender,
         tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
         lang: &Lang,
     ) -> Result<String> {
         let entries = Self::parse_command(raw_message)?;
+        let actor = AuditActor::from_chat(binding, sender);
 
         let mut response = String::new();
         response.push_str(&lang.get("MESSENGER__CATEGORY_EDIT_SUCCESS_HEADER"));
@@ -131,25 +152,48 @@ impl CategoryEditCommand {
             }
 
             // Update the category name
-            CategoryRepo::update(
+            let updated = CategoryRepo::update(
                 tx,
                 *id,
                 UpdateCategoryDbPayload {
                     name: Some(entry.name.clone()),
                     description: None,
+                    parent_uid: None,
+                    icon: None,
+                    color: None,
+                    expected_updated_at: Some(category.updated_at),
                 },
             )
+            .await
+            .map_err(|e| edit_conflict_error(e, lang))?;
+            AuditRepo::record(
+                tx,
+                &actor,
+                AuditEntity::Category,
+                binding.group_uid,
+                updated.uid,
+                AuditChange::update(&category, &updated),
+            )
             .await?;
 
             // Delete existing aliases for this category
             let existing_aliases = CategoryAliasRepo::list_by_category(tx, *id).await?;
             for alias in existing_aliases {
                 CategoryAliasRepo::delete(tx, alias.alias_uid).await?;
+                AuditRepo::record(
+                    tx,
+                    &actor,
+                    AuditEntity::CategoryAlias,
+                    binding.group_uid,
+                    alias.alias_uid,
+                    AuditChange::delete(&alias),
+                )
+                .await?;
             }
 
             // Create new aliases
             for alias in &entry.aliases {
-                CategoryAliasRepo::create(
+                let created = CategoryAliasRepo::create(
                     tx,
                     CreateCategoryAliasDbPayload {
                         group_uid: binding.group_uid,
@@ -158,6 +202,15 @@ impl CategoryEditCommand {
                     },
                 )
                 .await?;
+                AuditRepo::record(
+                    tx,
+                    &actor,
+                    AuditEntity::CategoryAlias,
+                    binding.group_uid,
+                    created.alias_uid,
+                    AuditChange::create(&created),
+                )
+                .await?;
             }
 
             let aliases_str = if entry.aliases.is_empty() {
@@ -188,6 +241,10 @@ impl Command for CategoryEditCommand {
     fn get_instruction_text_key() -> &'static str {
         "MESSENGER__CATEGORY_EDIT_SHORT_INSTRUCTION"
     }
+
+    fn get_help_text_key() -> Option<&'static str> {
+        Some("MESSENGER__CATEGORY_EDIT_HELP")
+    }
 }
 
 #[cfg(test)]
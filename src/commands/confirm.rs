@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use chrono::Duration;
+
+use crate::{
+    commands::{
+        base::{ChatReply, ChatSender, Command},
+        expense::{ExpenseCommand, OVER_LIMIT_CONFIRM_MINUTES},
+    },
+    lang::Lang,
+    repos::{chat_binding::ChatBinding, pending_chat_expense::PendingChatExpenseRepo},
+};
+
+#[derive(Debug)]
+pub struct ConfirmCommand;
+
+impl ConfirmCommand {
+    /*
+        Should be in format:
+        /confirm
+    */
+    fn parse_command(input: &str) -> Result<Self> {
+        let input = input.trim();
+
+        if input != Self::get_command() {
+            return Err(anyhow::anyhow!("Invalid format: expected only /confirm"));
+        }
+
+        Ok(Self {})
+    }
+
+    /*
+        Records the /expense the sender sent last in this chat when it was held back because
+        it goes over a budget with a hard limit, replying like /expense does.
+    */
+    pub async fn run(
+        raw_message: &str,
+        binding: &ChatBinding,
+        sender: &ChatSender,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        lang: &Lang,
+        buttons: bool,
+    ) -> Result<ChatReply> {
+        let _command = Self::parse_command(raw_message)?;
+
+        let pending = PendingChatExpenseRepo::take(
+            tx,
+            binding.id,
+            &sender.p_user_id,
+            Duration::minutes(OVER_LIMIT_CONFIRM_MINUTES),
+        )
+        .await?;
+        let Some(expense_message) = pending else {
+            return Ok(lang
+                .get_with_vars(
+                    "MESSENGER__CONFIRM_NOTHING_PENDING",
+                    HashMap::from([(
+                        "minutes".to_string(),
+                        OVER_LIMIT_CONFIRM_MINUTES.to_string(),
+                    )]),
+                )
+                .into());
+        };
+        ExpenseCommand::run(&expense_message, binding, sender, tx, lang, buttons, true).await
+    }
+}
+
+impl Command for ConfirmCommand {
+    fn get_command() -> &'static str {
+        "/confirm"
+    }
+
+    fn get_instruction_text_key() -> &'static str {
+        "MESSENGER__CONFIRM_SHORT_INSTRUCTION"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_command() {
+        assert!(ConfirmCommand::parse_command(" /confirm ").is_ok());
+        assert!(ConfirmCommand::parse_command("/confirm now").is_err());
+    }
+}
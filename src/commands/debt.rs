@@ -0,0 +1,337 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use chrono::{NaiveDate, Utc};
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+use crate::{
+    commands::base::{ChatSender, Command},
+    lang::Lang,
+    repos::{
+        audit_log::{AuditActor, AuditChange, AuditEntity, AuditRepo},
+        chat_binding::ChatBinding,
+        debt::{CreateDebtDbPayload, Debt, DebtDirection, DebtRepo, OutstandingDebts},
+        exchange_rate::ExchangeRateRepo,
+        expense_group::ExpenseGroupRepo,
+    },
+    utils::parse_price::{format_price_in, parse_price},
+};
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+// Marks a debt as paid back
+const PAID_ARGUMENTS: [&str; 2] = ["paid", "lunas"];
+
+#[derive(Debug, PartialEq)]
+pub enum DebtCommand {
+    List,
+    Paid {
+        uid: Uuid,
+    },
+    Create {
+        direction: DebtDirection,
+        person: String,
+        amount: Decimal,
+        due_date: Option<NaiveDate>,
+        note: Option<String>,
+    },
+}
+
+impl DebtCommand {
+    /*
+     Expected format:
+     /debt
+     -> list the open debts of the group with their totals
+
+     /debt [piutang|utang],[person],[amount],[optional due date],[optional note]
+     -> record money lent to (piutang) or borrowed from (utang) someone outside the group
+
+     /debt lunas [id]
+     -> mark a debt as paid back
+
+     Examples:
+     /debt piutang,Andi,500.000,2025-11-30
+     /debt utang,Ibu,2.000.000,,DP motor
+     /debt lunas 3f1c...
+    */
+    fn parse_command(input: &str) -> Result<Self> {
+        let args = input
+            .trim()
+            .strip_prefix(Self::get_command())
+            .ok_or_else(|| anyhow::anyhow!("Invalid format: expected /debt"))?;
+        if !args.is_empty() && !args.starts_with(char::is_whitespace) {
+            return Err(anyhow::anyhow!("Invalid format: expected /debt"));
+        }
+
+        let args = args.trim();
+        if args.is_empty() {
+            return Ok(Self::List);
+        }
+        let mut words = args.split_whitespace();
+        if words
+            .next()
+            .is_some_and(|word| PAID_ARGUMENTS.contains(&word.to_lowercase().as_str()))
+        {
+            let uid = words
+                .next()
+                .ok_or_else(|| anyhow::anyhow!("Missing debt id: {}", args))?;
+            let uid = Uuid::parse_str(uid).map_err(|_| anyhow::anyhow!("Invalid id: {}", uid))?;
+            return Ok(Self::Paid { uid });
+        }
+
+        let parts: Vec<&str> = args.split(',').map(|s| s.trim()).collect();
+        if parts.len() < 3 || parts.len() > 5 {
+            return Err(anyhow::anyhow!("Invalid debt format: {}", args));
+        }
+        let direction = DebtDirection::parse(parts[0])
+            .ok_or_else(|| anyhow::anyhow!("Invalid debt type: {}", parts[0]))?;
+        let person = parts[1].to_string();
+        if person.is_empty() {
+            return Err(anyhow::anyhow!("Empty person name: {}", args));
+        }
+        let amount = parse_price(parts[2])
+            .map_err(|_| anyhow::anyhow!("Invalid amount format: {}", parts[2]))?;
+        if amount <= Decimal::ZERO {
+            return Err(anyhow::anyhow!("Invalid amount format: {}", parts[2]));
+        }
+        let due_date = match parts.get(3).filter(|date| !date.is_empty()) {
+            Some(date) => Some(
+                NaiveDate::parse_from_str(date, DATE_FORMAT)
+                    .map_err(|_| anyhow::anyhow!("Invalid date format: {}", date))?,
+            ),
+            None => None,
+        };
+        let note = parts
+            .get(4)
+            .filter(|note| !note.is_empty())
+            .map(|note| note.to_string());
+
+        Ok(Self::Create {
+            direction,
+            person,
+            amount,
+            due_date,
+            note,
+        })
+    }
+
+    /*
+     Output format:
+     💸 Utang & Piutang: (can be found on lang/id.json)
+
+     1. Andi - Rp. 500.000 (piutang, jatuh tempo 2025-11-30)
+     id: <uid>
+
+     Total piutang Rp. 500.000, utang Rp. 0
+    */
+    pub async fn run(
+        raw_message: &str,
+        binding: &ChatBinding,
+        sender: &ChatSender,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        lang: &Lang,
+    ) -> Result<String> {
+        let command = Self::parse_command(raw_message)?;
+        let today = Utc::now().date_naive();
+
+        match command {
+            Self::List => {
+                let debts = DebtRepo::list_by_group(tx, binding.group_uid, false).await?;
+                if debts.is_empty() {
+                    return Ok(lang.get("MESSENGER__DEBT_LIST_EMPTY"));
+                }
+                let group = ExpenseGroupRepo::get(tx, binding.group_uid).await?;
+                let rates = ExchangeRateRepo::rate_table(tx).await?;
+                let mut response = lang.get("MESSENGER__DEBT_LIST_HEADER");
+                for (index, debt) in debts.iter().enumerate() {
+                    response.push_str(&Self::format_item(index + 1, debt, today, lang));
+                }
+                let outstanding = OutstandingDebts::of(&debts, &rates, &group.currency, today);
+                response.push_str(&lang.get_with_vars(
+                    "MESSENGER__DEBT_TOTALS",
+                    HashMap::from([
+                        (
+                            "lent".to_string(),
+                            format_price_in(outstanding.lent, &group.currency),
+                        ),
+                        (
+                            "borrowed".to_string(),
+                            format_price_in(outstanding.borrowed, &group.currency),
+                        ),
+                    ]),
+                ));
+                Ok(response)
+            }
+            Self::Paid { uid } => {
+                let prev_rec = DebtRepo::get(tx, uid).await?;
+                if prev_rec.group_uid != binding.group_uid {
+                    return Err(anyhow::anyhow!("Debt {} not found", uid));
+                }
+                if prev_rec.is_settled() {
+                    return Err(anyhow::anyhow!("Debt {} is already paid back", uid));
+                }
+                let settled = DebtRepo::set_settled(tx, uid, true).await?;
+                AuditRepo::record(
+                    tx,
+                    &AuditActor::from_chat(binding, sender),
+                    AuditEntity::Debt,
+                    binding.group_uid,
+                    uid,
+                    AuditChange::update(&prev_rec, &settled),
+                )
+                .await?;
+                Ok(lang.get_with_vars("MESSENGER__DEBT_SETTLED", Self::vars(&settled, lang)))
+            }
+            Self::Create {
+                direction,
+                person,
+                amount,
+                due_date,
+                note,
+            } => {
+                let created = DebtRepo::create(
+                    tx,
+                    CreateDebtDbPayload {
+                        group_uid: binding.group_uid,
+                        direction,
+                        person,
+                        amount,
+                        currency: None,
+                        due_date,
+                        note,
+                    },
+                )
+                .await?;
+                AuditRepo::record(
+                    tx,
+                    &AuditActor::from_chat(binding, sender),
+                    AuditEntity::Debt,
+                    binding.group_uid,
+                    created.uid,
+                    AuditChange::create(&created),
+                )
+                .await?;
+                let mut response = lang.get("MESSENGER__DEBT_CREATED_HEADER");
+                response.push_str(&Self::format_item(1, &created, today, lang));
+                Ok(response)
+            }
+        }
+    }
+
+    pub fn direction_text(direction: DebtDirection, lang: &Lang) -> String {
+        lang.get(match direction {
+            DebtDirection::Lent => "MESSENGER__DEBT_LENT",
+            DebtDirection::Borrowed => "MESSENGER__DEBT_BORROWED",
+        })
+    }
+
+    // Variables shared by the messages about a single debt
+    pub fn vars(debt: &Debt, lang: &Lang) -> HashMap<String, String> {
+        HashMap::from([
+            ("id".to_string(), debt.uid.to_string()),
+            ("person".to_string(), debt.person.clone()),
+            (
+                "amount".to_string(),
+                format_price_in(debt.amount, &debt.currency),
+            ),
+            (
+                "direction".to_string(),
+                Self::direction_text(debt.direction(), lang),
+            ),
+            (
+                "due_date".to_string(),
+                debt.due_date
+                    .map(|date| date.format(DATE_FORMAT).to_string())
+                    .unwrap_or_default(),
+            ),
+        ])
+    }
+
+    fn format_item(index: usize, debt: &Debt, today: NaiveDate, lang: &Lang) -> String {
+        let mut vars = Self::vars(debt, lang);
+        vars.insert("index".to_string(), index.to_string());
+        let key = match debt.due_date {
+            Some(due_date) if due_date < today => "MESSENGER__DEBT_LIST_ITEM_OVERDUE",
+            Some(_) => "MESSENGER__DEBT_LIST_ITEM_DUE",
+            None => "MESSENGER__DEBT_LIST_ITEM",
+        };
+        lang.get_with_vars(key, vars)
+    }
+}
+
+impl Command for DebtCommand {
+    fn get_command() -> &'static str {
+        "/debt"
+    }
+
+    fn get_instruction_text_key() -> &'static str {
+        "MESSENGER__DEBT_SHORT_INSTRUCTION"
+    }
+
+    fn get_help_text_key() -> Option<&'static str> {
+        Some("MESSENGER__DEBT_HELP")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parse_command_list() {
+        assert_eq!(
+            DebtCommand::parse_command("/debt").unwrap(),
+            DebtCommand::List
+        );
+        assert!(DebtCommand::parse_command("/debts").is_err());
+    }
+
+    #[test]
+    fn test_parse_command_create() {
+        let command =
+            DebtCommand::parse_command("/debt piutang, Andi, 500.000, 2025-11-30").unwrap();
+        assert_eq!(
+            command,
+            DebtCommand::Create {
+                direction: DebtDirection::Lent,
+                person: "Andi".to_string(),
+                amount: dec!(500000),
+                due_date: NaiveDate::from_ymd_opt(2025, 11, 30),
+                note: None,
+            }
+        );
+
+        let command = DebtCommand::parse_command("/debt utang,Ibu,2.000.000,,DP motor").unwrap();
+        assert_eq!(
+            command,
+            DebtCommand::Create {
+                direction: DebtDirection::Borrowed,
+                person: "Ibu".to_string(),
+                amount: dec!(2000000),
+                due_date: None,
+                note: Some("DP motor".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_command_paid() {
+        let uid = Uuid::new_v4();
+        assert_eq!(
+            DebtCommand::parse_command(&format!("/debt LUNAS {}", uid)).unwrap(),
+            DebtCommand::Paid { uid }
+        );
+        assert!(DebtCommand::parse_command("/debt paid").is_err());
+        assert!(DebtCommand::parse_command("/debt paid 123").is_err());
+    }
+
+    #[test]
+    fn test_parse_command_invalid() {
+        assert!(DebtCommand::parse_command("/debt pinjam,Andi,500000").is_err());
+        assert!(DebtCommand::parse_command("/debt piutang,,500000").is_err());
+        assert!(DebtCommand::parse_command("/debt piutang,Andi,0").is_err());
+        assert!(DebtCommand::parse_command("/debt piutang,Andi,500000,30-11-2025").is_err());
+        assert!(DebtCommand::parse_command("/debt piutang,Andi").is_err());
+    }
+}
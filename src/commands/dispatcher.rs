@@ -0,0 +1,624 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use chrono::{Duration, Utc};
+use uuid::Uuid;
+
+use crate::commands::base::{ChatReply, ChatSender, Command};
+use crate::commands::expense::{CONVERSATION_TIMEOUT_MINUTES, natural_message};
+use crate::commands::{
+    budget::BudgetCommand, budget_edit::BudgetEditCommand, callback::CallbackAction,
+    cancel::CancelCommand, category::CategoryCommand, category_delete::CategoryDeleteCommand,
+    category_edit::CategoryEditCommand, confirm::ConfirmCommand, debt::DebtCommand,
+    expense::ExpenseCommand, expense_delete::ExpenseDeleteCommand,
+    expense_edit::ExpenseEditCommand, feed::FeedCommand, help::HelpCommand,
+    history::HistoryCommand, income::IncomeCommand, lang::LangCommand, link_me::LinkMeCommand,
+    logout::LogoutCommand, recurring::RecurringCommand, report::ReportCommand,
+    search::SearchCommand, settle::SettleCommand, switch::SwitchCommand, trip::TripCommand,
+    undo::UndoCommand,
+};
+use crate::error::DatabaseError;
+use crate::lang::Lang;
+use crate::middleware::rate_limit::{RateLimitDecision, RateLimiter};
+use crate::repos::{
+    chat_bind_request::{ChatBindRequestRepo, CreateChatBindRequestDbPayload},
+    chat_binding::{ChatBinding, ChatBindingRepo},
+    chat_conversation::{ChatConversationRepo, Conversation},
+    chat_member_link::ChatMemberLinkRepo,
+    expense_group::ExpenseGroupRepo,
+    expense_group_member::GroupMemberRepo,
+    group_settings::GroupSettingsRepo,
+};
+use crate::telemetry;
+use crate::types::SubscriptionTier;
+use crate::utils::pii;
+
+// Starts binding the chat to a group, handled outside of the bound group's commands
+const LOGIN_COMMAND: &str = "/login";
+
+// Longest message we send back to a chat, shared by every messenger. Longer replies are split
+// into several messages by the messenger, edited replies are truncated
+pub const MAX_RESPONSE_LENGTH: usize = 4000;
+const TRUNCATED_RESPONSE_LENGTH: usize = 3950;
+
+/*
+    Platform-agnostic entry point for chat commands.
+    Messengers only need to resolve the binding and deliver the returned reply, messengers
+    with buttons also pass their pressed buttons to `dispatch_callback`.
+*/
+pub struct CommandDispatcher;
+
+impl CommandDispatcher {
+    // Returns None when the message is not a known command
+    #[tracing::instrument(
+        name = "chat_command",
+        skip_all,
+        fields(
+            platform = %binding.platform,
+            binding = %binding.id,
+            group_uid = %binding.group_uid,
+            sender = pii::lookup_hash(&sender.p_user_id).as_deref(),
+            command = raw_message.split_whitespace().next().unwrap_or(""),
+        )
+    )]
+    pub async fn dispatch(
+        raw_message: &str,
+        binding: &ChatBinding,
+        sender: &ChatSender,
+        chat_bind_url: &str,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        lang: &Lang,
+        rate_limiter: &RateLimiter,
+    ) -> Option<ChatReply> {
+        let mut command = raw_message.split_whitespace().next().unwrap_or("");
+        // Plain messages answer the sender's ongoing conversation, handled and reported as the
+        // command that started it. Otherwise groups with natural-language entries record them
+        // as an /expense
+        let mut in_conversation = !command.starts_with('/');
+        let mut natural = None;
+        if in_conversation {
+            match Self::conversation_command(tx, binding, sender).await {
+                Some(conversation_command) => command = conversation_command,
+                None => {
+                    natural = Some(Self::natural_expense(tx, binding, raw_message).await?);
+                    command = ExpenseCommand::get_command();
+                    in_conversation = false;
+                }
+            }
+        }
+        let raw_message = natural.as_deref().unwrap_or(raw_message);
+        let lang = &*Self::resolve_lang(tx, binding, lang).await;
+
+        // Limited per chat, with the limits of whoever bound the chat
+        let tier = rate_limiter
+            .tier_for(tx, binding.bound_by)
+            .await
+            .unwrap_or_else(|e| {
+                tracing::error!("Error loading subscription tier for rate limiting: {}", e);
+                SubscriptionTier::Free
+            });
+        let key = format!("chat:{}:{}", binding.platform, binding.p_uid);
+        match rate_limiter.check(&key, tier.limits().chat_commands_per_minute) {
+            RateLimitDecision::Allowed => {}
+            RateLimitDecision::Limited {
+                retry_after_secs,
+                first,
+            } => {
+                // Stay quiet after the first warning, replying to every message would be spam too
+                return first.then(|| {
+                    lang.get_with_vars(
+                        "MESSENGER__RATE_LIMITED",
+                        HashMap::from([("seconds".to_string(), retry_after_secs.to_string())]),
+                    )
+                    .into()
+                });
+            }
+        }
+
+        let sender = &Self::resolve_sender(tx, binding, sender).await;
+        if Self::is_binder_command(command) && !sender.is_binder(binding) {
+            return Some(lang.get("MESSENGER__BINDER_ONLY").into());
+        }
+
+        // A bound chat can /login again to bind another group
+        if command == LOGIN_COMMAND {
+            return match Self::dispatch_unbound(
+                &binding.platform,
+                &binding.p_uid,
+                raw_message,
+                chat_bind_url,
+                tx,
+                lang,
+            )
+            .await
+            {
+                Ok(response) => Some(response.into()),
+                Err(e) => {
+                    tracing::error!("Error handling {} command: {}", command, e);
+                    Some(e.to_string().into())
+                }
+            };
+        }
+
+        // Bindings are kept while the group is soft-deleted so restoring it brings the chat back,
+        // the chat can still detach from it or switch to another group
+        let changes_binding =
+            command == LogoutCommand::get_command() || command == SwitchCommand::get_command();
+        let group = match ExpenseGroupRepo::get(tx, binding.group_uid).await {
+            Ok(group) => Some(group),
+            Err(DatabaseError::NotFound(_)) if !changes_binding => {
+                return Some(lang.get("MESSENGER__GROUP_DELETED").into());
+            }
+            Err(_) => None,
+        };
+        // Archived groups only answer /help, the chat can still detach or switch away
+        let archived = group.as_ref().is_some_and(|g| g.archived_at.is_some());
+        if archived && !changes_binding && command != HelpCommand::get_command() {
+            return Some(lang.get("MESSENGER__GROUP_ARCHIVED").into());
+        }
+        let group_name = group.map(|g| g.name);
+        let buttons = Self::supports_buttons(&binding.platform);
+
+        let (result, help_key) = match command {
+            c if c == ExpenseCommand::get_command() && in_conversation => (
+                ExpenseCommand::answer(raw_message, binding, sender, tx, lang, buttons).await,
+                ExpenseCommand::get_help_text_key(),
+            ),
+            c if c == ExpenseCommand::get_command() => (
+                ExpenseCommand::run(raw_message, binding, sender, tx, lang, buttons, false).await,
+                ExpenseCommand::get_help_text_key(),
+            ),
+            c if c == ConfirmCommand::get_command() => (
+                ConfirmCommand::run(raw_message, binding, sender, tx, lang, buttons).await,
+                ConfirmCommand::get_help_text_key(),
+            ),
+            c if c == CancelCommand::get_command() => (
+                CancelCommand::run(raw_message, binding, sender, tx, lang)
+                    .await
+                    .map(ChatReply::from),
+                CancelCommand::get_help_text_key(),
+            ),
+            c if c == UndoCommand::get_command() => (
+                UndoCommand::run(raw_message, binding, sender, tx, lang)
+                    .await
+                    .map(ChatReply::from),
+                UndoCommand::get_help_text_key(),
+            ),
+            c if c == ExpenseEditCommand::get_command() => (
+                ExpenseEditCommand::run(raw_message, binding, sender, tx, lang)
+                    .await
+                    .map(ChatReply::from),
+                ExpenseEditCommand::get_help_text_key(),
+            ),
+            c if c == ExpenseDeleteCommand::get_command() => (
+                ExpenseDeleteCommand::run(raw_message, binding, sender, tx, lang, buttons).await,
+                ExpenseDeleteCommand::get_help_text_key(),
+            ),
+            c if c == IncomeCommand::get_command() => (
+                IncomeCommand::run(raw_message, binding, sender, tx, lang)
+                    .await
+                    .map(ChatReply::from),
+                IncomeCommand::get_help_text_key(),
+            ),
+            c if c == RecurringCommand::get_command() => (
+                RecurringCommand::run(raw_message, binding, sender, tx, lang)
+                    .await
+                    .map(ChatReply::from),
+                RecurringCommand::get_help_text_key(),
+            ),
+            c if c == TripCommand::get_command() => (
+                TripCommand::run(raw_message, binding, sender, tx, lang)
+                    .await
+                    .map(ChatReply::from),
+                TripCommand::get_help_text_key(),
+            ),
+            c if c == DebtCommand::get_command() => (
+                DebtCommand::run(raw_message, binding, sender, tx, lang)
+                    .await
+                    .map(ChatReply::from),
+                DebtCommand::get_help_text_key(),
+            ),
+            c if c == SettleCommand::get_command() => (
+                SettleCommand::run(raw_message, binding, sender, tx, lang, buttons).await,
+                SettleCommand::get_help_text_key(),
+            ),
+            c if c == SearchCommand::get_command() => (
+                SearchCommand::run(raw_message, binding, tx, lang)
+                    .await
+                    .map(ChatReply::from),
+                SearchCommand::get_help_text_key(),
+            ),
+            c if c == FeedCommand::get_command() => (
+                FeedCommand::run(raw_message, binding, tx, lang)
+                    .await
+                    .map(ChatReply::from),
+                FeedCommand::get_help_text_key(),
+            ),
+            c if c == ReportCommand::get_command() => (
+                ReportCommand::run(raw_message, binding, tx, lang)
+                    .await
+                    .map(ChatReply::from),
+                ReportCommand::get_help_text_key(),
+            ),
+            c if c == HistoryCommand::get_command() => (
+                HistoryCommand::run(raw_message, binding, tx, lang, buttons).await,
+                HistoryCommand::get_help_text_key(),
+            ),
+            c if c == BudgetCommand::get_command() => (
+                BudgetCommand::run(raw_message, binding, sender, tx, lang)
+                    .await
+                    .map(ChatReply::from),
+                BudgetCommand::get_help_text_key(),
+            ),
+            c if c == BudgetEditCommand::get_command() => (
+                BudgetEditCommand::run(raw_message, binding, sender, tx, lang)
+                    .await
+                    .map(ChatReply::from),
+                BudgetEditCommand::get_help_text_key(),
+            ),
+            c if c == CategoryCommand::get_command() => (
+                CategoryCommand::run(raw_message, binding, sender, tx, lang)
+                    .await
+                    .map(ChatReply::from),
+                CategoryCommand::get_help_text_key(),
+            ),
+            c if c == CategoryEditCommand::get_command() => (
+                CategoryEditCommand::run(raw_message, binding, sender, tx, lang)
+                    .await
+                    .map(ChatReply::from),
+                CategoryEditCommand::get_help_text_key(),
+            ),
+            c if c == CategoryDeleteCommand::get_command() => (
+                CategoryDeleteCommand::run(raw_message, binding, sender, tx, lang)
+                    .await
+                    .map(ChatReply::from),
+                CategoryDeleteCommand::get_help_text_key(),
+            ),
+            c if c == LogoutCommand::get_command() => (
+                LogoutCommand::run(raw_message, binding, tx, lang)
+                    .await
+                    .map(ChatReply::from),
+                LogoutCommand::get_help_text_key(),
+            ),
+            c if c == LinkMeCommand::get_command() => (
+                LinkMeCommand::run(raw_message, binding, sender, tx, lang)
+                    .await
+                    .map(ChatReply::from),
+                LinkMeCommand::get_help_text_key(),
+            ),
+            c if c == SwitchCommand::get_command() => (
+                SwitchCommand::run(raw_message, binding, tx, lang)
+                    .await
+                    .map(ChatReply::from),
+                SwitchCommand::get_help_text_key(),
+            ),
+            c if c == LangCommand::get_command() => (
+                LangCommand::run(raw_message, binding, tx, lang)
+                    .await
+                    .map(ChatReply::from),
+                LangCommand::get_help_text_key(),
+            ),
+            c if c == HelpCommand::get_command() => (
+                HelpCommand::run(HelpCommand::get_command(), binding, tx, lang)
+                    .await
+                    .map(ChatReply::from),
+                HelpCommand::get_help_text_key(),
+            ),
+            _ => {
+                // TODO: maybe track unknown commands later
+                return None;
+            }
+        };
+
+        telemetry::record_chat_command(&binding.platform, command, result.is_ok());
+        let mut response = match result {
+            Ok(response) => response,
+            Err(e) => {
+                tracing::error!("Error handling {} command: {}", command, e);
+                Self::format_error(&e.to_string(), help_key.map(|key| lang.get(key))).into()
+            }
+        };
+
+        // A chat bound to several groups is told which one the command went to
+        if let Some(group_name) = group_name.filter(|_| !changes_binding) {
+            let bound = ChatBindingRepo::list_active_by_chat(tx, &binding.platform, &binding.p_uid)
+                .await
+                .map(|bindings| bindings.len())
+                .unwrap_or_else(|e| {
+                    tracing::error!("Error loading chat bindings: {}", e);
+                    1
+                });
+            if bound > 1 {
+                response.text = Self::with_group_header(&response.text, &group_name, lang);
+            }
+        }
+
+        Some(response)
+    }
+
+    /*
+     Handles a pressed button under one of our replies, `message` is the text of that reply.
+     Returns the reply replacing it, or None when the button data is not ours.
+    */
+    #[tracing::instrument(
+        name = "chat_callback",
+        skip_all,
+        fields(
+            platform = %binding.platform,
+            binding = %binding.id,
+            group_uid = %binding.group_uid,
+            sender = pii::lookup_hash(&sender.p_user_id).as_deref(),
+            data,
+        )
+    )]
+    pub async fn dispatch_callback(
+        data: &str,
+        message: &str,
+        binding: &ChatBinding,
+        sender: &ChatSender,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        lang: &Lang,
+    ) -> Option<ChatReply> {
+        let action = CallbackAction::decode(data)?;
+        let lang = &*Self::resolve_lang(tx, binding, lang).await;
+        match ExpenseGroupRepo::get(tx, binding.group_uid).await {
+            Err(DatabaseError::NotFound(_)) => {
+                return Some(lang.get("MESSENGER__GROUP_DELETED").into());
+            }
+            Ok(group) if group.archived_at.is_some() => {
+                return Some(lang.get("MESSENGER__GROUP_ARCHIVED").into());
+            }
+            _ => {}
+        }
+
+        let sender = &Self::resolve_sender(tx, binding, sender).await;
+        let result = match action {
+            CallbackAction::SetCategory {
+                entry_uid,
+                category_uid,
+            } => {
+                ExpenseCommand::set_category(entry_uid, category_uid, binding, sender, tx, lang)
+                    .await
+            }
+            // Only whoever may run /expense-delete answers its confirmation
+            CallbackAction::ConfirmDelete | CallbackAction::CancelDelete
+                if !sender.is_binder(binding) =>
+            {
+                return Some(lang.get("MESSENGER__BINDER_ONLY").into());
+            }
+            CallbackAction::ConfirmDelete => {
+                ExpenseDeleteCommand::confirm(message, binding, sender, tx, lang).await
+            }
+            CallbackAction::CancelDelete => {
+                Ok(lang.get("MESSENGER__ENTRY_DELETE_CANCELLED").into())
+            }
+            CallbackAction::ConfirmSettlement { settlement_uid } => {
+                SettleCommand::confirm(settlement_uid, binding, sender, tx, lang).await
+            }
+            CallbackAction::HistoryPage {
+                start_date,
+                end_date,
+                filter,
+                page,
+            } => HistoryCommand::page(start_date, end_date, &filter, page, binding, tx, lang).await,
+        };
+
+        let mut response = result.unwrap_or_else(|e| {
+            tracing::error!("Error handling {} callback: {}", data, e);
+            e.to_string().into()
+        });
+        // The reply replaces the message with the button, it cannot be split
+        response.text = Self::truncate(response.text, lang);
+        Some(response)
+    }
+
+    // Only Telegram shows buttons under replies for now
+    fn supports_buttons(platform: &str) -> bool {
+        platform == "telegram"
+    }
+
+    fn with_group_header(response: &str, group: &str, lang: &Lang) -> String {
+        let header = lang.get_with_vars(
+            "MESSENGER__ACTIVE_GROUP_HEADER",
+            HashMap::from([("group".to_string(), group.to_string())]),
+        );
+        format!("{}\n{}", header, response)
+    }
+
+    // Handles messages from chats that are not bound to any group yet, and /login from bound
+    // chats adding another group
+    #[tracing::instrument(
+        name = "chat_unbound",
+        skip_all,
+        fields(platform = %platform, chat = pii::lookup_hash(p_uid).as_deref())
+    )]
+    pub async fn dispatch_unbound(
+        platform: &str,
+        p_uid: &str,
+        raw_message: &str,
+        chat_bind_url: &str,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        lang: &Lang,
+    ) -> anyhow::Result<String> {
+        if raw_message.trim() != LOGIN_COMMAND {
+            return Ok(lang.get("TELEGRAM__CHAT_NOT_BOUND"));
+        }
+
+        let request = ChatBindRequestRepo::create(
+            tx,
+            CreateChatBindRequestDbPayload {
+                platform: platform.to_string(),
+                p_uid: p_uid.to_string(),
+                nonce: Uuid::new_v4().to_string(),
+                user_uid: None,
+                expires_at: Utc::now() + Duration::hours(1),
+            },
+        )
+        .await?;
+
+        let bind_url = format!("{}/{}", chat_bind_url, request.id);
+        Ok(lang.get_with_vars(
+            "TELEGRAM__SIGN_IN_REQUEST",
+            HashMap::from([("link".to_string(), bind_url)]),
+        ))
+    }
+
+    // The group's language, or the language of the user who bound the chat
+    async fn resolve_lang<'a>(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        binding: &ChatBinding,
+        lang: &'a Lang,
+    ) -> Cow<'a, Lang> {
+        match ExpenseGroupRepo::find_lang(tx, binding.group_uid, binding.bound_by).await {
+            Ok(code) => lang.preferred(code.as_deref()),
+            Err(e) => {
+                tracing::error!("Error loading chat language: {}", e);
+                Cow::Borrowed(lang)
+            }
+        }
+    }
+
+    /*
+     Senders in group chats are attributed to the account they linked with /link-me, as long
+     as it is a member of the bound group. Lookup failures leave the sender unattributed.
+    */
+    async fn resolve_sender(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        binding: &ChatBinding,
+        sender: &ChatSender,
+    ) -> ChatSender {
+        let mut resolved = sender.clone();
+        if sender.p_user_id == binding.p_uid {
+            return resolved;
+        }
+        let linked =
+            match ChatMemberLinkRepo::find_user(tx, &binding.platform, &sender.p_user_id).await {
+                Ok(linked) => linked,
+                Err(e) => {
+                    tracing::error!("Error loading chat member link: {}", e);
+                    None
+                }
+            };
+        if let Some(user_uid) = linked {
+            match GroupMemberRepo::find_role(tx, binding.group_uid, user_uid).await {
+                Ok(Some(_)) => resolved.linked_user_uid = Some(user_uid),
+                Ok(None) | Err(DatabaseError::NotFound(_)) => {}
+                Err(e) => tracing::error!("Error loading group role of chat member: {}", e),
+            }
+        }
+        resolved
+    }
+
+    // Command of the sender's ongoing conversation in the chat, if any
+    async fn conversation_command(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        binding: &ChatBinding,
+        sender: &ChatSender,
+    ) -> Option<&'static str> {
+        let conversation = ChatConversationRepo::get(
+            tx,
+            binding.id,
+            &sender.p_user_id,
+            Duration::minutes(CONVERSATION_TIMEOUT_MINUTES),
+        )
+        .await
+        .unwrap_or_else(|e| {
+            tracing::error!("Error loading chat conversation: {}", e);
+            None
+        })?;
+        match conversation {
+            Conversation::Expense { .. } => Some(ExpenseCommand::get_command()),
+        }
+    }
+
+    // The /expense a plain message stands for, when the group records those as expenses
+    async fn natural_expense(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        binding: &ChatBinding,
+        message: &str,
+    ) -> Option<String> {
+        let settings = GroupSettingsRepo::get(tx, binding.group_uid)
+            .await
+            .unwrap_or_else(|e| {
+                tracing::error!("Error loading group settings: {}", e);
+                Default::default()
+            });
+        if !settings.natural_language_entries {
+            return None;
+        }
+        natural_message(message)
+    }
+
+    // Commands changing the chat's bindings or editing and deleting group data
+    fn is_binder_command(command: &str) -> bool {
+        [
+            LOGIN_COMMAND,
+            SwitchCommand::get_command(),
+            LogoutCommand::get_command(),
+            LangCommand::get_command(),
+            ExpenseEditCommand::get_command(),
+            ExpenseDeleteCommand::get_command(),
+            BudgetEditCommand::get_command(),
+            CategoryEditCommand::get_command(),
+            CategoryDeleteCommand::get_command(),
+        ]
+        .contains(&command)
+    }
+
+    fn format_error(error: &str, help: Option<String>) -> String {
+        let mut response = error.to_string();
+        if let Some(help) = help {
+            response.push_str("\n-----\n");
+            response.push_str(&help);
+        }
+        response
+    }
+
+    pub fn truncate(response: String, lang: &Lang) -> String {
+        if response.len() <= MAX_RESPONSE_LENGTH {
+            return response;
+        }
+
+        let mut truncated = response
+            .chars()
+            .take(TRUNCATED_RESPONSE_LENGTH)
+            .collect::<String>();
+        truncated.push_str(&lang.get("MESSENGER__RESPONSE_TRUNCATED"));
+        truncated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_error_with_help() {
+        let response = CommandDispatcher::format_error("Invalid price", Some("Format".to_string()));
+        assert_eq!(response, "Invalid price\n-----\nFormat");
+    }
+
+    #[test]
+    fn test_with_group_header() {
+        let lang = Lang::from_json("id");
+        let response = CommandDispatcher::with_group_header("Expense berhasil!", "Rumah", &lang);
+        assert!(response.starts_with("📁 Rumah"));
+        assert!(response.ends_with("\nExpense berhasil!"));
+    }
+
+    #[test]
+    fn test_is_binder_command() {
+        assert!(CommandDispatcher::is_binder_command("/login"));
+        assert!(CommandDispatcher::is_binder_command("/category-delete"));
+        assert!(CommandDispatcher::is_binder_command("/expense-edit"));
+        assert!(CommandDispatcher::is_binder_command("/lang"));
+        assert!(!CommandDispatcher::is_binder_command("/expense"));
+        assert!(!CommandDispatcher::is_binder_command("/link-me"));
+    }
+
+    #[test]
+    fn test_format_error_without_help() {
+        let response = CommandDispatcher::format_error("Invalid price", None);
+        assert_eq!(response, "Invalid price");
+    }
+}
@@ -1,28 +1,64 @@
 use std::collections::HashMap;
 
 use anyhow::Result;
+use chrono::{Duration, NaiveDate, NaiveTime, Utc};
+use rust_decimal::Decimal;
 use teloxide::types::ChatId;
 use uuid::Uuid;
 
 use crate::{
-    commands::base::Command,
+    commands::{
+        base::{ChatButton, ChatReply, ChatSender, Command},
+        callback::CallbackAction,
+        expense_edit::edit_conflict_error,
+        undo::UndoCommand,
+    },
     lang::Lang,
     middleware::tier::check_tier_limit,
     repos::{
-        category::CategoryRepo,
+        audit_log::{AuditActor, AuditChange, AuditEntity, AuditRepo},
+        budget_alert::{BudgetAlertRepo, HardLimitAction},
+        category::{Category, CategoryRepo},
         category_alias::CategoryAliasRepo,
+        chat_action::ChatAction,
         chat_binding::ChatBinding,
-        expense_entry::{CreateExpenseEntryDbPayload, ExpenseEntryRepo},
+        chat_conversation::{ChatConversationRepo, Conversation},
+        envelope::EnvelopeRepo,
+        expense_entry::{
+            CreateExpenseEntryDbPayload, ExpenseEntryRepo, UpdateExpenseEntryDbPayload,
+        },
+        pending_chat_expense::PendingChatExpenseRepo,
+        product::{ProductRepo, normalize_product_name},
         subscription::{SubscriptionRepo, UserUsageRepo},
+        tag::{TagRepo, normalize_tag_name},
+    },
+    utils::{
+        budget_limit::{self, HardLimitBreach, NewSpend},
+        parse_price::{format_price_in, parse_price},
     },
-    utils::parse_price::{format_price, parse_price},
+    webhooks::{self, WebhookEvent},
 };
 
+// How long an /expense held back by a hard budget limit waits for /confirm
+pub const OVER_LIMIT_CONFIRM_MINUTES: i64 = 10;
+// How long an /expense conversation waits for the next answer before plain messages are ignored
+pub const CONVERSATION_TIMEOUT_MINUTES: i64 = 10;
+// Answered to the category question to record the expense without one
+const SKIP_ANSWER: &str = "-";
+// Category buttons shown per row under an entry recorded without a category
+const CATEGORY_BUTTONS_PER_ROW: usize = 2;
+// Of the optional date backdating an entry
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
 #[derive(Debug)]
 pub struct ExpenseCommandEntry {
     pub name: String,
-    pub price: f64,
+    pub price: Decimal,
     pub category_or_alias: Option<String>,
+    // Normalized tag names, without the leading #
+    pub tags: Vec<String>,
+    // Day the expense was made when it was not today, the entry is recorded at its midnight UTC
+    pub date: Option<NaiveDate>,
 }
 
 #[derive(Debug)]
@@ -35,14 +71,15 @@ impl ExpenseCommand {
     /*
      Expected format:
      /expense
-     [name],[price],[optional category]
+     [name],[price],[optional category],[optional date] [optional #tags]
      or
-     /expense [name],[price],[optional category]
+     /expense [name],[price],[optional category],[optional date] [optional #tags]
 
      Examples:
      /expense
-     Nasi Padang,10000,Makanan
+     Nasi Padang,10000,Makanan #bali #vacation
      Warteg,15000
+     Kopi,20000,Minuman,2025-09-01
 
      or
      /expense Nasi Padang,10000,Makanan
@@ -68,13 +105,25 @@ impl ExpenseCommand {
             if line.is_empty() {
                 continue;
             }
+            let (fields, tags) = split_tags(line);
 
             // Split by commas
-            let parts: Vec<&str> = line.split(',').map(|s| s.trim()).collect();
+            let mut parts: Vec<&str> = fields.split(',').map(|s| s.trim()).collect();
             if parts.len() < 2 {
                 fail_entries.push(line.to_string());
                 continue; // Invalid entry, skip
             }
+            let date = match parts.last() {
+                Some(part) if parts.len() >= 3 => NaiveDate::parse_from_str(part, DATE_FORMAT).ok(),
+                _ => None,
+            };
+            if date.is_some() {
+                parts.pop();
+            }
+            if date.is_some_and(|date| date > Utc::now().date_naive()) {
+                fail_entries.push(line.to_string());
+                continue; // Dated in the future, skip
+            }
 
             let name = parts[0].to_string();
             if name.is_empty() {
@@ -95,6 +144,8 @@ impl ExpenseCommand {
                 name,
                 price,
                 category_or_alias,
+                tags,
+                date,
             });
         }
 
@@ -112,15 +163,21 @@ impl ExpenseCommand {
        If no success, at all, return error
        If some success, some fail, return success message with fail info
 
+       With `buttons`, a single entry recorded without a category gets a button per category
+       of the group to pick one, see `set_category`.
+
        Format
     */
 
     pub async fn run(
         raw_message: &str,
         binding: &ChatBinding,
+        sender: &ChatSender,
         tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
         lang: &Lang,
-    ) -> Result<String> {
+        buttons: bool,
+        over_limit_confirmed: bool,
+    ) -> Result<ChatReply> {
         // TODO: Change subscription, check the
         // let subscription = SubscriptionRepo::get_by_user(tx, binding.bound_by).await?;
         // let usage_payload = UserUsageRepo::calculate_current_usage(tx, binding.bound_by).await?;
@@ -130,50 +187,164 @@ impl ExpenseCommand {
         //     usage_payload.total_expenses,
         // )?;
 
+        // Without entries, the expense is asked for one field at a time, see `answer`
+        if raw_message.trim() == Self::get_command() {
+            ChatConversationRepo::upsert(
+                tx,
+                binding.id,
+                &sender.p_user_id,
+                &Conversation::Expense {
+                    name: None,
+                    price: None,
+                },
+            )
+            .await?;
+            return Ok(lang.get("MESSENGER__EXPENSE_ASK_NAME").into());
+        }
+
         let command = Self::parse_command(raw_message)?;
+        let created_by_user_uid = sender.resolve_user_uid(binding);
+        let actor = AuditActor::from_chat(binding, sender);
         let categories = CategoryRepo::list_by_group(tx, binding.group_uid).await?;
         let aliases = CategoryAliasRepo::list_by_group(tx, binding.group_uid).await?;
+        // Entries dated within the chat's active trip go to that envelope
+        let envelope = EnvelopeRepo::get_active_for_binding(tx, binding.id).await?;
+        let today = Utc::now().date_naive();
 
         // For now, assume category already exists or is optional
         let mut category_map: HashMap<String, Uuid> = HashMap::new();
         let mut category_id_map: HashMap<Uuid, String> = HashMap::new();
 
-        for category in categories {
+        for category in categories.iter() {
             category_map.insert(category.name.to_lowercase(), category.uid);
-            category_id_map.insert(category.uid, category.name);
+            category_id_map.insert(category.uid, category.name.clone());
         }
 
         for alias in aliases {
             category_map.insert(alias.alias.to_lowercase(), alias.category_uid);
         }
+        // Entries sent without a category get the one their product was last recorded with
+        let names: Vec<String> = command
+            .entries
+            .iter()
+            .filter(|entry| entry.category_or_alias.is_none())
+            .map(|entry| entry.name.clone())
+            .collect();
+        let learned_categories =
+            ProductRepo::categories_by_name(tx, binding.group_uid, &names).await?;
+
+        // Resolved up front so hard budget limits are checked before anything is recorded
+        let entries: Vec<(ExpenseCommandEntry, Option<Uuid>, Option<Uuid>)> = command
+            .entries
+            .into_iter()
+            .map(|entry| {
+                let category_uid = match &entry.category_or_alias {
+                    Some(cat) => category_map.get(&cat.to_lowercase()).copied(),
+                    None => learned_categories
+                        .get(&normalize_product_name(&entry.name))
+                        .copied(),
+                };
+                let envelope_uid = envelope
+                    .as_ref()
+                    .filter(|envelope| envelope.contains(entry.date.unwrap_or(today)))
+                    .map(|envelope| envelope.uid);
+                (entry, category_uid, envelope_uid)
+            })
+            .collect();
+
+        // Entries in an envelope do not count against the group's budgets
+        let spends: Vec<NewSpend> = entries
+            .iter()
+            .filter(|(_, _, envelope_uid)| envelope_uid.is_none())
+            .filter_map(|(entry, category_uid, _)| {
+                Some(NewSpend {
+                    category_uid: (*category_uid)?,
+                    amount: entry.price,
+                    currency: None,
+                    date: entry.date.unwrap_or(today),
+                })
+            })
+            .collect();
+        let breaches = budget_limit::find_breaches(tx, binding.group_uid, &spends).await?;
+        if !breaches.is_empty() && !over_limit_confirmed {
+            let settings = BudgetAlertRepo::get_settings(tx, binding.group_uid).await?;
+            if settings.hard_limit_action() == HardLimitAction::Confirm {
+                PendingChatExpenseRepo::upsert(tx, binding.id, &sender.p_user_id, raw_message)
+                    .await?;
+                let mut response = lang.get("MESSENGER__BUDGET_HARD_LIMIT_CONFIRM");
+                response.push_str(&format_breaches(&breaches, &category_id_map, lang));
+                response.push_str(&lang.get_with_vars(
+                    "MESSENGER__BUDGET_HARD_LIMIT_CONFIRM_FOOTER",
+                    HashMap::from([(
+                        "minutes".to_string(),
+                        OVER_LIMIT_CONFIRM_MINUTES.to_string(),
+                    )]),
+                ));
+                return Ok(response.into());
+            }
+        }
 
         // TODO: Better formatting
         let mut response = String::new();
         response.push_str(&lang.get("MESSENGER__ENTRY_SUCCESS_HEADER"));
+        let entry_count = entries.len();
+        let mut uncategorized = None;
+        let mut in_envelope = false;
+        let mut entry_uids = Vec::new();
 
-        for entry in command.entries {
+        for (entry, category_uid, envelope_uid) in entries {
             let price = entry.price;
             let product = entry.name;
-            let category_uid = if let Some(cat) = entry.category_or_alias {
-                if let Some(uid) = category_map.get(&cat.to_lowercase()) {
-                    Some(*uid)
-                } else {
-                    None
-                }
-            } else {
-                None
-            };
+            in_envelope |= envelope_uid.is_some();
             // Create expense entry
             let expense = ExpenseEntryRepo::create_expense_entry(
                 tx,
                 CreateExpenseEntryDbPayload {
                     price,
+                    currency: None,
                     product,
                     group_uid: binding.group_uid,
                     category_uid,
+                    created_by: sender.name.clone(),
+                    created_by_user_uid,
+                    created_at: entry
+                        .date
+                        .map(|date| date.and_time(NaiveTime::MIN).and_utc()),
+                    note: None,
+                    envelope_uid,
                 },
             )
             .await?;
+            if !entry.tags.is_empty() {
+                let tag_uids: Vec<Uuid> =
+                    TagRepo::get_or_create(tx, binding.group_uid, &entry.tags)
+                        .await?
+                        .into_iter()
+                        .map(|tag| tag.uid)
+                        .collect();
+                TagRepo::set_for_entry(tx, expense.uid, &tag_uids).await?;
+            }
+            AuditRepo::record(
+                tx,
+                &actor,
+                AuditEntity::ExpenseEntry,
+                binding.group_uid,
+                expense.uid,
+                AuditChange::create(&expense),
+            )
+            .await?;
+            webhooks::dispatch(
+                tx,
+                binding.group_uid,
+                WebhookEvent::ExpenseCreated,
+                &expense,
+            )
+            .await?;
+            ProductRepo::learn(tx, &expense).await?;
+            entry_uids.push(expense.uid);
+            if expense.category_uid.is_none() {
+                uncategorized = Some((expense.uid, expense.product.clone()));
+            }
 
             response.push_str(
                 &lang.get_with_vars(
@@ -181,10 +352,7 @@ impl ExpenseCommand {
                     HashMap::from([
                         ("id".to_string(), expense.uid.to_string()),
                         ("item".to_string(), expense.product),
-                        (
-                            "price".to_string(),
-                            format!("Rp. {}", format_price(expense.price)),
-                        ),
+                        ("price".to_string(), format_price_in(expense.price, &expense.currency)),
                         (
                             "category".to_string(),
                             category_id_map
@@ -192,11 +360,26 @@ impl ExpenseCommand {
                                 .cloned()
                                 .unwrap_or_else(|| lang.get("MESSENGER__NO_CATEGORY_ASSIGNED")),
                         ),
+                        ("tags".to_string(), format_tags(&entry.tags)),
                     ]),
                 ),
             );
         }
 
+        UndoCommand::journal(binding, sender, tx, &ChatAction::ExpenseCreate { entry_uids }).await?;
+
+        if let Some(envelope) = envelope.filter(|_| in_envelope) {
+            response.push_str(&lang.get_with_vars(
+                "MESSENGER__ENTRY_IN_ENVELOPE",
+                HashMap::from([("name".to_string(), envelope.name)]),
+            ));
+        }
+
+        if !breaches.is_empty() {
+            response.push_str(&lang.get("MESSENGER__BUDGET_HARD_LIMIT_WARNING"));
+            response.push_str(&format_breaches(&breaches, &category_id_map, lang));
+        }
+
         if !command.fail_entries.is_empty() {
             response.push_str("-----\n");
             response.push_str(&&lang.get_with_vars(
@@ -205,8 +388,283 @@ impl ExpenseCommand {
             ));
         }
 
-        Ok(response)
+        // Picking replaces the whole reply, so only offered when it has a single entry
+        let pick = uncategorized.filter(|_| buttons && entry_count == 1 && !categories.is_empty());
+        let Some((entry_uid, product)) = pick else {
+            return Ok(response.into());
+        };
+        response.push_str(&lang.get_with_vars(
+            "MESSENGER__ENTRY_PICK_CATEGORY",
+            HashMap::from([("item".to_string(), product)]),
+        ));
+        Ok(ChatReply::from(response).with_buttons(category_buttons(entry_uid, categories)))
+    }
+
+    /*
+     Sets the category picked with the buttons under an entry recorded without one, replying
+     with the entry the same way as when it was recorded
+    */
+    pub async fn set_category(
+        entry_uid: Uuid,
+        category_uid: Uuid,
+        binding: &ChatBinding,
+        sender: &ChatSender,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        lang: &Lang,
+    ) -> Result<ChatReply> {
+        let prev_expense = ExpenseEntryRepo::get(tx, entry_uid)
+            .await
+            .ok()
+            .filter(|expense| expense.group_uid == binding.group_uid)
+            .ok_or_else(|| anyhow::anyhow!("Expense entry not found: {}", entry_uid))?;
+        let category = CategoryRepo::get(tx, category_uid)
+            .await
+            .ok()
+            .filter(|category| category.group_uid == binding.group_uid)
+            .ok_or_else(|| anyhow::anyhow!("Category not found"))?;
+
+        let expense = ExpenseEntryRepo::update(
+            tx,
+            entry_uid,
+            UpdateExpenseEntryDbPayload {
+                price: None,
+                currency: None,
+                product: None,
+                category_uid: Some(category.uid),
+                note: None,
+                envelope_uid: None,
+                expected_updated_at: Some(prev_expense.updated_at),
+            },
+        )
+        .await
+        .map_err(|e| edit_conflict_error(e, lang))?;
+        AuditRepo::record(
+            tx,
+            &AuditActor::from_chat(binding, sender),
+            AuditEntity::ExpenseEntry,
+            binding.group_uid,
+            expense.uid,
+            AuditChange::update(&prev_expense, &expense),
+        )
+        .await?;
+        webhooks::dispatch(
+            tx,
+            binding.group_uid,
+            WebhookEvent::ExpenseUpdated,
+            &expense,
+        )
+        .await?;
+        ProductRepo::learn_category(tx, binding.group_uid, &expense.product, category.uid).await?;
+        UndoCommand::journal(
+            binding,
+            sender,
+            tx,
+            &ChatAction::ExpenseEdit {
+                before: vec![prev_expense],
+            },
+        )
+        .await?;
+        let tags = TagRepo::list_names_for_entry(tx, expense.uid).await?;
+
+        let mut response = lang.get("MESSENGER__ENTRY_SUCCESS_HEADER");
+        response.push_str(&lang.get_with_vars(
+            "MESSENGER__ENTRY_SUCCESS_EDIT_ENTRY",
+            HashMap::from([
+                ("id".to_string(), expense.uid.to_string()),
+                ("item".to_string(), expense.product),
+                ("price".to_string(), format_price_in(expense.price, &expense.currency)),
+                ("category".to_string(), category.name),
+                ("tags".to_string(), format_tags(&tags)),
+            ]),
+        ));
+        Ok(response.into())
+    }
+
+    /*
+     Takes the sender's answer in the conversation started by a bare /expense: what was
+     bought, then how much, then which category. The expense is recorded once the category
+     is answered, replying as /expense does. Invalid answers ask the same question again.
+    */
+    pub async fn answer(
+        raw_message: &str,
+        binding: &ChatBinding,
+        sender: &ChatSender,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        lang: &Lang,
+        buttons: bool,
+    ) -> Result<ChatReply> {
+        let answer = raw_message.trim();
+        let conversation = ChatConversationRepo::get(
+            tx,
+            binding.id,
+            &sender.p_user_id,
+            Duration::minutes(CONVERSATION_TIMEOUT_MINUTES),
+        )
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("No ongoing conversation"))?;
+        let Conversation::Expense { name, price } = conversation;
+
+        let (next, question) = match (name, price) {
+            (None, _) => {
+                // Commas and lines separate the fields and entries of an /expense
+                if answer.is_empty() || answer.contains([',', '\n']) {
+                    return Ok(lang.get("MESSENGER__EXPENSE_ASK_NAME_AGAIN").into());
+                }
+                let next = Conversation::Expense {
+                    name: Some(answer.to_string()),
+                    price: None,
+                };
+                (next, lang.get("MESSENGER__EXPENSE_ASK_PRICE"))
+            }
+            (Some(name), None) => {
+                let Ok(price) = parse_price(answer) else {
+                    return Ok(lang.get("MESSENGER__EXPENSE_ASK_PRICE_AGAIN").into());
+                };
+                let categories = CategoryRepo::list_by_group(tx, binding.group_uid).await?;
+                let names: Vec<String> = categories.into_iter().map(|c| c.name).collect();
+                let question = lang.get_with_vars(
+                    "MESSENGER__EXPENSE_ASK_CATEGORY",
+                    HashMap::from([
+                        ("categories".to_string(), names.join(", ")),
+                        ("skip".to_string(), SKIP_ANSWER.to_string()),
+                    ]),
+                );
+                let next = Conversation::Expense {
+                    name: Some(name),
+                    price: Some(price),
+                };
+                (next, question)
+            }
+            (Some(name), Some(price)) => {
+                ChatConversationRepo::delete(tx, binding.id, &sender.p_user_id).await?;
+                let message = conversation_message(&name, price, answer);
+                return Self::run(&message, binding, sender, tx, lang, buttons, false).await;
+            }
+        };
+        ChatConversationRepo::upsert(tx, binding.id, &sender.p_user_id, &next).await?;
+        Ok(question.into())
+    }
+}
+
+// The /expense message the answers of a conversation add up to
+fn conversation_message(name: &str, price: Decimal, category_answer: &str) -> String {
+    let mut message = format!("{} {},{}", ExpenseCommand::get_command(), name, price);
+    let category_answer = category_answer.trim();
+    if !category_answer.is_empty() && category_answer != SKIP_ANSWER {
+        message.push(',');
+        message.push_str(category_answer);
+    }
+    message
+}
+
+/*
+ The /expense message a plain chat message stands for in groups with
+ `natural_language_entries`: every line is `[name] [price]`, e.g. `Nasi Padang 25000 #lunch`,
+ or in the /expense format. None when a line is neither, so regular conversation is left
+ alone.
+*/
+pub fn natural_message(message: &str) -> Option<String> {
+    let mut lines = Vec::new();
+    for line in message.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        let (fields, tags) = split_tags(line);
+        if fields.contains(',') {
+            lines.push(line.to_string());
+            continue;
+        }
+        let (name, price) = fields.rsplit_once(char::is_whitespace)?;
+        parse_price(price).ok()?;
+        lines.push(format!("{},{}{}", name.trim(), price, format_tags(&tags)));
+    }
+    let message = format!("{}\n{}", ExpenseCommand::get_command(), lines.join("\n"));
+    let command = ExpenseCommand::parse_command(&message).ok()?;
+    command.fail_entries.is_empty().then_some(message)
+}
+
+// One line per budget with a hard limit the entries go over
+fn format_breaches(
+    breaches: &[HardLimitBreach],
+    category_names: &HashMap<Uuid, String>,
+    lang: &Lang,
+) -> String {
+    breaches
+        .iter()
+        .map(|breach| {
+            lang.get_with_vars(
+                "MESSENGER__BUDGET_HARD_LIMIT_ENTRY",
+                HashMap::from([
+                    (
+                        "category".to_string(),
+                        category_names
+                            .get(&breach.category_uid)
+                            .cloned()
+                            .unwrap_or_default(),
+                    ),
+                    (
+                        "spent".to_string(),
+                        format_price_in(breach.spent, &breach.currency),
+                    ),
+                    (
+                        "amount".to_string(),
+                        format_price_in(breach.amount, &breach.currency),
+                    ),
+                ]),
+            )
+        })
+        .collect()
+}
+
+// One button per category, sorted by name
+fn category_buttons(entry_uid: Uuid, mut categories: Vec<Category>) -> Vec<Vec<ChatButton>> {
+    categories.sort_by_key(|category| category.name.to_lowercase());
+    categories
+        .chunks(CATEGORY_BUTTONS_PER_ROW)
+        .map(|row| {
+            row.iter()
+                .map(|category| {
+                    ChatButton::new(
+                        category.name.clone(),
+                        &CallbackAction::SetCategory {
+                            entry_uid,
+                            category_uid: category.uid,
+                        },
+                    )
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/*
+ Splits the `#tag` words off the end of an entry line, e.g.
+ "Nasi Padang,10000,Makanan #Bali #vacation" -> ("Nasi Padang,10000,Makanan", ["bali", "vacation"])
+ A `#` word followed by anything else is part of the entry, not a tag.
+*/
+fn split_tags(line: &str) -> (&str, Vec<String>) {
+    let is_separator = |c: char| c.is_whitespace() || c == ',';
+    let mut rest = line.trim_end();
+    let mut tags = Vec::new();
+    loop {
+        let start = rest.rfind(is_separator).map(|i| i + 1).unwrap_or(0);
+        let word = &rest[start..];
+        let Some(tag) = word.strip_prefix('#').and_then(normalize_tag_name) else {
+            break;
+        };
+        if start == 0 {
+            // A line made of tags only has no entry left to tag
+            break;
+        }
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+        rest = rest[..start].trim_end_matches(is_separator);
     }
+    tags.reverse();
+    (rest, tags)
+}
+
+// " #bali #vacation" for the entry summary, empty without tags
+pub fn format_tags(tags: &[String]) -> String {
+    tags.iter().map(|tag| format!(" #{}", tag)).collect()
 }
 
 impl Command for ExpenseCommand {
@@ -217,11 +675,52 @@ impl Command for ExpenseCommand {
     fn get_instruction_text_key() -> &'static str {
         "MESSENGER__EXPENSE_SHORT_INSTRUCTION"
     }
+
+    fn get_help_text_key() -> Option<&'static str> {
+        Some("MESSENGER__ENTRY_HELP")
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_conversation_message() {
+        let message = conversation_message("Nasi Padang", dec!(25000), " Makanan #lunch ");
+        let command = ExpenseCommand::parse_command(&message).unwrap();
+        assert_eq!(command.entries.len(), 1);
+        assert_eq!(command.entries[0].name, "Nasi Padang");
+        assert_eq!(command.entries[0].price, dec!(25000));
+        assert_eq!(
+            command.entries[0].category_or_alias.as_deref(),
+            Some("Makanan")
+        );
+        assert_eq!(command.entries[0].tags, vec!["lunch".to_string()]);
+
+        let command =
+            ExpenseCommand::parse_command(&conversation_message("Kopi", dec!(20000), "-")).unwrap();
+        assert_eq!(command.entries[0].category_or_alias, None);
+    }
+
+    #[test]
+    fn test_natural_message() {
+        let message = natural_message("Nasi Padang 25000 #lunch\nKopi,20000,Minuman").unwrap();
+        let command = ExpenseCommand::parse_command(&message).unwrap();
+        assert_eq!(command.entries.len(), 2);
+        assert_eq!(command.entries[0].name, "Nasi Padang");
+        assert_eq!(command.entries[0].price, dec!(25000));
+        assert_eq!(command.entries[0].tags, vec!["lunch".to_string()]);
+        assert_eq!(
+            command.entries[1].category_or_alias.as_deref(),
+            Some("Minuman")
+        );
+
+        assert_eq!(natural_message("good morning everyone"), None);
+        assert_eq!(natural_message("Nasi Padang 25000\nsee you"), None);
+        assert_eq!(natural_message("25000"), None);
+    }
 
     #[test]
     fn test_parse_string() {
@@ -237,24 +736,109 @@ mod tests {
         assert_eq!(entries.entries.len(), 2);
         assert_eq!(entries.fail_entries.len(), 3);
         assert_eq!(entries.entries[0].name, "Nasi Padang");
-        assert_eq!(entries.entries[0].price, 10000.0);
+        assert_eq!(entries.entries[0].price, dec!(10000));
         assert_eq!(
             entries.entries[0].category_or_alias.as_deref(),
             Some("Makanan")
         );
         assert_eq!(entries.entries[1].name, "Warteg");
-        assert_eq!(entries.entries[1].price, 15000.0);
+        assert_eq!(entries.entries[1].price, dec!(15000));
         assert_eq!(entries.entries[1].category_or_alias, None);
 
+        assert!(entries.entries[0].tags.is_empty());
+
         let input2 = "/expense Nasi Goreng,20000,Makanan";
         let entries2 = ExpenseCommand::parse_command(input2).unwrap();
         assert_eq!(entries2.entries.len(), 1);
         assert_eq!(entries2.fail_entries.len(), 0);
         assert_eq!(entries2.entries[0].name, "Nasi Goreng");
-        assert_eq!(entries2.entries[0].price, 20000.0);
+        assert_eq!(entries2.entries[0].price, dec!(20000));
         assert_eq!(
             entries2.entries[0].category_or_alias.as_deref(),
             Some("Makanan")
         );
     }
+
+    #[test]
+    fn test_parse_tags() {
+        let input = "/expense
+        Nasi Padang,10000,Makanan #Bali #vacation
+        Warteg,15000 #bali
+        Kopi,20000,#vacation
+        Burger #1,25000
+        ";
+
+        let entries = ExpenseCommand::parse_command(input).unwrap();
+        assert_eq!(entries.entries.len(), 4);
+        assert_eq!(
+            entries.entries[0].category_or_alias.as_deref(),
+            Some("Makanan")
+        );
+        assert_eq!(entries.entries[0].tags, vec!["bali", "vacation"]);
+        assert_eq!(entries.entries[1].price, dec!(15000));
+        assert_eq!(entries.entries[1].tags, vec!["bali"]);
+        assert_eq!(entries.entries[2].category_or_alias, None);
+        assert_eq!(entries.entries[2].tags, vec!["vacation"]);
+        // Only trailing words are tags
+        assert_eq!(entries.entries[3].name, "Burger #1");
+        assert!(entries.entries[3].tags.is_empty());
+    }
+
+    #[test]
+    fn test_parse_date() {
+        let input = "/expense
+        Nasi Padang,10000,Makanan,2025-09-01
+        Warteg,15000,2025-09-02 #bali
+        Kopi,20000,Minuman
+        Roti,5000,Makanan,2999-01-01
+        ";
+
+        let entries = ExpenseCommand::parse_command(input).unwrap();
+        assert_eq!(entries.entries.len(), 3);
+        assert_eq!(
+            entries.entries[0].category_or_alias.as_deref(),
+            Some("Makanan")
+        );
+        assert_eq!(entries.entries[0].date, NaiveDate::from_ymd_opt(2025, 9, 1));
+        assert_eq!(entries.entries[1].category_or_alias, None);
+        assert_eq!(entries.entries[1].date, NaiveDate::from_ymd_opt(2025, 9, 2));
+        assert_eq!(entries.entries[1].tags, vec!["bali"]);
+        assert_eq!(entries.entries[2].date, None);
+        // Expenses cannot be logged ahead
+        assert_eq!(entries.fail_entries.len(), 1);
+    }
+
+    #[test]
+    fn test_category_buttons() {
+        let entry_uid = Uuid::new_v4();
+        let category = |name: &str| Category {
+            uid: Uuid::new_v4(),
+            group_uid: Uuid::new_v4(),
+            name: name.to_string(),
+            description: None,
+            parent_uid: None,
+            icon: "🏷️".to_string(),
+            color: "#64748b".to_string(),
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        };
+        let transport = category("Transportasi");
+        let buttons = category_buttons(
+            entry_uid,
+            vec![transport.clone(), category("makanan"), category("Jajan")],
+        );
+
+        let labels: Vec<Vec<&str>> = buttons
+            .iter()
+            .map(|row| row.iter().map(|button| button.label.as_str()).collect())
+            .collect();
+        assert_eq!(labels, vec![vec!["Jajan", "makanan"], vec!["Transportasi"]]);
+        assert_eq!(
+            CallbackAction::decode(&buttons[1][0].data),
+            Some(CallbackAction::SetCategory {
+                entry_uid,
+                category_uid: transport.uid,
+            })
+        );
+    }
 }
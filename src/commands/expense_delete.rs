@@ -0,0 +1,306 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use uuid::Uuid;
+
+use crate::{
+    commands::{
+        base::{ChatButton, ChatReply, ChatSender, Command},
+        callback::CallbackAction,
+        undo::UndoCommand,
+    },
+    lang::Lang,
+    repos::{
+        audit_log::{AuditActor, AuditChange, AuditEntity, AuditRepo},
+        chat_action::ChatAction,
+        chat_binding::ChatBinding,
+        expense_entry::{ExpenseEntry, ExpenseEntryRepo},
+    },
+    utils::parse_price::format_price_in,
+    webhooks::{self, WebhookEvent},
+};
+
+#[derive(Debug)]
+pub struct ExpenseDeleteCommand {
+    pub ids: Vec<Uuid>,
+}
+
+impl ExpenseDeleteCommand {
+    /*
+     Expected format:
+     /expense-delete
+     [id] - UUID of the expense entry to delete, one per line
+
+     Examples:
+     /expense-delete
+     123e4567-e89b-12d3-a456-426614174000
+     123e4567-e89b-12d3-a456-426614174001
+    */
+    fn parse_command(input: &str) -> Result<Self> {
+        let input = input.trim();
+
+        // Should start with /expense-delete
+        let input = if input.starts_with(Self::get_command()) {
+            input[Self::get_command().len()..].trim()
+        } else {
+            input
+        };
+
+        let mut ids = Vec::new();
+        for id_str in input.split_whitespace() {
+            let id = Uuid::parse_str(id_str)
+                .map_err(|_| anyhow::anyhow!("Invalid UUID format: {}", id_str))?;
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+
+        if ids.is_empty() {
+            return Err(anyhow::anyhow!("No expense IDs found"));
+        }
+
+        Ok(Self { ids })
+    }
+
+    // The ids listed in a confirmation message, other words are skipped
+    fn parse_confirmation(message: &str) -> Vec<Uuid> {
+        let mut ids = Vec::new();
+        for id in message.split_whitespace().filter_map(|w| Uuid::parse_str(w).ok()) {
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+        ids
+    }
+
+    /*
+       Output format:
+       🗑️ Pengeluaran berhasil dihapus: (can be found on lang/id.json)
+
+       <id>
+       <name>, Rp. <price>
+
+       With `buttons`, the entries are listed for confirmation instead, in the same format
+       with confirm and cancel buttons. Confirming deletes the entries listed in the message,
+       see `confirm`.
+    */
+    pub async fn run(
+        raw_message: &str,
+        binding: &ChatBinding,
+        sender: &ChatSender,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        lang: &Lang,
+        buttons: bool,
+    ) -> Result<ChatReply> {
+        let command = Self::parse_command(raw_message)?;
+        if !buttons {
+            return Ok(Self::delete(&command.ids, binding, sender, tx, lang)
+                .await?
+                .into());
+        }
+
+        let expenses = Self::get_entries(&command.ids, binding, tx).await?;
+        let mut response = lang.get("MESSENGER__ENTRY_DELETE_CONFIRM_HEADER");
+        for expense in expenses {
+            response.push_str(&Self::format_entry(expense, lang));
+        }
+        Ok(ChatReply::from(response).with_buttons(vec![vec![
+            ChatButton::new(
+                lang.get("MESSENGER__ENTRY_DELETE_CONFIRM"),
+                &CallbackAction::ConfirmDelete,
+            ),
+            ChatButton::new(
+                lang.get("MESSENGER__ENTRY_DELETE_CANCEL"),
+                &CallbackAction::CancelDelete,
+            ),
+        ]]))
+    }
+
+    // Deletes the entries listed in the confirmation message the button was under
+    pub async fn confirm(
+        message: &str,
+        binding: &ChatBinding,
+        sender: &ChatSender,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        lang: &Lang,
+    ) -> Result<ChatReply> {
+        let ids = Self::parse_confirmation(message);
+        if ids.is_empty() {
+            return Err(anyhow::anyhow!("No expense IDs found"));
+        }
+        Ok(Self::delete(&ids, binding, sender, tx, lang).await?.into())
+    }
+
+    // Verifies every entry belongs to this chat's group before touching any of them
+    async fn get_entries(
+        ids: &[Uuid],
+        binding: &ChatBinding,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<Vec<ExpenseEntry>> {
+        let mut expenses = Vec::new();
+        for id in ids.iter() {
+            let expense = ExpenseEntryRepo::get(tx, *id)
+                .await
+                .map_err(|_| anyhow::anyhow!("Expense entry not found: {}", id))?;
+            if expense.group_uid != binding.group_uid {
+                return Err(anyhow::anyhow!("Expense entry not found: {}", id));
+            }
+            expenses.push(expense);
+        }
+        Ok(expenses)
+    }
+
+    async fn delete(
+        ids: &[Uuid],
+        binding: &ChatBinding,
+        sender: &ChatSender,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        lang: &Lang,
+    ) -> Result<String> {
+        let actor = AuditActor::from_chat(binding, sender);
+        Self::get_entries(ids, binding, tx).await?;
+
+        let mut response = String::new();
+        response.push_str(&lang.get("MESSENGER__ENTRY_DELETE_SUCCESS_HEADER"));
+
+        for id in ids.iter() {
+            let expense = ExpenseEntryRepo::soft_delete(tx, *id).await?;
+            AuditRepo::record(
+                tx,
+                &actor,
+                AuditEntity::ExpenseEntry,
+                binding.group_uid,
+                expense.uid,
+                AuditChange::delete(&expense),
+            )
+            .await?;
+            webhooks::dispatch(
+                tx,
+                binding.group_uid,
+                WebhookEvent::ExpenseDeleted,
+                &expense,
+            )
+            .await?;
+
+            response.push_str(&Self::format_entry(expense, lang));
+        }
+        UndoCommand::journal(
+            binding,
+            sender,
+            tx,
+            &ChatAction::ExpenseDelete {
+                entry_uids: ids.to_vec(),
+            },
+        )
+        .await?;
+
+        Ok(response)
+    }
+
+    fn format_entry(expense: ExpenseEntry, lang: &Lang) -> String {
+        lang.get_with_vars(
+            "MESSENGER__ENTRY_SUCCESS_DELETE_ENTRY",
+            HashMap::from([
+                ("id".to_string(), expense.uid.to_string()),
+                ("item".to_string(), expense.product),
+                ("price".to_string(), format_price_in(expense.price, &expense.currency)),
+            ]),
+        )
+    }
+}
+
+impl Command for ExpenseDeleteCommand {
+    fn get_command() -> &'static str {
+        "/expense-delete"
+    }
+
+    fn get_instruction_text_key() -> &'static str {
+        "MESSENGER__EXPENSE_DELETE_SHORT_INSTRUCTION"
+    }
+
+    fn get_help_text_key() -> Option<&'static str> {
+        Some("MESSENGER__EXPENSE_DELETE_HELP")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_command() {
+        let input = "/expense-delete
+44444444-4444-4444-4444-000000000002
+
+44444444-4444-4444-4444-000000000003
+";
+
+        let command = ExpenseDeleteCommand::parse_command(input).unwrap();
+
+        assert_eq!(command.ids.len(), 2);
+        assert_eq!(
+            command.ids[0].to_string(),
+            "44444444-4444-4444-4444-000000000002"
+        );
+        assert_eq!(
+            command.ids[1].to_string(),
+            "44444444-4444-4444-4444-000000000003"
+        );
+    }
+
+    #[test]
+    fn test_parse_command_single_line() {
+        let input = "/expense-delete 44444444-4444-4444-4444-000000000002";
+
+        let command = ExpenseDeleteCommand::parse_command(input).unwrap();
+
+        assert_eq!(command.ids.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_command_deduplicates() {
+        let input = "/expense-delete
+44444444-4444-4444-4444-000000000002
+44444444-4444-4444-4444-000000000002";
+
+        let command = ExpenseDeleteCommand::parse_command(input).unwrap();
+
+        assert_eq!(command.ids.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_confirmation() {
+        let message = "🗑️ Hapus pengeluaran berikut?
+
+44444444-4444-4444-4444-000000000002
+Nasi Padang, Rp 10.000
+
+44444444-4444-4444-4444-000000000003
+Warteg, Rp 15.000
+";
+
+        let ids = ExpenseDeleteCommand::parse_confirmation(message);
+
+        assert_eq!(
+            ids,
+            vec![
+                Uuid::parse_str("44444444-4444-4444-4444-000000000002").unwrap(),
+                Uuid::parse_str("44444444-4444-4444-4444-000000000003").unwrap(),
+            ]
+        );
+        assert!(ExpenseDeleteCommand::parse_confirmation("Dibatalkan").is_empty());
+    }
+
+    #[test]
+    fn test_parse_command_empty() {
+        assert!(ExpenseDeleteCommand::parse_command("/expense-delete").is_err());
+    }
+
+    #[test]
+    fn test_parse_command_invalid_uuid() {
+        let input = "/expense-delete
+invalid-uuid";
+
+        assert!(ExpenseDeleteCommand::parse_command(input).is_err());
+    }
+}
@@ -1,25 +1,60 @@
 use std::collections::HashMap;
 
 use anyhow::Result;
+use rust_decimal::Decimal;
 use uuid::Uuid;
 
 use crate::{
-    commands::base::Command,
+    commands::{
+        base::{ChatSender, Command},
+        expense::format_tags,
+        undo::UndoCommand,
+    },
+    error::DatabaseError,
     lang::Lang,
     repos::{
+        audit_log::{AuditActor, AuditChange, AuditEntity, AuditRepo},
         category::CategoryRepo,
         category_alias::CategoryAliasRepo,
+        chat_action::ChatAction,
         chat_binding::ChatBinding,
-        expense_entry::{ExpenseEntryRepo, UpdateExpenseEntryDbPayload},
+        expense_entry::{ExpenseEntry, ExpenseEntryRepo, UpdateExpenseEntryDbPayload},
+        product::ProductRepo,
+        tag::TagRepo,
     },
-    utils::parse_price::{format_price, parse_price},
+    utils::parse_price::{format_price_in, parse_price},
+    webhooks::{self, WebhookEvent},
 };
 
+/*
+ Reply for an edit that lost the race against someone else changing the same entry, e.g. from
+ the web app, showing the entry as it is now in the `/expense-edit` format so it can be copied
+*/
+pub fn edit_conflict_error(error: DatabaseError, lang: &Lang) -> anyhow::Error {
+    let DatabaseError::ConcurrentModification(current) = &error else {
+        return error.into();
+    };
+    let Ok(current) = serde_json::from_value::<ExpenseEntry>(current.clone()) else {
+        return error.into();
+    };
+    anyhow::anyhow!(lang.get_with_vars(
+        "MESSENGER__ENTRY_EDIT_CONFLICT",
+        HashMap::from([
+            ("id".to_string(), current.uid.to_string()),
+            ("item".to_string(), current.product),
+            (
+                "price".to_string(),
+                format_price_in(current.price, &current.currency),
+            ),
+        ]),
+    ))
+}
+
 #[derive(Debug)]
 pub struct ExpenseEditCommandEntry {
     pub id: Uuid,
     pub name: String,
-    pub price: f64,
+    pub price: Decimal,
     pub category_or_alias: Option<String>,
 }
 
@@ -117,10 +152,12 @@ impl ExpenseEditCommand {
     pub async fn run(
         raw_message: &str,
         binding: &ChatBinding,
+        sender: &ChatSender,
         tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
         lang: &Lang,
     ) -> Result<String> {
         let entries = Self::parse_command(raw_message)?;
+        let actor = AuditActor::from_chat(binding, sender);
 
         let categories = CategoryRepo::list_by_group(tx, binding.group_uid).await?;
         let aliases = CategoryAliasRepo::list_by_group(tx, binding.group_uid).await?;
@@ -136,6 +173,7 @@ impl ExpenseEditCommand {
 
         let mut response = String::new();
         response.push_str(&lang.get("MESSENGER__ENTRY_EDIT_SUCCESS_HEADER"));
+        let mut before = Vec::new();
 
         for entry in entries.iter() {
             let id = &entry.id;
@@ -145,17 +183,52 @@ impl ExpenseEditCommand {
                 None
             };
 
+            let prev_expense = ExpenseEntryRepo::get(tx, *id)
+                .await
+                .map_err(|_| anyhow::anyhow!("Expense entry not found: {}", id))?;
+            if prev_expense.group_uid != binding.group_uid {
+                return Err(anyhow::anyhow!("Expense entry not found: {}", id));
+            }
+
             // Update the expense entry
             let expense = ExpenseEntryRepo::update(
                 tx,
                 *id,
                 UpdateExpenseEntryDbPayload {
                     price: Some(entry.price),
+                    currency: None,
                     product: Some(entry.name.clone()),
                     category_uid,
+                    note: None,
+                    envelope_uid: None,
+                    expected_updated_at: Some(prev_expense.updated_at),
                 },
             )
+            .await
+            .map_err(|e| edit_conflict_error(e, lang))?;
+            AuditRepo::record(
+                tx,
+                &actor,
+                AuditEntity::ExpenseEntry,
+                binding.group_uid,
+                expense.uid,
+                AuditChange::update(&prev_expense, &expense),
+            )
+            .await?;
+            webhooks::dispatch(
+                tx,
+                binding.group_uid,
+                WebhookEvent::ExpenseUpdated,
+                &expense,
+            )
             .await?;
+            if let Some(category_uid) = category_uid {
+                ProductRepo::learn_category(tx, binding.group_uid, &expense.product, category_uid)
+                    .await?;
+            }
+            before.push(prev_expense);
+            // Editing keeps the entry's tags
+            let tags = TagRepo::list_names_for_entry(tx, expense.uid).await?;
 
             response.push_str(
                 &lang.get_with_vars(
@@ -163,20 +236,19 @@ impl ExpenseEditCommand {
                     HashMap::from([
                         ("id".to_string(), expense.uid.to_string()),
                         ("item".to_string(), expense.product),
-                        (
-                            "price".to_string(),
-                            format!("Rp. {}", format_price(expense.price)),
-                        ),
+                        ("price".to_string(), format_price_in(expense.price, &expense.currency)),
                         (
                             "category".to_string(),
                             category_uid
                                 .map(|uid| uid.to_string())
                                 .unwrap_or_else(|| "Uncategorized".to_string()),
                         ),
+                        ("tags".to_string(), format_tags(&tags)),
                     ]),
                 ),
             );
         }
+        UndoCommand::journal(binding, sender, tx, &ChatAction::ExpenseEdit { before }).await?;
 
         Ok(response)
     }
@@ -190,11 +262,16 @@ impl Command for ExpenseEditCommand {
     fn get_instruction_text_key() -> &'static str {
         "MESSENGER__EXPENSE_EDIT_SHORT_INSTRUCTION"
     }
+
+    fn get_help_text_key() -> Option<&'static str> {
+        Some("MESSENGER__EXPENSE_EDIT_HELP")
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rust_decimal_macros::dec;
 
     #[test]
     fn test_parse_command() {
@@ -218,7 +295,7 @@ Bakso,20000,Food
             "44444444-4444-4444-4444-000000000002"
         );
         assert_eq!(entries[0].name, "Nasi Padang");
-        assert_eq!(entries[0].price, 10000.0);
+        assert_eq!(entries[0].price, dec!(10000));
         assert_eq!(entries[0].category_or_alias.as_deref(), Some("Makanan"));
 
         assert_eq!(
@@ -226,7 +303,7 @@ Bakso,20000,Food
             "44444444-4444-4444-4444-000000000003"
         );
         assert_eq!(entries[1].name, "Warteg");
-        assert_eq!(entries[1].price, 15000.0);
+        assert_eq!(entries[1].price, dec!(15000));
         assert_eq!(entries[1].category_or_alias, None);
 
         assert_eq!(
@@ -234,7 +311,7 @@ Bakso,20000,Food
             "44444444-4444-4444-4444-000000000004"
         );
         assert_eq!(entries[2].name, "Bakso");
-        assert_eq!(entries[2].price, 20000.0);
+        assert_eq!(entries[2].price, dec!(20000));
         assert_eq!(entries[2].category_or_alias.as_deref(), Some("Food"));
     }
 
@@ -246,6 +323,32 @@ Bakso,20000,Food
         assert!(ExpenseEditCommand::parse_command(input).is_err());
     }
 
+    #[test]
+    fn test_edit_conflict_error() {
+        let lang = Lang::from_json("en");
+        let current = serde_json::json!({
+            "uid": "44444444-4444-4444-4444-000000000002",
+            "price": "15000",
+            "currency": "IDR",
+            "product": "Nasi Padang",
+            "created_by": "web",
+            "created_by_user_uid": null,
+            "group_uid": "44444444-4444-4444-4444-000000000001",
+            "category_uid": null,
+            "created_at": "2025-10-01T10:00:00Z",
+            "updated_at": "2025-10-01T10:05:00Z",
+            "note": null,
+            "receipt_url": null,
+            "envelope_uid": null,
+        });
+        let message =
+            edit_conflict_error(DatabaseError::ConcurrentModification(current), &lang).to_string();
+        assert!(message.contains("44444444-4444-4444-4444-000000000002\nNasi Padang, "));
+
+        let other = edit_conflict_error(DatabaseError::NotFound("expense".into()), &lang);
+        assert!(other.to_string().starts_with("Not found"));
+    }
+
     #[test]
     fn test_parse_command_invalid_uuid() {
         let input = "/expense-edit
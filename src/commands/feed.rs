@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use crate::{
+    commands::base::Command,
+    lang::Lang,
+    repos::chat_binding::ChatBinding,
+    utils::{
+        activity::{ActivityEvent, ActivityKind, group_activity},
+        parse_price::format_price_in,
+    },
+};
+
+// Events shown by /feed
+const FEED_SIZE: i64 = 10;
+
+pub struct FeedCommand;
+
+impl FeedCommand {
+    /*
+     Expected format:
+     /feed
+     -> the last 10 expenses added, edited or deleted, members joining or leaving and budget
+        changes of the group
+    */
+    fn parse_command(input: &str) -> Result<()> {
+        let args = input
+            .trim()
+            .strip_prefix(Self::get_command())
+            .ok_or_else(|| anyhow::anyhow!("Invalid format: expected /feed"))?;
+        if !args.trim().is_empty() {
+            return Err(anyhow::anyhow!("Invalid format: expected /feed"));
+        }
+        Ok(())
+    }
+
+    /*
+     Output format:
+     📰 Aktivitas terbaru: (can be found on lang/id.json)
+
+     16/10/2026 09:24 - Siti menambahkan Kopi, Rp. 25.000
+     16/10/2026 08:10 - budi mengubah budget Makanan menjadi Rp. 1.500.000
+    */
+    pub async fn run(
+        raw_message: &str,
+        binding: &ChatBinding,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        lang: &Lang,
+    ) -> Result<String> {
+        Self::parse_command(raw_message)?;
+
+        let (events, _) = group_activity(tx, binding.group_uid, FEED_SIZE, 0, None).await?;
+        if events.is_empty() {
+            return Ok(lang.get("MESSENGER__FEED_EMPTY"));
+        }
+
+        let mut response = lang.get("MESSENGER__FEED_HEADER");
+        for event in &events {
+            response.push_str(&lang.get_with_vars(
+                "MESSENGER__FEED_ITEM",
+                HashMap::from([
+                    (
+                        "date".to_string(),
+                        event.created_at.format("%d/%m/%Y %H:%M").to_string(),
+                    ),
+                    ("text".to_string(), Self::describe(event, lang)),
+                ]),
+            ));
+        }
+        Ok(response)
+    }
+
+    fn describe(event: &ActivityEvent, lang: &Lang) -> String {
+        let key = match ActivityKind::parse(&event.kind) {
+            Some(ActivityKind::ExpenseAdded) => "MESSENGER__FEED_EXPENSE_ADDED",
+            Some(ActivityKind::ExpenseEdited) if event.previous_amount.is_some() => {
+                "MESSENGER__FEED_EXPENSE_EDITED_AMOUNT"
+            }
+            Some(ActivityKind::ExpenseEdited) => "MESSENGER__FEED_EXPENSE_EDITED",
+            Some(ActivityKind::ExpenseDeleted) => "MESSENGER__FEED_EXPENSE_DELETED",
+            Some(ActivityKind::ExpenseRestored) => "MESSENGER__FEED_EXPENSE_RESTORED",
+            Some(ActivityKind::MemberJoined) => "MESSENGER__FEED_MEMBER_JOINED",
+            Some(ActivityKind::MemberRoleChanged) => "MESSENGER__FEED_MEMBER_ROLE_CHANGED",
+            Some(ActivityKind::MemberLeft) => "MESSENGER__FEED_MEMBER_LEFT",
+            Some(ActivityKind::BudgetAdded) => "MESSENGER__FEED_BUDGET_ADDED",
+            Some(ActivityKind::BudgetChanged) => "MESSENGER__FEED_BUDGET_CHANGED",
+            Some(ActivityKind::BudgetRemoved) | None => "MESSENGER__FEED_BUDGET_REMOVED",
+        };
+        let price = |amount| format_price_in(amount, event.currency.as_deref().unwrap_or_default());
+        lang.get_with_vars(
+            key,
+            HashMap::from([
+                (
+                    "actor".to_string(),
+                    event
+                        .actor_name
+                        .clone()
+                        .unwrap_or_else(|| lang.get("MESSENGER__FEED_SOMEONE")),
+                ),
+                (
+                    "subject".to_string(),
+                    event.subject.clone().unwrap_or_else(|| "-".to_string()),
+                ),
+                (
+                    "amount".to_string(),
+                    event.amount.map(price).unwrap_or_default(),
+                ),
+                (
+                    "previous_amount".to_string(),
+                    event.previous_amount.map(price).unwrap_or_default(),
+                ),
+                ("role".to_string(), event.role.clone().unwrap_or_default()),
+            ]),
+        )
+    }
+}
+
+impl Command for FeedCommand {
+    fn get_command() -> &'static str {
+        "/feed"
+    }
+
+    fn get_instruction_text_key() -> &'static str {
+        "MESSENGER__FEED_SHORT_INSTRUCTION"
+    }
+
+    fn get_help_text_key() -> Option<&'static str> {
+        Some("MESSENGER__FEED_HELP")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_command() {
+        assert!(FeedCommand::parse_command("/feed").is_ok());
+        assert!(FeedCommand::parse_command(" /feed  ").is_ok());
+        assert!(FeedCommand::parse_command("/feed 20").is_err());
+        assert!(FeedCommand::parse_command("/feeds").is_err());
+    }
+}
@@ -33,11 +33,16 @@ impl HelpCommand {
         Berikut adalah daftar perintah yang tersedia:
         1. /expense [nama],[harga],[kategori] - Menambahkan entri pengeluaran.
         2. /expense-edit [id] [nama],[harga],[kategori] - Mengedit entri pengeluaran.
-        3. /category [nama kategori]=[alias1, alias2, ...] - Menampilkan atau menambahkan kategori.
-        4. /category-edit [id] [nama kategori]=[alias1, alias2, ...] - Mengedit kategori.
-        5. /history (start_date) (end_date) - Menampilkan riwayat pengeluaran.
-        6. /report - Menampilkan laporan pengeluaran bulanan.
-        7. /help - Menampilkan daftar perintah yang tersedia.
+        3. /expense-delete [id] - Menghapus entri pengeluaran.
+        4. /category [nama kategori]=[alias1, alias2, ...] - Menampilkan atau menambahkan kategori.
+        5. /category-edit [id] [nama kategori]=[alias1, alias2, ...] - Mengedit kategori.
+        6. /history (start_date) (end_date) - Menampilkan riwayat pengeluaran.
+        7. /report (last | YYYY-MM | start_date end_date) - Menampilkan laporan pengeluaran bulanan.
+        8. /link-me [kode] - Menghubungkan akun Anda di chat grup.
+        9. /switch [nama grup] - Menampilkan atau mengganti grup aktif chat ini.
+        10. /lang [id|en] - Menampilkan atau mengganti bahasa grup.
+        11. /logout - Memutus chat ini dari grup.
+        12. /help - Menampilkan daftar perintah yang tersedia.
         Gunakan perintah di atas untuk mengelola pengeluaran Anda dengan mudah!
 
         Untuk bantuan lebih lanjut, hubungi admin @mustafamilyas
@@ -74,12 +79,28 @@ impl HelpCommand {
         let commands = vec![
             "MESSENGER__EXPENSE_SHORT_INSTRUCTION",
             "MESSENGER__EXPENSE_EDIT_SHORT_INSTRUCTION",
+            "MESSENGER__EXPENSE_DELETE_SHORT_INSTRUCTION",
+            "MESSENGER__CONFIRM_SHORT_INSTRUCTION",
+            "MESSENGER__UNDO_SHORT_INSTRUCTION",
+            "MESSENGER__CANCEL_SHORT_INSTRUCTION",
+            "MESSENGER__INCOME_SHORT_INSTRUCTION",
+            "MESSENGER__RECURRING_SHORT_INSTRUCTION",
+            "MESSENGER__TRIP_SHORT_INSTRUCTION",
+            "MESSENGER__DEBT_SHORT_INSTRUCTION",
+            "MESSENGER__SETTLE_SHORT_INSTRUCTION",
             "MESSENGER__BUDGET_SHORT_INSTRUCTION",
             "MESSENGER__BUDGET_EDIT_SHORT_INSTRUCTION",
             "MESSENGER__CATEGORY_SHORT_INSTRUCTION",
             "MESSENGER__CATEGORY_EDIT_SHORT_INSTRUCTION",
+            "MESSENGER__CATEGORY_DELETE_SHORT_INSTRUCTION",
             "MESSENGER__HISTORY_SHORT_INSTRUCTION",
+            "MESSENGER__SEARCH_SHORT_INSTRUCTION",
+            "MESSENGER__FEED_SHORT_INSTRUCTION",
             "MESSENGER__REPORT_SHORT_INSTRUCTION",
+            "MESSENGER__LINK_ME_SHORT_INSTRUCTION",
+            "MESSENGER__SWITCH_SHORT_INSTRUCTION",
+            "MESSENGER__LANG_SHORT_INSTRUCTION",
+            "MESSENGER__LOGOUT_SHORT_INSTRUCTION",
             "MESSENGER__HELP_SHORT_INSTRUCTION",
         ];
 
@@ -1,38 +1,85 @@
+use std::collections::HashMap;
+
 use anyhow::Result;
-use chrono::{Datelike, Duration, NaiveDate, Utc};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use rust_decimal::Decimal;
 use sqlx::Row;
 use tracing::info;
 
+use uuid::Uuid;
+
 use crate::{
-    commands::base::Command,
+    commands::{
+        base::{ChatButton, ChatReply, Command},
+        callback::{CallbackAction, MAX_CALLBACK_DATA_LENGTH},
+    },
     lang::Lang,
     repos::{
-        chat_binding::ChatBinding, expense_group::ExpenseGroupRepo,
-        expense_group_member::GroupMemberRepo, user::UserRepo,
+        category::CategoryRepo, category_alias::CategoryAliasRepo, chat_binding::ChatBinding,
+        exchange_rate::ExchangeRateRepo, expense_entry::creator_name,
+        expense_group::ExpenseGroupRepo, expense_group_member::GroupMemberRepo,
+        group_settings::GroupSettingsRepo, user::UserRepo,
+    },
+    utils::{
+        parse_price::{format_price_in, parse_price},
+        pii,
     },
-    utils::parse_price::format_price,
 };
 
+// Entries per page when the chat can page through them with buttons
+const HISTORY_PAGE_SIZE: usize = 10;
+
+// Keys of the category filter, `kategori=Makanan` or `category=Makanan`
+const CATEGORY_FILTER_KEYS: [&str; 2] = ["kategori=", "category="];
+
 #[derive(Debug)]
 pub struct HistoryCommand {
     pub start_date: Option<chrono::NaiveDate>,
     pub end_date: Option<chrono::NaiveDate>,
+    // Category name or alias, resolved when the command runs
+    pub category: Option<String>,
+    // Lowercase, without the leading @
+    pub member: Option<String>,
+    // Exclusive bounds, in each entry's own currency
+    pub min_price: Option<Decimal>,
+    pub max_price: Option<Decimal>,
+}
+
+/*
+ Which entries of the range /history shows, kept in the page buttons so every page is
+ filtered the same way.
+*/
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HistoryFilter {
+    pub category_uid: Option<Uuid>,
+    // Matches the name entries are shown with, or its first word
+    pub member: Option<String>,
+    pub min_price: Option<Decimal>,
+    pub max_price: Option<Decimal>,
 }
 
 impl HistoryCommand {
     /*
         Should be in format:
-        /history (start_date) (end_date)
+        /history (start_date) (end_date) (filters)
 
         Both dates are optional, if not provided, will default to last 3 days
         If only one date is provided, will use that date as start_date and end_date
         Dates should be in format YYYY-MM-DD
         The maximum range is 3 days
 
+        Filters can be combined and given in any order:
+        kategori=<name or alias>   only entries of this category
+        @<member>                  only entries added by this member
+        ><price>                   only entries above this price
+        <<price>                   only entries below this price
+
         Examples:
         /history
         /history 2023-01-01
         /history 2023-01-01 2023-01-31
+        /history kategori=Makanan
+        /history @budi >50000
     */
     fn parse_command(input: &str) -> Result<Self> {
         let input = input.trim();
@@ -44,16 +91,46 @@ impl HistoryCommand {
             input
         };
 
-        let parts: Vec<&str> = input.split_whitespace().collect();
-        let now = Utc::now().date_naive();
+        let mut category = None;
+        let mut member = None;
+        let mut min_price = None;
+        let mut max_price = None;
+        let mut parts: Vec<&str> = Vec::new();
+        for part in input.split_whitespace() {
+            let key = CATEGORY_FILTER_KEYS
+                .iter()
+                .find(|key| part.to_lowercase().starts_with(*key));
+            if let Some(key) = key {
+                let name = &part[key.len()..];
+                if name.is_empty() {
+                    return Err(anyhow::anyhow!("Missing category name after {}", key));
+                }
+                category = Some(name.to_string());
+            } else if let Some(name) = part.strip_prefix('@') {
+                if name.is_empty() {
+                    return Err(anyhow::anyhow!("Missing member name after @"));
+                }
+                member = Some(name.to_lowercase());
+            } else if let Some(price) = part.strip_prefix('>') {
+                min_price = Some(parse_price(price)?);
+            } else if let Some(price) = part.strip_prefix('<') {
+                max_price = Some(parse_price(price)?);
+            } else {
+                parts.push(part);
+            }
+        }
+        if min_price
+            .zip(max_price)
+            .is_some_and(|(min_price, max_price)| min_price >= max_price)
+        {
+            return Err(anyhow::anyhow!(
+                "The minimum price must be below the maximum price"
+            ));
+        }
 
         let (start_date, end_date) = match parts.len() {
-            0 => {
-                // Default to last 3 days
-                let end_date = now;
-                let start_date = end_date - Duration::days(3);
-                (Some(start_date), Some(end_date))
-            }
+            // The group's `history_days` up to today, see `run`
+            0 => (None, None),
             1 => {
                 // Single date provided - use as both start and end
                 let date_str = parts[0];
@@ -95,7 +172,7 @@ impl HistoryCommand {
             }
             _ => {
                 return Err(anyhow::anyhow!(
-                    "Invalid format. Use: /history [start_date] [end_date] (dates in YYYY-MM-DD format)"
+                    "Invalid format. Use: /history [start_date] [end_date] [filters] (dates in YYYY-MM-DD format)"
                 ));
             }
         };
@@ -103,6 +180,10 @@ impl HistoryCommand {
         Ok(Self {
             start_date,
             end_date,
+            category,
+            member,
+            min_price,
+            max_price,
         })
     }
 
@@ -118,6 +199,9 @@ impl HistoryCommand {
 
         Total: Rp. [total]
 
+        Groups with more than one member also show who added each entry:
+        [item], Rp. [price], ([category]) - oleh [name]
+
         If no expenses found, return "Tidak ada pengeluaran dalam periode ini."
 
         Example:
@@ -132,6 +216,11 @@ impl HistoryCommand {
         Ojek Online, Rp. 50000, (Transportasi)
 
         Total: Rp. 115000
+
+        Filters add a line under the header, e.g. `Filter: Makanan, @budi, > Rp. 50.000`.
+
+        With `buttons`, the entries are shown a page at a time with previous and next buttons,
+        the total still covers the whole range.
     */
 
     pub async fn run(
@@ -139,126 +228,323 @@ impl HistoryCommand {
         binding: &ChatBinding,
         tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
         lang: &Lang,
-    ) -> Result<String> {
+        buttons: bool,
+    ) -> Result<ChatReply> {
         let command = Self::parse_command(raw_message)?;
 
-        // Get the expense group to determine the date range
-        let group = ExpenseGroupRepo::get(tx, binding.group_uid).await?;
-
-        let (default_start, default_end) = Self::calculate_month_range(group.start_over_date);
+        let category_uid = match &command.category {
+            Some(name) => {
+                let Some(uid) = Self::find_category(tx, binding, name).await? else {
+                    return Ok(lang
+                        .get_with_vars(
+                            "HISTORY__UNKNOWN_CATEGORY",
+                            HashMap::from([("category".to_string(), name.clone())]),
+                        )
+                        .into());
+                };
+                Some(uid)
+            }
+            None => None,
+        };
+        let filter = HistoryFilter {
+            category_uid,
+            member: command.member,
+            min_price: command.min_price,
+            max_price: command.max_price,
+        };
 
-        // Use provided dates or fall back to monthly range
+        // Without dates, the last `history_days` of the group
+        let settings = GroupSettingsRepo::get(tx, binding.group_uid).await?;
+        let today = Utc::now().date_naive();
         let start_date = command
             .start_date
-            .map(|d| d.and_hms_opt(0, 0, 0).unwrap().and_utc())
-            .unwrap_or(default_start);
+            .unwrap_or(today - Duration::days(settings.history_days as i64))
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+            .and_utc();
         let end_date = command
             .end_date
-            .map(|d| d.and_hms_opt(23, 59, 59).unwrap().and_utc())
-            .unwrap_or(default_end);
+            .unwrap_or(today)
+            .and_hms_opt(23, 59, 59)
+            .unwrap()
+            .and_utc();
+
+        // Filters too long for the buttons' callback data show every entry at once instead
+        let pageable = fits_in_buttons(start_date.date_naive(), end_date.date_naive(), &filter);
+        let page = if buttons && pageable { Some(0) } else { None };
+        Self::history(start_date, end_date, &filter, page, binding, tx, lang).await
+    }
+
+    // Category of the group with this name or alias, ignoring case
+    async fn find_category(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        binding: &ChatBinding,
+        name: &str,
+    ) -> Result<Option<Uuid>> {
+        let name = name.to_lowercase();
+        let categories = CategoryRepo::list_by_group(tx, binding.group_uid).await?;
+        if let Some(category) = categories.iter().find(|c| c.name.to_lowercase() == name) {
+            return Ok(Some(category.uid));
+        }
+        let aliases = CategoryAliasRepo::list_by_group(tx, binding.group_uid).await?;
+        Ok(aliases
+            .into_iter()
+            .find(|alias| alias.alias.to_lowercase() == name)
+            .map(|alias| alias.category_uid))
+    }
+
+    // Shows the page picked with the buttons under an earlier /history reply
+    pub async fn page(
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+        filter: &HistoryFilter,
+        page: u32,
+        binding: &ChatBinding,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        lang: &Lang,
+    ) -> Result<ChatReply> {
+        Self::history(
+            start_date.and_hms_opt(0, 0, 0).unwrap().and_utc(),
+            end_date.and_hms_opt(23, 59, 59).unwrap().and_utc(),
+            filter,
+            Some(page as usize),
+            binding,
+            tx,
+            lang,
+        )
+        .await
+    }
+
+    // Shows every entry in the range when there is no page
+    async fn history(
+        start_date: DateTime<Utc>,
+        end_date: DateTime<Utc>,
+        filter: &HistoryFilter,
+        page: Option<usize>,
+        binding: &ChatBinding,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        lang: &Lang,
+    ) -> Result<ChatReply> {
+        let group = ExpenseGroupRepo::get(tx, binding.group_uid).await?;
 
         info!(
             "Fetching history for group {} from {} to {}",
             binding.group_uid, start_date, end_date
         );
 
-        // Query all expenses for the group in the specified date range
+        // Emails may be encrypted, so linked members are found by theirs before the query
+        let member_uids = match &filter.member {
+            Some(member) => UserRepo::list_creators_named(tx, binding.group_uid, member).await?,
+            None => Vec::new(),
+        };
+
+        // Query all expenses for the group in the specified date range, matching the filter
         let expenses = sqlx::query(
             r#"
-            SELECT e.uid, e.price::float8 AS price, e.product, e.created_at, c.name as category_name
+            SELECT e.uid, e.price, e.currency, e.product, e.created_at, c.name as category_name,
+                   e.created_by, u.email AS creator_email
             FROM expense_entries e
             LEFT JOIN categories c ON e.category_uid = c.uid
+            LEFT JOIN users u ON e.created_by_user_uid = u.uid
             WHERE e.group_uid = $1
+              AND e.deleted_at IS NULL
               AND e.created_at >= $2
               AND e.created_at < $3
+              AND ($4::uuid IS NULL OR e.category_uid = $4)
+              AND ($5::text IS NULL
+                   OR e.created_by_user_uid = ANY($8)
+                   OR lower(e.created_by) = $5
+                   OR split_part(lower(e.created_by), ' ', 1) = $5)
+              AND ($6::numeric IS NULL OR e.price > $6)
+              AND ($7::numeric IS NULL OR e.price < $7)
             ORDER BY e.created_at DESC
             "#,
         )
         .bind(binding.group_uid)
         .bind(start_date)
         .bind(end_date)
+        .bind(filter.category_uid)
+        .bind(filter.member.as_deref())
+        .bind(filter.min_price)
+        .bind(filter.max_price)
+        .bind(&member_uids)
         .fetch_all(tx.as_mut())
         .await?;
 
         if expenses.is_empty() {
-            return Ok(lang.get("REPORT__NO_EXPENSES"));
+            return Ok(lang.get("REPORT__NO_EXPENSES").into());
         }
 
-        // Calculate total
-        let mut total_expenses = 0.0;
+        // Only worth showing who added what when several people share the group
+        let show_creator = GroupMemberRepo::count_by_group(tx, binding.group_uid).await? > 1;
+
+        // Calculate total in the group's currency
+        let mut currency_totals: HashMap<String, Decimal> = HashMap::new();
         for row in &expenses {
-            total_expenses += row.get::<f64, _>("price");
+            *currency_totals.entry(row.get("currency")).or_default() += row.get::<Decimal, _>("price");
         }
+        let rates = ExchangeRateRepo::rate_table(tx).await?;
+        let (total_expenses, unconverted) = rates.sum_in(
+            &currency_totals.into_iter().collect::<Vec<_>>(),
+            &group.currency,
+        );
 
         // Format the response
         let start_date_str = start_date.format("%d/%m/%Y").to_string();
         let end_date_str = end_date.format("%d/%m/%Y").to_string();
 
-        let mut response = format!("Pengeluaran {} -> {}:\n\n", start_date_str, end_date_str);
+        let mut response = format!("Pengeluaran {} -> {}:\n", start_date_str, end_date_str);
+        if let Some(filters) = Self::describe_filter(tx, filter, &group.currency).await? {
+            response.push_str(&lang.get_with_vars(
+                "HISTORY__FILTERED",
+                HashMap::from([("filters".to_string(), filters)]),
+            ));
+        }
+        response.push('\n');
+
+        let pages = expenses.len().div_ceil(HISTORY_PAGE_SIZE);
+        // Entries may have been deleted since the buttons were sent
+        let page = page.map(|page| page.min(pages - 1));
+        let shown = match page {
+            Some(page) => expenses
+                .into_iter()
+                .skip(page * HISTORY_PAGE_SIZE)
+                .take(HISTORY_PAGE_SIZE)
+                .collect(),
+            None => expenses,
+        };
 
-        for row in expenses {
+        for row in shown {
             let uid: uuid::Uuid = row.get("uid");
-            let price: f64 = row.get("price");
+            let price: Decimal = row.get("price");
+            let currency: String = row.get("currency");
             let product: String = row.get("product");
             let created_at: chrono::DateTime<Utc> = row.get("created_at");
             let category_name: Option<String> = row.get("category_name");
+            let created_by: String = row.get("created_by");
+            let creator_email: Option<String> = row.get("creator_email");
+            let creator_email = creator_email
+                .map(|email| pii::decrypt(&email))
+                .transpose()?;
 
             let category = category_name.unwrap_or_else(|| lang.get("REPORT__UNCATEGORIZED"));
             let date_str = created_at.format("%d/%m/%Y %H:%M").to_string();
+            let creator = creator_name(&created_by, creator_email.as_deref())
+                .filter(|_| show_creator)
+                .map(|name| {
+                    lang.get_with_vars(
+                        "HISTORY__CREATED_BY",
+                        HashMap::from([("name".to_string(), name)]),
+                    )
+                })
+                .unwrap_or_default();
 
             response.push_str(&format!(
-                "{} {}\n{}, Rp. {}, ({})\n\n",
+                "{} {}\n{}, {}, ({}){}\n\n",
                 date_str,
                 uid,
                 product,
-                format_price(price),
-                category
+                format_price_in(price, &currency),
+                category,
+                creator
             ));
         }
 
-        response.push_str(&format!("Total: Rp. {}", format_price(total_expenses)));
+        response.push_str(&format!(
+            "Total: {}",
+            format_price_in(total_expenses, &group.currency)
+        ));
+        if !unconverted.is_empty() {
+            response.push_str(&lang.get_with_vars(
+                "REPORT__UNCONVERTED",
+                HashMap::from([("currencies".to_string(), unconverted.join(", "))]),
+            ));
+        }
 
-        Ok(response)
+        let Some(page) = page.filter(|_| pages > 1) else {
+            return Ok(response.into());
+        };
+        response.push_str(&lang.get_with_vars(
+            "HISTORY__PAGE",
+            HashMap::from([
+                ("page".to_string(), (page + 1).to_string()),
+                ("pages".to_string(), pages.to_string()),
+            ]),
+        ));
+        let buttons = page_buttons(
+            start_date.date_naive(),
+            end_date.date_naive(),
+            filter,
+            page,
+            pages,
+            lang,
+        );
+        Ok(ChatReply::from(response).with_buttons(vec![buttons]))
     }
 
-    fn calculate_month_range(
-        start_over_date: i16,
-    ) -> (chrono::DateTime<Utc>, chrono::DateTime<Utc>) {
-        let now = Utc::now();
-        let current_year = now.year();
-        let current_month = now.month();
-
-        // Calculate the start date based on start_over_date
-        let start_day = start_over_date as u32;
-        let mut start_date = if current_month == 1 {
-            // January - go back to previous year
-            NaiveDate::from_ymd_opt(current_year - 1, 12, start_day)
-        } else {
-            NaiveDate::from_ymd_opt(current_year, current_month - 1, start_day)
+    // e.g. "Makanan, @budi, > Rp. 50.000", None without filters
+    async fn describe_filter(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        filter: &HistoryFilter,
+        currency: &str,
+    ) -> Result<Option<String>> {
+        let mut parts = Vec::new();
+        if let Some(category_uid) = filter.category_uid {
+            parts.push(CategoryRepo::get(tx, category_uid).await?.name);
         }
-        .unwrap_or_else(|| NaiveDate::from_ymd_opt(current_year, current_month, 1).unwrap());
-
-        // If the calculated start date is in the future, use the previous month's start date
-        if start_date > now.date_naive() {
-            start_date = if current_month == 1 {
-                NaiveDate::from_ymd_opt(current_year - 1, 11, start_day)
-            } else if current_month == 2 {
-                NaiveDate::from_ymd_opt(current_year - 1, 12, start_day)
-            } else {
-                NaiveDate::from_ymd_opt(current_year, current_month - 2, start_day)
-            }
-            .unwrap_or_else(|| {
-                NaiveDate::from_ymd_opt(current_year, current_month - 1, 1).unwrap()
-            });
+        if let Some(member) = &filter.member {
+            parts.push(format!("@{member}"));
+        }
+        if let Some(min_price) = filter.min_price {
+            parts.push(format!("> {}", format_price_in(min_price, currency)));
+        }
+        if let Some(max_price) = filter.max_price {
+            parts.push(format!("< {}", format_price_in(max_price, currency)));
         }
+        Ok((!parts.is_empty()).then(|| parts.join(", ")))
+    }
+}
 
-        let end_date = start_date + Duration::days(30); // Approximate month length
+// Whether the page buttons can carry the filter, member names may be too long or hold `:`
+fn fits_in_buttons(start_date: NaiveDate, end_date: NaiveDate, filter: &HistoryFilter) -> bool {
+    let action = CallbackAction::HistoryPage {
+        start_date,
+        end_date,
+        filter: filter.clone(),
+        page: 999,
+    };
+    let data = action.encode();
+    data.len() <= MAX_CALLBACK_DATA_LENGTH && CallbackAction::decode(&data) == Some(action)
+}
 
-        (
-            start_date.and_hms_opt(0, 0, 0).unwrap().and_utc(),
-            end_date.and_hms_opt(23, 59, 59).unwrap().and_utc(),
+// Previous and next buttons, without the ones past the first or last page
+fn page_buttons(
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+    filter: &HistoryFilter,
+    page: usize,
+    pages: usize,
+    lang: &Lang,
+) -> Vec<ChatButton> {
+    let button = |key: &str, page: usize| {
+        ChatButton::new(
+            lang.get(key),
+            &CallbackAction::HistoryPage {
+                start_date,
+                end_date,
+                filter: filter.clone(),
+                page: page as u32,
+            },
         )
+    };
+    let mut buttons = Vec::new();
+    if page > 0 {
+        buttons.push(button("HISTORY__PREVIOUS_PAGE", page - 1));
     }
+    if page + 1 < pages {
+        buttons.push(button("HISTORY__NEXT_PAGE", page + 1));
+    }
+    buttons
 }
 
 impl Command for HistoryCommand {
@@ -269,6 +555,10 @@ impl Command for HistoryCommand {
     fn get_instruction_text_key() -> &'static str {
         "MESSENGER__HISTORY_SHORT_INSTRUCTION"
     }
+
+    fn get_help_text_key() -> Option<&'static str> {
+        Some("MESSENGER__HISTORY_HELP")
+    }
 }
 
 #[cfg(test)]
@@ -279,11 +569,9 @@ mod tests {
     fn test_parse_command_no_dates() {
         let input = "/history";
         let command = HistoryCommand::parse_command(input).unwrap();
-        assert!(command.start_date.is_some());
-        assert!(command.end_date.is_some());
-        // Should be 3 days apart
-        let days_diff = (command.end_date.unwrap() - command.start_date.unwrap()).num_days();
-        assert_eq!(days_diff, 3);
+        // The range comes from the group's settings when the command runs
+        assert!(command.start_date.is_none());
+        assert!(command.end_date.is_none());
     }
 
     #[test]
@@ -314,6 +602,76 @@ mod tests {
         assert!(HistoryCommand::parse_command(input).is_err());
     }
 
+    #[test]
+    fn test_page_buttons() {
+        let lang = Lang::from_json("id");
+        let start_date = NaiveDate::from_ymd_opt(2025, 9, 1).unwrap();
+        let end_date = NaiveDate::from_ymd_opt(2025, 9, 3).unwrap();
+        let pages = |page| -> Vec<Option<CallbackAction>> {
+            page_buttons(start_date, end_date, &HistoryFilter::default(), page, 3, &lang)
+                .iter()
+                .map(|button| CallbackAction::decode(&button.data))
+                .collect()
+        };
+        let action = |page| {
+            Some(CallbackAction::HistoryPage {
+                start_date,
+                end_date,
+                filter: HistoryFilter::default(),
+                page,
+            })
+        };
+
+        assert_eq!(pages(0), vec![action(1)]);
+        assert_eq!(pages(1), vec![action(0), action(2)]);
+        assert_eq!(pages(2), vec![action(1)]);
+    }
+
+    #[test]
+    fn test_parse_command_filters() {
+        let input = "/history 2025-09-01 kategori=Makanan @Budi >50.000 <rp100000";
+        let command = HistoryCommand::parse_command(input).unwrap();
+        assert_eq!(command.start_date.unwrap().to_string(), "2025-09-01");
+        assert_eq!(command.category.as_deref(), Some("Makanan"));
+        assert_eq!(command.member.as_deref(), Some("budi"));
+        assert_eq!(command.min_price, Some(Decimal::from(50000)));
+        assert_eq!(command.max_price, Some(Decimal::from(100000)));
+
+        let command = HistoryCommand::parse_command("/history Category=jajan").unwrap();
+        assert_eq!(command.category.as_deref(), Some("jajan"));
+        // Without dates it still covers the group's default range
+        assert!(command.start_date.is_none());
+    }
+
+    #[test]
+    fn test_parse_command_invalid_filters() {
+        assert!(HistoryCommand::parse_command("/history kategori=").is_err());
+        assert!(HistoryCommand::parse_command("/history @").is_err());
+        assert!(HistoryCommand::parse_command("/history >abc").is_err());
+        assert!(HistoryCommand::parse_command("/history >100000 <50000").is_err());
+    }
+
+    #[test]
+    fn test_fits_in_buttons() {
+        let date = NaiveDate::from_ymd_opt(2025, 9, 1).unwrap();
+        let filter = |member: &str| HistoryFilter {
+            category_uid: Some(Uuid::new_v4()),
+            member: Some(member.to_string()),
+            min_price: Some(Decimal::from(50000)),
+            max_price: None,
+        };
+        assert!(fits_in_buttons(date, date, &HistoryFilter::default()));
+        assert!(fits_in_buttons(date, date, &filter("budi")));
+        assert!(!fits_in_buttons(date, date, &filter("bu:di")));
+        assert!(!fits_in_buttons(date, date, &filter(&"a".repeat(40))));
+        // Too long with every filter set, see `test_encode_oversized_history_page`
+        let every_filter = HistoryFilter {
+            max_price: Some(Decimal::from(100000)),
+            ..filter("budi")
+        };
+        assert!(!fits_in_buttons(date, date, &every_filter));
+    }
+
     #[test]
     fn test_parse_command_invalid_date_format() {
         let input = "/history invalid-date";
@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use rust_decimal::Decimal;
+
+use crate::{
+    commands::base::{ChatSender, Command},
+    lang::Lang,
+    repos::{
+        audit_log::{AuditActor, AuditChange, AuditEntity, AuditRepo},
+        chat_binding::ChatBinding,
+        income_entry::{CreateIncomeEntryDbPayload, IncomeEntryRepo},
+    },
+    utils::parse_price::{format_price, parse_price},
+};
+
+#[derive(Debug)]
+pub struct IncomeCommandEntry {
+    pub source: String,
+    pub amount: Decimal,
+}
+
+#[derive(Debug)]
+pub struct IncomeCommand {
+    pub entries: Vec<IncomeCommandEntry>,
+    pub fail_entries: Vec<String>, // Store failed entries for reporting
+}
+
+impl IncomeCommand {
+    /*
+     Expected format:
+     /income
+     [source],[amount]
+     or
+     /income [source],[amount]
+
+     Examples:
+     /income
+     Gaji,10.000.000
+     Transfer dari Ayah, Rp. 500.000
+
+     or
+     /income Gaji,10000000
+    */
+    fn parse_command(input: &str) -> Result<Self> {
+        let mut entries = Vec::new();
+        let mut fail_entries = Vec::new();
+        let input = input.trim();
+
+        // Should start with /income
+        let input = if input.starts_with(Self::get_command()) {
+            input[Self::get_command().len()..].trim()
+        } else {
+            input
+        };
+
+        for line in input.lines() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            // Split on the first comma only, amounts may contain thousand separators
+            let Some((source, amount)) = line.split_once(',') else {
+                fail_entries.push(line.to_string());
+                continue;
+            };
+
+            let source = source.trim().to_string();
+            if source.is_empty() {
+                fail_entries.push(line.to_string());
+                continue;
+            }
+            let Ok(amount) = parse_price(amount.trim()) else {
+                fail_entries.push(line.to_string());
+                continue;
+            };
+
+            entries.push(IncomeCommandEntry { source, amount });
+        }
+
+        if entries.is_empty() {
+            return Err(anyhow::anyhow!("No valid income entries found"));
+        }
+
+        Ok(Self {
+            entries,
+            fail_entries,
+        })
+    }
+
+    /*
+       Output format:
+       ✅ Pemasukan berhasil dicatat! (can be found on lang/id.json)
+
+       <id>
+       <source>, Rp. <amount>
+    */
+    pub async fn run(
+        raw_message: &str,
+        binding: &ChatBinding,
+        sender: &ChatSender,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        lang: &Lang,
+    ) -> Result<String> {
+        let command = Self::parse_command(raw_message)?;
+        let actor = AuditActor::from_chat(binding, sender);
+
+        let mut response = String::new();
+        response.push_str(&lang.get("MESSENGER__INCOME_SUCCESS_HEADER"));
+
+        for entry in command.entries {
+            let income = IncomeEntryRepo::create(
+                tx,
+                CreateIncomeEntryDbPayload {
+                    amount: entry.amount,
+                    source: entry.source,
+                    group_uid: binding.group_uid,
+                },
+            )
+            .await?;
+            AuditRepo::record(
+                tx,
+                &actor,
+                AuditEntity::IncomeEntry,
+                binding.group_uid,
+                income.uid,
+                AuditChange::create(&income),
+            )
+            .await?;
+
+            response.push_str(&lang.get_with_vars(
+                "MESSENGER__INCOME_SUCCESS_ENTRY",
+                HashMap::from([
+                    ("id".to_string(), income.uid.to_string()),
+                    ("source".to_string(), income.source),
+                    (
+                        "amount".to_string(),
+                        format!("Rp. {}", format_price(income.amount)),
+                    ),
+                ]),
+            ));
+        }
+
+        if !command.fail_entries.is_empty() {
+            response.push_str("-----\n");
+            response.push_str(&lang.get_with_vars(
+                "MESSENGER__INCOME_FAIL_INVALID_FORMAT",
+                HashMap::from([("line".to_string(), command.fail_entries.join("\n"))]),
+            ));
+        }
+
+        Ok(response)
+    }
+}
+
+impl Command for IncomeCommand {
+    fn get_command() -> &'static str {
+        "/income"
+    }
+
+    fn get_instruction_text_key() -> &'static str {
+        "MESSENGER__INCOME_SHORT_INSTRUCTION"
+    }
+
+    fn get_help_text_key() -> Option<&'static str> {
+        Some("MESSENGER__INCOME_HELP")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parse_command() {
+        let input = "/income
+Gaji,10000000
+Transfer dari Ayah, 500000
+invalid line";
+
+        let command = IncomeCommand::parse_command(input).unwrap();
+
+        assert_eq!(command.entries.len(), 2);
+        assert_eq!(command.entries[0].source, "Gaji");
+        assert_eq!(command.entries[0].amount, dec!(10000000));
+        assert_eq!(command.entries[1].source, "Transfer dari Ayah");
+        assert_eq!(command.entries[1].amount, dec!(500000));
+        assert_eq!(command.fail_entries, vec!["invalid line".to_string()]);
+    }
+
+    #[test]
+    fn test_parse_command_single_line() {
+        let command = IncomeCommand::parse_command("/income Bonus,250000").unwrap();
+
+        assert_eq!(command.entries.len(), 1);
+        assert_eq!(command.entries[0].source, "Bonus");
+        assert_eq!(command.entries[0].amount, dec!(250000));
+    }
+
+    #[test]
+    fn test_parse_command_no_valid_entries() {
+        assert!(IncomeCommand::parse_command("/income\n,10000").is_err());
+    }
+}
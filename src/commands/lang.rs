@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use crate::{
+    commands::base::Command,
+    lang::{self, Lang},
+    repos::{chat_binding::ChatBinding, expense_group::ExpenseGroupRepo},
+};
+
+#[derive(Debug, PartialEq)]
+pub struct LangCommand {
+    pub lang: Option<String>,
+}
+
+impl LangCommand {
+    /*
+        Should be in format:
+        /lang
+        /lang [id|en]
+    */
+    fn parse_command(input: &str) -> Result<Self> {
+        let args = input
+            .trim()
+            .strip_prefix(Self::get_command())
+            .ok_or_else(|| anyhow::anyhow!("Invalid format: expected /lang"))?;
+        if !args.is_empty() && !args.starts_with(char::is_whitespace) {
+            return Err(anyhow::anyhow!("Invalid format: expected /lang"));
+        }
+
+        let code = args.trim().to_lowercase();
+        if code.is_empty() {
+            return Ok(Self { lang: None });
+        }
+        if !lang::is_supported(&code) {
+            return Err(anyhow::anyhow!(
+                "Unsupported language '{}', expected one of: {}",
+                code,
+                lang::SUPPORTED_LANGS.join(", ")
+            ));
+        }
+        Ok(Self { lang: Some(code) })
+    }
+
+    /*
+        Without a language shows the one the group's replies use, otherwise sets it for the
+        group. The confirmation is already in the new language.
+
+        Output format:
+
+        🌐 Bahasa grup ini: id. Pilihan: id, en.
+    */
+    pub async fn run(
+        raw_message: &str,
+        binding: &ChatBinding,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        lang: &Lang,
+    ) -> Result<String> {
+        let command = Self::parse_command(raw_message)?;
+
+        let Some(code) = command.lang else {
+            return Ok(lang.get_with_vars(
+                "MESSENGER__LANG_CURRENT",
+                HashMap::from([
+                    ("lang".to_string(), lang.lang.clone()),
+                    ("langs".to_string(), lang::SUPPORTED_LANGS.join(", ")),
+                ]),
+            ));
+        };
+
+        ExpenseGroupRepo::set_lang(tx, binding.group_uid, &code).await?;
+        Ok(lang.preferred(Some(&code)).get("MESSENGER__LANG_SUCCESS"))
+    }
+}
+
+impl Command for LangCommand {
+    fn get_command() -> &'static str {
+        "/lang"
+    }
+
+    fn get_instruction_text_key() -> &'static str {
+        "MESSENGER__LANG_SHORT_INSTRUCTION"
+    }
+
+    fn get_help_text_key() -> Option<&'static str> {
+        Some("MESSENGER__LANG_HELP")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_command_current() {
+        let command = LangCommand::parse_command("/lang").unwrap();
+        assert_eq!(command, LangCommand { lang: None });
+    }
+
+    #[test]
+    fn test_parse_command_lang() {
+        let command = LangCommand::parse_command("/lang EN \n").unwrap();
+        assert_eq!(
+            command,
+            LangCommand {
+                lang: Some("en".to_string())
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_command_invalid() {
+        assert!(LangCommand::parse_command("/langen").is_err());
+        assert!(LangCommand::parse_command("/lang fr").is_err());
+    }
+}
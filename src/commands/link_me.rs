@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use chrono::Utc;
+
+use crate::{
+    commands::base::{ChatSender, Command},
+    lang::Lang,
+    repos::{
+        chat_binding::ChatBinding, chat_link_code::ChatLinkCodeRepo,
+        chat_member_link::ChatMemberLinkRepo, expense_group_member::GroupMemberRepo,
+    },
+};
+
+#[derive(Debug, PartialEq)]
+pub struct LinkMeCommand {
+    pub code: String,
+}
+
+impl LinkMeCommand {
+    /*
+        Should be in format:
+        /link-me [kode]
+
+        The code is created in the web app, see `POST /chat-links/code`.
+    */
+    fn parse_command(input: &str) -> Result<Self> {
+        let args = input
+            .trim()
+            .strip_prefix(Self::get_command())
+            .ok_or_else(|| anyhow::anyhow!("Invalid format: expected /link-me [code]"))?;
+
+        match args.split_whitespace().collect::<Vec<_>>().as_slice() {
+            [code] if args.starts_with(char::is_whitespace) => Ok(Self {
+                code: code.to_string(),
+            }),
+            _ => Err(anyhow::anyhow!("Invalid format: expected /link-me [code]")),
+        }
+    }
+
+    /*
+        Links the sender's platform account to the account that created the code, so their
+        entries in group chats are attributed to them. The account has to be a member of the
+        group the chat is bound to.
+    */
+    pub async fn run(
+        raw_message: &str,
+        binding: &ChatBinding,
+        sender: &ChatSender,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        lang: &Lang,
+    ) -> Result<String> {
+        let command = Self::parse_command(raw_message)?;
+
+        let code = ChatLinkCodeRepo::get_by_code_for_update(tx, &command.code)
+            .await?
+            .filter(|code| code.is_usable(Utc::now()))
+            .ok_or_else(|| anyhow::anyhow!("Invalid or expired code"))?;
+        if GroupMemberRepo::find_role(tx, binding.group_uid, code.user_uid)
+            .await?
+            .is_none()
+        {
+            return Err(anyhow::anyhow!(
+                "The account of this code is not a member of the group"
+            ));
+        }
+
+        ChatMemberLinkRepo::upsert(tx, &binding.platform, &sender.p_user_id, code.user_uid).await?;
+        ChatLinkCodeRepo::mark_used(tx, code.uid).await?;
+
+        Ok(lang.get_with_vars(
+            "MESSENGER__LINK_ME_SUCCESS",
+            HashMap::from([("name".to_string(), sender.name.clone())]),
+        ))
+    }
+}
+
+impl Command for LinkMeCommand {
+    fn get_command() -> &'static str {
+        "/link-me"
+    }
+
+    fn get_instruction_text_key() -> &'static str {
+        "MESSENGER__LINK_ME_SHORT_INSTRUCTION"
+    }
+
+    fn get_help_text_key() -> Option<&'static str> {
+        Some("MESSENGER__LINK_ME_HELP")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_command_valid() {
+        let command = LinkMeCommand::parse_command("/link-me AB3K9XYZ").unwrap();
+        assert_eq!(
+            command,
+            LinkMeCommand {
+                code: "AB3K9XYZ".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_command_invalid() {
+        assert!(LinkMeCommand::parse_command("/link-me").is_err());
+        assert!(LinkMeCommand::parse_command("/link-meAB3K9XYZ").is_err());
+        assert!(LinkMeCommand::parse_command("/link-me AB3K 9XYZ").is_err());
+    }
+}
@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use uuid::Uuid;
+
+use crate::{
+    commands::base::Command,
+    lang::Lang,
+    repos::{
+        chat_binding::{ChatBinding, ChatBindingRepo},
+        expense_group::ExpenseGroupRepo,
+    },
+};
+
+#[derive(Debug)]
+pub struct LogoutCommand;
+
+impl LogoutCommand {
+    /*
+        Should be in format:
+        /logout
+    */
+    fn parse_command(input: &str) -> Result<Self> {
+        let input = input.trim();
+
+        if input != Self::get_command() {
+            return Err(anyhow::anyhow!("Invalid format: expected only /logout"));
+        }
+
+        Ok(Self {})
+    }
+
+    /*
+        Revokes the binding of this chat to its selected group. The group and its data are
+        kept, /login binds the chat again.
+    */
+    pub async fn run(
+        raw_message: &str,
+        binding: &ChatBinding,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        lang: &Lang,
+    ) -> Result<String> {
+        let _command = Self::parse_command(raw_message)?;
+
+        ChatBindingRepo::revoke(tx, binding.id).await?;
+
+        let group = Self::group_name(tx, binding.group_uid).await?;
+        let mut response = lang.get_with_vars(
+            "MESSENGER__LOGOUT_SUCCESS",
+            HashMap::from([("group".to_string(), group)]),
+        );
+        // Chats bound to several groups continue with the next one
+        let next = ChatBindingRepo::get_selected(tx, &binding.platform, &binding.p_uid).await?;
+        if let Some(next) = next {
+            let next_group = Self::group_name(tx, next.group_uid).await?;
+            response.push_str("\n\n");
+            response.push_str(&lang.get_with_vars(
+                "MESSENGER__SWITCH_SUCCESS",
+                HashMap::from([("group".to_string(), next_group)]),
+            ));
+        }
+        Ok(response)
+    }
+
+    // The group may be soft-deleted, the chat can still detach from it
+    async fn group_name(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+    ) -> Result<String> {
+        let group = match ExpenseGroupRepo::get(tx, group_uid).await {
+            Ok(group) => group,
+            Err(_) => ExpenseGroupRepo::get_deleted(tx, group_uid).await?,
+        };
+        Ok(group.name)
+    }
+}
+
+impl Command for LogoutCommand {
+    fn get_command() -> &'static str {
+        "/logout"
+    }
+
+    fn get_instruction_text_key() -> &'static str {
+        "MESSENGER__LOGOUT_SHORT_INSTRUCTION"
+    }
+
+    fn get_help_text_key() -> Option<&'static str> {
+        Some("MESSENGER__LOGOUT_HELP")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_command_valid() {
+        assert!(LogoutCommand::parse_command("/logout").is_ok());
+        assert!(LogoutCommand::parse_command("  /logout\n").is_ok());
+    }
+
+    #[test]
+    fn test_parse_command_invalid() {
+        assert!(LogoutCommand::parse_command("/logout now").is_err());
+    }
+}
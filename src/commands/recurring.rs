@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+use crate::{
+    commands::base::{ChatSender, Command},
+    lang::Lang,
+    repos::{
+        audit_log::{AuditActor, AuditChange, AuditEntity, AuditRepo},
+        category::CategoryRepo,
+        category_alias::CategoryAliasRepo,
+        chat_binding::ChatBinding,
+        recurring_expense::{
+            CreateRecurringExpenseDbPayload, RecurringCadence, RecurringExpense,
+            RecurringExpenseRepo,
+        },
+    },
+    utils::parse_price::{format_price, parse_price},
+};
+
+#[derive(Debug)]
+pub struct RecurringCommandEntry {
+    pub name: String,
+    pub price: Decimal,
+    pub cadence: RecurringCadence,
+    pub run_day: i16,
+    pub category_or_alias: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct RecurringCommand {
+    pub entries: Vec<RecurringCommandEntry>,
+}
+
+impl RecurringCommand {
+    /*
+     Expected format:
+     /recurring
+     -> list all recurring expenses of the group
+
+     /recurring
+     [name],[price],[bulanan|mingguan],[day],[optional category]
+     -> create recurring expenses, day is the day of month (1-31) for bulanan
+        or the weekday (1 = Senin .. 7 = Minggu) for mingguan
+
+     Examples:
+     /recurring
+     Sewa Kos,1.500.000,bulanan,1,Tempat Tinggal
+     Netflix,54000,monthly,15
+     Laundry,30000,mingguan,6
+    */
+    fn parse_command(input: &str) -> Result<Self> {
+        let input = input.trim();
+
+        // Should start with /recurring
+        let input = if input.starts_with(Self::get_command()) {
+            input[Self::get_command().len()..].trim()
+        } else {
+            input
+        };
+
+        let mut entries = Vec::new();
+        for line in input.lines() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let parts: Vec<&str> = line.split(',').map(|s| s.trim()).collect();
+            if parts.len() < 4 {
+                return Err(anyhow::anyhow!("Invalid recurring format: {}", line));
+            }
+
+            let name = parts[0].to_string();
+            if name.is_empty() {
+                return Err(anyhow::anyhow!("Empty expense name: {}", line));
+            }
+
+            let price = parse_price(parts[1])
+                .map_err(|_| anyhow::anyhow!("Invalid price format: {}", parts[1]))?;
+
+            let cadence = RecurringCadence::parse(parts[2])
+                .ok_or_else(|| anyhow::anyhow!("Invalid cadence: {}", parts[2]))?;
+
+            let run_day: i16 = parts[3]
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid day: {}", parts[3]))?;
+            if !cadence.is_valid_day(run_day) {
+                return Err(anyhow::anyhow!("Invalid day for {}: {}", parts[2], run_day));
+            }
+
+            let category_or_alias = if parts.len() >= 5 && !parts[4].is_empty() {
+                Some(parts[4].to_string())
+            } else {
+                None
+            };
+
+            entries.push(RecurringCommandEntry {
+                name,
+                price,
+                cadence,
+                run_day,
+                category_or_alias,
+            });
+        }
+
+        Ok(Self { entries })
+    }
+
+    /*
+       Output format:
+       🔁 Daftar Pengeluaran Rutin: (can be found on lang/id.json)
+
+       1. Sewa Kos - Rp. 1.500.000 (bulanan, hari 1)
+       id: <uid>
+    */
+    pub async fn run(
+        raw_message: &str,
+        binding: &ChatBinding,
+        sender: &ChatSender,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        lang: &Lang,
+    ) -> Result<String> {
+        let command = Self::parse_command(raw_message)?;
+
+        if command.entries.is_empty() {
+            let items = RecurringExpenseRepo::list_by_group(tx, binding.group_uid).await?;
+            if items.is_empty() {
+                return Ok(lang.get("MESSENGER__RECURRING_LIST_EMPTY"));
+            }
+
+            let mut response = lang.get("MESSENGER__RECURRING_LIST_HEADER");
+            for (index, item) in items.iter().enumerate() {
+                response.push_str(&Self::format_item(index + 1, item, lang));
+            }
+            return Ok(response);
+        }
+
+        let categories = CategoryRepo::list_by_group(tx, binding.group_uid).await?;
+        let aliases = CategoryAliasRepo::list_by_group(tx, binding.group_uid).await?;
+        let mut category_map: HashMap<String, Uuid> = HashMap::new();
+
+        for category in categories {
+            category_map.insert(category.name.to_lowercase(), category.uid);
+        }
+
+        for alias in aliases {
+            category_map.insert(alias.alias.to_lowercase(), alias.category_uid);
+        }
+
+        let mut response = lang.get("MESSENGER__RECURRING_CREATED_HEADER");
+        let actor = AuditActor::from_chat(binding, sender);
+        for (index, entry) in command.entries.into_iter().enumerate() {
+            let category_uid = entry
+                .category_or_alias
+                .and_then(|cat| category_map.get(&cat.to_lowercase()).copied());
+
+            let item = RecurringExpenseRepo::create(
+                tx,
+                CreateRecurringExpenseDbPayload {
+                    group_uid: binding.group_uid,
+                    product: entry.name,
+                    price: entry.price,
+                    category_uid,
+                    cadence: entry.cadence,
+                    run_day: entry.run_day,
+                },
+            )
+            .await?;
+            AuditRepo::record(
+                tx,
+                &actor,
+                AuditEntity::RecurringExpense,
+                binding.group_uid,
+                item.uid,
+                AuditChange::create(&item),
+            )
+            .await?;
+
+            response.push_str(&Self::format_item(index + 1, &item, lang));
+        }
+
+        Ok(response)
+    }
+
+    fn format_item(index: usize, item: &RecurringExpense, lang: &Lang) -> String {
+        let cadence_key = match RecurringCadence::parse(&item.cadence) {
+            Some(RecurringCadence::Weekly) => "MESSENGER__RECURRING_WEEKLY",
+            _ => "MESSENGER__RECURRING_MONTHLY",
+        };
+
+        lang.get_with_vars(
+            "MESSENGER__RECURRING_LIST_ITEM",
+            HashMap::from([
+                ("index".to_string(), index.to_string()),
+                ("id".to_string(), item.uid.to_string()),
+                ("item".to_string(), item.product.clone()),
+                (
+                    "price".to_string(),
+                    format!("Rp. {}", format_price(item.price)),
+                ),
+                ("cadence".to_string(), lang.get(cadence_key)),
+                ("day".to_string(), item.run_day.to_string()),
+            ]),
+        )
+    }
+}
+
+impl Command for RecurringCommand {
+    fn get_command() -> &'static str {
+        "/recurring"
+    }
+
+    fn get_instruction_text_key() -> &'static str {
+        "MESSENGER__RECURRING_SHORT_INSTRUCTION"
+    }
+
+    fn get_help_text_key() -> Option<&'static str> {
+        Some("MESSENGER__RECURRING_HELP")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parse_command_list() {
+        let command = RecurringCommand::parse_command("/recurring").unwrap();
+        assert!(command.entries.is_empty());
+    }
+
+    #[test]
+    fn test_parse_command_create() {
+        let input = "/recurring
+Sewa Kos,1.500.000,bulanan,1,Tempat Tinggal
+Laundry,30000,mingguan,6";
+
+        let command = RecurringCommand::parse_command(input).unwrap();
+
+        assert_eq!(command.entries.len(), 2);
+        assert_eq!(command.entries[0].name, "Sewa Kos");
+        assert_eq!(command.entries[0].price, dec!(1500000));
+        assert_eq!(command.entries[0].cadence, RecurringCadence::Monthly);
+        assert_eq!(command.entries[0].run_day, 1);
+        assert_eq!(
+            command.entries[0].category_or_alias.as_deref(),
+            Some("Tempat Tinggal")
+        );
+
+        assert_eq!(command.entries[1].cadence, RecurringCadence::Weekly);
+        assert_eq!(command.entries[1].run_day, 6);
+        assert_eq!(command.entries[1].category_or_alias, None);
+    }
+
+    #[test]
+    fn test_parse_command_invalid_day() {
+        assert!(RecurringCommand::parse_command("/recurring\nLaundry,30000,mingguan,8").is_err());
+        assert!(RecurringCommand::parse_command("/recurring\nSewa,100000,bulanan,0").is_err());
+    }
+
+    #[test]
+    fn test_parse_command_invalid_cadence() {
+        assert!(RecurringCommand::parse_command("/recurring\nSewa,100000,harian,1").is_err());
+    }
+}
@@ -1,36 +1,223 @@
 use std::collections::HashMap;
 
 use anyhow::Result;
-use chrono::{Datelike, Duration, NaiveDate, Utc};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use rust_decimal::Decimal;
 use sqlx::Row;
 use tracing::info;
+use uuid::Uuid;
 
 use crate::{
     commands::base::Command,
     lang::Lang,
     repos::{
-        chat_binding::ChatBinding, expense_group::ExpenseGroupRepo,
-        expense_group_member::GroupMemberRepo, user::UserRepo,
+        chat_binding::ChatBinding, closed_period::ClosedPeriodRepo,
+        exchange_rate::ExchangeRateRepo, expense_entry::creator_name,
+        expense_group::ExpenseGroupRepo, expense_group_member::GroupMemberRepo,
+        group_settings::{GroupSettings, GroupSettingsRepo}, income_entry::IncomeEntryRepo,
+    },
+    utils::{
+        currency::RateTable, forecast::group_forecast, parse_price::format_price_in,
+        period::BillingPeriod, pii,
     },
-    utils::parse_price::format_price,
 };
 
+// Longest custom range accepted by /report
+const MAX_RANGE_DAYS: i64 = 366;
+// Single expenses listed as the largest of the period
+const LARGEST_EXPENSES: usize = 3;
+
+#[derive(Debug, PartialEq)]
+pub enum ReportPeriod {
+    Current,
+    Last,
+    // The current week, from Monday
+    Week,
+    // The cycle starting in the given month
+    Month { year: i32, month: u32 },
+    // Custom [start, end) range
+    Range { start: NaiveDate, end: NaiveDate },
+}
+
 #[derive(Debug, PartialEq)]
-pub struct ReportCommand;
+pub struct ReportCommand {
+    pub period: ReportPeriod,
+}
 
 impl ReportCommand {
     /*
         Should be in format:
         /report
+        /report last
+        /report week
+        /report [YYYY-MM]
+        /report [start YYYY-MM-DD] [end YYYY-MM-DD]
+
+        The end date of a custom range is exclusive, so
+        /report 2025-08-01 2025-09-01 covers the whole of August.
     */
     fn parse_command(input: &str) -> Result<Self> {
-        let input = input.trim();
+        let args = input
+            .trim()
+            .strip_prefix(Self::get_command())
+            .ok_or_else(|| anyhow::anyhow!("Invalid format: expected /report"))?;
 
-        if input != Self::get_command() {
-            return Err(anyhow::anyhow!("Invalid format: expected only /report"));
-        }
+        let parts: Vec<&str> = args.split_whitespace().collect();
+        let period = match parts.as_slice() {
+            [] => ReportPeriod::Current,
+            ["last"] => ReportPeriod::Last,
+            ["week"] => ReportPeriod::Week,
+            [month] => {
+                let date = NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d")
+                    .map_err(|_| {
+                        anyhow::anyhow!("Invalid month format: {}. Expected YYYY-MM", month)
+                    })?;
+                ReportPeriod::Month {
+                    year: date.year(),
+                    month: date.month(),
+                }
+            }
+            [start_str, end_str] => {
+                let start = NaiveDate::parse_from_str(start_str, "%Y-%m-%d").map_err(|_| {
+                    anyhow::anyhow!(
+                        "Invalid start date format: {}. Expected YYYY-MM-DD",
+                        start_str
+                    )
+                })?;
+                let end = NaiveDate::parse_from_str(end_str, "%Y-%m-%d").map_err(|_| {
+                    anyhow::anyhow!("Invalid end date format: {}. Expected YYYY-MM-DD", end_str)
+                })?;
+
+                if start >= end {
+                    return Err(anyhow::anyhow!("Start date must be before end date"));
+                }
+                let days = (end - start).num_days();
+                if days > MAX_RANGE_DAYS {
+                    return Err(anyhow::anyhow!(
+                        "Date range cannot exceed {} days. Current range: {} days",
+                        MAX_RANGE_DAYS,
+                        days
+                    ));
+                }
+
+                ReportPeriod::Range { start, end }
+            }
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "Invalid format. Use: /report [last | week | YYYY-MM | start_date end_date]"
+                ));
+            }
+        };
+
+        Ok(Self { period })
+    }
+
+    /*
+        The reported range and the one right before it, both as [start, end).
+        A week starts on the group's `week_start` and is compared against the week before it,
+        a custom range against the same number of days before it.
+    */
+    fn ranges(
+        &self,
+        start_over_date: i16,
+        settings: &GroupSettings,
+    ) -> ((NaiveDate, NaiveDate), (NaiveDate, NaiveDate)) {
+        let period = match self.period {
+            ReportPeriod::Current => BillingPeriod::current(start_over_date),
+            ReportPeriod::Last => BillingPeriod::current(start_over_date).previous(),
+            ReportPeriod::Month { year, month } => {
+                BillingPeriod::for_month(year, month, start_over_date)
+            }
+            ReportPeriod::Week => {
+                let start = settings.week_start_of(Utc::now().date_naive());
+                let end = start + Duration::days(7);
+                return ((start, end), (start - Duration::days(7), start));
+            }
+            ReportPeriod::Range { start, end } => {
+                return ((start, end), (start - (end - start), start));
+            }
+        };
+        let previous = period.previous();
+        ((period.start, period.end), (previous.start, previous.end))
+    }
+
+    /*
+        Expense totals of the regular cycle in [start, end) per category and per member,
+        converted to the group's currency where a rate is known, with the largest single
+        expenses. Entries assigned to an envelope are only totalled per envelope, and
+        subcategories are totalled under their parent.
+    */
+    async fn period_totals(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+        (start, end): (NaiveDate, NaiveDate),
+        rates: &RateTable,
+        currency: &str,
+        lang: &Lang,
+    ) -> Result<PeriodTotals> {
+        let expenses = sqlx::query(
+            r#"
+            SELECT e.product, e.price, e.currency, e.created_at, COALESCE(p.name, c.name) as category_name,
+                   e.created_by, u.email AS creator_email, v.name AS envelope_name
+            FROM expense_entries e
+            LEFT JOIN categories c ON e.category_uid = c.uid
+            LEFT JOIN categories p ON c.parent_uid = p.uid
+            LEFT JOIN users u ON e.created_by_user_uid = u.uid
+            LEFT JOIN envelopes v ON e.envelope_uid = v.uid
+            WHERE e.group_uid = $1
+              AND e.deleted_at IS NULL
+              AND e.created_at >= $2
+              AND e.created_at < $3
+            "#,
+        )
+        .bind(group_uid)
+        .bind(midnight(start))
+        .bind(midnight(end))
+        .fetch_all(tx.as_mut())
+        .await?;
+
+        let mut totals = PeriodTotals::default();
+        for row in expenses {
+            let price: Decimal = row.get("price");
+            let from: String = row.get("currency");
+            let price = match rates.convert(price, &from, currency) {
+                Some(converted) => converted,
+                None => {
+                    if !totals.unconverted.contains(&from) {
+                        totals.unconverted.push(from);
+                    }
+                    price
+                }
+            };
+            let envelope_name: Option<String> = row.get("envelope_name");
+            if let Some(envelope_name) = envelope_name {
+                *totals.envelopes.entry(envelope_name).or_default() += price;
+                continue;
+            }
+            let category_name: Option<String> = row.get("category_name");
+            let category_name = category_name.unwrap_or_else(|| lang.get("REPORT__UNCATEGORIZED"));
+            *totals.categories.entry(category_name).or_default() += price;
+
+            let created_by: String = row.get("created_by");
+            let creator_email: Option<String> = row.get("creator_email");
+            let creator_email = creator_email
+                .map(|email| pii::decrypt(&email))
+                .transpose()?;
+            let member = creator_name(&created_by, creator_email.as_deref())
+                .unwrap_or_else(|| lang.get("REPORT__UNKNOWN_MEMBER"));
+            *totals.members.entry(member).or_default() += price;
+            totals.total += price;
 
-        Ok(Self {})
+            let created_at: DateTime<Utc> = row.get("created_at");
+            totals.largest.push(LargeExpense {
+                product: row.get("product"),
+                amount: price,
+                date: created_at.date_naive(),
+            });
+        }
+        totals.largest.sort_by(|a, b| b.amount.cmp(&a.amount));
+        totals.largest.truncate(LARGEST_EXPENSES);
+        Ok(totals)
     }
 
     /*
@@ -38,11 +225,28 @@ impl ReportCommand {
         Pengeluaran <start_date> -> <end_date>:
 
         Kategori:
-        1. Makanan: Rp. 100.000
-        2. Transportasi: Rp. 50.000
-        3. Tidak Berkategori: Rp. 25.000
+        1. Makanan: Rp. 100.000 (+25%)
+        2. Transportasi: Rp. 50.000 (-10%)
+        3. Tidak Berkategori: Rp. 25.000 (baru)
 
         Total: Rp. 175.000
+        Periode sebelumnya: Rp. 150.000 (+16.7%)
+        Perkiraan akhir periode: Rp. 420.000 (Rp. 360.000 - Rp. 490.000) (only for the current cycle)
+
+        Per Anggota: (only for groups with more than one member)
+        1. budi: Rp. 125.000, 71.4% dari total (+5%)
+        2. siti: Rp. 50.000, 28.6% dari total (-20%)
+
+        Pengeluaran Terbesar:
+        1. Sepatu (12/08/2025): Rp. 60.000
+        2. Bensin (03/08/2025): Rp. 40.000
+        3. Nasi Padang (10/08/2025): Rp. 30.000
+
+        Trip (di luar siklus bulanan): (only when entries were assigned to an envelope)
+        1. Bali: Rp. 1.000.000
+
+        Pemasukan: Rp. 500.000
+        Arus Kas Bersih: -Rp. 675.000
     */
 
     pub async fn run(
@@ -51,77 +255,70 @@ impl ReportCommand {
         tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
         lang: &Lang,
     ) -> Result<String> {
-        let _command = Self::parse_command(raw_message)?;
-
-        // Get expenses for the current month based on each user's start_over_date
-        let mut category_totals: HashMap<String, f64> = HashMap::new();
-        let mut total_expenses = 0.0;
-        let mut earliest_start = Utc::now();
-        let mut latest_end = Utc::now() - Duration::days(365); // Far in the past
+        let command = Self::parse_command(raw_message)?;
 
         let group = ExpenseGroupRepo::get(tx, binding.group_uid).await?;
-        let (start_date, end_date) = Self::calculate_month_range(group.start_over_date);
+        let settings = GroupSettingsRepo::get(tx, group.uid).await?;
+        let ((start, end), previous_range) = command.ranges(group.start_over_date, &settings);
+        let (start_date, end_date) = (midnight(start), midnight(end));
         info!(
             "Calculating report for group {} from {} to {}",
             group.name, start_date, end_date
         );
 
-        // Track the overall date range
-        if start_date < earliest_start {
-            earliest_start = start_date;
-        }
-        if end_date > latest_end {
-            latest_end = end_date;
-        }
-
-        // Query expenses for this user in the current month
-        let expenses = sqlx::query(
-            r#"
-            SELECT e.price::float8 AS price, c.name as category_name
-            FROM expense_entries e
-            LEFT JOIN categories c ON e.category_uid = c.uid
-            WHERE e.group_uid = $1
-              AND e.created_at >= $2
-              AND e.created_at < $3
-            "#,
+        // Everything is reported in the group's currency
+        let rates = ExchangeRateRepo::rate_table(tx).await?;
+        let current = Self::period_totals(
+            tx,
+            binding.group_uid,
+            (start, end),
+            &rates,
+            &group.currency,
+            lang,
         )
-        .bind(binding.group_uid)
-        .bind(start_date)
-        .bind(end_date)
-        .fetch_all(tx.as_mut())
         .await?;
 
-        for row in expenses {
-            let price: f64 = row.get("price");
-            let category_name: Option<String> = row.get("category_name");
-            let category_name = category_name.unwrap_or_else(|| lang.get("REPORT__UNCATEGORIZED"));
-            *category_totals.entry(category_name).or_insert(0.0) += price;
-            total_expenses += price;
-        }
+        let total_income =
+            IncomeEntryRepo::sum_by_group_in_range(tx, binding.group_uid, start_date, end_date)
+                .await?;
 
-        if total_expenses == 0.0 {
+        let envelope_total: Decimal = current.envelopes.values().copied().sum();
+        if current.total.is_zero() && envelope_total.is_zero() && total_income.is_zero() {
             return Ok(lang.get("REPORT__NO_EXPENSES"));
         }
 
+        let previous = Self::period_totals(
+            tx,
+            binding.group_uid,
+            previous_range,
+            &rates,
+            &group.currency,
+            lang,
+        )
+        .await?;
+
         // Format the response
         let mut response = lang.get_with_vars(
             "REPORT__HEADER",
             HashMap::from([
                 (
                     "start_date".to_string(),
-                    earliest_start.format("%d/%m/%Y").to_string(),
-                ),
-                (
-                    "end_date".to_string(),
-                    latest_end.format("%d/%m/%Y").to_string(),
+                    start.format("%d/%m/%Y").to_string(),
                 ),
+                ("end_date".to_string(), end.format("%d/%m/%Y").to_string()),
             ]),
         );
+        if ClosedPeriodRepo::find_covering(tx, binding.group_uid, start_date, end_date)
+            .await?
+            .is_some()
+        {
+            response.push_str(&lang.get("REPORT__PERIOD_CLOSED"));
+        }
 
         response.push_str(&lang.get("REPORT__CATEGORY_HEADER"));
 
-        let mut sorted_categories: Vec<_> = category_totals.iter().collect();
-        sorted_categories.sort_by(|a, b| b.1.partial_cmp(a.1).unwrap()); // Sort by amount descending
+        let mut sorted_categories: Vec<_> = current.categories.iter().collect();
+        sorted_categories.sort_by(|a, b| b.1.cmp(a.1)); // Sort by amount descending
 
         for (index, (category, amount)) in sorted_categories.iter().enumerate() {
             response.push_str(&lang.get_with_vars(
@@ -129,68 +326,229 @@ impl ReportCommand {
                 HashMap::from([
                     ("index".to_string(), (index + 1).to_string()),
                     ("category".to_string(), (*category).clone()),
-                    ("amount".to_string(), format_price(**amount)),
+                    ("amount".to_string(), format_price_in(**amount, &group.currency)),
+                    (
+                        "change".to_string(),
+                        format_change(**amount, previous.categories.get(*category), lang),
+                    ),
                 ]),
             ));
         }
 
         response.push_str(&lang.get_with_vars(
             "REPORT__TOTAL",
-            HashMap::from([("total".to_string(), format_price(total_expenses))]),
+            HashMap::from([(
+                "total".to_string(),
+                format_price_in(current.total, &group.currency),
+            )]),
+        ));
+        response.push_str(&previous_comparison(
+            current.total,
+            previous.total,
+            &group.currency,
+            lang,
         ));
 
-        Ok(response)
-    }
+        // Only the running cycle has days left to forecast
+        if command.period == ReportPeriod::Current {
+            let forecast = group_forecast(tx, &group, Utc::now().date_naive()).await?;
+            if forecast.days_elapsed < forecast.days_in_period {
+                let projection = forecast.projection;
+                response.push_str(&lang.get_with_vars(
+                    "REPORT__FORECAST",
+                    HashMap::from([
+                        (
+                            "total".to_string(),
+                            format_price_in(projection.projected_total, &group.currency),
+                        ),
+                        (
+                            "lower".to_string(),
+                            format_price_in(projection.lower_bound, &group.currency),
+                        ),
+                        (
+                            "upper".to_string(),
+                            format_price_in(projection.upper_bound, &group.currency),
+                        ),
+                    ]),
+                ));
+            }
+        }
 
-    /*
-     * Calculate the start and end date for the monthly report based on the user's start_over_date
-     * For example, if today is 15th June and start_over_date is 10,
-     * the range is 10th June to 10th July.
-     * If today is 5th June and start_over_date is 10,
-     * the range is 10th May to 10th June.
-     */
-    fn calculate_month_range(
-        start_over_date: i16,
-    ) -> (chrono::DateTime<Utc>, chrono::DateTime<Utc>) {
-        let now = Utc::now();
-        let current_year = now.year();
-        let current_month = now.month();
-        let current_start_over_date =
-            NaiveDate::from_ymd_opt(current_year, current_month, start_over_date as u32)
-                .unwrap_or_else(|| {
-                    NaiveDate::from_ymd_opt(current_year, current_month, 1).unwrap()
-                });
-
-        let start_date = if current_start_over_date > now.date_naive() {
-            // If the start_over_date hasn't occurred yet this month, use last month's date
-            if current_month == 1 {
-                NaiveDate::from_ymd_opt(current_year - 1, 12, start_over_date as u32)
-            } else {
-                NaiveDate::from_ymd_opt(current_year, current_month - 1, start_over_date as u32)
+        if GroupMemberRepo::count_by_group(tx, binding.group_uid).await? > 1 {
+            response.push_str(&lang.get("REPORT__MEMBER_HEADER"));
+
+            let mut sorted_members: Vec<_> = current.members.iter().collect();
+            sorted_members.sort_by(|a, b| b.1.cmp(a.1));
+
+            for (index, (member, amount)) in sorted_members.iter().enumerate() {
+                // Entries of zero can make the total zero
+                let share = percentage_of(**amount, current.total);
+                response.push_str(&lang.get_with_vars(
+                    "REPORT__MEMBER_ITEM",
+                    HashMap::from([
+                        ("index".to_string(), (index + 1).to_string()),
+                        ("member".to_string(), (*member).clone()),
+                        ("amount".to_string(), format_price_in(**amount, &group.currency)),
+                        ("share".to_string(), share.normalize().to_string()),
+                        (
+                            "change".to_string(),
+                            format_change(**amount, previous.members.get(*member), lang),
+                        ),
+                    ]),
+                ));
             }
-            .unwrap_or_else(|| NaiveDate::from_ymd_opt(current_year, current_month - 1, 1).unwrap())
-        } else {
-            current_start_over_date
-        };
+        }
 
-        let end_date = if start_date.month() == 12 {
-            NaiveDate::from_ymd_opt(start_date.year() + 1, 1, start_over_date as u32)
-        } else {
-            NaiveDate::from_ymd_opt(
-                start_date.year(),
-                start_date.month() + 1,
-                start_over_date as u32,
-            )
+        if !current.largest.is_empty() {
+            response.push_str(&lang.get("REPORT__LARGEST_HEADER"));
+
+            for (index, expense) in current.largest.iter().enumerate() {
+                response.push_str(&lang.get_with_vars(
+                    "REPORT__LARGEST_ITEM",
+                    HashMap::from([
+                        ("index".to_string(), (index + 1).to_string()),
+                        ("item".to_string(), expense.product.clone()),
+                        ("date".to_string(), expense.date.format("%d/%m/%Y").to_string()),
+                        (
+                            "amount".to_string(),
+                            format_price_in(expense.amount, &group.currency),
+                        ),
+                    ]),
+                ));
+            }
         }
-        .unwrap_or_else(|| {
-            NaiveDate::from_ymd_opt(start_date.year(), start_date.month() + 1, 1).unwrap()
-        });
 
-        (
-            start_date.and_hms_opt(0, 0, 0).unwrap().and_utc(),
-            end_date.and_hms_opt(0, 0, 0).unwrap().and_utc(),
-        )
+        if !current.envelopes.is_empty() {
+            response.push_str(&lang.get("REPORT__ENVELOPE_HEADER"));
+
+            let mut sorted_envelopes: Vec<_> = current.envelopes.iter().collect();
+            sorted_envelopes.sort_by(|a, b| b.1.cmp(a.1));
+
+            for (index, (envelope, amount)) in sorted_envelopes.iter().enumerate() {
+                response.push_str(&lang.get_with_vars(
+                    "REPORT__ENVELOPE_ITEM",
+                    HashMap::from([
+                        ("index".to_string(), (index + 1).to_string()),
+                        ("envelope".to_string(), (*envelope).clone()),
+                        ("amount".to_string(), format_price_in(**amount, &group.currency)),
+                    ]),
+                ));
+            }
+        }
+
+        if total_income > Decimal::ZERO {
+            // Trips are paid from the same income
+            let net = total_income - current.total - envelope_total;
+            response.push_str(&lang.get_with_vars(
+                "REPORT__INCOME_TOTAL",
+                HashMap::from([(
+                    "total".to_string(),
+                    format_price_in(total_income, &group.currency),
+                )]),
+            ));
+            response.push_str(&lang.get_with_vars(
+                "REPORT__NET_CASH_FLOW",
+                HashMap::from([
+                    (
+                        "sign".to_string(),
+                        if net < Decimal::ZERO { "-" } else { "" }.to_string(),
+                    ),
+                    ("total".to_string(), format_price_in(net.abs(), &group.currency)),
+                ]),
+            ));
+        }
+
+        if !current.unconverted.is_empty() {
+            response.push_str(&lang.get_with_vars(
+                "REPORT__UNCONVERTED",
+                HashMap::from([("currencies".to_string(), current.unconverted.join(", "))]),
+            ));
+        }
+
+        Ok(response)
+    }
+}
+
+// What a period of the report adds up to, in the group's currency
+#[derive(Debug, Default)]
+struct PeriodTotals {
+    categories: HashMap<String, Decimal>,
+    members: HashMap<String, Decimal>,
+    envelopes: HashMap<String, Decimal>,
+    // Regular cycle only, envelopes are left out
+    total: Decimal,
+    // Largest first, at most `LARGEST_EXPENSES`
+    largest: Vec<LargeExpense>,
+    // Currencies without a rate, added without conversion
+    unconverted: Vec<String>,
+}
+
+#[derive(Debug)]
+struct LargeExpense {
+    product: String,
+    amount: Decimal,
+    date: NaiveDate,
+}
+
+fn midnight(date: NaiveDate) -> DateTime<Utc> {
+    date.and_hms_opt(0, 0, 0).unwrap().and_utc()
+}
+
+fn percentage_of(amount: Decimal, total: Decimal) -> Decimal {
+    if total.is_zero() {
+        return Decimal::ZERO;
     }
+    (amount / total * Decimal::ONE_HUNDRED).round_dp(1)
+}
+
+// Change from `previous` to `total` in percent, None without anything to compare against
+fn percentage_change(total: Decimal, previous: Decimal) -> Option<Decimal> {
+    if previous <= Decimal::ZERO {
+        return None;
+    }
+    Some(((total - previous) / previous * Decimal::ONE_HUNDRED).round_dp(1))
+}
+
+// " (+25%)" after a category or member, " (new)" when it had nothing in the previous period
+fn format_change(total: Decimal, previous: Option<&Decimal>, lang: &Lang) -> String {
+    let Some(change) = percentage_change(total, previous.copied().unwrap_or_default()) else {
+        return lang.get("REPORT__CHANGE_NEW");
+    };
+    lang.get_with_vars(
+        "REPORT__CHANGE",
+        HashMap::from([
+            (
+                "sign".to_string(),
+                if change < Decimal::ZERO { "-" } else { "+" }.to_string(),
+            ),
+            ("percentage".to_string(), change.abs().normalize().to_string()),
+        ]),
+    )
+}
+
+fn previous_comparison(
+    total: Decimal,
+    previous_total: Decimal,
+    currency: &str,
+    lang: &Lang,
+) -> String {
+    let Some(change) = percentage_change(total, previous_total) else {
+        return lang.get("REPORT__PREVIOUS_EMPTY");
+    };
+    lang.get_with_vars(
+        "REPORT__PREVIOUS_COMPARISON",
+        HashMap::from([
+            (
+                "total".to_string(),
+                format_price_in(previous_total, currency),
+            ),
+            (
+                "sign".to_string(),
+                if change < Decimal::ZERO { "-" } else { "+" }.to_string(),
+            ),
+            ("percentage".to_string(), change.abs().to_string()),
+        ]),
+    )
 }
 
 impl Command for ReportCommand {
@@ -201,9 +559,114 @@ impl Command for ReportCommand {
     fn get_instruction_text_key() -> &'static str {
         "MESSENGER__REPORT_SHORT_INSTRUCTION"
     }
+
+    fn get_help_text_key() -> Option<&'static str> {
+        Some("MESSENGER__REPORT_HELP")
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(
+            ReportCommand::parse_command("/report").unwrap().period,
+            ReportPeriod::Current
+        );
+        assert_eq!(
+            ReportCommand::parse_command("/report last").unwrap().period,
+            ReportPeriod::Last
+        );
+        assert_eq!(
+            ReportCommand::parse_command("/report week").unwrap().period,
+            ReportPeriod::Week
+        );
+        assert_eq!(
+            ReportCommand::parse_command("/report 2025-08")
+                .unwrap()
+                .period,
+            ReportPeriod::Month {
+                year: 2025,
+                month: 8
+            }
+        );
+        assert_eq!(
+            ReportCommand::parse_command("/report 2025-08-01 2025-09-01")
+                .unwrap()
+                .period,
+            ReportPeriod::Range {
+                start: date(2025, 8, 1),
+                end: date(2025, 9, 1)
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_command_invalid() {
+        assert!(ReportCommand::parse_command("/report 2025-13").is_err());
+        assert!(ReportCommand::parse_command("/report 2025-08-01").is_err());
+        assert!(ReportCommand::parse_command("/report 2025-09-01 2025-08-01").is_err());
+        assert!(ReportCommand::parse_command("/report 2024-01-01 2025-06-01").is_err());
+        assert!(ReportCommand::parse_command("/report a b c").is_err());
+    }
+
+    #[test]
+    fn test_ranges() {
+        let command = ReportCommand {
+            period: ReportPeriod::Month {
+                year: 2025,
+                month: 8,
+            },
+        };
+        assert_eq!(
+            command.ranges(25, &GroupSettings::default()),
+            (
+                (date(2025, 8, 25), date(2025, 9, 25)),
+                (date(2025, 7, 25), date(2025, 8, 25))
+            )
+        );
+
+        let command = ReportCommand {
+            period: ReportPeriod::Range {
+                start: date(2025, 8, 10),
+                end: date(2025, 8, 20),
+            },
+        };
+        assert_eq!(
+            command.ranges(1, &GroupSettings::default()),
+            (
+                (date(2025, 8, 10), date(2025, 8, 20)),
+                (date(2025, 7, 31), date(2025, 8, 10))
+            )
+        );
+    }
+
+    #[test]
+    fn test_percentage_change() {
+        assert_eq!(
+            percentage_change(Decimal::from(125), Decimal::from(100)),
+            Some(Decimal::from(25))
+        );
+        assert_eq!(
+            percentage_change(Decimal::from(100), Decimal::from(150)),
+            Some(Decimal::new(-333, 1))
+        );
+        assert_eq!(percentage_change(Decimal::from(50), Decimal::ZERO), None);
+    }
+
+    #[test]
+    fn test_format_change() {
+        let lang = Lang::from_json("en");
+        assert_eq!(
+            format_change(Decimal::from(90), Some(&Decimal::from(100)), &lang),
+            " (-10%)"
+        );
+        assert_eq!(format_change(Decimal::from(90), None, &lang), " (new)");
+    }
 }
@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use crate::{
+    commands::base::Command,
+    lang::Lang,
+    repos::{chat_binding::ChatBinding, expense_entry::ExpenseEntryRepo},
+    utils::parse_price::format_price_in,
+};
+
+const SEARCH_RESULT_LIMIT: i64 = 10;
+
+#[derive(Debug)]
+pub struct SearchCommand {
+    pub term: String,
+}
+
+impl SearchCommand {
+    /*
+     Expected format:
+     /search [term]
+
+     Examples:
+     /search kopi
+     /search nasi padang
+    */
+    fn parse_command(input: &str) -> Result<Self> {
+        let input = input.trim();
+
+        // Should start with /search
+        let input = if input.starts_with(Self::get_command()) {
+            input[Self::get_command().len()..].trim()
+        } else {
+            input
+        };
+
+        let term = input.split_whitespace().collect::<Vec<_>>().join(" ");
+        if term.is_empty() {
+            return Err(anyhow::anyhow!("Search term cannot be empty"));
+        }
+
+        Ok(Self { term })
+    }
+
+    /*
+       Output format:
+       🔎 Hasil pencarian "<term>": (can be found on lang/id.json)
+
+       <date> <id>
+       <name>, Rp. <price>
+    */
+    pub async fn run(
+        raw_message: &str,
+        binding: &ChatBinding,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        lang: &Lang,
+    ) -> Result<String> {
+        let command = Self::parse_command(raw_message)?;
+
+        let results = ExpenseEntryRepo::search_by_group(
+            tx,
+            binding.group_uid,
+            &command.term,
+            SEARCH_RESULT_LIMIT,
+        )
+        .await?;
+
+        if results.is_empty() {
+            return Ok(lang.get_with_vars(
+                "MESSENGER__SEARCH_EMPTY",
+                HashMap::from([("term".to_string(), command.term)]),
+            ));
+        }
+
+        let mut response = lang.get_with_vars(
+            "MESSENGER__SEARCH_HEADER",
+            HashMap::from([("term".to_string(), command.term)]),
+        );
+        for result in results {
+            let entry = result.entry;
+            response.push_str(&lang.get_with_vars(
+                "MESSENGER__SEARCH_ENTRY",
+                HashMap::from([
+                    (
+                        "date".to_string(),
+                        entry.created_at.format("%Y-%m-%d").to_string(),
+                    ),
+                    ("id".to_string(), entry.uid.to_string()),
+                    ("item".to_string(), entry.product),
+                    ("price".to_string(), format_price_in(entry.price, &entry.currency)),
+                ]),
+            ));
+        }
+
+        Ok(response)
+    }
+}
+
+impl Command for SearchCommand {
+    fn get_command() -> &'static str {
+        "/search"
+    }
+
+    fn get_instruction_text_key() -> &'static str {
+        "MESSENGER__SEARCH_SHORT_INSTRUCTION"
+    }
+
+    fn get_help_text_key() -> Option<&'static str> {
+        Some("MESSENGER__SEARCH_HELP")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_command() {
+        let command = SearchCommand::parse_command("/search   nasi   padang ").unwrap();
+        assert_eq!(command.term, "nasi padang");
+    }
+
+    #[test]
+    fn test_parse_command_empty() {
+        assert!(SearchCommand::parse_command("/search").is_err());
+        assert!(SearchCommand::parse_command("/search   ").is_err());
+    }
+}
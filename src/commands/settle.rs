@@ -0,0 +1,352 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+use crate::{
+    commands::{
+        base::{ChatButton, ChatReply, ChatSender, Command},
+        callback::CallbackAction,
+    },
+    lang::Lang,
+    repos::{
+        audit_log::{AuditActor, AuditChange, AuditEntity, AuditRepo},
+        chat_binding::ChatBinding,
+        expense_group::ExpenseGroupRepo,
+        settlement::{CreateSettlementDbPayload, Settlement, SettlementRepo},
+        user::UserRepo,
+    },
+    utils::parse_price::{format_price_in, parse_price},
+};
+
+// Confirms a settlement on messengers without buttons
+const CONFIRM_ARGUMENTS: [&str; 2] = ["confirm", "konfirmasi"];
+
+#[derive(Debug, PartialEq)]
+pub enum SettleCommand {
+    Balances,
+    Confirm {
+        uid: Uuid,
+    },
+    Create {
+        member: String,
+        amount: Decimal,
+        note: Option<String>,
+    },
+}
+
+impl SettleCommand {
+    /*
+     Expected format:
+     /settle
+     -> where every member stands, confirmed settlements included
+
+     /settle @[member] [amount] [optional note]
+     -> record that the sender paid a member back, the member is the part of their email
+        before the `@`
+
+     /settle konfirmasi [id]
+     -> the receiving member confirms the settlement, chats with buttons get a button instead
+
+     Examples:
+     /settle @budi 50.000
+     /settle @siti 120.000 patungan listrik
+    */
+    fn parse_command(input: &str) -> Result<Self> {
+        let args = input
+            .trim()
+            .strip_prefix(Self::get_command())
+            .ok_or_else(|| anyhow::anyhow!("Invalid format: expected /settle"))?;
+        if !args.is_empty() && !args.starts_with(char::is_whitespace) {
+            return Err(anyhow::anyhow!("Invalid format: expected /settle"));
+        }
+
+        let mut words = args.split_whitespace();
+        let Some(first) = words.next() else {
+            return Ok(Self::Balances);
+        };
+        if CONFIRM_ARGUMENTS.contains(&first.to_lowercase().as_str()) {
+            let uid = words
+                .next()
+                .ok_or_else(|| anyhow::anyhow!("Missing settlement id: {}", args.trim()))?;
+            let uid = Uuid::parse_str(uid).map_err(|_| anyhow::anyhow!("Invalid id: {}", uid))?;
+            return Ok(Self::Confirm { uid });
+        }
+
+        let member = first
+            .strip_prefix('@')
+            .filter(|member| !member.is_empty())
+            .ok_or_else(|| anyhow::anyhow!("Invalid format: expected /settle @[member] [amount]"))?
+            .to_lowercase();
+        let amount = words
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("Missing amount: {}", args.trim()))?;
+        let amount = parse_price(amount)
+            .ok()
+            .filter(|amount| *amount > Decimal::ZERO)
+            .ok_or_else(|| anyhow::anyhow!("Invalid amount format: {}", amount))?;
+        let note = words.collect::<Vec<_>>().join(" ");
+
+        Ok(Self::Create {
+            member,
+            amount,
+            note: (!note.is_empty()).then_some(note),
+        })
+    }
+
+    /*
+     Output format:
+     💸 Saldo anggota: (can be found on lang/id.json)
+
+     1. budi menerima Rp. 50.000
+     2. siti membayar Rp. 50.000
+
+     Recording a settlement replies with a confirm button for the receiving member, see
+     `confirm`.
+    */
+    pub async fn run(
+        raw_message: &str,
+        binding: &ChatBinding,
+        sender: &ChatSender,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        lang: &Lang,
+        buttons: bool,
+    ) -> Result<ChatReply> {
+        let command = Self::parse_command(raw_message)?;
+        let group = ExpenseGroupRepo::get(tx, binding.group_uid).await?;
+        let balances =
+            SettlementRepo::member_balances(tx, binding.group_uid, &group.currency).await?;
+        let member_uids: Vec<Uuid> = balances.iter().map(|balance| balance.user_uid).collect();
+        let names = Self::member_names(tx, &member_uids).await?;
+
+        match command {
+            Self::Balances => {
+                let mut response = lang.get("MESSENGER__SETTLE_BALANCES_HEADER");
+                for (index, balance) in balances.iter().enumerate() {
+                    let key = match balance.balance {
+                        b if b > Decimal::ZERO => "MESSENGER__SETTLE_BALANCE_OWED",
+                        b if b < Decimal::ZERO => "MESSENGER__SETTLE_BALANCE_OWES",
+                        _ => "MESSENGER__SETTLE_BALANCE_EVEN",
+                    };
+                    response.push_str(&lang.get_with_vars(
+                        key,
+                        HashMap::from([
+                            ("index".to_string(), (index + 1).to_string()),
+                            (
+                                "name".to_string(),
+                                names.get(&balance.user_uid).cloned().unwrap_or_default(),
+                            ),
+                            (
+                                "amount".to_string(),
+                                format_price_in(balance.balance.abs(), &balance.currency),
+                            ),
+                        ]),
+                    ));
+                }
+                Ok(response.into())
+            }
+            Self::Confirm { uid } => Self::confirm(uid, binding, sender, tx, lang).await,
+            Self::Create {
+                member,
+                amount,
+                note,
+            } => {
+                let from_user_uid = sender.resolve_user_uid(binding).ok_or_else(|| {
+                    anyhow::anyhow!("Link your account with /link-me before recording a settlement")
+                })?;
+                let named: Vec<Uuid> = names
+                    .iter()
+                    .filter(|(_, name)| name.to_lowercase() == member)
+                    .map(|(uid, _)| *uid)
+                    .collect();
+                let to_user_uid = match named.as_slice() {
+                    [uid] => *uid,
+                    [] => return Err(anyhow::anyhow!("No member named @{} in this group", member)),
+                    _ => {
+                        return Err(anyhow::anyhow!(
+                            "Several members are named @{}, record the settlement in the app",
+                            member
+                        ));
+                    }
+                };
+                if to_user_uid == from_user_uid {
+                    return Err(anyhow::anyhow!("Cannot settle with yourself"));
+                }
+
+                let created = SettlementRepo::create(
+                    tx,
+                    CreateSettlementDbPayload {
+                        group_uid: binding.group_uid,
+                        from_user_uid,
+                        to_user_uid,
+                        amount,
+                        currency: group.currency,
+                        note,
+                    },
+                )
+                .await?;
+                AuditRepo::record(
+                    tx,
+                    &AuditActor::from_chat(binding, sender),
+                    AuditEntity::Settlement,
+                    binding.group_uid,
+                    created.uid,
+                    AuditChange::create(&created),
+                )
+                .await?;
+
+                let response =
+                    lang.get_with_vars("MESSENGER__SETTLE_CREATED", Self::vars(&created, &names));
+                if !buttons {
+                    return Ok(response.into());
+                }
+                Ok(
+                    ChatReply::from(response).with_buttons(vec![vec![ChatButton::new(
+                        lang.get("MESSENGER__SETTLE_CONFIRM"),
+                        &CallbackAction::ConfirmSettlement {
+                            settlement_uid: created.uid,
+                        },
+                    )]]),
+                )
+            }
+        }
+    }
+
+    // Only the receiving member can confirm, from the button or with /settle konfirmasi
+    pub async fn confirm(
+        uid: Uuid,
+        binding: &ChatBinding,
+        sender: &ChatSender,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        lang: &Lang,
+    ) -> Result<ChatReply> {
+        let prev_settlement = SettlementRepo::get(tx, uid)
+            .await
+            .ok()
+            .filter(|settlement| settlement.group_uid == binding.group_uid)
+            .ok_or_else(|| anyhow::anyhow!("Settlement {} not found", uid))?;
+        if sender.resolve_user_uid(binding) != Some(prev_settlement.to_user_uid) {
+            return Err(anyhow::anyhow!(
+                "Only the receiving member can confirm a settlement"
+            ));
+        }
+        if prev_settlement.is_confirmed() {
+            return Err(anyhow::anyhow!("Settlement is already confirmed"));
+        }
+
+        let confirmed = SettlementRepo::confirm(tx, uid).await?;
+        AuditRepo::record(
+            tx,
+            &AuditActor::from_chat(binding, sender),
+            AuditEntity::Settlement,
+            binding.group_uid,
+            uid,
+            AuditChange::update(&prev_settlement, &confirmed),
+        )
+        .await?;
+
+        let names =
+            Self::member_names(tx, &[confirmed.from_user_uid, confirmed.to_user_uid]).await?;
+        Ok(lang
+            .get_with_vars(
+                "MESSENGER__SETTLE_CONFIRMED",
+                Self::vars(&confirmed, &names),
+            )
+            .into())
+    }
+
+    // Members go by the part of their email before the `@`, like /history @member
+    async fn member_names(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uids: &[Uuid],
+    ) -> Result<HashMap<Uuid, String>> {
+        Ok(UserRepo::emails_by_uids(tx, uids)
+            .await?
+            .into_iter()
+            .filter_map(|(uid, email)| Some((uid, email.split('@').next()?.to_string())))
+            .collect())
+    }
+
+    fn vars(settlement: &Settlement, names: &HashMap<Uuid, String>) -> HashMap<String, String> {
+        let name = |uid| names.get(uid).cloned().unwrap_or_default();
+        HashMap::from([
+            ("id".to_string(), settlement.uid.to_string()),
+            ("from".to_string(), name(&settlement.from_user_uid)),
+            ("to".to_string(), name(&settlement.to_user_uid)),
+            (
+                "amount".to_string(),
+                format_price_in(settlement.amount, &settlement.currency),
+            ),
+        ])
+    }
+}
+
+impl Command for SettleCommand {
+    fn get_command() -> &'static str {
+        "/settle"
+    }
+
+    fn get_instruction_text_key() -> &'static str {
+        "MESSENGER__SETTLE_SHORT_INSTRUCTION"
+    }
+
+    fn get_help_text_key() -> Option<&'static str> {
+        Some("MESSENGER__SETTLE_HELP")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parse_command_balances() {
+        assert_eq!(
+            SettleCommand::parse_command("/settle").unwrap(),
+            SettleCommand::Balances
+        );
+        assert!(SettleCommand::parse_command("/settlement").is_err());
+    }
+
+    #[test]
+    fn test_parse_command_create() {
+        assert_eq!(
+            SettleCommand::parse_command("/settle @Budi 50.000").unwrap(),
+            SettleCommand::Create {
+                member: "budi".to_string(),
+                amount: dec!(50000),
+                note: None,
+            }
+        );
+        assert_eq!(
+            SettleCommand::parse_command("/settle @siti 120.000 patungan listrik").unwrap(),
+            SettleCommand::Create {
+                member: "siti".to_string(),
+                amount: dec!(120000),
+                note: Some("patungan listrik".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_command_confirm() {
+        let uid = Uuid::new_v4();
+        assert_eq!(
+            SettleCommand::parse_command(&format!("/settle KONFIRMASI {}", uid)).unwrap(),
+            SettleCommand::Confirm { uid }
+        );
+        assert!(SettleCommand::parse_command("/settle confirm").is_err());
+        assert!(SettleCommand::parse_command("/settle confirm 123").is_err());
+    }
+
+    #[test]
+    fn test_parse_command_invalid() {
+        assert!(SettleCommand::parse_command("/settle budi 50.000").is_err());
+        assert!(SettleCommand::parse_command("/settle @ 50.000").is_err());
+        assert!(SettleCommand::parse_command("/settle @budi").is_err());
+        assert!(SettleCommand::parse_command("/settle @budi abc").is_err());
+        assert!(SettleCommand::parse_command("/settle @budi 0").is_err());
+    }
+}
@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use crate::{
+    commands::base::Command,
+    error::DatabaseError,
+    lang::Lang,
+    repos::{
+        chat_binding::{ChatBinding, ChatBindingRepo},
+        expense_group::ExpenseGroupRepo,
+    },
+};
+
+#[derive(Debug, PartialEq)]
+pub struct SwitchCommand {
+    pub group_name: Option<String>,
+}
+
+impl SwitchCommand {
+    /*
+        Should be in format:
+        /switch
+        /switch [nama grup]
+    */
+    fn parse_command(input: &str) -> Result<Self> {
+        let args = input
+            .trim()
+            .strip_prefix(Self::get_command())
+            .ok_or_else(|| anyhow::anyhow!("Invalid format: expected /switch"))?;
+        if !args.is_empty() && !args.starts_with(char::is_whitespace) {
+            return Err(anyhow::anyhow!("Invalid format: expected /switch"));
+        }
+
+        let group_name = args.trim();
+        Ok(Self {
+            group_name: (!group_name.is_empty()).then(|| group_name.to_string()),
+        })
+    }
+
+    /*
+        Without a name lists the groups the chat is bound to, otherwise makes the group with
+        that name (case-insensitive) the one commands go to.
+
+        Output format:
+
+        Chat ini terhubung dengan grup berikut:
+        1. Rumah (aktif)
+        2. Kantor
+    */
+    pub async fn run(
+        raw_message: &str,
+        binding: &ChatBinding,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        lang: &Lang,
+    ) -> Result<String> {
+        let command = Self::parse_command(raw_message)?;
+
+        let bindings =
+            ChatBindingRepo::list_active_by_chat(tx, &binding.platform, &binding.p_uid).await?;
+        let mut groups = Vec::with_capacity(bindings.len());
+        for b in bindings {
+            // Soft-deleted groups cannot be switched to until they are restored
+            match ExpenseGroupRepo::get(tx, b.group_uid).await {
+                Ok(group) => groups.push((b, group)),
+                Err(DatabaseError::NotFound(_)) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        let Some(group_name) = command.group_name else {
+            let mut response = format!("{}\n", lang.get("MESSENGER__SWITCH_LIST_HEADER"));
+            for (index, (b, group)) in groups.iter().enumerate() {
+                let mut line = format!("{}. {}", index + 1, group.name);
+                if b.id == binding.id {
+                    line.push_str(&lang.get("MESSENGER__SWITCH_ACTIVE_MARK"));
+                }
+                response.push_str(&line);
+                response.push('\n');
+            }
+            return Ok(response);
+        };
+
+        let (target, group) = groups
+            .into_iter()
+            .find(|(_, group)| group.name.eq_ignore_ascii_case(&group_name))
+            .ok_or_else(|| anyhow::anyhow!("Group '{}' is not bound to this chat", group_name))?;
+        if target.id != binding.id {
+            ChatBindingRepo::select(tx, target.id).await?;
+        }
+
+        Ok(lang.get_with_vars(
+            "MESSENGER__SWITCH_SUCCESS",
+            HashMap::from([("group".to_string(), group.name)]),
+        ))
+    }
+}
+
+impl Command for SwitchCommand {
+    fn get_command() -> &'static str {
+        "/switch"
+    }
+
+    fn get_instruction_text_key() -> &'static str {
+        "MESSENGER__SWITCH_SHORT_INSTRUCTION"
+    }
+
+    fn get_help_text_key() -> Option<&'static str> {
+        Some("MESSENGER__SWITCH_HELP")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_command_list() {
+        let command = SwitchCommand::parse_command("/switch").unwrap();
+        assert_eq!(command, SwitchCommand { group_name: None });
+    }
+
+    #[test]
+    fn test_parse_command_group_name() {
+        let command = SwitchCommand::parse_command("/switch  Rumah Tangga \n").unwrap();
+        assert_eq!(
+            command,
+            SwitchCommand {
+                group_name: Some("Rumah Tangga".to_string())
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_command_invalid() {
+        assert!(SwitchCommand::parse_command("/switchRumah").is_err());
+    }
+}
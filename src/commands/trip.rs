@@ -0,0 +1,337 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+
+use crate::{
+    commands::base::{ChatSender, Command},
+    lang::Lang,
+    repos::{
+        audit_log::{AuditActor, AuditChange, AuditEntity, AuditRepo},
+        chat_binding::ChatBinding,
+        envelope::{CreateEnvelopeDbPayload, Envelope, EnvelopeRepo},
+        exchange_rate::ExchangeRateRepo,
+    },
+    utils::{
+        currency::RateTable,
+        parse_price::{format_price_in, parse_price},
+    },
+};
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+// Ends the active trip, later expenses go back to the regular cycle
+const OFF_ARGUMENT: &str = "off";
+
+#[derive(Debug, PartialEq)]
+pub enum TripCommand {
+    List,
+    Off,
+    Activate {
+        name: String,
+    },
+    Create {
+        name: String,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+        budget: Option<Decimal>,
+    },
+}
+
+impl TripCommand {
+    /*
+     Expected format:
+     /trip
+     -> list the trips of the group with what was spent in each
+
+     /trip [name],[start date],[end date],[optional budget]
+     -> create a trip and make it active for this chat
+
+     /trip [name]
+     -> make an existing trip active for this chat
+
+     /trip off
+     -> stop recording expenses into the active trip
+
+     Examples:
+     /trip Bali,2025-11-01,2025-11-07,5.000.000
+     /trip Bali
+    */
+    fn parse_command(input: &str) -> Result<Self> {
+        let args = input
+            .trim()
+            .strip_prefix(Self::get_command())
+            .ok_or_else(|| anyhow::anyhow!("Invalid format: expected /trip"))?;
+        if !args.is_empty() && !args.starts_with(char::is_whitespace) {
+            return Err(anyhow::anyhow!("Invalid format: expected /trip"));
+        }
+
+        let args = args.trim();
+        if args.is_empty() {
+            return Ok(Self::List);
+        }
+        if args.eq_ignore_ascii_case(OFF_ARGUMENT) {
+            return Ok(Self::Off);
+        }
+
+        let parts: Vec<&str> = args.split(',').map(|s| s.trim()).collect();
+        let name = parts[0].to_string();
+        if name.is_empty() {
+            return Err(anyhow::anyhow!("Empty trip name: {}", args));
+        }
+        match parts.len() {
+            1 => Ok(Self::Activate { name }),
+            3 | 4 => {
+                let parse_date = |date: &str| {
+                    NaiveDate::parse_from_str(date, DATE_FORMAT)
+                        .map_err(|_| anyhow::anyhow!("Invalid date format: {}", date))
+                };
+                let start_date = parse_date(parts[1])?;
+                let end_date = parse_date(parts[2])?;
+                if start_date > end_date {
+                    return Err(anyhow::anyhow!(
+                        "The trip cannot end before it starts: {}",
+                        args
+                    ));
+                }
+                let budget = match parts.get(3).filter(|budget| !budget.is_empty()) {
+                    Some(budget) => Some(
+                        parse_price(budget)
+                            .map_err(|_| anyhow::anyhow!("Invalid budget format: {}", budget))?,
+                    ),
+                    None => None,
+                };
+                Ok(Self::Create {
+                    name,
+                    start_date,
+                    end_date,
+                    budget,
+                })
+            }
+            _ => Err(anyhow::anyhow!("Invalid trip format: {}", args)),
+        }
+    }
+
+    /*
+     Output format:
+     🧳 Trip: (can be found on lang/id.json)
+
+     1. Bali (2025-11-01 - 2025-11-07) (aktif)
+     Terpakai Rp. 1.250.000 dari Rp. 5.000.000
+    */
+    pub async fn run(
+        raw_message: &str,
+        binding: &ChatBinding,
+        sender: &ChatSender,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        lang: &Lang,
+    ) -> Result<String> {
+        let command = Self::parse_command(raw_message)?;
+        let active = EnvelopeRepo::get_active_for_binding(tx, binding.id).await?;
+
+        let envelope = match command {
+            Self::List => {
+                let envelopes = EnvelopeRepo::list_by_group(tx, binding.group_uid).await?;
+                if envelopes.is_empty() {
+                    return Ok(lang.get("MESSENGER__TRIP_LIST_EMPTY"));
+                }
+                let rates = ExchangeRateRepo::rate_table(tx).await?;
+                let mut response = lang.get("MESSENGER__TRIP_LIST_HEADER");
+                for (index, envelope) in envelopes.iter().enumerate() {
+                    let is_active = active.as_ref().is_some_and(|a| a.uid == envelope.uid);
+                    response.push_str(
+                        &Self::format_item(index + 1, envelope, is_active, &rates, tx, lang)
+                            .await?,
+                    );
+                }
+                return Ok(response);
+            }
+            Self::Off => {
+                let Some(active) = active else {
+                    return Ok(lang.get("MESSENGER__TRIP_NONE_ACTIVE"));
+                };
+                EnvelopeRepo::set_active_for_binding(tx, binding.id, None).await?;
+                return Ok(lang.get_with_vars(
+                    "MESSENGER__TRIP_ENDED",
+                    HashMap::from([("name".to_string(), active.name)]),
+                ));
+            }
+            Self::Activate { name } => EnvelopeRepo::find_by_name(tx, binding.group_uid, &name)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("Trip '{}' not found", name))?,
+            Self::Create {
+                name,
+                start_date,
+                end_date,
+                budget,
+            } => {
+                if EnvelopeRepo::find_by_name(tx, binding.group_uid, &name)
+                    .await?
+                    .is_some()
+                {
+                    return Err(anyhow::anyhow!("Trip '{}' already exists", name));
+                }
+                let created = EnvelopeRepo::create(
+                    tx,
+                    CreateEnvelopeDbPayload {
+                        group_uid: binding.group_uid,
+                        name,
+                        start_date,
+                        end_date,
+                        budget,
+                        currency: None,
+                    },
+                )
+                .await?;
+                AuditRepo::record(
+                    tx,
+                    &AuditActor::from_chat(binding, sender),
+                    AuditEntity::Envelope,
+                    binding.group_uid,
+                    created.uid,
+                    AuditChange::create(&created),
+                )
+                .await?;
+                created
+            }
+        };
+
+        EnvelopeRepo::set_active_for_binding(tx, binding.id, Some(envelope.uid)).await?;
+        Ok(lang.get_with_vars(
+            "MESSENGER__TRIP_ACTIVATED",
+            HashMap::from([
+                ("name".to_string(), envelope.name),
+                (
+                    "start_date".to_string(),
+                    envelope.start_date.format(DATE_FORMAT).to_string(),
+                ),
+                (
+                    "end_date".to_string(),
+                    envelope.end_date.format(DATE_FORMAT).to_string(),
+                ),
+            ]),
+        ))
+    }
+
+    async fn format_item(
+        index: usize,
+        envelope: &Envelope,
+        is_active: bool,
+        rates: &RateTable,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        lang: &Lang,
+    ) -> Result<String> {
+        let mut totals: Vec<(String, Decimal)> = Vec::new();
+        for (_, currency, amount) in
+            EnvelopeRepo::totals_by_category(tx, envelope.uid, None).await?
+        {
+            totals.push((currency, amount));
+        }
+        let (spent, _) = rates.sum_in(&totals, &envelope.currency);
+
+        let mut line = format!(
+            "{}. {} ({} - {})",
+            index,
+            envelope.name,
+            envelope.start_date.format(DATE_FORMAT),
+            envelope.end_date.format(DATE_FORMAT)
+        );
+        if is_active {
+            line.push_str(&lang.get("MESSENGER__TRIP_ACTIVE_MARK"));
+        }
+        line.push('\n');
+        line.push_str(&match envelope.budget {
+            Some(budget) => lang.get_with_vars(
+                "MESSENGER__TRIP_SPENT_OF_BUDGET",
+                HashMap::from([
+                    (
+                        "spent".to_string(),
+                        format_price_in(spent, &envelope.currency),
+                    ),
+                    (
+                        "budget".to_string(),
+                        format_price_in(budget, &envelope.currency),
+                    ),
+                ]),
+            ),
+            None => lang.get_with_vars(
+                "MESSENGER__TRIP_SPENT",
+                HashMap::from([(
+                    "spent".to_string(),
+                    format_price_in(spent, &envelope.currency),
+                )]),
+            ),
+        });
+        Ok(line)
+    }
+}
+
+impl Command for TripCommand {
+    fn get_command() -> &'static str {
+        "/trip"
+    }
+
+    fn get_instruction_text_key() -> &'static str {
+        "MESSENGER__TRIP_SHORT_INSTRUCTION"
+    }
+
+    fn get_help_text_key() -> Option<&'static str> {
+        Some("MESSENGER__TRIP_HELP")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parse_command_list_and_off() {
+        assert_eq!(
+            TripCommand::parse_command("/trip").unwrap(),
+            TripCommand::List
+        );
+        assert_eq!(
+            TripCommand::parse_command("/trip OFF").unwrap(),
+            TripCommand::Off
+        );
+        assert!(TripCommand::parse_command("/tripx").is_err());
+    }
+
+    #[test]
+    fn test_parse_command_create() {
+        let command =
+            TripCommand::parse_command("/trip Bali trip, 2025-11-01, 2025-11-07, 5.000.000")
+                .unwrap();
+        assert_eq!(
+            command,
+            TripCommand::Create {
+                name: "Bali trip".to_string(),
+                start_date: NaiveDate::from_ymd_opt(2025, 11, 1).unwrap(),
+                end_date: NaiveDate::from_ymd_opt(2025, 11, 7).unwrap(),
+                budget: Some(dec!(5000000)),
+            }
+        );
+
+        let command = TripCommand::parse_command("/trip Bali,2025-11-01,2025-11-07").unwrap();
+        assert!(matches!(command, TripCommand::Create { budget: None, .. }));
+    }
+
+    #[test]
+    fn test_parse_command_activate() {
+        assert_eq!(
+            TripCommand::parse_command("/trip Bali trip").unwrap(),
+            TripCommand::Activate {
+                name: "Bali trip".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_command_invalid() {
+        assert!(TripCommand::parse_command("/trip Bali,2025-11-07,2025-11-01").is_err());
+        assert!(TripCommand::parse_command("/trip Bali,01-11-2025,2025-11-07").is_err());
+        assert!(TripCommand::parse_command("/trip Bali,2025-11-01").is_err());
+        assert!(TripCommand::parse_command("/trip ,2025-11-01,2025-11-07").is_err());
+    }
+}
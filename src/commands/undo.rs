@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use chrono::{Duration, Utc};
+
+use crate::{
+    commands::base::{ChatSender, Command},
+    error::DatabaseError,
+    lang::Lang,
+    repos::{
+        audit_log::{AuditActor, AuditChange, AuditEntity, AuditRepo},
+        category::CategoryRepo,
+        chat_action::{ChatAction, ChatActionRepo},
+        chat_binding::ChatBinding,
+        expense_entry::ExpenseEntryRepo,
+    },
+    webhooks::{self, WebhookEvent},
+};
+
+// How far back /undo reaches, older actions are pruned from the journal
+pub const UNDO_WINDOW_MINUTES: i64 = 15;
+
+#[derive(Debug)]
+pub struct UndoCommand;
+
+impl UndoCommand {
+    /*
+        Should be in format:
+        /undo
+    */
+    fn parse_command(input: &str) -> Result<Self> {
+        let input = input.trim();
+
+        if input != Self::get_command() {
+            return Err(anyhow::anyhow!("Invalid format: expected only /undo"));
+        }
+
+        Ok(Self {})
+    }
+
+    // Keeps `action` so the sender can revert it with /undo for a while
+    pub async fn journal(
+        binding: &ChatBinding,
+        sender: &ChatSender,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        action: &ChatAction,
+    ) -> Result<()> {
+        ChatActionRepo::record(
+            tx,
+            binding.id,
+            &sender.p_user_id,
+            action,
+            Duration::minutes(UNDO_WINDOW_MINUTES),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /*
+     Reverts the most recent action made from this chat in the undo window. Whoever bound the
+     chat can revert anyone's action, other senders only their own. Entries or categories
+     changed since by other means are skipped.
+
+     Output format:
+     ↩️ Dibatalkan: 2 pengeluaran yang baru dicatat dihapus. (can be found on lang/id.json)
+    */
+    pub async fn run(
+        raw_message: &str,
+        binding: &ChatBinding,
+        sender: &ChatSender,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        lang: &Lang,
+    ) -> Result<String> {
+        let _command = Self::parse_command(raw_message)?;
+
+        let own_only = (!sender.is_binder(binding)).then_some(sender.p_user_id.as_str());
+        let since = Utc::now() - Duration::minutes(UNDO_WINDOW_MINUTES);
+        let Some(record) = ChatActionRepo::latest(tx, binding.id, own_only, since).await? else {
+            return Ok(lang.get_with_vars(
+                "MESSENGER__UNDO_NOTHING",
+                HashMap::from([("minutes".to_string(), UNDO_WINDOW_MINUTES.to_string())]),
+            ));
+        };
+
+        let actor = AuditActor::from_chat(binding, sender);
+        let mut count = 0;
+        let key = match &record.action {
+            ChatAction::ExpenseCreate { entry_uids } => {
+                for uid in entry_uids {
+                    let expense = match ExpenseEntryRepo::soft_delete(tx, *uid).await {
+                        Ok(expense) => expense,
+                        // Deleted in the meantime
+                        Err(DatabaseError::NotFound(_)) => continue,
+                        Err(e) => return Err(e.into()),
+                    };
+                    AuditRepo::record(
+                        tx,
+                        &actor,
+                        AuditEntity::ExpenseEntry,
+                        binding.group_uid,
+                        expense.uid,
+                        AuditChange::delete(&expense),
+                    )
+                    .await?;
+                    webhooks::dispatch(
+                        tx,
+                        binding.group_uid,
+                        WebhookEvent::ExpenseDeleted,
+                        &expense,
+                    )
+                    .await?;
+                    count += 1;
+                }
+                "MESSENGER__UNDO_EXPENSE_CREATE"
+            }
+            ChatAction::ExpenseEdit { before } => {
+                for entry in before {
+                    let current = match ExpenseEntryRepo::get(tx, entry.uid).await {
+                        Ok(current) => current,
+                        Err(DatabaseError::NotFound(_)) => continue,
+                        Err(e) => return Err(e.into()),
+                    };
+                    let restored = ExpenseEntryRepo::overwrite(tx, entry).await?;
+                    AuditRepo::record(
+                        tx,
+                        &actor,
+                        AuditEntity::ExpenseEntry,
+                        binding.group_uid,
+                        restored.uid,
+                        AuditChange::update(&current, &restored),
+                    )
+                    .await?;
+                    webhooks::dispatch(
+                        tx,
+                        binding.group_uid,
+                        WebhookEvent::ExpenseUpdated,
+                        &restored,
+                    )
+                    .await?;
+                    count += 1;
+                }
+                "MESSENGER__UNDO_EXPENSE_EDIT"
+            }
+            ChatAction::ExpenseDelete { entry_uids } => {
+                for uid in entry_uids {
+                    let expense = match ExpenseEntryRepo::restore(tx, *uid).await {
+                        Ok(expense) => expense,
+                        // Restored or purged in the meantime
+                        Err(DatabaseError::NotFound(_)) => continue,
+                        Err(e) => return Err(e.into()),
+                    };
+                    AuditRepo::record(
+                        tx,
+                        &actor,
+                        AuditEntity::ExpenseEntry,
+                        binding.group_uid,
+                        expense.uid,
+                        AuditChange::restore(&expense),
+                    )
+                    .await?;
+                    webhooks::dispatch(
+                        tx,
+                        binding.group_uid,
+                        WebhookEvent::ExpenseCreated,
+                        &expense,
+                    )
+                    .await?;
+                    count += 1;
+                }
+                "MESSENGER__UNDO_EXPENSE_DELETE"
+            }
+            ChatAction::CategoryCreate { category_uids } => {
+                for uid in category_uids {
+                    let category = match CategoryRepo::get(tx, *uid).await {
+                        Ok(category) => category,
+                        Err(DatabaseError::NotFound(_)) => continue,
+                        Err(e) => return Err(e.into()),
+                    };
+                    // Entries recorded in it since are kept, uncategorized
+                    CategoryRepo::delete_reassigning(tx, category.uid, None).await?;
+                    AuditRepo::record(
+                        tx,
+                        &actor,
+                        AuditEntity::Category,
+                        binding.group_uid,
+                        category.uid,
+                        AuditChange::delete(&category),
+                    )
+                    .await?;
+                    count += 1;
+                }
+                "MESSENGER__UNDO_CATEGORY_CREATE"
+            }
+        };
+        ChatActionRepo::mark_undone(tx, record.uid).await?;
+
+        Ok(lang.get_with_vars(
+            key,
+            HashMap::from([("count".to_string(), count.to_string())]),
+        ))
+    }
+}
+
+impl Command for UndoCommand {
+    fn get_command() -> &'static str {
+        "/undo"
+    }
+
+    fn get_instruction_text_key() -> &'static str {
+        "MESSENGER__UNDO_SHORT_INSTRUCTION"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_command() {
+        assert!(UndoCommand::parse_command(" /undo ").is_ok());
+        assert!(UndoCommand::parse_command("/undo 2").is_err());
+    }
+}
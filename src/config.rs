@@ -1,41 +1,591 @@
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::Duration;
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use thiserror::Error;
+
+// Variables the server cannot start without
+const REQUIRED_VARS: &[&str] = &[
+    "CHAT_RELAY_SECRET",
+    "FRONT_END_URL",
+    "CHAT_BIND_URL",
+    "TELEGRAM_BOT_TOKEN",
+    "DATABASE_URL",
+];
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("missing required environment variables: {} (see .env.example)", .0.join(", "))]
+    Missing(Vec<&'static str>),
+    #[error("invalid {var}={value:?}: {reason}")]
+    Invalid {
+        var: &'static str,
+        value: String,
+        reason: String,
+    },
+}
+
+// Allows any origin in CORS_ALLOWED_ORIGINS
+pub const ANY_ORIGIN: &str = "*";
+
+// Applied to every request of the REST API
+#[derive(Debug, Clone)]
+pub struct HttpConfig {
+    pub request_timeout: Duration,
+    pub max_body_bytes: usize,
+    // Browser origins allowed to call the API, besides FRONT_END_URL
+    pub cors_allowed_origins: Vec<String>,
+    // Take client IPs from X-Forwarded-For, only safe behind a proxy that sets it
+    pub trust_proxy: bool,
+    // Bearer token `/metrics` asks for, the metrics are not served without one
+    pub metrics_token: Option<String>,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            request_timeout: Duration::from_secs(30),
+            // Large enough for the CSV and Excel imports
+            max_body_bytes: 10 * 1024 * 1024,
+            cors_allowed_origins: vec![
+                "http://localhost:3000".to_string(),
+                "http://localhost:5173".to_string(), // Vite dev server
+            ],
+            trust_proxy: false,
+            metrics_token: None,
+        }
+    }
+}
+
+// Postgres connection pool, see `db::connect`
+#[derive(Debug, Clone)]
+pub struct DbConfig {
+    pub max_connections: u32,
+    // How long a query waits for a free connection before failing
+    pub acquire_timeout: Duration,
+    // Postgres cancels statements running longer, None leaves them unlimited
+    pub statement_timeout: Option<Duration>,
+    // Statements taking longer are logged as warnings, the others at debug level
+    pub slow_statement_threshold: Duration,
+}
+
+impl Default for DbConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: 5,
+            acquire_timeout: Duration::from_secs(3),
+            statement_timeout: None,
+            slow_statement_threshold: Duration::from_millis(500),
+        }
+    }
+}
+
+// A Telegram chat by id, or a chat or user by @username
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChatFilter {
+    ChatId(i64),
+    // Lowercase, without the @
+    Username(String),
+}
+
+impl ChatFilter {
+    pub fn matches(&self, chat_id: i64, usernames: &[&str]) -> bool {
+        match self {
+            ChatFilter::ChatId(id) => *id == chat_id,
+            ChatFilter::Username(name) => usernames
+                .iter()
+                .any(|username| username.eq_ignore_ascii_case(name)),
+        }
+    }
+}
+
+// Who may start binding a Telegram chat, chats that are already bound are not affected
+#[derive(Debug, Clone)]
+pub struct TelegramAccessConfig {
+    // When not empty, only these chats and users get a reply before the chat is bound
+    pub allowed: Vec<ChatFilter>,
+    // Ignored before the chat is bound, even when also allowed
+    pub denied: Vec<ChatFilter>,
+    // Unbound chats get at most one reply this often, zero disables it
+    pub unbound_cooldown: Duration,
+}
+
+impl TelegramAccessConfig {
+    // Why the chat may not start a binding, None when it may
+    pub fn refusal(&self, chat_id: i64, usernames: &[&str]) -> Option<&'static str> {
+        let listed = |filters: &[ChatFilter]| {
+            filters
+                .iter()
+                .any(|filter| filter.matches(chat_id, usernames))
+        };
+        if listed(&self.denied) {
+            Some("on TELEGRAM_DENIED_CHATS")
+        } else if !self.allowed.is_empty() && !listed(&self.allowed) {
+            Some("not on TELEGRAM_ALLOWED_CHATS")
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for TelegramAccessConfig {
+    fn default() -> Self {
+        Self {
+            allowed: Vec::new(),
+            denied: Vec::new(),
+            unbound_cooldown: Duration::from_secs(5),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Config {
-    pub jwt_secret: String,
+    pub host: String,
+    pub port: u16,
+    pub http: HttpConfig,
+
+    // Signs tokens without a `kid`, only verifies them when JWT_KEYS is set
+    pub jwt_secret: Option<String>,
+    // (kid, secret) of JWT_KEYS, the first one signs new tokens
+    pub jwt_keys: Vec<(String, String)>,
+    // Encrypts emails and chat platform user ids at rest, see `utils::pii`
+    pub pii_encryption_key: Option<[u8; 32]>,
     pub chat_relay_secret: String,
     pub front_end_url: String,
     pub chat_bind_url: String,
     pub telegram_bot_token: String,
+    pub telegram_access: TelegramAccessConfig,
     pub database_url: String,
+    pub db: DbConfig,
 
     pub telegram_log_token: Option<String>,
     pub telegram_log_chat_id: Option<i64>,
+
+    pub whatsapp_access_token: Option<String>,
+    pub whatsapp_phone_number_id: Option<String>,
+    pub whatsapp_verify_token: Option<String>,
+    pub whatsapp_app_secret: Option<String>,
+
+    pub slack_bot_token: Option<String>,
+    pub slack_signing_secret: Option<String>,
+
+    pub exchange_rate_provider: Option<String>,
+    pub exchange_rate_api_key: Option<String>,
+
+    pub resend_api_key: Option<String>,
+    pub email_from: Option<String>,
+    // Inbound domain routed to POST /ingest/email by the mail provider
+    pub email_ingest_domain: Option<String>,
+
+    pub stripe_secret_key: Option<String>,
+    pub stripe_webhook_secret: Option<String>,
+    pub stripe_price_ids: Option<String>,
+
+    // Directory for uploads when no S3 bucket is configured
+    pub storage_dir: String,
+    pub s3_endpoint: Option<String>,
+    pub s3_bucket: Option<String>,
+    pub s3_region: Option<String>,
+    pub s3_access_key_id: Option<String>,
+    pub s3_secret_access_key: Option<String>,
+
+    pub data_retention_dry_run: bool,
+    pub lang_hot_reload: bool,
+
+    // Directory the nightly backup is written to, none disables it
+    pub backup_dir: Option<String>,
+    // How many nightly archives are kept in `backup_dir`
+    pub backup_keep: usize,
 }
 
 impl Config {
-    pub fn from_env() -> Self {
+    // Reports every missing required variable at once, not just the first one
+    pub fn from_env() -> Result<Self, ConfigError> {
         dotenv::dotenv().ok();
 
-        let jwt_secret = std::env::var("JWT_SECRET").unwrap();
-        let chat_relay_secret = std::env::var("CHAT_RELAY_SECRET").unwrap();
-        let front_end_url = std::env::var("FRONT_END_URL").unwrap();
-        let chat_bind_url = std::env::var("CHAT_BIND_URL").unwrap();
-        let telegram_bot_token = std::env::var("TELEGRAM_BOT_TOKEN").unwrap();
-        let database_url = std::env::var("DATABASE_URL").unwrap();
+        let unset = |var: &str| std::env::var(var).unwrap_or_default().trim().is_empty();
+        let mut missing: Vec<&'static str> = REQUIRED_VARS
+            .iter()
+            .copied()
+            .filter(|var| unset(var))
+            .collect();
+        // JWT_KEYS replaces JWT_SECRET
+        if unset("JWT_SECRET") && unset("JWT_KEYS") {
+            missing.insert(0, "JWT_SECRET");
+        }
+        if !missing.is_empty() {
+            return Err(ConfigError::Missing(missing));
+        }
+        let required = |var: &str| std::env::var(var).unwrap_or_default();
+
+        let jwt_secret = std::env::var("JWT_SECRET")
+            .ok()
+            .filter(|secret| !secret.trim().is_empty());
+        let jwt_keys = parse_jwt_keys(&std::env::var("JWT_KEYS").unwrap_or_default())?;
+        let pii_encryption_key = pii_encryption_key_from_env()?;
+        let chat_relay_secret = required("CHAT_RELAY_SECRET");
+        let front_end_url = required("FRONT_END_URL");
+        let chat_bind_url = required("CHAT_BIND_URL");
+        let telegram_bot_token = required("TELEGRAM_BOT_TOKEN");
+        let database_url = required("DATABASE_URL");
+
+        let host = std::env::var("HOST").unwrap_or_else(|_| "0.0.0.0".to_string());
+        let port = parse_var("PORT")?.unwrap_or(3000);
+        let defaults = HttpConfig::default();
+        let http = HttpConfig {
+            request_timeout: parse_var("REQUEST_TIMEOUT_SECS")?
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.request_timeout),
+            max_body_bytes: parse_var("MAX_BODY_BYTES")?.unwrap_or(defaults.max_body_bytes),
+            cors_allowed_origins: match std::env::var("CORS_ALLOWED_ORIGINS") {
+                Ok(origins) => parse_origins(&origins)?,
+                Err(_) => defaults.cors_allowed_origins,
+            },
+            trust_proxy: std::env::var("TRUST_PROXY")
+                .is_ok_and(|value| matches!(value.trim(), "1" | "true")),
+            metrics_token: std::env::var("METRICS_TOKEN")
+                .ok()
+                .filter(|token| !token.trim().is_empty()),
+        };
+        if http.request_timeout.is_zero() {
+            return Err(ConfigError::Invalid {
+                var: "REQUEST_TIMEOUT_SECS",
+                value: "0".to_string(),
+                reason: "must be at least 1 second".to_string(),
+            });
+        }
+
+        let defaults = DbConfig::default();
+        let db = DbConfig {
+            max_connections: parse_var("DB_MAX_CONNECTIONS")?.unwrap_or(defaults.max_connections),
+            acquire_timeout: parse_var("DB_ACQUIRE_TIMEOUT_SECS")?
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.acquire_timeout),
+            statement_timeout: parse_var::<u64>("DB_STATEMENT_TIMEOUT_SECS")?
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs),
+            slow_statement_threshold: parse_var("DB_SLOW_STATEMENT_MS")?
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.slow_statement_threshold),
+        };
+        if db.max_connections == 0 {
+            return Err(ConfigError::Invalid {
+                var: "DB_MAX_CONNECTIONS",
+                value: "0".to_string(),
+                reason: "must be at least 1".to_string(),
+            });
+        }
+
+        let defaults = TelegramAccessConfig::default();
+        let telegram_access = TelegramAccessConfig {
+            allowed: parse_chat_filters("TELEGRAM_ALLOWED_CHATS")?,
+            denied: parse_chat_filters("TELEGRAM_DENIED_CHATS")?,
+            unbound_cooldown: parse_var("TELEGRAM_UNBOUND_COOLDOWN_SECS")?
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.unbound_cooldown),
+        };
 
         let telegram_log_token = std::env::var("TELEGRAM_LOG_BOT_TOKEN").ok();
-        let telegram_log_chat_id = std::env::var("TELEGRAM_LOG_CHAT_ID")
+        let telegram_log_chat_id = parse_var("TELEGRAM_LOG_CHAT_ID")?;
+
+        let whatsapp_access_token = std::env::var("WHATSAPP_ACCESS_TOKEN").ok();
+        let whatsapp_phone_number_id = std::env::var("WHATSAPP_PHONE_NUMBER_ID").ok();
+        let whatsapp_verify_token = std::env::var("WHATSAPP_VERIFY_TOKEN").ok();
+        let whatsapp_app_secret = std::env::var("WHATSAPP_APP_SECRET").ok();
+
+        let slack_bot_token = std::env::var("SLACK_BOT_TOKEN").ok();
+        let slack_signing_secret = std::env::var("SLACK_SIGNING_SECRET").ok();
+
+        let exchange_rate_provider = std::env::var("EXCHANGE_RATE_PROVIDER").ok();
+        let exchange_rate_api_key = std::env::var("EXCHANGE_RATE_API_KEY").ok();
+
+        let resend_api_key = std::env::var("RESEND_API_KEY").ok();
+        let email_from = std::env::var("EMAIL_FROM").ok();
+        let email_ingest_domain = std::env::var("EMAIL_INGEST_DOMAIN")
+            .ok()
+            .map(|domain| domain.trim().to_lowercase())
+            .filter(|domain| !domain.is_empty());
+
+        let stripe_secret_key = std::env::var("STRIPE_SECRET_KEY").ok();
+        let stripe_webhook_secret = std::env::var("STRIPE_WEBHOOK_SECRET").ok();
+        let stripe_price_ids = std::env::var("STRIPE_PRICE_IDS").ok();
+
+        let storage_dir = std::env::var("STORAGE_DIR").unwrap_or_else(|_| "./uploads".to_string());
+        let s3_endpoint = std::env::var("S3_ENDPOINT").ok();
+        let s3_bucket = std::env::var("S3_BUCKET").ok();
+        let s3_region = std::env::var("S3_REGION").ok();
+        let s3_access_key_id = std::env::var("S3_ACCESS_KEY_ID").ok();
+        let s3_secret_access_key = std::env::var("S3_SECRET_ACCESS_KEY").ok();
+
+        let data_retention_dry_run = std::env::var("DATA_RETENTION_DRY_RUN")
+            .is_ok_and(|value| matches!(value.trim(), "1" | "true"));
+        let lang_hot_reload = std::env::var("LANG_HOT_RELOAD")
+            .is_ok_and(|value| matches!(value.trim(), "1" | "true"));
+
+        let backup_dir = std::env::var("BACKUP_DIR")
             .ok()
-            .and_then(|id_str| id_str.parse::<i64>().ok());
+            .filter(|dir| !dir.trim().is_empty());
+        let backup_keep = parse_var("BACKUP_KEEP")?.unwrap_or(7);
 
-        Config {
+        Ok(Config {
+            host,
+            port,
+            http,
             jwt_secret,
+            jwt_keys,
+            pii_encryption_key,
             chat_relay_secret,
             front_end_url,
             chat_bind_url,
             telegram_bot_token,
+            telegram_access,
             database_url,
+            db,
             telegram_log_token,
             telegram_log_chat_id,
+            whatsapp_access_token,
+            whatsapp_phone_number_id,
+            whatsapp_verify_token,
+            whatsapp_app_secret,
+            slack_bot_token,
+            slack_signing_secret,
+            exchange_rate_provider,
+            exchange_rate_api_key,
+            resend_api_key,
+            email_from,
+            email_ingest_domain,
+            stripe_secret_key,
+            stripe_webhook_secret,
+            stripe_price_ids,
+            storage_dir,
+            s3_endpoint,
+            s3_bucket,
+            s3_region,
+            s3_access_key_id,
+            s3_secret_access_key,
+            data_retention_dry_run,
+            lang_hot_reload,
+            backup_dir,
+            backup_keep,
+        })
+    }
+
+    pub fn bind_address(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+}
+
+// Comma separated origins such as https://app.example.com, or * for any origin
+fn parse_origins(origins: &str) -> Result<Vec<String>, ConfigError> {
+    origins
+        .split(',')
+        .map(str::trim)
+        .filter(|origin| !origin.is_empty())
+        .map(|origin| {
+            let valid = origin == ANY_ORIGIN
+                || ((origin.starts_with("http://") || origin.starts_with("https://"))
+                    && !origin.ends_with('/')
+                    && origin.parse::<axum::http::HeaderValue>().is_ok());
+            if valid {
+                Ok(origin.to_string())
+            } else {
+                Err(ConfigError::Invalid {
+                    var: "CORS_ALLOWED_ORIGINS",
+                    value: origin.to_string(),
+                    reason: "expected a scheme and host without a trailing slash, e.g. https://app.example.com".to_string(),
+                })
+            }
+        })
+        .collect()
+}
+
+// PII_ENCRYPTION_KEY, also read on its own by the `encrypt_pii` binary
+pub fn pii_encryption_key_from_env() -> Result<Option<[u8; 32]>, ConfigError> {
+    match std::env::var("PII_ENCRYPTION_KEY") {
+        Ok(key) if !key.trim().is_empty() => parse_pii_encryption_key(&key).map(Some),
+        _ => Ok(None),
+    }
+}
+
+// 32 bytes in standard base64, e.g. from `openssl rand -base64 32`. Errors never echo the key
+fn parse_pii_encryption_key(key: &str) -> Result<[u8; 32], ConfigError> {
+    STANDARD
+        .decode(key.trim())
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .ok_or_else(|| ConfigError::Invalid {
+            var: "PII_ENCRYPTION_KEY",
+            value: "(hidden)".to_string(),
+            reason: "expected 32 bytes in base64, e.g. from `openssl rand -base64 32`".to_string(),
+        })
+}
+
+// Comma separated kid:secret pairs, newest first. Errors name the kid, never the secret
+fn parse_jwt_keys(keys: &str) -> Result<Vec<(String, String)>, ConfigError> {
+    let mut parsed: Vec<(String, String)> = Vec::new();
+    for entry in keys
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+    {
+        let invalid = |value: &str, reason: &str| ConfigError::Invalid {
+            var: "JWT_KEYS",
+            value: value.to_string(),
+            reason: reason.to_string(),
+        };
+        let Some((kid, secret)) = entry.split_once(':') else {
+            return Err(invalid(
+                "(hidden)",
+                "expected kid:secret pairs, e.g. 2025-11:new-secret,2025-08:old-secret",
+            ));
+        };
+        let kid = kid.trim();
+        if kid.is_empty() || secret.trim().is_empty() {
+            return Err(invalid(kid, "the kid and the secret cannot be empty"));
         }
+        if parsed.iter().any(|(other, _)| other == kid) {
+            return Err(invalid(kid, "each kid can only be used once"));
+        }
+        parsed.push((kid.to_string(), secret.trim().to_string()));
+    }
+    Ok(parsed)
+}
+
+// Comma separated chat ids and @usernames, e.g. 123456789,-1001234567890,@alice
+fn parse_chat_filters(var: &'static str) -> Result<Vec<ChatFilter>, ConfigError> {
+    std::env::var(var)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| parse_chat_filter(var, entry))
+        .collect()
+}
+
+fn parse_chat_filter(var: &'static str, entry: &str) -> Result<ChatFilter, ConfigError> {
+    let invalid = || ConfigError::Invalid {
+        var,
+        value: entry.to_string(),
+        reason: "expected a chat id or an @username".to_string(),
+    };
+    match entry.strip_prefix('@') {
+        Some(username)
+            if !username.is_empty()
+                && username
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_') =>
+        {
+            Ok(ChatFilter::Username(username.to_ascii_lowercase()))
+        }
+        Some(_) => Err(invalid()),
+        None => entry.parse().map(ChatFilter::ChatId).map_err(|_| invalid()),
+    }
+}
+
+// None when the variable is unset or empty
+fn parse_var<T>(var: &'static str) -> Result<Option<T>, ConfigError>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    match std::env::var(var) {
+        Ok(value) if !value.trim().is_empty() => {
+            value
+                .trim()
+                .parse()
+                .map(Some)
+                .map_err(|e: T::Err| ConfigError::Invalid {
+                    var,
+                    reason: e.to_string(),
+                    value,
+                })
+        }
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_origins() {
+        assert_eq!(
+            parse_origins("https://app.example.com, http://localhost:5173,").unwrap(),
+            vec!["https://app.example.com", "http://localhost:5173"]
+        );
+        assert_eq!(parse_origins("*").unwrap(), vec![ANY_ORIGIN]);
+        assert!(parse_origins("app.example.com").is_err());
+        assert!(parse_origins("https://app.example.com/").is_err());
+    }
+    #[test]
+    fn test_parse_jwt_keys() {
+        assert_eq!(
+            parse_jwt_keys("2025-11:new-secret, 2025-08:old:secret").unwrap(),
+            vec![
+                ("2025-11".to_string(), "new-secret".to_string()),
+                ("2025-08".to_string(), "old:secret".to_string()),
+            ]
+        );
+        assert!(parse_jwt_keys("").unwrap().is_empty());
+        assert!(parse_jwt_keys("just-a-secret").is_err());
+        assert!(parse_jwt_keys(":secret").is_err());
+        assert!(parse_jwt_keys("a:one,a:two").is_err());
+    }
+
+    #[test]
+    fn test_parse_pii_encryption_key() {
+        let key = STANDARD.encode([7u8; 32]);
+        assert_eq!(parse_pii_encryption_key(&key).unwrap(), [7u8; 32]);
+        assert_eq!(
+            parse_pii_encryption_key(&format!(" {}\n", key)).unwrap(),
+            [7u8; 32]
+        );
+        assert!(parse_pii_encryption_key("not base64!").is_err());
+        let short = STANDARD.encode([7u8; 16]);
+        let err = parse_pii_encryption_key(&short).unwrap_err();
+        assert!(!err.to_string().contains(&short));
+    }
+
+    #[test]
+    fn test_parse_chat_filter() {
+        let parse = |entry| parse_chat_filter("TELEGRAM_ALLOWED_CHATS", entry);
+        assert_eq!(parse("123456789").unwrap(), ChatFilter::ChatId(123456789));
+        assert_eq!(
+            parse("-1001234567890").unwrap(),
+            ChatFilter::ChatId(-1001234567890)
+        );
+        assert_eq!(
+            parse("@Alice_B").unwrap(),
+            ChatFilter::Username("alice_b".to_string())
+        );
+        assert!(parse("alice").is_err());
+        assert!(parse("@").is_err());
+        assert!(parse("@ali ce").is_err());
+    }
+
+    #[test]
+    fn test_chat_filter_matches() {
+        let by_id = ChatFilter::ChatId(-100123);
+        assert!(by_id.matches(-100123, &[]));
+        assert!(!by_id.matches(123, &["alice"]));
+        let by_name = ChatFilter::Username("alice".to_string());
+        assert!(by_name.matches(1, &["family_group", "Alice"]));
+        assert!(!by_name.matches(1, &["bob"]));
+    }
+    #[test]
+    fn test_telegram_access_refusal() {
+        let mut access = TelegramAccessConfig::default();
+        assert_eq!(access.refusal(1, &["alice"]), None);
+
+        access.allowed = vec![ChatFilter::Username("alice".to_string())];
+        assert_eq!(access.refusal(1, &["alice"]), None);
+        assert!(access.refusal(2, &["bob"]).is_some());
+        assert!(access.refusal(2, &[]).is_some());
+
+        // Denied wins over allowed
+        access.denied = vec![ChatFilter::ChatId(1)];
+        assert!(access.refusal(1, &["alice"]).is_some());
     }
 }
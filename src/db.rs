@@ -1,13 +1,28 @@
-use std::time::Duration;
+use std::str::FromStr;
 
-use sqlx::postgres::PgPoolOptions;
 use anyhow::Result;
+use sqlx::ConnectOptions;
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+
+use crate::config::DbConfig;
 
 pub async fn make_db_pool(db_url: &str) -> Result<sqlx::PgPool> {
+    connect(db_url, &DbConfig::default()).await
+}
+
+// Every statement is logged at debug level under `sqlx::query`, slow ones as warnings
+pub async fn connect(db_url: &str, config: &DbConfig) -> Result<sqlx::PgPool> {
+    let mut options = PgConnectOptions::from_str(db_url)?
+        .log_statements(log::LevelFilter::Debug)
+        .log_slow_statements(log::LevelFilter::Warn, config.slow_statement_threshold);
+    if let Some(timeout) = config.statement_timeout {
+        // In milliseconds, set per connection so it also covers the jobs and messengers
+        options = options.options([("statement_timeout", timeout.as_millis())]);
+    }
     let pool = PgPoolOptions::new()
-        .max_connections(5)
-        .acquire_timeout(Duration::from_secs(3))
-        .connect(db_url)
+        .max_connections(config.max_connections)
+        .acquire_timeout(config.acquire_timeout)
+        .connect_with(options)
         .await?;
     Ok(pool)
-}
\ No newline at end of file
+}
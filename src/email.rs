@@ -0,0 +1,53 @@
+pub mod resend;
+
+use async_trait::async_trait;
+
+use crate::config::Config;
+
+#[async_trait]
+pub trait EmailSender {
+    async fn send(
+        &self,
+        to: &str,
+        subject: &str,
+        body: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // HTML email with a plain text part, senders without HTML support only send the text
+    async fn send_html(
+        &self,
+        to: &str,
+        subject: &str,
+        _html: &str,
+        text: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.send(to, subject, text).await
+    }
+}
+
+// Fallback for local development: emails only end up in the logs
+pub struct LogEmailSender;
+
+#[async_trait]
+impl EmailSender for LogEmailSender {
+    async fn send(
+        &self,
+        to: &str,
+        subject: &str,
+        body: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        tracing::info!("Email to {}: {}\n{}", to, subject, body);
+        Ok(())
+    }
+}
+
+// Picks Resend when configured, otherwise logs emails instead of sending them
+pub fn email_sender_from_config(config: &Config) -> Box<dyn EmailSender + Send + Sync> {
+    match resend::ResendEmailSender::from_config(config) {
+        Some(sender) => Box::new(sender),
+        None => {
+            tracing::warn!("RESEND_API_KEY or EMAIL_FROM not set, emails will only be logged");
+            Box::new(LogEmailSender)
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use async_trait::async_trait;
+use serde::Serialize;
+
+use crate::config::Config;
+
+use super::EmailSender;
+
+const RESEND_API_URL: &str = "https://api.resend.com/emails";
+
+#[derive(Clone)]
+pub struct ResendEmailSender {
+    api_key: String,
+    from: String,
+    client: reqwest::Client,
+}
+
+#[derive(Debug, Serialize)]
+struct SendEmailRequest<'a> {
+    from: &'a str,
+    to: [&'a str; 1],
+    subject: &'a str,
+    text: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    html: Option<&'a str>,
+}
+
+impl ResendEmailSender {
+    pub fn from_config(config: &Config) -> Option<Self> {
+        Some(Self {
+            api_key: config.resend_api_key.clone()?,
+            from: config.email_from.clone()?,
+            client: reqwest::Client::new(),
+        })
+    }
+}
+
+impl ResendEmailSender {
+    async fn post(
+        &self,
+        request: SendEmailRequest<'_>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let response = self
+            .client
+            .post(RESEND_API_URL)
+            .bearer_auth(&self.api_key)
+            .json(&request)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!("Resend returned {}: {}", status, body).into());
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl EmailSender for ResendEmailSender {
+    async fn send(
+        &self,
+        to: &str,
+        subject: &str,
+        body: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.post(SendEmailRequest {
+            from: &self.from,
+            to: [to],
+            subject,
+            text: body,
+            html: None,
+        })
+        .await
+    }
+
+    async fn send_html(
+        &self,
+        to: &str,
+        subject: &str,
+        html: &str,
+        text: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.post(SendEmailRequest {
+            from: &self.from,
+            to: [to],
+            subject,
+            text,
+            html: Some(html),
+        })
+        .await
+    }
+}
@@ -1,5 +1,7 @@
 mod app;
+mod code;
 mod db;
 
 pub use app::AppError;
+pub use code::{ErrorBody, ErrorCode};
 pub use db::DatabaseError;
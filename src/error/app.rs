@@ -1,10 +1,12 @@
 use axum::{
-    http::StatusCode,
+    Json,
     response::{IntoResponse, Response},
 };
+use serde_json::json;
 use thiserror::Error;
 
-use crate::error::DatabaseError;
+use crate::error::{DatabaseError, ErrorBody, ErrorCode};
+use crate::middleware::request_id;
 
 #[derive(Debug, Error)]
 pub enum AppError {
@@ -16,28 +18,86 @@ pub enum AppError {
     Internal(#[from] anyhow::Error),
     #[error("unauthorized")]
     Unauthorized(String),
+    // Errors with a more specific code than the variants above
+    #[error("{message}")]
+    Coded {
+        code: ErrorCode,
+        message: String,
+        details: Option<serde_json::Value>,
+    },
 }
 
-impl IntoResponse for AppError {
-    fn into_response(self) -> Response {
+impl AppError {
+    pub fn coded(code: ErrorCode, message: impl Into<String>) -> Self {
+        AppError::Coded {
+            code,
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn with_details(self, details: serde_json::Value) -> Self {
+        let ErrorBody { code, message, .. } = self.into_body();
+        AppError::Coded {
+            code,
+            message,
+            details: Some(details),
+        }
+    }
+
+    pub fn code(&self) -> ErrorCode {
         match self {
-            AppError::NotFound(msg) => (StatusCode::NOT_FOUND, msg).into_response(),
-            AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg).into_response(),
-            AppError::Internal(err) => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("internal error: {}", err),
-            )
-                .into_response(),
-            AppError::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, msg).into_response(),
+            AppError::NotFound(_) => ErrorCode::NotFound,
+            AppError::BadRequest(_) => ErrorCode::BadRequest,
+            AppError::Internal(_) => ErrorCode::InternalError,
+            AppError::Unauthorized(_) => ErrorCode::Unauthorized,
+            AppError::Coded { code, .. } => *code,
         }
     }
+
+    fn into_body(self) -> ErrorBody {
+        let code = self.code();
+        let (message, details) = match self {
+            AppError::NotFound(msg) | AppError::BadRequest(msg) | AppError::Unauthorized(msg) => {
+                (msg, None)
+            }
+            AppError::Internal(err) => (format!("internal error: {}", err), None),
+            AppError::Coded {
+                message, details, ..
+            } => (message, details),
+        };
+        ErrorBody {
+            code,
+            message,
+            details,
+            request_id: request_id::current(),
+        }
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let body = self.into_body();
+        (body.code.status(), Json(body)).into_response()
+    }
 }
 
 impl From<DatabaseError> for AppError {
     fn from(err: DatabaseError) -> Self {
         match err {
             DatabaseError::NotFound(msg) => AppError::NotFound(msg),
-            DatabaseError::ConstraintViolation(msg) => AppError::BadRequest(msg),
+            DatabaseError::ConstraintViolation(msg) => {
+                AppError::coded(ErrorCode::ConstraintViolation, msg)
+            }
+            DatabaseError::PeriodClosed(ref period) => {
+                let details = serde_json::json!({ "period": period });
+                AppError::coded(ErrorCode::PeriodClosed, err.to_string()).with_details(details)
+            }
+            DatabaseError::ConcurrentModification(ref current) => {
+                let details = json!({ "current": current });
+                AppError::coded(ErrorCode::ConcurrentModification, err.to_string())
+                    .with_details(details)
+            }
             _ => AppError::Internal(err.into()),
         }
     }
@@ -45,11 +105,7 @@ impl From<DatabaseError> for AppError {
 
 impl AppError {
     pub fn from_sqlx_error(err: sqlx::Error, context: &str) -> Self {
-        match DatabaseError::from_sqlx_error(err, context) {
-            DatabaseError::NotFound(msg) => AppError::NotFound(msg),
-            DatabaseError::ConstraintViolation(msg) => AppError::BadRequest(msg),
-            db_err => AppError::Internal(db_err.into()),
-        }
+        AppError::from(DatabaseError::from_sqlx_error(err, context))
     }
 }
 
@@ -70,27 +126,44 @@ impl From<crate::types::TierError> for AppError {
                     _ => crate::types::SubscriptionTier::Personal,
                 };
 
-                AppError::BadRequest(format!(
-                    "{} limit exceeded: {}/{}. Upgrade to {} for ${:.2}/month to increase your {} limit.",
-                    resource_type,
-                    current,
-                    limit,
-                    suggested_tier.display_name(),
-                    suggested_tier.price(),
-                    resource_type
-                ))
+                AppError::coded(
+                    ErrorCode::TierLimitExceeded,
+                    format!(
+                        "{} limit exceeded: {}/{}. Upgrade to {} for ${:.2}/month to increase your {} limit.",
+                        resource_type,
+                        current,
+                        limit,
+                        suggested_tier.display_name(),
+                        suggested_tier.price(),
+                        resource_type
+                    ),
+                )
+                .with_details(json!({
+                    "resource_type": resource_type,
+                    "current": current,
+                    "limit": limit,
+                    "suggested_tier": suggested_tier,
+                }))
             }
             crate::types::TierError::InsufficientTier {
                 required_tier,
                 current_tier,
-            } => AppError::Unauthorized(format!(
-                "Feature requires {} tier (you have {}). Upgrade for ${:.2}/month.",
-                required_tier.display_name(),
-                current_tier.display_name(),
-                required_tier.price()
-            )),
-            crate::types::TierError::SubscriptionExpired => AppError::Unauthorized(
-                "Subscription has expired. Please renew your subscription.".to_string(),
+            } => AppError::coded(
+                ErrorCode::TierRequired,
+                format!(
+                    "Feature requires {} tier (you have {}). Upgrade for ${:.2}/month.",
+                    required_tier.display_name(),
+                    current_tier.display_name(),
+                    required_tier.price()
+                ),
+            )
+            .with_details(json!({
+                "required_tier": required_tier,
+                "current_tier": current_tier,
+            })),
+            crate::types::TierError::SubscriptionExpired => AppError::coded(
+                ErrorCode::SubscriptionExpired,
+                "Subscription has expired. Please renew your subscription.",
             ),
         }
     }
@@ -98,6 +171,85 @@ impl From<crate::types::TierError> for AppError {
 
 impl From<validator::ValidationErrors> for AppError {
     fn from(err: validator::ValidationErrors) -> Self {
-        AppError::BadRequest(format!("Validation error: {}", err))
+        let details = serde_json::to_value(err.field_errors()).unwrap_or_default();
+        AppError::coded(
+            ErrorCode::ValidationFailed,
+            format!("Validation error: {}", err),
+        )
+        .with_details(details)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{SubscriptionTier, TierError};
+    use axum::http::StatusCode;
+
+    #[test]
+    fn test_plain_variants_have_generic_codes() {
+        let body = AppError::NotFound("getting expense group".into()).into_body();
+        assert_eq!(body.code, ErrorCode::NotFound);
+        assert_eq!(body.message, "getting expense group");
+        assert!(body.details.is_none());
+        assert_eq!(
+            AppError::Unauthorized("Missing credentials".into()).code(),
+            ErrorCode::Unauthorized
+        );
+    }
+
+    #[test]
+    fn test_tier_error_details() {
+        let err = AppError::from(TierError::LimitExceeded {
+            current: 1,
+            limit: 1,
+            resource_type: "groups".to_string(),
+        });
+        let body = err.into_body();
+        assert_eq!(body.code, ErrorCode::TierLimitExceeded);
+        assert_eq!(body.code.status(), StatusCode::BAD_REQUEST);
+        let details = body.details.unwrap();
+        assert_eq!(details["resource_type"], "groups");
+        assert_eq!(details["limit"], 1);
+        assert_eq!(
+            details["suggested_tier"],
+            serde_json::to_value(SubscriptionTier::Family).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_validation_errors_per_field() {
+        let mut errors = validator::ValidationErrors::new();
+        errors.add("price", validator::ValidationError::new("positive"));
+        let body = AppError::from(errors).into_body();
+        assert_eq!(body.code, ErrorCode::ValidationFailed);
+        assert_eq!(body.code.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body.details.unwrap()["price"][0]["code"], "positive");
+    }
+
+    #[test]
+    fn test_constraint_violation() {
+        let err = AppError::from(DatabaseError::ConstraintViolation("duplicate".into()));
+        assert_eq!(err.code(), ErrorCode::ConstraintViolation);
+        assert_eq!(err.code().status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_period_closed() {
+        let body = AppError::from(DatabaseError::PeriodClosed("2025-09".into())).into_body();
+        assert_eq!(body.code, ErrorCode::PeriodClosed);
+        assert_eq!(body.code.status(), StatusCode::CONFLICT);
+        assert!(body.message.contains("2025-09"));
+        assert_eq!(body.details.unwrap()["period"], "2025-09");
+    }
+
+    #[test]
+    fn test_concurrent_modification() {
+        let current = json!({ "uid": "44444444-4444-4444-4444-000000000002", "price": "15000" });
+        let body =
+            AppError::from(DatabaseError::ConcurrentModification(current.clone())).into_body();
+        assert_eq!(body.code, ErrorCode::ConcurrentModification);
+        assert_eq!(body.code.status(), StatusCode::CONFLICT);
+        assert_eq!(body.details.unwrap()["current"], current);
     }
 }
@@ -0,0 +1,90 @@
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/*
+ Stable machine-readable error codes, frontends branch on these instead of the message.
+ Codes are only ever added; renaming or removing one breaks clients.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    BadRequest,
+    ValidationFailed,
+    ConstraintViolation,
+    Unauthorized,
+    NotGroupMember,
+    GroupRoleRequired,
+    NotFound,
+    GroupNotFound,
+    TierLimitExceeded,
+    TierRequired,
+    SubscriptionInactive,
+    SubscriptionExpired,
+    RateLimited,
+    RequestTimeout,
+    InternalError,
+    BudgetHardLimitExceeded,
+    GroupArchived,
+    ApiKeyScopeRequired,
+    ApiKeyNotAllowed,
+    PeriodClosed,
+    ConcurrentModification,
+}
+
+impl ErrorCode {
+    pub fn status(self) -> StatusCode {
+        match self {
+            Self::BadRequest | Self::ConstraintViolation | Self::TierLimitExceeded => {
+                StatusCode::BAD_REQUEST
+            }
+            Self::ValidationFailed => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::Unauthorized
+            | Self::NotGroupMember
+            | Self::GroupRoleRequired
+            | Self::ApiKeyScopeRequired
+            | Self::TierRequired => StatusCode::UNAUTHORIZED,
+            Self::ApiKeyNotAllowed => StatusCode::FORBIDDEN,
+            Self::NotFound | Self::GroupNotFound => StatusCode::NOT_FOUND,
+            Self::SubscriptionInactive | Self::SubscriptionExpired => StatusCode::PAYMENT_REQUIRED,
+            Self::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            Self::RequestTimeout => StatusCode::REQUEST_TIMEOUT,
+            Self::InternalError => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::BudgetHardLimitExceeded
+            | Self::GroupArchived
+            | Self::PeriodClosed
+            | Self::ConcurrentModification => StatusCode::CONFLICT,
+        }
+    }
+}
+
+// Body of every error response
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ErrorBody {
+    pub code: ErrorCode,
+    /// Human-readable, may change between versions
+    pub message: String,
+    /// Extra fields depending on the code, e.g. the invalid fields of `VALIDATION_FAILED`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<serde_json::Value>,
+    /// Same as the `x-request-id` response header, for finding the request in the logs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_code_serialization() {
+        assert_eq!(
+            serde_json::to_value(ErrorCode::TierLimitExceeded).unwrap(),
+            "TIER_LIMIT_EXCEEDED"
+        );
+        assert_eq!(
+            serde_json::to_value(ErrorCode::GroupNotFound).unwrap(),
+            "GROUP_NOT_FOUND"
+        );
+    }
+}
@@ -17,14 +17,31 @@ pub enum DatabaseError {
     #[error("Not found: {0}")]
     NotFound(String),
 
-    #[error("Concurrent modification detected")]
-    ConcurrentModification,
+    // The row as it is now, after someone else changed it since the caller read it
+    #[error("Concurrent modification detected, it was changed by someone else in the meantime")]
+    ConcurrentModification(serde_json::Value),
+
+    // The `YYYY-MM` period the changed row belongs to
+    #[error("Period {0} is closed, an owner has to reopen it before its entries can change")]
+    PeriodClosed(String),
+
+    // An encrypted column could not be read back, see `utils::pii`
+    #[error("Decryption error: {0}")]
+    Decryption(#[from] crate::utils::pii::PiiError),
 }
 
 impl DatabaseError {
+    pub fn concurrent_modification(current: &impl serde::Serialize) -> Self {
+        DatabaseError::ConcurrentModification(serde_json::to_value(current).unwrap_or_default())
+    }
+
     pub fn from_sqlx_error(error: sqlx::Error, context: &str) -> Self {
         match error {
             sqlx::Error::RowNotFound => DatabaseError::NotFound(context.to_string()),
+            sqlx::Error::PoolTimedOut => {
+                crate::telemetry::record_db_pool_timeout();
+                DatabaseError::ConnectionError(error)
+            }
             sqlx::Error::Database(db_error) => {
                 if let Some(code) = db_error.code() {
                     match code.as_ref() {
@@ -0,0 +1,23 @@
+pub mod anomaly_alerts;
+pub mod backup;
+pub mod bind_request_cleanup;
+pub mod budget_alerts;
+pub mod data_retention;
+pub mod debt_reminders;
+pub mod exchange_rates;
+pub mod purge_deleted;
+pub mod queue;
+pub mod recurring_expenses;
+pub mod tier_reconciliation;
+
+pub use anomaly_alerts::AnomalyAlertScheduler;
+pub use backup::BackupScheduler;
+pub use bind_request_cleanup::BindRequestCleanupScheduler;
+pub use budget_alerts::BudgetAlertScheduler;
+pub use data_retention::RetentionScheduler;
+pub use debt_reminders::DebtReminderScheduler;
+pub use exchange_rates::ExchangeRateScheduler;
+pub use purge_deleted::PurgeScheduler;
+pub use queue::{JobQueue, JobTask, JobWorker};
+pub use recurring_expenses::RecurringScheduler;
+pub use tier_reconciliation::TierReconciliationScheduler;
@@ -0,0 +1,404 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use rust_decimal::{Decimal, prelude::ToPrimitive};
+use sqlx::PgPool;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use tokio_cron_scheduler::{Job, JobScheduler};
+use uuid::Uuid;
+
+use crate::lang::Lang;
+use crate::messengers::MessengerManager;
+use crate::repos::{
+    chat_binding::ChatBindingRepo,
+    exchange_rate::ExchangeRateRepo,
+    expense_group::ExpenseGroupRepo,
+    group_settings::GroupSettingsRepo,
+    spending_anomaly::{CategorySpend, SpendingAnomalyRepo},
+};
+use crate::shutdown::Shutdown;
+use crate::utils::anomaly::{AnomalySensitivity, Baseline, format_ratio};
+use crate::utils::parse_price::format_price_in;
+
+// Days of spending before today the baselines are built from
+const BASELINE_DAYS: i64 = 30;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnomalyKind {
+    // A single expense, next to the category's past expenses
+    Entry { entry_uid: Uuid, product: String },
+    // The category's total for today, next to past days with spending in it
+    Day,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpendingAnomaly {
+    pub category_uid: Uuid,
+    pub category: String,
+    pub kind: AnomalyKind,
+    pub amount: Decimal,
+    // Median of the baseline
+    pub usual: Decimal,
+    pub ratio: f64,
+}
+
+pub struct AnomalyAlert {
+    pub group_uid: Uuid,
+    pub currency: String,
+    pub anomaly: SpendingAnomaly,
+}
+
+/*
+ * Unusual spending on `today` among `spends`, whose prices are all in one currency. An
+ * unusual expense already explains its day, so a category's total is only checked when none
+ * of its expenses stood out.
+ */
+pub fn find_anomalies(
+    spends: &[CategorySpend],
+    today: NaiveDate,
+    sensitivity: AnomalySensitivity,
+) -> Vec<SpendingAnomaly> {
+    let mut by_category: BTreeMap<Uuid, Vec<&CategorySpend>> = BTreeMap::new();
+    for spend in spends {
+        by_category
+            .entry(spend.category_uid)
+            .or_default()
+            .push(spend);
+    }
+
+    let mut anomalies = Vec::new();
+    for (category_uid, spends) in by_category {
+        let (past, todays): (Vec<&CategorySpend>, Vec<&CategorySpend>) = spends
+            .into_iter()
+            .filter(|spend| spend.created_at.date_naive() <= today)
+            .partition(|spend| spend.created_at.date_naive() < today);
+        let Some(first) = todays.first() else {
+            continue;
+        };
+        let anomaly = |kind, amount, baseline: &Baseline, ratio| SpendingAnomaly {
+            category_uid,
+            category: first.category.clone(),
+            kind,
+            amount,
+            usual: Decimal::try_from(baseline.median)
+                .unwrap_or_default()
+                .round_dp(2),
+            ratio,
+        };
+
+        let prices: Vec<f64> = past.iter().map(|spend| to_f64(spend.price)).collect();
+        let found = anomalies.len();
+        if let Some(baseline) = Baseline::from_samples(&prices) {
+            for spend in &todays {
+                if let Some(ratio) = baseline.outlier_ratio(to_f64(spend.price), sensitivity) {
+                    let kind = AnomalyKind::Entry {
+                        entry_uid: spend.entry_uid,
+                        product: spend.product.clone(),
+                    };
+                    anomalies.push(anomaly(kind, spend.price, &baseline, ratio));
+                }
+            }
+        }
+        if anomalies.len() > found {
+            continue;
+        }
+
+        let mut days: BTreeMap<NaiveDate, Decimal> = BTreeMap::new();
+        for spend in &past {
+            *days.entry(spend.created_at.date_naive()).or_default() += spend.price;
+        }
+        let totals: Vec<f64> = days.values().copied().map(to_f64).collect();
+        let total: Decimal = todays.iter().map(|spend| spend.price).sum();
+        let day = Baseline::from_samples(&totals).and_then(|baseline| {
+            let ratio = baseline.outlier_ratio(to_f64(total), sensitivity)?;
+            Some((baseline, ratio))
+        });
+        if let Some((baseline, ratio)) = day {
+            anomalies.push(anomaly(AnomalyKind::Day, total, &baseline, ratio));
+        }
+    }
+    anomalies
+}
+
+fn to_f64(amount: Decimal) -> f64 {
+    amount.to_f64().unwrap_or_default()
+}
+
+pub struct AnomalyAlertScheduler {
+    db_pool: PgPool,
+    messenger_manager: Arc<MessengerManager>,
+    lang: Lang,
+}
+
+impl AnomalyAlertScheduler {
+    pub fn new(db_pool: PgPool, messenger_manager: Arc<MessengerManager>, lang: Lang) -> Self {
+        Self {
+            db_pool,
+            messenger_manager,
+            lang,
+        }
+    }
+
+    pub async fn start(
+        &self,
+        shutdown: &Shutdown,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let sched = JobScheduler::new().await?;
+
+        // Runs every 15 minutes; `spending_anomaly_alerts` keeps each alert to one message
+        let db_pool = self.db_pool.clone();
+        let messenger_manager = self.messenger_manager.clone();
+        let lang = self.lang.clone();
+        let job_shutdown = shutdown.clone();
+
+        let anomaly_job = Job::new_async("0 */15 * * * *", move |_, _| {
+            let db_pool = db_pool.clone();
+            let messenger_manager = messenger_manager.clone();
+            let lang = lang.clone();
+            let running = job_shutdown.track();
+
+            Box::pin(async move {
+                let Some(_running) = running else {
+                    return;
+                };
+                match Self::collect_alerts(&db_pool, Utc::now()).await {
+                    Ok(alerts) => {
+                        Self::notify_groups(&db_pool, &messenger_manager, &lang, alerts).await;
+                    }
+                    Err(e) => {
+                        tracing::error!("Error checking spending anomalies: {:?}", e);
+                    }
+                }
+            })
+        })?;
+
+        sched.add(anomaly_job).await?;
+        sched.start().await?;
+        shutdown.stop_with(sched);
+
+        tracing::info!("Anomaly alert scheduler started");
+        Ok(())
+    }
+
+    /*
+     * Checks today's spending of every unarchived group with an active chat and its anomaly
+     * alerts on, and records the alerts not sent yet. Prices are compared in the group's
+     * currency, expenses without an exchange rate to it are left out.
+     */
+    pub async fn collect_alerts(
+        db_pool: &PgPool,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<AnomalyAlert>, Box<dyn std::error::Error + Send + Sync>> {
+        let today = now.date_naive();
+        let since = (today - Duration::days(BASELINE_DAYS))
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+            .and_utc();
+        let mut tx = db_pool.begin().await?;
+        let mut alerts = Vec::new();
+        let rates = ExchangeRateRepo::rate_table(&mut tx).await?;
+
+        let mut group_uids: Vec<Uuid> = ChatBindingRepo::list(&mut tx)
+            .await?
+            .into_iter()
+            .filter(|binding| binding.status == "active")
+            .map(|binding| binding.group_uid)
+            .collect();
+        group_uids.sort();
+        group_uids.dedup();
+
+        for group_uid in group_uids {
+            let group = ExpenseGroupRepo::get(&mut tx, group_uid).await?;
+            let settings = GroupSettingsRepo::get(&mut tx, group_uid).await?;
+            if group.archived_at.is_some()
+                || settings.anomaly_sensitivity == AnomalySensitivity::Off
+            {
+                continue;
+            }
+            let spends: Vec<CategorySpend> =
+                SpendingAnomalyRepo::list_spends_since(&mut tx, group_uid, since)
+                    .await?
+                    .into_iter()
+                    .filter_map(|mut spend| {
+                        spend.price =
+                            rates.convert(spend.price, &spend.currency, &group.currency)?;
+                        spend.currency = group.currency.clone();
+                        Some(spend)
+                    })
+                    .collect();
+
+            for anomaly in find_anomalies(&spends, today, settings.anomaly_sensitivity) {
+                let entry_uid = match &anomaly.kind {
+                    AnomalyKind::Entry { entry_uid, .. } => Some(*entry_uid),
+                    AnomalyKind::Day => None,
+                };
+                if SpendingAnomalyRepo::record_sent(
+                    &mut tx,
+                    group_uid,
+                    anomaly.category_uid,
+                    today,
+                    entry_uid,
+                )
+                .await?
+                {
+                    alerts.push(AnomalyAlert {
+                        group_uid,
+                        currency: group.currency.clone(),
+                        anomaly,
+                    });
+                }
+            }
+        }
+
+        tx.commit().await?;
+
+        if !alerts.is_empty() {
+            tracing::info!("Collected {} spending anomaly alerts", alerts.len());
+        }
+        Ok(alerts)
+    }
+
+    async fn notify_groups(
+        db_pool: &PgPool,
+        messenger_manager: &MessengerManager,
+        lang: &Lang,
+        alerts: Vec<AnomalyAlert>,
+    ) {
+        if alerts.is_empty() {
+            return;
+        }
+
+        let bindings = match db_pool.begin().await {
+            Ok(mut tx) => ChatBindingRepo::list(&mut tx).await.unwrap_or_default(),
+            Err(e) => {
+                tracing::error!("Failed to load chat bindings for anomaly alert: {:?}", e);
+                return;
+            }
+        };
+
+        for alert in alerts {
+            let anomaly = alert.anomaly;
+            let mut vars = HashMap::from([
+                ("category".to_string(), anomaly.category),
+                ("ratio".to_string(), format_ratio(anomaly.ratio)),
+                (
+                    "amount".to_string(),
+                    format_price_in(anomaly.amount, &alert.currency),
+                ),
+                (
+                    "usual".to_string(),
+                    format_price_in(anomaly.usual, &alert.currency),
+                ),
+            ]);
+            let key = match anomaly.kind {
+                AnomalyKind::Entry { product, .. } => {
+                    vars.insert("product".to_string(), product);
+                    "MESSENGER__ANOMALY_ALERT_ENTRY"
+                }
+                AnomalyKind::Day => "MESSENGER__ANOMALY_ALERT_DAY",
+            };
+            let message = lang.get_with_vars(key, vars);
+
+            for binding in bindings
+                .iter()
+                .filter(|b| b.group_uid == alert.group_uid && b.status == "active")
+            {
+                if let Err(e) = messenger_manager
+                    .send_message(&binding.platform, &binding.p_uid, &message)
+                    .await
+                {
+                    tracing::error!("Failed to send anomaly alert: {:?}", e);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn spend(category_uid: Uuid, day: u32, price: Decimal) -> CategorySpend {
+        CategorySpend {
+            entry_uid: Uuid::new_v4(),
+            category_uid,
+            category: "Transportasi".to_string(),
+            product: "Ojek".to_string(),
+            price,
+            currency: "IDR".to_string(),
+            created_at: NaiveDate::from_ymd_opt(2025, 9, day)
+                .unwrap()
+                .and_hms_opt(8, 0, 0)
+                .unwrap()
+                .and_utc(),
+        }
+    }
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 9, 10).unwrap()
+    }
+
+    #[test]
+    fn test_unusual_entry() {
+        let category = Uuid::new_v4();
+        let mut spends: Vec<CategorySpend> = (1..=6)
+            .map(|day| spend(category, day, dec!(20000)))
+            .collect();
+        spends.push(spend(category, 10, dec!(25000)));
+        assert!(find_anomalies(&spends, today(), AnomalySensitivity::Medium).is_empty());
+
+        let taxi = spend(category, 10, dec!(80000));
+        let taxi_uid = taxi.entry_uid;
+        spends.push(taxi);
+        let anomalies = find_anomalies(&spends, today(), AnomalySensitivity::Medium);
+        assert_eq!(anomalies.len(), 1);
+        assert_eq!(
+            anomalies[0].kind,
+            AnomalyKind::Entry {
+                entry_uid: taxi_uid,
+                product: "Ojek".to_string()
+            }
+        );
+        assert_eq!(anomalies[0].amount, dec!(80000));
+        assert_eq!(anomalies[0].usual, dec!(20000));
+        assert_eq!(anomalies[0].ratio, 4.0);
+
+        assert!(find_anomalies(&spends, today(), AnomalySensitivity::Off).is_empty());
+    }
+
+    #[test]
+    fn test_unusual_day() {
+        // Several ordinary rides add up to a day well above the usual
+        let category = Uuid::new_v4();
+        let mut spends: Vec<CategorySpend> = (1..=6)
+            .flat_map(|day| {
+                [
+                    spend(category, day, dec!(10000)),
+                    spend(category, day, dec!(10000)),
+                ]
+            })
+            .collect();
+        spends.extend((0..8).map(|_| spend(category, 10, dec!(10000))));
+        let anomalies = find_anomalies(&spends, today(), AnomalySensitivity::Medium);
+        assert_eq!(anomalies.len(), 1);
+        assert_eq!(anomalies[0].kind, AnomalyKind::Day);
+        assert_eq!(anomalies[0].amount, dec!(80000));
+        assert_eq!(anomalies[0].usual, dec!(20000));
+    }
+
+    #[test]
+    fn test_needs_a_baseline() {
+        let category = Uuid::new_v4();
+        let mut spends: Vec<CategorySpend> = (1..=4)
+            .map(|day| spend(category, day, dec!(20000)))
+            .collect();
+        spends.push(spend(category, 10, dec!(500000)));
+        assert!(find_anomalies(&spends, today(), AnomalySensitivity::High).is_empty());
+
+        // Other categories have baselines of their own
+        let other = Uuid::new_v4();
+        spends.extend((1..=6).map(|day| spend(other, day, dec!(500000))));
+        spends.push(spend(other, 10, dec!(500000)));
+        assert!(find_anomalies(&spends, today(), AnomalySensitivity::High).is_empty());
+    }
+}
@@ -0,0 +1,102 @@
+use std::path::{Path, PathBuf};
+
+use sqlx::PgPool;
+use tokio_cron_scheduler::{Job, JobScheduler};
+
+use crate::backup;
+use crate::config::Config;
+use crate::shutdown::Shutdown;
+
+pub struct BackupScheduler {
+    db_pool: PgPool,
+    dir: Option<PathBuf>,
+    keep: usize,
+}
+
+impl BackupScheduler {
+    pub fn new(db_pool: PgPool, config: &Config) -> Self {
+        Self {
+            db_pool,
+            dir: config.backup_dir.as_ref().map(PathBuf::from),
+            keep: config.backup_keep,
+        }
+    }
+
+    pub async fn start(
+        &self,
+        shutdown: &Shutdown,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let Some(dir) = self.dir.clone() else {
+            tracing::info!("BACKUP_DIR not set, skipping scheduled backups");
+            return Ok(());
+        };
+        tokio::fs::create_dir_all(&dir).await?;
+
+        let sched = JobScheduler::new().await?;
+
+        // Runs daily at 02:00 UTC, before the purge and retention jobs delete anything
+        let db_pool = self.db_pool.clone();
+        let keep = self.keep;
+        let job_shutdown = shutdown.clone();
+
+        let backup_job = Job::new_async("0 0 2 * * *", move |_, _| {
+            let db_pool = db_pool.clone();
+            let dir = dir.clone();
+            let running = job_shutdown.track();
+
+            Box::pin(async move {
+                let Some(_running) = running else {
+                    return;
+                };
+                if let Err(e) = Self::backup(&db_pool, &dir, keep).await {
+                    tracing::error!("Error backing up the database: {:?}", e);
+                }
+            })
+        })?;
+
+        sched.add(backup_job).await?;
+        sched.start().await?;
+        shutdown.stop_with(sched);
+
+        tracing::info!("Backup scheduler started");
+        Ok(())
+    }
+
+    // Writes an archive to `dir` and deletes all but the `keep` newest ones
+    pub async fn backup(
+        db_pool: &PgPool,
+        dir: &Path,
+        keep: usize,
+    ) -> Result<PathBuf, Box<dyn std::error::Error + Send + Sync>> {
+        let archive = backup::dump(db_pool).await?;
+        let path = dir.join(backup::file_name(archive.created_at));
+        // Written under a temporary name so a crash never leaves a truncated archive behind
+        let partial = path.with_extension("partial");
+        tokio::fs::write(&partial, archive.encode()?).await?;
+        tokio::fs::rename(&partial, &path).await?;
+        tracing::info!(
+            "Backed up {} rows of {} tables to {}",
+            archive.row_count(),
+            archive.tables.len(),
+            path.display()
+        );
+
+        let mut archives = Vec::new();
+        let mut entries = tokio::fs::read_dir(dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with(backup::FILE_PREFIX) && name.ends_with(backup::FILE_SUFFIX) {
+                archives.push(entry.path());
+            }
+        }
+        // Names hold the time they were taken, sorting them sorts by age
+        archives.sort();
+        let stale = archives.len().saturating_sub(keep.max(1));
+        for old in &archives[..stale] {
+            if let Err(e) = tokio::fs::remove_file(old).await {
+                tracing::warn!("Failed to delete old backup {}: {}", old.display(), e);
+            }
+        }
+        Ok(path)
+    }
+}
@@ -0,0 +1,68 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use tokio_cron_scheduler::{Job, JobScheduler};
+
+use crate::repos::{chat_bind_request::ChatBindRequestRepo, chat_link_code::ChatLinkCodeRepo};
+use crate::shutdown::Shutdown;
+
+pub struct BindRequestCleanupScheduler {
+    db_pool: PgPool,
+}
+
+impl BindRequestCleanupScheduler {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self { db_pool }
+    }
+
+    pub async fn start(
+        &self,
+        shutdown: &Shutdown,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let sched = JobScheduler::new().await?;
+
+        // Runs hourly, requests and codes are valid for an hour at most
+        let db_pool = self.db_pool.clone();
+        let job_shutdown = shutdown.clone();
+
+        let cleanup_job = Job::new_async("0 15 * * * *", move |_, _| {
+            let db_pool = db_pool.clone();
+            let running = job_shutdown.track();
+
+            Box::pin(async move {
+                let Some(_running) = running else {
+                    return;
+                };
+                if let Err(e) = Self::cleanup(&db_pool, Utc::now()).await {
+                    tracing::error!("Error cleaning up chat bind requests: {:?}", e);
+                }
+            })
+        })?;
+
+        sched.add(cleanup_job).await?;
+        sched.start().await?;
+        shutdown.stop_with(sched);
+
+        tracing::info!("Chat bind request cleanup scheduler started");
+        Ok(())
+    }
+
+    // Deletes chat bind requests and /link-me codes that were used or expired, returns how many
+    pub async fn cleanup(
+        db_pool: &PgPool,
+        now: DateTime<Utc>,
+    ) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tx = db_pool.begin().await?;
+        let requests = ChatBindRequestRepo::delete_stale(&mut tx, now).await?;
+        let codes = ChatLinkCodeRepo::delete_stale(&mut tx, now).await?;
+        tx.commit().await?;
+
+        if requests + codes > 0 {
+            tracing::info!(
+                "Deleted {} chat bind requests and {} chat link codes that were used or expired",
+                requests,
+                codes
+            );
+        }
+        Ok(requests + codes)
+    }
+}
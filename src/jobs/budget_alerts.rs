@@ -0,0 +1,241 @@
+use chrono::{NaiveDate, Utc};
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio_cron_scheduler::{Job, JobScheduler};
+use uuid::Uuid;
+
+use crate::lang::Lang;
+use crate::messengers::MessengerManager;
+use crate::repos::{
+    budget::BudgetRepo,
+    budget_alert::{BudgetAlertRepo, BudgetAlertSettings, EXCEEDED_THRESHOLD},
+    category::CategoryRepo,
+    chat_binding::ChatBindingRepo,
+    exchange_rate::ExchangeRateRepo,
+    expense_entry::ExpenseEntryRepo,
+    expense_group::ExpenseGroupRepo,
+    group_settings::GroupSettingsRepo,
+};
+use crate::shutdown::Shutdown;
+use crate::utils::parse_price::format_price_in;
+use crate::webhooks::{self, WebhookEvent};
+
+pub struct BudgetAlert {
+    pub group_uid: Uuid,
+    pub category: String,
+    pub threshold: i16,
+    pub spent: Decimal,
+    pub amount: Decimal,
+    pub currency: String,
+}
+
+pub struct BudgetAlertScheduler {
+    db_pool: PgPool,
+    messenger_manager: Arc<MessengerManager>,
+    lang: Lang,
+}
+
+impl BudgetAlertScheduler {
+    pub fn new(db_pool: PgPool, messenger_manager: Arc<MessengerManager>, lang: Lang) -> Self {
+        Self {
+            db_pool,
+            messenger_manager,
+            lang,
+        }
+    }
+
+    pub async fn start(
+        &self,
+        shutdown: &Shutdown,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let sched = JobScheduler::new().await?;
+
+        // Runs every 5 minutes; `budget_alerts` keeps each threshold to one alert per period
+        let db_pool = self.db_pool.clone();
+        let messenger_manager = self.messenger_manager.clone();
+        let lang = self.lang.clone();
+        let job_shutdown = shutdown.clone();
+
+        let alert_job = Job::new_async("0 */5 * * * *", move |_, _| {
+            let db_pool = db_pool.clone();
+            let messenger_manager = messenger_manager.clone();
+            let lang = lang.clone();
+            let running = job_shutdown.track();
+
+            Box::pin(async move {
+                let Some(_running) = running else {
+                    return;
+                };
+                let today = Utc::now().date_naive();
+                match Self::collect_alerts(&db_pool, today).await {
+                    Ok(alerts) => {
+                        Self::notify_groups(&db_pool, &messenger_manager, &lang, alerts).await;
+                    }
+                    Err(e) => {
+                        tracing::error!("Error checking budget alerts: {:?}", e);
+                    }
+                }
+            })
+        })?;
+
+        sched.add(alert_job).await?;
+        sched.start().await?;
+        shutdown.stop_with(sched);
+
+        tracing::info!("Budget alert scheduler started");
+        Ok(())
+    }
+
+    /*
+     * Finds budgets whose spending crossed a threshold that has not been alerted yet in the
+     * current period, and records them as sent. Only the highest new threshold per budget is
+     * returned so a jump from 50% to 110% produces a single message.
+     */
+    pub async fn collect_alerts(
+        db_pool: &PgPool,
+        today: NaiveDate,
+    ) -> Result<Vec<BudgetAlert>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tx = db_pool.begin().await?;
+        let mut alerts = Vec::new();
+        // (start_over_date, week_start, alert settings) of each group
+        let mut groups: HashMap<Uuid, (i16, i16, BudgetAlertSettings)> = HashMap::new();
+        let rates = ExchangeRateRepo::rate_table(&mut tx).await?;
+
+        for budget in BudgetRepo::list(&mut tx).await? {
+            let (start_over_date, week_start, settings) = match groups.get(&budget.group_uid) {
+                Some(group) => group.clone(),
+                None => {
+                    let group = ExpenseGroupRepo::get(&mut tx, budget.group_uid).await?;
+                    let week_start = GroupSettingsRepo::get(&mut tx, budget.group_uid)
+                        .await?
+                        .week_start;
+                    let settings = BudgetAlertRepo::get_settings(&mut tx, budget.group_uid).await?;
+                    let group = (group.start_over_date, week_start, settings);
+                    groups.insert(budget.group_uid, group.clone());
+                    group
+                }
+            };
+            if !settings.enabled || budget.amount <= Decimal::ZERO {
+                continue;
+            }
+
+            let (period_start, period_end) =
+                budget.period_range(start_over_date, week_start, today);
+            if today < period_start || today >= period_end {
+                // Pinned to a period that is not running
+                continue;
+            }
+            let totals = ExpenseEntryRepo::sum_by_category_in_range(
+                &mut tx,
+                budget.group_uid,
+                budget.category_uid,
+                period_start.and_hms_opt(0, 0, 0).unwrap().and_utc(),
+                period_end.and_hms_opt(0, 0, 0).unwrap().and_utc(),
+            )
+            .await?;
+            let (spent, _) = rates.sum_in(&totals, &budget.currency);
+
+            let mut newest = None;
+            for threshold in settings.crossed_thresholds(budget.percentage_used(spent)) {
+                if BudgetAlertRepo::record_sent(&mut tx, budget.uid, period_start, threshold)
+                    .await?
+                {
+                    newest = Some(threshold);
+                }
+            }
+            if let Some(threshold) = newest {
+                let category = CategoryRepo::get(&mut tx, budget.category_uid).await?;
+                if threshold >= EXCEEDED_THRESHOLD {
+                    webhooks::dispatch(
+                        &mut tx,
+                        budget.group_uid,
+                        WebhookEvent::BudgetExceeded,
+                        &serde_json::json!({
+                            "budget_uid": budget.uid,
+                            "category_uid": budget.category_uid,
+                            "category": category.name,
+                            "threshold": threshold,
+                            "spent": spent,
+                            "amount": budget.amount,
+                            "currency": budget.currency,
+                            "period_start": period_start,
+                            "period_end": period_end,
+                        }),
+                    )
+                    .await?;
+                }
+                alerts.push(BudgetAlert {
+                    group_uid: budget.group_uid,
+                    category: category.name,
+                    threshold,
+                    spent,
+                    amount: budget.amount,
+                    currency: budget.currency,
+                });
+            }
+        }
+
+        tx.commit().await?;
+
+        if !alerts.is_empty() {
+            tracing::info!("Collected {} budget alerts", alerts.len());
+        }
+        Ok(alerts)
+    }
+
+    async fn notify_groups(
+        db_pool: &PgPool,
+        messenger_manager: &MessengerManager,
+        lang: &Lang,
+        alerts: Vec<BudgetAlert>,
+    ) {
+        if alerts.is_empty() {
+            return;
+        }
+
+        let bindings = match db_pool.begin().await {
+            Ok(mut tx) => ChatBindingRepo::list(&mut tx).await.unwrap_or_default(),
+            Err(e) => {
+                tracing::error!("Failed to load chat bindings for budget alert: {:?}", e);
+                return;
+            }
+        };
+
+        for alert in alerts {
+            let key = if alert.threshold >= EXCEEDED_THRESHOLD {
+                "MESSENGER__BUDGET_ALERT_EXCEEDED"
+            } else {
+                "MESSENGER__BUDGET_ALERT_WARNING"
+            };
+            let message = lang.get_with_vars(
+                key,
+                HashMap::from([
+                    ("category".to_string(), alert.category),
+                    ("percent".to_string(), alert.threshold.to_string()),
+                    (
+                        "spent".to_string(),
+                        format_price_in(alert.spent, &alert.currency),
+                    ),
+                    (
+                        "amount".to_string(),
+                        format_price_in(alert.amount, &alert.currency),
+                    ),
+                ]),
+            );
+
+            for binding in bindings
+                .iter()
+                .filter(|b| b.group_uid == alert.group_uid && b.status == "active")
+            {
+                if let Err(e) = messenger_manager
+                    .send_message(&binding.platform, &binding.p_uid, &message)
+                    .await
+                {
+                    tracing::error!("Failed to send budget alert: {:?}", e);
+                }
+            }
+        }
+    }
+}
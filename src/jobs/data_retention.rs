@@ -0,0 +1,131 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use tokio_cron_scheduler::{Job, JobScheduler};
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::jobs::purge_deleted::{owner_tier, retention_cutoff};
+use crate::repos::{expense_entry::ExpenseEntryRepo, expense_group::ExpenseGroupRepo};
+use crate::shutdown::Shutdown;
+use crate::types::SubscriptionTier;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct RetentionSummary {
+    // Groups that had entries past their retention window
+    pub groups: usize,
+    pub entries: u64,
+    // Groups skipped because the owner's tier keeps data indefinitely
+    pub exempt_groups: usize,
+}
+
+// Enterprise data is kept for as long as the customer stays, whatever `data_retention_days` says
+pub fn is_retention_exempt(tier: &SubscriptionTier) -> bool {
+    *tier == SubscriptionTier::Enterprise
+}
+
+pub struct RetentionScheduler {
+    db_pool: PgPool,
+    dry_run: bool,
+}
+
+impl RetentionScheduler {
+    pub fn new(db_pool: PgPool, config: &Config) -> Self {
+        Self {
+            db_pool,
+            dry_run: config.data_retention_dry_run,
+        }
+    }
+
+    pub async fn start(
+        &self,
+        shutdown: &Shutdown,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let sched = JobScheduler::new().await?;
+
+        // Runs daily at 03:30 UTC, after the purge of soft-deleted data
+        let db_pool = self.db_pool.clone();
+        let dry_run = self.dry_run;
+        let job_shutdown = shutdown.clone();
+
+        let retention_job = Job::new_async("0 30 3 * * *", move |_, _| {
+            let db_pool = db_pool.clone();
+            let running = job_shutdown.track();
+
+            Box::pin(async move {
+                let Some(_running) = running else {
+                    return;
+                };
+                if let Err(e) = Self::enforce(&db_pool, Utc::now(), dry_run).await {
+                    tracing::error!("Error enforcing data retention: {:?}", e);
+                }
+            })
+        })?;
+
+        sched.add(retention_job).await?;
+        sched.start().await?;
+        shutdown.stop_with(sched);
+
+        if self.dry_run {
+            tracing::info!("Data retention scheduler started in dry-run mode");
+        } else {
+            tracing::info!("Data retention scheduler started");
+        }
+        Ok(())
+    }
+
+    /*
+     * Expense entries older than the `data_retention_days` of the group owner's tier are
+     * deleted permanently. With `dry_run` nothing is deleted, the summary counts what would be.
+     */
+    pub async fn enforce(
+        db_pool: &PgPool,
+        now: DateTime<Utc>,
+        dry_run: bool,
+    ) -> Result<RetentionSummary, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tx = db_pool.begin().await?;
+        let mut tiers: HashMap<Uuid, SubscriptionTier> = HashMap::new();
+        let mut summary = RetentionSummary::default();
+
+        for group in ExpenseGroupRepo::list(&mut tx).await? {
+            let tier = owner_tier(&mut tx, &mut tiers, group.owner).await?;
+            if is_retention_exempt(&tier) {
+                summary.exempt_groups += 1;
+                continue;
+            }
+            let cutoff = retention_cutoff(&tier, now);
+            let entries = if dry_run {
+                ExpenseEntryRepo::count_expired(&mut tx, group.uid, cutoff).await? as u64
+            } else {
+                ExpenseEntryRepo::delete_expired(&mut tx, group.uid, cutoff).await?
+            };
+            if entries > 0 {
+                summary.groups += 1;
+                summary.entries += entries;
+            }
+        }
+
+        tx.commit().await?;
+
+        tracing::info!(
+            "Data retention{}: {} expense entries in {} groups past retention, {} enterprise groups exempt",
+            if dry_run { " (dry run)" } else { "" },
+            summary.entries,
+            summary.groups,
+            summary.exempt_groups
+        );
+        Ok(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_retention_exempt() {
+        assert!(is_retention_exempt(&SubscriptionTier::Enterprise));
+        assert!(!is_retention_exempt(&SubscriptionTier::Team));
+        assert!(!is_retention_exempt(&SubscriptionTier::Free));
+    }
+}
@@ -0,0 +1,214 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use sqlx::PgPool;
+use std::sync::Arc;
+use tokio_cron_scheduler::{Job, JobScheduler};
+
+use crate::commands::debt::DebtCommand;
+use crate::lang::Lang;
+use crate::messengers::MessengerManager;
+use crate::repos::{
+    chat_binding::ChatBindingRepo,
+    debt::{Debt, DebtDirection, DebtRepo},
+    expense_group::ExpenseGroupRepo,
+};
+use crate::shutdown::Shutdown;
+
+// Days before the due date the chats are reminded
+const REMINDER_DAYS: i64 = 3;
+
+pub struct DebtReminder {
+    pub debt: Debt,
+    // The group's preferred language
+    pub lang: Option<String>,
+    pub today: NaiveDate,
+}
+
+// Message key for a debt due on or before `today + REMINDER_DAYS`
+pub fn reminder_key(debt: &Debt, today: NaiveDate) -> &'static str {
+    let overdue = debt.due_date.is_some_and(|due_date| due_date < today);
+    match (debt.direction(), overdue) {
+        (DebtDirection::Lent, false) => "MESSENGER__DEBT_REMINDER_LENT",
+        (DebtDirection::Borrowed, false) => "MESSENGER__DEBT_REMINDER_BORROWED",
+        (DebtDirection::Lent, true) => "MESSENGER__DEBT_REMINDER_LENT_OVERDUE",
+        (DebtDirection::Borrowed, true) => "MESSENGER__DEBT_REMINDER_BORROWED_OVERDUE",
+    }
+}
+
+pub struct DebtReminderScheduler {
+    db_pool: PgPool,
+    messenger_manager: Arc<MessengerManager>,
+    lang: Lang,
+}
+
+impl DebtReminderScheduler {
+    pub fn new(db_pool: PgPool, messenger_manager: Arc<MessengerManager>, lang: Lang) -> Self {
+        Self {
+            db_pool,
+            messenger_manager,
+            lang,
+        }
+    }
+
+    pub async fn start(
+        &self,
+        shutdown: &Shutdown,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let sched = JobScheduler::new().await?;
+
+        // Runs daily at 02:00 UTC (09:00 WIB); `reminded_at` keeps each debt to one reminder
+        let db_pool = self.db_pool.clone();
+        let messenger_manager = self.messenger_manager.clone();
+        let lang = self.lang.clone();
+        let job_shutdown = shutdown.clone();
+
+        let reminder_job = Job::new_async("0 0 2 * * *", move |_, _| {
+            let db_pool = db_pool.clone();
+            let messenger_manager = messenger_manager.clone();
+            let lang = lang.clone();
+            let running = job_shutdown.track();
+
+            Box::pin(async move {
+                let Some(_running) = running else {
+                    return;
+                };
+                match Self::collect_reminders(&db_pool, Utc::now()).await {
+                    Ok(reminders) => {
+                        Self::notify_groups(&db_pool, &messenger_manager, &lang, reminders).await;
+                    }
+                    Err(e) => {
+                        tracing::error!("Error checking debts due: {:?}", e);
+                    }
+                }
+            })
+        })?;
+
+        sched.add(reminder_job).await?;
+        sched.start().await?;
+        shutdown.stop_with(sched);
+
+        tracing::info!("Debt reminder scheduler started");
+        Ok(())
+    }
+
+    /*
+     * Open debts of unarchived groups due within `REMINDER_DAYS` or already overdue, that were
+     * not reminded of yet. They are marked as reminded right away, so a failed send is not
+     * retried the next day.
+     */
+    pub async fn collect_reminders(
+        db_pool: &PgPool,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<DebtReminder>, Box<dyn std::error::Error + Send + Sync>> {
+        let today = now.date_naive();
+        let mut tx = db_pool.begin().await?;
+
+        let debts =
+            DebtRepo::list_due_for_reminder(&mut tx, today + Duration::days(REMINDER_DAYS)).await?;
+        let uids: Vec<_> = debts.iter().map(|debt| debt.uid).collect();
+        DebtRepo::mark_reminded(&mut tx, &uids).await?;
+
+        let mut reminders = Vec::new();
+        for debt in debts {
+            let group = ExpenseGroupRepo::get(&mut tx, debt.group_uid).await?;
+            reminders.push(DebtReminder {
+                debt,
+                lang: group.lang,
+                today,
+            });
+        }
+
+        tx.commit().await?;
+
+        if !reminders.is_empty() {
+            tracing::info!("Collected {} debt reminders", reminders.len());
+        }
+        Ok(reminders)
+    }
+
+    async fn notify_groups(
+        db_pool: &PgPool,
+        messenger_manager: &MessengerManager,
+        lang: &Lang,
+        reminders: Vec<DebtReminder>,
+    ) {
+        if reminders.is_empty() {
+            return;
+        }
+
+        let bindings = match db_pool.begin().await {
+            Ok(mut tx) => ChatBindingRepo::list(&mut tx).await.unwrap_or_default(),
+            Err(e) => {
+                tracing::error!("Failed to load chat bindings for debt reminder: {:?}", e);
+                return;
+            }
+        };
+
+        for reminder in reminders {
+            let lang = lang.preferred(reminder.lang.as_deref());
+            let message = lang.get_with_vars(
+                reminder_key(&reminder.debt, reminder.today),
+                DebtCommand::vars(&reminder.debt, &lang),
+            );
+
+            for binding in bindings
+                .iter()
+                .filter(|b| b.group_uid == reminder.debt.group_uid && b.status == "active")
+            {
+                if let Err(e) = messenger_manager
+                    .send_message(&binding.platform, &binding.p_uid, &message)
+                    .await
+                {
+                    tracing::error!("Failed to send debt reminder: {:?}", e);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+    use uuid::Uuid;
+
+    fn debt(direction: DebtDirection, due_date: NaiveDate) -> Debt {
+        Debt {
+            uid: Uuid::new_v4(),
+            group_uid: Uuid::new_v4(),
+            direction: direction.as_str().to_string(),
+            person: "Andi".to_string(),
+            amount: dec!(500000),
+            currency: "IDR".to_string(),
+            due_date: Some(due_date),
+            note: None,
+            settled_at: None,
+            reminded_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_reminder_key() {
+        let today = NaiveDate::from_ymd_opt(2025, 11, 27).unwrap();
+        let due_soon = NaiveDate::from_ymd_opt(2025, 11, 30).unwrap();
+        let overdue = NaiveDate::from_ymd_opt(2025, 11, 26).unwrap();
+
+        assert_eq!(
+            reminder_key(&debt(DebtDirection::Lent, due_soon), today),
+            "MESSENGER__DEBT_REMINDER_LENT"
+        );
+        assert_eq!(
+            reminder_key(&debt(DebtDirection::Borrowed, today), today),
+            "MESSENGER__DEBT_REMINDER_BORROWED"
+        );
+        assert_eq!(
+            reminder_key(&debt(DebtDirection::Lent, overdue), today),
+            "MESSENGER__DEBT_REMINDER_LENT_OVERDUE"
+        );
+        assert_eq!(
+            reminder_key(&debt(DebtDirection::Borrowed, overdue), today),
+            "MESSENGER__DEBT_REMINDER_BORROWED_OVERDUE"
+        );
+    }
+}
@@ -0,0 +1,300 @@
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use tokio_cron_scheduler::{Job, JobScheduler};
+
+use crate::config::Config;
+use crate::repos::exchange_rate::ExchangeRateRepo;
+use crate::shutdown::Shutdown;
+use crate::utils::currency::SUPPORTED_CURRENCIES;
+
+const ECB_URL: &str = "https://api.frankfurter.app/latest";
+const EXCHANGERATE_HOST_URL: &str = "https://api.exchangerate.host/live";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExchangeRateProvider {
+    // European Central Bank reference rates, served by frankfurter.app. EUR based, no key
+    Ecb,
+    // exchangerate.host, USD based on the free plan
+    ExchangeRateHost { access_key: String },
+}
+
+#[derive(Debug, Deserialize)]
+struct EcbResponse {
+    base: String,
+    rates: HashMap<String, Decimal>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExchangeRateHostResponse {
+    success: bool,
+    source: Option<String>,
+    #[serde(default)]
+    quotes: HashMap<String, Decimal>,
+}
+
+impl ExchangeRateProvider {
+    // `None` when no provider is configured, which disables the sync job
+    pub fn from_config(config: &Config) -> Option<Self> {
+        match config.exchange_rate_provider.as_deref()?.trim() {
+            "ecb" => Some(Self::Ecb),
+            "exchangerate_host" => match config.exchange_rate_api_key.clone() {
+                Some(access_key) => Some(Self::ExchangeRateHost { access_key }),
+                None => {
+                    tracing::warn!("EXCHANGE_RATE_API_KEY is required for exchangerate_host");
+                    None
+                }
+            },
+            other => {
+                tracing::warn!("Unknown EXCHANGE_RATE_PROVIDER: {}", other);
+                None
+            }
+        }
+    }
+
+    pub fn source(&self) -> &'static str {
+        match self {
+            Self::Ecb => "ecb",
+            Self::ExchangeRateHost { .. } => "exchangerate_host",
+        }
+    }
+
+    fn base(&self) -> &'static str {
+        match self {
+            Self::Ecb => "EUR",
+            Self::ExchangeRateHost { .. } => "USD",
+        }
+    }
+
+    async fn fetch(
+        &self,
+        client: &reqwest::Client,
+    ) -> Result<HashMap<String, Decimal>, Box<dyn std::error::Error + Send + Sync>> {
+        let currencies = SUPPORTED_CURRENCIES
+            .iter()
+            .map(|currency| currency.code)
+            .filter(|code| *code != self.base())
+            .collect::<Vec<_>>()
+            .join(",");
+
+        let request = match self {
+            Self::Ecb => client
+                .get(ECB_URL)
+                .query(&[("from", self.base()), ("to", currencies.as_str())]),
+            Self::ExchangeRateHost { access_key } => client.get(EXCHANGERATE_HOST_URL).query(&[
+                ("access_key", access_key.as_str()),
+                ("source", self.base()),
+                ("currencies", currencies.as_str()),
+            ]),
+        };
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!("{} returned {}: {}", self.source(), status, body).into());
+        }
+        let body = response.text().await?;
+
+        match self {
+            Self::Ecb => parse_ecb(&body),
+            Self::ExchangeRateHost { .. } => parse_exchangerate_host(&body),
+        }
+    }
+}
+
+// Quotes per 1 EUR, keyed by currency code (base included with rate 1)
+fn parse_ecb(
+    body: &str,
+) -> Result<HashMap<String, Decimal>, Box<dyn std::error::Error + Send + Sync>> {
+    let response: EcbResponse = serde_json::from_str(body)?;
+    let mut rates = response.rates;
+    rates.insert(response.base, Decimal::ONE);
+    Ok(rates)
+}
+
+// Quotes are keyed as "USDIDR"; the source prefix is stripped
+fn parse_exchangerate_host(
+    body: &str,
+) -> Result<HashMap<String, Decimal>, Box<dyn std::error::Error + Send + Sync>> {
+    let response: ExchangeRateHostResponse = serde_json::from_str(body)?;
+    let Some(source) = response.source.filter(|_| response.success) else {
+        return Err(format!("exchangerate_host request failed: {}", body).into());
+    };
+    let mut rates = response
+        .quotes
+        .into_iter()
+        .filter_map(|(pair, rate)| {
+            pair.strip_prefix(source.as_str())
+                .map(|quote| (quote.to_string(), rate))
+        })
+        .collect::<HashMap<_, _>>();
+    rates.insert(source, Decimal::ONE);
+    Ok(rates)
+}
+
+/*
+ * Expands quotes against a single base into every supported pair so that reports can convert
+ * directly between any two currencies, e.g. EUR->USD and EUR->IDR give USD->IDR.
+ */
+pub fn cross_rates(quotes: &HashMap<String, Decimal>) -> Vec<(String, String, Decimal)> {
+    let known = SUPPORTED_CURRENCIES
+        .iter()
+        .filter_map(|currency| {
+            quotes
+                .get(currency.code)
+                .filter(|rate| **rate > Decimal::ZERO)
+                .map(|rate| (currency.code, *rate))
+        })
+        .collect::<Vec<_>>();
+
+    let mut pairs = Vec::new();
+    for (base, base_rate) in &known {
+        for (quote, quote_rate) in &known {
+            if base != quote {
+                pairs.push((
+                    base.to_string(),
+                    quote.to_string(),
+                    // The column keeps 10 decimals
+                    (quote_rate / base_rate).round_dp(10),
+                ));
+            }
+        }
+    }
+    pairs
+}
+
+pub struct ExchangeRateScheduler {
+    db_pool: PgPool,
+    provider: Option<ExchangeRateProvider>,
+    client: reqwest::Client,
+}
+
+impl ExchangeRateScheduler {
+    pub fn new(db_pool: PgPool, config: &Config) -> Self {
+        Self {
+            db_pool,
+            provider: ExchangeRateProvider::from_config(config),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub async fn start(
+        &self,
+        shutdown: &Shutdown,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let Some(provider) = self.provider.clone() else {
+            tracing::info!("No exchange rate provider configured, skipping rate sync");
+            return Ok(());
+        };
+
+        // Sync once on boot so a fresh deployment does not wait a day for rates
+        let db_pool = self.db_pool.clone();
+        let client = self.client.clone();
+        let initial_provider = provider.clone();
+        shutdown.spawn(async move {
+            if let Err(e) = Self::sync(&db_pool, &client, &initial_provider).await {
+                tracing::error!("Error syncing exchange rates: {:?}", e);
+            }
+        });
+
+        let sched = JobScheduler::new().await?;
+
+        // Runs daily at 16:00 UTC, after the ECB publishes its reference rates (around 16:00 CET)
+        let db_pool = self.db_pool.clone();
+        let client = self.client.clone();
+        let job_shutdown = shutdown.clone();
+
+        let sync_job = Job::new_async("0 0 16 * * *", move |_, _| {
+            let db_pool = db_pool.clone();
+            let client = client.clone();
+            let provider = provider.clone();
+            let running = job_shutdown.track();
+
+            Box::pin(async move {
+                let Some(_running) = running else {
+                    return;
+                };
+                if let Err(e) = Self::sync(&db_pool, &client, &provider).await {
+                    tracing::error!("Error syncing exchange rates: {:?}", e);
+                }
+            })
+        })?;
+
+        sched.add(sync_job).await?;
+        sched.start().await?;
+        shutdown.stop_with(sched);
+
+        tracing::info!("Exchange rate scheduler started");
+        Ok(())
+    }
+
+    pub async fn sync(
+        db_pool: &PgPool,
+        client: &reqwest::Client,
+        provider: &ExchangeRateProvider,
+    ) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let quotes = provider.fetch(client).await?;
+        let pairs = cross_rates(&quotes);
+
+        let mut tx = db_pool.begin().await?;
+        for (base, quote, rate) in &pairs {
+            ExchangeRateRepo::upsert(&mut tx, base, quote, *rate, provider.source()).await?;
+        }
+        tx.commit().await?;
+
+        tracing::info!(
+            "Synced {} exchange rates from {}",
+            pairs.len(),
+            provider.source()
+        );
+        Ok(pairs.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parse_ecb() {
+        let body =
+            r#"{"amount":1.0,"base":"EUR","date":"2025-10-15","rates":{"USD":1.08,"IDR":17500.0}}"#;
+        let rates = parse_ecb(body).unwrap();
+        assert_eq!(rates.get("EUR"), Some(&Decimal::ONE));
+        assert_eq!(rates.get("USD"), Some(&dec!(1.08)));
+        assert_eq!(rates.get("IDR"), Some(&dec!(17500.0)));
+    }
+
+    #[test]
+    fn test_parse_exchangerate_host() {
+        let body = r#"{"success":true,"source":"USD","quotes":{"USDIDR":16000.0,"USDEUR":0.92}}"#;
+        let rates = parse_exchangerate_host(body).unwrap();
+        assert_eq!(rates.get("USD"), Some(&Decimal::ONE));
+        assert_eq!(rates.get("IDR"), Some(&dec!(16000.0)));
+        assert_eq!(rates.get("EUR"), Some(&dec!(0.92)));
+
+        let error = r#"{"success":false,"error":{"code":101,"info":"invalid key"}}"#;
+        assert!(parse_exchangerate_host(error).is_err());
+    }
+
+    #[test]
+    fn test_cross_rates() {
+        let quotes = HashMap::from([
+            ("EUR".to_string(), Decimal::ONE),
+            ("USD".to_string(), dec!(1.25)),
+            ("IDR".to_string(), dec!(20000)),
+            ("XYZ".to_string(), dec!(3)),
+        ]);
+        let pairs = cross_rates(&quotes);
+        assert_eq!(pairs.len(), 6);
+        assert!(pairs.contains(&("USD".to_string(), "IDR".to_string(), dec!(16000))));
+        assert!(pairs.contains(&("IDR".to_string(), "EUR".to_string(), dec!(0.00005))));
+        assert!(
+            !pairs
+                .iter()
+                .any(|(base, quote, _)| base == "XYZ" || quote == "XYZ")
+        );
+    }
+}
@@ -0,0 +1,142 @@
+use chrono::{DateTime, Duration, Utc};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use tokio_cron_scheduler::{Job, JobScheduler};
+use uuid::Uuid;
+
+use crate::error::DatabaseError;
+use crate::repos::{
+    expense_entry::ExpenseEntryRepo, expense_group::ExpenseGroupRepo,
+    subscription::SubscriptionRepo,
+};
+use crate::shutdown::Shutdown;
+use crate::types::SubscriptionTier;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct PurgeSummary {
+    pub groups: usize,
+    pub entries: u64,
+}
+
+// Soft-deleted rows older than this are removed for good
+pub fn retention_cutoff(tier: &SubscriptionTier, now: DateTime<Utc>) -> DateTime<Utc> {
+    now - Duration::days(tier.limits().data_retention_days as i64)
+}
+
+pub struct PurgeScheduler {
+    db_pool: PgPool,
+}
+
+impl PurgeScheduler {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self { db_pool }
+    }
+
+    pub async fn start(
+        &self,
+        shutdown: &Shutdown,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let sched = JobScheduler::new().await?;
+
+        // Runs daily at 03:00 UTC, outside of peak usage
+        let db_pool = self.db_pool.clone();
+        let job_shutdown = shutdown.clone();
+
+        let purge_job = Job::new_async("0 0 3 * * *", move |_, _| {
+            let db_pool = db_pool.clone();
+            let running = job_shutdown.track();
+
+            Box::pin(async move {
+                let Some(_running) = running else {
+                    return;
+                };
+                if let Err(e) = Self::purge(&db_pool, Utc::now()).await {
+                    tracing::error!("Error purging deleted data: {:?}", e);
+                }
+            })
+        })?;
+
+        sched.add(purge_job).await?;
+        sched.start().await?;
+        shutdown.stop_with(sched);
+
+        tracing::info!("Purge scheduler started");
+        Ok(())
+    }
+
+    /*
+     * Soft-deleted groups and expense entries stay restorable for the `data_retention_days` of
+     * the group owner's tier, after which they are deleted permanently.
+     */
+    pub async fn purge(
+        db_pool: &PgPool,
+        now: DateTime<Utc>,
+    ) -> Result<PurgeSummary, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tx = db_pool.begin().await?;
+        let mut tiers: HashMap<Uuid, SubscriptionTier> = HashMap::new();
+        let mut summary = PurgeSummary::default();
+
+        for group in ExpenseGroupRepo::list_deleted(&mut tx).await? {
+            let tier = owner_tier(&mut tx, &mut tiers, group.owner).await?;
+            if group.deleted_at < retention_cutoff(&tier, now) {
+                ExpenseGroupRepo::delete(&mut tx, group.uid).await?;
+                summary.groups += 1;
+            }
+        }
+
+        for group in ExpenseGroupRepo::list(&mut tx).await? {
+            let tier = owner_tier(&mut tx, &mut tiers, group.owner).await?;
+            summary.entries +=
+                ExpenseEntryRepo::purge_deleted(&mut tx, group.uid, retention_cutoff(&tier, now))
+                    .await?;
+        }
+
+        tx.commit().await?;
+
+        if summary != PurgeSummary::default() {
+            tracing::info!(
+                "Purged {} deleted groups and {} deleted expense entries",
+                summary.groups,
+                summary.entries
+            );
+        }
+        Ok(summary)
+    }
+}
+
+// Owners without an active subscription get the free tier retention
+pub async fn owner_tier(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    tiers: &mut HashMap<Uuid, SubscriptionTier>,
+    owner: Uuid,
+) -> Result<SubscriptionTier, DatabaseError> {
+    if let Some(tier) = tiers.get(&owner) {
+        return Ok(tier.clone());
+    }
+    let tier = match SubscriptionRepo::get_by_user(tx, owner).await {
+        Ok(subscription) => subscription.get_tier(),
+        Err(DatabaseError::NotFound(_)) => SubscriptionTier::Free,
+        Err(e) => return Err(e),
+    };
+    tiers.insert(owner, tier.clone());
+    Ok(tier)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_retention_cutoff() {
+        let now = Utc.with_ymd_and_hms(2025, 10, 20, 12, 0, 0).unwrap();
+        assert_eq!(
+            retention_cutoff(&SubscriptionTier::Free, now),
+            Utc.with_ymd_and_hms(2025, 7, 22, 12, 0, 0).unwrap()
+        );
+        assert_eq!(
+            retention_cutoff(&SubscriptionTier::Team, now),
+            now - Duration::days(730)
+        );
+    }
+}
@@ -0,0 +1,282 @@
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::sync::Arc;
+use tokio_cron_scheduler::{Job, JobScheduler};
+use uuid::Uuid;
+
+use crate::email::EmailSender;
+use crate::error::DatabaseError;
+use crate::messengers::MessengerManager;
+use crate::reports::{MonthlyReportGenerator, ReportScheduler};
+use crate::repos::job::{Job as QueuedJob, JobRepo, JobStatus};
+use crate::shutdown::Shutdown;
+use crate::webhooks::WebhookSender;
+
+const WORKERS: usize = 4;
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+// A job running longer than this belonged to a worker that died, it is claimed again
+const STALE_AFTER_MINUTES: i64 = 15;
+// Dead jobs are kept until someone looks at them, succeeded ones only this long
+const SUCCEEDED_RETENTION_DAYS: i64 = 7;
+
+/*
+ Work run in the background by `JobWorker`. The variant is stored as the job's `kind`, its
+ fields as the payload, so renaming either strands jobs already queued.
+*/
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JobTask {
+    // Monthly report of a group member, to the group's chats or by email without one
+    MonthlyReport {
+        group_uid: Uuid,
+        user_uid: Uuid,
+    },
+    // A message to one chat, e.g. a digest
+    ChatMessage {
+        platform: String,
+        chat_id: String,
+        text: String,
+    },
+    // One event to one webhook, see `webhooks::dispatch`
+    WebhookDelivery {
+        delivery_uid: Uuid,
+    },
+}
+
+impl JobTask {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::MonthlyReport { .. } => "monthly_report",
+            Self::ChatMessage { .. } => "chat_message",
+            Self::WebhookDelivery { .. } => "webhook_delivery",
+        }
+    }
+}
+
+pub struct JobQueue;
+
+impl JobQueue {
+    /*
+     Queues `task`, it runs once the transaction commits and is dropped with it on
+     rollback. Enqueue in the transaction that records why the task is needed, e.g. marking
+     a digest sent, so the two cannot disagree.
+    */
+    pub async fn enqueue(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        task: &JobTask,
+    ) -> Result<QueuedJob, DatabaseError> {
+        let payload = serde_json::to_value(task)
+            .map_err(|e| DatabaseError::TransactionError(format!("encoding job: {e}")))?;
+        JobRepo::enqueue(tx, task.kind(), &payload, None).await
+    }
+}
+
+#[derive(Clone)]
+struct JobContext {
+    db_pool: PgPool,
+    messenger_manager: Arc<MessengerManager>,
+    email_sender: Arc<dyn EmailSender + Send + Sync>,
+    report_generator: MonthlyReportGenerator,
+    webhook_sender: WebhookSender,
+}
+
+impl JobContext {
+    async fn run(&self, task: &JobTask) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        match task {
+            JobTask::MonthlyReport {
+                group_uid,
+                user_uid,
+            } => {
+                ReportScheduler::send_monthly_report(
+                    &self.db_pool,
+                    &self.messenger_manager,
+                    self.email_sender.as_ref(),
+                    &self.report_generator,
+                    *group_uid,
+                    *user_uid,
+                )
+                .await
+            }
+            JobTask::ChatMessage {
+                platform,
+                chat_id,
+                text,
+            } => {
+                self.messenger_manager
+                    .send_message(platform, chat_id, text)
+                    .await
+            }
+            JobTask::WebhookDelivery { delivery_uid } => {
+                self.webhook_sender
+                    .deliver(&self.db_pool, *delivery_uid)
+                    .await
+            }
+        }
+    }
+}
+
+/*
+ Pool of workers running the queued jobs. Each polls the `jobs` table and runs one job at a
+ time, outside of any transaction, so a slow chat API does not hold database locks. Once
+ shutdown is triggered no job is claimed, the running ones finish within the drain timeout.
+*/
+pub struct JobWorker {
+    context: JobContext,
+}
+
+impl JobWorker {
+    pub fn new(
+        db_pool: PgPool,
+        messenger_manager: Arc<MessengerManager>,
+        email_sender: Arc<dyn EmailSender + Send + Sync>,
+    ) -> Self {
+        let report_generator = MonthlyReportGenerator::new(db_pool.clone());
+        Self {
+            context: JobContext {
+                db_pool,
+                messenger_manager,
+                email_sender,
+                report_generator,
+                webhook_sender: WebhookSender::new(),
+            },
+        }
+    }
+
+    pub async fn start(
+        &self,
+        shutdown: &Shutdown,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        for _ in 0..WORKERS {
+            let context = self.context.clone();
+            let worker_shutdown = shutdown.clone();
+            shutdown.spawn(async move { Self::work(context, worker_shutdown).await });
+        }
+
+        // Runs daily at 04:30 UTC
+        let sched = JobScheduler::new().await?;
+        let db_pool = self.context.db_pool.clone();
+        let job_shutdown = shutdown.clone();
+        let cleanup_job = Job::new_async("0 30 4 * * *", move |_, _| {
+            let db_pool = db_pool.clone();
+            let running = job_shutdown.track();
+
+            Box::pin(async move {
+                let Some(_running) = running else {
+                    return;
+                };
+                if let Err(e) = Self::cleanup(&db_pool).await {
+                    tracing::error!("Error deleting succeeded jobs: {:?}", e);
+                }
+            })
+        })?;
+
+        sched.add(cleanup_job).await?;
+        sched.start().await?;
+        shutdown.stop_with(sched);
+
+        tracing::info!("Job worker pool started with {} workers", WORKERS);
+        Ok(())
+    }
+
+    async fn work(context: JobContext, shutdown: Shutdown) {
+        while !shutdown.is_triggered() {
+            let ran = match Self::run_next(&context).await {
+                Ok(ran) => ran,
+                Err(e) => {
+                    tracing::error!("Error running queued job: {:?}", e);
+                    false
+                }
+            };
+            // Keeps going while jobs are due, waits for new ones otherwise
+            if !ran {
+                tokio::select! {
+                    _ = shutdown.triggered() => {}
+                    _ = tokio::time::sleep(POLL_INTERVAL) => {}
+                }
+            }
+        }
+    }
+
+    // Claims and runs a due job, false when there was none
+    async fn run_next(
+        context: &JobContext,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let stale_before = Utc::now() - Duration::minutes(STALE_AFTER_MINUTES);
+        let mut tx = context.db_pool.begin().await?;
+        let claimed = JobRepo::claim_due(&mut tx, 1, stale_before).await?;
+        tx.commit().await?;
+        let Some(job) = claimed.into_iter().next() else {
+            return Ok(false);
+        };
+
+        let result = match serde_json::from_value::<JobTask>(job.payload.clone()) {
+            Ok(task) => context.run(&task).await,
+            Err(e) => Err(format!("decoding job payload: {e}").into()),
+        };
+
+        let mut tx = context.db_pool.begin().await?;
+        match result {
+            Ok(()) => JobRepo::mark_succeeded(&mut tx, job.uid).await?,
+            Err(e) => {
+                let error = e.to_string();
+                let status = JobRepo::mark_failed(&mut tx, &job, &error, Utc::now()).await?;
+                if status == JobStatus::Dead {
+                    tracing::error!(
+                        "Job {} ({}) failed {} times, giving up: {}",
+                        job.uid,
+                        job.kind,
+                        job.attempts,
+                        error
+                    );
+                } else {
+                    tracing::warn!(
+                        "Job {} ({}) failed attempt {}, retrying: {}",
+                        job.uid,
+                        job.kind,
+                        job.attempts,
+                        error
+                    );
+                }
+            }
+        }
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn cleanup(db_pool: &PgPool) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        let before = Utc::now() - Duration::days(SUCCEEDED_RETENTION_DAYS);
+        let mut tx = db_pool.begin().await?;
+        let deleted = JobRepo::delete_succeeded_before(&mut tx, before).await?;
+        tx.commit().await?;
+        if deleted > 0 {
+            tracing::info!("Deleted {} succeeded jobs", deleted);
+        }
+        Ok(deleted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_round_trips_with_its_kind() {
+        let task = JobTask::MonthlyReport {
+            group_uid: Uuid::new_v4(),
+            user_uid: Uuid::new_v4(),
+        };
+        let payload = serde_json::to_value(&task).unwrap();
+        assert_eq!(payload["kind"], task.kind());
+        assert_eq!(serde_json::from_value::<JobTask>(payload).unwrap(), task);
+
+        let task = JobTask::ChatMessage {
+            platform: "telegram".to_string(),
+            chat_id: "42".to_string(),
+            text: "Daily digest".to_string(),
+        };
+        let payload = serde_json::to_value(&task).unwrap();
+        assert_eq!(payload["kind"], task.kind());
+        assert_eq!(serde_json::from_value::<JobTask>(payload).unwrap(), task);
+    }
+}
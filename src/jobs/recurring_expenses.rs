@@ -0,0 +1,176 @@
+use chrono::{NaiveDate, Utc};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio_cron_scheduler::{Job, JobScheduler};
+use uuid::Uuid;
+
+use crate::lang::Lang;
+use crate::messengers::MessengerManager;
+use crate::repos::{
+    chat_binding::ChatBindingRepo,
+    expense_entry::{CreateExpenseEntryDbPayload, ExpenseEntry, ExpenseEntryRepo},
+    recurring_expense::RecurringExpenseRepo,
+};
+use crate::shutdown::Shutdown;
+use crate::utils::parse_price::format_price_in;
+use crate::webhooks::{self, WebhookEvent};
+
+pub struct RecurringScheduler {
+    db_pool: PgPool,
+    messenger_manager: Arc<MessengerManager>,
+    lang: Lang,
+}
+
+impl RecurringScheduler {
+    pub fn new(db_pool: PgPool, messenger_manager: Arc<MessengerManager>, lang: Lang) -> Self {
+        Self {
+            db_pool,
+            messenger_manager,
+            lang,
+        }
+    }
+
+    pub async fn start(
+        &self,
+        shutdown: &Shutdown,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let sched = JobScheduler::new().await?;
+
+        // Runs hourly; `last_run_on` keeps each item to a single entry per day
+        let db_pool = self.db_pool.clone();
+        let messenger_manager = self.messenger_manager.clone();
+        let lang = self.lang.clone();
+        let job_shutdown = shutdown.clone();
+
+        let recurring_job = Job::new_async("0 0 * * * *", move |_, _| {
+            let db_pool = db_pool.clone();
+            let messenger_manager = messenger_manager.clone();
+            let lang = lang.clone();
+            let running = job_shutdown.track();
+
+            Box::pin(async move {
+                let Some(_running) = running else {
+                    return;
+                };
+                let today = Utc::now().date_naive();
+                match Self::materialize_due(&db_pool, today).await {
+                    Ok(created) => {
+                        Self::notify_groups(&db_pool, &messenger_manager, &lang, created).await;
+                    }
+                    Err(e) => {
+                        tracing::error!("Error materializing recurring expenses: {:?}", e);
+                    }
+                }
+            })
+        })?;
+
+        sched.add(recurring_job).await?;
+        sched.start().await?;
+        shutdown.stop_with(sched);
+
+        tracing::info!("Recurring expense scheduler started");
+        Ok(())
+    }
+
+    /*
+     * Creates an expense entry for every recurring item due on `today`.
+     * Returns the created entries keyed by group so callers can notify chats.
+     */
+    pub async fn materialize_due(
+        db_pool: &PgPool,
+        today: NaiveDate,
+    ) -> Result<HashMap<Uuid, Vec<ExpenseEntry>>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tx = db_pool.begin().await?;
+        let mut created: HashMap<Uuid, Vec<ExpenseEntry>> = HashMap::new();
+
+        let due_items = RecurringExpenseRepo::list_active(&mut tx)
+            .await?
+            .into_iter()
+            .filter(|item| item.is_due_on(today));
+
+        for item in due_items {
+            let expense = ExpenseEntryRepo::create_expense_entry(
+                &mut tx,
+                CreateExpenseEntryDbPayload {
+                    price: item.price,
+                    currency: None,
+                    product: item.product.clone(),
+                    group_uid: item.group_uid,
+                    category_uid: item.category_uid,
+                    created_by: "recurring".to_string(),
+                    created_by_user_uid: None,
+                    created_at: None,
+                    note: None,
+                    envelope_uid: None,
+                },
+            )
+            .await?;
+            RecurringExpenseRepo::mark_run(&mut tx, item.uid, today).await?;
+            webhooks::dispatch(
+                &mut tx,
+                item.group_uid,
+                WebhookEvent::ExpenseCreated,
+                &expense,
+            )
+            .await?;
+
+            created.entry(item.group_uid).or_default().push(expense);
+        }
+
+        tx.commit().await?;
+
+        let total: usize = created.values().map(|items| items.len()).sum();
+        if total > 0 {
+            tracing::info!("Materialized {} recurring expenses", total);
+        }
+        Ok(created)
+    }
+
+    async fn notify_groups(
+        db_pool: &PgPool,
+        messenger_manager: &MessengerManager,
+        lang: &Lang,
+        created: HashMap<Uuid, Vec<ExpenseEntry>>,
+    ) {
+        if created.is_empty() {
+            return;
+        }
+
+        let bindings = match db_pool.begin().await {
+            Ok(mut tx) => ChatBindingRepo::list(&mut tx).await.unwrap_or_default(),
+            Err(e) => {
+                tracing::error!("Failed to load chat bindings for recurring notice: {:?}", e);
+                return;
+            }
+        };
+
+        for (group_uid, items) in created {
+            let mut message = lang.get("MESSENGER__RECURRING_MATERIALIZED_HEADER");
+            for expense in items {
+                message.push_str(&lang.get_with_vars(
+                    "MESSENGER__RECURRING_MATERIALIZED_ENTRY",
+                    HashMap::from([
+                        ("item".to_string(), expense.product),
+                        (
+                            "price".to_string(),
+                            format_price_in(expense.price, &expense.currency),
+                        ),
+                    ]),
+                ));
+            }
+
+            for binding in bindings
+                .iter()
+                .filter(|b| b.group_uid == group_uid && b.status == "active")
+            {
+                if let Err(e) = messenger_manager
+                    .send_message(&binding.platform, &binding.p_uid, &message)
+                    .await
+                {
+                    tracing::error!("Failed to send recurring expense notice: {:?}", e);
+                }
+            }
+        }
+    }
+}
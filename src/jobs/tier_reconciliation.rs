@@ -0,0 +1,325 @@
+use serde::Serialize;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio_cron_scheduler::{Job, JobScheduler};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::email::EmailSender;
+use crate::jobs::purge_deleted::owner_tier;
+use crate::lang::Lang;
+use crate::repos::{
+    budget::BudgetRepo,
+    category::CategoryRepo,
+    expense_group::{ExpenseGroup, ExpenseGroupRepo},
+    expense_group_member::GroupMemberRepo,
+    tier_violation::{CreateTierViolationDbPayload, TierViolation, TierViolationRepo},
+    user::UserRepo,
+};
+use crate::shutdown::Shutdown;
+use crate::types::SubscriptionTier;
+
+#[derive(Debug, Default, PartialEq, Serialize, ToSchema)]
+pub struct ReconcileSummary {
+    /// Group owners whose resources were checked
+    pub users_checked: usize,
+    /// Resources over the limit after this run
+    pub violations: usize,
+    /// Violations first found in this run, their owners were notified
+    pub new_violations: usize,
+    /// Violations of earlier runs that are back within the limit
+    pub resolved: usize,
+}
+
+// Unlimited tiers use -1. Being at the limit is fine, only creating more is blocked
+pub fn exceeds_limit(count: i64, limit: i32) -> bool {
+    limit != -1 && count > limit as i64
+}
+
+fn violation(
+    user_uid: Uuid,
+    group_uid: Option<Uuid>,
+    resource_type: &str,
+    count: i64,
+    limit: i32,
+) -> CreateTierViolationDbPayload {
+    CreateTierViolationDbPayload {
+        user_uid,
+        group_uid,
+        resource_type: resource_type.to_string(),
+        current_count: count as i32,
+        limit_count: limit,
+    }
+}
+
+pub struct TierReconciliationScheduler {
+    db_pool: PgPool,
+    email_sender: Arc<dyn EmailSender + Send + Sync>,
+    lang: Lang,
+}
+
+impl TierReconciliationScheduler {
+    pub fn new(
+        db_pool: PgPool,
+        email_sender: Arc<dyn EmailSender + Send + Sync>,
+        lang: Lang,
+    ) -> Self {
+        Self {
+            db_pool,
+            email_sender,
+            lang,
+        }
+    }
+
+    pub async fn start(
+        &self,
+        shutdown: &Shutdown,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let sched = JobScheduler::new().await?;
+
+        // Runs daily at 04:00 UTC, subscriptions that lapsed overnight are already on free
+        let db_pool = self.db_pool.clone();
+        let email_sender = self.email_sender.clone();
+        let lang = self.lang.clone();
+        let job_shutdown = shutdown.clone();
+
+        let reconcile_job = Job::new_async("0 0 4 * * *", move |_, _| {
+            let db_pool = db_pool.clone();
+            let email_sender = email_sender.clone();
+            let lang = lang.clone();
+            let running = job_shutdown.track();
+
+            Box::pin(async move {
+                let Some(_running) = running else {
+                    return;
+                };
+                if let Err(e) = Self::reconcile(&db_pool, email_sender.as_ref(), &lang).await {
+                    tracing::error!("Error reconciling tier limits: {:?}", e);
+                }
+            })
+        })?;
+
+        sched.add(reconcile_job).await?;
+        sched.start().await?;
+        shutdown.stop_with(sched);
+
+        tracing::info!("Tier reconciliation scheduler started");
+        Ok(())
+    }
+
+    /*
+     * Compares what every group owner holds with the limits of their current tier and keeps
+     * `tier_violations` in sync: new violations are flagged and emailed to the owner, the ones
+     * back within the limit are cleared. Nothing is deleted, the owner decides what to remove.
+     */
+    pub async fn reconcile(
+        db_pool: &PgPool,
+        email_sender: &(dyn EmailSender + Send + Sync),
+        lang: &Lang,
+    ) -> Result<ReconcileSummary, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tx = db_pool.begin().await?;
+        let mut tiers: HashMap<Uuid, SubscriptionTier> = HashMap::new();
+
+        let mut groups_by_owner: HashMap<Uuid, Vec<ExpenseGroup>> = HashMap::new();
+        for group in ExpenseGroupRepo::list(&mut tx).await? {
+            groups_by_owner.entry(group.owner).or_default().push(group);
+        }
+
+        let mut found = Vec::new();
+        let mut group_names = HashMap::new();
+        for (owner, groups) in &groups_by_owner {
+            let limits = owner_tier(&mut tx, &mut tiers, *owner).await?.limits();
+            // Archived groups don't count against the group limit
+            let active_groups = groups.iter().filter(|g| g.archived_at.is_none()).count() as i64;
+            if exceeds_limit(active_groups, limits.max_groups) {
+                found.push(violation(
+                    *owner,
+                    None,
+                    "groups",
+                    active_groups,
+                    limits.max_groups,
+                ));
+            }
+            for group in groups {
+                group_names.insert(group.uid, group.name.clone());
+                let counts = [
+                    (
+                        "members_per_group",
+                        GroupMemberRepo::count_by_group(&mut tx, group.uid).await?,
+                        limits.max_members_per_group,
+                    ),
+                    (
+                        "categories_per_group",
+                        CategoryRepo::count_by_group(&mut tx, group.uid).await?,
+                        limits.max_categories_per_group,
+                    ),
+                    (
+                        "budgets_per_group",
+                        BudgetRepo::count_by_group(&mut tx, group.uid).await?,
+                        limits.max_budgets_per_group,
+                    ),
+                ];
+                for (resource_type, count, limit) in counts {
+                    if exceeds_limit(count, limit) {
+                        found.push(violation(
+                            *owner,
+                            Some(group.uid),
+                            resource_type,
+                            count,
+                            limit,
+                        ));
+                    }
+                }
+            }
+        }
+
+        let mut summary = ReconcileSummary {
+            users_checked: groups_by_owner.len(),
+            violations: found.len(),
+            ..Default::default()
+        };
+        let mut existing: HashMap<(Uuid, Option<Uuid>, String), TierViolation> =
+            TierViolationRepo::list(&mut tx)
+                .await?
+                .into_iter()
+                .map(|v| ((v.user_uid, v.group_uid, v.resource_type.clone()), v))
+                .collect();
+        let mut new_by_user: HashMap<Uuid, Vec<CreateTierViolationDbPayload>> = HashMap::new();
+        for payload in found {
+            let key = (
+                payload.user_uid,
+                payload.group_uid,
+                payload.resource_type.clone(),
+            );
+            match existing.remove(&key) {
+                Some(current) => {
+                    TierViolationRepo::update_counts(
+                        &mut tx,
+                        current.uid,
+                        payload.current_count,
+                        payload.limit_count,
+                    )
+                    .await?;
+                }
+                None => {
+                    TierViolationRepo::create(&mut tx, payload.clone()).await?;
+                    summary.new_violations += 1;
+                    new_by_user
+                        .entry(payload.user_uid)
+                        .or_default()
+                        .push(payload);
+                }
+            }
+        }
+        // Whatever was not found again is back within the limit, or its group is gone
+        for resolved in existing.into_values() {
+            TierViolationRepo::delete(&mut tx, resolved.uid).await?;
+            summary.resolved += 1;
+        }
+
+        let mut notifications = Vec::new();
+        for (user_uid, violations) in &new_by_user {
+            let user = UserRepo::get(&mut tx, *user_uid).await?;
+            let tier = owner_tier(&mut tx, &mut tiers, *user_uid).await?;
+            let (subject, body) = Self::notification(lang, &tier, violations, &group_names);
+            notifications.push((user.email, subject, body));
+        }
+
+        tx.commit().await?;
+
+        for (email, subject, body) in notifications {
+            if let Err(e) = email_sender.send(&email, &subject, &body).await {
+                tracing::error!("Failed to send tier limit email to {}: {:?}", email, e);
+            }
+        }
+
+        tracing::info!(
+            "Reconciled tier limits of {} users: {} violations, {} new, {} resolved",
+            summary.users_checked,
+            summary.violations,
+            summary.new_violations,
+            summary.resolved
+        );
+        Ok(summary)
+    }
+
+    fn notification(
+        lang: &Lang,
+        tier: &SubscriptionTier,
+        violations: &[CreateTierViolationDbPayload],
+        group_names: &HashMap<Uuid, String>,
+    ) -> (String, String) {
+        let resources = violations
+            .iter()
+            .map(|v| {
+                let mut vars = HashMap::from([
+                    (
+                        "resource".to_string(),
+                        lang.get(&format!(
+                            "TIER__RESOURCE_{}",
+                            v.resource_type.to_uppercase()
+                        )),
+                    ),
+                    ("current".to_string(), v.current_count.to_string()),
+                    ("limit".to_string(), v.limit_count.to_string()),
+                ]);
+                match v.group_uid.and_then(|uid| group_names.get(&uid)) {
+                    Some(group) => {
+                        vars.insert("group".to_string(), group.clone());
+                        lang.get_with_vars("EMAIL__TIER_LIMIT_GROUP_ITEM", vars)
+                    }
+                    None => lang.get_with_vars("EMAIL__TIER_LIMIT_ITEM", vars),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let tier_name = tier.display_name().to_string();
+        let subject = lang.get_with_vars(
+            "EMAIL__TIER_LIMIT_SUBJECT",
+            HashMap::from([("tier".to_string(), tier_name.clone())]),
+        );
+        let body = lang.get_with_vars(
+            "EMAIL__TIER_LIMIT_BODY",
+            HashMap::from([
+                ("tier".to_string(), tier_name),
+                ("resources".to_string(), resources),
+            ]),
+        );
+        (subject, body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exceeds_limit() {
+        assert!(exceeds_limit(6, 5));
+        assert!(!exceeds_limit(5, 5));
+        assert!(!exceeds_limit(1000, -1));
+    }
+
+    #[test]
+    fn test_notification() {
+        let lang = Lang::from_json("id");
+        let group_uid = Uuid::new_v4();
+        let user_uid = Uuid::new_v4();
+        let violations = [
+            violation(user_uid, None, "groups", 3, 1),
+            violation(user_uid, Some(group_uid), "categories_per_group", 12, 5),
+        ];
+        let group_names = HashMap::from([(group_uid, "Rumah".to_string())]);
+
+        let (subject, body) = TierReconciliationScheduler::notification(
+            &lang,
+            &SubscriptionTier::Free,
+            &violations,
+            &group_names,
+        );
+        assert!(subject.contains("Free"));
+        assert!(body.contains("- Grup: 3/1"));
+        assert!(body.contains("- Kategori di grup Rumah: 12/5"));
+    }
+}
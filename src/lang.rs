@@ -1,75 +1,328 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock, RwLockReadGuard};
+use std::time::{Duration, SystemTime};
 use tera::{Context, Tera};
 
+// Languages with a pack in lang/, chosen per user and per group
+pub const SUPPORTED_LANGS: &[&str] = &["id", "en"];
+pub const DEFAULT_LANG: &str = "id";
+
+// Every [code].json in this directory is loaded as the pack of that language
+pub const LANG_DIR: &str = "lang";
+
+// How often the reloader looks for edited packs
+const RELOAD_INTERVAL: Duration = Duration::from_secs(2);
+
+pub fn is_supported(lang: &str) -> bool {
+    SUPPORTED_LANGS.contains(&lang)
+}
+
+// The messages of one language, compiled as templates for `get_with_vars`
 #[derive(Debug)]
-pub struct Lang {
-    pub lang: String,
-    pub messages: HashMap<String, String>,
-    pub tera: Tera,
+struct Pack {
+    messages: HashMap<String, String>,
+    tera: Tera,
 }
 
-impl Clone for Lang {
-    fn clone(&self) -> Self {
+impl Pack {
+    fn parse(json: &str) -> anyhow::Result<Self> {
+        let messages: HashMap<String, String> = serde_json::from_str(json)?;
         let mut tera = Tera::default();
-        for (key, message) in &self.messages {
-            tera.add_raw_template(key, message).unwrap();
+        for (key, message) in &messages {
+            tera.add_raw_template(key, message)?;
         }
-        Lang {
-            lang: self.lang.clone(),
-            messages: self.messages.clone(),
-            tera,
+        Ok(Self { messages, tera })
+    }
+}
+
+// All packs of a directory, shared by every `Lang` loaded from it
+#[derive(Debug)]
+struct Packs {
+    dir: PathBuf,
+    packs: HashMap<String, Pack>,
+    modified: Option<SystemTime>,
+}
+
+impl Packs {
+    fn load(dir: &Path) -> anyhow::Result<Self> {
+        let modified = last_modified(dir);
+        let mut packs = HashMap::new();
+        for path in pack_files(dir)? {
+            let Some(code) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            let pack = std::fs::read_to_string(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|json| Pack::parse(&json))
+                .map_err(|e| anyhow::anyhow!("Invalid language pack {}: {}", path.display(), e))?;
+            packs.insert(code.to_string(), pack);
+        }
+        if !packs.contains_key(DEFAULT_LANG) {
+            return Err(anyhow::anyhow!(
+                "Missing default language pack {}/{}.json",
+                dir.display(),
+                DEFAULT_LANG
+            ));
+        }
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            packs,
+            modified,
+        })
+    }
+}
+
+fn pack_files(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            files.push(path);
         }
     }
+    Ok(files)
+}
+
+// Changes when a pack is edited, added or removed
+fn last_modified(dir: &Path) -> Option<SystemTime> {
+    let files = pack_files(dir).ok()?;
+    std::iter::once(dir.to_path_buf())
+        .chain(files)
+        .filter_map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+        .max()
+}
+
+/*
+ Messages in one language. Keys missing from its pack are looked up in the default language,
+ then the key itself is returned. Clones share the loaded packs, so a reload reaches all of them.
+*/
+#[derive(Debug, Clone)]
+pub struct Lang {
+    pub lang: String,
+    packs: Arc<RwLock<Packs>>,
 }
 
 impl Lang {
     pub fn from_json(lang: &str) -> Self {
-        let path = format!("lang/{}.json", lang);
-        let lang_data = std::fs::read_to_string(&path).unwrap_or_else(|_| {
-            std::fs::read_to_string("lang/id.json").expect("Failed to read default language file")
-        });
-        let messages: HashMap<String, String> = serde_json::from_str(&lang_data).unwrap();
-
-        let mut tera = Tera::default();
-        for (key, message) in &messages {
-            tera.add_raw_template(key, message).unwrap();
-        }
+        Self::from_dir(LANG_DIR, lang)
+    }
 
+    // Panics when the default pack is missing or any pack is invalid
+    pub fn from_dir(dir: impl AsRef<Path>, lang: &str) -> Self {
+        let packs = Packs::load(dir.as_ref()).expect("Failed to load language packs");
         Lang {
             lang: lang.to_string(),
-            messages,
-            tera,
+            packs: Arc::new(RwLock::new(packs)),
+        }
+    }
+
+    // The pack for a user's or group's language, this one when it is unset or unsupported
+    pub fn preferred(&self, lang: Option<&str>) -> Cow<'_, Lang> {
+        match lang {
+            Some(lang) if is_supported(lang) && lang != self.lang => Cow::Owned(Lang {
+                lang: lang.to_string(),
+                packs: self.packs.clone(),
+            }),
+            _ => Cow::Borrowed(self),
         }
     }
 
     pub fn get(&self, key: &str) -> String {
-        self.messages
-            .get(key)
+        let packs = self.read();
+        self.chain(&packs)
+            .find_map(|pack| pack.messages.get(key))
             .cloned()
             .unwrap_or_else(|| key.to_string())
     }
 
     pub fn get_with_vars(&self, key: &str, vars: HashMap<String, String>) -> String {
+        let packs = self.read();
+        let Some(pack) = self
+            .chain(&packs)
+            .find(|pack| pack.messages.contains_key(key))
+        else {
+            return key.to_string();
+        };
+
         let mut context = Context::new();
         for (var, value) in vars {
             context.insert(&var, &value);
         }
 
         // Set default values for missing variables (the variable name itself)
-        if let Some(message) = self.messages.get(key) {
-            // Extract variable names from the message template
-            let var_regex = regex::Regex::new(r"\{\{([^}]+)\}\}").unwrap();
-            for cap in var_regex.captures_iter(message) {
-                let var_name = &cap[1];
-                if !context.contains_key(var_name) {
-                    context.insert(var_name, var_name);
-                }
+        let message = &pack.messages[key];
+        // Extract variable names from the message template
+        let var_regex = regex::Regex::new(r"\{\{([^}]+)\}\}").unwrap();
+        for cap in var_regex.captures_iter(message) {
+            let var_name = &cap[1];
+            if !context.contains_key(var_name) {
+                context.insert(var_name, var_name);
             }
         }
 
-        match self.tera.render(key, &context) {
+        match pack.tera.render(key, &context) {
             Ok(rendered) => rendered,
-            Err(_) => self.get(key), // Fallback to the raw message if rendering fails
+            Err(_) => message.clone(), // Fallback to the raw message if rendering fails
+        }
+    }
+
+    /*
+     Reloads the packs every few seconds when their files change, so translators see their edits
+     without a restart. Debug builds only; invalid edits are logged and the loaded packs kept.
+    */
+    pub fn spawn_reloader(&self) {
+        if !cfg!(debug_assertions) {
+            tracing::warn!("Language pack reloading is only available in debug builds");
+            return;
+        }
+
+        let lang = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(RELOAD_INTERVAL);
+            loop {
+                interval.tick().await;
+                let (dir, modified) = {
+                    let packs = lang.read();
+                    (packs.dir.clone(), packs.modified)
+                };
+                if last_modified(&dir) == modified {
+                    continue;
+                }
+                match lang.reload() {
+                    Ok(()) => tracing::info!("Reloaded language packs from {}", dir.display()),
+                    Err(e) => tracing::error!("Failed to reload language packs: {}", e),
+                }
+            }
+        });
+    }
+
+    fn reload(&self) -> anyhow::Result<()> {
+        let dir = self.read().dir.clone();
+        let result = Packs::load(&dir);
+        let mut packs = self.packs.write().unwrap_or_else(|e| e.into_inner());
+        match result {
+            Ok(loaded) => {
+                *packs = loaded;
+                Ok(())
+            }
+            Err(e) => {
+                // Not retried until the files change again
+                packs.modified = last_modified(&dir);
+                Err(e)
+            }
+        }
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, Packs> {
+        self.packs.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // The packs a key is looked up in, in order
+    fn chain<'a>(&self, packs: &'a Packs) -> impl Iterator<Item = &'a Pack> {
+        [self.lang.as_str(), DEFAULT_LANG]
+            .into_iter()
+            .filter_map(|code| packs.packs.get(code))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A lang directory of its own, removed when the test is done
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(packs: &[(&str, &str)]) -> Self {
+            let dir = std::env::temp_dir().join(format!("lang-{}", uuid::Uuid::new_v4()));
+            std::fs::create_dir_all(&dir).unwrap();
+            let dir = Self(dir);
+            for (code, json) in packs {
+                dir.write(code, json);
+            }
+            dir
+        }
+
+        fn write(&self, code: &str, json: &str) {
+            std::fs::write(self.0.join(format!("{}.json", code)), json).unwrap();
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_supported_langs_have_the_same_keys() {
+        let lang = Lang::from_json(DEFAULT_LANG);
+        let packs = lang.read();
+        let keys = |code: &str| {
+            let mut keys: Vec<_> = packs.packs[code].messages.keys().cloned().collect();
+            keys.sort();
+            keys
+        };
+        for code in SUPPORTED_LANGS {
+            assert_eq!(keys(code), keys(DEFAULT_LANG), "keys of {}", code);
         }
     }
+
+    #[test]
+    fn test_preferred() {
+        let lang = Lang::from_json("id");
+        assert_eq!(lang.preferred(None).lang, "id");
+        assert_eq!(lang.preferred(Some("fr")).lang, "id");
+        assert_eq!(lang.preferred(Some("en")).lang, "en");
+        assert!(matches!(lang.preferred(Some("id")), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_fallback_chain() {
+        let dir = TempDir::new(&[
+            (
+                "id",
+                r#"{"GREETING": "Halo", "THANKS": "Terima kasih {{name}}"}"#,
+            ),
+            ("en", r#"{"GREETING": "Hello"}"#),
+        ]);
+        let lang = Lang::from_dir(&dir.0, "en");
+
+        assert_eq!(lang.get("GREETING"), "Hello");
+        assert_eq!(lang.get("THANKS"), "Terima kasih {{name}}");
+        assert_eq!(
+            lang.get_with_vars(
+                "THANKS",
+                HashMap::from([("name".to_string(), "Budi".to_string())])
+            ),
+            "Terima kasih Budi"
+        );
+        assert_eq!(lang.get("MISSING"), "MISSING");
+        assert_eq!(lang.get_with_vars("MISSING", HashMap::new()), "MISSING");
+
+        // Without a pack of its own everything comes from the default one
+        assert_eq!(Lang::from_dir(&dir.0, "fr").get("GREETING"), "Halo");
+    }
+
+    #[test]
+    fn test_reload() {
+        let dir = TempDir::new(&[("id", r#"{"GREETING": "Halo"}"#)]);
+        let lang = Lang::from_dir(&dir.0, "id");
+        let clone = lang.clone();
+
+        dir.write("id", r#"{"GREETING": "Halo semua"}"#);
+        lang.reload().unwrap();
+        assert_eq!(clone.get("GREETING"), "Halo semua");
+
+        // Invalid edits keep the loaded packs
+        dir.write("id", r#"{"GREETING": "#);
+        assert!(lang.reload().is_err());
+        assert_eq!(clone.get("GREETING"), "Halo semua");
+    }
 }
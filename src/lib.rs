@@ -1,16 +1,29 @@
+#[cfg(feature = "server")]
 pub mod app;
 pub mod auth;
+pub mod backup;
+pub mod billing;
+#[cfg(feature = "client")]
+pub mod client;
 pub mod commands;
 pub mod config;
 pub mod db;
+pub mod email;
 pub mod error;
+pub mod jobs;
 pub mod lang;
 pub mod messengers;
 pub mod middleware;
+#[cfg(feature = "server")]
 pub mod openapi;
 pub mod reports;
 pub mod repos;
 pub mod routes;
+pub mod shutdown;
+pub mod storage;
+#[cfg(feature = "server")]
 pub mod telegram_logger;
+pub mod telemetry;
 pub mod types;
 pub mod utils;
+pub mod webhooks;
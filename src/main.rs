@@ -1,20 +1,40 @@
 use anyhow::Result;
 use expense_tracker::{
-    app, db,
-    lang::Lang,
-    messengers::{MessengerManager, telegram::TelegramMessenger},
+    app,
+    auth::{jwt::JwtKeys, membership_cache::MembershipCache},
+    billing::{PaymentProvider, payment_provider_from_config},
+    db,
+    email::{EmailSender, email_sender_from_config},
+    jobs::{
+        AnomalyAlertScheduler, BackupScheduler, BindRequestCleanupScheduler, BudgetAlertScheduler,
+        DebtReminderScheduler, ExchangeRateScheduler, JobWorker, PurgeScheduler,
+        RecurringScheduler, RetentionScheduler, TierReconciliationScheduler,
+    },
+    lang::{DEFAULT_LANG, Lang},
+    messengers::{
+        MessengerManager, slack::SlackMessenger, telegram::TelegramMessenger,
+        whatsapp::WhatsAppMessenger,
+    },
+    middleware::rate_limit::RateLimiter,
     reports::ReportScheduler,
+    shutdown::Shutdown,
+    storage::{FileStorage, file_storage_from_config},
     telegram_logger::TelegramLogger,
+    telemetry,
     types::AppState,
+    utils::pii::{self, PiiCipher},
 };
-use std::sync::Arc;
+use std::{net::SocketAddr, sync::Arc, time::Duration};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+// How long shutting down waits for chat messages and scheduled jobs still running
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+
 #[tokio::main]
 async fn main() -> Result<()> {
     // initialize tracing
-    let config = expense_tracker::config::Config::from_env();
-    let lang = Lang::from_json("id");
+    let config = expense_tracker::config::Config::from_env()?;
+    let lang = Lang::from_json(DEFAULT_LANG);
 
     let registry = tracing_subscriber::registry();
 
@@ -31,59 +51,221 @@ async fn main() -> Result<()> {
         registry.with(tracing_subscriber::fmt::layer()).init();
     }
 
-    let db_pool = db::make_db_pool(&config.database_url).await?;
+    if config.lang_hot_reload {
+        lang.spawn_reloader();
+    }
+
+    // Before anything records metrics
+    telemetry::handle();
+    telemetry::spawn_upkeep();
+
+    // Before anything reads or writes users
+    if let Some(key) = &config.pii_encryption_key {
+        pii::install(PiiCipher::new(key));
+    }
+
+    let db_pool = db::connect(&config.database_url, &config.db).await?;
+
+    // Shared by the REST API and the chat messengers
+    let rate_limiter = Arc::new(RateLimiter::new());
 
     // Initialize messenger manager
     let mut messenger_manager = MessengerManager::new();
 
     // Add Telegram bot if token is provided
     if !config.telegram_bot_token.is_empty() {
-        let telegram_messenger = TelegramMessenger::new(&config, db_pool.clone());
+        let telegram_messenger =
+            TelegramMessenger::new(&config, db_pool.clone(), lang.clone(), rate_limiter.clone());
         messenger_manager.add_messenger(Box::new(telegram_messenger));
     }
 
+    // Add WhatsApp Cloud API messenger if credentials are provided
+    let mut whatsapp_router = None;
+    if WhatsAppMessenger::is_configured(&config) {
+        let whatsapp_messenger =
+            WhatsAppMessenger::new(&config, db_pool.clone(), lang.clone(), rate_limiter.clone());
+        whatsapp_router = Some(whatsapp_messenger.clone().router());
+        messenger_manager.add_messenger(Box::new(whatsapp_messenger));
+    }
+
+    // Add Slack messenger if the bot token and signing secret are provided
+    let mut slack_router = None;
+    if SlackMessenger::is_configured(&config) {
+        let slack_messenger =
+            SlackMessenger::new(&config, db_pool.clone(), lang.clone(), rate_limiter.clone());
+        slack_router = Some(slack_messenger.clone().router());
+        messenger_manager.add_messenger(Box::new(slack_messenger));
+    }
+
     // Create Arc for messenger manager
     let messenger_manager_arc = Arc::new(messenger_manager);
 
+    // Stops the messengers and schedulers together with the HTTP server
+    let shutdown = Shutdown::new();
+
     // Start messengers
-    if let Err(e) = messenger_manager_arc.start_all().await {
+    if let Err(e) = messenger_manager_arc.start_all(&shutdown).await {
         tracing::error!("Failed to start messengers: {:?}", e);
         return Err(anyhow::anyhow!("Failed to start messengers"));
     }
 
-    // Start report scheduler
-    // let report_scheduler = ReportScheduler::new(db_pool.clone(), messenger_manager_arc.clone());
-    // if let Err(e) = report_scheduler.start().await {
-    //     tracing::error!("Failed to start report scheduler: {:?}", e);
-    //     return Err(anyhow::anyhow!("Failed to start report scheduler"));
-    // }
+    let email_sender: Arc<dyn EmailSender + Send + Sync> =
+        Arc::from(email_sender_from_config(&config));
+    let payment_provider: Option<Arc<dyn PaymentProvider + Send + Sync>> =
+        payment_provider_from_config(&config).map(Arc::from);
+    let file_storage: Arc<dyn FileStorage + Send + Sync> =
+        Arc::from(file_storage_from_config(&config));
+
+    // Start the workers running queued jobs, e.g. the reports queued below
+    let job_worker = JobWorker::new(
+        db_pool.clone(),
+        messenger_manager_arc.clone(),
+        email_sender.clone(),
+    );
+    if let Err(e) = job_worker.start(&shutdown).await {
+        tracing::error!("Failed to start job workers: {:?}", e);
+        return Err(anyhow::anyhow!("Failed to start job workers"));
+    }
+
+    // Start report scheduler, reports go by email to groups without a chat binding
+    let report_scheduler = ReportScheduler::new(db_pool.clone(), lang.clone());
+    if let Err(e) = report_scheduler.start(&shutdown).await {
+        tracing::error!("Failed to start report scheduler: {:?}", e);
+        return Err(anyhow::anyhow!("Failed to start report scheduler"));
+    }
+
+    // Start recurring expense scheduler
+    let recurring_scheduler = RecurringScheduler::new(
+        db_pool.clone(),
+        messenger_manager_arc.clone(),
+        lang.clone(),
+    );
+    if let Err(e) = recurring_scheduler.start(&shutdown).await {
+        tracing::error!("Failed to start recurring expense scheduler: {:?}", e);
+        return Err(anyhow::anyhow!("Failed to start recurring expense scheduler"));
+    }
+
+    // Start budget alert scheduler
+    let budget_alert_scheduler = BudgetAlertScheduler::new(
+        db_pool.clone(),
+        messenger_manager_arc.clone(),
+        lang.clone(),
+    );
+    if let Err(e) = budget_alert_scheduler.start(&shutdown).await {
+        tracing::error!("Failed to start budget alert scheduler: {:?}", e);
+        return Err(anyhow::anyhow!("Failed to start budget alert scheduler"));
+    }
+
+    // Start unusual spending alerts, groups pick the sensitivity in their settings
+    let anomaly_alert_scheduler =
+        AnomalyAlertScheduler::new(db_pool.clone(), messenger_manager_arc.clone(), lang.clone());
+    if let Err(e) = anomaly_alert_scheduler.start(&shutdown).await {
+        tracing::error!("Failed to start anomaly alert scheduler: {:?}", e);
+        return Err(anyhow::anyhow!("Failed to start anomaly alert scheduler"));
+    }
+
+    // Start reminders of debts near their due date
+    let debt_reminder_scheduler =
+        DebtReminderScheduler::new(db_pool.clone(), messenger_manager_arc.clone(), lang.clone());
+    if let Err(e) = debt_reminder_scheduler.start(&shutdown).await {
+        tracing::error!("Failed to start debt reminder scheduler: {:?}", e);
+        return Err(anyhow::anyhow!("Failed to start debt reminder scheduler"));
+    }
+
+    // Start exchange rate sync, a no-op unless EXCHANGE_RATE_PROVIDER is set
+    let exchange_rate_scheduler = ExchangeRateScheduler::new(db_pool.clone(), &config);
+    if let Err(e) = exchange_rate_scheduler.start(&shutdown).await {
+        tracing::error!("Failed to start exchange rate scheduler: {:?}", e);
+        return Err(anyhow::anyhow!("Failed to start exchange rate scheduler"));
+    }
+
+    // Start purge of soft-deleted groups and entries past their retention
+    let purge_scheduler = PurgeScheduler::new(db_pool.clone());
+    if let Err(e) = purge_scheduler.start(&shutdown).await {
+        tracing::error!("Failed to start purge scheduler: {:?}", e);
+        return Err(anyhow::anyhow!("Failed to start purge scheduler"));
+    }
+
+    // Start deletion of expense entries older than the owner's tier retention
+    let retention_scheduler = RetentionScheduler::new(db_pool.clone(), &config);
+    if let Err(e) = retention_scheduler.start(&shutdown).await {
+        tracing::error!("Failed to start data retention scheduler: {:?}", e);
+        return Err(anyhow::anyhow!("Failed to start data retention scheduler"));
+    }
+
+    // Start flagging resources over the limits of their owner's tier, e.g. after a downgrade
+    let tier_reconciliation_scheduler =
+        TierReconciliationScheduler::new(db_pool.clone(), email_sender.clone(), lang.clone());
+    if let Err(e) = tier_reconciliation_scheduler.start(&shutdown).await {
+        tracing::error!("Failed to start tier reconciliation scheduler: {:?}", e);
+        return Err(anyhow::anyhow!("Failed to start tier reconciliation scheduler"));
+    }
+
+    // Start cleanup of chat bind requests that were used or expired
+    let bind_request_cleanup_scheduler = BindRequestCleanupScheduler::new(db_pool.clone());
+    if let Err(e) = bind_request_cleanup_scheduler.start(&shutdown).await {
+        tracing::error!("Failed to start chat bind request cleanup scheduler: {:?}", e);
+        return Err(anyhow::anyhow!("Failed to start chat bind request cleanup scheduler"));
+    }
+
+    // Start nightly backups, a no-op unless BACKUP_DIR is set
+    let backup_scheduler = BackupScheduler::new(db_pool.clone(), &config);
+    if let Err(e) = backup_scheduler.start(&shutdown).await {
+        tracing::error!("Failed to start backup scheduler: {:?}", e);
+        return Err(anyhow::anyhow!("Failed to start backup scheduler"));
+    }
 
     // build our application with a route
-    let app = app::build_router(AppState {
+    let bind_address = config.bind_address();
+    let messengers = messenger_manager_arc.clone();
+    let mut app = app::build_router_with_config(AppState {
         version: "0.1.0".to_string(),
-        db_pool,
-        jwt_secret: config.jwt_secret,
+        db_pool: db_pool.clone(),
+        jwt_keys: Arc::new(JwtKeys::from_config(&config)),
         chat_relay_secret: config.chat_relay_secret,
         front_end_url: config.front_end_url,
+        chat_bind_url: config.chat_bind_url,
+        email_ingest_domain: config.email_ingest_domain,
         messenger_manager: Some(messenger_manager_arc),
+        email_sender,
+        payment_provider,
+        file_storage,
+        rate_limiter,
+        membership_cache: Arc::new(MembershipCache::new()),
         lang,
-    });
+    }, &config.http);
+
+    if let Some(whatsapp_router) = whatsapp_router {
+        app = app.merge(whatsapp_router);
+    }
+    if let Some(slack_router) = slack_router {
+        app = app.merge(slack_router);
+    }
 
-    // run our app with hyper, listening globally on port 3000
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
+    let listener = tokio::net::TcpListener::bind(&bind_address)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to listen on {}: {}", bind_address, e))?;
     tracing::info!("listening on {}", listener.local_addr().unwrap());
 
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal())
+    // Connection info lets the rate limiter key unauthenticated requests on the client IP
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+        .with_graceful_shutdown(shutdown.clone().on_signal())
         .await?;
 
+    // Requests are done, let the chat messages and scheduled jobs being handled finish
+    messengers.stop_all().await;
+    if !shutdown.drain(SHUTDOWN_TIMEOUT).await {
+        tracing::warn!(
+            "Background tasks still running after {} seconds, exiting anyway",
+            SHUTDOWN_TIMEOUT.as_secs()
+        );
+    }
+    db_pool.close().await;
+    tracing::info!("shutdown complete");
+
     Ok(())
 }
 
-async fn shutdown_signal() {
-    // Wait for the CTRL+C signal
-    tokio::signal::ctrl_c()
-        .await
-        .expect("failed to install CTRL+C signal handler");
-    tracing::info!("signal received, starting graceful shutdown");
-}
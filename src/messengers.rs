@@ -1,8 +1,15 @@
+#[cfg(feature = "test-util")]
+pub mod mock;
+pub mod paginator;
+pub mod slack;
 pub mod telegram;
+pub mod whatsapp;
 
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 
+use crate::shutdown::Shutdown;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
     pub platform: String,
@@ -19,7 +26,10 @@ pub trait Messenger {
         chat_id: &str,
         text: &str,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
-    async fn start(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+    // Long running tasks are spawned through `shutdown`, so shutting down waits for them
+    async fn start(&self, shutdown: &Shutdown) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+    // Stops taking messages and waits for the ones being handled
+    async fn stop(&self) {}
     fn platform(&self) -> &str;
 }
 
@@ -38,13 +48,20 @@ impl MessengerManager {
         self.messengers.push(messenger);
     }
 
-    pub async fn start_all(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    pub async fn start_all(&self, shutdown: &Shutdown) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         for messenger in &self.messengers {
-            messenger.start().await?;
+            messenger.start(shutdown).await?;
         }
         Ok(())
     }
 
+    pub async fn stop_all(&self) {
+        for messenger in &self.messengers {
+            messenger.stop().await;
+            tracing::info!("Stopped {} messenger", messenger.platform());
+        }
+    }
+
     pub async fn send_message(
         &self,
         platform: &str,
@@ -0,0 +1,170 @@
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use sqlx::PgPool;
+
+use crate::commands::base::{ChatButton, ChatReply, ChatSender};
+use crate::commands::dispatcher::CommandDispatcher;
+use crate::lang::Lang;
+use crate::middleware::rate_limit::RateLimiter;
+use crate::repos::chat_binding::ChatBindingRepo;
+use crate::shutdown::Shutdown;
+
+use super::Messenger;
+
+// A message sent to a chat, kept whole where a real messenger would split a long one
+#[derive(Debug, Clone, PartialEq)]
+pub struct SentMessage {
+    pub chat_id: String,
+    pub text: String,
+    pub buttons: Vec<Vec<ChatButton>>,
+}
+
+/*
+ In-memory messenger for end-to-end tests of the chat flow, no bot credentials needed.
+ Incoming messages and pressed buttons are injected with `receive` and `press` and go through
+ the command dispatcher the way a real messenger's updates do. Their replies and whatever is
+ sent through `send_message` are recorded. Clones share the recording, so one clone can be
+ added to the `MessengerManager` while the test keeps the other.
+*/
+#[derive(Clone)]
+pub struct MockMessenger {
+    platform: String,
+    db_pool: PgPool,
+    lang: Lang,
+    chat_bind_url: String,
+    rate_limiter: Arc<RateLimiter>,
+    sent: Arc<Mutex<Vec<SentMessage>>>,
+}
+
+impl MockMessenger {
+    // Poses as `platform`, bindings are stored per platform so it has to be a `chat_platform`
+    pub fn new(platform: &str, db_pool: PgPool, lang: Lang, chat_bind_url: &str) -> Self {
+        Self {
+            platform: platform.to_string(),
+            db_pool,
+            lang,
+            chat_bind_url: chat_bind_url.to_string(),
+            rate_limiter: Arc::new(RateLimiter::new()),
+            sent: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    // Handles `text` sent by `sender` in the chat, returns the reply or None when it is ignored
+    pub async fn receive(
+        &self,
+        chat_id: &str,
+        sender: &ChatSender,
+        text: &str,
+    ) -> Result<Option<ChatReply>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tx = self.db_pool.begin().await?;
+        let binding = ChatBindingRepo::get_selected(&mut tx, &self.platform, chat_id).await?;
+
+        let reply = match binding {
+            Some(binding) => {
+                CommandDispatcher::dispatch(
+                    text,
+                    &binding,
+                    sender,
+                    &self.chat_bind_url,
+                    &mut tx,
+                    &self.lang,
+                    &self.rate_limiter,
+                )
+                .await
+            }
+            None => Some(
+                CommandDispatcher::dispatch_unbound(
+                    &self.platform,
+                    chat_id,
+                    text,
+                    &self.chat_bind_url,
+                    &mut tx,
+                    &self.lang,
+                )
+                .await?
+                .into(),
+            ),
+        };
+        tx.commit().await?;
+
+        if let Some(reply) = &reply {
+            self.record(chat_id, reply);
+        }
+        Ok(reply)
+    }
+
+    /*
+     Presses the button carrying `data` under our reply `message`, returns the reply the
+     message is edited into. Edits are recorded like sent messages.
+    */
+    pub async fn press(
+        &self,
+        chat_id: &str,
+        sender: &ChatSender,
+        message: &str,
+        data: &str,
+    ) -> Result<Option<ChatReply>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tx = self.db_pool.begin().await?;
+        let Some(binding) = ChatBindingRepo::get_selected(&mut tx, &self.platform, chat_id).await?
+        else {
+            return Ok(None);
+        };
+
+        let reply = CommandDispatcher::dispatch_callback(
+            data, message, &binding, sender, &mut tx, &self.lang,
+        )
+        .await;
+        tx.commit().await?;
+
+        if let Some(reply) = &reply {
+            self.record(chat_id, reply);
+        }
+        Ok(reply)
+    }
+
+    // Everything sent so far, oldest first
+    pub fn sent(&self) -> Vec<SentMessage> {
+        self.sent.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    // Everything sent so far, leaving the recording empty for the next step of a test
+    pub fn take_sent(&self) -> Vec<SentMessage> {
+        std::mem::take(&mut *self.sent.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    fn record(&self, chat_id: &str, reply: &ChatReply) {
+        self.sent
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(SentMessage {
+                chat_id: chat_id.to_string(),
+                text: reply.text.clone(),
+                buttons: reply.buttons.clone(),
+            });
+    }
+}
+
+#[async_trait]
+impl Messenger for MockMessenger {
+    async fn send_message(
+        &self,
+        chat_id: &str,
+        text: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.record(chat_id, &text.to_string().into());
+        Ok(())
+    }
+
+    // Nothing to poll, messages come in through `receive`
+    async fn start(
+        &self,
+        _shutdown: &Shutdown,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(())
+    }
+
+    fn platform(&self) -> &str {
+        &self.platform
+    }
+}
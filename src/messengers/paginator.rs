@@ -0,0 +1,130 @@
+use crate::commands::dispatcher::MAX_RESPONSE_LENGTH;
+use crate::lang::Lang;
+
+// Longer responses are cut after this many messages, one command should not flood the chat
+pub const MAX_MESSAGES_PER_RESPONSE: usize = 5;
+
+/*
+ Splits a response into the messages sent to the chat, each at most `MAX_RESPONSE_LENGTH`
+ characters. Responses needing more than `MAX_MESSAGES_PER_RESPONSE` messages are cut with
+ a note at the end of the last one.
+*/
+pub fn split_response(text: &str, lang: &Lang) -> Vec<String> {
+    let mut pages = paginate(text, MAX_RESPONSE_LENGTH);
+    if pages.len() <= MAX_MESSAGES_PER_RESPONSE {
+        return pages;
+    }
+
+    pages.truncate(MAX_MESSAGES_PER_RESPONSE);
+    let note = lang.get("MESSENGER__RESPONSE_TRUNCATED");
+    let keep = MAX_RESPONSE_LENGTH.saturating_sub(note.chars().count());
+    if let Some(last) = pages.last_mut() {
+        *last = last.chars().take(keep).collect::<String>() + &note;
+    }
+    pages
+}
+
+/*
+ Splits text into pages of at most `max_length` characters. Entries are separated by blank
+ lines in every response, so pages break between entries where possible, then between lines,
+ and inside a line only when the line alone is longer than a page.
+*/
+pub fn paginate(text: &str, max_length: usize) -> Vec<String> {
+    let mut pages = Vec::new();
+    let mut page = String::new();
+    let mut page_length = 0;
+
+    for piece in pieces(text, max_length) {
+        let length = piece.chars().count();
+        if page_length + length > max_length {
+            push_page(&mut pages, &page);
+            page.clear();
+            page_length = 0;
+        }
+        page.push_str(piece);
+        page_length += length;
+    }
+    push_page(&mut pages, &page);
+    pages
+}
+
+fn push_page(pages: &mut Vec<String>, page: &str) {
+    let page = page.trim_end();
+    if !page.is_empty() {
+        pages.push(page.to_string());
+    }
+}
+
+// Entries that fit on a page, otherwise their lines, otherwise chunks of the line
+fn pieces(text: &str, max_length: usize) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    for entry in text.split_inclusive("\n\n") {
+        if entry.chars().count() <= max_length {
+            pieces.push(entry);
+            continue;
+        }
+        for line in entry.split_inclusive('\n') {
+            if line.chars().count() <= max_length {
+                pieces.push(line);
+                continue;
+            }
+            let mut start = 0;
+            for (count, (i, _)) in line.char_indices().enumerate() {
+                if count > 0 && count % max_length == 0 {
+                    pieces.push(&line[start..i]);
+                    start = i;
+                }
+            }
+            pieces.push(&line[start..]);
+        }
+    }
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paginate_short_text() {
+        assert_eq!(
+            paginate("Nasi Padang\n\nWarteg\n", 100),
+            vec!["Nasi Padang\n\nWarteg"]
+        );
+        assert!(paginate("", 100).is_empty());
+    }
+
+    #[test]
+    fn test_paginate_splits_between_entries() {
+        let text = "1 Nasi Padang\n10000\n\n2 Warteg\n15000\n\n3 Kopi\n5000";
+        assert_eq!(
+            paginate(text, 30),
+            vec!["1 Nasi Padang\n10000", "2 Warteg\n15000\n\n3 Kopi\n5000"]
+        );
+    }
+
+    #[test]
+    fn test_paginate_splits_long_entries() {
+        assert_eq!(
+            paginate("line one\nline two\nline three\n\nx", 12),
+            vec!["line one", "line two", "line three", "x"]
+        );
+        assert_eq!(paginate("abcdefghij", 4), vec!["abcd", "efgh", "ij"]);
+        assert_eq!(paginate("ééééé", 2), vec!["éé", "éé", "é"]);
+    }
+
+    #[test]
+    fn test_split_response_cuts_after_max_messages() {
+        let lang = Lang::from_json("id");
+        let entry = "x".repeat(3000);
+        let text = [entry.as_str(); MAX_MESSAGES_PER_RESPONSE + 1].join("\n\n");
+
+        let pages = split_response(&text, &lang);
+
+        assert_eq!(pages.len(), MAX_MESSAGES_PER_RESPONSE);
+        assert_eq!(pages[0], entry);
+        let last = pages.last().unwrap();
+        assert!(last.ends_with(&lang.get("MESSENGER__RESPONSE_TRUNCATED")));
+        assert!(last.chars().count() <= MAX_RESPONSE_LENGTH);
+    }
+}
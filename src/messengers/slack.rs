@@ -0,0 +1,642 @@
+use async_trait::async_trait;
+use axum::{
+    Router,
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio_util::task::TaskTracker;
+
+use crate::commands::base::ChatSender;
+use crate::commands::dispatcher::CommandDispatcher;
+use crate::config::Config;
+use crate::error::DatabaseError;
+use crate::lang::Lang;
+use crate::middleware::rate_limit::RateLimiter;
+use crate::middleware::tier::check_feature_access;
+use crate::repos::chat_binding::{ChatBinding, ChatBindingRepo};
+use crate::repos::expense_group::ExpenseGroupRepo;
+use crate::repos::slack_workspace::SlackWorkspaceRepo;
+use crate::repos::subscription::SubscriptionRepo;
+use crate::shutdown::Shutdown;
+use crate::types::{SubscriptionTier, TierError};
+
+use super::{Messenger, paginator::split_response};
+
+pub const SLACK_PLATFORM: &str = "slack";
+// The tier feature groups need to be used from Slack
+pub const SLACK_FEATURE: &str = "slack_integration";
+
+const SLACK_API_URL: &str = "https://slack.com/api";
+// Requests signed earlier than this are rejected, so captured requests cannot be replayed
+const MAX_SIGNATURE_AGE_SECS: i64 = 60 * 5;
+
+// Slack slash commands and the chat commands they run. Each one has to be created in the Slack
+// app, the other commands are sent by mentioning the bot
+const SLASH_COMMANDS: [(&str, &str); 2] = [("/expense", "/expense"), ("/report", "/report")];
+
+/*
+Workflow:
+1) Slack verifies `POST /webhooks/slack/events` once with a `url_verification` challenge.
+2) Mentions of the bot and direct messages arrive as Events API callbacks on the same URL, slash
+   commands as forms on `POST /webhooks/slack/commands`. Both are signed with the app's signing
+   secret in `X-Slack-Signature`.
+3) Replies are posted to the channel the command came from with the Web API `chat.postMessage`.
+
+A workspace is bound as a whole: the team id is the chat, whichever channel a command is sent
+in. Commands only run for groups whose owner is on a tier with `SLACK_FEATURE`.
+ */
+#[derive(Clone)]
+pub struct SlackMessenger {
+    config: Config,
+    client: reqwest::Client,
+    db_pool: PgPool,
+    lang: Lang,
+    rate_limiter: Arc<RateLimiter>,
+    // Replies still being handled after the request was acknowledged, shared by clones
+    replies: TaskTracker,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EventPayload {
+    UrlVerification {
+        challenge: String,
+    },
+    EventCallback {
+        team_id: String,
+        event: SlackEvent,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SlackEvent {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub user: Option<String>,
+    pub text: Option<String>,
+    pub channel: Option<String>,
+    pub channel_type: Option<String>,
+    // Set on messages of bots, our own replies included
+    pub bot_id: Option<String>,
+    // Set on edits, joins and other messages that were not typed by a user
+    pub subtype: Option<String>,
+}
+
+impl SlackEvent {
+    // Mentions of the bot and direct messages to it, None for the other events
+    fn into_message(self, team_id: String) -> Option<SlackMessage> {
+        let addressed = self.kind == "app_mention"
+            || (self.kind == "message" && self.channel_type.as_deref() == Some("im"));
+        if !addressed || self.bot_id.is_some() || self.subtype.is_some() {
+            return None;
+        }
+        let (Some(user), Some(channel_id), Some(text)) = (self.user, self.channel, self.text)
+        else {
+            return None;
+        };
+        let text = unescape(strip_mention(&text));
+        if text.trim().is_empty() {
+            return None;
+        }
+        Some(SlackMessage {
+            team_id,
+            channel_id,
+            sender: ChatSender {
+                p_user_id: user.clone(),
+                name: user,
+                linked_user_uid: None,
+            },
+            text,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SlashCommand {
+    pub command: String,
+    #[serde(default)]
+    pub text: String,
+    pub team_id: String,
+    pub channel_id: String,
+    pub user_id: String,
+    pub user_name: Option<String>,
+}
+
+impl SlashCommand {
+    // None for slash commands without a chat command
+    fn into_message(self) -> Option<SlackMessage> {
+        let (_, chat_command) = SLASH_COMMANDS
+            .iter()
+            .find(|(slash_command, _)| *slash_command == self.command)?;
+        let text = unescape(self.text.trim());
+        let text = if text.is_empty() {
+            chat_command.to_string()
+        } else {
+            format!("{} {}", chat_command, text)
+        };
+        Some(SlackMessage {
+            team_id: self.team_id,
+            channel_id: self.channel_id,
+            sender: ChatSender {
+                name: self.user_name.unwrap_or_else(|| self.user_id.clone()),
+                p_user_id: self.user_id,
+                linked_user_uid: None,
+            },
+            text,
+        })
+    }
+}
+
+// A command for the bot, from an event or a slash command
+#[derive(Debug)]
+struct SlackMessage {
+    team_id: String,
+    channel_id: String,
+    sender: ChatSender,
+    text: String,
+}
+
+#[derive(Debug, Serialize)]
+struct PostMessagePayload<'a> {
+    channel: &'a str,
+    text: &'a str,
+    // Replies are plain text, like on the other messengers
+    mrkdwn: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiResponse {
+    ok: bool,
+    error: Option<String>,
+}
+
+// Drops the mention starting the text of an `app_mention`, e.g. `<@U012AB3CD> /report`
+fn strip_mention(text: &str) -> &str {
+    let text = text.trim_start();
+    match text
+        .strip_prefix("<@")
+        .and_then(|rest| rest.split_once('>'))
+    {
+        Some((_, rest)) => rest.trim_start(),
+        None => text,
+    }
+}
+
+// Slack escapes these three characters in the text it sends
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+/*
+ Checks `X-Slack-Signature`, `v0=` followed by the hex HMAC-SHA256 of `v0:<timestamp>:<body>`
+ keyed with the signing secret, where the timestamp is `X-Slack-Request-Timestamp`
+*/
+fn verify_signature(secret: &str, headers: &HeaderMap, body: &[u8], now: i64) -> bool {
+    let Some(timestamp) = headers
+        .get("X-Slack-Request-Timestamp")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<i64>().ok())
+    else {
+        return false;
+    };
+    if (now - timestamp).abs() > MAX_SIGNATURE_AGE_SECS {
+        return false;
+    }
+    let Some(presented) = headers
+        .get("X-Slack-Signature")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("v0="))
+        .and_then(|v| hex::decode(v).ok())
+    else {
+        return false;
+    };
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else {
+        return false;
+    };
+    mac.update(format!("v0:{}:", timestamp).as_bytes());
+    mac.update(body);
+    mac.verify_slice(&presented).is_ok()
+}
+
+impl SlackMessenger {
+    pub fn new(
+        config: &Config,
+        db_pool: PgPool,
+        lang: Lang,
+        rate_limiter: Arc<RateLimiter>,
+    ) -> Self {
+        Self {
+            config: config.clone(),
+            client: reqwest::Client::new(),
+            db_pool,
+            lang,
+            rate_limiter,
+            replies: TaskTracker::new(),
+        }
+    }
+
+    // Unlike WhatsApp, the signing secret is required: the endpoints run commands for any caller
+    pub fn is_configured(config: &Config) -> bool {
+        config.slack_bot_token.is_some() && config.slack_signing_secret.is_some()
+    }
+
+    /// Routes for the Slack Events API and slash commands. These are merged outside of the
+    /// auth middleware because Slack authenticates with its own signature header.
+    pub fn router(self) -> Router {
+        Router::new()
+            .route("/webhooks/slack/events", post(receive_event))
+            .route("/webhooks/slack/commands", post(receive_command))
+            .with_state(Arc::new(self))
+    }
+
+    fn verify_signature(&self, headers: &HeaderMap, body: &[u8]) -> bool {
+        match self.config.slack_signing_secret.as_deref() {
+            Some(secret) => verify_signature(secret, headers, body, chrono::Utc::now().timestamp()),
+            None => false,
+        }
+    }
+
+    async fn send_pages(
+        &self,
+        channel: &str,
+        text: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        for page in split_response(text, &self.lang) {
+            self.send_text(channel, &page).await?;
+        }
+        Ok(())
+    }
+
+    async fn send_text(
+        &self,
+        channel: &str,
+        text: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let Some(token) = self.config.slack_bot_token.as_deref() else {
+            return Err("Slack messenger is not configured".into());
+        };
+
+        let response = self
+            .client
+            .post(format!("{}/chat.postMessage", SLACK_API_URL))
+            .bearer_auth(token)
+            .json(&PostMessagePayload {
+                channel,
+                text,
+                mrkdwn: false,
+            })
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!("Slack API returned {}: {}", status, body).into());
+        }
+        // Failures are reported with a 200 and `ok: false`
+        let body: ApiResponse = response.json().await?;
+        if !body.ok {
+            return Err(format!(
+                "Slack API returned error: {}",
+                body.error.unwrap_or_default()
+            )
+            .into());
+        }
+
+        Ok(())
+    }
+
+    // The reply when the owner of the bound group is not on a tier with Slack, None when they are
+    async fn tier_required(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        binding: &ChatBinding,
+    ) -> Result<Option<String>, DatabaseError> {
+        let group = match ExpenseGroupRepo::get(tx, binding.group_uid).await {
+            Ok(group) => group,
+            // The dispatcher tells the chat about deleted groups
+            Err(DatabaseError::NotFound(_)) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let required_tier = match SubscriptionRepo::get_by_user(tx, group.owner).await {
+            Ok(subscription) => match check_feature_access(&subscription, SLACK_FEATURE) {
+                Ok(()) => return Ok(None),
+                Err(TierError::InsufficientTier { required_tier, .. }) => required_tier,
+                Err(_) => SubscriptionTier::Team,
+            },
+            // Owners without a subscription are on the free tier
+            Err(DatabaseError::NotFound(_)) => SubscriptionTier::Team,
+            Err(e) => return Err(e),
+        };
+        let message = self.lang.preferred(group.lang.as_deref()).get_with_vars(
+            "MESSENGER__SLACK_TIER_REQUIRED",
+            HashMap::from([
+                ("tier".to_string(), required_tier.display_name().to_string()),
+                ("group".to_string(), group.name),
+            ]),
+        );
+        Ok(Some(message))
+    }
+
+    async fn handle_message(
+        &self,
+        message: &SlackMessage,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut tx = self.db_pool.begin().await?;
+        SlackWorkspaceRepo::set_channel(&mut tx, &message.team_id, &message.channel_id).await?;
+        let binding =
+            ChatBindingRepo::get_selected(&mut tx, SLACK_PLATFORM, &message.team_id).await?;
+
+        let response = match binding {
+            Some(binding) => match self.tier_required(&mut tx, &binding).await? {
+                Some(tier_required) => Some(tier_required.into()),
+                None => {
+                    CommandDispatcher::dispatch(
+                        &message.text,
+                        &binding,
+                        &message.sender,
+                        &self.config.chat_bind_url,
+                        &mut tx,
+                        &self.lang,
+                        &self.rate_limiter,
+                    )
+                    .await
+                }
+            },
+            None => Some(
+                CommandDispatcher::dispatch_unbound(
+                    SLACK_PLATFORM,
+                    &message.team_id,
+                    &message.text,
+                    &self.config.chat_bind_url,
+                    &mut tx,
+                    &self.lang,
+                )
+                .await?
+                .into(),
+            ),
+        };
+
+        // Slack replies are plain text, buttons are left out
+        if let Some(response) = response {
+            self.send_pages(&message.channel_id, &response.text).await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    // Slack expects an answer within 3 seconds, so commands are handled after acknowledging
+    fn spawn_reply(self: &Arc<Self>, message: SlackMessage) {
+        let replier = self.clone();
+        self.replies.spawn(async move {
+            if let Err(e) = replier.handle_message(&message).await {
+                tracing::error!("Error handling Slack message: {:?}", e);
+            }
+        });
+    }
+}
+
+async fn receive_event(
+    State(messenger): State<Arc<SlackMessenger>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    if !messenger.verify_signature(&headers, &body) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    // Retries come when the first delivery was acknowledged late, it is handled already and
+    // running a command twice could record an expense twice
+    if headers.contains_key("X-Slack-Retry-Num") {
+        return StatusCode::OK.into_response();
+    }
+
+    let payload: EventPayload = match serde_json::from_slice(&body) {
+        Ok(payload) => payload,
+        Err(e) => {
+            tracing::warn!("Invalid Slack event payload: {:?}", e);
+            return StatusCode::BAD_REQUEST.into_response();
+        }
+    };
+
+    match payload {
+        EventPayload::UrlVerification { challenge } => (StatusCode::OK, challenge).into_response(),
+        EventPayload::EventCallback { team_id, event } => {
+            if let Some(message) = event.into_message(team_id) {
+                messenger.spawn_reply(message);
+            }
+            StatusCode::OK.into_response()
+        }
+        EventPayload::Other => StatusCode::OK.into_response(),
+    }
+}
+
+async fn receive_command(
+    State(messenger): State<Arc<SlackMessenger>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> StatusCode {
+    if !messenger.verify_signature(&headers, &body) {
+        return StatusCode::UNAUTHORIZED;
+    }
+
+    let command: SlashCommand = match serde_urlencoded::from_bytes(&body) {
+        Ok(command) => command,
+        Err(e) => {
+            tracing::warn!("Invalid Slack slash command: {:?}", e);
+            return StatusCode::BAD_REQUEST;
+        }
+    };
+
+    let name = command.command.clone();
+    match command.into_message() {
+        Some(message) => messenger.spawn_reply(message),
+        None => tracing::warn!("Slack slash command {} has no chat command", name),
+    }
+    StatusCode::OK
+}
+
+#[async_trait]
+impl Messenger for SlackMessenger {
+    // The chat of a workspace is its team id, messages go to the channel it last used the bot in
+    async fn send_message(
+        &self,
+        chat_id: &str,
+        text: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut tx = self.db_pool.begin().await?;
+        let channel = SlackWorkspaceRepo::get_channel(&mut tx, chat_id).await?;
+        tx.commit().await?;
+        let Some(channel) = channel else {
+            return Err(format!("No channel known for Slack workspace {}", chat_id).into());
+        };
+        self.send_pages(&channel, text).await
+    }
+
+    async fn start(
+        &self,
+        _shutdown: &Shutdown,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        // Commands arrive through the webhook router, nothing to poll
+        tracing::info!("Slack messenger ready, waiting for events and slash commands");
+        Ok(())
+    }
+
+    // The webhooks stop with the HTTP server, only the replies in flight are left
+    async fn stop(&self) {
+        self.replies.close();
+        self.replies.wait().await;
+    }
+
+    fn platform(&self) -> &str {
+        SLACK_PLATFORM
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signed_headers(secret: &str, timestamp: i64, body: &[u8]) -> HeaderMap {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(format!("v0:{}:", timestamp).as_bytes());
+        mac.update(body);
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "X-Slack-Request-Timestamp",
+            timestamp.to_string().parse().unwrap(),
+        );
+        headers.insert(
+            "X-Slack-Signature",
+            format!("v0={}", hex::encode(mac.finalize().into_bytes()))
+                .parse()
+                .unwrap(),
+        );
+        headers
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        let body = b"token=x&team_id=T1&command=%2Freport";
+        let now = 1_700_000_000;
+        let headers = signed_headers("secret", now, body);
+
+        assert!(verify_signature("secret", &headers, body, now));
+        assert!(verify_signature("secret", &headers, body, now + 60));
+        assert!(!verify_signature("other", &headers, body, now));
+        assert!(!verify_signature("secret", &headers, b"tampered", now));
+        // Too old to be trusted
+        assert!(!verify_signature("secret", &headers, body, now + 60 * 10));
+        assert!(!verify_signature("secret", &HeaderMap::new(), body, now));
+    }
+
+    #[test]
+    fn test_parse_url_verification() {
+        let body = r#"{ "token": "x", "challenge": "3eZbrw1aBm2rZgRNFdxV2595E9CY3gmdALWMmHkvFXO7tYXAYM8P", "type": "url_verification" }"#;
+        match serde_json::from_str::<EventPayload>(body).unwrap() {
+            EventPayload::UrlVerification { challenge } => {
+                assert_eq!(
+                    challenge,
+                    "3eZbrw1aBm2rZgRNFdxV2595E9CY3gmdALWMmHkvFXO7tYXAYM8P"
+                )
+            }
+            other => panic!("expected url_verification, got {:?}", other),
+        }
+        let body = r#"{ "type": "app_rate_limited", "team_id": "T1" }"#;
+        assert!(matches!(
+            serde_json::from_str::<EventPayload>(body).unwrap(),
+            EventPayload::Other
+        ));
+    }
+
+    #[test]
+    fn test_mention_becomes_message() {
+        let body = r#"{
+            "type": "event_callback",
+            "team_id": "T061EG9R6",
+            "event": {
+                "type": "app_mention",
+                "user": "U061F7AUR",
+                "text": "<@U0LAN0Z89> /expense Coffee &amp; cake, 45000",
+                "ts": "1515449522.000016",
+                "channel": "C0LAN2Q65",
+                "event_ts": "1515449522000016"
+            }
+        }"#;
+        let EventPayload::EventCallback { team_id, event } = serde_json::from_str(body).unwrap()
+        else {
+            panic!("expected event_callback");
+        };
+        let message = event.into_message(team_id).unwrap();
+
+        assert_eq!(message.team_id, "T061EG9R6");
+        assert_eq!(message.channel_id, "C0LAN2Q65");
+        assert_eq!(message.sender.p_user_id, "U061F7AUR");
+        assert_eq!(message.text, "/expense Coffee & cake, 45000");
+    }
+
+    #[test]
+    fn test_bot_and_channel_messages_ignored() {
+        let event = |kind: &str, channel_type: &str, bot_id: Option<&str>| SlackEvent {
+            kind: kind.to_string(),
+            user: Some("U1".to_string()),
+            text: Some("/report".to_string()),
+            channel: Some("D1".to_string()),
+            channel_type: Some(channel_type.to_string()),
+            bot_id: bot_id.map(str::to_string),
+            subtype: None,
+        };
+
+        assert!(
+            event("message", "im", None)
+                .into_message("T1".into())
+                .is_some()
+        );
+        assert!(
+            event("message", "im", Some("B1"))
+                .into_message("T1".into())
+                .is_none()
+        );
+        // Channel messages only count when they mention the bot
+        assert!(
+            event("message", "channel", None)
+                .into_message("T1".into())
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_slash_command_mapping() {
+        let body = "token=x&team_id=T1&team_domain=acme&channel_id=C1&channel_name=expenses&user_id=U1&user_name=budi&command=%2Fexpense&text=Lunch%2C+50000%2C+Food&response_url=https%3A%2F%2Fhooks.slack.com%2Fcommands%2F1";
+        let command: SlashCommand = serde_urlencoded::from_str(body).unwrap();
+        let message = command.into_message().unwrap();
+
+        assert_eq!(message.text, "/expense Lunch, 50000, Food");
+        assert_eq!(message.sender.name, "budi");
+        assert_eq!(message.channel_id, "C1");
+
+        let command: SlashCommand = serde_urlencoded::from_str(
+            "team_id=T1&channel_id=C1&user_id=U1&command=%2Freport&text=",
+        )
+        .unwrap();
+        assert_eq!(command.into_message().unwrap().text, "/report");
+
+        let command: SlashCommand =
+            serde_urlencoded::from_str("team_id=T1&channel_id=C1&user_id=U1&command=%2Fdeploy")
+                .unwrap();
+        assert!(command.into_message().is_none());
+    }
+}
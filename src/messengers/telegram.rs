@@ -1,57 +1,78 @@
 use async_trait::async_trait;
-use chrono::{Datelike, Duration, NaiveDate, Utc};
 use sqlx::{PgPool, Row};
 use std::collections::HashMap;
-use teloxide::{prelude::*, types::Message as TgMessage};
-use tracing::info;
-use uuid::Uuid;
-
-use crate::commands::report::ReportCommand;
-use crate::commands::{
-    budget::BudgetCommand, budget_edit::BudgetEditCommand, category::CategoryCommand, category_edit::CategoryEditCommand, expense::ExpenseCommand,
-    expense_edit::ExpenseEditCommand, help::HelpCommand, history::HistoryCommand,
+use std::sync::{Arc, Mutex};
+use teloxide::{
+    dispatching::ShutdownToken,
+    prelude::*,
+    types::{CallbackQuery, InlineKeyboardButton, InlineKeyboardMarkup, Message as TgMessage},
 };
+use tracing::warn;
+
+use crate::commands::base::{ChatButton, ChatReply, ChatSender};
+use crate::commands::dispatcher::CommandDispatcher;
 use crate::config::Config;
 use crate::lang::Lang;
+use crate::middleware::rate_limit::{RateLimitDecision, RateLimiter};
 use crate::middleware::tier::check_tier_limit;
 use crate::reports::MonthlyReportGenerator;
 use crate::repos::{
     budget::{BudgetRepo, CreateBudgetDbPayload},
     category::CategoryRepo,
-    chat_bind_request::{ChatBindRequestRepo, CreateChatBindRequestDbPayload},
     chat_binding::ChatBindingRepo,
     expense_group::ExpenseGroupRepo,
     expense_group_member::GroupMemberRepo,
     subscription::{SubscriptionRepo, UserUsageRepo},
     user::UserRepo,
 };
+use crate::shutdown::Shutdown;
 use crate::types::SubscriptionTier;
 
-use super::Messenger;
+use super::{Messenger, paginator::split_response};
 
 pub struct TelegramMessenger {
     config: Config,
     bot: Bot,
     db_pool: PgPool,
     lang: Lang,
+    rate_limiter: Arc<RateLimiter>,
+    // Set once the polling dispatcher is started
+    dispatcher: Arc<Mutex<Option<ShutdownToken>>>,
 }
 
 impl TelegramMessenger {
-    pub fn new(config: &Config, db_pool: PgPool) -> Self {
+    pub fn new(
+        config: &Config,
+        db_pool: PgPool,
+        lang: Lang,
+        rate_limiter: Arc<RateLimiter>,
+    ) -> Self {
         Self {
             config: config.clone(),
             bot: Bot::new(config.telegram_bot_token.clone()),
             db_pool,
-            lang: Lang::from_json("id"),
+            lang,
+            rate_limiter,
+            dispatcher: Arc::new(Mutex::new(None)),
         }
     }
 
-    async fn send_message(
+    // Long replies are sent as several messages, the buttons go under the last one
+    async fn send_reply(
         &self,
         chat_id: ChatId,
-        text: &str,
+        reply: &ChatReply,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        self.bot.send_message(chat_id, text).await?;
+        let pages = split_response(&reply.text, &self.lang);
+        let last = pages.len().saturating_sub(1);
+        for (i, page) in pages.into_iter().enumerate() {
+            let request = self.bot.send_message(chat_id, page);
+            if i < last || reply.buttons.is_empty() {
+                request.await?;
+            } else {
+                request.reply_markup(keyboard(&reply.buttons)).await?;
+            }
+        }
         Ok(())
     }
 
@@ -60,97 +81,42 @@ impl TelegramMessenger {
         msg: TgMessage,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let chat_id = msg.chat.id.to_string();
-        let _user_id = msg
-            .from
-            .clone()
-            .map(|u| u.id.to_string())
-            .unwrap_or_default();
+        let sender = ChatSender {
+            p_user_id: msg
+                .from
+                .as_ref()
+                .map(|u| u.id.to_string())
+                .unwrap_or_default(),
+            name: msg.from.as_ref().map(|u| u.full_name()).unwrap_or_default(),
+            linked_user_uid: None,
+        };
 
         if let Some(text) = msg.text() {
             // Check if chat is bound
             let mut tx = self.db_pool.begin().await?;
-            let binding = ChatBindingRepo::list(&mut tx)
-                .await?
-                .into_iter()
-                .find(|b| b.platform == "telegram" && b.p_uid == chat_id && b.status == "active");
-
-            match binding {
-                Some(binding) => {
-                    let command = text.split_whitespace().next().unwrap_or("");
-                    match command {
-                        "/expense" => {
-                            self.handle_expense_command(msg.chat.id, text, &binding, &mut tx)
-                                .await?;
-                        }
-                        "/expense-edit" => {
-                            self.handle_expense_edit_command(msg.chat.id, text, &binding, &mut tx)
-                                .await?;
-                        }
-                        "/report" => {
-                            self.handle_report_command(msg.chat.id, text, &binding, &mut tx)
-                                .await?;
-                        }
-                        "/history" => {
-                            self.handle_history_command(msg.chat.id, text, &binding, &mut tx)
-                                .await?;
-                        }
-                        "/budget" => {
-                            self.handle_budget_command(msg.chat.id, text, &binding, &mut tx)
-                                .await?;
-                        }
-                        "/budget-edit" => {
-                            self.handle_budget_edit_command(msg.chat.id, text, &binding, &mut tx)
-                                .await?;
-                        }
-                        "/category" => {
-                            self.handle_category_command(msg.chat.id, text, &binding, &mut tx)
-                                .await?;
-                        }
-                        "/category-edit" => {
-                            self.handle_category_edit_command(msg.chat.id, text, &binding, &mut tx)
-                                .await?;
-                        }
-                        "/help" => {
-                            self.handle_help_command(msg.chat.id, &binding, &mut tx)
-                                .await?;
-                        }
-                        _ => {
-                            // do nothing
-                            // TODO: maybe track unknown commands later
-                        }
-                    }
-                }
-                None => {
-                    // Chat not bound, handle binding request
-                    if text.trim() == "/login" {
-                        // Create bind request
-                        let nonce = Uuid::new_v4().to_string();
-                        let expires_at = Utc::now() + Duration::hours(1);
-
-                        let request = ChatBindRequestRepo::create(
+            let binding = ChatBindingRepo::get_selected(&mut tx, "telegram", &chat_id).await?;
+
+            let response = match binding {
+                Some(binding) => CommandDispatcher::dispatch(text, &binding, &sender, &self.config.chat_bind_url, &mut tx, &self.lang, &self.rate_limiter).await,
+                None => match self.check_unbound(&msg) {
+                    Ok(()) => Some(
+                        CommandDispatcher::dispatch_unbound(
+                            "telegram",
+                            &chat_id,
+                            text,
+                            &self.config.chat_bind_url,
                             &mut tx,
-                            CreateChatBindRequestDbPayload {
-                                platform: "telegram".to_string(),
-                                p_uid: chat_id.clone(),
-                                nonce: nonce.clone(),
-                                user_uid: None,
-                                expires_at,
-                            },
+                            &self.lang,
                         )
-                        .await?;
-
-                        let bind_url = format!("{}/{}", self.config.chat_bind_url, request.id);
-                        let response = self.lang.get_with_vars(
-                            "TELEGRAM__SIGN_IN_REQUEST",
-                            HashMap::from([("link".to_string(), bind_url)]),
-                        );
-
-                        self.send_message(msg.chat.id, &response).await?;
-                    } else {
-                        let response = self.lang.get("TELEGRAM__CHAT_NOT_BOUND");
-                        self.bot.send_message(msg.chat.id, response).await?;
-                    }
-                }
+                        .await?
+                        .into(),
+                    ),
+                    Err(reply) => reply,
+                },
+            };
+
+            if let Some(response) = response {
+                self.send_reply(msg.chat.id, &response).await?;
             }
 
             tx.commit().await?;
@@ -158,215 +124,107 @@ impl TelegramMessenger {
         Ok(())
     }
 
-    async fn handle_expense_command(
-        &self,
-        chat_id: ChatId,
-        text: &str,
-        binding: &crate::repos::chat_binding::ChatBinding,
-        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let response = match ExpenseCommand::run(text, binding, tx, &self.lang).await {
-            Ok(result) => result,
-            Err(e) => {
-                tracing::error!("Error handling expense command: {}", e);
-                let mut response = e.to_string();
-
-                response.push_str("\n-----\n");
-                response.push_str(&self.lang.get("MESSENGER__ENTRY_HELP"));
-
-                self.bot.send_message(chat_id, response).await?;
-                return Ok(());
-            }
-        };
-
-        self.bot.send_message(chat_id, response).await?;
-        Ok(())
-    }
-
-    async fn handle_report_command(
-        &self,
-        chat_id: ChatId,
-        raw_message: &str,
-        binding: &crate::repos::chat_binding::ChatBinding,
-        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let response = match ReportCommand::run(raw_message, binding, tx, &self.lang).await {
-            Ok(result) => result,
-            Err(e) => {
-                tracing::error!("Error generating report: {}", e);
-                let response = e.to_string();
-                self.bot.send_message(chat_id, response).await?;
-                return Ok(());
-            }
-        };
-
-        self.bot.send_message(chat_id, response).await?;
-        Ok(())
-    }
-
-    async fn handle_history_command(
-        &self,
-        chat_id: ChatId,
-        text: &str,
-        binding: &crate::repos::chat_binding::ChatBinding,
-        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let response = match HistoryCommand::run(text, binding, tx, &self.lang).await {
-            Ok(result) => result,
-            Err(e) => {
-                tracing::error!("Error handling history command: {}", e);
-                let mut response = e.to_string();
-
-                response.push_str("\n-----\n");
-                response.push_str("Format:\n/history\n/history YYYY-MM-DD\n/history YYYY-MM-DD YYYY-MM-DD\n\nContoh:\n/history\n/history 2025-09-01\n/history 2025-09-01 2025-09-03");
-
-                self.bot.send_message(chat_id, response).await?;
-                return Ok(());
-            }
-        };
-
-        // Truncate if too long for Telegram
-        let final_response = if response.len() > 4000 {
-            let mut truncated = response.chars().take(3950).collect::<String>();
-            truncated.push_str("...\n\n(Message truncated due to length)");
-            truncated
-        } else {
-            response
-        };
-
-        self.bot.send_message(chat_id, final_response).await?;
-        Ok(())
-    }
-
-    async fn handle_budget_command(
-        &self,
-        chat_id: ChatId,
-        text: &str,
-        binding: &crate::repos::chat_binding::ChatBinding,
-        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let response = match BudgetCommand::run(text, binding, tx, &self.lang).await {
-            Ok(result) => result,
-            Err(e) => {
-                tracing::error!("Error handling budget command: {}", e);
-                let mut response = e.to_string();
-                response.push_str("\n-----\n");
-                response.push_str("Format:\n/budget\n\nMenampilkan semua budget yang tersedia untuk grup ini.");
-
-                self.bot.send_message(chat_id, response).await?;
-                return Ok(());
+    /*
+     Public bots get strangers spamming /login, every one of which creates a bind request.
+     Unbound chats on TELEGRAM_DENIED_CHATS, or missing from a non-empty TELEGRAM_ALLOWED_CHATS,
+     are ignored and the others get at most one reply per TELEGRAM_UNBOUND_COOLDOWN_SECS. Errs
+     with what to reply instead of dispatching. Refusals are logged once per cooldown, so the
+     spam does not flood the log either.
+    */
+    fn check_unbound(&self, msg: &TgMessage) -> Result<(), Option<ChatReply>> {
+        let access = &self.config.telegram_access;
+        let usernames: Vec<&str> = msg
+            .from
+            .as_ref()
+            .and_then(|u| u.username.as_deref())
+            .into_iter()
+            .chain(msg.chat.username())
+            .collect();
+        let key = format!("unbound:telegram:{}", msg.chat.id);
+        let decision = self.rate_limiter.cooldown(&key, access.unbound_cooldown);
+
+        if let Some(reason) = access.refusal(msg.chat.id.0, &usernames) {
+            if decision == RateLimitDecision::Allowed {
+                warn!(
+                    "Ignoring unbound Telegram chat {} ({}), {}",
+                    msg.chat.id,
+                    usernames.join(", "),
+                    reason
+                );
             }
-        };
-
-        // Truncate if too long for Telegram
-        let final_response = if response.len() > 4000 {
-            let mut truncated = response.chars().take(3950).collect::<String>();
-            truncated.push_str("...\n\n(Message truncated due to length)");
-            truncated
-        } else {
-            response
-        };
-
-        self.bot.send_message(chat_id, final_response).await?;
-        Ok(())
-    }
-
-    async fn handle_budget_edit_command(
-        &self,
-        chat_id: ChatId,
-        text: &str,
-        binding: &crate::repos::chat_binding::ChatBinding,
-        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let response = match BudgetEditCommand::run(text, binding, tx, &self.lang).await {
-            Ok(result) => result,
-            Err(e) => {
-                tracing::error!("Error handling budget edit command: {}", e);
-                let mut response = e.to_string();
-                response.push_str("\n-----\n");
-                response.push_str("Format:\n/budget-edit\n[id]\n[category]=[amount]\n\nContoh:\n/budget-edit\n123e4567-e89b-12d3-a456-426614174000\nMakanan=50000");
-
-                self.bot.send_message(chat_id, response).await?;
-                return Ok(());
+            return Err(None);
+        }
+        match decision {
+            RateLimitDecision::Allowed => Ok(()),
+            RateLimitDecision::Limited {
+                retry_after_secs,
+                first,
+            } => {
+                if !first {
+                    return Err(None);
+                }
+                warn!(
+                    "Unbound Telegram chat {} ({}) is sending messages faster than its cooldown",
+                    msg.chat.id,
+                    usernames.join(", ")
+                );
+                Err(Some(
+                    self.lang
+                        .get_with_vars(
+                            "MESSENGER__RATE_LIMITED",
+                            HashMap::from([("seconds".to_string(), retry_after_secs.to_string())]),
+                        )
+                        .into(),
+                ))
             }
-        };
-
-        self.bot.send_message(chat_id, response).await?;
-        Ok(())
+        }
     }
 
-    async fn handle_category_command(
+    // A button pressed under one of our replies, the reply is edited with the result
+    async fn handle_callback(
         &self,
-        chat_id: ChatId,
-        text: &str,
-        binding: &crate::repos::chat_binding::ChatBinding,
-        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        query: CallbackQuery,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let response = match CategoryCommand::run(text, binding, tx, &self.lang).await {
-            Ok(result) => result,
-            Err(e) => {
-                tracing::error!("Error handling category command: {}", e);
-                let mut response = e.to_string();
-                response.push_str("\n-----\n");
-                response.push_str("Format:\n/category\n\nMenampilkan semua kategori dan alias yang tersedia untuk grup ini.");
-
-                self.bot.send_message(chat_id, response).await?;
-                return Ok(());
-            }
+        // Stops the loading indicator on the button
+        self.bot.answer_callback_query(query.id.clone()).await?;
+        let (Some(data), Some(msg)) = (query.data.as_deref(), query.regular_message()) else {
+            return Ok(());
         };
-
-        // Truncate if too long for Telegram
-        let final_response = if response.len() > 4000 {
-            let mut truncated = response.chars().take(3950).collect::<String>();
-            truncated.push_str("...\n\n(Message truncated due to length)");
-            truncated
-        } else {
-            response
+        let chat_id = msg.chat.id.to_string();
+        let sender = ChatSender {
+            p_user_id: query.from.id.to_string(),
+            name: query.from.full_name(),
+            linked_user_uid: None,
         };
 
-        self.bot.send_message(chat_id, final_response).await?;
-        Ok(())
-    }
-
-    async fn handle_category_edit_command(
-        &self,
-        chat_id: ChatId,
-        text: &str,
-        binding: &crate::repos::chat_binding::ChatBinding,
-        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let response = match CategoryEditCommand::run(text, binding, tx, &self.lang).await {
-            Ok(result) => result,
-            Err(e) => {
-                tracing::error!("Error handling category edit command: {}", e);
-                let mut response = e.to_string();
-                response.push_str("\n-----\n");
-                response.push_str("Format:\n/category-edit\n[id]\n[name]=[alias1, alias2, ...]\n\nContoh:\n/category-edit\n123e4567-e89b-12d3-a456-426614174000\nMakanan=makan, food");
-
-                self.bot.send_message(chat_id, response).await?;
-                return Ok(());
-            }
+        let mut tx = self.db_pool.begin().await?;
+        // Buttons stay under old replies after the chat logs out
+        let Some(binding) = ChatBindingRepo::get_selected(&mut tx, "telegram", &chat_id).await?
+        else {
+            return Ok(());
         };
 
-        self.bot.send_message(chat_id, response).await?;
-        Ok(())
-    }
+        let response = CommandDispatcher::dispatch_callback(
+            data,
+            msg.text().unwrap_or_default(),
+            &binding,
+            &sender,
+            &mut tx,
+            &self.lang,
+        )
+        .await;
 
-    async fn handle_help_command(
-        &self,
-        chat_id: ChatId,
-        binding: &crate::repos::chat_binding::ChatBinding,
-        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let response = match HelpCommand::run("/help", binding, tx, &self.lang).await {
-            Ok(result) => result,
-            Err(e) => {
-                tracing::error!("Error handling help command: {}", e);
-                format!("Error: {}", e)
+        if let Some(response) = response {
+            // Without a markup the buttons are removed
+            let request = self.bot.edit_message_text(msg.chat.id, msg.id, &response.text);
+            if response.buttons.is_empty() {
+                request.await?;
+            } else {
+                request.reply_markup(keyboard(&response.buttons)).await?;
             }
-        };
+        }
 
-        self.bot.send_message(chat_id, response).await?;
+        tx.commit().await?;
         Ok(())
     }
 
@@ -411,71 +269,6 @@ impl TelegramMessenger {
 
         Ok(())
     }
-
-    async fn handle_expense_edit_command(
-        &self,
-        chat_id: ChatId,
-        text: &str,
-        binding: &crate::repos::chat_binding::ChatBinding,
-        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let response = match ExpenseEditCommand::run(text, binding, tx, &self.lang).await {
-            Ok(result) => result,
-            Err(e) => {
-                tracing::error!("Error handling expense edit command: {}", e);
-                let mut response = e.to_string();
-
-                response.push_str("\n-----\n");
-                response.push_str("Format:\n/expense-edit\n[id]\n[nama],[harga],[kategori]\n\nContoh:\n/expense-edit\n123e4567-e89b-12d3-a456-426614174000\nNasi Padang,10000,Makanan");
-
-                self.bot.send_message(chat_id, response).await?;
-                return Ok(());
-            }
-        };
-
-        self.bot.send_message(chat_id, response).await?;
-        Ok(())
-    }
-
-    fn calculate_month_range(
-        &self,
-        start_over_date: i16,
-    ) -> (chrono::DateTime<Utc>, chrono::DateTime<Utc>) {
-        let now = Utc::now();
-        let current_year = now.year();
-        let current_month = now.month();
-
-        // Calculate the start date based on start_over_date
-        let start_day = start_over_date as u32;
-        let mut start_date = if current_month == 1 {
-            // January - go back to previous year
-            NaiveDate::from_ymd_opt(current_year - 1, 12, start_day)
-        } else {
-            NaiveDate::from_ymd_opt(current_year, current_month - 1, start_day)
-        }
-        .unwrap_or_else(|| NaiveDate::from_ymd_opt(current_year, current_month, 1).unwrap());
-
-        // If the calculated start date is in the future, use the previous month's start date
-        if start_date > now.date_naive() {
-            start_date = if current_month == 1 {
-                NaiveDate::from_ymd_opt(current_year - 1, 11, start_day)
-            } else if current_month == 2 {
-                NaiveDate::from_ymd_opt(current_year - 1, 12, start_day)
-            } else {
-                NaiveDate::from_ymd_opt(current_year, current_month - 2, start_day)
-            }
-            .unwrap_or_else(|| {
-                NaiveDate::from_ymd_opt(current_year, current_month - 1, 1).unwrap()
-            });
-        }
-
-        let end_date = start_date + Duration::days(30); // Approximate month length
-
-        (
-            start_date.and_hms_opt(0, 0, 0).unwrap().and_utc(),
-            end_date.and_hms_opt(23, 59, 59).unwrap().and_utc(),
-        )
-    }
 }
 
 #[async_trait]
@@ -486,39 +279,85 @@ impl Messenger for TelegramMessenger {
         text: &str,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let chat_id: i64 = chat_id.parse()?;
-        self.bot.send_message(ChatId(chat_id), text).await?;
-        Ok(())
+        self.send_reply(ChatId(chat_id), &text.to_string().into()).await
     }
 
-    async fn start(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    async fn start(&self, shutdown: &Shutdown) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let bot = self.bot.clone();
         let db_pool = self.db_pool.clone();
         let config = self.config.clone();
-
-        tokio::spawn(async move {
-            let handler = Update::filter_message().endpoint(move |bot: Bot, msg: TgMessage| {
-                let db_pool = db_pool.clone();
-                let config = config.clone();
-                async move {
-                    let messenger = TelegramMessenger::new(&config, db_pool);
-                    if let Err(e) = messenger.handle_message(msg).await {
-                        tracing::error!("Error handling message: {:?}", e);
+        let lang = self.lang.clone();
+        let rate_limiter = self.rate_limiter.clone();
+
+        let handler = {
+            let (message_db_pool, message_config, message_lang, message_rate_limiter) = (
+                db_pool.clone(),
+                config.clone(),
+                lang.clone(),
+                rate_limiter.clone(),
+            );
+            dptree::entry()
+                .branch(Update::filter_message().endpoint(move |msg: TgMessage| {
+                    let messenger = TelegramMessenger::new(
+                        &message_config,
+                        message_db_pool.clone(),
+                        message_lang.clone(),
+                        message_rate_limiter.clone(),
+                    );
+                    async move {
+                        if let Err(e) = messenger.handle_message(msg).await {
+                            tracing::error!("Error handling message: {:?}", e);
+                        }
+                        respond(())
                     }
-                    respond(())
-                }
-            });
+                }))
+                .branch(
+                    Update::filter_callback_query().endpoint(move |query: CallbackQuery| {
+                        let messenger = TelegramMessenger::new(
+                            &config,
+                            db_pool.clone(),
+                            lang.clone(),
+                            rate_limiter.clone(),
+                        );
+                        async move {
+                            if let Err(e) = messenger.handle_callback(query).await {
+                                tracing::error!("Error handling callback query: {:?}", e);
+                            }
+                            respond(())
+                        }
+                    }),
+                )
+        };
 
-            Dispatcher::builder(bot, handler)
-                .enable_ctrlc_handler()
-                .build()
-                .dispatch()
-                .await;
+        // Stopped by `stop`, signals are handled in main together with the other services
+        let mut dispatcher = Dispatcher::builder(bot, handler).build();
+        *self.dispatcher.lock().unwrap_or_else(|e| e.into_inner()) = Some(dispatcher.shutdown_token());
+        shutdown.spawn(async move {
+            dispatcher.dispatch().await;
         });
 
         Ok(())
     }
 
+    async fn stop(&self) {
+        let token = self.dispatcher.lock().unwrap_or_else(|e| e.into_inner()).take();
+        // Fails when the dispatcher is not running, then there is nothing to wait for
+        if let Some(token) = token
+            && let Ok(stopped) = token.shutdown()
+        {
+            stopped.await;
+        }
+    }
+
     fn platform(&self) -> &str {
         "telegram"
     }
 }
+
+fn keyboard(buttons: &[Vec<ChatButton>]) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(buttons.iter().map(|row| {
+        row.iter().map(|button| {
+            InlineKeyboardButton::callback(button.label.clone(), button.data.clone())
+        })
+    }))
+}
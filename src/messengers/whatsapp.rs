@@ -0,0 +1,453 @@
+use async_trait::async_trait;
+use axum::{
+    Router,
+    body::Bytes,
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    routing::get,
+};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tokio_util::task::TaskTracker;
+
+use crate::commands::base::ChatSender;
+use crate::commands::dispatcher::CommandDispatcher;
+use crate::config::Config;
+use crate::lang::Lang;
+use crate::middleware::rate_limit::RateLimiter;
+use crate::repos::chat_binding::ChatBindingRepo;
+use crate::shutdown::Shutdown;
+
+use super::{Messenger, paginator::split_response};
+
+const GRAPH_API_URL: &str = "https://graph.facebook.com/v20.0";
+
+/*
+Workflow:
+1) Meta calls `GET /webhooks/whatsapp` once with `hub.verify_token` to verify the webhook.
+2) Every incoming message is delivered to `POST /webhooks/whatsapp`, signed with the app secret
+   in `X-Hub-Signature-256`.
+3) Replies are sent through the Cloud API `/{phone_number_id}/messages` endpoint.
+ */
+#[derive(Clone)]
+pub struct WhatsAppMessenger {
+    config: Config,
+    client: reqwest::Client,
+    db_pool: PgPool,
+    lang: Lang,
+    rate_limiter: Arc<RateLimiter>,
+    // Replies still being handled after the webhook was acknowledged, shared by clones
+    replies: TaskTracker,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WebhookVerifyQuery {
+    #[serde(rename = "hub.mode")]
+    pub mode: Option<String>,
+    #[serde(rename = "hub.verify_token")]
+    pub verify_token: Option<String>,
+    #[serde(rename = "hub.challenge")]
+    pub challenge: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WebhookPayload {
+    #[serde(default)]
+    pub entry: Vec<WebhookEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WebhookEntry {
+    #[serde(default)]
+    pub changes: Vec<WebhookChange>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WebhookChange {
+    pub value: WebhookValue,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WebhookValue {
+    #[serde(default)]
+    pub contacts: Vec<WebhookContact>,
+    #[serde(default)]
+    pub messages: Vec<WebhookMessage>,
+}
+
+impl WebhookValue {
+    // Pairs each message with the sender's profile name, falling back to the phone number
+    fn into_messages(self) -> Vec<(WebhookMessage, ChatSender)> {
+        let contacts = self.contacts;
+        self.messages
+            .into_iter()
+            .map(|message| {
+                let name = contacts
+                    .iter()
+                    .find(|c| c.wa_id == message.from)
+                    .and_then(|c| c.profile.as_ref().map(|p| p.name.clone()))
+                    .unwrap_or_else(|| message.from.clone());
+                let sender = ChatSender {
+                    p_user_id: message.from.clone(),
+                    name,
+                    linked_user_uid: None,
+                };
+                (message, sender)
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WebhookContact {
+    pub wa_id: String,
+    pub profile: Option<WebhookProfile>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WebhookProfile {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WebhookMessage {
+    pub from: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub text: Option<WebhookText>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WebhookText {
+    pub body: String,
+}
+
+#[derive(Debug, Serialize)]
+struct SendTextPayload<'a> {
+    messaging_product: &'a str,
+    to: &'a str,
+    #[serde(rename = "type")]
+    kind: &'a str,
+    text: SendTextBody<'a>,
+}
+
+#[derive(Debug, Serialize)]
+struct SendTextBody<'a> {
+    body: &'a str,
+}
+
+impl WhatsAppMessenger {
+    pub fn new(
+        config: &Config,
+        db_pool: PgPool,
+        lang: Lang,
+        rate_limiter: Arc<RateLimiter>,
+    ) -> Self {
+        Self {
+            config: config.clone(),
+            client: reqwest::Client::new(),
+            db_pool,
+            lang,
+            rate_limiter,
+            replies: TaskTracker::new(),
+        }
+    }
+
+    pub fn is_configured(config: &Config) -> bool {
+        config.whatsapp_access_token.is_some() && config.whatsapp_phone_number_id.is_some()
+    }
+
+    /// Webhook routes for the WhatsApp Cloud API. These are merged outside of the
+    /// auth middleware because Meta authenticates with its own signature header.
+    pub fn router(self) -> Router {
+        Router::new()
+            .route("/webhooks/whatsapp", get(verify_webhook).post(receive_webhook))
+            .with_state(Arc::new(self))
+    }
+
+    // The Cloud API rejects text bodies longer than 4096 characters, so long texts are sent as
+    // several messages
+    async fn send_pages(
+        &self,
+        to: &str,
+        text: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        for page in split_response(text, &self.lang) {
+            self.send_text(to, &page).await?;
+        }
+        Ok(())
+    }
+
+    async fn send_text(
+        &self,
+        to: &str,
+        text: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let (Some(token), Some(phone_number_id)) = (
+            self.config.whatsapp_access_token.as_deref(),
+            self.config.whatsapp_phone_number_id.as_deref(),
+        ) else {
+            return Err("WhatsApp messenger is not configured".into());
+        };
+
+        let response = self
+            .client
+            .post(format!("{}/{}/messages", GRAPH_API_URL, phone_number_id))
+            .bearer_auth(token)
+            .json(&SendTextPayload {
+                messaging_product: "whatsapp",
+                to,
+                kind: "text",
+                text: SendTextBody { body: text },
+            })
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!("WhatsApp API returned {}: {}", status, body).into());
+        }
+
+        Ok(())
+    }
+
+    fn verify_signature(&self, headers: &HeaderMap, body: &[u8]) -> bool {
+        match self.config.whatsapp_app_secret.as_deref() {
+            Some(secret) => verify_signature(secret, headers, body),
+            None => false,
+        }
+    }
+
+    async fn handle_message(
+        &self,
+        chat_id: &str,
+        sender: &ChatSender,
+        text: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        // Check if chat is bound
+        let mut tx = self.db_pool.begin().await?;
+        let binding = ChatBindingRepo::get_selected(&mut tx, "whatsapp", chat_id).await?;
+
+        let response = match binding {
+            Some(binding) => CommandDispatcher::dispatch(text, &binding, sender, &self.config.chat_bind_url, &mut tx, &self.lang, &self.rate_limiter).await,
+            None => Some(
+                CommandDispatcher::dispatch_unbound(
+                    "whatsapp",
+                    chat_id,
+                    text,
+                    &self.config.chat_bind_url,
+                    &mut tx,
+                    &self.lang,
+                )
+                .await?
+                .into(),
+            ),
+        };
+
+        // WhatsApp replies are plain text, buttons are left out
+        if let Some(response) = response {
+            self.send_pages(chat_id, &response.text).await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+async fn verify_webhook(
+    State(messenger): State<Arc<WhatsAppMessenger>>,
+    Query(query): Query<WebhookVerifyQuery>,
+) -> impl IntoResponse {
+    let expected = messenger.config.whatsapp_verify_token.as_deref();
+    match (query.mode.as_deref(), query.verify_token.as_deref(), query.challenge) {
+        (Some("subscribe"), Some(token), Some(challenge)) if Some(token) == expected => {
+            (StatusCode::OK, challenge).into_response()
+        }
+        _ => StatusCode::FORBIDDEN.into_response(),
+    }
+}
+
+/*
+ Checks `X-Hub-Signature-256`, `sha256=` followed by the hex HMAC-SHA256 of the body keyed with
+ the app secret
+*/
+fn verify_signature(secret: &str, headers: &HeaderMap, body: &[u8]) -> bool {
+    let Some(presented) = headers
+        .get("X-Hub-Signature-256")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("sha256="))
+        .and_then(|v| hex::decode(v).ok())
+    else {
+        return false;
+    };
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else {
+        return false;
+    };
+    mac.update(body);
+    mac.verify_slice(&presented).is_ok()
+}
+
+async fn receive_webhook(
+    State(messenger): State<Arc<WhatsAppMessenger>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> StatusCode {
+    if !messenger.verify_signature(&headers, &body) {
+        return StatusCode::UNAUTHORIZED;
+    }
+
+    let payload: WebhookPayload = match serde_json::from_slice(&body) {
+        Ok(payload) => payload,
+        Err(e) => {
+            tracing::warn!("Invalid WhatsApp webhook payload: {:?}", e);
+            return StatusCode::BAD_REQUEST;
+        }
+    };
+
+    for (message, sender) in payload
+        .entry
+        .into_iter()
+        .flat_map(|entry| entry.changes)
+        .flat_map(|change| change.value.into_messages())
+    {
+        let Some(text) = message.text.filter(|_| message.kind == "text") else {
+            continue;
+        };
+
+        // Meta retries deliveries that are not acknowledged quickly, so reply asynchronously
+        let replier = messenger.clone();
+        let from = message.from;
+        messenger.replies.spawn(async move {
+            if let Err(e) = replier.handle_message(&from, &sender, &text.body).await {
+                tracing::error!("Error handling WhatsApp message: {:?}", e);
+            }
+        });
+    }
+
+    StatusCode::OK
+}
+
+#[async_trait]
+impl Messenger for WhatsAppMessenger {
+    async fn send_message(
+        &self,
+        chat_id: &str,
+        text: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.send_pages(chat_id, text).await
+    }
+
+    async fn start(&self, _shutdown: &Shutdown) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        // Unsigned deliveries are refused, so without the secret no message would get through
+        if self.config.whatsapp_app_secret.is_none() {
+            return Err("WHATSAPP_APP_SECRET must be set to verify WhatsApp webhook deliveries".into());
+        }
+        // Incoming messages arrive through the webhook router, nothing to poll
+        tracing::info!("WhatsApp messenger ready, waiting for webhook deliveries");
+        Ok(())
+    }
+
+    // The webhook stops with the HTTP server, only the replies in flight are left
+    async fn stop(&self) {
+        self.replies.close();
+        self.replies.wait().await;
+    }
+
+    fn platform(&self) -> &str {
+        "whatsapp"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_webhook_payload() {
+        let body = r#"{
+            "object": "whatsapp_business_account",
+            "entry": [{
+                "id": "123",
+                "changes": [{
+                    "field": "messages",
+                    "value": {
+                        "messaging_product": "whatsapp",
+                        "metadata": { "display_phone_number": "15550000000", "phone_number_id": "1" },
+                        "messages": [
+                            { "from": "6281234567890", "id": "wamid.1", "timestamp": "1", "type": "text", "text": { "body": "/report" } },
+                            { "from": "6281234567890", "id": "wamid.2", "timestamp": "2", "type": "image" }
+                        ]
+                    }
+                }]
+            }]
+        }"#;
+
+        let payload: WebhookPayload = serde_json::from_str(body).unwrap();
+        let messages: Vec<_> = payload
+            .entry
+            .into_iter()
+            .flat_map(|entry| entry.changes)
+            .flat_map(|change| change.value.messages)
+            .collect();
+
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].from, "6281234567890");
+        assert_eq!(messages[0].text.as_ref().unwrap().body, "/report");
+        assert_eq!(messages[1].kind, "image");
+        assert!(messages[1].text.is_none());
+    }
+
+    #[test]
+    fn test_parse_status_only_payload() {
+        let body = r#"{ "entry": [{ "changes": [{ "value": { "statuses": [] } }] }] }"#;
+        let payload: WebhookPayload = serde_json::from_str(body).unwrap();
+        assert!(payload.entry[0].changes[0].value.messages.is_empty());
+    }
+
+    #[test]
+    fn test_messages_paired_with_contact_name() {
+        let body = r#"{ "entry": [{ "changes": [{ "value": {
+            "contacts": [{ "profile": { "name": "Budi" }, "wa_id": "6281234567890" }],
+            "messages": [
+                { "from": "6281234567890", "type": "text", "text": { "body": "/expense" } },
+                { "from": "6289999999999", "type": "text", "text": { "body": "/expense" } }
+            ]
+        } }] }] }"#;
+
+        let payload: WebhookPayload = serde_json::from_str(body).unwrap();
+        let messages: Vec<_> = payload
+            .entry
+            .into_iter()
+            .flat_map(|entry| entry.changes)
+            .flat_map(|change| change.value.into_messages())
+            .collect();
+
+        assert_eq!(messages[0].1.name, "Budi");
+        assert_eq!(messages[0].1.p_user_id, "6281234567890");
+        assert_eq!(messages[1].1.name, "6289999999999");
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        let body = br#"{ "entry": [] }"#;
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"secret").unwrap();
+        mac.update(body);
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "X-Hub-Signature-256",
+            format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+                .parse()
+                .unwrap(),
+        );
+
+        assert!(verify_signature("secret", &headers, body));
+        assert!(!verify_signature("other", &headers, body));
+        assert!(!verify_signature("secret", &headers, b"tampered"));
+        assert!(!verify_signature("secret", &HeaderMap::new(), body));
+    }
+}
@@ -1 +1,9 @@
-pub mod tier;
\ No newline at end of file
+pub mod client_ip;
+pub mod metrics_auth;
+pub mod rate_limit;
+pub mod request_id;
+pub mod request_metrics;
+pub mod security_headers;
+pub mod tier;
+pub mod timeout;
+pub mod validated_json;
@@ -0,0 +1,73 @@
+use std::net::{IpAddr, SocketAddr};
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::HeaderMap,
+    middleware::Next,
+    response::Response,
+};
+
+// Address of whoever sent the request, set on requests when it is known
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+/*
+ * Behind a trusted proxy the client is the address the proxy appended to X-Forwarded-For,
+ * otherwise the peer of the connection. Without connection info (e.g. in tests) and without a
+ * forwarded address, no ClientIp is set.
+ */
+pub async fn client_ip_middleware(
+    State(trust_proxy): State<bool>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let forwarded = if trust_proxy {
+        forwarded_for(request.headers())
+    } else {
+        None
+    };
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    if let Some(ip) = forwarded.or(peer) {
+        request.extensions_mut().insert(ClientIp(ip));
+    }
+    next.run(request).await
+}
+
+// Clients can send X-Forwarded-For themselves, only the last entry was added by our proxy
+fn forwarded_for(headers: &HeaderMap) -> Option<IpAddr> {
+    headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .next_back()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_forwarded_for() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(forwarded_for(&headers), None);
+
+        headers.insert("x-forwarded-for", "1.1.1.1, 10.0.0.2".parse().unwrap());
+        assert_eq!(forwarded_for(&headers), Some("10.0.0.2".parse().unwrap()));
+
+        headers.append("x-forwarded-for", "203.0.113.7".parse().unwrap());
+        assert_eq!(
+            forwarded_for(&headers),
+            Some("203.0.113.7".parse().unwrap())
+        );
+
+        headers.insert("x-forwarded-for", "unknown".parse().unwrap());
+        assert_eq!(forwarded_for(&headers), None);
+    }
+}
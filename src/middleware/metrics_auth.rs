@@ -0,0 +1,72 @@
+use axum::{
+    extract::{Request, State},
+    http::{HeaderMap, header::AUTHORIZATION},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::error::AppError;
+
+pub const METRICS_PATH: &str = "/metrics";
+
+/*
+ * `/metrics` skips the user auth and is served only to requests bearing `METRICS_TOKEN`
+ * instead. Without a token configured the metrics are not served at all.
+ */
+pub async fn metrics_auth_middleware(
+    State(token): State<Option<String>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if request.uri().path() != METRICS_PATH {
+        return next.run(request).await;
+    }
+    let Some(token) = token else {
+        return AppError::NotFound("Metrics are disabled".into()).into_response();
+    };
+    if !bearer_matches(request.headers(), &token) {
+        return AppError::Unauthorized("Invalid metrics token".into()).into_response();
+    }
+    next.run(request).await
+}
+
+// Compares MACs of both tokens, so the time taken says nothing about how much of it matched
+fn bearer_matches(headers: &HeaderMap, token: &str) -> bool {
+    let Some(presented) = headers
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+    else {
+        return false;
+    };
+    let mac = |value: &str| {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(METRICS_PATH.as_bytes()).expect("HMAC takes any key");
+        mac.update(value.as_bytes());
+        mac
+    };
+    mac(token)
+        .verify_slice(&mac(presented).finalize().into_bytes())
+        .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bearer_matches() {
+        let mut headers = HeaderMap::new();
+        assert!(!bearer_matches(&headers, "secret"));
+
+        headers.insert(AUTHORIZATION, "Bearer secret".parse().unwrap());
+        assert!(bearer_matches(&headers, "secret"));
+        assert!(!bearer_matches(&headers, "secret2"));
+        assert!(!bearer_matches(&headers, "secre"));
+
+        headers.insert(AUTHORIZATION, "secret".parse().unwrap());
+        assert!(!bearer_matches(&headers, "secret"));
+    }
+}
@@ -0,0 +1,290 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{Request, State},
+    http::header::RETRY_AFTER,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    auth::AuthContext,
+    error::{AppError, DatabaseError, ErrorCode},
+    middleware::client_ip::ClientIp,
+    repos::subscription::SubscriptionRepo,
+    types::{AppState, SubscriptionTier},
+};
+
+// Tier changes are picked up after at most this long
+const TIER_CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+// Above this many tracked keys, buckets that have refilled completely are dropped
+const MAX_TRACKED_KEYS: usize = 10_000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RateLimitDecision {
+    Allowed,
+    // `first` is only set for the first rejection in a row, so chats are told once instead of
+    // getting a reply to every spammed message
+    Limited { retry_after_secs: u64, first: bool },
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+    limited: bool,
+}
+
+/*
+ * In-memory token buckets, one per key (user, client IP or chat). Each bucket holds up to
+ * `per_minute` tokens and refills at `per_minute` tokens per minute; a request takes one token.
+ * State is per process, which is enough for a single instance deployment.
+ */
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    buckets: Mutex<HashMap<String, Bucket>>,
+    tiers: Mutex<HashMap<Uuid, (SubscriptionTier, Instant)>>,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // A negative `per_minute` means unlimited, following `TierLimits`
+    pub fn check(&self, key: &str, per_minute: i32) -> RateLimitDecision {
+        self.check_at(key, per_minute, Instant::now())
+    }
+
+    // At most one request per `period` for the key, a zero period lets everything through
+    pub fn cooldown(&self, key: &str, period: Duration) -> RateLimitDecision {
+        self.cooldown_at(key, period, Instant::now())
+    }
+
+    fn check_at(&self, key: &str, per_minute: i32, now: Instant) -> RateLimitDecision {
+        if per_minute < 0 {
+            return RateLimitDecision::Allowed;
+        }
+        let capacity = per_minute as f64;
+        self.take(key, capacity, capacity / 60.0, now)
+    }
+
+    fn cooldown_at(&self, key: &str, period: Duration, now: Instant) -> RateLimitDecision {
+        if period.is_zero() {
+            return RateLimitDecision::Allowed;
+        }
+        self.take(key, 1.0, 1.0 / period.as_secs_f64(), now)
+    }
+
+    // Takes a token from the key's bucket holding up to `capacity` tokens
+    fn take(
+        &self,
+        key: &str,
+        capacity: f64,
+        refill_per_sec: f64,
+        now: Instant,
+    ) -> RateLimitDecision {
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_TRACKED_KEYS && !buckets.contains_key(key) {
+            buckets.retain(|_, bucket| now.duration_since(bucket.updated_at).as_secs() < 60);
+        }
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            updated_at: now,
+            limited: false,
+        });
+
+        let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * refill_per_sec).min(capacity);
+        bucket.updated_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            bucket.limited = false;
+            return RateLimitDecision::Allowed;
+        }
+
+        let first = !bucket.limited;
+        bucket.limited = true;
+        let retry_after_secs = if refill_per_sec > 0.0 {
+            ((1.0 - bucket.tokens) / refill_per_sec).ceil() as u64
+        } else {
+            60
+        };
+        RateLimitDecision::Limited {
+            retry_after_secs: retry_after_secs.max(1),
+            first,
+        }
+    }
+
+    pub fn cached_tier(&self, user_uid: Uuid) -> Option<SubscriptionTier> {
+        let tiers = self.tiers.lock().unwrap();
+        tiers
+            .get(&user_uid)
+            .filter(|(_, cached_at)| cached_at.elapsed() < TIER_CACHE_TTL)
+            .map(|(tier, _)| tier.clone())
+    }
+
+    // Users without an active subscription are limited like the free tier
+    pub async fn tier_for(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        user_uid: Uuid,
+    ) -> Result<SubscriptionTier, DatabaseError> {
+        if let Some(tier) = self.cached_tier(user_uid) {
+            return Ok(tier);
+        }
+        let tier = match SubscriptionRepo::get_by_user(tx, user_uid).await {
+            Ok(subscription) => subscription.get_tier(),
+            Err(DatabaseError::NotFound(_)) => SubscriptionTier::Free,
+            Err(e) => return Err(e),
+        };
+        self.tiers
+            .lock()
+            .unwrap()
+            .insert(user_uid, (tier.clone(), Instant::now()));
+        Ok(tier)
+    }
+}
+
+/*
+ * Limits REST requests per user, based on their subscription tier. Must run after the auth
+ * middleware. Unauthenticated requests (login, register, ...) are limited per client IP with the
+ * free tier limits when the client IP is known.
+ */
+pub async fn rate_limit_middleware(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let auth = request.extensions().get::<AuthContext>().cloned();
+    let (key, per_minute) = match auth {
+        Some(auth) => {
+            let tier = match state.rate_limiter.cached_tier(auth.user_uid) {
+                Some(tier) => tier,
+                None => {
+                    let mut tx = state.db_pool.begin().await.map_err(|e| {
+                        AppError::from_sqlx_error(e, "beginning transaction for rate limiting")
+                    })?;
+                    let tier = state.rate_limiter.tier_for(&mut tx, auth.user_uid).await?;
+                    tx.commit().await.map_err(|e| {
+                        AppError::from_sqlx_error(e, "committing transaction for rate limiting")
+                    })?;
+                    tier
+                }
+            };
+            (
+                format!("user:{}", auth.user_uid),
+                tier.limits().api_requests_per_minute,
+            )
+        }
+        None => match request.extensions().get::<ClientIp>() {
+            Some(ClientIp(ip)) => (
+                format!("ip:{}", ip),
+                SubscriptionTier::Free.limits().api_requests_per_minute,
+            ),
+            None => return Ok(next.run(request).await),
+        },
+    };
+
+    match state.rate_limiter.check(&key, per_minute) {
+        RateLimitDecision::Allowed => Ok(next.run(request).await),
+        RateLimitDecision::Limited {
+            retry_after_secs, ..
+        } => Ok((
+            [(RETRY_AFTER, retry_after_secs.to_string())],
+            AppError::coded(
+                ErrorCode::RateLimited,
+                format!(
+                    "Rate limit exceeded. Try again in {} seconds.",
+                    retry_after_secs
+                ),
+            )
+            .with_details(json!({ "retry_after": retry_after_secs })),
+        )
+            .into_response()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_drains_and_refills() {
+        let limiter = RateLimiter::new();
+        let start = Instant::now();
+        for _ in 0..3 {
+            assert_eq!(
+                limiter.check_at("chat", 3, start),
+                RateLimitDecision::Allowed
+            );
+        }
+        assert_eq!(
+            limiter.check_at("chat", 3, start),
+            RateLimitDecision::Limited {
+                retry_after_secs: 20,
+                first: true
+            }
+        );
+        assert_eq!(
+            limiter.check_at("chat", 3, start + Duration::from_secs(5)),
+            RateLimitDecision::Limited {
+                retry_after_secs: 15,
+                first: false
+            }
+        );
+        // 3 per minute refills one token every 20 seconds
+        assert_eq!(
+            limiter.check_at("chat", 3, start + Duration::from_secs(20)),
+            RateLimitDecision::Allowed
+        );
+        // Other keys have their own bucket
+        assert_eq!(
+            limiter.check_at("other", 3, start),
+            RateLimitDecision::Allowed
+        );
+    }
+
+    #[test]
+    fn test_cooldown() {
+        let limiter = RateLimiter::new();
+        let start = Instant::now();
+        let period = Duration::from_secs(16);
+        assert_eq!(
+            limiter.cooldown_at("unbound", period, start),
+            RateLimitDecision::Allowed
+        );
+        assert_eq!(
+            limiter.cooldown_at("unbound", period, start + Duration::from_secs(4)),
+            RateLimitDecision::Limited {
+                retry_after_secs: 12,
+                first: true
+            }
+        );
+        assert_eq!(
+            limiter.cooldown_at("unbound", period, start + Duration::from_secs(16)),
+            RateLimitDecision::Allowed
+        );
+        assert_eq!(
+            limiter.cooldown_at("unbound", Duration::ZERO, start),
+            RateLimitDecision::Allowed
+        );
+    }
+
+    #[test]
+    fn test_unlimited() {
+        let limiter = RateLimiter::new();
+        let now = Instant::now();
+        for _ in 0..1000 {
+            assert_eq!(
+                limiter.check_at("chat", -1, now),
+                RateLimitDecision::Allowed
+            );
+        }
+    }
+}
@@ -0,0 +1,25 @@
+use std::time::Instant;
+
+use axum::{
+    extract::{MatchedPath, Request},
+    middleware::Next,
+    response::Response,
+};
+
+use crate::telemetry;
+
+// Counts requests and their latency per route, including the ones rejected by auth
+pub async fn request_metrics_middleware(request: Request, next: Next) -> Response {
+    let started = Instant::now();
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        // Unknown paths share one label, they would otherwise grow the metrics without bound
+        .unwrap_or_else(|| "unmatched".to_string());
+
+    let response = next.run(request).await;
+    telemetry::record_request(&method, &route, response.status().as_u16(), started);
+    response
+}
@@ -1,19 +1,21 @@
 use axum::{
-    Json,
     extract::{Request, State},
-    http::StatusCode,
     middleware::Next,
-    response::{IntoResponse, Response},
+    response::Response,
 };
 use serde_json::json;
+use uuid::Uuid;
 
 use crate::{
     auth::AuthContext,
-    error::AppError,
-    repos::subscription::SubscriptionRepo,
+    error::{AppError, ErrorCode},
+    repos::{subscription::SubscriptionRepo, tier_violation::TierViolationRepo},
     types::{AppState, SubscriptionTier, TierError},
 };
 
+// Where the frontend offers plan upgrades
+const UPGRADE_URL: &str = "/billing/upgrade";
+
 #[derive(Debug)]
 pub struct TierCheck {
     pub required_tier: Option<SubscriptionTier>,
@@ -65,27 +67,21 @@ pub async fn tier_enforcement_middleware(
             Ok(sub) => {
                 // Check if subscription is active
                 if sub.status != "active" {
-                    return Ok((
-                        StatusCode::PAYMENT_REQUIRED,
-                        Json(json!({
-                            "error": "Subscription inactive",
-                            "message": "Your subscription is not active. Please renew your subscription.",
-                            "upgrade_url": "/billing/upgrade"
-                        })),
-                    ).into_response());
+                    return Err(AppError::coded(
+                        ErrorCode::SubscriptionInactive,
+                        "Your subscription is not active. Please renew your subscription.",
+                    )
+                    .with_details(json!({ "upgrade_url": UPGRADE_URL })));
                 }
 
                 // Check if subscription has expired
                 if let Some(end_date) = sub.current_period_end {
                     if end_date < chrono::Utc::now() {
-                        return Ok((
-                            StatusCode::PAYMENT_REQUIRED,
-                            Json(json!({
-                                "error": "Subscription expired",
-                                "message": "Your subscription has expired. Please renew your subscription.",
-                                "upgrade_url": "/billing/upgrade"
-                            })),
-                        ).into_response());
+                        return Err(AppError::coded(
+                            ErrorCode::SubscriptionExpired,
+                            "Your subscription has expired. Please renew your subscription.",
+                        )
+                        .with_details(json!({ "upgrade_url": UPGRADE_URL })));
                     }
                 }
 
@@ -147,28 +143,65 @@ pub fn check_tier_limit(
         })
 }
 
-pub fn check_feature_access(
-    subscription: &crate::repos::subscription::Subscription,
-    feature: &str,
-) -> Result<(), TierError> {
-    let limits = subscription.get_tier().limits();
+/*
+ Blocks creating more of a resource the tier reconciliation flagged for the group, e.g. after
+ its owner was downgraded. Needed where the count check uses the caller's tier, not the owner's.
+*/
+pub async fn check_tier_violation(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    group_uid: Uuid,
+    resource_type: &str,
+) -> Result<(), AppError> {
+    if TierViolationRepo::exists_for_group(tx, group_uid, resource_type).await? {
+        return Err(AppError::coded(
+            ErrorCode::TierLimitExceeded,
+            format!(
+                "The group is over the {} limit of its owner's plan, remove some or upgrade first",
+                resource_type
+            ),
+        )
+        .with_details(json!({ "resource_type": resource_type })));
+    }
+    Ok(())
+}
 
-    let has_access = match feature {
+// Whether the tier includes `feature`, unknown features are included everywhere
+fn has_feature(tier: &SubscriptionTier, feature: &str) -> bool {
+    let limits = tier.limits();
+    match feature {
         "advanced_reports" => limits.advanced_reports,
         "export_data" => limits.export_data,
         "priority_support" => limits.priority_support,
         "custom_categories" => limits.custom_categories,
+        "receipts" => limits.receipts,
+        "slack_integration" => limits.slack_integration,
         _ => true, // Unknown feature, allow access
-    };
+    }
+}
 
-    if !has_access {
-        return Err(TierError::InsufficientTier {
-            required_tier: SubscriptionTier::Personal, // Default to personal for unknown features
-            current_tier: subscription.get_tier(),
-        });
+pub fn check_feature_access(
+    subscription: &crate::repos::subscription::Subscription,
+    feature: &str,
+) -> Result<(), TierError> {
+    let current_tier = subscription.get_tier();
+    if has_feature(&current_tier, feature) {
+        return Ok(());
     }
 
-    Ok(())
+    // The cheapest tier with the feature
+    let required_tier = [
+        SubscriptionTier::Personal,
+        SubscriptionTier::Family,
+        SubscriptionTier::Team,
+        SubscriptionTier::Enterprise,
+    ]
+    .into_iter()
+    .find(|tier| has_feature(tier, feature))
+    .unwrap_or(SubscriptionTier::Enterprise);
+    Err(TierError::InsufficientTier {
+        required_tier,
+        current_tier,
+    })
 }
 
 pub fn get_upgrade_message(
@@ -193,7 +226,7 @@ pub fn get_upgrade_message(
         "current_tier": current_tier_name,
         "suggested_upgrade": suggested_tier.display_name(),
         "upgrade_price": suggested_tier.price(),
-        "upgrade_url": "/billing/upgrade",
+        "upgrade_url": UPGRADE_URL,
         "message": format!(
             "Consider upgrading to {} for ${:.2}/month to increase your {} limit.",
             suggested_tier.display_name(),
@@ -202,3 +235,43 @@ pub fn get_upgrade_message(
         )
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repos::subscription::Subscription;
+
+    fn subscription(tier: SubscriptionTier) -> Subscription {
+        Subscription {
+            id: Uuid::new_v4(),
+            user_uid: Uuid::new_v4(),
+            tier,
+            status: "active".to_string(),
+            current_period_start: None,
+            current_period_end: None,
+            cancel_at_period_end: false,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_check_feature_access_names_cheapest_tier() {
+        match check_feature_access(&subscription(SubscriptionTier::Family), "slack_integration") {
+            Err(TierError::InsufficientTier {
+                required_tier,
+                current_tier,
+            }) => {
+                assert_eq!(required_tier, SubscriptionTier::Team);
+                assert_eq!(current_tier, SubscriptionTier::Family);
+            }
+            other => panic!("expected InsufficientTier, got {:?}", other),
+        }
+        assert!(
+            check_feature_access(&subscription(SubscriptionTier::Team), "slack_integration")
+                .is_ok()
+        );
+        assert!(check_feature_access(&subscription(SubscriptionTier::Free), "receipts").is_err());
+        assert!(check_feature_access(&subscription(SubscriptionTier::Free), "unknown").is_ok());
+    }
+}
@@ -0,0 +1,25 @@
+use std::time::Duration;
+
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::error::{AppError, ErrorCode};
+
+// Dropping the handler rolls back its open transaction, nothing is half written
+pub async fn timeout_middleware(
+    State(timeout): State<Duration>,
+    request: Request,
+    next: Next,
+) -> Response {
+    match tokio::time::timeout(timeout, next.run(request)).await {
+        Ok(response) => response,
+        Err(_) => AppError::coded(
+            ErrorCode::RequestTimeout,
+            format!("Request took longer than {} seconds", timeout.as_secs()),
+        )
+        .into_response(),
+    }
+}
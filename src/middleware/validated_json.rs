@@ -0,0 +1,161 @@
+use axum::{
+    Json,
+    extract::{FromRequest, Request, rejection::JsonRejection},
+};
+use rust_decimal::Decimal;
+use serde::de::DeserializeOwned;
+use validator::{Validate, ValidationError};
+
+use crate::error::{AppError, ErrorCode};
+use crate::utils::category_style;
+
+/*
+ * `Json` that also runs the payload's `Validate` rules, so handlers only see valid bodies.
+ * Failed rules are a 422 `VALIDATION_FAILED` listing every invalid field; a body of the wrong
+ * shape (missing field, wrong type) is a 422 too, malformed JSON stays a 400.
+ */
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ValidatedJson<T>(pub T);
+
+impl<T, S> FromRequest<S> for ValidatedJson<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<T>::from_request(req, state)
+            .await
+            .map_err(json_rejection)?;
+        value.validate()?;
+        Ok(Self(value))
+    }
+}
+
+fn json_rejection(rejection: JsonRejection) -> AppError {
+    match rejection {
+        JsonRejection::JsonDataError(err) => {
+            AppError::coded(ErrorCode::ValidationFailed, err.body_text())
+        }
+        other => AppError::BadRequest(other.body_text()),
+    }
+}
+
+// For `#[validate(custom(function = "positive_amount"))]` on prices
+pub fn positive_amount(amount: &Decimal) -> Result<(), ValidationError> {
+    if *amount <= Decimal::ZERO {
+        let err = ValidationError::new("positive");
+        return Err(err.with_message("must be greater than 0".into()));
+    }
+    Ok(())
+}
+
+pub fn non_negative_amount(amount: &Decimal) -> Result<(), ValidationError> {
+    if *amount < Decimal::ZERO {
+        let err = ValidationError::new("non_negative");
+        return Err(err.with_message("must not be negative".into()));
+    }
+    Ok(())
+}
+
+pub fn category_icon(icon: &str) -> Result<(), ValidationError> {
+    if !category_style::is_valid_icon(icon) {
+        let err = ValidationError::new("icon");
+        return Err(err.with_message("must be a single emoji or a short code like :coffee:".into()));
+    }
+    Ok(())
+}
+
+pub fn hex_color(color: &str) -> Result<(), ValidationError> {
+    if !category_style::is_valid_color(color) {
+        let err = ValidationError::new("hex_color");
+        return Err(err.with_message("must be a hex color like #f97316".into()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::header::CONTENT_TYPE};
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, Validate)]
+    struct Payload {
+        #[validate(length(min = 1, max = 10))]
+        name: String,
+        #[validate(range(min = 1, max = 12))]
+        month: Option<i32>,
+        #[validate(custom(function = "positive_amount"))]
+        price: Option<Decimal>,
+    }
+
+    async fn extract(body: &str) -> Result<Payload, AppError> {
+        let request = Request::builder()
+            .method("POST")
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        ValidatedJson::<Payload>::from_request(request, &())
+            .await
+            .map(|ValidatedJson(payload)| payload)
+    }
+
+    #[tokio::test]
+    async fn test_valid_payload() {
+        let payload = extract(r#"{"name": "Food", "month": 3}"#).await.unwrap();
+        assert_eq!(payload.name, "Food");
+        assert_eq!(payload.month, Some(3));
+    }
+
+    #[tokio::test]
+    async fn test_failed_rules_list_each_field() {
+        let err = extract(r#"{"name": "", "month": 13, "price": 0}"#)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ValidationFailed);
+        let AppError::Coded {
+            details: Some(details),
+            ..
+        } = err
+        else {
+            panic!("expected validation details");
+        };
+        assert_eq!(details["name"][0]["code"], "length");
+        assert_eq!(details["month"][0]["code"], "range");
+        assert_eq!(details["price"][0]["code"], "positive");
+    }
+
+    #[test]
+    fn test_amounts() {
+        assert!(positive_amount(&Decimal::ONE).is_ok());
+        assert_eq!(
+            positive_amount(&Decimal::ZERO).unwrap_err().code,
+            "positive"
+        );
+        assert!(non_negative_amount(&Decimal::ZERO).is_ok());
+        assert_eq!(
+            non_negative_amount(&Decimal::NEGATIVE_ONE)
+                .unwrap_err()
+                .code,
+            "non_negative"
+        );
+    }
+
+    #[test]
+    fn test_category_style() {
+        assert!(category_icon("☕").is_ok());
+        assert_eq!(category_icon("coffee").unwrap_err().code, "icon");
+        assert!(hex_color("#92400e").is_ok());
+        assert_eq!(hex_color("brown").unwrap_err().code, "hex_color");
+    }
+
+    #[tokio::test]
+    async fn test_body_rejections() {
+        let err = extract(r#"{"month": 3}"#).await.unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ValidationFailed);
+        let err = extract(r#"{"name": "#).await.unwrap_err();
+        assert_eq!(err.code(), ErrorCode::BadRequest);
+    }
+}
@@ -1,96 +1,192 @@
 use utoipa::OpenApi;
 
-use crate::{repos as repo, routes, types};
+use crate::{error, jobs, repos as repo, routes, types, utils};
 
+/*
+ Components, tags and security of the OpenAPI doc. Paths aren't listed here: the routers
+ document their routes, see `app::api_router`.
+*/
 #[derive(OpenApi)]
 #[openapi(
-    paths(
-        // routes::users::list_users,
-        routes::users::get_me,
-        routes::users::create_user,
-        routes::users::update_user,
-        routes::users::login_user,
-
-        routes::expense_entry::list_expense_entries,
-        routes::expense_entry::create_expense_entry,
-        routes::expense_entry::get_expense_entry,
-        routes::expense_entry::update_expense_entry,
-        routes::expense_entry::delete_expense_entry,
-
-        routes::expense_groups::list,
-        routes::expense_groups::get,
-        routes::expense_groups::create,
-        routes::expense_groups::update,
-        // routes::expense_groups::delete_,
-
-        routes::categories::list,
-        routes::categories::get,
-        routes::categories::create,
-        routes::categories::update,
-        // routes::categories::delete_,
-
-        routes::budgets::list,
-        routes::budgets::get,
-        routes::budgets::create,
-        routes::budgets::update,
-        routes::budgets::delete_,
-
-        routes::chat_bind_requests::create,
-        routes::chat_bind_requests::get,
-
-        routes::chat_bindings::accept,
-
-        routes::group_members::list,
-        routes::group_members::get,
-        routes::group_members::create,
-        routes::group_members::update,
-        routes::group_members::delete_,
-
-        routes::health::health,
-        routes::version::version,
-    ),
     components(schemas(
         // Repo models
+        repo::api_key::ApiKey,
+        repo::refresh_token::Session,
+        repo::webhook::Webhook,
+        repo::webhook::WebhookDelivery,
         repo::user::User,
         repo::user::UserRead,
         repo::expense_group::ExpenseGroup,
         repo::category::Category,
+        repo::category_alias::CategoryAlias,
+        repo::tag::Tag,
+        repo::product::Product,
+        repo::envelope::Envelope,
         repo::expense_entry::ExpenseEntry,
+        repo::expense_entry::ExpenseEntrySearchResult,
+        repo::receipt::Receipt,
+        repo::expense_draft::ExpenseDraft,
+        repo::reconciliation::Reconciliation,
+        repo::reconciliation::ReconciliationRow,
+        repo::income_entry::IncomeEntry,
+        repo::recurring_expense::RecurringExpense,
         repo::expense_group::UpdateExpenseGroupDbPayload,
         repo::budget::Budget,
+        repo::budget_alert::BudgetAlertSettings,
+        repo::budget_plan::BudgetPlan,
+        repo::budget_plan::PlannedBudget,
+        repo::notification_settings::NotificationSettings,
+        repo::group_settings::GroupSettings,
+        repo::exchange_rate::ExchangeRate,
         repo::chat_bind_request::ChatBindRequest,
         repo::chat_binding::ChatBinding,
+        repo::chat_member_link::ChatMemberLink,
         repo::expense_group_member::GroupMember,
+        repo::group_invite::GroupInvite,
+        repo::settlement::Settlement,
+        repo::settlement::MemberBalance,
+        repo::debt::Debt,
+        repo::debt::OutstandingDebts,
+        repo::user::UserSummary,
+        repo::admin::PlatformStats,
+        repo::admin::TierCount,
+        repo::admin::GroupInspection,
+        repo::audit_log::AuditLog,
+        repo::closed_period::ClosedPeriod,
+        repo::overview::PendingInvite,
+        repo::job::Job,
         // Route models
         routes::users::CreateUserPayload,
         routes::users::UpdateUserPayload,
         routes::users::LoginUserPayload,
         routes::users::LoginResponse,
+        routes::users::SessionTokens,
+        routes::users::RefreshTokenPayload,
+        routes::users::ForgotPasswordPayload,
+        routes::users::ResetPasswordPayload,
+        routes::users::MfaEnrollResponse,
+        routes::users::MfaVerifyPayload,
+        routes::users::MfaRecoveryCodesResponse,
+        routes::overview::UserOverview,
+        routes::overview::GroupOverview,
+        routes::overview::BudgetAlertSummary,
+        routes::overview::TierUsage,
+        routes::overview::UsageLimit,
+        routes::api_keys::CreateApiKeyPayload,
+        routes::api_keys::CreateApiKeyResponse,
+        routes::webhooks::CreateWebhookPayload,
+        routes::webhooks::UpdateWebhookPayload,
+        routes::webhooks::CreateWebhookResponse,
+        routes::email_ingest::EmailIngestAddressResponse,
+        routes::email_ingest::IngestEmailForm,
+        routes::reconciliations::ReconciliationStatementForm,
+        routes::reconciliations::ReconciliationDetail,
+        routes::reconciliations::CreateReconciliationResponse,
+        routes::reconciliations::CreateReconciliationEntriesPayload,
         routes::expense_groups::CreateExpenseGroupPayload,
         routes::expense_entry::CreateExpenseEntryPayload,
+        routes::expense_entry::CreateExpenseEntriesBatchPayload,
+        routes::expense_entry::CreateExpenseEntriesBatchResponse,
+        routes::expense_entry::ExpenseEntryBatchResult,
+        routes::expense_entry::ImportExpenseEntriesForm,
+        routes::expense_entry::UploadReceiptForm,
+        routes::expense_entry::ImportExpenseEntriesResponse,
+        routes::stats::GroupStats,
+        routes::stats::StatsBucket,
+        routes::stats::CategoryStats,
+        routes::stats::ProductStats,
+        utils::forecast::GroupForecast,
+        utils::forecast::CategoryForecast,
+        utils::forecast::Projection,
+        utils::activity::ActivityEvent,
+        utils::expense_import::ImportRowError,
+        routes::income_entry::CreateIncomeEntryPayload,
+        routes::income_entry::UpdateIncomeEntryPayload,
+        routes::recurring_expenses::CreateRecurringExpensePayload,
+        routes::recurring_expenses::UpdateRecurringExpensePayload,
         
         routes::categories::CreateCategoryPayload,
         routes::categories::UpdateCategoryPayload,
+        routes::categories::DeleteCategoryResponse,
+        routes::categories_aliases::CreateCategoryAliasPayload,
+        routes::categories_aliases::UpdateCategoryAliasPayload,
+        routes::tags::TagPayload,
+        routes::envelopes::CreateEnvelopePayload,
+        routes::envelopes::UpdateEnvelopePayload,
+        routes::envelopes::EnvelopeWithSpend,
+        routes::envelopes::EnvelopeStats,
+        routes::envelopes::EnvelopeCategoryStats,
+        routes::settlements::CreateSettlementPayload,
+        routes::debts::CreateDebtPayload,
+        routes::debts::UpdateDebtPayload,
+        routes::debts::SettleDebtPayload,
+        routes::admin::OverrideSubscriptionPayload,
+        routes::admin::AdminStats,
+        routes::admin::AdminGroupDetail,
+        jobs::tier_reconciliation::ReconcileSummary,
+        routes::billing::CheckoutPayload,
+        routes::billing::CheckoutResponse,
         routes::budgets::CreateBudgetPayload,
+        routes::budgets::BudgetWithSpend,
+        routes::budgets::BudgetAnalytics,
+        routes::budgets::UpdateBudgetAlertSettingsPayload,
         routes::budgets::UpdateBudgetPayload,
+        routes::budget_plans::CreateBudgetPlanPayload,
+        routes::budget_plans::SimulateBudgetPlanPayload,
+        routes::budget_plans::BudgetSimulation,
+        routes::budget_plans::SimulatedCategory,
+        routes::budget_plans::SimulatedPeriod,
+        routes::budget_plans::AppliedBudgetPlan,
+        routes::notification_settings::UpdateNotificationSettingsPayload,
+        routes::group_settings::GroupSettingsResponse,
+        routes::group_settings::UpdateGroupSettingsPayload,
         routes::chat_bind_requests::CreateChatBindRequestPayload,
         routes::chat_bindings::AcceptChatBindingPayload,
+        routes::chat_bindings::UpdateChatBindingPayload,
+        routes::chat_links::ChatLinkCodeResponse,
+        routes::chat_relay::ChatRelayPayload,
+        routes::chat_relay::ChatRelayResponse,
         routes::group_members::CreateGroupMemberPayload,
         routes::group_members::UpdateGroupMemberPayload,
+        routes::group_invites::CreateGroupInvitePayload,
+        routes::group_invites::GroupInviteResponse,
+        routes::currencies::CurrencyInfo,
         routes::version::VersionBody,
-        // Auth docs live in docs/auth.md; OpenAPI only declares bearer scheme.
+        // Auth docs live in docs/auth.md; OpenAPI only declares the bearer and API key schemes.
         // Common models
         types::DeleteResponse,
+        types::SubscriptionTier,
+        utils::anomaly::AnomalySensitivity,
+        error::ErrorBody,
+        error::ErrorCode,
     )),
     tags(
         (name = "Users"),
         (name = "Expense Entries"),
+        (name = "Income Entries"),
+        (name = "Recurring Expenses"),
         (name = "Expense Groups"),
         (name = "Categories"),
+        (name = "Category Aliases"),
         (name = "Budgets"),
+        (name = "Tags"),
+        (name = "Products"),
+        (name = "Envelopes"),
+        (name = "Settlements"),
+        (name = "Debts"),
+        (name = "Activity"),
+        (name = "Webhooks"),
+        (name = "Email Ingest"),
+        (name = "Reconciliations"),
+        (name = "Admin"),
+        (name = "Billing"),
+        (name = "Currencies"),
         (name = "Chat Bind Requests"),
         (name = "Chat Bindings"),
+        (name = "Chat Links"),
+        (name = "Chat Relay"),
         (name = "Group Members"),
+        (name = "Group Invites"),
         (name = "System"),
     ),
     modifiers(&ApiSecurity)
@@ -99,11 +195,23 @@ pub struct ApiDoc;
 
 use utoipa::Modify;
 
+// Finishes the doc of `app::api_router` once its paths are in
+pub fn complete(mut api: utoipa::openapi::OpenApi) -> utoipa::openapi::OpenApi {
+    ErrorResponses.modify(&mut api);
+    api
+}
+
+// The full OpenAPI doc, as served at `/api-doc/openapi.json`
+pub fn build() -> utoipa::openapi::OpenApi {
+    let (_, api) = crate::app::api_router().split_for_parts();
+    complete(api)
+}
+
 pub struct ApiSecurity;
 
 impl Modify for ApiSecurity {
     fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
-        use utoipa::openapi::security::{HttpAuthScheme, SecurityScheme};
+        use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, SecurityScheme};
         use utoipa::openapi::security::HttpBuilder;
         let components = openapi.components.get_or_insert_with(Default::default);
         let bearer = SecurityScheme::Http(
@@ -113,5 +221,44 @@ impl Modify for ApiSecurity {
                 .build(),
         );
         components.add_security_scheme("bearerAuth", bearer);
+        // Accepted wherever bearerAuth is, see `POST /users/me/api-keys`
+        let api_key = SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new(
+            crate::auth::API_KEY_HEADER,
+        )));
+        components.add_security_scheme("apiKeyAuth", api_key);
+    }
+}
+
+// Every operation can fail with an `ErrorBody`, documented once instead of on each path
+pub struct ErrorResponses;
+
+impl Modify for ErrorResponses {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        use utoipa::openapi::{ContentBuilder, Ref, ResponseBuilder};
+        let error = ResponseBuilder::new()
+            .description("Error, `code` tells which one")
+            .content(
+                "application/json",
+                ContentBuilder::new()
+                    .schema(Some(Ref::from_schema_name("ErrorBody")))
+                    .build(),
+            )
+            .build();
+        for item in openapi.paths.paths.values_mut() {
+            let operations = [
+                &mut item.get,
+                &mut item.put,
+                &mut item.post,
+                &mut item.delete,
+                &mut item.patch,
+            ];
+            for operation in operations.into_iter().flatten() {
+                operation
+                    .responses
+                    .responses
+                    .entry("default".to_string())
+                    .or_insert_with(|| error.clone().into());
+            }
+        }
     }
 }
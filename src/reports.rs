@@ -1,5 +1,10 @@
+pub mod digest;
+pub mod html_report;
 pub mod monthly_report;
+pub mod renderer;
 pub mod scheduler;
 
-pub use monthly_report::MonthlyReportGenerator;
+pub use html_report::HtmlReportRenderer;
+pub use monthly_report::{MonthlyReportGenerator, PdfReportRenderer};
+pub use renderer::ReportRenderer;
 pub use scheduler::ReportScheduler;
\ No newline at end of file
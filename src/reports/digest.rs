@@ -0,0 +1,195 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use std::collections::{HashMap, HashSet};
+
+use crate::error::DatabaseError;
+use crate::lang::Lang;
+use crate::repos::{
+    budget::BudgetRepo,
+    budget_alert::{BudgetAlertRepo, EXCEEDED_THRESHOLD},
+    category::CategoryRepo,
+    exchange_rate::ExchangeRateRepo,
+    expense_entry::ExpenseEntryRepo,
+    expense_group::ExpenseGroup,
+    group_settings::GroupSettingsRepo,
+    notification_settings::DigestKind,
+};
+use crate::utils::{currency::RateTable, parse_price::format_price_in};
+
+// Categories listed by name, the rest only count towards the total
+const TOP_CATEGORIES: usize = 3;
+
+/*
+ * Compact chat summary of a group's spending: the total, the biggest categories and how
+ * the running budgets are doing. Returns None when nothing was spent in the digest's
+ * range, since an empty digest is not worth a notification.
+ *
+ * Output format (can be found on lang/id.json):
+ * 📅 Ringkasan pengeluaran hari ini (01/09/2025)
+ *
+ * Total: Rp. 175.000
+ *
+ * Kategori teratas:
+ * 1. Makanan: Rp. 100.000
+ * 2. Transportasi: Rp. 50.000
+ *
+ * Status budget:
+ * ⚠️ Makanan: 85% (Rp. 850.000 dari Rp. 1.000.000)
+ */
+pub async fn build_digest(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    group: &ExpenseGroup,
+    kind: DigestKind,
+    today: NaiveDate,
+    lang: &Lang,
+) -> Result<Option<String>, DatabaseError> {
+    let (start, end) = kind.range(today);
+    let rates = ExchangeRateRepo::rate_table(tx).await?;
+
+    let mut category_totals: HashMap<String, Decimal> = HashMap::new();
+    let totals = ExpenseEntryRepo::totals_by_category_in_range(
+        tx,
+        group.uid,
+        None,
+        None,
+        true,
+        midnight(start),
+        midnight(end),
+    )
+    .await?;
+    for (category, currency, amount) in totals {
+        let amount = rates
+            .convert(amount, &currency, &group.currency)
+            .unwrap_or(amount);
+        let category = category.unwrap_or_else(|| lang.get("REPORT__UNCATEGORIZED"));
+        *category_totals.entry(category).or_default() += amount;
+    }
+    let total: Decimal = category_totals.values().sum();
+    if total.is_zero() {
+        return Ok(None);
+    }
+
+    let mut message = match kind {
+        DigestKind::Daily => lang.get_with_vars(
+            "DIGEST__DAILY_HEADER",
+            HashMap::from([("date".to_string(), start.format("%d/%m/%Y").to_string())]),
+        ),
+        DigestKind::Weekly => lang.get_with_vars(
+            "DIGEST__WEEKLY_HEADER",
+            HashMap::from([
+                (
+                    "start_date".to_string(),
+                    start.format("%d/%m/%Y").to_string(),
+                ),
+                (
+                    "end_date".to_string(),
+                    (end - Duration::days(1)).format("%d/%m/%Y").to_string(),
+                ),
+            ]),
+        ),
+    };
+    message.push_str(&lang.get_with_vars(
+        "DIGEST__TOTAL",
+        HashMap::from([("total".to_string(), format_price_in(total, &group.currency))]),
+    ));
+
+    let mut sorted_categories: Vec<_> = category_totals.into_iter().collect();
+    sorted_categories.sort_by(|a, b| b.1.cmp(&a.1));
+    message.push_str(&lang.get("DIGEST__TOP_CATEGORIES_HEADER"));
+    for (index, (category, amount)) in sorted_categories
+        .into_iter()
+        .take(TOP_CATEGORIES)
+        .enumerate()
+    {
+        message.push_str(&lang.get_with_vars(
+            "REPORT__CATEGORY_ITEM",
+            HashMap::from([
+                ("index".to_string(), (index + 1).to_string()),
+                ("category".to_string(), category),
+                (
+                    "amount".to_string(),
+                    format_price_in(amount, &group.currency),
+                ),
+                // The digest is not compared with an earlier period
+                ("change".to_string(), String::new()),
+            ]),
+        ));
+    }
+
+    let budget_lines = budget_status(tx, group, today, &rates, lang).await?;
+    if !budget_lines.is_empty() {
+        message.push_str(&lang.get("DIGEST__BUDGET_HEADER"));
+        message.push_str(&budget_lines.concat());
+    }
+
+    Ok(Some(message))
+}
+
+// One line per budget running today, fullest first
+async fn budget_status(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    group: &ExpenseGroup,
+    today: NaiveDate,
+    rates: &RateTable,
+    lang: &Lang,
+) -> Result<Vec<String>, DatabaseError> {
+    let settings = BudgetAlertRepo::get_settings(tx, group.uid).await?;
+    let week_start = GroupSettingsRepo::get(tx, group.uid).await?.week_start;
+    let mut budgets = BudgetRepo::list_by_group(tx, group.uid).await?;
+    // A budget pinned to the running period takes the place of the category's recurring one
+    budgets.sort_by_key(|budget| !budget.is_pinned());
+
+    let mut seen = HashSet::new();
+    let mut lines: Vec<(f64, String)> = Vec::new();
+    for budget in budgets {
+        let (period_start, period_end) =
+            budget.period_range(group.start_over_date, week_start, today);
+        if today < period_start || today >= period_end || !seen.insert(budget.category_uid) {
+            continue;
+        }
+        let totals = ExpenseEntryRepo::sum_by_category_in_range(
+            tx,
+            group.uid,
+            budget.category_uid,
+            midnight(period_start),
+            midnight(period_end),
+        )
+        .await?;
+        let (spent, _) = rates.sum_in(&totals, &budget.currency);
+        let percentage = budget.percentage_used(spent);
+        let icon = if percentage >= EXCEEDED_THRESHOLD as f64 {
+            "🚨"
+        } else if percentage >= settings.warning_percent as f64 {
+            "⚠️"
+        } else {
+            "✅"
+        };
+        let category = CategoryRepo::get(tx, budget.category_uid).await?;
+        lines.push((
+            percentage,
+            lang.get_with_vars(
+                "DIGEST__BUDGET_ITEM",
+                HashMap::from([
+                    ("icon".to_string(), icon.to_string()),
+                    ("category".to_string(), category.name),
+                    ("percent".to_string(), percentage.round().to_string()),
+                    (
+                        "spent".to_string(),
+                        format_price_in(spent, &budget.currency),
+                    ),
+                    (
+                        "amount".to_string(),
+                        format_price_in(budget.amount, &budget.currency),
+                    ),
+                ]),
+            ),
+        ));
+    }
+
+    lines.sort_by(|a, b| b.0.total_cmp(&a.0));
+    Ok(lines.into_iter().map(|(_, line)| line).collect())
+}
+
+fn midnight(date: NaiveDate) -> DateTime<Utc> {
+    date.and_hms_opt(0, 0, 0).unwrap().and_utc()
+}
@@ -0,0 +1,360 @@
+use rust_decimal::Decimal;
+use std::fmt::Write as _;
+
+use super::monthly_report::{BudgetStatus, MonthlyExpenseData};
+use super::renderer::ReportRenderer;
+use crate::utils::category_style;
+
+/*
+ Monthly report as a self-contained HTML page with inline styles, so it can be used as an
+ email body. Email clients that do not render HTML get `render_text` instead.
+*/
+#[derive(Clone, Copy)]
+pub struct HtmlReportRenderer;
+
+impl ReportRenderer for HtmlReportRenderer {
+    fn content_type(&self) -> &'static str {
+        "text/html; charset=utf-8"
+    }
+
+    fn file_extension(&self) -> &'static str {
+        "html"
+    }
+
+    fn render(
+        &self,
+        data: &MonthlyExpenseData,
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.render_html(data).into_bytes())
+    }
+}
+
+impl HtmlReportRenderer {
+    pub fn subject(&self, data: &MonthlyExpenseData) -> String {
+        format!(
+            "Monthly Expense Report - {}",
+            data.period_start.format("%B %Y")
+        )
+    }
+
+    pub fn render_html(&self, data: &MonthlyExpenseData) -> String {
+        let mut html = String::new();
+        let _ = write!(
+            html,
+            "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>{title}</title></head>\
+            <body style=\"font-family:Helvetica,Arial,sans-serif;color:#222;max-width:640px;margin:0 auto;\">\
+            <h1 style=\"font-size:22px;\">{title}</h1>",
+            title = escape(&self.subject(data))
+        );
+
+        html.push_str("<h2 style=\"font-size:18px;\">Summary</h2>");
+        let _ = write!(
+            html,
+            "<p>Total Expenses: <strong>{}</strong><br>{}</p>",
+            format_amount(data.total_expenses),
+            escape(&change_text(data))
+        );
+        if !data.outstanding_debts.is_empty() {
+            let _ = write!(html, "<p>{}</p>", escape(&debts_text(data)));
+        }
+
+        let categories = data.sorted_categories();
+        if !categories.is_empty() {
+            html.push_str("<h2 style=\"font-size:18px;\">Category Breakdown</h2>");
+            html.push_str(TABLE_OPEN);
+            for (category, amount) in &categories {
+                let _ = write!(
+                    html,
+                    "<tr><td{CELL}><span style=\"color:{}\">&#9679;</span> {}</td><td{CELL_RIGHT}>{}</td><td{CELL_RIGHT}>{:.1}%</td></tr>",
+                    swatch_color(data, category),
+                    escape(category),
+                    format_amount(*amount),
+                    data.percentage_of_total(*amount)
+                );
+            }
+            html.push_str("</table>");
+        }
+
+        if !data.budget_comparison.is_empty() {
+            let mut budgets: Vec<_> = data.budget_comparison.iter().collect();
+            budgets.sort_by(|a, b| a.0.cmp(b.0));
+            html.push_str("<h2 style=\"font-size:18px;\">Budget Status</h2>");
+            html.push_str(TABLE_OPEN);
+            for (category, budget) in budgets {
+                let _ = write!(
+                    html,
+                    "<tr><td{CELL}>{}</td><td{CELL_RIGHT}>{} / {}</td><td{CELL_RIGHT}>{:.1}%</td>\
+                    <td{CELL} style=\"color:{}\">{}</td></tr>",
+                    escape(category),
+                    format_amount(budget.spent_amount),
+                    format_amount(budget.budget_amount),
+                    budget.percentage_used,
+                    status_color(&budget.status),
+                    status_text(&budget.status)
+                );
+            }
+            html.push_str("</table>");
+        }
+
+        if !data.envelope_totals.is_empty() {
+            html.push_str("<h2 style=\"font-size:18px;\">Trips &amp; Envelopes</h2>");
+            html.push_str(TABLE_OPEN);
+            for envelope in &data.envelope_totals {
+                let _ = write!(
+                    html,
+                    "<tr><td{CELL}>{}</td><td{CELL_RIGHT}>{}</td></tr>",
+                    escape(&envelope.name),
+                    envelope_amount(envelope.spent, envelope.budget)
+                );
+            }
+            html.push_str("</table>");
+        }
+
+        if !data.expense_trend.is_empty() {
+            html.push_str("<h2 style=\"font-size:18px;\">Expense Trend</h2>");
+            html.push_str(TABLE_OPEN);
+            for (month, amount) in &data.expense_trend {
+                let _ = write!(
+                    html,
+                    "<tr><td{CELL}>{}</td><td{CELL_RIGHT}>{}</td></tr>",
+                    escape(month),
+                    format_amount(*amount)
+                );
+            }
+            html.push_str("</table>");
+        }
+
+        html.push_str("</body></html>");
+        html
+    }
+
+    // Plain text version of the same report, used as the email's text part
+    pub fn render_text(&self, data: &MonthlyExpenseData) -> String {
+        let mut text = format!("{}\n\n", self.subject(data));
+        let _ = writeln!(
+            text,
+            "Total Expenses: {}\n{}",
+            format_amount(data.total_expenses),
+            change_text(data)
+        );
+        if !data.outstanding_debts.is_empty() {
+            let _ = writeln!(text, "{}", debts_text(data));
+        }
+
+        let categories = data.sorted_categories();
+        if !categories.is_empty() {
+            text.push_str("\nCategory Breakdown\n");
+            for (category, amount) in &categories {
+                let _ = writeln!(
+                    text,
+                    "- {}: {} ({:.1}%)",
+                    category,
+                    format_amount(*amount),
+                    data.percentage_of_total(*amount)
+                );
+            }
+        }
+
+        if !data.budget_comparison.is_empty() {
+            let mut budgets: Vec<_> = data.budget_comparison.iter().collect();
+            budgets.sort_by(|a, b| a.0.cmp(b.0));
+            text.push_str("\nBudget Status\n");
+            for (category, budget) in budgets {
+                let _ = writeln!(
+                    text,
+                    "- {}: {}/{} ({:.1}%) {}",
+                    category,
+                    format_amount(budget.spent_amount),
+                    format_amount(budget.budget_amount),
+                    budget.percentage_used,
+                    status_text(&budget.status)
+                );
+            }
+        }
+
+        if !data.envelope_totals.is_empty() {
+            text.push_str("\nTrips & Envelopes\n");
+            for envelope in &data.envelope_totals {
+                let _ = writeln!(
+                    text,
+                    "- {}: {}",
+                    envelope.name,
+                    envelope_amount(envelope.spent, envelope.budget)
+                );
+            }
+        }
+
+        if !data.expense_trend.is_empty() {
+            text.push_str("\nExpense Trend\n");
+            for (month, amount) in &data.expense_trend {
+                let _ = writeln!(text, "- {}: {}", month, format_amount(*amount));
+            }
+        }
+
+        text
+    }
+}
+
+const TABLE_OPEN: &str = "<table style=\"border-collapse:collapse;width:100%;\">";
+const CELL: &str = " style=\"padding:4px 8px;border-bottom:1px solid #eee;\"";
+const CELL_RIGHT: &str =
+    " style=\"padding:4px 8px;border-bottom:1px solid #eee;text-align:right;\"";
+
+fn format_amount(amount: Decimal) -> String {
+    format!("Rp. {:.0}", amount)
+}
+
+fn envelope_amount(spent: Decimal, budget: Option<Decimal>) -> String {
+    match budget {
+        Some(budget) => format!("{} / {}", format_amount(spent), format_amount(budget)),
+        None => format_amount(spent),
+    }
+}
+
+fn change_text(data: &MonthlyExpenseData) -> String {
+    let change_percentage = data.change_from_previous();
+    if change_percentage > Decimal::ZERO {
+        format!("+{:.1}% from last month", change_percentage)
+    } else if change_percentage < Decimal::ZERO {
+        format!("{:.1}% from last month", change_percentage)
+    } else {
+        "No change from last month".to_string()
+    }
+}
+
+// Debts with people outside the group, summed in the group's currency
+fn debts_text(data: &MonthlyExpenseData) -> String {
+    let debts = &data.outstanding_debts;
+    let mut text = format!(
+        "Outstanding debts: {} owed to you, {} you owe",
+        format_amount(debts.lent),
+        format_amount(debts.borrowed)
+    );
+    if debts.overdue > 0 {
+        let _ = write!(text, " ({} overdue)", debts.overdue);
+    }
+    text
+}
+
+fn status_text(status: &BudgetStatus) -> &'static str {
+    match status {
+        BudgetStatus::OnTrack => "On track",
+        BudgetStatus::NearLimit => "Near limit",
+        BudgetStatus::OverBudget => "Over budget",
+    }
+}
+
+fn status_color(status: &BudgetStatus) -> &'static str {
+    match status {
+        BudgetStatus::OnTrack => "#2e7d32",
+        BudgetStatus::NearLimit => "#ef6c00",
+        BudgetStatus::OverBudget => "#c62828",
+    }
+}
+
+// The category's color as on the dashboard, only checked colors make it into the style
+fn swatch_color<'a>(data: &'a MonthlyExpenseData, category: &str) -> &'a str {
+    data.category_colors
+        .get(category)
+        .map(String::as_str)
+        .filter(|color| category_style::is_valid_color(color))
+        .unwrap_or(category_style::DEFAULT_COLOR)
+}
+
+// Category names are user input and end up in an email body
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reports::monthly_report::{BudgetComparison, EnvelopeSummary};
+    use crate::repos::debt::OutstandingDebts;
+    use chrono::{TimeZone, Utc};
+    use rust_decimal_macros::dec;
+    use std::collections::HashMap;
+
+    fn data() -> MonthlyExpenseData {
+        MonthlyExpenseData {
+            period_start: Utc.with_ymd_and_hms(2025, 9, 1, 0, 0, 0).unwrap(),
+            period_end: Utc.with_ymd_and_hms(2025, 10, 1, 0, 0, 0).unwrap(),
+            total_expenses: dec!(150000),
+            category_breakdown: HashMap::from([
+                ("Food & <Drinks>".to_string(), dec!(100000)),
+                ("Transport".to_string(), dec!(50000)),
+            ]),
+            category_colors: HashMap::from([("Transport".to_string(), "#2563eb".to_string())]),
+            budget_comparison: HashMap::from([(
+                "Transport".to_string(),
+                BudgetComparison {
+                    budget_amount: dec!(40000),
+                    spent_amount: dec!(50000),
+                    remaining: dec!(-10000),
+                    percentage_used: 125.0,
+                    status: BudgetStatus::OverBudget,
+                },
+            )]),
+            previous_month_total: dec!(100000),
+            expense_trend: vec![
+                ("August 2025".to_string(), dec!(100000)),
+                ("September 2025".to_string(), dec!(150000)),
+            ],
+            envelope_totals: vec![EnvelopeSummary {
+                name: "Bali".to_string(),
+                spent: dec!(2000000),
+                budget: Some(dec!(5000000)),
+            }],
+            outstanding_debts: OutstandingDebts {
+                currency: "IDR".to_string(),
+                lent: dec!(500000),
+                borrowed: Decimal::ZERO,
+                overdue: 1,
+                unconverted_currencies: vec![],
+            },
+        }
+    }
+
+    #[test]
+    fn test_render_html_escapes_categories() {
+        let html = HtmlReportRenderer.render_html(&data());
+        assert!(html.contains("Food &amp; &lt;Drinks&gt;"));
+        assert!(!html.contains("<Drinks>"));
+        assert!(html.contains("Monthly Expense Report - September 2025"));
+        assert!(html.contains("Over budget"));
+        assert!(html.contains("<span style=\"color:#2563eb\">&#9679;</span> Transport"));
+        assert!(html.contains(&format!(
+            "<span style=\"color:{}\">&#9679;</span> Food &amp;",
+            category_style::DEFAULT_COLOR
+        )));
+    }
+
+    #[test]
+    fn test_render_text() {
+        let text = HtmlReportRenderer.render_text(&data());
+        assert!(text.contains("Total Expenses: Rp. 150000\n+50.0% from last month"));
+        // Largest category first
+        assert!(
+            text.find("Food & <Drinks>: Rp. 100000").unwrap()
+                < text.find("Transport: Rp. 50000").unwrap()
+        );
+        assert!(text.contains("- Transport: Rp. 50000/Rp. 40000 (125.0%) Over budget"));
+        assert!(text.contains("- September 2025: Rp. 150000"));
+        // Envelopes stay out of the regular total
+        assert!(text.contains("- Bali: Rp. 2000000 / Rp. 5000000"));
+        assert!(
+            text.contains("Outstanding debts: Rp. 500000 owed to you, Rp. 0 you owe (1 overdue)")
+        );
+    }
+}
@@ -1,27 +1,90 @@
-use chrono::{DateTime, Datelike, Duration, Utc};
+use chrono::{DateTime, Datelike, Utc};
+use plotters::prelude::{
+    BLACK, BitMapBackend, Color as _, IntoDrawingArea, Polygon as ChartPolygon, RGBColor,
+    Rectangle, WHITE,
+};
 use printpdf::*;
+use rust_decimal::{Decimal, prelude::ToPrimitive};
 use sqlx::PgPool;
 use std::collections::HashMap;
 use std::io::BufWriter;
-
-use crate::repos::{budget::BudgetRepo, category::CategoryRepo, expense_entry::ExpenseEntryRepo};
+use std::time::Instant;
+
+use super::renderer::ReportRenderer;
+use crate::repos::{
+    budget::{BudgetPeriodType, BudgetRepo},
+    category::CategoryRepo,
+    debt::{DebtRepo, OutstandingDebts},
+    envelope::EnvelopeRepo,
+    exchange_rate::ExchangeRateRepo,
+    expense_entry::ExpenseEntryRepo,
+    expense_group::ExpenseGroupRepo,
+};
+use crate::telemetry;
+use crate::utils::{category_style, period::BillingPeriod};
 
 #[derive(Debug)]
 pub struct MonthlyExpenseData {
     pub period_start: DateTime<Utc>,
     pub period_end: DateTime<Utc>,
-    pub total_expenses: f64,
-    pub category_breakdown: HashMap<String, f64>,
+    pub total_expenses: Decimal,
+    pub category_breakdown: HashMap<String, Decimal>,
+    // `#rrggbb` of the categories in the breakdown, so charts match the dashboard
+    pub category_colors: HashMap<String, String>,
     pub budget_comparison: HashMap<String, BudgetComparison>,
-    pub previous_month_total: f64,
-    pub expense_trend: Vec<(String, f64)>, // Last 6 months
+    pub previous_month_total: Decimal,
+    pub expense_trend: Vec<(String, Decimal)>, // Last 6 months
+    // Envelopes (e.g. trips) overlapping the period, kept out of the totals above
+    pub envelope_totals: Vec<EnvelopeSummary>,
+    // Debts of the whole group still open when the report is made
+    pub outstanding_debts: OutstandingDebts,
+}
+
+impl MonthlyExpenseData {
+    // Change against the previous period in percent, zero when nothing was spent before
+    pub fn change_from_previous(&self) -> Decimal {
+        if self.previous_month_total > Decimal::ZERO {
+            ((self.total_expenses - self.previous_month_total) / self.previous_month_total)
+                * Decimal::ONE_HUNDRED
+        } else {
+            Decimal::ZERO
+        }
+    }
+
+    // Share of the total in percent
+    pub fn percentage_of_total(&self, amount: Decimal) -> Decimal {
+        if self.total_expenses > Decimal::ZERO {
+            (amount / self.total_expenses) * Decimal::ONE_HUNDRED
+        } else {
+            Decimal::ZERO
+        }
+    }
+
+    // Categories with their totals, largest first
+    pub fn sorted_categories(&self) -> Vec<(String, Decimal)> {
+        let mut categories: Vec<(String, Decimal)> = self
+            .category_breakdown
+            .iter()
+            .map(|(category, amount)| (category.clone(), *amount))
+            .collect();
+        categories.sort_by(|a, b| b.1.cmp(&a.1));
+        categories
+    }
+}
+
+#[derive(Debug)]
+pub struct EnvelopeSummary {
+    pub name: String,
+    // Everything spent in the envelope so far, not only during the period
+    pub spent: Decimal,
+    pub budget: Option<Decimal>,
 }
 
 #[derive(Debug)]
 pub struct BudgetComparison {
-    pub budget_amount: f64,
-    pub spent_amount: f64,
-    pub remaining: f64,
+    pub budget_amount: Decimal,
+    pub spent_amount: Decimal,
+    pub remaining: Decimal,
     pub percentage_used: f64,
     pub status: BudgetStatus,
 }
@@ -49,66 +112,118 @@ impl MonthlyReportGenerator {
         user_uid: uuid::Uuid,
         start_over_date: i16,
     ) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
-        // Calculate current month period
-        let (current_start, current_end) = self.calculate_month_range(start_over_date);
+        self.generate_report_with(&PdfReportRenderer, group_uid, user_uid, start_over_date)
+            .await
+    }
 
-        // Gather all data
+    // Same report as `generate_monthly_report`, in the format of the given renderer
+    pub async fn generate_report_with(
+        &self,
+        renderer: &dyn ReportRenderer,
+        group_uid: uuid::Uuid,
+        user_uid: uuid::Uuid,
+        start_over_date: i16,
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
         let expense_data = self
-            .gather_expense_data(group_uid, user_uid, current_start, current_end)
+            .monthly_data(group_uid, user_uid, start_over_date)
             .await?;
+        let started = Instant::now();
+        let rendered = renderer.render(&expense_data);
+        telemetry::record_report("render", started);
+        rendered
+    }
 
-        // Generate PDF
-        let pdf_bytes = self.create_pdf_report(expense_data).await?;
-
-        Ok(pdf_bytes)
+    // Data of the current period's report, to be rendered once per output format
+    pub async fn monthly_data(
+        &self,
+        group_uid: uuid::Uuid,
+        user_uid: uuid::Uuid,
+        start_over_date: i16,
+    ) -> Result<MonthlyExpenseData, Box<dyn std::error::Error + Send + Sync>> {
+        // Calculate current month period
+        let period = BillingPeriod::current(start_over_date);
+        let started = Instant::now();
+        let data = self.gather_expense_data(group_uid, user_uid, period).await;
+        telemetry::record_report("monthly", started);
+        data
     }
 
     async fn gather_expense_data(
         &self,
         group_uid: uuid::Uuid,
         user_uid: uuid::Uuid,
-        current_start: DateTime<Utc>,
-        current_end: DateTime<Utc>,
+        period: BillingPeriod,
     ) -> Result<MonthlyExpenseData, Box<dyn std::error::Error + Send + Sync>> {
+        let (current_start, current_end) = (period.start_utc(), period.end_utc());
         let mut tx = self.db_pool.begin().await?;
 
-        // Get current month expenses
-        let current_expenses = ExpenseEntryRepo::list_by_group(&mut tx, group_uid).await?;
-        let mut category_breakdown = HashMap::new();
-        let mut total_expenses = 0.0;
-
-        for expense in current_expenses {
-            if expense.created_by == user_uid.to_string()
-                && expense.created_at >= current_start
-                && expense.created_at < current_end
-            {
-                let category_uid = match expense.category_uid {
-                    Some(uid) => uid,
-                    None => continue, // Skip if no category
-                };
-                let category = CategoryRepo::get(&mut tx, category_uid).await?;
-                let category_name = category.name;
-
-                *category_breakdown.entry(category_name).or_insert(0.0) += expense.price;
-                total_expenses += expense.price;
-            }
+        // Current period per category, everything is summed in SQL; envelope spending is
+        // reported on its own below
+        let mut category_breakdown: HashMap<String, Decimal> = HashMap::new();
+        let mut total_expenses = Decimal::ZERO;
+        let totals = ExpenseEntryRepo::totals_by_category_in_range(
+            &mut tx,
+            group_uid,
+            Some(user_uid),
+            None,
+            false,
+            current_start,
+            current_end,
+        )
+        .await?;
+        for (category, _currency, amount) in totals {
+            let Some(category) = category else {
+                continue; // Skip if no category
+            };
+            *category_breakdown.entry(category).or_default() += amount;
+            total_expenses += amount;
         }
 
-        // Get budget information
-        let budgets = BudgetRepo::list_by_group(&mut tx, group_uid).await?;
+        // Get budget information; a budget pinned to this period replaces the recurring one.
+        // Budgets on subcategories are left out, their spending is part of the parent's total,
+        // and so are budgets over other periods than the cycle
+        let top_level: Vec<_> = CategoryRepo::list_by_group(&mut tx, group_uid)
+            .await?
+            .into_iter()
+            .filter(|category| category.parent_uid.is_none())
+            .collect();
+        let category_colors: HashMap<_, _> = top_level
+            .iter()
+            .map(|category| (category.name.clone(), category.color.clone()))
+            .collect();
+        let category_names: HashMap<_, _> = top_level
+            .into_iter()
+            .map(|category| (category.uid, category.name))
+            .collect();
+        let mut budgets: Vec<_> = BudgetRepo::list_by_group(&mut tx, group_uid)
+            .await?
+            .into_iter()
+            .filter(|budget| budget.period_type() == BudgetPeriodType::Monthly)
+            .collect();
+        budgets.sort_by_key(|budget| budget.period_year.is_some());
         let mut budget_comparison = HashMap::new();
 
         for budget in budgets {
-            let category = CategoryRepo::get(&mut tx, budget.category_uid).await?;
-            let spent = category_breakdown.get(&category.name).unwrap_or(&0.0);
-            let remaining = budget.amount - spent;
-            let percentage = if budget.amount > 0.0 {
-                (spent / budget.amount) * 100.0
-            } else {
-                0.0
+            let pinned_elsewhere = match (budget.period_year, budget.period_month) {
+                (Some(year), Some(month)) => {
+                    (year, month as u32) != (period.start.year(), period.start.month())
+                }
+                _ => false,
             };
+            let Some(category_name) = category_names.get(&budget.category_uid) else {
+                continue;
+            };
+            if pinned_elsewhere {
+                continue;
+            }
+            let spent = category_breakdown
+                .get(category_name)
+                .copied()
+                .unwrap_or_default();
+            let remaining = budget.amount - spent;
+            let percentage = budget.percentage_used(spent);
 
-            let status = if remaining < 0.0 {
+            let status = if remaining < Decimal::ZERO {
                 BudgetStatus::OverBudget
             } else if percentage >= 80.0 {
                 BudgetStatus::NearLimit
@@ -117,10 +232,10 @@ impl MonthlyReportGenerator {
             };
 
             budget_comparison.insert(
-                category.name,
+                category_name.clone(),
                 BudgetComparison {
                     budget_amount: budget.amount,
-                    spent_amount: *spent,
+                    spent_amount: spent,
                     remaining,
                     percentage_used: percentage,
                     status,
@@ -128,43 +243,55 @@ impl MonthlyReportGenerator {
             );
         }
 
-        // Get previous month total
-        let previous_month_start = current_start - Duration::days(30);
-        let previous_month_end = current_start;
-
-        let previous_expenses = ExpenseEntryRepo::list_by_group(&mut tx, group_uid).await?;
-        let mut previous_total = 0.0;
-
-        for expense in previous_expenses {
-            if expense.created_by == user_uid.to_string()
-                && expense.created_at >= previous_month_start
-                && expense.created_at < previous_month_end
-            {
-                previous_total += expense.price;
+        // Expense trend (last 6 periods, oldest first) in one query; the previous period
+        // total is the second to last window
+        let trend_periods: Vec<BillingPeriod> =
+            (0..6).rev().map(|i| period.nth_previous(i)).collect();
+        let windows: Vec<_> = trend_periods
+            .iter()
+            .map(|period| (period.start_utc(), period.end_utc()))
+            .collect();
+        let window_totals =
+            ExpenseEntryRepo::sum_by_windows(&mut tx, group_uid, Some(user_uid), &windows).await?;
+        let previous_total = window_totals[window_totals.len() - 2];
+        let expense_trend = trend_periods
+            .iter()
+            .zip(window_totals)
+            .map(|(period, total)| {
+                let month_name = format!("{} {}", period.start.format("%B"), period.start.year());
+                (month_name, total)
+            })
+            .collect();
+
+        let mut envelope_totals = Vec::new();
+        let envelopes = EnvelopeRepo::list_overlapping(
+            &mut tx,
+            group_uid,
+            period.start,
+            period.end.pred_opt().unwrap_or(period.end),
+        )
+        .await?;
+        for envelope in envelopes {
+            let spent: Decimal =
+                EnvelopeRepo::totals_by_category(&mut tx, envelope.uid, Some(user_uid))
+                    .await?
+                    .into_iter()
+                    .map(|(_, _, amount)| amount)
+                    .sum();
+            if spent > Decimal::ZERO {
+                envelope_totals.push(EnvelopeSummary {
+                    name: envelope.name,
+                    spent,
+                    budget: envelope.budget,
+                });
             }
         }
 
-        // Get expense trend (last 6 months)
-        let mut expense_trend = Vec::new();
-        for i in (0..6).rev() {
-            let month_start = current_start - Duration::days(30 * i);
-            let month_end = month_start + Duration::days(30);
-
-            let month_expenses = ExpenseEntryRepo::list_by_group(&mut tx, group_uid).await?;
-            let mut month_total = 0.0;
-
-            for expense in month_expenses {
-                if expense.created_by == user_uid.to_string()
-                    && expense.created_at >= month_start
-                    && expense.created_at < month_end
-                {
-                    month_total += expense.price;
-                }
-            }
-
-            let month_name = format!("{} {}", month_start.format("%B"), month_start.year());
-            expense_trend.push((month_name, month_total));
-        }
+        let group = ExpenseGroupRepo::get(&mut tx, group_uid).await?;
+        let rates = ExchangeRateRepo::rate_table(&mut tx).await?;
+        let debts = DebtRepo::list_by_group(&mut tx, group_uid, false).await?;
+        let outstanding_debts =
+            OutstandingDebts::of(&debts, &rates, &group.currency, Utc::now().date_naive());
 
         tx.commit().await?;
 
@@ -173,21 +300,46 @@ impl MonthlyReportGenerator {
             period_end: current_end,
             total_expenses,
             category_breakdown,
+            category_colors,
             budget_comparison,
             previous_month_total: previous_total,
             expense_trend,
+            envelope_totals,
+            outstanding_debts,
         })
     }
+}
+
+#[derive(Clone, Copy)]
+pub struct PdfReportRenderer;
 
-    async fn create_pdf_report(
+impl ReportRenderer for PdfReportRenderer {
+    fn content_type(&self) -> &'static str {
+        "application/pdf"
+    }
+
+    fn file_extension(&self) -> &'static str {
+        "pdf"
+    }
+
+    fn render(
+        &self,
+        data: &MonthlyExpenseData,
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+        self.create_pdf_report(data)
+    }
+}
+
+impl PdfReportRenderer {
+    fn create_pdf_report(
         &self,
-        data: MonthlyExpenseData,
+        data: &MonthlyExpenseData,
     ) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
         // Create PDF document
         let (doc, page1, layer1) = PdfDocument::new(
             "Monthly Expense Report",
-            Mm(210.0), // A4 width
-            Mm(297.0), // A4 height
+            Mm(PAGE_WIDTH),  // A4 width
+            Mm(PAGE_HEIGHT), // A4 height
             "Layer 1",
         );
 
@@ -208,64 +360,81 @@ impl MonthlyReportGenerator {
 
         // Add summary section
         let font_regular = doc.add_builtin_font(BuiltinFont::Helvetica)?;
-        let mut y_position = 250.0;
-
-        current_layer.use_text("Summary", 18.0, Mm(20.0), Mm(y_position), &font);
-        y_position -= 15.0;
+        let mut cursor = PdfCursor::new(&doc, current_layer, 250.0);
 
-        current_layer.use_text(
+        cursor.text("Summary", 18.0, 20.0, &font, 15.0);
+        cursor.text(
             &format!("Total Expenses: Rp. {:.0}", data.total_expenses),
             12.0,
-            Mm(25.0),
-            Mm(y_position),
+            25.0,
             &font_regular,
+            10.0,
         );
-        y_position -= 10.0;
 
-        let change_percentage = if data.previous_month_total > 0.0 {
-            ((data.total_expenses - data.previous_month_total) / data.previous_month_total) * 100.0
-        } else {
-            0.0
-        };
+        let change_percentage = data.change_from_previous();
 
-        let change_text = if change_percentage > 0.0 {
+        let change_text = if change_percentage > Decimal::ZERO {
             format!("↗️ +{:.1}% from last month", change_percentage)
-        } else if change_percentage < 0.0 {
+        } else if change_percentage < Decimal::ZERO {
             format!("↘️ {:.1}% from last month", change_percentage)
         } else {
             "→ No change from last month".to_string()
         };
 
-        current_layer.use_text(&change_text, 12.0, Mm(25.0), Mm(y_position), &font_regular);
-        y_position -= 20.0;
+        cursor.text(&change_text, 12.0, 25.0, &font_regular, 10.0);
 
-        // Add category breakdown
-        current_layer.use_text("Category Breakdown", 16.0, Mm(20.0), Mm(y_position), &font);
-        y_position -= 15.0;
+        // Debts with people outside the group, not part of the spending above
+        let debts = &data.outstanding_debts;
+        if !debts.is_empty() {
+            let mut debts_text = format!(
+                "Outstanding debts: Rp. {:.0} owed to you, Rp. {:.0} you owe",
+                debts.lent, debts.borrowed
+            );
+            if debts.overdue > 0 {
+                debts_text.push_str(&format!(" ({} overdue)", debts.overdue));
+            }
+            cursor.text(&debts_text, 12.0, 25.0, &font_regular, 10.0);
+        }
+        cursor.y -= 10.0;
+
+        // Add category breakdown, largest first, with a pie chart of the biggest ones
+        cursor.text("Category Breakdown", 16.0, 20.0, &font, 15.0);
+
+        let categories = data.sorted_categories();
+
+        let slices = chart_slices(&categories);
+        if !slices.is_empty() {
+            let chart = self.generate_category_chart(&slices, &data.category_colors)?;
+            let top = cursor.image(chart, 25.0, 10.0);
+            // Legend names line up with the swatches drawn next to the pie
+            for (index, (category, amount)) in slices.iter().enumerate() {
+                cursor.layer.use_text(
+                    format!("{} (Rp. {:.0})", category, amount),
+                    9.0,
+                    Mm(25.0 + px_to_mm(LEGEND_TEXT_X)),
+                    Mm(top - px_to_mm(LEGEND_TOP + LEGEND_ROW * index as u32 + SWATCH_SIZE)),
+                    &font_regular,
+                );
+            }
+        }
 
-        for (category, amount) in &data.category_breakdown {
-            let percentage = if data.total_expenses > 0.0 {
-                (amount / data.total_expenses) * 100.0
-            } else {
-                0.0
-            };
+        for (category, amount) in &categories {
+            let percentage = data.percentage_of_total(*amount);
 
-            current_layer.use_text(
+            cursor.text(
                 &format!("{}: Rp. {:.0} ({:.1}%)", category, amount, percentage),
                 12.0,
-                Mm(25.0),
-                Mm(y_position),
+                25.0,
                 &font_regular,
+                10.0,
             );
-            y_position -= 10.0;
         }
 
-        y_position -= 10.0;
+        cursor.y -= 10.0;
 
         // Add budget comparison
         if !data.budget_comparison.is_empty() {
-            current_layer.use_text("Budget Status", 16.0, Mm(20.0), Mm(y_position), &font);
-            y_position -= 15.0;
+            cursor.text("Budget Status", 16.0, 20.0, &font, 15.0);
 
             for (category, budget) in &data.budget_comparison {
                 let status_text = match budget.status {
@@ -274,7 +443,7 @@ impl MonthlyReportGenerator {
                     BudgetStatus::OverBudget => "❌ Over budget",
                 };
 
-                current_layer.use_text(
+                cursor.text(
                     &format!(
                         "{}: Rp. {:.0}/Rp. {:.0} ({:.1}%) {}",
                         category,
@@ -284,19 +453,55 @@ impl MonthlyReportGenerator {
                         status_text
                     ),
                     12.0,
-                    Mm(25.0),
-                    Mm(y_position),
+                    25.0,
                     &font_regular,
+                    10.0,
                 );
-                y_position -= 10.0;
             }
+
+            cursor.y -= 10.0;
         }
 
-        // Generate and add chart
-        if y_position > 100.0 {
-            let _chart_image = self.generate_expense_chart(&data.expense_trend)?;
-            // Note: In a real implementation, you'd embed the chart image in the PDF
-            // This is a simplified version
+        // Envelopes are outside the regular cycle, listed apart from the totals above
+        if !data.envelope_totals.is_empty() {
+            cursor.text("Trips & Envelopes", 16.0, 20.0, &font, 15.0);
+
+            for envelope in &data.envelope_totals {
+                let text = match envelope.budget {
+                    Some(budget) => format!(
+                        "{}: Rp. {:.0}/Rp. {:.0}",
+                        envelope.name, envelope.spent, budget
+                    ),
+                    None => format!("{}: Rp. {:.0}", envelope.name, envelope.spent),
+                };
+                cursor.text(&text, 12.0, 25.0, &font_regular, 10.0);
+            }
+
+            cursor.y -= 10.0;
+        }
+
+        // Add the 6-month trend, labels go under each bar
+        if !data.expense_trend.is_empty() {
+            cursor.text("Expense Trend", 16.0, 20.0, &font, 15.0);
+            let chart = self.generate_expense_chart(&data.expense_trend)?;
+            let slot_width = px_to_mm(chart.width) / data.expense_trend.len() as f32;
+            // Keep the labels on the same page as the bars
+            cursor.reserve(px_to_mm(chart.height) + 15.0);
+            cursor.image(chart, 25.0, 5.0);
+            for (index, (month, amount)) in data.expense_trend.iter().enumerate() {
+                let x = 25.0 + slot_width * index as f32;
+                cursor
+                    .layer
+                    .use_text(month.as_str(), 8.0, Mm(x), Mm(cursor.y), &font_regular);
+                cursor.layer.use_text(
+                    format!("Rp. {:.0}", amount),
+                    8.0,
+                    Mm(x),
+                    Mm(cursor.y - 4.0),
+                    &font_regular,
+                );
+            }
+            cursor.y -= 10.0;
         }
 
         // Save PDF to bytes
@@ -309,52 +514,279 @@ impl MonthlyReportGenerator {
         Ok(bytes)
     }
 
+    // One bar per month, the current one highlighted
     fn generate_expense_chart(
         &self,
-        _expense_trend: &[(String, f64)],
-    ) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
-        // Simplified chart generation - in a real implementation,
-        // you'd use a proper bitmap backend or external service
-        // For now, return empty bytes
-        Ok(Vec::new())
+        expense_trend: &[(String, Decimal)],
+    ) -> Result<ChartImage, Box<dyn std::error::Error + Send + Sync>> {
+        let (width, height) = TREND_CHART_SIZE;
+        let mut pixels = vec![0; (width * height * 3) as usize];
+        {
+            let root = BitMapBackend::with_buffer(&mut pixels, (width, height)).into_drawing_area();
+            root.fill(&WHITE)?;
+
+            let max = expense_trend
+                .iter()
+                .map(|(_, amount)| amount.to_f64().unwrap_or_default())
+                .fold(0.0, f64::max);
+            let slot = width as i32 / expense_trend.len().max(1) as i32;
+            let bar_width = slot * 3 / 5;
+            let baseline = height as i32 - 4;
+            for (index, (_, amount)) in expense_trend.iter().enumerate() {
+                if max <= 0.0 {
+                    // Nothing spent in any month, only the axis is drawn
+                    break;
+                }
+                let bar_height =
+                    (amount.to_f64().unwrap_or_default() / max * (baseline - 10) as f64) as i32;
+                let left = slot * index as i32 + (slot - bar_width) / 2;
+                let color = if index + 1 == expense_trend.len() {
+                    CHART_COLORS[0]
+                } else {
+                    CHART_COLORS[CHART_COLORS.len() - 1]
+                };
+                root.draw(&Rectangle::new(
+                    [(left, baseline - bar_height), (left + bar_width, baseline)],
+                    color.filled(),
+                ))?;
+            }
+            root.draw(&Rectangle::new(
+                [(0, baseline), (width as i32, height as i32)],
+                BLACK.filled(),
+            ))?;
+            root.present()?;
+        }
+
+        Ok(ChartImage {
+            width,
+            height,
+            pixels,
+        })
     }
 
-    fn calculate_month_range(&self, start_over_date: i16) -> (DateTime<Utc>, DateTime<Utc>) {
-        let now = Utc::now();
-        let current_year = now.year();
-        let current_month = now.month();
+    // Pie of the given slices with a color swatch per slice, names are added by the PDF
+    fn generate_category_chart(
+        &self,
+        slices: &[(String, Decimal)],
+        colors: &HashMap<String, String>,
+    ) -> Result<ChartImage, Box<dyn std::error::Error + Send + Sync>> {
+        let (width, height) = CATEGORY_CHART_SIZE;
+        let mut pixels = vec![0; (width * height * 3) as usize];
+        {
+            let root = BitMapBackend::with_buffer(&mut pixels, (width, height)).into_drawing_area();
+            root.fill(&WHITE)?;
+
+            let total: f64 = slices
+                .iter()
+                .map(|(_, amount)| amount.to_f64().unwrap_or_default())
+                .sum();
+            let center = (height as i32 / 2, height as i32 / 2);
+            let radius = height as f64 / 2.0 - 10.0;
+            // Start at 12 o'clock and go clockwise
+            let mut start = -std::f64::consts::FRAC_PI_2;
+            for (index, (category, amount)) in slices.iter().enumerate() {
+                let color = slice_color(category, index, colors);
+                if total > 0.0 {
+                    let sweep = amount.to_f64().unwrap_or_default() / total * std::f64::consts::TAU;
+                    let steps = (sweep.to_degrees().ceil() as usize).max(1);
+                    let mut points = vec![center];
+                    points.extend((0..=steps).map(|step| {
+                        let angle = start + sweep * step as f64 / steps as f64;
+                        (
+                            center.0 + (radius * angle.cos()).round() as i32,
+                            center.1 + (radius * angle.sin()).round() as i32,
+                        )
+                    }));
+                    root.draw(&ChartPolygon::new(points, color.filled()))?;
+                    start += sweep;
+                }
 
-        // Calculate the start date based on start_over_date
-        let start_day = start_over_date as u32;
-        let mut start_date = if current_month == 1 {
-            // January - go back to previous year
-            chrono::NaiveDate::from_ymd_opt(current_year - 1, 12, start_day)
-        } else {
-            chrono::NaiveDate::from_ymd_opt(current_year, current_month - 1, start_day)
-        }
-        .unwrap_or_else(|| {
-            chrono::NaiveDate::from_ymd_opt(current_year, current_month, 1).unwrap()
-        });
-
-        // If the calculated start date is in the future, use the previous month's start date
-        if start_date > now.date_naive() {
-            start_date = if current_month == 1 {
-                chrono::NaiveDate::from_ymd_opt(current_year - 1, 11, start_day)
-            } else if current_month == 2 {
-                chrono::NaiveDate::from_ymd_opt(current_year - 1, 12, start_day)
-            } else {
-                chrono::NaiveDate::from_ymd_opt(current_year, current_month - 2, start_day)
+                let top = (LEGEND_TOP + LEGEND_ROW * index as u32) as i32;
+                let left = LEGEND_SWATCH_X as i32;
+                root.draw(&Rectangle::new(
+                    [
+                        (left, top),
+                        (left + SWATCH_SIZE as i32, top + SWATCH_SIZE as i32),
+                    ],
+                    color.filled(),
+                ))?;
             }
-            .unwrap_or_else(|| {
-                chrono::NaiveDate::from_ymd_opt(current_year, current_month - 1, 1).unwrap()
-            });
+            root.present()?;
+        }
+
+        Ok(ChartImage {
+            width,
+            height,
+            pixels,
+        })
+    }
+}
+
+const PAGE_WIDTH: f32 = 210.0;
+const PAGE_HEIGHT: f32 = 297.0;
+// Where content starts on continuation pages and how low it may go
+const PAGE_TOP: f32 = 280.0;
+const PAGE_BOTTOM: f32 = 20.0;
+
+// Charts are rendered as bitmaps, sizes below are in pixels at this resolution
+const CHART_DPI: f32 = 300.0;
+const TREND_CHART_SIZE: (u32, u32) = (1800, 600);
+const CATEGORY_CHART_SIZE: (u32, u32) = (1800, 600);
+const LEGEND_SWATCH_X: u32 = 680;
+const LEGEND_TEXT_X: u32 = 760;
+const LEGEND_TOP: u32 = 20;
+const LEGEND_ROW: u32 = 70;
+const SWATCH_SIZE: u32 = 40;
+
+// The last color is kept for the "Other" slice and past months in the trend
+const CHART_COLORS: [RGBColor; 8] = [
+    RGBColor(59, 130, 246),
+    RGBColor(16, 185, 129),
+    RGBColor(245, 158, 11),
+    RGBColor(239, 68, 68),
+    RGBColor(139, 92, 246),
+    RGBColor(236, 72, 153),
+    RGBColor(20, 184, 166),
+    RGBColor(156, 163, 175),
+];
+
+/*
+ * Slices of the category pie chart from categories sorted by amount: the biggest ones
+ * get their own color and the rest are merged into "Other", so the legend fits next to
+ * the pie. Categories without spending are left out.
+ */
+fn chart_slices(categories: &[(String, Decimal)]) -> Vec<(String, Decimal)> {
+    let categories: Vec<_> = categories
+        .iter()
+        .filter(|(_, amount)| *amount > Decimal::ZERO)
+        .cloned()
+        .collect();
+    if categories.len() <= CHART_COLORS.len() {
+        return categories;
+    }
+    let named = CHART_COLORS.len() - 1;
+    let other: Decimal = categories[named..].iter().map(|(_, amount)| *amount).sum();
+    let mut slices = categories[..named].to_vec();
+    slices.push(("Other".to_string(), other));
+    slices
+}
+
+// The category's own color, or one of the chart colors for "Other" and unknown names
+fn slice_color(category: &str, index: usize, colors: &HashMap<String, String>) -> RGBColor {
+    colors
+        .get(category)
+        .and_then(|color| category_style::rgb(color))
+        .map(|(r, g, b)| RGBColor(r, g, b))
+        .unwrap_or(CHART_COLORS[index % CHART_COLORS.len()])
+}
+
+fn px_to_mm(px: u32) -> f32 {
+    px as f32 * 25.4 / CHART_DPI
+}
+
+// Raw RGB pixels of a rendered chart
+struct ChartImage {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+// Writes content top to bottom, starting a new page when the current one is full
+struct PdfCursor<'a> {
+    doc: &'a PdfDocumentReference,
+    layer: PdfLayerReference,
+    y: f32,
+}
+
+impl<'a> PdfCursor<'a> {
+    fn new(doc: &'a PdfDocumentReference, layer: PdfLayerReference, y: f32) -> Self {
+        Self { doc, layer, y }
+    }
+
+    fn reserve(&mut self, height: f32) {
+        if self.y - height < PAGE_BOTTOM {
+            let (page, layer) = self
+                .doc
+                .add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+            self.layer = self.doc.get_page(page).get_layer(layer);
+            self.y = PAGE_TOP;
         }
+    }
 
-        let end_date = start_date + Duration::days(30); // Approximate month length
+    fn text(&mut self, text: &str, size: f32, x: f32, font: &IndirectFontRef, advance: f32) {
+        self.reserve(advance);
+        self.layer.use_text(text, size, Mm(x), Mm(self.y), font);
+        self.y -= advance;
+    }
 
-        (
-            start_date.and_hms_opt(0, 0, 0).unwrap().and_utc(),
-            end_date.and_hms_opt(23, 59, 59).unwrap().and_utc(),
-        )
+    // Places the chart below the cursor and returns the y of its top edge
+    fn image(&mut self, chart: ChartImage, x: f32, spacing: f32) -> f32 {
+        let height = px_to_mm(chart.height);
+        self.reserve(height + spacing);
+        let top = self.y;
+        Image::from(ImageXObject {
+            width: Px(chart.width as usize),
+            height: Px(chart.height as usize),
+            color_space: ColorSpace::Rgb,
+            bits_per_component: ColorBits::Bit8,
+            interpolate: true,
+            image_data: chart.pixels,
+            image_filter: None,
+            smask: None,
+            clipping_bbox: None,
+        })
+        .add_to_layer(
+            self.layer.clone(),
+            ImageTransform {
+                translate_x: Some(Mm(x)),
+                translate_y: Some(Mm(top - height)),
+                dpi: Some(CHART_DPI),
+                ..Default::default()
+            },
+        );
+        self.y = top - height - spacing;
+        top
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn categories(count: usize) -> Vec<(String, Decimal)> {
+        (0..count)
+            .map(|i| (format!("Category {}", i), Decimal::from(100 - i as i64)))
+            .collect()
+    }
+
+    #[test]
+    fn test_chart_slices_merges_small_categories() {
+        assert_eq!(chart_slices(&categories(3)).len(), 3);
+
+        let slices = chart_slices(&categories(10));
+        assert_eq!(slices.len(), CHART_COLORS.len());
+        assert_eq!(slices[0], ("Category 0".to_string(), dec!(100)));
+        // Categories 7, 8 and 9
+        assert_eq!(
+            slices.last().unwrap(),
+            &("Other".to_string(), dec!(93) + dec!(92) + dec!(91))
+        );
+    }
+
+    #[test]
+    fn test_slice_color() {
+        let colors = HashMap::from([("Food".to_string(), "#f97316".to_string())]);
+        assert_eq!(slice_color("Food", 3, &colors), RGBColor(249, 115, 22));
+        assert_eq!(slice_color("Other", 1, &colors), CHART_COLORS[1]);
+    }
+
+    #[test]
+    fn test_chart_slices_skips_empty_categories() {
+        let slices = chart_slices(&[
+            ("Food".to_string(), dec!(10)),
+            ("Travel".to_string(), Decimal::ZERO),
+        ]);
+        assert_eq!(slices, vec![("Food".to_string(), dec!(10))]);
     }
 }
@@ -0,0 +1,13 @@
+use super::monthly_report::MonthlyExpenseData;
+
+// Output format of a monthly report, the data is gathered once and rendered by each
+pub trait ReportRenderer: Send + Sync {
+    fn content_type(&self) -> &'static str;
+
+    fn file_extension(&self) -> &'static str;
+
+    fn render(
+        &self,
+        data: &MonthlyExpenseData,
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>>;
+}
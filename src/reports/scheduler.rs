@@ -1,144 +1,253 @@
-use chrono::{Utc, Timelike, Datelike};
-use std::sync::Arc;
+use chrono::{Utc, Timelike};
+use std::time::Instant;
 use tokio_cron_scheduler::{Job, JobScheduler};
 use sqlx::PgPool;
+use uuid::Uuid;
 
+use crate::email::EmailSender;
+use crate::error::DatabaseError;
+use crate::jobs::queue::{JobQueue, JobTask};
+use crate::lang::Lang;
 use crate::repos::{
     user::UserRepo,
     expense_group::ExpenseGroupRepo,
     expense_group_member::GroupMemberRepo,
     chat_binding::ChatBindingRepo,
+    notification_settings::NotificationSettingsRepo,
     subscription::UserUsageRepo,
 };
 use crate::messengers::MessengerManager;
-use super::monthly_report::MonthlyReportGenerator;
+use crate::shutdown::Shutdown;
+use crate::telemetry;
+use super::digest::build_digest;
+use super::html_report::HtmlReportRenderer;
+use super::monthly_report::{MonthlyExpenseData, MonthlyReportGenerator, PdfReportRenderer};
+use super::renderer::ReportRenderer;
+use crate::utils::period::BillingPeriod;
 
+/*
+ Decides which reports are due and queues them, `JobWorker` sends them so a failed delivery
+ is retried instead of lost.
+*/
 pub struct ReportScheduler {
     db_pool: PgPool,
-    messenger_manager: Arc<MessengerManager>,
-    report_generator: MonthlyReportGenerator,
+    lang: Lang,
 }
 
 impl ReportScheduler {
-    pub fn new(
-        db_pool: PgPool,
-        messenger_manager: Arc<MessengerManager>,
-    ) -> Self {
-        let report_generator = MonthlyReportGenerator::new(db_pool.clone());
-        Self {
-            db_pool,
-            messenger_manager,
-            report_generator,
-        }
+    pub fn new(db_pool: PgPool, lang: Lang) -> Self {
+        Self { db_pool, lang }
     }
 
-    pub async fn start(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    pub async fn start(&self, shutdown: &Shutdown) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let sched = JobScheduler::new().await?;
 
         // Schedule job to run every hour to check for reports to send
         let db_pool = self.db_pool.clone();
-        let messenger_manager = self.messenger_manager.clone();
-        let report_generator = self.report_generator.clone();
+        let report_shutdown = shutdown.clone();
 
-        let report_job = Job::new_async("0 * * * * *", move |_, _| {
+        let report_job = Job::new_async("0 0 * * * *", move |_, _| {
             let db_pool = db_pool.clone();
-            let messenger_manager = messenger_manager.clone();
-            let report_generator = report_generator.clone();
+            let running = report_shutdown.track();
 
             Box::pin(async move {
-                if let Err(e) = Self::check_and_send_reports(
-                    db_pool,
-                    messenger_manager,
-                    report_generator,
-                ).await {
-                    tracing::error!("Error sending monthly reports: {:?}", e);
+                let Some(_running) = running else {
+                    return;
+                };
+                if let Err(e) = Self::enqueue_monthly_reports(&db_pool).await {
+                    tracing::error!("Error queueing monthly reports: {:?}", e);
                 }
             })
         })?;
 
         // Schedule job to run daily at 2 AM to update usage statistics
         let db_pool_usage = self.db_pool.clone();
-        let usage_job = Job::new_async("0 2 * * * *", move |_, _| {
+        let usage_shutdown = shutdown.clone();
+        let usage_job = Job::new_async("0 0 2 * * *", move |_, _| {
             let db_pool = db_pool_usage.clone();
+            let running = usage_shutdown.track();
 
             Box::pin(async move {
+                let Some(_running) = running else {
+                    return;
+                };
                 if let Err(e) = Self::update_usage_statistics(db_pool).await {
                     tracing::error!("Error updating usage statistics: {:?}", e);
                 }
             })
         })?;
 
+        // Daily and weekly digests, checked hourly against each group's notification settings
+        let db_pool_digest = self.db_pool.clone();
+        let lang = self.lang.clone();
+        let digest_shutdown = shutdown.clone();
+        let digest_job = Job::new_async("0 0 * * * *", move |_, _| {
+            let db_pool = db_pool_digest.clone();
+            let lang = lang.clone();
+            let running = digest_shutdown.track();
+
+            Box::pin(async move {
+                let Some(_running) = running else {
+                    return;
+                };
+                if let Err(e) = Self::enqueue_digests(&db_pool, &lang).await {
+                    tracing::error!("Error queueing digests: {:?}", e);
+                }
+            })
+        })?;
+
         sched.add(report_job).await?;
         sched.add(usage_job).await?;
+        sched.add(digest_job).await?;
         sched.start().await?;
+        shutdown.stop_with(sched);
 
         tracing::info!("Report scheduler and usage tracker started");
         Ok(())
     }
 
-    async fn check_and_send_reports(
-        db_pool: PgPool,
-        messenger_manager: Arc<MessengerManager>,
-        report_generator: MonthlyReportGenerator,
+    // Queues the report of every member of the groups whose period starts now
+    async fn enqueue_monthly_reports(
+        db_pool: &PgPool,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let mut tx = db_pool.begin().await?;
-
-        // Get all users
         let groups = ExpenseGroupRepo::list(&mut tx).await?;
+        let group_members = GroupMemberRepo::list(&mut tx).await?;
 
+        let mut queued = 0;
         for group in groups {
-            // Check if it's time to send the monthly report for this group
-            if Self::should_send_report(group.start_over_date) {
-                // Get group members
-                let group_members = GroupMemberRepo::list(&mut tx).await?;
-                let current_group_members: Vec<_> = group_members
+            if !Self::should_send_report(group.start_over_date) {
+                continue;
+            }
+            for group_member in group_members.iter().filter(|gm| gm.group_uid == group.uid) {
+                JobQueue::enqueue(
+                    &mut tx,
+                    &JobTask::MonthlyReport {
+                        group_uid: group.uid,
+                        user_uid: group_member.user_uid,
+                    },
+                )
+                .await?;
+                queued += 1;
+            }
+        }
+
+        tx.commit().await?;
+        if queued > 0 {
+            tracing::info!("Queued {} monthly reports", queued);
+        }
+        Ok(())
+    }
+
+    /*
+     Sends the monthly report of a group member, run by `JobWorker` for a queued
+     `JobTask::MonthlyReport`. An error fails the attempt so the report is retried.
+    */
+    pub async fn send_monthly_report(
+        db_pool: &PgPool,
+        messenger_manager: &MessengerManager,
+        email_sender: &(dyn EmailSender + Send + Sync),
+        report_generator: &MonthlyReportGenerator,
+        group_uid: Uuid,
+        user_uid: Uuid,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut tx = db_pool.begin().await?;
+        let group = match ExpenseGroupRepo::get(&mut tx, group_uid).await {
+            Ok(group) => group,
+            // Deleted since the report was queued, there is nothing to send
+            Err(DatabaseError::NotFound(_)) => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        let chat_bindings = ChatBindingRepo::list(&mut tx).await?;
+        let active_binding = chat_bindings
+            .into_iter()
+            .find(|cb| cb.group_uid == group_uid && cb.status == "active");
+        let user = UserRepo::get(&mut tx, user_uid).await?;
+        tx.commit().await?;
+
+        let expense_data = report_generator
+            .monthly_data(group_uid, user_uid, group.start_over_date)
+            .await?;
+
+        let Some(binding) = active_binding else {
+            // Groups without a chat binding get the report by email
+            return Self::email_report(email_sender, &user.email, &expense_data).await;
+        };
+
+        // Generate and send report
+        let _pdf_bytes = PdfReportRenderer.render(&expense_data)?;
+        let message = format!(
+            "📊 Your monthly expense report for {} is ready!",
+            Utc::now().format("%B %Y")
+        );
+
+        // Note: In a real implementation, you'd need to modify the messenger
+        // to support sending files/documents. For now, we'll just send the message.
+        messenger_manager
+            .send_message(&binding.platform, &binding.p_uid, &message)
+            .await
+    }
+
+    async fn email_report(
+        email_sender: &(dyn EmailSender + Send + Sync),
+        to: &str,
+        expense_data: &MonthlyExpenseData,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let renderer = HtmlReportRenderer;
+        email_sender
+            .send_html(
+                to,
+                &renderer.subject(expense_data),
+                &renderer.render_html(expense_data),
+                &renderer.render_text(expense_data),
+            )
+            .await
+    }
+
+    // Builds the due digests and queues one message per chat of the group
+    async fn enqueue_digests(
+        db_pool: &PgPool,
+        lang: &Lang,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let now = Utc::now();
+        let today = now.date_naive();
+        let mut tx = db_pool.begin().await?;
+        let bindings = ChatBindingRepo::list(&mut tx).await?;
+
+        // Queued in the transaction marking them sent, so a digest is neither lost nor doubled
+        for settings in NotificationSettingsRepo::list_enabled(&mut tx).await? {
+            for kind in settings.due_digests(now) {
+                if !NotificationSettingsRepo::mark_sent(&mut tx, settings.group_uid, kind, today)
+                    .await?
+                {
+                    continue;
+                }
+                let group = match ExpenseGroupRepo::get(&mut tx, settings.group_uid).await {
+                    Ok(group) => group,
+                    // Soft-deleted groups keep their settings until they are purged
+                    Err(DatabaseError::NotFound(_)) => continue,
+                    Err(e) => return Err(e.into()),
+                };
+                let lang = lang.preferred(group.lang.as_deref());
+                let started = Instant::now();
+                let digest = build_digest(&mut tx, &group, kind, today, &lang).await?;
+                telemetry::record_report("digest", started);
+                let Some(message) = digest else {
+                    continue;
+                };
+                for binding in bindings
                     .iter()
-                    .filter(|gm| gm.group_uid == group.uid)
-                    .collect();
-
-                for group_member in current_group_members {
-                    // Check if group has active chat binding
-                    let chat_bindings = ChatBindingRepo::list(&mut tx).await?;
-                    let active_binding = chat_bindings
-                        .iter()
-                        .find(|cb| cb.group_uid == group_member.group_uid && cb.status == "active");
-
-                    if let Some(binding) = active_binding {
-                        // Generate and send report
-                        match report_generator.generate_monthly_report(
-                            group_member.group_uid,
-                            group_member.user_uid,
-                            group.start_over_date,
-                        ).await {
-                            Ok(_pdf_bytes) => {
-                                let _filename = format!(
-                                    "monthly_report_{}_{}.pdf",
-                                    group_member.user_uid,
-                                    Utc::now().format("%Y_%m")
-                                );
-
-                                let message = format!(
-                                    "📊 Your monthly expense report for {} is ready!",
-                                    Utc::now().format("%B %Y")
-                                );
-
-                                // Send PDF via Telegram
-                                if let Err(e) = messenger_manager.send_message(
-                                    &binding.platform,
-                                    &binding.p_uid,
-                                    &message,
-                                ).await {
-                                    tracing::error!("Failed to send monthly report message: {:?}", e);
-                                }
-
-                                // Note: In a real implementation, you'd need to modify the messenger
-                                // to support sending files/documents. For now, we'll just send the message.
-                            }
-                            Err(e) => {
-                                tracing::error!("Failed to generate monthly report for user {}: {:?}", group_member.user_uid, e);
-                            }
-                        }
-                    }
+                    .filter(|b| b.group_uid == group.uid && b.status == "active")
+                {
+                    JobQueue::enqueue(
+                        &mut tx,
+                        &JobTask::ChatMessage {
+                            platform: binding.platform.clone(),
+                            chat_id: binding.p_uid.clone(),
+                            text: message.clone(),
+                        },
+                    )
+                    .await?;
                 }
             }
         }
@@ -180,10 +289,10 @@ impl ReportScheduler {
 
     fn should_send_report(start_over_date: i16) -> bool {
         let now = Utc::now();
-        let current_day = now.day() as i16;
         let current_hour = now.hour();
 
-        // Send report on the start_over_date at 9 AM
-        current_day == start_over_date && current_hour == 9
+        // Send report on the day the period starts at 9 AM, which is the last day of
+        // the month when start_over_date is past the end of it
+        BillingPeriod::current(start_over_date).start == now.date_naive() && current_hour == 9
     }
 }
\ No newline at end of file
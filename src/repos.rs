@@ -1,11 +1,47 @@
+pub mod admin;
+pub mod api_key;
+pub mod audit_log;
 pub mod base;
+pub mod billing_event;
 pub mod budget;
+pub mod budget_alert;
+pub mod budget_plan;
 pub mod category;
 pub mod category_alias;
+pub mod chat_action;
 pub mod chat_bind_request;
 pub mod chat_binding;
+pub mod chat_conversation;
+pub mod chat_link_code;
+pub mod chat_member_link;
+pub mod closed_period;
+pub mod debt;
+pub mod email_ingest_address;
+pub mod exchange_rate;
+pub mod expense_draft;
 pub mod expense_entry;
 pub mod expense_group;
 pub mod expense_group_member;
+pub mod group_invite;
+pub mod group_settings;
+pub mod envelope;
+pub mod income_entry;
+pub mod job;
+pub mod notification_settings;
+pub mod overview;
+pub mod password_reset_token;
+pub mod pending_chat_expense;
+pub mod product;
+pub mod receipt;
+pub mod reconciliation;
+pub mod recurring_expense;
+pub mod refresh_token;
+pub mod settlement;
+pub mod spending_anomaly;
+pub mod slack_workspace;
 pub mod subscription;
+pub mod tag;
+pub mod tier_violation;
 pub mod user;
+pub mod user_mfa;
+pub mod webhook;
@@ -0,0 +1,104 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::DatabaseError;
+use crate::types::SubscriptionTier;
+use crate::utils::pii;
+
+#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct PlatformStats {
+    pub total_users: i64,
+    pub admin_users: i64,
+    pub active_groups: i64,
+    /// Soft-deleted groups waiting to be purged
+    pub deleted_groups: i64,
+    /// Entries created since the start of the current month (UTC)
+    pub expense_entries_this_month: i64,
+    pub active_chat_bindings: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct TierCount {
+    pub tier: SubscriptionTier,
+    pub users: i64,
+}
+
+// A group as seen by an admin, soft-deleted groups included
+#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct GroupInspection {
+    pub uid: Uuid,
+    pub name: String,
+    pub owner: Uuid,
+    pub owner_email: String,
+    pub currency: String,
+    pub start_over_date: i16,
+    pub created_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    /// The owner included
+    pub member_count: i64,
+    pub expense_entry_count: i64,
+    pub last_expense_at: Option<DateTime<Utc>>,
+    pub active_chat_binding_count: i64,
+}
+
+/*
+ Read-only queries across every group and user for the admin API. They bypass the group
+ guards, so only routes behind `AdminGuard` may call them.
+*/
+pub struct AdminRepo;
+
+impl AdminRepo {
+    pub async fn platform_stats(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<PlatformStats, DatabaseError> {
+        let row = sqlx::query_as::<_, PlatformStats>(
+            "SELECT \
+                (SELECT COUNT(*) FROM users) AS total_users, \
+                (SELECT COUNT(*) FROM users WHERE role = 'admin') AS admin_users, \
+                (SELECT COUNT(*) FROM expense_groups WHERE deleted_at IS NULL) AS active_groups, \
+                (SELECT COUNT(*) FROM expense_groups WHERE deleted_at IS NOT NULL) AS deleted_groups, \
+                (SELECT COUNT(*) FROM expense_entries WHERE deleted_at IS NULL AND created_at >= date_trunc('month', now())) AS expense_entries_this_month, \
+                (SELECT COUNT(*) FROM chat_bindings WHERE status = 'active') AS active_chat_bindings",
+        )
+        .fetch_one(tx.as_mut())
+        .await
+        .map_err(|e| DatabaseError::from_sqlx_error(e, "getting platform stats"))?;
+        Ok(row)
+    }
+
+    // Users per tier of their active subscription
+    pub async fn users_by_tier(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<Vec<TierCount>, DatabaseError> {
+        let rows = sqlx::query_as::<_, TierCount>(
+            "SELECT tier, COUNT(DISTINCT user_uid) AS users FROM subscriptions WHERE status = 'active' GROUP BY tier ORDER BY tier",
+        )
+        .fetch_all(tx.as_mut())
+        .await
+        .map_err(|e| DatabaseError::from_sqlx_error(e, "counting users by tier"))?;
+        Ok(rows)
+    }
+
+    pub async fn inspect_group(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+    ) -> Result<GroupInspection, DatabaseError> {
+        let mut row = sqlx::query_as::<_, GroupInspection>(
+            "SELECT g.uid, g.name, g.owner, u.email AS owner_email, g.currency, g.start_over_date, g.created_at, g.deleted_at, \
+                (SELECT COUNT(*) + 1 FROM group_members gm WHERE gm.group_uid = g.uid AND gm.user_uid <> g.owner) AS member_count, \
+                (SELECT COUNT(*) FROM expense_entries e WHERE e.group_uid = g.uid AND e.deleted_at IS NULL) AS expense_entry_count, \
+                (SELECT MAX(e.created_at) FROM expense_entries e WHERE e.group_uid = g.uid AND e.deleted_at IS NULL) AS last_expense_at, \
+                (SELECT COUNT(*) FROM chat_bindings cb WHERE cb.group_uid = g.uid AND cb.status = 'active') AS active_chat_binding_count \
+            FROM expense_groups g JOIN users u ON u.uid = g.owner WHERE g.uid = $1",
+        )
+        .bind(group_uid)
+        .fetch_one(tx.as_mut())
+        .await
+        .map_err(|e| DatabaseError::from_sqlx_error(e, "inspecting group"))?;
+        row.owner_email = pii::decrypt(&row.owner_email)?;
+        Ok(row)
+    }
+}
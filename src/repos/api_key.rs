@@ -0,0 +1,225 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::DatabaseError;
+use crate::repos::base::BaseRepo;
+use crate::utils::secret_token::{generate_token, hash_token};
+
+const API_KEY_COLUMNS: &str =
+    "uid, user_uid, name, key_hash, key_prefix, scope, last_used_at, revoked_at, created_at";
+
+// Marks the string as an API key, e.g. for secret scanners
+pub const API_KEY_PREFIX: &str = "etk_";
+// Characters of the key kept in `key_prefix`, including `API_KEY_PREFIX`
+const STORED_PREFIX_LEN: usize = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiKeyScope {
+    // Requests that change nothing, such as GET and HEAD
+    ReadOnly,
+    // Everything the user can do, except managing API keys
+    ReadWrite,
+}
+
+impl ApiKeyScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ReadOnly => "read_only",
+            Self::ReadWrite => "read_write",
+        }
+    }
+
+    pub fn parse(input: &str) -> Option<Self> {
+        match input.trim().to_lowercase().as_str() {
+            "read_only" => Some(Self::ReadOnly),
+            "read_write" => Some(Self::ReadWrite),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct ApiKey {
+    pub uid: Uuid,
+    pub user_uid: Uuid,
+    pub name: String,
+    #[serde(skip_serializing, default)]
+    pub key_hash: String,
+    /// First characters of the key, e.g. `etk_1a2b3c4d`
+    pub key_prefix: String,
+    /// `read_only` or `read_write`
+    pub scope: String,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ApiKey {
+    pub fn scope(&self) -> ApiKeyScope {
+        ApiKeyScope::parse(&self.scope).unwrap_or(ApiKeyScope::ReadOnly)
+    }
+}
+
+#[derive(Debug)]
+pub struct CreateApiKeyDbPayload {
+    pub user_uid: Uuid,
+    pub name: String,
+    pub scope: ApiKeyScope,
+}
+
+pub struct ApiKeyRepo;
+
+impl BaseRepo for ApiKeyRepo {
+    fn get_table_name() -> &'static str {
+        "api_keys"
+    }
+}
+
+impl ApiKeyRepo {
+    // Returns the stored row together with the plain key, which is only available here
+    pub async fn create(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        payload: CreateApiKeyDbPayload,
+    ) -> Result<(ApiKey, String), DatabaseError> {
+        let key = format!("{}{}", API_KEY_PREFIX, generate_token());
+        let query = format!(
+            "INSERT INTO {} (user_uid, name, key_hash, key_prefix, scope) VALUES ($1, $2, $3, $4, $5) RETURNING {API_KEY_COLUMNS}",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, ApiKey>(&query)
+            .bind(payload.user_uid)
+            .bind(payload.name)
+            .bind(hash_token(&key))
+            .bind(&key[..STORED_PREFIX_LEN])
+            .bind(payload.scope.as_str())
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "creating api key"))?;
+        Ok((row, key))
+    }
+
+    // Newest first, revoked keys included
+    pub async fn list_by_user(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        user_uid: Uuid,
+    ) -> Result<Vec<ApiKey>, DatabaseError> {
+        let query = format!(
+            "SELECT {API_KEY_COLUMNS} FROM {} WHERE user_uid = $1 ORDER BY created_at DESC",
+            Self::get_table_name()
+        );
+        let rows = sqlx::query_as::<_, ApiKey>(&query)
+            .bind(user_uid)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "listing api keys"))?;
+        Ok(rows)
+    }
+
+    pub async fn count_active_by_user(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        user_uid: Uuid,
+    ) -> Result<i64, DatabaseError> {
+        let query = format!(
+            "SELECT COUNT(*) FROM {} WHERE user_uid = $1 AND revoked_at IS NULL",
+            Self::get_table_name()
+        );
+        let count = sqlx::query_scalar::<_, i64>(&query)
+            .bind(user_uid)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "counting api keys"))?;
+        Ok(count)
+    }
+
+    // None for unknown and revoked keys
+    pub async fn find_active_by_key(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        key: &str,
+    ) -> Result<Option<ApiKey>, DatabaseError> {
+        let query = format!(
+            "SELECT {API_KEY_COLUMNS} FROM {} WHERE key_hash = $1 AND revoked_at IS NULL",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, ApiKey>(&query)
+            .bind(hash_token(key))
+            .fetch_optional(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "getting api key by key"))?;
+        Ok(row)
+    }
+
+    // Written at most once a minute per key, so busy scripts do not update the row on every call
+    pub async fn touch(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+    ) -> Result<(), DatabaseError> {
+        let query = format!(
+            "UPDATE {} SET last_used_at = now() WHERE uid = $1 AND (last_used_at IS NULL OR last_used_at < now() - interval '1 minute')",
+            Self::get_table_name()
+        );
+        sqlx::query(&query)
+            .bind(uid)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "updating api key last use"))?;
+        Ok(())
+    }
+
+    // NotFound when the user has no such key, revoking twice is a no-op
+    pub async fn revoke(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+        user_uid: Uuid,
+    ) -> Result<ApiKey, DatabaseError> {
+        let query = format!(
+            "UPDATE {} SET revoked_at = COALESCE(revoked_at, now()) WHERE uid = $1 AND user_uid = $2 RETURNING {API_KEY_COLUMNS}",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, ApiKey>(&query)
+            .bind(uid)
+            .bind(user_uid)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "revoking api key"))?;
+        Ok(row)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_scope() {
+        assert_eq!(
+            ApiKeyScope::parse(" Read_Only "),
+            Some(ApiKeyScope::ReadOnly)
+        );
+        assert_eq!(
+            ApiKeyScope::parse("read_write"),
+            Some(ApiKeyScope::ReadWrite)
+        );
+        assert_eq!(ApiKeyScope::parse("admin"), None);
+    }
+
+    #[test]
+    fn test_key_hash_is_not_serialized() {
+        let key = ApiKey {
+            uid: Uuid::new_v4(),
+            user_uid: Uuid::new_v4(),
+            name: "Spreadsheet".to_string(),
+            key_hash: hash_token("key"),
+            key_prefix: "etk_1a2b3c4d".to_string(),
+            scope: "read_only".to_string(),
+            last_used_at: None,
+            revoked_at: None,
+            created_at: Utc::now(),
+        };
+        let value = serde_json::to_value(&key).unwrap();
+        assert!(value.get("key_hash").is_none());
+        assert_eq!(value["key_prefix"], "etk_1a2b3c4d");
+    }
+}
@@ -0,0 +1,295 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::auth::{AuthContext, AuthSource};
+use crate::commands::base::ChatSender;
+use crate::error::DatabaseError;
+use crate::repos::base::BaseRepo;
+use crate::repos::chat_binding::ChatBinding;
+use crate::utils::cursor::Cursor;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditEntity {
+    ExpenseGroup,
+    ExpenseEntry,
+    IncomeEntry,
+    RecurringExpense,
+    Budget,
+    BudgetPlan,
+    Category,
+    CategoryAlias,
+    Tag,
+    Envelope,
+    Settlement,
+    Debt,
+    GroupMember,
+    // Logged with the group's uid as the entity
+    ClosedPeriod,
+}
+
+impl AuditEntity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ExpenseGroup => "expense_group",
+            Self::ExpenseEntry => "expense_entry",
+            Self::IncomeEntry => "income_entry",
+            Self::RecurringExpense => "recurring_expense",
+            Self::Budget => "budget",
+            Self::BudgetPlan => "budget_plan",
+            Self::Category => "category",
+            Self::CategoryAlias => "category_alias",
+            Self::Tag => "tag",
+            Self::Envelope => "envelope",
+            Self::Settlement => "settlement",
+            Self::Debt => "debt",
+            Self::GroupMember => "group_member",
+            Self::ClosedPeriod => "closed_period",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    Create,
+    Update,
+    Delete,
+    Restore,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Create => "create",
+            Self::Update => "update",
+            Self::Delete => "delete",
+            Self::Restore => "restore",
+        }
+    }
+}
+
+// Who made a change and through which channel (`web`, `chat` for the relay API, or the chat platform)
+#[derive(Debug, Clone)]
+pub struct AuditActor {
+    pub user_uid: Option<Uuid>,
+    pub name: Option<String>,
+    pub source: String,
+}
+
+impl AuditActor {
+    pub fn from_auth(auth: &AuthContext) -> Self {
+        let source = match auth.source {
+            AuthSource::Web => "web",
+            AuthSource::Chat => "chat",
+        };
+        Self {
+            user_uid: Some(auth.user_uid),
+            name: None,
+            source: source.to_string(),
+        }
+    }
+
+    pub fn from_chat(binding: &ChatBinding, sender: &ChatSender) -> Self {
+        Self {
+            user_uid: sender.resolve_user_uid(binding),
+            name: Some(sender.name.clone()),
+            source: binding.platform.clone(),
+        }
+    }
+}
+
+// Snapshot of an entity before and after a change, serialized as JSON
+#[derive(Debug, Clone)]
+pub struct AuditChange {
+    pub action: AuditAction,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+}
+
+impl AuditChange {
+    pub fn create<T: Serialize>(after: &T) -> Self {
+        Self::new(AuditAction::Create, None, Some(after))
+    }
+
+    pub fn update<T: Serialize>(before: &T, after: &T) -> Self {
+        Self::new(AuditAction::Update, Some(before), Some(after))
+    }
+
+    pub fn delete<T: Serialize>(before: &T) -> Self {
+        Self::new(AuditAction::Delete, Some(before), None)
+    }
+
+    pub fn restore<T: Serialize>(after: &T) -> Self {
+        Self::new(AuditAction::Restore, None, Some(after))
+    }
+
+    fn new<T: Serialize>(action: AuditAction, before: Option<&T>, after: Option<&T>) -> Self {
+        // Repo structs always serialize, a failure would only lose the snapshot
+        let to_value = |v: &T| serde_json::to_value(v).ok();
+        Self {
+            action,
+            before: before.and_then(to_value),
+            after: after.and_then(to_value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct AuditLog {
+    pub uid: Uuid,
+    pub group_uid: Uuid,
+    pub actor_user_uid: Option<Uuid>,
+    pub actor_name: Option<String>,
+    pub source: String,
+    pub entity_type: String,
+    pub entity_uid: Uuid,
+    pub action: String,
+    #[schema(value_type = Option<Object>)]
+    pub before: Option<serde_json::Value>,
+    #[schema(value_type = Option<Object>)]
+    pub after: Option<serde_json::Value>,
+    pub created_at: DateTime<Utc>,
+}
+
+pub struct AuditRepo;
+
+impl BaseRepo for AuditRepo {
+    fn get_table_name() -> &'static str {
+        "audit_logs"
+    }
+}
+
+impl AuditRepo {
+    pub async fn record(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        actor: &AuditActor,
+        entity: AuditEntity,
+        group_uid: Uuid,
+        entity_uid: Uuid,
+        change: AuditChange,
+    ) -> Result<AuditLog, DatabaseError> {
+        let query = format!(
+            "INSERT INTO {} (uid, group_uid, actor_user_uid, actor_name, source, entity_type, entity_uid, action, before, after) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10) RETURNING uid, group_uid, actor_user_uid, actor_name, source, entity_type, entity_uid, action, before, after, created_at",
+            Self::get_table_name()
+        );
+        let rec = sqlx::query_as::<_, AuditLog>(&query)
+            .bind(Uuid::new_v4())
+            .bind(group_uid)
+            .bind(actor.user_uid)
+            .bind(actor.name.as_deref())
+            .bind(&actor.source)
+            .bind(entity.as_str())
+            .bind(entity_uid)
+            .bind(change.action.as_str())
+            .bind(change.before)
+            .bind(change.after)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "recording audit log"))?;
+        Ok(rec)
+    }
+
+    // Newest first, together with the total count for pagination. With `after` the page starts
+    // below that cursor instead of at `offset`, the total still counts every log of the group
+    pub async fn list_by_group(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+        limit: i64,
+        offset: i64,
+        after: Option<Cursor>,
+    ) -> Result<(Vec<AuditLog>, i64), DatabaseError> {
+        let query = format!(
+            "SELECT uid, group_uid, actor_user_uid, actor_name, source, entity_type, entity_uid, action, before, after, created_at FROM {} WHERE group_uid = $1 AND ($4::timestamptz IS NULL OR (created_at, uid) < ($4, $5)) ORDER BY created_at DESC, uid DESC LIMIT $2 OFFSET $3",
+            Self::get_table_name()
+        );
+        let recs = sqlx::query_as::<_, AuditLog>(&query)
+            .bind(group_uid)
+            .bind(limit)
+            .bind(offset)
+            .bind(after.map(|c| c.created_at))
+            .bind(after.map(|c| c.uid))
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "listing audit logs by group"))?;
+
+        let count_query = format!(
+            "SELECT COUNT(*) FROM {} WHERE group_uid = $1",
+            Self::get_table_name()
+        );
+        let total: i64 = sqlx::query_scalar(&count_query)
+            .bind(group_uid)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "counting audit logs by group"))?;
+        Ok((recs, total))
+    }
+
+    // Like `list_by_group`, only the logs of `entities`
+    pub async fn list_by_group_and_entities(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+        entities: &[AuditEntity],
+        limit: i64,
+        offset: i64,
+        after: Option<Cursor>,
+    ) -> Result<(Vec<AuditLog>, i64), DatabaseError> {
+        let entity_types: Vec<&str> = entities.iter().map(|entity| entity.as_str()).collect();
+        let query = format!(
+            "SELECT uid, group_uid, actor_user_uid, actor_name, source, entity_type, entity_uid, action, before, after, created_at FROM {} WHERE group_uid = $1 AND entity_type = ANY($6) AND ($4::timestamptz IS NULL OR (created_at, uid) < ($4, $5)) ORDER BY created_at DESC, uid DESC LIMIT $2 OFFSET $3",
+            Self::get_table_name()
+        );
+        let recs = sqlx::query_as::<_, AuditLog>(&query)
+            .bind(group_uid)
+            .bind(limit)
+            .bind(offset)
+            .bind(after.map(|c| c.created_at))
+            .bind(after.map(|c| c.uid))
+            .bind(&entity_types)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| {
+                DatabaseError::from_sqlx_error(e, "listing audit logs by group and entities")
+            })?;
+
+        let count_query = format!(
+            "SELECT COUNT(*) FROM {} WHERE group_uid = $1 AND entity_type = ANY($2)",
+            Self::get_table_name()
+        );
+        let total: i64 = sqlx::query_scalar(&count_query)
+            .bind(group_uid)
+            .bind(&entity_types)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| {
+                DatabaseError::from_sqlx_error(e, "counting audit logs by group and entities")
+            })?;
+        Ok((recs, total))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Entity {
+        name: &'static str,
+    }
+
+    #[test]
+    fn test_change_snapshots() {
+        let before = Entity { name: "old" };
+        let after = Entity { name: "new" };
+
+        let change = AuditChange::update(&before, &after);
+        assert_eq!(change.action.as_str(), "update");
+        assert_eq!(change.before, Some(serde_json::json!({ "name": "old" })));
+        assert_eq!(change.after, Some(serde_json::json!({ "name": "new" })));
+
+        let change = AuditChange::delete(&before);
+        assert_eq!(change.action, AuditAction::Delete);
+        assert!(change.after.is_none());
+    }
+}
@@ -0,0 +1,34 @@
+use crate::error::DatabaseError;
+use crate::repos::base::BaseRepo;
+
+// Ids of processed payment provider webhooks, providers deliver an event at least once
+pub struct BillingEventRepo;
+
+impl BaseRepo for BillingEventRepo {
+    fn get_table_name() -> &'static str {
+        "billing_events"
+    }
+}
+
+impl BillingEventRepo {
+    // Returns false when the event was already recorded, it must not be applied again
+    pub async fn record(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        provider: &str,
+        event_id: &str,
+        event_type: &str,
+    ) -> Result<bool, DatabaseError> {
+        let query = format!(
+            "INSERT INTO {} (provider, event_id, event_type) VALUES ($1, $2, $3) ON CONFLICT (provider, event_id) DO NOTHING",
+            Self::get_table_name()
+        );
+        let result = sqlx::query(&query)
+            .bind(provider)
+            .bind(event_id)
+            .bind(event_type)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "recording billing event"))?;
+        Ok(result.rows_affected() == 1)
+    }
+}
@@ -1,3 +1,7 @@
+use std::collections::HashMap;
+
+use chrono::{Duration, NaiveDate};
+use rust_decimal::{Decimal, prelude::ToPrimitive};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use utoipa::ToSchema;
@@ -5,31 +9,224 @@ use uuid::Uuid;
 
 use crate::error::DatabaseError;
 use crate::repos::base::BaseRepo;
+use crate::utils::period::{self, BillingPeriod};
+
+const BUDGET_COLUMNS: &str = "uid, group_uid, category_uid, amount, currency, period_type::text AS period_type, period_year, period_month, range_start, range_end, hard_limit";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BudgetPeriodType {
+    Weekly,
+    Monthly,
+    Quarterly,
+    Yearly,
+    // A single range with its own start and end
+    Custom,
+}
+
+impl BudgetPeriodType {
+    // Accepts both the stored names and the Indonesian words used in chat
+    pub fn parse(input: &str) -> Option<Self> {
+        match input.trim().to_lowercase().as_str() {
+            "weekly" | "mingguan" => Some(Self::Weekly),
+            "monthly" | "bulanan" => Some(Self::Monthly),
+            "quarterly" | "kuartalan" | "triwulan" => Some(Self::Quarterly),
+            "yearly" | "tahunan" => Some(Self::Yearly),
+            "custom" => Some(Self::Custom),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Weekly => "weekly",
+            Self::Monthly => "monthly",
+            Self::Quarterly => "quarterly",
+            Self::Yearly => "yearly",
+            Self::Custom => "custom",
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for BudgetPeriodType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        BudgetPeriodType::parse(&s)
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid period type: {}", s)))
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
 pub struct Budget {
     pub uid: Uuid,
     pub group_uid: Uuid,
     pub category_uid: Uuid,
-    pub amount: f64,
+    pub amount: Decimal,
+    pub currency: String,
+    /// `weekly`, `monthly`, `quarterly`, `yearly` or `custom`
+    pub period_type: String, // from enum via ::text
     pub period_year: Option<i32>,
     pub period_month: Option<i32>,
+    /// Start of a `custom` budget's range
+    pub range_start: Option<NaiveDate>,
+    /// End of a `custom` budget's range, exclusive
+    pub range_end: Option<NaiveDate>,
+    // Spending past the amount is flagged or held back when recorded, not only reported
+    pub hard_limit: bool,
+}
+
+impl Budget {
+    pub fn period_type(&self) -> BudgetPeriodType {
+        BudgetPeriodType::parse(&self.period_type).unwrap_or(BudgetPeriodType::Monthly)
+    }
+
+    // Covers a single period: a monthly budget pinned to a cycle, or a custom range
+    pub fn is_pinned(&self) -> bool {
+        self.period_month.is_some() || self.period_type() == BudgetPeriodType::Custom
+    }
+
+    /*
+     Date range the budget is tracked against, as [start, end). Weekly budgets follow the
+     group's weeks starting on `week_start`, quarterly and yearly ones the group's cycles
+     grouped from January, and monthly ones the current cycle, or the cycle of their
+     period_year/period_month when pinned. Custom budgets cover their own range.
+    */
+    pub fn period_range(
+        &self,
+        start_over_date: i16,
+        week_start: i16,
+        today: NaiveDate,
+    ) -> (NaiveDate, NaiveDate) {
+        match (self.period_type(), self.range_start, self.range_end) {
+            (BudgetPeriodType::Custom, Some(start), Some(end)) => (start, end),
+            (BudgetPeriodType::Weekly, ..) => {
+                let start = period::week_start_of(today, week_start);
+                (start, start + Duration::days(7))
+            }
+            (BudgetPeriodType::Quarterly, ..) => {
+                period::cycles_containing(today, start_over_date, 3)
+            }
+            (BudgetPeriodType::Yearly, ..) => period::cycles_containing(today, start_over_date, 12),
+            _ => {
+                let period = match (self.period_year, self.period_month) {
+                    (Some(year), Some(month)) if (1..=12).contains(&month) => {
+                        BillingPeriod::for_month(year, month as u32, start_over_date)
+                    }
+                    _ => BillingPeriod::containing(today, start_over_date),
+                };
+                (period.start, period.end)
+            }
+        }
+    }
+
+    // Share of the budget already spent, in percent rounded to 2 decimals; 0 for empty budgets
+    pub fn percentage_used(&self, spent: Decimal) -> f64 {
+        if self.amount <= Decimal::ZERO {
+            return 0.0;
+        }
+        (spent / self.amount * Decimal::ONE_HUNDRED)
+            .round_dp(2)
+            .to_f64()
+            .unwrap_or_default()
+    }
+
+    /*
+     The budget each category is tracked against on `today`, out of `budgets`. Pinned budgets
+     whose period is not running are skipped, a pinned budget running today takes precedence
+     over the recurring one.
+    */
+    pub fn active_by_category(
+        budgets: Vec<Budget>,
+        start_over_date: i16,
+        week_start: i16,
+        today: NaiveDate,
+    ) -> HashMap<Uuid, Budget> {
+        let mut active: HashMap<Uuid, Budget> = HashMap::new();
+        for budget in budgets {
+            let (period_start, period_end) =
+                budget.period_range(start_over_date, week_start, today);
+            if !(period_start..period_end).contains(&today) {
+                continue;
+            }
+            let pinned = budget.is_pinned();
+            match active.get(&budget.category_uid) {
+                Some(existing) if existing.is_pinned() || !pinned => {}
+                _ => {
+                    active.insert(budget.category_uid, budget);
+                }
+            }
+        }
+        active
+    }
+}
+
+// Where spending ends up if it keeps the pace of the period so far
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpendProjection {
+    /// Days of the period that have started, today included
+    pub days_elapsed: i64,
+    pub days_in_period: i64,
+    pub daily_burn_rate: Decimal,
+    pub projected_total: Decimal,
+}
+
+impl SpendProjection {
+    pub fn new(
+        spent: Decimal,
+        period_start: NaiveDate,
+        period_end: NaiveDate,
+        today: NaiveDate,
+    ) -> Self {
+        let days_in_period = (period_end - period_start).num_days().max(1);
+        let days_elapsed = ((today - period_start).num_days() + 1).clamp(0, days_in_period);
+        if days_elapsed == 0 {
+            return Self {
+                days_elapsed,
+                days_in_period,
+                daily_burn_rate: Decimal::ZERO,
+                projected_total: spent,
+            };
+        }
+        let daily_burn_rate = spent / Decimal::from(days_elapsed);
+        Self {
+            days_elapsed,
+            days_in_period,
+            daily_burn_rate: daily_burn_rate.round_dp(2),
+            projected_total: (daily_burn_rate * Decimal::from(days_in_period)).round_dp(2),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
 pub struct CreateBudgetDbPayload {
     pub group_uid: Uuid,
     pub category_uid: Uuid,
-    pub amount: f64,
+    pub amount: Decimal,
+    // Defaults to the group's currency when not provided
+    pub currency: Option<String>,
+    pub period_type: BudgetPeriodType,
+    // Only for monthly budgets
     pub period_year: Option<i32>,
     pub period_month: Option<i32>,
+    // Only for custom budgets, which need both
+    pub range_start: Option<NaiveDate>,
+    pub range_end: Option<NaiveDate>,
+    pub hard_limit: bool,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct UpdateBudgetDbPayload {
-    pub amount: Option<f64>,
+    pub amount: Option<Decimal>,
+    pub currency: Option<String>,
+    // Switching to another period type drops what only applied to the previous one
+    pub period_type: Option<BudgetPeriodType>,
     pub period_year: Option<i32>,
     pub period_month: Option<i32>,
+    pub range_start: Option<NaiveDate>,
+    pub range_end: Option<NaiveDate>,
+    pub hard_limit: Option<bool>,
 }
 
 pub struct BudgetRepo;
@@ -45,7 +242,7 @@ impl BudgetRepo {
         tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
     ) -> Result<Vec<Budget>, DatabaseError> {
         let query = format!(
-            "SELECT uid, group_uid, category_uid, amount, period_year, period_month FROM {} ORDER BY group_uid, category_uid",
+            "SELECT {BUDGET_COLUMNS} FROM {} WHERE group_uid IN (SELECT uid FROM expense_groups WHERE deleted_at IS NULL) ORDER BY group_uid, category_uid",
             Self::get_table_name()
         );
         let rows = sqlx::query_as::<_, Budget>(&query)
@@ -60,7 +257,7 @@ impl BudgetRepo {
         group_uid: Uuid,
     ) -> Result<Vec<Budget>, DatabaseError> {
         let query = format!(
-            "SELECT uid, group_uid, category_uid, amount, period_year, period_month FROM {} WHERE group_uid = $1 ORDER BY uid",
+            "SELECT {BUDGET_COLUMNS} FROM {} WHERE group_uid = $1 ORDER BY uid",
             Self::get_table_name()
         );
         let rows = sqlx::query_as::<_, Budget>(&query)
@@ -71,13 +268,30 @@ impl BudgetRepo {
         Ok(rows)
     }
 
+    // Budgets of several groups at once, e.g. every group of a user
+    pub async fn list_by_groups(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uids: &[Uuid],
+    ) -> Result<Vec<Budget>, DatabaseError> {
+        let query = format!(
+            "SELECT {BUDGET_COLUMNS} FROM {} WHERE group_uid = ANY($1) ORDER BY group_uid, uid",
+            Self::get_table_name()
+        );
+        let rows = sqlx::query_as::<_, Budget>(&query)
+            .bind(group_uids)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "listing budgets of groups"))?;
+        Ok(rows)
+    }
+
     pub async fn get_by_group_and_category(
         tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
         group_uid: Uuid,
         category_uid: Uuid,
     ) -> Result<Option<Budget>, DatabaseError> {
         let query = format!(
-            "SELECT uid, group_uid, category_uid, amount, period_year, period_month FROM {} WHERE group_uid = $1 AND category_uid = $2",
+            "SELECT {BUDGET_COLUMNS} FROM {} WHERE group_uid = $1 AND category_uid = $2",
             Self::get_table_name()
         );
         let budget = sqlx::query_as::<_, Budget>(&query)
@@ -110,7 +324,7 @@ impl BudgetRepo {
         uid: Uuid,
     ) -> Result<Budget, DatabaseError> {
         let query = format!(
-            "SELECT uid, group_uid, category_uid, amount, period_year, period_month FROM {} WHERE uid = $1",
+            "SELECT {BUDGET_COLUMNS} FROM {} WHERE uid = $1",
             Self::get_table_name()
         );
         let row = sqlx::query_as::<_, Budget>(&query)
@@ -127,7 +341,7 @@ impl BudgetRepo {
     ) -> Result<Budget, DatabaseError> {
         let uid = Uuid::new_v4();
         let query = format!(
-            "INSERT INTO {} (uid, group_uid, category_uid, amount, period_year, period_month) VALUES ($1, $2, $3, $4, $5, $6) RETURNING uid, group_uid, category_uid, amount, period_year, period_month",
+            "INSERT INTO {} (uid, group_uid, category_uid, amount, period_year, period_month, currency, hard_limit, period_type, range_start, range_end) VALUES ($1, $2, $3, $4, $5, $6, COALESCE($7, (SELECT currency FROM expense_groups WHERE uid = $2)), $8, $9::budget_period_type, $10, $11) RETURNING {BUDGET_COLUMNS}",
             Self::get_table_name()
         );
         let row = sqlx::query_as::<_, Budget>(&query)
@@ -137,6 +351,11 @@ impl BudgetRepo {
             .bind(payload.amount)
             .bind(payload.period_year)
             .bind(payload.period_month)
+            .bind(payload.currency)
+            .bind(payload.hard_limit)
+            .bind(payload.period_type.as_str())
+            .bind(payload.range_start)
+            .bind(payload.range_end)
             .fetch_one(tx.as_mut())
             .await
             .map_err(|e| DatabaseError::from_sqlx_error(e, "creating budget"))?;
@@ -150,10 +369,25 @@ impl BudgetRepo {
     ) -> Result<Budget, DatabaseError> {
         let current = Self::get(tx, uid).await?;
         let amount = payload.amount.unwrap_or(current.amount);
-        let period_year = payload.period_year.or(current.period_year);
-        let period_month = payload.period_month.or(current.period_month);
+        let period_type = payload.period_type.unwrap_or(current.period_type());
+        let currency = payload.currency.unwrap_or(current.currency);
+        let (period_year, period_month) = match period_type {
+            BudgetPeriodType::Monthly => (
+                payload.period_year.or(current.period_year),
+                payload.period_month.or(current.period_month),
+            ),
+            _ => (None, None),
+        };
+        let (range_start, range_end) = match period_type {
+            BudgetPeriodType::Custom => (
+                payload.range_start.or(current.range_start),
+                payload.range_end.or(current.range_end),
+            ),
+            _ => (None, None),
+        };
+        let hard_limit = payload.hard_limit.unwrap_or(current.hard_limit);
         let query = format!(
-            "UPDATE {} SET amount = $1, period_year = $2, period_month = $3 WHERE uid = $4 RETURNING uid, group_uid, category_uid, amount, period_year, period_month",
+            "UPDATE {} SET amount = $1, period_year = $2, period_month = $3, currency = $5, hard_limit = $6, period_type = $7::budget_period_type, range_start = $8, range_end = $9 WHERE uid = $4 RETURNING {BUDGET_COLUMNS}",
             Self::get_table_name()
         );
         let row = sqlx::query_as::<_, Budget>(&query)
@@ -161,6 +395,11 @@ impl BudgetRepo {
             .bind(period_year)
             .bind(period_month)
             .bind(uid)
+            .bind(currency)
+            .bind(hard_limit)
+            .bind(period_type.as_str())
+            .bind(range_start)
+            .bind(range_end)
             .fetch_one(tx.as_mut())
             .await
             .map_err(|e| DatabaseError::from_sqlx_error(e, "updating budget"))?;
@@ -180,3 +419,225 @@ impl BudgetRepo {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn budget(period_year: Option<i32>, period_month: Option<i32>) -> Budget {
+        Budget {
+            uid: Uuid::new_v4(),
+            group_uid: Uuid::new_v4(),
+            category_uid: Uuid::new_v4(),
+            amount: dec!(100000),
+            currency: "IDR".to_string(),
+            period_type: "monthly".to_string(),
+            period_year,
+            period_month,
+            range_start: None,
+            range_end: None,
+            hard_limit: false,
+        }
+    }
+
+    fn of_type(period_type: BudgetPeriodType) -> Budget {
+        Budget {
+            period_type: period_type.as_str().to_string(),
+            ..budget(None, None)
+        }
+    }
+
+    fn custom(start: NaiveDate, end: NaiveDate) -> Budget {
+        Budget {
+            range_start: Some(start),
+            range_end: Some(end),
+            ..of_type(BudgetPeriodType::Custom)
+        }
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_period_range_current_cycle() {
+        let budget = budget(None, None);
+        assert_eq!(
+            budget.period_range(25, 1, date(2025, 3, 26)),
+            (date(2025, 3, 25), date(2025, 4, 25))
+        );
+        assert_eq!(
+            budget.period_range(25, 1, date(2025, 3, 24)),
+            (date(2025, 2, 25), date(2025, 3, 25))
+        );
+        assert_eq!(
+            budget.period_range(25, 1, date(2025, 1, 10)),
+            (date(2024, 12, 25), date(2025, 1, 25))
+        );
+    }
+
+    #[test]
+    fn test_period_range_clamps_to_month_length() {
+        let budget = budget(None, None);
+        assert_eq!(
+            budget.period_range(31, 1, date(2025, 2, 28)),
+            (date(2025, 2, 28), date(2025, 3, 31))
+        );
+    }
+
+    #[test]
+    fn test_period_range_pinned_period() {
+        let budget = budget(Some(2024), Some(12));
+        assert_eq!(
+            budget.period_range(1, 1, date(2025, 6, 15)),
+            (date(2024, 12, 1), date(2025, 1, 1))
+        );
+    }
+
+    #[test]
+    fn test_active_by_category_prefers_pinned_budget() {
+        let recurring = budget(None, None);
+        let pinned = Budget {
+            category_uid: recurring.category_uid,
+            ..budget(Some(2025), Some(3))
+        };
+        let not_running = Budget {
+            category_uid: recurring.category_uid,
+            ..budget(Some(2024), Some(12))
+        };
+        let other = budget(None, None);
+
+        let active = Budget::active_by_category(
+            vec![recurring.clone(), pinned.clone(), not_running, other.clone()],
+            1,
+            1,
+            date(2025, 3, 10),
+        );
+        assert_eq!(active.len(), 2);
+        assert_eq!(active[&recurring.category_uid].uid, pinned.uid);
+        assert_eq!(active[&other.category_uid].uid, other.uid);
+
+        let active = Budget::active_by_category(vec![recurring.clone()], 1, 1, date(2025, 3, 10));
+        assert_eq!(active[&recurring.category_uid].uid, recurring.uid);
+    }
+
+    #[test]
+    fn test_period_range_by_type() {
+        // 13 August 2025 is a Wednesday, cycles start on the 25th
+        let today = date(2025, 8, 13);
+        assert_eq!(
+            of_type(BudgetPeriodType::Weekly).period_range(25, 1, today),
+            (date(2025, 8, 11), date(2025, 8, 18))
+        );
+        assert_eq!(
+            of_type(BudgetPeriodType::Weekly).period_range(25, 7, today),
+            (date(2025, 8, 10), date(2025, 8, 17))
+        );
+        assert_eq!(
+            of_type(BudgetPeriodType::Monthly).period_range(25, 1, today),
+            (date(2025, 7, 25), date(2025, 8, 25))
+        );
+        assert_eq!(
+            of_type(BudgetPeriodType::Quarterly).period_range(25, 1, today),
+            (date(2025, 7, 25), date(2025, 10, 25))
+        );
+        assert_eq!(
+            of_type(BudgetPeriodType::Yearly).period_range(25, 1, today),
+            (date(2025, 1, 25), date(2026, 1, 25))
+        );
+        assert_eq!(
+            custom(date(2025, 12, 20), date(2026, 1, 5)).period_range(25, 1, today),
+            (date(2025, 12, 20), date(2026, 1, 5))
+        );
+    }
+
+    #[test]
+    fn test_active_by_category_custom_range() {
+        let yearly = of_type(BudgetPeriodType::Yearly);
+        let holidays = Budget {
+            category_uid: yearly.category_uid,
+            ..custom(date(2025, 12, 20), date(2026, 1, 5))
+        };
+
+        let active = Budget::active_by_category(
+            vec![holidays.clone(), yearly.clone()],
+            1,
+            1,
+            date(2025, 12, 24),
+        );
+        assert_eq!(active[&yearly.category_uid].uid, holidays.uid);
+
+        // Outside its range the custom budget gives way to the recurring one
+        let active =
+            Budget::active_by_category(vec![holidays, yearly.clone()], 1, 1, date(2026, 1, 5));
+        assert_eq!(active[&yearly.category_uid].uid, yearly.uid);
+    }
+
+    #[test]
+    fn test_parse_period_type() {
+        assert_eq!(
+            BudgetPeriodType::parse("Tahunan"),
+            Some(BudgetPeriodType::Yearly)
+        );
+        assert_eq!(
+            BudgetPeriodType::parse("quarterly"),
+            Some(BudgetPeriodType::Quarterly)
+        );
+        assert_eq!(
+            BudgetPeriodType::parse("mingguan"),
+            Some(BudgetPeriodType::Weekly)
+        );
+        assert_eq!(BudgetPeriodType::parse("daily"), None);
+    }
+
+    #[test]
+    fn test_percentage_used() {
+        let budget = budget(None, None);
+        assert_eq!(budget.percentage_used(dec!(25000)), 25.0);
+        assert_eq!(budget.percentage_used(dec!(33333.33)), 33.33);
+        assert_eq!(budget.percentage_used(dec!(150000)), 150.0);
+
+        let empty = Budget {
+            amount: Decimal::ZERO,
+            ..budget
+        };
+        assert_eq!(empty.percentage_used(dec!(1000)), 0.0);
+    }
+
+    #[test]
+    fn test_spend_projection() {
+        // 10 of 31 days in, spending 3000 a day
+        let projection = SpendProjection::new(
+            dec!(30000),
+            date(2025, 3, 1),
+            date(2025, 4, 1),
+            date(2025, 3, 10),
+        );
+        assert_eq!(projection.days_elapsed, 10);
+        assert_eq!(projection.days_in_period, 31);
+        assert_eq!(projection.daily_burn_rate, dec!(3000));
+        assert_eq!(projection.projected_total, dec!(93000));
+
+        // Past periods are not extrapolated further
+        let projection = SpendProjection::new(
+            dec!(30000),
+            date(2025, 1, 1),
+            date(2025, 2, 1),
+            date(2025, 3, 10),
+        );
+        assert_eq!(projection.days_elapsed, 31);
+        assert_eq!(projection.projected_total, dec!(30000));
+
+        // Nothing to extrapolate before the period starts
+        let projection = SpendProjection::new(
+            dec!(0),
+            date(2025, 4, 1),
+            date(2025, 5, 1),
+            date(2025, 3, 10),
+        );
+        assert_eq!(projection.days_elapsed, 0);
+        assert_eq!(projection.daily_burn_rate, Decimal::ZERO);
+        assert_eq!(projection.projected_total, Decimal::ZERO);
+    }
+}
@@ -0,0 +1,214 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::DatabaseError;
+use crate::repos::base::BaseRepo;
+
+pub const EXCEEDED_THRESHOLD: i16 = 100;
+
+// What happens to an expense that takes a budget with `hard_limit` past its amount
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HardLimitAction {
+    // Recorded, with a warning in the reply
+    Warn,
+    // Held back until it is confirmed
+    Confirm,
+}
+
+impl HardLimitAction {
+    pub fn parse(input: &str) -> Option<Self> {
+        match input.trim().to_lowercase().as_str() {
+            "warn" => Some(Self::Warn),
+            "confirm" => Some(Self::Confirm),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Warn => "warn",
+            Self::Confirm => "confirm",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct BudgetAlertSettings {
+    pub group_uid: Uuid,
+    pub enabled: bool,
+    pub warning_percent: i16,
+    pub notify_exceeded: bool,
+    /// `warn` or `confirm`, see `HardLimitAction`
+    pub hard_limit_action: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl BudgetAlertSettings {
+    // Used for groups that never changed their settings, mirrors the table defaults
+    pub fn default_for(group_uid: Uuid) -> Self {
+        Self {
+            group_uid,
+            enabled: true,
+            warning_percent: 80,
+            notify_exceeded: true,
+            hard_limit_action: HardLimitAction::Warn.as_str().to_string(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    pub fn hard_limit_action(&self) -> HardLimitAction {
+        HardLimitAction::parse(&self.hard_limit_action).unwrap_or(HardLimitAction::Warn)
+    }
+
+    // Thresholds (in percent) reached by `percentage_used`, lowest first
+    pub fn crossed_thresholds(&self, percentage_used: f64) -> Vec<i16> {
+        if !self.enabled {
+            return Vec::new();
+        }
+        let mut thresholds = vec![self.warning_percent];
+        if self.notify_exceeded {
+            thresholds.push(EXCEEDED_THRESHOLD);
+        }
+        thresholds
+            .into_iter()
+            .filter(|threshold| percentage_used >= *threshold as f64)
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+pub struct UpsertBudgetAlertSettingsDbPayload {
+    pub enabled: bool,
+    pub warning_percent: i16,
+    pub notify_exceeded: bool,
+    pub hard_limit_action: HardLimitAction,
+}
+
+pub struct BudgetAlertRepo;
+
+impl BaseRepo for BudgetAlertRepo {
+    fn get_table_name() -> &'static str {
+        "budget_alerts"
+    }
+}
+
+impl BudgetAlertRepo {
+    pub async fn get_settings(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+    ) -> Result<BudgetAlertSettings, DatabaseError> {
+        let row = sqlx::query_as::<_, BudgetAlertSettings>(
+            "SELECT group_uid, enabled, warning_percent, notify_exceeded, hard_limit_action, updated_at FROM budget_alert_settings WHERE group_uid = $1",
+        )
+        .bind(group_uid)
+        .fetch_optional(tx.as_mut())
+        .await
+        .map_err(|e| DatabaseError::from_sqlx_error(e, "getting budget alert settings"))?;
+        Ok(row.unwrap_or_else(|| BudgetAlertSettings::default_for(group_uid)))
+    }
+
+    // Settings of several groups at once, defaults filled in for groups without a row
+    pub async fn list_settings(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uids: &[Uuid],
+    ) -> Result<Vec<BudgetAlertSettings>, DatabaseError> {
+        let rows = sqlx::query_as::<_, BudgetAlertSettings>(
+            "SELECT group_uid, enabled, warning_percent, notify_exceeded, hard_limit_action, updated_at FROM budget_alert_settings WHERE group_uid = ANY($1)",
+        )
+        .bind(group_uids)
+        .fetch_all(tx.as_mut())
+        .await
+        .map_err(|e| DatabaseError::from_sqlx_error(e, "listing budget alert settings"))?;
+        Ok(group_uids
+            .iter()
+            .map(|group_uid| {
+                rows.iter()
+                    .find(|settings| settings.group_uid == *group_uid)
+                    .cloned()
+                    .unwrap_or_else(|| BudgetAlertSettings::default_for(*group_uid))
+            })
+            .collect())
+    }
+
+    pub async fn upsert_settings(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+        payload: UpsertBudgetAlertSettingsDbPayload,
+    ) -> Result<BudgetAlertSettings, DatabaseError> {
+        let row = sqlx::query_as::<_, BudgetAlertSettings>(
+            "INSERT INTO budget_alert_settings (group_uid, enabled, warning_percent, notify_exceeded, hard_limit_action) VALUES ($1, $2, $3, $4, $5) \
+            ON CONFLICT (group_uid) DO UPDATE SET enabled = EXCLUDED.enabled, warning_percent = EXCLUDED.warning_percent, notify_exceeded = EXCLUDED.notify_exceeded, hard_limit_action = EXCLUDED.hard_limit_action, updated_at = now() \
+            RETURNING group_uid, enabled, warning_percent, notify_exceeded, hard_limit_action, updated_at",
+        )
+        .bind(group_uid)
+        .bind(payload.enabled)
+        .bind(payload.warning_percent)
+        .bind(payload.notify_exceeded)
+        .bind(payload.hard_limit_action.as_str())
+        .fetch_one(tx.as_mut())
+        .await
+        .map_err(|e| DatabaseError::from_sqlx_error(e, "saving budget alert settings"))?;
+        Ok(row)
+    }
+
+    // Returns false when the alert was already recorded for this period
+    pub async fn record_sent(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        budget_uid: Uuid,
+        period_start: NaiveDate,
+        threshold: i16,
+    ) -> Result<bool, DatabaseError> {
+        let query = format!(
+            "INSERT INTO {} (budget_uid, period_start, threshold) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING",
+            Self::get_table_name()
+        );
+        let res = sqlx::query(&query)
+            .bind(budget_uid)
+            .bind(period_start)
+            .bind(threshold)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "recording budget alert"))?;
+        Ok(res.rows_affected() > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crossed_thresholds() {
+        let settings = BudgetAlertSettings::default_for(Uuid::new_v4());
+        assert!(settings.crossed_thresholds(79.9).is_empty());
+        assert_eq!(settings.crossed_thresholds(80.0), vec![80]);
+        assert_eq!(settings.crossed_thresholds(120.0), vec![80, 100]);
+    }
+
+    #[test]
+    fn test_crossed_thresholds_respects_settings() {
+        let mut settings = BudgetAlertSettings::default_for(Uuid::new_v4());
+        settings.notify_exceeded = false;
+        assert_eq!(settings.crossed_thresholds(150.0), vec![80]);
+
+        settings.enabled = false;
+        assert!(settings.crossed_thresholds(150.0).is_empty());
+    }
+
+    #[test]
+    fn test_hard_limit_action() {
+        let mut settings = BudgetAlertSettings::default_for(Uuid::new_v4());
+        assert_eq!(settings.hard_limit_action(), HardLimitAction::Warn);
+
+        settings.hard_limit_action = "confirm".to_string();
+        assert_eq!(settings.hard_limit_action(), HardLimitAction::Confirm);
+        assert_eq!(
+            HardLimitAction::parse(" Warn "),
+            Some(HardLimitAction::Warn)
+        );
+        assert_eq!(HardLimitAction::parse("block"), None);
+    }
+}
@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::DatabaseError;
+use crate::repos::base::BaseRepo;
+
+const BUDGET_PLAN_COLUMNS: &str = "uid, group_uid, name, created_at, updated_at";
+const BUDGET_PLAN_ITEMS_TABLE: &str = "budget_plan_items";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct PlannedBudget {
+    pub category_uid: Uuid,
+    /// In the group's currency
+    pub amount: Decimal,
+}
+
+// A named set of budgets to try against past cycles before applying it
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct BudgetPlan {
+    pub uid: Uuid,
+    pub group_uid: Uuid,
+    pub name: String,
+    #[sqlx(skip)]
+    pub budgets: Vec<PlannedBudget>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateBudgetPlanDbPayload {
+    pub group_uid: Uuid,
+    pub name: String,
+    pub budgets: Vec<PlannedBudget>,
+}
+
+pub struct BudgetPlanRepo;
+
+impl BaseRepo for BudgetPlanRepo {
+    fn get_table_name() -> &'static str {
+        "budget_plans"
+    }
+}
+
+impl BudgetPlanRepo {
+    pub async fn list_by_group(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+    ) -> Result<Vec<BudgetPlan>, DatabaseError> {
+        let query = format!(
+            "SELECT {BUDGET_PLAN_COLUMNS} FROM {} WHERE group_uid = $1 ORDER BY name",
+            Self::get_table_name()
+        );
+        let mut plans = sqlx::query_as::<_, BudgetPlan>(&query)
+            .bind(group_uid)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "listing budget plans"))?;
+        let uids: Vec<Uuid> = plans.iter().map(|plan| plan.uid).collect();
+        let mut items = Self::items(tx, &uids).await?;
+        for plan in &mut plans {
+            plan.budgets = items.remove(&plan.uid).unwrap_or_default();
+        }
+        Ok(plans)
+    }
+
+    pub async fn get(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+    ) -> Result<BudgetPlan, DatabaseError> {
+        let query = format!(
+            "SELECT {BUDGET_PLAN_COLUMNS} FROM {} WHERE uid = $1",
+            Self::get_table_name()
+        );
+        let mut plan = sqlx::query_as::<_, BudgetPlan>(&query)
+            .bind(uid)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "getting budget plan"))?;
+        plan.budgets = Self::items(tx, &[uid])
+            .await?
+            .remove(&uid)
+            .unwrap_or_default();
+        Ok(plan)
+    }
+
+    pub async fn create(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        payload: CreateBudgetPlanDbPayload,
+    ) -> Result<BudgetPlan, DatabaseError> {
+        let query = format!(
+            "INSERT INTO {} (uid, group_uid, name) VALUES ($1, $2, $3) RETURNING {BUDGET_PLAN_COLUMNS}",
+            Self::get_table_name()
+        );
+        let mut plan = sqlx::query_as::<_, BudgetPlan>(&query)
+            .bind(Uuid::new_v4())
+            .bind(payload.group_uid)
+            .bind(payload.name)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "creating budget plan"))?;
+
+        let (category_uids, amounts): (Vec<Uuid>, Vec<Decimal>) = payload
+            .budgets
+            .iter()
+            .map(|budget| (budget.category_uid, budget.amount))
+            .unzip();
+        let query = format!(
+            "INSERT INTO {BUDGET_PLAN_ITEMS_TABLE} (plan_uid, category_uid, amount) SELECT $1, * FROM unnest($2::uuid[], $3::numeric[])"
+        );
+        sqlx::query(&query)
+            .bind(plan.uid)
+            .bind(category_uids)
+            .bind(amounts)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "creating budget plan items"))?;
+        plan.budgets = payload.budgets;
+        Ok(plan)
+    }
+
+    // Its budgets go with it, budgets it was applied as are kept
+    pub async fn delete(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+    ) -> Result<(), DatabaseError> {
+        let query = format!("DELETE FROM {} WHERE uid = $1", Self::get_table_name());
+        sqlx::query(&query)
+            .bind(uid)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "deleting budget plan"))?;
+        Ok(())
+    }
+
+    // Budgets of each plan in `plan_uids`, ordered by category
+    async fn items(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        plan_uids: &[Uuid],
+    ) -> Result<HashMap<Uuid, Vec<PlannedBudget>>, DatabaseError> {
+        let query = format!(
+            "SELECT plan_uid, category_uid, amount FROM {BUDGET_PLAN_ITEMS_TABLE} WHERE plan_uid = ANY($1) ORDER BY plan_uid, category_uid"
+        );
+        let rows = sqlx::query_as::<_, (Uuid, Uuid, Decimal)>(&query)
+            .bind(plan_uids)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "listing budget plan items"))?;
+        let mut items: HashMap<Uuid, Vec<PlannedBudget>> = HashMap::new();
+        for (plan_uid, category_uid, amount) in rows {
+            items.entry(plan_uid).or_default().push(PlannedBudget {
+                category_uid,
+                amount,
+            });
+        }
+        Ok(items)
+    }
+}
@@ -6,6 +6,11 @@ use uuid::Uuid;
 
 use crate::error::DatabaseError;
 use crate::repos::base::BaseRepo;
+use crate::repos::closed_period::ClosedPeriodRepo;
+use crate::utils::category_style;
+
+const CATEGORY_COLUMNS: &str =
+    "uid, group_uid, name, description, parent_uid, icon, color, created_at, updated_at";
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
 pub struct Category {
@@ -13,6 +18,12 @@ pub struct Category {
     pub group_uid: Uuid,
     pub name: String,
     pub description: Option<String>,
+    /// Set on subcategories, whose spending is also counted under this top-level category
+    pub parent_uid: Option<Uuid>,
+    /// An emoji or a short code like `:coffee:`
+    pub icon: String,
+    /// `#rrggbb`
+    pub color: String,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -22,12 +33,21 @@ pub struct CreateCategoryDbPayload {
     pub group_uid: Uuid,
     pub name: String,
     pub description: Option<String>,
+    pub parent_uid: Option<Uuid>,
+    // None picks the style of the template matching the name, see `category_style`
+    pub icon: Option<String>,
+    pub color: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct UpdateCategoryDbPayload {
     pub name: Option<String>,
     pub description: Option<String>,
+    pub parent_uid: Option<Option<Uuid>>, // Some(None) moves it back to the top level
+    pub icon: Option<String>,
+    pub color: Option<String>,
+    // The `updated_at` the caller last saw, the update fails when the category changed since
+    pub expected_updated_at: Option<DateTime<Utc>>,
 }
 
 pub struct CategoryRepo;
@@ -43,7 +63,7 @@ impl CategoryRepo {
         tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
     ) -> Result<Vec<Category>, DatabaseError> {
         let query = format!(
-            "SELECT uid, group_uid, name, description,  created_at, updated_at FROM {} ORDER BY created_at DESC",
+            "SELECT {CATEGORY_COLUMNS} FROM {} ORDER BY created_at DESC",
             Self::get_table_name()
         );
         let rows = sqlx::query_as::<_, Category>(&query)
@@ -58,7 +78,7 @@ impl CategoryRepo {
         group_uid: Uuid,
     ) -> Result<Vec<Category>, DatabaseError> {
         let query = format!(
-            "SELECT uid, group_uid, name, description,  created_at, updated_at FROM {} WHERE group_uid = $1 ORDER BY created_at DESC",
+            "SELECT {CATEGORY_COLUMNS} FROM {} WHERE group_uid = $1 ORDER BY created_at DESC",
             Self::get_table_name()
         );
         let rows = sqlx::query_as::<_, Category>(&query)
@@ -90,7 +110,7 @@ impl CategoryRepo {
         uid: Uuid,
     ) -> Result<Category, DatabaseError> {
         let query = format!(
-            "SELECT uid, group_uid, name, description,  created_at, updated_at FROM {} WHERE uid = $1",
+            "SELECT {CATEGORY_COLUMNS} FROM {} WHERE uid = $1",
             Self::get_table_name()
         );
         let row = sqlx::query_as::<_, Category>(&query)
@@ -106,8 +126,17 @@ impl CategoryRepo {
         payload: CreateCategoryDbPayload,
     ) -> Result<Category, DatabaseError> {
         let uid = Uuid::new_v4();
+        if let Some(parent_uid) = payload.parent_uid {
+            Self::check_parent(tx, payload.group_uid, None, parent_uid).await?;
+        }
+        let (default_icon, default_color) = category_style::default_style(&payload.name);
+        let icon = payload.icon.unwrap_or_else(|| default_icon.to_string());
+        let color = payload
+            .color
+            .unwrap_or_else(|| default_color.to_string())
+            .to_ascii_lowercase();
         let query = format!(
-            "INSERT INTO {} (uid, group_uid, name, description) VALUES ($1, $2, $3, $4) RETURNING uid, group_uid, name, description, created_at, updated_at",
+            "INSERT INTO {} (uid, group_uid, name, description, parent_uid, icon, color) VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING {CATEGORY_COLUMNS}",
             Self::get_table_name()
         );
         let row = sqlx::query_as::<_, Category>(&query)
@@ -115,32 +144,108 @@ impl CategoryRepo {
             .bind(payload.group_uid)
             .bind(payload.name)
             .bind(payload.description)
+            .bind(payload.parent_uid)
+            .bind(icon)
+            .bind(color)
             .fetch_one(tx.as_mut())
             .await
             .map_err(|e| DatabaseError::from_sqlx_error(e, "creating category"))?;
         Ok(row)
     }
 
+    // Fails with `ConcurrentModification` like `ExpenseEntryRepo::update`
     pub async fn update(
         tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
         uid: Uuid,
         payload: UpdateCategoryDbPayload,
     ) -> Result<Category, DatabaseError> {
         let current = Self::get(tx, uid).await?;
+        if payload
+            .expected_updated_at
+            .is_some_and(|expected| expected != current.updated_at)
+        {
+            return Err(DatabaseError::concurrent_modification(&current));
+        }
+        let read_updated_at = current.updated_at;
+        let parent_uid = payload.parent_uid.unwrap_or(current.parent_uid);
+        if let Some(parent_uid) = parent_uid.filter(|parent| Some(*parent) != current.parent_uid) {
+            Self::check_parent(tx, current.group_uid, Some(uid), parent_uid).await?;
+        }
         let name = payload.name.unwrap_or(current.name);
         let description = payload.description.or(current.description);
+        let icon = payload.icon.unwrap_or(current.icon);
+        let color = payload.color.unwrap_or(current.color).to_ascii_lowercase();
         let query = format!(
-            "UPDATE {} SET name = $1, description = $2 WHERE uid = $3 RETURNING uid, group_uid, name, description, created_at, updated_at",
+            "UPDATE {} SET name = $1, description = $2, parent_uid = $5, icon = $6, color = $7 WHERE uid = $3 AND updated_at = $4 RETURNING {CATEGORY_COLUMNS}",
             Self::get_table_name()
         );
         let row = sqlx::query_as::<_, Category>(&query)
             .bind(name)
             .bind(description)
             .bind(uid)
-            .fetch_one(tx.as_mut())
+            .bind(read_updated_at)
+            .bind(parent_uid)
+            .bind(icon)
+            .bind(color)
+            .fetch_optional(tx.as_mut())
             .await
             .map_err(|e| DatabaseError::from_sqlx_error(e, "updating category"))?;
-        Ok(row)
+        match row {
+            Some(row) => Ok(row),
+            None => Err(DatabaseError::concurrent_modification(
+                &Self::get(tx, uid).await?,
+            )),
+        }
+    }
+
+    /*
+     Categories nest one level deep: the parent of `uid` (None for a new category) has to be a
+     top-level category of the same group, and a category with subcategories of its own can't
+     be moved under another one. Fails with `ConstraintViolation` otherwise
+    */
+    async fn check_parent(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+        uid: Option<Uuid>,
+        parent_uid: Uuid,
+    ) -> Result<(), DatabaseError> {
+        if uid == Some(parent_uid) {
+            return Err(DatabaseError::ConstraintViolation(
+                "A category cannot be its own parent".to_string(),
+            ));
+        }
+        let parent = match Self::get(tx, parent_uid).await {
+            Ok(parent) if parent.group_uid == group_uid => parent,
+            Ok(_) | Err(DatabaseError::NotFound(_)) => {
+                return Err(DatabaseError::ConstraintViolation(
+                    "The parent category must belong to the same group".to_string(),
+                ));
+            }
+            Err(e) => return Err(e),
+        };
+        if parent.parent_uid.is_some() {
+            return Err(DatabaseError::ConstraintViolation(format!(
+                "{} is a subcategory itself, categories nest only one level deep",
+                parent.name
+            )));
+        }
+        if let Some(uid) = uid {
+            let query = format!(
+                "SELECT COUNT(*) FROM {} WHERE parent_uid = $1",
+                Self::get_table_name()
+            );
+            let children = sqlx::query_scalar::<_, i64>(&query)
+                .bind(uid)
+                .fetch_one(tx.as_mut())
+                .await
+                .map_err(|e| DatabaseError::from_sqlx_error(e, "counting subcategories"))?;
+            if children > 0 {
+                return Err(DatabaseError::ConstraintViolation(
+                    "A category with subcategories cannot be moved under another one".to_string(),
+                ));
+            }
+        }
+        Ok(())
     }
 
     pub async fn delete(
@@ -156,6 +261,63 @@ impl CategoryRepo {
         Ok(())
     }
 
+    /*
+     Deletes a category together with everything pointing at it.
+     Expense entries (including soft-deleted ones) and recurring expenses move to
+     `reassign_to`, or become uncategorized when it is None. Aliases follow the entries
+     so chat messages using them keep working; budgets are dropped since a budget only
+     makes sense for the category it was set on. Subcategories move to the top level.
+     Returns the number of expense entries that were moved. Fails with `PeriodClosed` when
+     one of them is in a closed period.
+    */
+    pub async fn delete_reassigning(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+        reassign_to: Option<Uuid>,
+    ) -> Result<u64, DatabaseError> {
+        ClosedPeriodRepo::ensure_category_open(tx, uid).await?;
+        let moved = sqlx::query(
+            "UPDATE expense_entries SET category_uid = $2, updated_at = now() WHERE category_uid = $1",
+        )
+        .bind(uid)
+        .bind(reassign_to)
+        .execute(tx.as_mut())
+        .await
+        .map_err(|e| DatabaseError::from_sqlx_error(e, "reassigning category expense entries"))?
+        .rows_affected();
+
+        sqlx::query(
+            "UPDATE recurring_expenses SET category_uid = $2, updated_at = now() WHERE category_uid = $1",
+        )
+        .bind(uid)
+        .bind(reassign_to)
+        .execute(tx.as_mut())
+        .await
+        .map_err(|e| DatabaseError::from_sqlx_error(e, "reassigning category recurring expenses"))?;
+
+        let alias_query = match reassign_to {
+            Some(target) => sqlx::query(
+                "UPDATE categories_aliases SET category_uid = $2 WHERE category_uid = $1",
+            )
+            .bind(uid)
+            .bind(target),
+            None => sqlx::query("DELETE FROM categories_aliases WHERE category_uid = $1").bind(uid),
+        };
+        alias_query
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "moving category aliases"))?;
+
+        sqlx::query("DELETE FROM budgets WHERE category_uid = $1")
+            .bind(uid)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "deleting category budgets"))?;
+
+        Self::delete(tx, uid).await?;
+        Ok(moved)
+    }
+
     pub async fn find_by_name_or_alias(
         tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
         group_uid: Uuid,
@@ -163,7 +325,7 @@ impl CategoryRepo {
     ) -> Result<Option<Category>, DatabaseError> {
         // First check if it's a category name
         let query = format!(
-            "SELECT uid, group_uid, name, description, created_at, updated_at FROM {} WHERE group_uid = $1 AND name = $2",
+            "SELECT {CATEGORY_COLUMNS} FROM {} WHERE group_uid = $1 AND name = $2",
             Self::get_table_name()
         );
         if let Ok(category) = sqlx::query_as::<_, Category>(&query)
@@ -177,7 +339,7 @@ impl CategoryRepo {
 
         // Then check aliases
         let query = format!(
-            "SELECT c.uid, c.group_uid, c.name, c.description, c.created_at, c.updated_at FROM {} c JOIN categories_aliases ca ON c.uid = ca.category_uid WHERE ca.group_uid = $1 AND ca.alias = $2",
+            "SELECT c.uid, c.group_uid, c.name, c.description, c.parent_uid, c.icon, c.color, c.created_at, c.updated_at FROM {} c JOIN categories_aliases ca ON c.uid = ca.category_uid WHERE ca.group_uid = $1 AND ca.alias = $2",
             Self::get_table_name()
         );
         let category = sqlx::query_as::<_, Category>(&query)
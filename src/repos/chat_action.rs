@@ -0,0 +1,154 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::DatabaseError;
+use crate::repos::base::BaseRepo;
+use crate::repos::expense_entry::ExpenseEntry;
+
+/*
+ A change made from a chat binding that /undo can revert, holding what is needed to revert
+ it. Stored as JSON in `chat_actions.action`.
+*/
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ChatAction {
+    // Entries recorded with /expense
+    ExpenseCreate { entry_uids: Vec<Uuid> },
+    // Entries as they were before being edited
+    ExpenseEdit { before: Vec<ExpenseEntry> },
+    // Entries soft-deleted with /expense-delete
+    ExpenseDelete { entry_uids: Vec<Uuid> },
+    // Categories added with /category
+    CategoryCreate { category_uids: Vec<Uuid> },
+}
+
+#[derive(Debug, Clone)]
+pub struct ChatActionRecord {
+    pub uid: Uuid,
+    pub p_user_id: String,
+    pub action: ChatAction,
+    pub created_at: DateTime<Utc>,
+}
+
+/*
+ Journal of the changes made from each chat binding. Only the last few minutes are kept:
+ older rows are pruned whenever a new one is recorded for the binding.
+*/
+pub struct ChatActionRepo;
+
+impl BaseRepo for ChatActionRepo {
+    fn get_table_name() -> &'static str {
+        "chat_actions"
+    }
+}
+
+impl ChatActionRepo {
+    pub async fn record(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        binding_id: Uuid,
+        p_user_id: &str,
+        action: &ChatAction,
+        max_age: Duration,
+    ) -> Result<(), DatabaseError> {
+        let prune_query = format!(
+            "DELETE FROM {} WHERE binding_id = $1 AND created_at < $2",
+            Self::get_table_name()
+        );
+        sqlx::query(&prune_query)
+            .bind(binding_id)
+            .bind(Utc::now() - max_age)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "pruning chat actions"))?;
+
+        let action = serde_json::to_value(action)
+            .map_err(|e| DatabaseError::TransactionError(format!("encoding chat action: {e}")))?;
+        let query = format!(
+            "INSERT INTO {} (uid, binding_id, p_user_id, action) VALUES ($1, $2, $3, $4)",
+            Self::get_table_name()
+        );
+        sqlx::query(&query)
+            .bind(Uuid::new_v4())
+            .bind(binding_id)
+            .bind(p_user_id)
+            .bind(action)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "recording chat action"))?;
+        Ok(())
+    }
+
+    /*
+     Most recent action of the binding not undone yet and recorded after `since`,
+     only among those of `p_user_id` when given.
+    */
+    pub async fn latest(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        binding_id: Uuid,
+        p_user_id: Option<&str>,
+        since: DateTime<Utc>,
+    ) -> Result<Option<ChatActionRecord>, DatabaseError> {
+        let query = format!(
+            "SELECT uid, p_user_id, action, created_at FROM {} \
+            WHERE binding_id = $1 AND ($2::varchar IS NULL OR p_user_id = $2) AND created_at > $3 AND undone_at IS NULL \
+            ORDER BY created_at DESC LIMIT 1",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, (Uuid, String, serde_json::Value, DateTime<Utc>)>(&query)
+            .bind(binding_id)
+            .bind(p_user_id)
+            .bind(since)
+            .fetch_optional(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "getting latest chat action"))?;
+        let Some((uid, p_user_id, action, created_at)) = row else {
+            return Ok(None);
+        };
+        let action = serde_json::from_value(action)
+            .map_err(|e| DatabaseError::TransactionError(format!("decoding chat action: {e}")))?;
+        Ok(Some(ChatActionRecord {
+            uid,
+            p_user_id,
+            action,
+            created_at,
+        }))
+    }
+
+    pub async fn mark_undone(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+    ) -> Result<(), DatabaseError> {
+        let query = format!(
+            "UPDATE {} SET undone_at = now() WHERE uid = $1",
+            Self::get_table_name()
+        );
+        sqlx::query(&query)
+            .bind(uid)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "marking chat action undone"))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chat_action_json_round_trip() {
+        let uid = Uuid::new_v4();
+        let value = serde_json::to_value(ChatAction::ExpenseDelete {
+            entry_uids: vec![uid],
+        })
+        .unwrap();
+        assert_eq!(value["type"], "expense_delete");
+
+        let action: ChatAction = serde_json::from_value(value).unwrap();
+        assert!(matches!(
+            action,
+            ChatAction::ExpenseDelete { entry_uids } if entry_uids == vec![uid]
+        ));
+    }
+}
@@ -6,18 +6,31 @@ use uuid::Uuid;
 
 use crate::error::DatabaseError;
 use crate::repos::base::BaseRepo;
+use crate::utils::pii;
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
 pub struct ChatBindRequest {
     pub id: Uuid,
     pub platform: String, // from enum via ::text
-    pub p_uid: String,
+    pub p_uid: String,    // stored encrypted once PII_ENCRYPTION_KEY is set, see `utils::pii`
     pub nonce: String,
     pub user_uid: Option<Uuid>,
     pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
 }
 
+impl ChatBindRequest {
+    fn decrypted(mut self) -> Result<Self, DatabaseError> {
+        self.p_uid = pii::decrypt(&self.p_uid)?;
+        Ok(self)
+    }
+
+    pub fn is_usable(&self, now: DateTime<Utc>) -> bool {
+        self.used_at.is_none() && self.expires_at > now
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct CreateChatBindRequestDbPayload {
     pub platform: String,
@@ -46,14 +59,14 @@ impl ChatBindRequestRepo {
         tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
     ) -> Result<Vec<ChatBindRequest>, DatabaseError> {
         let query = format!(
-            "SELECT id, platform::text as platform, p_uid, nonce, user_uid, expires_at, created_at FROM {} ORDER BY created_at DESC",
+            "SELECT id, platform::text as platform, p_uid, nonce, user_uid, expires_at, used_at, created_at FROM {} ORDER BY created_at DESC",
             Self::get_table_name()
         );
         let rows = sqlx::query_as::<_, ChatBindRequest>(&query)
             .fetch_all(tx.as_mut())
             .await
             .map_err(|e| DatabaseError::from_sqlx_error(e, "listing chat bind requests"))?;
-        Ok(rows)
+        rows.into_iter().map(ChatBindRequest::decrypted).collect()
     }
 
     pub async fn get(
@@ -61,7 +74,24 @@ impl ChatBindRequestRepo {
         id: Uuid,
     ) -> Result<ChatBindRequest, DatabaseError> {
         let query = format!(
-            "SELECT id, platform::text as platform, p_uid, nonce, user_uid, expires_at, created_at FROM {} WHERE id = $1",
+            "SELECT id, platform::text as platform, p_uid, nonce, user_uid, expires_at, used_at, created_at FROM {} WHERE id = $1",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, ChatBindRequest>(&query)
+            .bind(id)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "getting chat bind request"))?;
+        row.decrypted()
+    }
+
+    // Locks the row so the same request cannot be accepted twice concurrently
+    pub async fn get_for_update(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        id: Uuid,
+    ) -> Result<ChatBindRequest, DatabaseError> {
+        let query = format!(
+            "SELECT id, platform::text as platform, p_uid, nonce, user_uid, expires_at, used_at, created_at FROM {} WHERE id = $1 FOR UPDATE",
             Self::get_table_name()
         );
         let row = sqlx::query_as::<_, ChatBindRequest>(&query)
@@ -69,7 +99,7 @@ impl ChatBindRequestRepo {
             .fetch_one(tx.as_mut())
             .await
             .map_err(|e| DatabaseError::from_sqlx_error(e, "getting chat bind request"))?;
-        Ok(row)
+        row.decrypted()
     }
 
     pub async fn create(
@@ -78,20 +108,21 @@ impl ChatBindRequestRepo {
     ) -> Result<ChatBindRequest, DatabaseError> {
         let id = Uuid::new_v4();
         let query = format!(
-            "INSERT INTO {} (id, platform, p_uid, nonce, user_uid, expires_at) VALUES ($1, CAST($2 AS chat_platform), $3, $4, $5, $6) RETURNING id, platform::text as platform, p_uid, nonce, user_uid, expires_at, created_at",
+            "INSERT INTO {} (id, platform, p_uid, p_uid_hash, nonce, user_uid, expires_at) VALUES ($1, CAST($2 AS chat_platform), $3, $7, $4, $5, $6) RETURNING id, platform::text as platform, p_uid, nonce, user_uid, expires_at, used_at, created_at",
             Self::get_table_name()
         );
         let row = sqlx::query_as::<_, ChatBindRequest>(&query)
             .bind(id)
             .bind(payload.platform)
-            .bind(payload.p_uid)
+            .bind(pii::encrypt(&payload.p_uid))
             .bind(payload.nonce)
             .bind(payload.user_uid)
             .bind(payload.expires_at)
+            .bind(pii::lookup_hash(&payload.p_uid))
             .fetch_one(tx.as_mut())
             .await
             .map_err(|e| DatabaseError::from_sqlx_error(e, "creating chat bind request"))?;
-        Ok(row)
+        row.decrypted()
     }
 
     pub async fn update(
@@ -106,7 +137,7 @@ impl ChatBindRequestRepo {
         };
         let expires_at = payload.expires_at.unwrap_or(current.expires_at);
         let query = format!(
-            "UPDATE {} SET user_uid = $1, expires_at = $2 WHERE id = $3 RETURNING id, platform::text as platform, p_uid, nonce, user_uid, expires_at, created_at",
+            "UPDATE {} SET user_uid = $1, expires_at = $2 WHERE id = $3 RETURNING id, platform::text as platform, p_uid, nonce, user_uid, expires_at, used_at, created_at",
             Self::get_table_name()
         );
         let row = sqlx::query_as::<_, ChatBindRequest>(&query)
@@ -116,7 +147,45 @@ impl ChatBindRequestRepo {
             .fetch_one(tx.as_mut())
             .await
             .map_err(|e| DatabaseError::from_sqlx_error(e, "updating chat bind request"))?;
-        Ok(row)
+        row.decrypted()
+    }
+
+    // Also invalidates any other outstanding requests of the same chat, found by the lookup hash
+    // or the chat id itself for rows not encrypted yet
+    pub async fn mark_used(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        platform: &str,
+        p_uid: &str,
+    ) -> Result<(), DatabaseError> {
+        let query = format!(
+            "UPDATE {} SET used_at = now() WHERE platform = CAST($1 AS chat_platform) AND (p_uid_hash = $3 OR p_uid = $2) AND used_at IS NULL",
+            Self::get_table_name()
+        );
+        sqlx::query(&query)
+            .bind(platform)
+            .bind(p_uid)
+            .bind(pii::lookup_hash(p_uid))
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "using chat bind request"))?;
+        Ok(())
+    }
+
+    // Requests that were used or expired before `now`, returns how many were deleted
+    pub async fn delete_stale(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        now: DateTime<Utc>,
+    ) -> Result<u64, DatabaseError> {
+        let query = format!(
+            "DELETE FROM {} WHERE used_at IS NOT NULL OR expires_at < $1",
+            Self::get_table_name()
+        );
+        let result = sqlx::query(&query)
+            .bind(now)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "deleting stale chat bind requests"))?;
+        Ok(result.rows_affected())
     }
 
     pub async fn delete(
@@ -132,3 +201,30 @@ impl ChatBindRequestRepo {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn bind_request(expires_at: DateTime<Utc>, used_at: Option<DateTime<Utc>>) -> ChatBindRequest {
+        ChatBindRequest {
+            id: Uuid::new_v4(),
+            platform: "telegram".to_string(),
+            p_uid: "12345".to_string(),
+            nonce: Uuid::new_v4().to_string(),
+            user_uid: None,
+            expires_at,
+            used_at,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_is_usable() {
+        let now = Utc::now();
+        assert!(bind_request(now + Duration::hours(1), None).is_usable(now));
+        assert!(!bind_request(now - Duration::hours(1), None).is_usable(now));
+        assert!(!bind_request(now + Duration::hours(1), Some(now)).is_usable(now));
+    }
+}
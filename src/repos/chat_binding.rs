@@ -6,17 +6,27 @@ use uuid::Uuid;
 
 use crate::error::DatabaseError;
 use crate::repos::base::BaseRepo;
+use crate::utils::pii;
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
 pub struct ChatBinding {
     pub id: Uuid,
     pub group_uid: Uuid,
     pub platform: String, // from enum via ::text
-    pub p_uid: String,
-    pub status: String, // from enum via ::text
+    pub p_uid: String,    // stored encrypted once PII_ENCRYPTION_KEY is set, see `utils::pii`
+    pub status: String,   // from enum via ::text
     pub bound_by: Uuid,
     pub bound_at: DateTime<Utc>,
     pub revoked_at: Option<DateTime<Utc>>,
+    // A chat bound to several groups sends its commands to the selected binding
+    pub selected: bool,
+}
+
+impl ChatBinding {
+    fn decrypted(mut self) -> Result<Self, DatabaseError> {
+        self.p_uid = pii::decrypt(&self.p_uid)?;
+        Ok(self)
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -47,14 +57,30 @@ impl ChatBindingRepo {
         tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
     ) -> Result<Vec<ChatBinding>, DatabaseError> {
         let query = format!(
-            "SELECT id, group_uid, platform::text as platform, p_uid, status::text as status, bound_by, bound_at, revoked_at FROM {} ORDER BY bound_at DESC",
+            "SELECT id, group_uid, platform::text as platform, p_uid, status::text as status, bound_by, bound_at, revoked_at, selected FROM {} ORDER BY bound_at DESC",
             Self::get_table_name()
         );
         let rows = sqlx::query_as::<_, ChatBinding>(&query)
             .fetch_all(tx.as_mut())
             .await
             .map_err(|e| DatabaseError::from_sqlx_error(e, "listing chat bindings"))?;
-        Ok(rows)
+        rows.into_iter().map(ChatBinding::decrypted).collect()
+    }
+
+    pub async fn list_by_group(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+    ) -> Result<Vec<ChatBinding>, DatabaseError> {
+        let query = format!(
+            "SELECT id, group_uid, platform::text as platform, p_uid, status::text as status, bound_by, bound_at, revoked_at, selected FROM {} WHERE group_uid = $1 ORDER BY bound_at DESC",
+            Self::get_table_name()
+        );
+        let rows = sqlx::query_as::<_, ChatBinding>(&query)
+            .bind(group_uid)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "listing chat bindings by group"))?;
+        rows.into_iter().map(ChatBinding::decrypted).collect()
     }
 
     pub async fn get(
@@ -62,7 +88,7 @@ impl ChatBindingRepo {
         id: Uuid,
     ) -> Result<ChatBinding, DatabaseError> {
         let query = format!(
-            "SELECT id, group_uid, platform::text as platform, p_uid, status::text as status, bound_by, bound_at, revoked_at FROM {} WHERE id = $1",
+            "SELECT id, group_uid, platform::text as platform, p_uid, status::text as status, bound_by, bound_at, revoked_at, selected FROM {} WHERE id = $1",
             Self::get_table_name()
         );
         let row = sqlx::query_as::<_, ChatBinding>(&query)
@@ -70,7 +96,48 @@ impl ChatBindingRepo {
             .fetch_one(tx.as_mut())
             .await
             .map_err(|e| DatabaseError::from_sqlx_error(e, "getting chat binding"))?;
-        Ok(row)
+        row.decrypted()
+    }
+
+    // Active bindings of a chat, most recent first. By the lookup hash, or the chat id itself
+    // for rows not encrypted yet
+    pub async fn list_active_by_chat(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        platform: &str,
+        p_uid: &str,
+    ) -> Result<Vec<ChatBinding>, DatabaseError> {
+        let query = format!(
+            "SELECT id, group_uid, platform::text as platform, p_uid, status::text as status, bound_by, bound_at, revoked_at, selected FROM {} WHERE platform = CAST($1 AS chat_platform) AND (p_uid_hash = $3 OR p_uid = $2) AND status = 'active' ORDER BY bound_at DESC",
+            Self::get_table_name()
+        );
+        let rows = sqlx::query_as::<_, ChatBinding>(&query)
+            .bind(platform)
+            .bind(p_uid)
+            .bind(pii::lookup_hash(p_uid))
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "listing chat bindings by chat"))?;
+        rows.into_iter().map(ChatBinding::decrypted).collect()
+    }
+
+    // The binding commands of the chat go to, None when the chat is not bound
+    pub async fn get_selected(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        platform: &str,
+        p_uid: &str,
+    ) -> Result<Option<ChatBinding>, DatabaseError> {
+        let query = format!(
+            "SELECT id, group_uid, platform::text as platform, p_uid, status::text as status, bound_by, bound_at, revoked_at, selected FROM {} WHERE platform = CAST($1 AS chat_platform) AND (p_uid_hash = $3 OR p_uid = $2) AND status = 'active' AND selected",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, ChatBinding>(&query)
+            .bind(platform)
+            .bind(p_uid)
+            .bind(pii::lookup_hash(p_uid))
+            .fetch_optional(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "getting selected chat binding"))?;
+        row.map(ChatBinding::decrypted).transpose()
     }
 
     pub async fn create(
@@ -79,20 +146,21 @@ impl ChatBindingRepo {
     ) -> Result<ChatBinding, DatabaseError> {
         let id = Uuid::new_v4();
         let query = format!(
-            "INSERT INTO {} (id, group_uid, platform, p_uid, status, bound_by) VALUES ($1, $2, CAST($3 AS chat_platform), $4, COALESCE(CAST($5 AS binding_status), 'active'::binding_status), $6) RETURNING id, group_uid, platform::text as platform, p_uid, status::text as status, bound_by, bound_at, revoked_at",
+            "INSERT INTO {} (id, group_uid, platform, p_uid, p_uid_hash, status, bound_by) VALUES ($1, $2, CAST($3 AS chat_platform), $4, $7, COALESCE(CAST($5 AS binding_status), 'active'::binding_status), $6) RETURNING id, group_uid, platform::text as platform, p_uid, status::text as status, bound_by, bound_at, revoked_at, selected",
             Self::get_table_name()
         );
         let row = sqlx::query_as::<_, ChatBinding>(&query)
             .bind(id)
             .bind(payload.group_uid)
             .bind(payload.platform)
-            .bind(payload.p_uid)
+            .bind(pii::encrypt(&payload.p_uid))
             .bind(payload.status)
             .bind(payload.bound_by)
+            .bind(pii::lookup_hash(&payload.p_uid))
             .fetch_one(tx.as_mut())
             .await
             .map_err(|e| DatabaseError::from_sqlx_error(e, "creating chat binding"))?;
-        Ok(row)
+        row.decrypted()
     }
 
     pub async fn update(
@@ -107,7 +175,7 @@ impl ChatBindingRepo {
             None => current.revoked_at,
         };
         let query = format!(
-            "UPDATE {} SET status = CAST($1 AS binding_status), revoked_at = $2 WHERE id = $3 RETURNING id, group_uid, platform::text as platform, p_uid, status::text as status, bound_by, bound_at, revoked_at",
+            "UPDATE {} SET status = CAST($1 AS binding_status), revoked_at = $2, selected = selected AND CAST($1 AS binding_status) = 'active' WHERE id = $3 RETURNING id, group_uid, platform::text as platform, p_uid, status::text as status, bound_by, bound_at, revoked_at, selected",
             Self::get_table_name()
         );
         let row = sqlx::query_as::<_, ChatBinding>(&query)
@@ -117,7 +185,66 @@ impl ChatBindingRepo {
             .fetch_one(tx.as_mut())
             .await
             .map_err(|e| DatabaseError::from_sqlx_error(e, "updating chat binding"))?;
-        Ok(row)
+        row.decrypted()
+    }
+
+    // Makes the binding the one its chat sends commands to, in place of the current one
+    pub async fn select(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        id: Uuid,
+    ) -> Result<ChatBinding, DatabaseError> {
+        let binding = Self::get(tx, id).await?;
+        // Two statements, the unique index allows one selected binding per chat at any time
+        let query = format!(
+            "UPDATE {} SET selected = false WHERE platform = CAST($1 AS chat_platform) AND (p_uid_hash = $3 OR p_uid = $2) AND selected",
+            Self::get_table_name()
+        );
+        sqlx::query(&query)
+            .bind(&binding.platform)
+            .bind(&binding.p_uid)
+            .bind(pii::lookup_hash(&binding.p_uid))
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "deselecting chat bindings"))?;
+        let query = format!(
+            "UPDATE {} SET selected = true WHERE id = $1 RETURNING id, group_uid, platform::text as platform, p_uid, status::text as status, bound_by, bound_at, revoked_at, selected",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, ChatBinding>(&query)
+            .bind(id)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "selecting chat binding"))?;
+        row.decrypted()
+    }
+
+    /*
+     Revoking the selected binding of a chat selects its most recent other binding, so the chat
+     keeps working with its remaining groups. Without one the chat falls back to unbound and a
+     new /login binds it again.
+    */
+    pub async fn revoke(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        id: Uuid,
+    ) -> Result<ChatBinding, DatabaseError> {
+        let current = Self::get(tx, id).await?;
+        let revoked = Self::update(
+            tx,
+            id,
+            UpdateChatBindingDbPayload {
+                status: Some("revoked".to_string()),
+                revoked_at: Some(Some(Utc::now())),
+            },
+        )
+        .await?;
+        if current.selected {
+            let remaining =
+                Self::list_active_by_chat(tx, &revoked.platform, &revoked.p_uid).await?;
+            if let Some(next) = remaining.first() {
+                Self::select(tx, next.id).await?;
+            }
+        }
+        Ok(revoked)
     }
 
     pub async fn delete(
@@ -0,0 +1,127 @@
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::DatabaseError;
+use crate::repos::base::BaseRepo;
+
+/*
+ A command asking for its fields one message at a time, with what the sender answered so
+ far. Stored as JSON in `chat_conversations.state`.
+*/
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "flow", rename_all = "snake_case")]
+pub enum Conversation {
+    // /expense sent without entries, the category is the last answer
+    Expense {
+        name: Option<String>,
+        price: Option<Decimal>,
+    },
+}
+
+/*
+ Ongoing conversations, at most one per sender and chat binding: starting another one
+ replaces it. Plain messages from the sender answer it until it ends, is cancelled or
+ goes stale.
+*/
+pub struct ChatConversationRepo;
+
+impl BaseRepo for ChatConversationRepo {
+    fn get_table_name() -> &'static str {
+        "chat_conversations"
+    }
+}
+
+impl ChatConversationRepo {
+    // The sender's conversation, unless untouched for longer than `max_age`
+    pub async fn get(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        binding_id: Uuid,
+        p_user_id: &str,
+        max_age: Duration,
+    ) -> Result<Option<Conversation>, DatabaseError> {
+        let query = format!(
+            "SELECT state, updated_at FROM {} WHERE binding_id = $1 AND p_user_id = $2",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, (serde_json::Value, DateTime<Utc>)>(&query)
+            .bind(binding_id)
+            .bind(p_user_id)
+            .fetch_optional(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "getting chat conversation"))?;
+        let Some((state, _)) = row.filter(|(_, updated_at)| *updated_at > Utc::now() - max_age)
+        else {
+            return Ok(None);
+        };
+        let conversation = serde_json::from_value(state).map_err(|e| {
+            DatabaseError::TransactionError(format!("decoding chat conversation: {e}"))
+        })?;
+        Ok(Some(conversation))
+    }
+
+    pub async fn upsert(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        binding_id: Uuid,
+        p_user_id: &str,
+        conversation: &Conversation,
+    ) -> Result<(), DatabaseError> {
+        let state = serde_json::to_value(conversation).map_err(|e| {
+            DatabaseError::TransactionError(format!("encoding chat conversation: {e}"))
+        })?;
+        let query = format!(
+            "INSERT INTO {} (binding_id, p_user_id, state) VALUES ($1, $2, $3) \
+            ON CONFLICT (binding_id, p_user_id) DO UPDATE SET state = EXCLUDED.state, updated_at = now()",
+            Self::get_table_name()
+        );
+        sqlx::query(&query)
+            .bind(binding_id)
+            .bind(p_user_id)
+            .bind(state)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "saving chat conversation"))?;
+        Ok(())
+    }
+
+    // Returns whether the sender had a conversation, stale ones included
+    pub async fn delete(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        binding_id: Uuid,
+        p_user_id: &str,
+    ) -> Result<bool, DatabaseError> {
+        let query = format!(
+            "DELETE FROM {} WHERE binding_id = $1 AND p_user_id = $2",
+            Self::get_table_name()
+        );
+        let deleted = sqlx::query(&query)
+            .bind(binding_id)
+            .bind(p_user_id)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "deleting chat conversation"))?
+            .rows_affected();
+        Ok(deleted > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_conversation_json_round_trip() {
+        let conversation = Conversation::Expense {
+            name: Some("Nasi Padang".to_string()),
+            price: Some(dec!(25000)),
+        };
+        let value = serde_json::to_value(&conversation).unwrap();
+        assert_eq!(value["flow"], "expense");
+        assert_eq!(
+            serde_json::from_value::<Conversation>(value).unwrap(),
+            conversation
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::error::DatabaseError;
+use crate::repos::base::BaseRepo;
+use crate::utils::secret_token::hash_token;
+
+// No 0/O or 1/I, the code is typed by hand. 32 symbols so every byte maps evenly
+const CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+const CODE_LENGTH: usize = 8;
+
+pub fn generate_code() -> String {
+    Uuid::new_v4().as_bytes()[..CODE_LENGTH]
+        .iter()
+        .map(|b| CODE_ALPHABET[*b as usize % CODE_ALPHABET.len()] as char)
+        .collect()
+}
+
+// Codes are shown uppercase, but typing them in lowercase is fine
+pub fn normalize_code(code: &str) -> String {
+    code.trim().to_ascii_uppercase()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ChatLinkCode {
+    pub uid: Uuid,
+    pub user_uid: Uuid,
+    pub code_hash: String,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ChatLinkCode {
+    pub fn is_usable(&self, now: DateTime<Utc>) -> bool {
+        self.used_at.is_none() && self.expires_at > now
+    }
+}
+
+pub struct ChatLinkCodeRepo;
+
+impl BaseRepo for ChatLinkCodeRepo {
+    fn get_table_name() -> &'static str {
+        "chat_link_codes"
+    }
+}
+
+impl ChatLinkCodeRepo {
+    // Returns the stored row together with the plain code, which is only available here
+    pub async fn create(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        user_uid: Uuid,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(ChatLinkCode, String), DatabaseError> {
+        let code = generate_code();
+        let query = format!(
+            "INSERT INTO {} (uid, user_uid, code_hash, expires_at) VALUES ($1, $2, $3, $4) RETURNING uid, user_uid, code_hash, expires_at, used_at, created_at",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, ChatLinkCode>(&query)
+            .bind(Uuid::new_v4())
+            .bind(user_uid)
+            .bind(hash_token(&code))
+            .bind(expires_at)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "creating chat link code"))?;
+        Ok((row, code))
+    }
+
+    // Locks the row so the same code cannot be used twice concurrently
+    pub async fn get_by_code_for_update(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        code: &str,
+    ) -> Result<Option<ChatLinkCode>, DatabaseError> {
+        let query = format!(
+            "SELECT uid, user_uid, code_hash, expires_at, used_at, created_at FROM {} WHERE code_hash = $1 FOR UPDATE",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, ChatLinkCode>(&query)
+            .bind(hash_token(&normalize_code(code)))
+            .fetch_optional(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "getting chat link code by code"))?;
+        Ok(row)
+    }
+
+    pub async fn mark_used(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+    ) -> Result<(), DatabaseError> {
+        let query = format!(
+            "UPDATE {} SET used_at = now() WHERE uid = $1",
+            Self::get_table_name()
+        );
+        sqlx::query(&query)
+            .bind(uid)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "using chat link code"))?;
+        Ok(())
+    }
+
+    // Codes that were used or expired before `now`, returns how many were deleted
+    pub async fn delete_stale(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        now: DateTime<Utc>,
+    ) -> Result<u64, DatabaseError> {
+        let query = format!(
+            "DELETE FROM {} WHERE used_at IS NOT NULL OR expires_at < $1",
+            Self::get_table_name()
+        );
+        let result = sqlx::query(&query)
+            .bind(now)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "deleting stale chat link codes"))?;
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_code() {
+        let code = generate_code();
+        assert_eq!(code.len(), CODE_LENGTH);
+        assert!(code.bytes().all(|c| CODE_ALPHABET.contains(&c)));
+        assert_eq!(normalize_code(&code.to_lowercase()), code);
+    }
+
+    #[test]
+    fn test_normalize_code() {
+        assert_eq!(normalize_code(" ab3k9xyz \n"), "AB3K9XYZ");
+    }
+}
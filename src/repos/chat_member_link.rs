@@ -0,0 +1,147 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::DatabaseError;
+use crate::repos::base::BaseRepo;
+use crate::utils::pii;
+
+// A chat platform user linked to an account with /link-me, shared by every chat they are in
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct ChatMemberLink {
+    pub uid: Uuid,
+    pub platform: String,  // from enum via ::text
+    pub p_user_id: String, // stored encrypted once PII_ENCRYPTION_KEY is set, see `utils::pii`
+    pub user_uid: Uuid,
+    pub linked_at: DateTime<Utc>,
+}
+
+impl ChatMemberLink {
+    fn decrypted(mut self) -> Result<Self, DatabaseError> {
+        self.p_user_id = pii::decrypt(&self.p_user_id)?;
+        Ok(self)
+    }
+}
+
+pub struct ChatMemberLinkRepo;
+
+impl BaseRepo for ChatMemberLinkRepo {
+    fn get_table_name() -> &'static str {
+        "chat_member_links"
+    }
+}
+
+impl ChatMemberLinkRepo {
+    pub async fn list_by_user(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        user_uid: Uuid,
+    ) -> Result<Vec<ChatMemberLink>, DatabaseError> {
+        let query = format!(
+            "SELECT uid, platform::text as platform, p_user_id, user_uid, linked_at FROM {} WHERE user_uid = $1 ORDER BY linked_at DESC",
+            Self::get_table_name()
+        );
+        let rows = sqlx::query_as::<_, ChatMemberLink>(&query)
+            .bind(user_uid)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "listing chat member links"))?;
+        rows.into_iter().map(ChatMemberLink::decrypted).collect()
+    }
+
+    pub async fn get(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+    ) -> Result<ChatMemberLink, DatabaseError> {
+        let query = format!(
+            "SELECT uid, platform::text as platform, p_user_id, user_uid, linked_at FROM {} WHERE uid = $1",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, ChatMemberLink>(&query)
+            .bind(uid)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "getting chat member link"))?;
+        row.decrypted()
+    }
+
+    // By the lookup hash, or the platform user id itself for rows not encrypted yet
+    pub async fn find_user(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        platform: &str,
+        p_user_id: &str,
+    ) -> Result<Option<Uuid>, DatabaseError> {
+        let query = format!(
+            "SELECT user_uid FROM {} WHERE platform = CAST($1 AS chat_platform) AND (p_user_id_hash = $3 OR p_user_id = $2)",
+            Self::get_table_name()
+        );
+        let user_uid = sqlx::query_scalar::<_, Uuid>(&query)
+            .bind(platform)
+            .bind(p_user_id)
+            .bind(pii::lookup_hash(p_user_id))
+            .fetch_optional(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "finding chat member link"))?;
+        Ok(user_uid)
+    }
+
+    /*
+     Linking again moves the platform user to the new account. Encrypted ids never conflict on
+     `p_user_id`, so the existing link is updated by its lookup hash before inserting. A
+     concurrent link of the same user still fails on the unique hash or id.
+    */
+    pub async fn upsert(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        platform: &str,
+        p_user_id: &str,
+        user_uid: Uuid,
+    ) -> Result<ChatMemberLink, DatabaseError> {
+        let encrypted = pii::encrypt(p_user_id);
+        let hash = pii::lookup_hash(p_user_id);
+        let update_query = format!(
+            "UPDATE {} SET user_uid = $4, linked_at = now(), p_user_id = $3, p_user_id_hash = $5 WHERE platform = CAST($1 AS chat_platform) AND (p_user_id_hash = $5 OR p_user_id = $2) RETURNING uid, platform::text as platform, p_user_id, user_uid, linked_at",
+            Self::get_table_name()
+        );
+        let updated = sqlx::query_as::<_, ChatMemberLink>(&update_query)
+            .bind(platform)
+            .bind(p_user_id)
+            .bind(&encrypted)
+            .bind(user_uid)
+            .bind(&hash)
+            .fetch_optional(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "relinking chat member"))?;
+        if let Some(row) = updated {
+            return row.decrypted();
+        }
+
+        let insert_query = format!(
+            "INSERT INTO {} (uid, platform, p_user_id, p_user_id_hash, user_uid) VALUES ($1, CAST($2 AS chat_platform), $3, $4, $5) RETURNING uid, platform::text as platform, p_user_id, user_uid, linked_at",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, ChatMemberLink>(&insert_query)
+            .bind(Uuid::new_v4())
+            .bind(platform)
+            .bind(&encrypted)
+            .bind(&hash)
+            .bind(user_uid)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "linking chat member"))?;
+        row.decrypted()
+    }
+
+    pub async fn delete(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+    ) -> Result<(), DatabaseError> {
+        let query = format!("DELETE FROM {} WHERE uid = $1", Self::get_table_name());
+        sqlx::query(&query)
+            .bind(uid)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "deleting chat member link"))?;
+        Ok(())
+    }
+}
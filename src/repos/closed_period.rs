@@ -0,0 +1,213 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::DatabaseError;
+use crate::repos::base::BaseRepo;
+
+const CLOSED_PERIOD_COLUMNS: &str =
+    "group_uid, period_year, period_month, starts_at, ends_at, closed_by, closed_at";
+
+// A billing cycle whose expense entries are locked, `starts_at` and `ends_at` as [start, end)
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct ClosedPeriod {
+    pub group_uid: Uuid,
+    pub period_year: i32,
+    pub period_month: i32,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    pub closed_by: Option<Uuid>,
+    pub closed_at: DateTime<Utc>,
+}
+
+impl ClosedPeriod {
+    // `YYYY-MM`, same as the period in the routes
+    pub fn label(&self) -> String {
+        format!("{:04}-{:02}", self.period_year, self.period_month)
+    }
+}
+
+pub struct CreateClosedPeriodDbPayload {
+    pub group_uid: Uuid,
+    pub period_year: i32,
+    pub period_month: i32,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    pub closed_by: Option<Uuid>,
+}
+
+pub struct ClosedPeriodRepo;
+
+impl BaseRepo for ClosedPeriodRepo {
+    fn get_table_name() -> &'static str {
+        "closed_periods"
+    }
+}
+
+impl ClosedPeriodRepo {
+    // None when the period was already closed
+    pub async fn close(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        payload: CreateClosedPeriodDbPayload,
+    ) -> Result<Option<ClosedPeriod>, DatabaseError> {
+        let query = format!(
+            "INSERT INTO {} (group_uid, period_year, period_month, starts_at, ends_at, closed_by) VALUES ($1, $2, $3, $4, $5, $6) \
+            ON CONFLICT (group_uid, period_year, period_month) DO NOTHING RETURNING {CLOSED_PERIOD_COLUMNS}",
+            Self::get_table_name()
+        );
+        let rec = sqlx::query_as::<_, ClosedPeriod>(&query)
+            .bind(payload.group_uid)
+            .bind(payload.period_year)
+            .bind(payload.period_month)
+            .bind(payload.starts_at)
+            .bind(payload.ends_at)
+            .bind(payload.closed_by)
+            .fetch_optional(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "closing period"))?;
+        Ok(rec)
+    }
+
+    // Returns the reopened period, None when it was not closed
+    pub async fn reopen(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+        period_year: i32,
+        period_month: i32,
+    ) -> Result<Option<ClosedPeriod>, DatabaseError> {
+        let query = format!(
+            "DELETE FROM {} WHERE group_uid = $1 AND period_year = $2 AND period_month = $3 RETURNING {CLOSED_PERIOD_COLUMNS}",
+            Self::get_table_name()
+        );
+        let rec = sqlx::query_as::<_, ClosedPeriod>(&query)
+            .bind(group_uid)
+            .bind(period_year)
+            .bind(period_month)
+            .fetch_optional(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "reopening period"))?;
+        Ok(rec)
+    }
+
+    // Newest first
+    pub async fn list_by_group(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+    ) -> Result<Vec<ClosedPeriod>, DatabaseError> {
+        let query = format!(
+            "SELECT {CLOSED_PERIOD_COLUMNS} FROM {} WHERE group_uid = $1 ORDER BY starts_at DESC",
+            Self::get_table_name()
+        );
+        let recs = sqlx::query_as::<_, ClosedPeriod>(&query)
+            .bind(group_uid)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "listing closed periods by group"))?;
+        Ok(recs)
+    }
+
+    // The closed period covering all of [start, end), for marking reports of a closed cycle
+    pub async fn find_covering(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Option<ClosedPeriod>, DatabaseError> {
+        let query = format!(
+            "SELECT {CLOSED_PERIOD_COLUMNS} FROM {} WHERE group_uid = $1 AND starts_at <= $2 AND ends_at >= $3 LIMIT 1",
+            Self::get_table_name()
+        );
+        let rec = sqlx::query_as::<_, ClosedPeriod>(&query)
+            .bind(group_uid)
+            .bind(start)
+            .bind(end)
+            .fetch_optional(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "finding closed period"))?;
+        Ok(rec)
+    }
+
+    /*
+     Fails with `PeriodClosed` when `at` falls in a closed period of the group. Called by
+     `ExpenseEntryRepo::create_expense_entry`, so backdated entries from the routes, batches,
+     CSV imports, reconciliations and chat commands can't land in a locked cycle
+    */
+    pub async fn ensure_date_open(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+        at: DateTime<Utc>,
+    ) -> Result<(), DatabaseError> {
+        let query = format!(
+            "SELECT {CLOSED_PERIOD_COLUMNS} FROM {} WHERE group_uid = $1 AND starts_at <= $2 AND ends_at > $2 LIMIT 1",
+            Self::get_table_name()
+        );
+        let closed = sqlx::query_as::<_, ClosedPeriod>(&query)
+            .bind(group_uid)
+            .bind(at)
+            .fetch_optional(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "checking closed period of date"))?;
+        match closed {
+            Some(period) => Err(DatabaseError::PeriodClosed(period.label())),
+            None => Ok(()),
+        }
+    }
+
+    /*
+     Fails with `PeriodClosed` when the expense entry was created in a closed period. Called by
+     the `ExpenseEntryRepo` functions that change an entry, so routes, chat commands and undo
+     are all covered. Entries that don't exist pass, the change itself reports them
+    */
+    pub async fn ensure_entry_open(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        entry_uid: Uuid,
+    ) -> Result<(), DatabaseError> {
+        let query = format!(
+            "SELECT cp.group_uid, cp.period_year, cp.period_month, cp.starts_at, cp.ends_at, cp.closed_by, cp.closed_at \
+            FROM {} cp JOIN expense_entries e ON e.group_uid = cp.group_uid AND e.created_at >= cp.starts_at AND e.created_at < cp.ends_at \
+            WHERE e.uid = $1 LIMIT 1",
+            Self::get_table_name()
+        );
+        let closed = sqlx::query_as::<_, ClosedPeriod>(&query)
+            .bind(entry_uid)
+            .fetch_optional(tx.as_mut())
+            .await
+            .map_err(|e| {
+                DatabaseError::from_sqlx_error(e, "checking closed period of expense entry")
+            })?;
+        match closed {
+            Some(period) => Err(DatabaseError::PeriodClosed(period.label())),
+            None => Ok(()),
+        }
+    }
+
+    /*
+     Fails with `PeriodClosed` when an expense entry of the category, deleted ones included,
+     was created in a closed period. Called by `CategoryRepo::delete_reassigning` before it
+     moves the entries, so deleting a category can't recategorize a locked cycle
+    */
+    pub async fn ensure_category_open(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        category_uid: Uuid,
+    ) -> Result<(), DatabaseError> {
+        let query = format!(
+            "SELECT cp.group_uid, cp.period_year, cp.period_month, cp.starts_at, cp.ends_at, cp.closed_by, cp.closed_at \
+            FROM {} cp JOIN expense_entries e ON e.group_uid = cp.group_uid AND e.created_at >= cp.starts_at AND e.created_at < cp.ends_at \
+            WHERE e.category_uid = $1 ORDER BY cp.starts_at LIMIT 1",
+            Self::get_table_name()
+        );
+        let closed = sqlx::query_as::<_, ClosedPeriod>(&query)
+            .bind(category_uid)
+            .fetch_optional(tx.as_mut())
+            .await
+            .map_err(|e| {
+                DatabaseError::from_sqlx_error(e, "checking closed periods of category entries")
+            })?;
+        match closed {
+            Some(period) => Err(DatabaseError::PeriodClosed(period.label())),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,366 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::DatabaseError;
+use crate::repos::base::BaseRepo;
+use crate::utils::currency::RateTable;
+
+const DEBT_COLUMNS: &str = "uid, group_uid, direction, person, amount, currency, due_date, note, settled_at, reminded_at, created_at, updated_at";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DebtDirection {
+    // Someone outside the group owes the group
+    Lent,
+    // The group owes someone outside it
+    Borrowed,
+}
+
+impl DebtDirection {
+    // Accepts both the stored names and the Indonesian words used in chat
+    pub fn parse(input: &str) -> Option<Self> {
+        match input.trim().to_lowercase().as_str() {
+            "lent" | "piutang" => Some(Self::Lent),
+            "borrowed" | "utang" | "hutang" => Some(Self::Borrowed),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Lent => "lent",
+            Self::Borrowed => "borrowed",
+        }
+    }
+}
+
+/*
+ Money the group lent to or borrowed from someone outside it, e.g. a friend or a relative.
+ Debts between members are settlements instead. A debt stays open until it is settled.
+*/
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct Debt {
+    pub uid: Uuid,
+    pub group_uid: Uuid,
+    /// `lent` or `borrowed`, see `DebtDirection`
+    pub direction: String,
+    /// Who the money was lent to or borrowed from
+    pub person: String,
+    pub amount: Decimal,
+    pub currency: String,
+    pub due_date: Option<NaiveDate>,
+    pub note: Option<String>,
+    pub settled_at: Option<DateTime<Utc>>,
+    /// When the chats were reminded of the due date
+    pub reminded_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Debt {
+    pub fn direction(&self) -> DebtDirection {
+        DebtDirection::parse(&self.direction).unwrap_or(DebtDirection::Lent)
+    }
+
+    pub fn is_settled(&self) -> bool {
+        self.settled_at.is_some()
+    }
+}
+
+// Open debts summed in one currency
+#[derive(Debug, Clone, Default, PartialEq, Serialize, ToSchema)]
+pub struct OutstandingDebts {
+    pub currency: String,
+    /// Owed to the group
+    pub lent: Decimal,
+    /// Owed by the group
+    pub borrowed: Decimal,
+    /// Open debts past their due date
+    pub overdue: usize,
+    /// Currencies without a known exchange rate, their amounts are added unconverted
+    pub unconverted_currencies: Vec<String>,
+}
+
+impl OutstandingDebts {
+    pub fn of(debts: &[Debt], rates: &RateTable, currency: &str, today: NaiveDate) -> Self {
+        let mut outstanding = Self {
+            currency: currency.to_string(),
+            ..Default::default()
+        };
+        for debt in debts.iter().filter(|debt| !debt.is_settled()) {
+            let (amount, unconverted) =
+                rates.sum_in(&[(debt.currency.clone(), debt.amount)], currency);
+            for code in unconverted {
+                if !outstanding.unconverted_currencies.contains(&code) {
+                    outstanding.unconverted_currencies.push(code);
+                }
+            }
+            match debt.direction() {
+                DebtDirection::Lent => outstanding.lent += amount,
+                DebtDirection::Borrowed => outstanding.borrowed += amount,
+            }
+            if debt.due_date.is_some_and(|due_date| due_date < today) {
+                outstanding.overdue += 1;
+            }
+        }
+        outstanding
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lent.is_zero() && self.borrowed.is_zero()
+    }
+}
+
+#[derive(Debug)]
+pub struct CreateDebtDbPayload {
+    pub group_uid: Uuid,
+    pub direction: DebtDirection,
+    pub person: String,
+    pub amount: Decimal,
+    // Defaults to the group's currency when not provided
+    pub currency: Option<String>,
+    pub due_date: Option<NaiveDate>,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateDebtDbPayload {
+    pub person: Option<String>,
+    pub amount: Option<Decimal>,
+    pub currency: Option<String>,
+    pub due_date: Option<NaiveDate>,
+    pub note: Option<String>,
+}
+
+pub struct DebtRepo;
+
+impl BaseRepo for DebtRepo {
+    fn get_table_name() -> &'static str {
+        "debts"
+    }
+}
+
+impl DebtRepo {
+    // Open debts first, by due date with undated ones last, then settled ones
+    pub async fn list_by_group(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+        include_settled: bool,
+    ) -> Result<Vec<Debt>, DatabaseError> {
+        let query = format!(
+            "SELECT {DEBT_COLUMNS} FROM {} WHERE group_uid = $1 AND ($2 OR settled_at IS NULL) ORDER BY settled_at IS NOT NULL, due_date NULLS LAST, created_at",
+            Self::get_table_name()
+        );
+        let rows = sqlx::query_as::<_, Debt>(&query)
+            .bind(group_uid)
+            .bind(include_settled)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "listing debts by group"))?;
+        Ok(rows)
+    }
+
+    pub async fn get(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+    ) -> Result<Debt, DatabaseError> {
+        let query = format!(
+            "SELECT {DEBT_COLUMNS} FROM {} WHERE uid = $1",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, Debt>(&query)
+            .bind(uid)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "getting debt"))?;
+        Ok(row)
+    }
+
+    pub async fn create(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        payload: CreateDebtDbPayload,
+    ) -> Result<Debt, DatabaseError> {
+        let query = format!(
+            "INSERT INTO {} (uid, group_uid, direction, person, amount, currency, due_date, note) VALUES ($1, $2, $3, $4, $5, COALESCE($6, (SELECT currency FROM expense_groups WHERE uid = $2)), $7, $8) RETURNING {DEBT_COLUMNS}",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, Debt>(&query)
+            .bind(Uuid::new_v4())
+            .bind(payload.group_uid)
+            .bind(payload.direction.as_str())
+            .bind(payload.person)
+            .bind(payload.amount)
+            .bind(payload.currency)
+            .bind(payload.due_date)
+            .bind(payload.note)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "creating debt"))?;
+        Ok(row)
+    }
+
+    // A new due date is reminded of again
+    pub async fn update(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+        payload: UpdateDebtDbPayload,
+    ) -> Result<Debt, DatabaseError> {
+        let current = Self::get(tx, uid).await?;
+        let query = format!(
+            "UPDATE {} SET person = $1, amount = $2, currency = $3, due_date = $4, note = $5, reminded_at = CASE WHEN due_date IS DISTINCT FROM $4 THEN NULL ELSE reminded_at END, updated_at = now() WHERE uid = $6 RETURNING {DEBT_COLUMNS}",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, Debt>(&query)
+            .bind(payload.person.unwrap_or(current.person))
+            .bind(payload.amount.unwrap_or(current.amount))
+            .bind(payload.currency.unwrap_or(current.currency))
+            .bind(payload.due_date.or(current.due_date))
+            .bind(payload.note.or(current.note))
+            .bind(uid)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "updating debt"))?;
+        Ok(row)
+    }
+
+    // Marks the debt as paid back, or open again when `settled` is false
+    pub async fn set_settled(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+        settled: bool,
+    ) -> Result<Debt, DatabaseError> {
+        let query = format!(
+            "UPDATE {} SET settled_at = CASE WHEN $1 THEN COALESCE(settled_at, now()) END, updated_at = now() WHERE uid = $2 RETURNING {DEBT_COLUMNS}",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, Debt>(&query)
+            .bind(settled)
+            .bind(uid)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "settling debt"))?;
+        Ok(row)
+    }
+
+    pub async fn delete(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+    ) -> Result<(), DatabaseError> {
+        let query = format!("DELETE FROM {} WHERE uid = $1", Self::get_table_name());
+        sqlx::query(&query)
+            .bind(uid)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "deleting debt"))?;
+        Ok(())
+    }
+
+    /*
+     Open debts due on or before `until` whose chats were not reminded yet, of groups that are
+     neither archived nor deleted. Overdue debts created after their due date are included.
+    */
+    pub async fn list_due_for_reminder(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        until: NaiveDate,
+    ) -> Result<Vec<Debt>, DatabaseError> {
+        let query = format!(
+            "SELECT {DEBT_COLUMNS} FROM {} WHERE settled_at IS NULL AND reminded_at IS NULL AND due_date <= $1 AND group_uid IN (SELECT uid FROM expense_groups WHERE archived_at IS NULL AND deleted_at IS NULL) ORDER BY group_uid, due_date",
+            Self::get_table_name()
+        );
+        let rows = sqlx::query_as::<_, Debt>(&query)
+            .bind(until)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "listing debts due for reminder"))?;
+        Ok(rows)
+    }
+
+    pub async fn mark_reminded(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uids: &[Uuid],
+    ) -> Result<(), DatabaseError> {
+        let query = format!(
+            "UPDATE {} SET reminded_at = now() WHERE uid = ANY($1)",
+            Self::get_table_name()
+        );
+        sqlx::query(&query)
+            .bind(uids)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "marking debts as reminded"))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn debt(direction: DebtDirection, amount: Decimal, currency: &str) -> Debt {
+        Debt {
+            uid: Uuid::new_v4(),
+            group_uid: Uuid::new_v4(),
+            direction: direction.as_str().to_string(),
+            person: "Andi".to_string(),
+            amount,
+            currency: currency.to_string(),
+            due_date: None,
+            note: None,
+            settled_at: None,
+            reminded_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_direction_parse() {
+        assert_eq!(DebtDirection::parse("Piutang"), Some(DebtDirection::Lent));
+        assert_eq!(
+            DebtDirection::parse("hutang"),
+            Some(DebtDirection::Borrowed)
+        );
+        assert_eq!(
+            DebtDirection::parse("borrowed"),
+            Some(DebtDirection::Borrowed)
+        );
+        assert_eq!(DebtDirection::parse("pinjam"), None);
+    }
+
+    #[test]
+    fn test_outstanding_debts() {
+        let today = NaiveDate::from_ymd_opt(2025, 11, 10).unwrap();
+        let rates = RateTable::new(vec![("USD".to_string(), "IDR".to_string(), dec!(16000))]);
+        let overdue = Debt {
+            due_date: Some(NaiveDate::from_ymd_opt(2025, 11, 9).unwrap()),
+            ..debt(DebtDirection::Lent, dec!(500000), "IDR")
+        };
+        let due_today = Debt {
+            due_date: Some(today),
+            ..debt(DebtDirection::Borrowed, dec!(10), "USD")
+        };
+        let settled = Debt {
+            settled_at: Some(Utc::now()),
+            ..debt(DebtDirection::Lent, dec!(300000), "IDR")
+        };
+        let unknown = debt(DebtDirection::Lent, dec!(20), "JPY");
+
+        let outstanding = OutstandingDebts::of(
+            &[overdue, due_today, settled, unknown],
+            &rates,
+            "IDR",
+            today,
+        );
+        assert_eq!(outstanding.lent, dec!(500020));
+        assert_eq!(outstanding.borrowed, dec!(160000));
+        assert_eq!(outstanding.overdue, 1);
+        assert_eq!(outstanding.unconverted_currencies, vec!["JPY".to_string()]);
+        assert!(!outstanding.is_empty());
+
+        assert!(OutstandingDebts::of(&[], &rates, "IDR", today).is_empty());
+    }
+}
@@ -0,0 +1,105 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::error::DatabaseError;
+use crate::repos::base::BaseRepo;
+use crate::utils::secret_token::generate_token;
+
+const ADDRESS_COLUMNS: &str = "group_uid, token, created_by, created_at";
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct EmailIngestAddress {
+    pub group_uid: Uuid,
+    // Local part of the address, anyone who knows it can add drafts to the group
+    pub token: String,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+/*
+ The address forwarded receipts are sent to, at most one per group. Rotating gives the group a
+ new token, mail to the old address is rejected from then on.
+*/
+pub struct EmailIngestAddressRepo;
+
+impl BaseRepo for EmailIngestAddressRepo {
+    fn get_table_name() -> &'static str {
+        "email_ingest_addresses"
+    }
+}
+
+impl EmailIngestAddressRepo {
+    pub async fn get_by_group(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+    ) -> Result<Option<EmailIngestAddress>, DatabaseError> {
+        let query = format!(
+            "SELECT {ADDRESS_COLUMNS} FROM {} WHERE group_uid = $1",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, EmailIngestAddress>(&query)
+            .bind(group_uid)
+            .fetch_optional(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "getting email ingest address"))?;
+        Ok(row)
+    }
+
+    // Creates the group's address, or replaces its token when there is one already
+    pub async fn rotate(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+        created_by: Uuid,
+    ) -> Result<EmailIngestAddress, DatabaseError> {
+        let query = format!(
+            "INSERT INTO {} (group_uid, token, created_by) VALUES ($1, $2, $3) \
+            ON CONFLICT (group_uid) DO UPDATE SET token = EXCLUDED.token, created_by = EXCLUDED.created_by, created_at = now() \
+            RETURNING {ADDRESS_COLUMNS}",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, EmailIngestAddress>(&query)
+            .bind(group_uid)
+            .bind(generate_token())
+            .bind(created_by)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "rotating email ingest address"))?;
+        Ok(row)
+    }
+
+    // False when the group had no address
+    pub async fn delete(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+    ) -> Result<bool, DatabaseError> {
+        let query = format!(
+            "DELETE FROM {} WHERE group_uid = $1",
+            Self::get_table_name()
+        );
+        let result = sqlx::query(&query)
+            .bind(group_uid)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "deleting email ingest address"))?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    // The group receiving mail sent to `token`, None for unknown tokens and deleted groups
+    pub async fn find_group_by_token(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        token: &str,
+    ) -> Result<Option<Uuid>, DatabaseError> {
+        let query = format!(
+            "SELECT a.group_uid FROM {} a JOIN expense_groups g ON g.uid = a.group_uid AND g.deleted_at IS NULL WHERE a.token = $1",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_scalar::<_, Uuid>(&query)
+            .bind(token)
+            .fetch_optional(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "finding group by ingest token"))?;
+        Ok(row)
+    }
+}
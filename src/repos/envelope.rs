@@ -0,0 +1,259 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::DatabaseError;
+use crate::repos::base::BaseRepo;
+
+const ENVELOPE_COLUMNS: &str =
+    "uid, group_uid, name, start_date, end_date, budget, currency, created_at, updated_at";
+
+/*
+ A named, time-boxed bucket of spending inside a group (e.g. "Bali trip"). Entries
+ assigned to an envelope count against its own budget and are reported apart from
+ the group's regular monthly cycle.
+*/
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct Envelope {
+    pub uid: Uuid,
+    pub group_uid: Uuid,
+    pub name: String,
+    pub start_date: NaiveDate,
+    // Inclusive
+    pub end_date: NaiveDate,
+    pub budget: Option<Decimal>,
+    pub currency: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Envelope {
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        (self.start_date..=self.end_date).contains(&date)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateEnvelopeDbPayload {
+    pub group_uid: Uuid,
+    pub name: String,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub budget: Option<Decimal>,
+    // Defaults to the group's currency when not provided
+    pub currency: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateEnvelopeDbPayload {
+    pub name: Option<String>,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    pub budget: Option<Decimal>,
+    pub currency: Option<String>,
+}
+
+pub struct EnvelopeRepo;
+
+impl BaseRepo for EnvelopeRepo {
+    fn get_table_name() -> &'static str {
+        "envelopes"
+    }
+}
+
+impl EnvelopeRepo {
+    // Most recent envelopes first
+    pub async fn list_by_group(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+    ) -> Result<Vec<Envelope>, DatabaseError> {
+        let query = format!(
+            "SELECT {ENVELOPE_COLUMNS} FROM {} WHERE group_uid = $1 ORDER BY start_date DESC, name",
+            Self::get_table_name()
+        );
+        let rows = sqlx::query_as::<_, Envelope>(&query)
+            .bind(group_uid)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "listing envelopes by group"))?;
+        Ok(rows)
+    }
+
+    // Envelopes whose dates overlap [start, end], both inclusive
+    pub async fn list_overlapping(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<Vec<Envelope>, DatabaseError> {
+        let query = format!(
+            "SELECT {ENVELOPE_COLUMNS} FROM {} WHERE group_uid = $1 AND start_date <= $3 AND end_date >= $2 ORDER BY start_date, name",
+            Self::get_table_name()
+        );
+        let rows = sqlx::query_as::<_, Envelope>(&query)
+            .bind(group_uid)
+            .bind(start)
+            .bind(end)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "listing overlapping envelopes"))?;
+        Ok(rows)
+    }
+
+    pub async fn get(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+    ) -> Result<Envelope, DatabaseError> {
+        let query = format!(
+            "SELECT {ENVELOPE_COLUMNS} FROM {} WHERE uid = $1",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, Envelope>(&query)
+            .bind(uid)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "getting envelope"))?;
+        Ok(row)
+    }
+
+    // Names are matched case-insensitively
+    pub async fn find_by_name(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+        name: &str,
+    ) -> Result<Option<Envelope>, DatabaseError> {
+        let query = format!(
+            "SELECT {ENVELOPE_COLUMNS} FROM {} WHERE group_uid = $1 AND lower(name) = lower($2)",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, Envelope>(&query)
+            .bind(group_uid)
+            .bind(name.trim())
+            .fetch_optional(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "finding envelope by name"))?;
+        Ok(row)
+    }
+
+    pub async fn create(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        payload: CreateEnvelopeDbPayload,
+    ) -> Result<Envelope, DatabaseError> {
+        let query = format!(
+            "INSERT INTO {} (uid, group_uid, name, start_date, end_date, budget, currency) VALUES ($1, $2, $3, $4, $5, $6, COALESCE($7, (SELECT currency FROM expense_groups WHERE uid = $2))) RETURNING {ENVELOPE_COLUMNS}",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, Envelope>(&query)
+            .bind(Uuid::new_v4())
+            .bind(payload.group_uid)
+            .bind(payload.name)
+            .bind(payload.start_date)
+            .bind(payload.end_date)
+            .bind(payload.budget)
+            .bind(payload.currency)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "creating envelope"))?;
+        Ok(row)
+    }
+
+    pub async fn update(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+        payload: UpdateEnvelopeDbPayload,
+    ) -> Result<Envelope, DatabaseError> {
+        let current = Self::get(tx, uid).await?;
+        let query = format!(
+            "UPDATE {} SET name = $1, start_date = $2, end_date = $3, budget = $4, currency = $5, updated_at = now() WHERE uid = $6 RETURNING {ENVELOPE_COLUMNS}",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, Envelope>(&query)
+            .bind(payload.name.unwrap_or(current.name))
+            .bind(payload.start_date.unwrap_or(current.start_date))
+            .bind(payload.end_date.unwrap_or(current.end_date))
+            .bind(payload.budget.or(current.budget))
+            .bind(payload.currency.unwrap_or(current.currency))
+            .bind(uid)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "updating envelope"))?;
+        Ok(row)
+    }
+
+    // Entries assigned to the envelope are kept and fall back to the regular cycle
+    pub async fn delete(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+    ) -> Result<(), DatabaseError> {
+        let query = format!("DELETE FROM {} WHERE uid = $1", Self::get_table_name());
+        sqlx::query(&query)
+            .bind(uid)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "deleting envelope"))?;
+        Ok(())
+    }
+
+    /*
+     Totals per category name (None when uncategorized) and currency of the live entries
+     in the envelope, optionally only those recorded by `created_by_user_uid`. Subcategories
+     are counted under their parent's name.
+    */
+    pub async fn totals_by_category(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+        created_by_user_uid: Option<Uuid>,
+    ) -> Result<Vec<(Option<String>, String, Decimal)>, DatabaseError> {
+        let query = "SELECT COALESCE(p.name, c.name), e.currency, COALESCE(SUM(e.price), 0) FROM expense_entries e \
+            LEFT JOIN categories c ON e.category_uid = c.uid LEFT JOIN categories p ON c.parent_uid = p.uid \
+            WHERE e.envelope_uid = $1 AND ($2::uuid IS NULL OR e.created_by_user_uid = $2) AND e.deleted_at IS NULL GROUP BY COALESCE(p.name, c.name), e.currency";
+        let totals = sqlx::query_as::<_, (Option<String>, String, Decimal)>(query)
+            .bind(uid)
+            .bind(created_by_user_uid)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| {
+                DatabaseError::from_sqlx_error(e, "summing envelope entries per category")
+            })?;
+        Ok(totals)
+    }
+
+    // The envelope new expenses from the chat binding are assigned to, if any
+    pub async fn get_active_for_binding(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        binding_id: Uuid,
+    ) -> Result<Option<Envelope>, DatabaseError> {
+        let query = format!(
+            "SELECT {ENVELOPE_COLUMNS} FROM {} WHERE uid = (SELECT envelope_uid FROM chat_bindings WHERE id = $1)",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, Envelope>(&query)
+            .bind(binding_id)
+            .fetch_optional(tx.as_mut())
+            .await
+            .map_err(|e| {
+                DatabaseError::from_sqlx_error(e, "getting active envelope of chat binding")
+            })?;
+        Ok(row)
+    }
+
+    // Switches the chat binding to `envelope_uid`, None goes back to the regular cycle
+    pub async fn set_active_for_binding(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        binding_id: Uuid,
+        envelope_uid: Option<Uuid>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query("UPDATE chat_bindings SET envelope_uid = $1 WHERE id = $2")
+            .bind(envelope_uid)
+            .bind(binding_id)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| {
+                DatabaseError::from_sqlx_error(e, "setting active envelope of chat binding")
+            })?;
+        Ok(())
+    }
+}
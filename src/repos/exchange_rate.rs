@@ -0,0 +1,78 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+
+use crate::error::DatabaseError;
+use crate::repos::base::BaseRepo;
+use crate::utils::currency::RateTable;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct ExchangeRate {
+    pub base: String,
+    pub quote: String,
+    // 1 unit of `base` in `quote`
+    pub rate: Decimal,
+    // Provider the rate was synced from, or "manual"
+    pub source: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+pub struct ExchangeRateRepo;
+
+impl BaseRepo for ExchangeRateRepo {
+    fn get_table_name() -> &'static str {
+        "exchange_rates"
+    }
+}
+
+impl ExchangeRateRepo {
+    pub async fn list(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<Vec<ExchangeRate>, DatabaseError> {
+        let query = format!(
+            "SELECT base, quote, rate, source, updated_at FROM {} ORDER BY base, quote",
+            Self::get_table_name()
+        );
+        let rows = sqlx::query_as::<_, ExchangeRate>(&query)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "listing exchange rates"))?;
+        Ok(rows)
+    }
+
+    pub async fn rate_table(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<RateTable, DatabaseError> {
+        let rates = Self::list(tx).await?;
+        Ok(RateTable::new(
+            rates
+                .into_iter()
+                .map(|rate| (rate.base, rate.quote, rate.rate)),
+        ))
+    }
+
+    pub async fn upsert(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        base: &str,
+        quote: &str,
+        rate: Decimal,
+        source: &str,
+    ) -> Result<(), DatabaseError> {
+        let query = format!(
+            "INSERT INTO {} (base, quote, rate, source) VALUES ($1, $2, $3, $4) \
+            ON CONFLICT (base, quote) DO UPDATE SET rate = EXCLUDED.rate, source = EXCLUDED.source, updated_at = now()",
+            Self::get_table_name()
+        );
+        sqlx::query(&query)
+            .bind(base)
+            .bind(quote)
+            .bind(rate)
+            .bind(source)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "saving exchange rate"))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,146 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::DatabaseError;
+use crate::repos::base::BaseRepo;
+
+const DRAFT_COLUMNS: &str = "uid, group_uid, source, merchant, price, currency, spent_at, sender, subject, body, created_at";
+
+/*
+ An expense read from a forwarded receipt, waiting for a member to confirm it by creating the
+ entry with `draft_uid`, or to discard it
+*/
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct ExpenseDraft {
+    pub uid: Uuid,
+    pub group_uid: Uuid,
+    /// Where the draft came from, `email`
+    pub source: String,
+    /// Suggested product, usually the store or service that sent the receipt
+    pub merchant: Option<String>,
+    /// Total found on the receipt, none when no amount could be read
+    pub price: Option<Decimal>,
+    /// ISO 4217 code printed next to the total, if any
+    pub currency: Option<String>,
+    /// Date on the receipt, or when the email arrived
+    pub spent_at: DateTime<Utc>,
+    pub sender: String,
+    pub subject: String,
+    /// Plain text of the email, shortened
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub struct CreateExpenseDraftDbPayload {
+    pub group_uid: Uuid,
+    pub source: String,
+    pub merchant: Option<String>,
+    pub price: Option<Decimal>,
+    pub currency: Option<String>,
+    pub spent_at: DateTime<Utc>,
+    pub sender: String,
+    pub subject: String,
+    pub body: String,
+}
+
+pub struct ExpenseDraftRepo;
+
+impl BaseRepo for ExpenseDraftRepo {
+    fn get_table_name() -> &'static str {
+        "expense_drafts"
+    }
+}
+
+impl ExpenseDraftRepo {
+    pub async fn create(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        payload: CreateExpenseDraftDbPayload,
+    ) -> Result<ExpenseDraft, DatabaseError> {
+        let query = format!(
+            "INSERT INTO {} (group_uid, source, merchant, price, currency, spent_at, sender, subject, body) \
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) RETURNING {DRAFT_COLUMNS}",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, ExpenseDraft>(&query)
+            .bind(payload.group_uid)
+            .bind(payload.source)
+            .bind(payload.merchant)
+            .bind(payload.price)
+            .bind(payload.currency)
+            .bind(payload.spent_at)
+            .bind(payload.sender)
+            .bind(payload.subject)
+            .bind(payload.body)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "creating expense draft"))?;
+        Ok(row)
+    }
+
+    pub async fn get(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+    ) -> Result<ExpenseDraft, DatabaseError> {
+        let query = format!(
+            "SELECT {DRAFT_COLUMNS} FROM {} WHERE uid = $1",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, ExpenseDraft>(&query)
+            .bind(uid)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "getting expense draft"))?;
+        Ok(row)
+    }
+
+    // Newest first
+    pub async fn list_by_group(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+    ) -> Result<Vec<ExpenseDraft>, DatabaseError> {
+        let query = format!(
+            "SELECT {DRAFT_COLUMNS} FROM {} WHERE group_uid = $1 ORDER BY created_at DESC",
+            Self::get_table_name()
+        );
+        let rows = sqlx::query_as::<_, ExpenseDraft>(&query)
+            .bind(group_uid)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "listing expense drafts"))?;
+        Ok(rows)
+    }
+
+    pub async fn count_by_group(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+    ) -> Result<i64, DatabaseError> {
+        let query = format!(
+            "SELECT COUNT(*) FROM {} WHERE group_uid = $1",
+            Self::get_table_name()
+        );
+        let count = sqlx::query_scalar::<_, i64>(&query)
+            .bind(group_uid)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "counting expense drafts"))?;
+        Ok(count)
+    }
+
+    pub async fn delete(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+    ) -> Result<(), DatabaseError> {
+        let query = format!("DELETE FROM {} WHERE uid = $1", Self::get_table_name());
+        sqlx::query(&query)
+            .bind(uid)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "deleting expense draft"))?;
+        Ok(())
+    }
+}
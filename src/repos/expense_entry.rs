@@ -1,4 +1,5 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDate, Utc};
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use utoipa::ToSchema;
@@ -6,9 +7,15 @@ use uuid::Uuid;
 
 use crate::error::DatabaseError;
 use crate::repos::base::BaseRepo;
+use crate::repos::closed_period::ClosedPeriodRepo;
+use crate::utils::cursor::Cursor;
 
 pub struct ExpenseEntryRepo;
 
+// Columns of `ExpenseEntry`, `receipt_url` points at the download route when a receipt was uploaded
+const ENTRY_COLUMNS: &str = "uid, price, currency, product, created_by, created_by_user_uid, group_uid, category_uid, created_at, updated_at, note, envelope_uid, \
+    CASE WHEN EXISTS (SELECT 1 FROM receipts r WHERE r.expense_uid = expense_entries.uid) THEN '/expense-entries/' || expense_entries.uid || '/receipt' END AS receipt_url";
+
 impl BaseRepo for ExpenseEntryRepo {
     fn get_table_name() -> &'static str {
         "expense_entries"
@@ -18,30 +25,200 @@ impl BaseRepo for ExpenseEntryRepo {
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
 pub struct ExpenseEntry {
     pub uid: Uuid,
-    pub price: f64,
+    pub price: Decimal,
+    pub currency: String,
     pub product: String,
     pub created_by: String,
+    // Set when the creator maps to a user account (web, or a chat the sender bound)
+    pub created_by_user_uid: Option<Uuid>,
 
     pub group_uid: Uuid,
     pub category_uid: Option<Uuid>,
 
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+
+    pub note: Option<String>,
+    pub receipt_url: Option<String>,
+    // Set when the entry belongs to an envelope (e.g. a trip) instead of the regular cycle
+    pub envelope_uid: Option<Uuid>,
+}
+
+/*
+ Human readable creator of an entry. Linked users are shown by the part of their email
+ before the `@`, otherwise the stored name is used unless it is a placeholder
+ ("system", "recurring" or a bare user id).
+*/
+pub fn creator_name(created_by: &str, creator_email: Option<&str>) -> Option<String> {
+    if let Some(email) = creator_email {
+        return email.split('@').next().map(|name| name.to_string());
+    }
+    let created_by = created_by.trim();
+    if created_by.is_empty()
+        || matches!(created_by, "system" | "recurring")
+        || Uuid::parse_str(created_by).is_ok()
+    {
+        return None;
+    }
+    Some(created_by.to_string())
+}
+
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct ExpenseEntrySearchResult {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub entry: ExpenseEntry,
+    // Higher is a better match
+    pub rank: f32,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct CreateExpenseEntryDbPayload {
-    pub price: f64,
+    pub price: Decimal,
+    // Defaults to the group's currency when not provided
+    pub currency: Option<String>,
     pub product: String,
     pub group_uid: Uuid,
     pub category_uid: Option<Uuid>,
+    // Display name of the creator, e.g. the chat sender's name or the user's email
+    pub created_by: String,
+    pub created_by_user_uid: Option<Uuid>,
+    // Defaults to now() when not provided (e.g. imported or backdated entries)
+    pub created_at: Option<DateTime<Utc>>,
+    pub note: Option<String>,
+    pub envelope_uid: Option<Uuid>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct UpdateExpenseEntryDbPayload {
-    pub price: Option<f64>,
+    pub price: Option<Decimal>,
+    pub currency: Option<String>,
     pub product: Option<String>,
     pub category_uid: Option<Uuid>,
+    // Replaces the note when set, an empty string clears it
+    pub note: Option<String>,
+    pub envelope_uid: Option<Uuid>,
+    // The `updated_at` the caller last saw, the update fails when the entry changed since
+    pub expected_updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ExpenseEntrySort {
+    #[default]
+    CreatedAtDesc,
+    CreatedAtAsc,
+    PriceDesc,
+    PriceAsc,
+    ProductAsc,
+    ProductDesc,
+}
+
+impl ExpenseEntrySort {
+    // `field` sorts ascending, `-field` sorts descending
+    pub fn parse(input: &str) -> Option<Self> {
+        match input.trim() {
+            "-created_at" => Some(Self::CreatedAtDesc),
+            "created_at" => Some(Self::CreatedAtAsc),
+            "-price" => Some(Self::PriceDesc),
+            "price" => Some(Self::PriceAsc),
+            "product" => Some(Self::ProductAsc),
+            "-product" => Some(Self::ProductDesc),
+            _ => None,
+        }
+    }
+
+    fn order_by(&self) -> &'static str {
+        match self {
+            Self::CreatedAtDesc => "created_at DESC, uid DESC",
+            Self::CreatedAtAsc => "created_at ASC, uid ASC",
+            Self::PriceDesc => "price DESC, created_at DESC",
+            Self::PriceAsc => "price ASC, created_at DESC",
+            Self::ProductAsc => "product ASC, created_at DESC",
+            Self::ProductDesc => "product DESC, created_at DESC",
+        }
+    }
+
+    // Rows after a cursor, only the `created_at` sorts are ordered by `(created_at, uid)`
+    fn after_cursor(&self) -> Option<&'static str> {
+        match self {
+            Self::CreatedAtDesc => Some("(created_at, uid) < ($11, $12)"),
+            Self::CreatedAtAsc => Some("(created_at, uid) > ($11, $12)"),
+            _ => None,
+        }
+    }
+
+    pub fn supports_cursor(&self) -> bool {
+        self.after_cursor().is_some()
+    }
+}
+
+// Size of the time buckets spending statistics are grouped in, weeks start on Monday
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum StatsGranularity {
+    #[default]
+    Day,
+    Week,
+    Month,
+}
+
+impl StatsGranularity {
+    pub fn parse(input: &str) -> Option<Self> {
+        match input.trim() {
+            "day" => Some(Self::Day),
+            "week" => Some(Self::Week),
+            "month" => Some(Self::Month),
+            _ => None,
+        }
+    }
+
+    // Also the unit passed to Postgres' date_trunc
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Day => "day",
+            Self::Week => "week",
+            Self::Month => "month",
+        }
+    }
+
+    // Start of the bucket `date` falls in, matches Postgres' date_trunc
+    pub fn truncate(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Self::Day => date,
+            Self::Week => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+            Self::Month => date.with_day(1).unwrap(),
+        }
+    }
+
+    // Starts of every bucket overlapping [from, to], including empty ones
+    pub fn bucket_starts(&self, from: NaiveDate, to: NaiveDate) -> Vec<NaiveDate> {
+        let mut starts = Vec::new();
+        let mut start = self.truncate(from);
+        while start <= to {
+            starts.push(start);
+            start = match self {
+                Self::Day => start + Duration::days(1),
+                Self::Week => start + Duration::weeks(1),
+                Self::Month => start + Months::new(1),
+            };
+        }
+        starts
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ExpenseEntryListFilter {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>, // exclusive
+    pub category_uid: Option<Uuid>,
+    pub tag_uid: Option<Uuid>,
+    pub envelope_uid: Option<Uuid>,
+    pub min_price: Option<Decimal>,
+    pub max_price: Option<Decimal>,
+    pub sort: ExpenseEntrySort,
+    pub limit: i64,
+    pub offset: i64,
+    // Keyset pagination, ignored unless the sort supports cursors
+    pub after: Option<Cursor>,
 }
 
 impl ExpenseEntryRepo {
@@ -49,9 +226,15 @@ impl ExpenseEntryRepo {
         tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
         payload: CreateExpenseEntryDbPayload,
     ) -> Result<ExpenseEntry, DatabaseError> {
+        ClosedPeriodRepo::ensure_date_open(
+            tx,
+            payload.group_uid,
+            payload.created_at.unwrap_or_else(Utc::now),
+        )
+        .await?;
         let uid = uuid::Uuid::new_v4();
         let query = format!(
-            "INSERT INTO {} (uid, price, product, group_uid, category_uid, created_by) VALUES ($1, $2, $3, $4, $5, $6) RETURNING uid, price::float8 AS price, product, created_by, group_uid, category_uid, created_at, updated_at",
+            "INSERT INTO {} (uid, price, product, group_uid, category_uid, created_by, created_by_user_uid, created_at, currency, note, envelope_uid) VALUES ($1, $2, $3, $4, $5, $6, $7, COALESCE($8, now()), COALESCE($9, (SELECT currency FROM expense_groups WHERE uid = $4)), $10, $11) RETURNING {ENTRY_COLUMNS}",
             Self::get_table_name()
         );
         let rec = sqlx::query_as::<_, ExpenseEntry>(&query)
@@ -60,7 +243,12 @@ impl ExpenseEntryRepo {
             .bind(payload.product)
             .bind(payload.group_uid)
             .bind(payload.category_uid)
-            .bind("system")
+            .bind(payload.created_by)
+            .bind(payload.created_by_user_uid)
+            .bind(payload.created_at)
+            .bind(payload.currency)
+            .bind(payload.note)
+            .bind(payload.envelope_uid)
             .fetch_one(tx.as_mut())
             .await
             .map_err(|e| DatabaseError::from_sqlx_error(e, "creating expense entry"))?;
@@ -71,7 +259,7 @@ impl ExpenseEntryRepo {
         tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
     ) -> Result<Vec<ExpenseEntry>, DatabaseError> {
         let query = format!(
-            "SELECT uid, price::float8 AS price, product, created_by, group_uid, category_uid, created_at, updated_at FROM {} ORDER BY created_at DESC",
+            "SELECT {ENTRY_COLUMNS} FROM {} WHERE deleted_at IS NULL ORDER BY created_at DESC",
             Self::get_table_name()
         );
         let recs = sqlx::query_as::<_, ExpenseEntry>(&query)
@@ -86,7 +274,7 @@ impl ExpenseEntryRepo {
         group_uid: Uuid,
     ) -> Result<Vec<ExpenseEntry>, DatabaseError> {
         let query = format!(
-            "SELECT uid, price::float8 AS price, product, created_by, group_uid, category_uid, created_at, updated_at FROM {} WHERE group_uid = $1 ORDER BY created_at DESC",
+            "SELECT {ENTRY_COLUMNS} FROM {} WHERE group_uid = $1 AND deleted_at IS NULL ORDER BY created_at DESC",
             Self::get_table_name()
         );
         let recs = sqlx::query_as::<_, ExpenseEntry>(&query)
@@ -97,12 +285,294 @@ impl ExpenseEntryRepo {
         Ok(recs)
     }
 
+    // Returns one page of entries along with the total number of matching rows, `after` does not change the total
+    pub async fn list_by_group_filtered(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+        filter: &ExpenseEntryListFilter,
+    ) -> Result<(Vec<ExpenseEntry>, i64), DatabaseError> {
+        let conditions = "group_uid = $1 AND deleted_at IS NULL \
+            AND ($2::timestamptz IS NULL OR created_at >= $2) \
+            AND ($3::timestamptz IS NULL OR created_at < $3) \
+            AND ($4::uuid IS NULL OR category_uid = $4) \
+            AND ($5::numeric IS NULL OR price >= $5) \
+            AND ($6::numeric IS NULL OR price <= $6) \
+            AND ($7::uuid IS NULL OR EXISTS (SELECT 1 FROM expense_tags et WHERE et.expense_uid = expense_entries.uid AND et.tag_uid = $7)) \
+            AND ($8::uuid IS NULL OR envelope_uid = $8)";
+
+        let count_query = format!(
+            "SELECT COUNT(*) FROM {} WHERE {}",
+            Self::get_table_name(),
+            conditions
+        );
+        let total = sqlx::query_scalar::<_, i64>(&count_query)
+            .bind(group_uid)
+            .bind(filter.from)
+            .bind(filter.to)
+            .bind(filter.category_uid)
+            .bind(filter.min_price)
+            .bind(filter.max_price)
+            .bind(filter.tag_uid)
+            .bind(filter.envelope_uid)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "counting expense entries by group"))?;
+
+        let after = match (filter.after, filter.sort.after_cursor()) {
+            (Some(_), Some(condition)) => format!(" AND {condition}"),
+            _ => String::new(),
+        };
+        let query = format!(
+            "SELECT {ENTRY_COLUMNS} FROM {} WHERE {}{} ORDER BY {} LIMIT $9 OFFSET $10",
+            Self::get_table_name(),
+            conditions,
+            after,
+            filter.sort.order_by()
+        );
+        let recs = sqlx::query_as::<_, ExpenseEntry>(&query)
+            .bind(group_uid)
+            .bind(filter.from)
+            .bind(filter.to)
+            .bind(filter.category_uid)
+            .bind(filter.min_price)
+            .bind(filter.max_price)
+            .bind(filter.tag_uid)
+            .bind(filter.envelope_uid)
+            .bind(filter.limit)
+            .bind(filter.offset)
+            .bind(filter.after.map(|c| c.created_at))
+            .bind(filter.after.map(|c| c.uid))
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "listing expense entries by group"))?;
+        Ok((recs, total))
+    }
+
+    /*
+     Matches `term` against the product name using full-text search for whole words
+     and trigram similarity for typos and partial words ("kopi" finds "Kopi Susu",
+     "cofee" finds "Coffee"). Results are ordered by the better of both scores.
+    */
+    pub async fn search_by_group(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+        term: &str,
+        limit: i64,
+    ) -> Result<Vec<ExpenseEntrySearchResult>, DatabaseError> {
+        let query = format!(
+            "SELECT {ENTRY_COLUMNS}, \
+                GREATEST(ts_rank(to_tsvector('simple', product), plainto_tsquery('simple', $2)), similarity(product, $2))::float4 AS rank \
+            FROM {} \
+            WHERE group_uid = $1 AND deleted_at IS NULL \
+                AND (to_tsvector('simple', product) @@ plainto_tsquery('simple', $2) OR product % $2 OR product ILIKE '%' || $2 || '%') \
+            ORDER BY rank DESC, created_at DESC \
+            LIMIT $3",
+            Self::get_table_name()
+        );
+        let recs = sqlx::query_as::<_, ExpenseEntrySearchResult>(&query)
+            .bind(group_uid)
+            .bind(term)
+            .bind(limit)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "searching expense entries"))?;
+        Ok(recs)
+    }
+
+    /*
+     Totals per currency, so callers can convert them to the currency they report in.
+     Entries assigned to an envelope count against the envelope's budget, not this one.
+     Spending in the category's subcategories is included.
+    */
+    pub async fn sum_by_category_in_range(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+        category_uid: Uuid,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<(String, Decimal)>, DatabaseError> {
+        let query = format!(
+            "SELECT currency, COALESCE(SUM(price), 0) FROM {} WHERE group_uid = $1 AND (category_uid = $2 OR category_uid IN (SELECT uid FROM categories WHERE parent_uid = $2)) AND envelope_uid IS NULL AND deleted_at IS NULL AND created_at >= $3 AND created_at < $4 GROUP BY currency",
+            Self::get_table_name()
+        );
+        let totals = sqlx::query_as::<_, (String, Decimal)>(&query)
+            .bind(group_uid)
+            .bind(category_uid)
+            .bind(start)
+            .bind(end)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "summing expense entries by category"))?;
+        Ok(totals)
+    }
+
+    /*
+     Totals per category name (None when uncategorized) and currency, for the whole group
+     or only the entries recorded by `created_by_user_uid`, optionally only those tagged
+     with `tag_uid`. Entries assigned to an envelope are left out unless `include_envelopes`.
+     Subcategories are counted under their parent's name.
+    */
+    pub async fn totals_by_category_in_range(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+        created_by_user_uid: Option<Uuid>,
+        tag_uid: Option<Uuid>,
+        include_envelopes: bool,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<(Option<String>, String, Decimal)>, DatabaseError> {
+        let query = format!(
+            "SELECT COALESCE(p.name, c.name), e.currency, COALESCE(SUM(e.price), 0) FROM {} e LEFT JOIN categories c ON e.category_uid = c.uid LEFT JOIN categories p ON c.parent_uid = p.uid WHERE e.group_uid = $1 AND ($2::uuid IS NULL OR e.created_by_user_uid = $2) AND e.deleted_at IS NULL AND e.created_at >= $3 AND e.created_at < $4 AND ($5::uuid IS NULL OR EXISTS (SELECT 1 FROM expense_tags et WHERE et.expense_uid = e.uid AND et.tag_uid = $5)) AND ($6 OR e.envelope_uid IS NULL) GROUP BY COALESCE(p.name, c.name), e.currency",
+            Self::get_table_name()
+        );
+        let totals = sqlx::query_as::<_, (Option<String>, String, Decimal)>(&query)
+            .bind(group_uid)
+            .bind(created_by_user_uid)
+            .bind(start)
+            .bind(end)
+            .bind(tag_uid)
+            .bind(include_envelopes)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| {
+                DatabaseError::from_sqlx_error(e, "summing expense entries per category")
+            })?;
+        Ok(totals)
+    }
+
+    /*
+     Totals per UTC day, category and currency, oldest day first. Subcategories are counted
+     under their parent, uncategorized entries under None, and entries assigned to an envelope
+     are left out like in the regular cycle.
+    */
+    pub async fn totals_by_day_and_category(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<(NaiveDate, Option<Uuid>, Option<String>, String, Decimal)>, DatabaseError>
+    {
+        let query = format!(
+            "SELECT (e.created_at AT TIME ZONE 'UTC')::date AS day, COALESCE(p.uid, c.uid), COALESCE(p.name, c.name), e.currency, COALESCE(SUM(e.price), 0) FROM {} e \
+            LEFT JOIN categories c ON e.category_uid = c.uid LEFT JOIN categories p ON c.parent_uid = p.uid \
+            WHERE e.group_uid = $1 AND e.envelope_uid IS NULL AND e.deleted_at IS NULL AND e.created_at >= $2 AND e.created_at < $3 \
+            GROUP BY day, COALESCE(p.uid, c.uid), COALESCE(p.name, c.name), e.currency ORDER BY day",
+            Self::get_table_name()
+        );
+        let totals =
+            sqlx::query_as::<_, (NaiveDate, Option<Uuid>, Option<String>, String, Decimal)>(&query)
+                .bind(group_uid)
+                .bind(start)
+                .bind(end)
+                .fetch_all(tx.as_mut())
+                .await
+                .map_err(|e| {
+                    DatabaseError::from_sqlx_error(e, "summing expense entries per day")
+                })?;
+        Ok(totals)
+    }
+
+    /*
+     Total spent in each [start, end) window, in the order the windows are given, in a
+     single query. Amounts are summed as recorded, without currency conversion, and
+     entries assigned to an envelope are left out like in the regular cycle.
+    */
+    pub async fn sum_by_windows(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+        created_by_user_uid: Option<Uuid>,
+        windows: &[(DateTime<Utc>, DateTime<Utc>)],
+    ) -> Result<Vec<Decimal>, DatabaseError> {
+        let (starts, ends): (Vec<DateTime<Utc>>, Vec<DateTime<Utc>>) =
+            windows.iter().copied().unzip();
+        let query = format!(
+            "SELECT COALESCE(SUM(e.price), 0) FROM unnest($2::timestamptz[], $3::timestamptz[]) WITH ORDINALITY AS w(start_at, end_at, idx) \
+            LEFT JOIN {} e ON e.group_uid = $1 AND ($4::uuid IS NULL OR e.created_by_user_uid = $4) AND e.envelope_uid IS NULL AND e.deleted_at IS NULL AND e.created_at >= w.start_at AND e.created_at < w.end_at \
+            GROUP BY w.idx ORDER BY w.idx",
+            Self::get_table_name()
+        );
+        let totals = sqlx::query_scalar::<_, Decimal>(&query)
+            .bind(group_uid)
+            .bind(starts)
+            .bind(ends)
+            .bind(created_by_user_uid)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "summing expense entries by window"))?;
+        Ok(totals)
+    }
+
+    /*
+     Totals and entry counts per bucket start (UTC) and currency, oldest bucket first,
+     optionally only for entries tagged with `tag_uid`.
+    */
+    pub async fn totals_by_bucket(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+        tag_uid: Option<Uuid>,
+        granularity: StatsGranularity,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<(NaiveDate, String, Decimal, i64)>, DatabaseError> {
+        let query = format!(
+            "SELECT date_trunc($2, e.created_at AT TIME ZONE 'UTC')::date AS bucket, e.currency, COALESCE(SUM(e.price), 0), COUNT(*) FROM {} e \
+            WHERE e.group_uid = $1 AND e.deleted_at IS NULL AND e.created_at >= $3 AND e.created_at < $4 \
+            AND ($5::uuid IS NULL OR EXISTS (SELECT 1 FROM expense_tags et WHERE et.expense_uid = e.uid AND et.tag_uid = $5)) GROUP BY bucket, e.currency ORDER BY bucket",
+            Self::get_table_name()
+        );
+        let totals = sqlx::query_as::<_, (NaiveDate, String, Decimal, i64)>(&query)
+            .bind(group_uid)
+            .bind(granularity.as_str())
+            .bind(start)
+            .bind(end)
+            .bind(tag_uid)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "summing expense entries by bucket"))?;
+        Ok(totals)
+    }
+
+    /*
+     The `limit` most frequently bought products, with their totals per currency. Products
+     are matched case-insensitively and reported by their most recent spelling. Only
+     entries tagged with `tag_uid` are counted when it is set.
+    */
+    pub async fn top_products_in_range(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+        tag_uid: Option<Uuid>,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<(String, String, Decimal, i64)>, DatabaseError> {
+        let table = Self::get_table_name();
+        let query = format!(
+            "WITH ranged AS (SELECT lower(trim(e.product)) AS product_key, e.product, e.currency, e.price, e.created_at FROM {table} e \
+            WHERE e.group_uid = $1 AND e.deleted_at IS NULL AND e.created_at >= $2 AND e.created_at < $3 \
+            AND ($5::uuid IS NULL OR EXISTS (SELECT 1 FROM expense_tags et WHERE et.expense_uid = e.uid AND et.tag_uid = $5))), \
+            top AS (SELECT product_key, (array_agg(product ORDER BY created_at DESC))[1] AS product, COUNT(*) AS entries FROM ranged \
+            GROUP BY product_key ORDER BY entries DESC, product_key LIMIT $4) \
+            SELECT top.product, ranged.currency, SUM(ranged.price), COUNT(*) FROM top JOIN ranged ON ranged.product_key = top.product_key \
+            GROUP BY top.product_key, top.product, top.entries, ranged.currency ORDER BY top.entries DESC, top.product_key"
+        );
+        let totals = sqlx::query_as::<_, (String, String, Decimal, i64)>(&query)
+            .bind(group_uid)
+            .bind(start)
+            .bind(end)
+            .bind(limit)
+            .bind(tag_uid)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "listing top products"))?;
+        Ok(totals)
+    }
+
     pub async fn get(
         tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
         uid: Uuid,
     ) -> Result<ExpenseEntry, DatabaseError> {
         let query = format!(
-            "SELECT uid, price::float8 AS price, product, created_by, group_uid, category_uid, created_at, updated_at FROM {} WHERE uid = $1",
+            "SELECT {ENTRY_COLUMNS} FROM {} WHERE uid = $1 AND deleted_at IS NULL",
             Self::get_table_name()
         );
         let rec = sqlx::query_as::<_, ExpenseEntry>(&query)
@@ -113,17 +583,37 @@ impl ExpenseEntryRepo {
         Ok(rec)
     }
 
+    /*
+     Fails with `ConcurrentModification` carrying the entry as it is now when it changed after
+     `expected_updated_at`, or between reading and writing it here, so two people editing the
+     same entry never silently overwrite each other
+    */
     pub async fn update(
         tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
         uid: Uuid,
         payload: UpdateExpenseEntryDbPayload,
     ) -> Result<ExpenseEntry, DatabaseError> {
+        ClosedPeriodRepo::ensure_entry_open(tx, uid).await?;
         let current = Self::get(tx, uid).await?;
+        if payload
+            .expected_updated_at
+            .is_some_and(|expected| expected != current.updated_at)
+        {
+            return Err(DatabaseError::concurrent_modification(&current));
+        }
+        let read_updated_at = current.updated_at;
         let price = payload.price.unwrap_or(current.price);
+        let currency = payload.currency.unwrap_or(current.currency);
         let product = payload.product.unwrap_or(current.product);
         let category_uid = payload.category_uid.or(current.category_uid);
+        let envelope_uid = payload.envelope_uid.or(current.envelope_uid);
+        let note = match payload.note {
+            Some(note) if note.trim().is_empty() => None,
+            Some(note) => Some(note),
+            None => current.note,
+        };
         let query = format!(
-            "UPDATE {} SET price = $1, product = $2, category_uid = $3, updated_at = now() WHERE uid = $4 RETURNING uid, price::float8 AS price, product, created_by, group_uid, category_uid, created_at, updated_at",
+            "UPDATE {} SET price = $1, product = $2, category_uid = $3, currency = $5, note = $6, envelope_uid = $7, updated_at = now() WHERE uid = $4 AND deleted_at IS NULL AND updated_at = $8 RETURNING {ENTRY_COLUMNS}",
             Self::get_table_name()
         );
         let rec = sqlx::query_as::<_, ExpenseEntry>(&query)
@@ -131,9 +621,79 @@ impl ExpenseEntryRepo {
             .bind(product)
             .bind(category_uid)
             .bind(uid)
-            .fetch_one(tx.as_mut())
+            .bind(currency)
+            .bind(note)
+            .bind(envelope_uid)
+            .bind(read_updated_at)
+            .fetch_optional(tx.as_mut())
             .await
             .map_err(|e| DatabaseError::from_sqlx_error(e, "updating expense entry"))?;
+        match rec {
+            Some(rec) => Ok(rec),
+            None => Err(DatabaseError::concurrent_modification(
+                &Self::get(tx, uid).await?,
+            )),
+        }
+    }
+
+    pub async fn soft_delete(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+    ) -> Result<ExpenseEntry, DatabaseError> {
+        ClosedPeriodRepo::ensure_entry_open(tx, uid).await?;
+        let query = format!(
+            "UPDATE {} SET deleted_at = now(), updated_at = now() WHERE uid = $1 AND deleted_at IS NULL RETURNING {ENTRY_COLUMNS}",
+            Self::get_table_name()
+        );
+        let rec = sqlx::query_as::<_, ExpenseEntry>(&query)
+            .bind(uid)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "soft deleting expense entry"))?;
+        Ok(rec)
+    }
+
+    pub async fn restore(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+    ) -> Result<ExpenseEntry, DatabaseError> {
+        ClosedPeriodRepo::ensure_entry_open(tx, uid).await?;
+        let query = format!(
+            "UPDATE {} SET deleted_at = NULL, updated_at = now() WHERE uid = $1 AND deleted_at IS NOT NULL RETURNING {ENTRY_COLUMNS}",
+            Self::get_table_name()
+        );
+        let rec = sqlx::query_as::<_, ExpenseEntry>(&query)
+            .bind(uid)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "restoring expense entry"))?;
+        Ok(rec)
+    }
+
+    /*
+     Puts back the editable fields of `entry` as they are, clearing the ones that are None
+     (unlike `update`, which keeps the current value for them).
+    */
+    pub async fn overwrite(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        entry: &ExpenseEntry,
+    ) -> Result<ExpenseEntry, DatabaseError> {
+        ClosedPeriodRepo::ensure_entry_open(tx, entry.uid).await?;
+        let query = format!(
+            "UPDATE {} SET price = $1, product = $2, category_uid = $3, currency = $5, note = $6, envelope_uid = $7, updated_at = now() WHERE uid = $4 AND deleted_at IS NULL RETURNING {ENTRY_COLUMNS}",
+            Self::get_table_name()
+        );
+        let rec = sqlx::query_as::<_, ExpenseEntry>(&query)
+            .bind(entry.price)
+            .bind(&entry.product)
+            .bind(entry.category_uid)
+            .bind(entry.uid)
+            .bind(&entry.currency)
+            .bind(&entry.note)
+            .bind(entry.envelope_uid)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "overwriting expense entry"))?;
         Ok(rec)
     }
 
@@ -141,7 +701,10 @@ impl ExpenseEntryRepo {
         tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
         uid: Uuid,
     ) -> Result<(), DatabaseError> {
-        let query = format!("DELETE FROM {} WHERE uid = $1", Self::get_table_name());
+        let query = format!(
+            "DELETE FROM {} WHERE uid = $1 AND deleted_at IS NULL",
+            Self::get_table_name()
+        );
         sqlx::query(&query)
             .bind(uid)
             .execute(tx.as_mut())
@@ -149,4 +712,123 @@ impl ExpenseEntryRepo {
             .map_err(|e| DatabaseError::from_sqlx_error(e, "deleting expense entry"))?;
         Ok(())
     }
+
+    // Permanently removes entries of the group that were soft-deleted before `before`
+    pub async fn purge_deleted(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+        before: DateTime<Utc>,
+    ) -> Result<u64, DatabaseError> {
+        let query = format!(
+            "DELETE FROM {} WHERE group_uid = $1 AND deleted_at IS NOT NULL AND deleted_at < $2",
+            Self::get_table_name()
+        );
+        let res = sqlx::query(&query)
+            .bind(group_uid)
+            .bind(before)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "purging deleted expense entries"))?;
+        Ok(res.rows_affected())
+    }
+
+    // Live entries created before `before`, what the retention job would delete
+    pub async fn count_expired(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+        before: DateTime<Utc>,
+    ) -> Result<i64, DatabaseError> {
+        let query = format!(
+            "SELECT COUNT(*) FROM {} WHERE group_uid = $1 AND deleted_at IS NULL AND created_at < $2",
+            Self::get_table_name()
+        );
+        let count: i64 = sqlx::query_scalar(&query)
+            .bind(group_uid)
+            .bind(before)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "counting expired expense entries"))?;
+        Ok(count)
+    }
+
+    pub async fn delete_expired(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+        before: DateTime<Utc>,
+    ) -> Result<u64, DatabaseError> {
+        let query = format!(
+            "DELETE FROM {} WHERE group_uid = $1 AND deleted_at IS NULL AND created_at < $2",
+            Self::get_table_name()
+        );
+        let res = sqlx::query(&query)
+            .bind(group_uid)
+            .bind(before)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "deleting expired expense entries"))?;
+        Ok(res.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_creator_name() {
+        assert_eq!(
+            creator_name("system", Some("budi@example.com")),
+            Some("budi".to_string())
+        );
+        assert_eq!(creator_name("Siti", None), Some("Siti".to_string()));
+        assert_eq!(creator_name("system", None), None);
+        assert_eq!(
+            creator_name("00000000-0000-0000-0000-000000000001", None),
+            None
+        );
+    }
+
+    #[test]
+    fn test_stats_granularity_buckets() {
+        let date = |month, day| NaiveDate::from_ymd_opt(2025, month, day).unwrap();
+        assert_eq!(
+            StatsGranularity::parse("week"),
+            Some(StatsGranularity::Week)
+        );
+        assert_eq!(StatsGranularity::parse("year"), None);
+
+        // 2025-09-03 is a Wednesday
+        assert_eq!(StatsGranularity::Week.truncate(date(9, 3)), date(9, 1));
+        assert_eq!(StatsGranularity::Month.truncate(date(9, 30)), date(9, 1));
+        assert_eq!(
+            StatsGranularity::Day.bucket_starts(date(9, 29), date(10, 1)),
+            vec![date(9, 29), date(9, 30), date(10, 1)]
+        );
+        assert_eq!(
+            StatsGranularity::Week.bucket_starts(date(9, 3), date(9, 15)),
+            vec![date(9, 1), date(9, 8), date(9, 15)]
+        );
+        assert_eq!(
+            StatsGranularity::Month.bucket_starts(date(8, 31), date(10, 1)),
+            vec![date(8, 1), date(9, 1), date(10, 1)]
+        );
+    }
+
+    #[test]
+    fn test_expense_entry_sort_parse() {
+        assert_eq!(
+            ExpenseEntrySort::parse("-created_at"),
+            Some(ExpenseEntrySort::CreatedAtDesc)
+        );
+        assert_eq!(
+            ExpenseEntrySort::parse("price"),
+            Some(ExpenseEntrySort::PriceAsc)
+        );
+        assert_eq!(
+            ExpenseEntrySort::parse("-product"),
+            Some(ExpenseEntrySort::ProductDesc)
+        );
+        assert_eq!(ExpenseEntrySort::parse("price; DROP TABLE"), None);
+        assert_eq!(ExpenseEntrySort::parse("uid"), None);
+    }
 }
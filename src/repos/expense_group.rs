@@ -12,6 +12,13 @@ pub struct ExpenseGroup {
     pub name: String,
     pub owner: Uuid,
     pub start_over_date: i16,
+    // Default currency for new entries and budgets, and the currency reports are shown in
+    pub currency: String,
+    // Language of the group's chats, set with /lang. Falls back to the language of the user
+    // who bound the chat
+    pub lang: Option<String>,
+    // Set while the group is archived: still readable, but closed to new entries and chat commands
+    pub archived_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
 }
 
@@ -20,14 +27,46 @@ pub struct CreateExpenseGroupDbPayload {
     pub name: String,
     pub owner: Uuid,
     pub start_over_date: i16,
+    pub currency: String,
 }
 
 #[derive(Debug, Deserialize, serde::Serialize, ToSchema)]
 pub struct UpdateExpenseGroupDbPayload {
     pub name: Option<String>,
     pub start_over_date: Option<i16>,
+    pub currency: Option<String>,
 }
 
+// Soft-deleted group as seen by the purge job
+#[derive(Debug, Clone, FromRow)]
+pub struct DeletedExpenseGroup {
+    pub uid: Uuid,
+    pub owner: Uuid,
+    pub deleted_at: DateTime<Utc>,
+}
+
+// Tables referencing `expense_groups`, in an order that satisfies their foreign keys
+const GROUP_DEPENDENT_TABLES: &[&str] = &[
+    "tier_violations",
+    "webhooks",
+    "group_settings",
+    "budget_alert_settings",
+    "notification_settings",
+    "debts",
+    "budget_plans",
+    "budgets",
+    "expense_entries",
+    "tags",
+    "settlements",
+    "income_entries",
+    "recurring_expenses",
+    "categories_aliases",
+    "categories",
+    "chat_bindings",
+    "group_invites",
+    "group_members",
+];
+
 pub struct ExpenseGroupRepo;
 
 impl BaseRepo for ExpenseGroupRepo {
@@ -41,7 +80,7 @@ impl ExpenseGroupRepo {
         tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
     ) -> Result<Vec<ExpenseGroup>, DatabaseError> {
         let query = format!(
-            "SELECT uid, name, owner, start_over_date, created_at FROM {} ORDER BY created_at DESC",
+            "SELECT uid, name, owner, start_over_date, currency, lang, archived_at, created_at FROM {} WHERE deleted_at IS NULL ORDER BY created_at DESC",
             Self::get_table_name()
         );
         let rows = sqlx::query_as::<_, ExpenseGroup>(&query)
@@ -56,7 +95,7 @@ impl ExpenseGroupRepo {
         owner: Uuid,
     ) -> Result<Vec<ExpenseGroup>, DatabaseError> {
         let query = format!(
-            "SELECT uid, name, owner, start_over_date, created_at FROM {} WHERE owner = $1 ORDER BY created_at DESC",
+            "SELECT uid, name, owner, start_over_date, currency, lang, archived_at, created_at FROM {} WHERE owner = $1 AND deleted_at IS NULL ORDER BY created_at DESC",
             Self::get_table_name()
         );
         let rows = sqlx::query_as::<_, ExpenseGroup>(&query)
@@ -67,11 +106,32 @@ impl ExpenseGroupRepo {
         Ok(rows)
     }
 
+    // Groups the user owns or has been added to as a member
+    pub async fn get_all_by_member(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        user_uid: Uuid,
+    ) -> Result<Vec<ExpenseGroup>, DatabaseError> {
+        let query = format!(
+            "SELECT g.uid, g.name, g.owner, g.start_over_date, g.currency, g.lang, g.archived_at, g.created_at FROM {} g WHERE g.deleted_at IS NULL AND (g.owner = $1 OR EXISTS (SELECT 1 FROM group_members gm WHERE gm.group_uid = g.uid AND gm.user_uid = $1)) ORDER BY g.created_at DESC",
+            Self::get_table_name()
+        );
+        let rows = sqlx::query_as::<_, ExpenseGroup>(&query)
+            .bind(user_uid)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "getting expense groups by member"))?;
+        Ok(rows)
+    }
+
+    // Archived groups are left out, they don't count against the tier's group limit
     pub async fn count_by_owner(
         tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
         owner: Uuid,
     ) -> Result<i64, DatabaseError> {
-        let query = format!("SELECT COUNT(*) FROM {} WHERE owner = $1", Self::get_table_name());
+        let query = format!(
+            "SELECT COUNT(*) FROM {} WHERE owner = $1 AND deleted_at IS NULL AND archived_at IS NULL",
+            Self::get_table_name()
+        );
         let count = sqlx::query_scalar::<_, i64>(&query)
             .bind(owner)
             .fetch_one(tx.as_mut())
@@ -85,7 +145,7 @@ impl ExpenseGroupRepo {
         uid: Uuid,
     ) -> Result<ExpenseGroup, DatabaseError> {
         let query = format!(
-            "SELECT uid, name, owner, start_over_date, created_at FROM {} WHERE uid = $1",
+            "SELECT uid, name, owner, start_over_date, currency, lang, archived_at, created_at FROM {} WHERE uid = $1 AND deleted_at IS NULL",
             Self::get_table_name()
         );
         let row = sqlx::query_as::<_, ExpenseGroup>(&query)
@@ -102,7 +162,7 @@ impl ExpenseGroupRepo {
     ) -> Result<ExpenseGroup, DatabaseError> {
         let uid = Uuid::new_v4();
         let query = format!(
-            "INSERT INTO {} (uid, name, owner, start_over_date) VALUES ($1, $2, $3, $4) RETURNING uid, name, owner, start_over_date, created_at",
+            "INSERT INTO {} (uid, name, owner, start_over_date, currency) VALUES ($1, $2, $3, $4, $5) RETURNING uid, name, owner, start_over_date, currency, lang, archived_at, created_at",
             Self::get_table_name()
         );
         let row = sqlx::query_as::<_, ExpenseGroup>(&query)
@@ -110,6 +170,7 @@ impl ExpenseGroupRepo {
             .bind(payload.name)
             .bind(payload.owner)
             .bind(payload.start_over_date)
+            .bind(payload.currency)
             .fetch_one(tx.as_mut())
             .await
             .map_err(|e| DatabaseError::from_sqlx_error(e, "creating expense group"))?;
@@ -124,24 +185,171 @@ impl ExpenseGroupRepo {
         let current = Self::get(tx, uid).await?;
         let name = payload.name.unwrap_or(current.name);
         let start_over_date = payload.start_over_date.unwrap_or(current.start_over_date);
+        let currency = payload.currency.unwrap_or(current.currency);
         let query = format!(
-            "UPDATE {} SET name = $1, start_over_date = $2 WHERE uid = $3 RETURNING uid, name, owner, start_over_date, created_at",
+            "UPDATE {} SET name = $1, start_over_date = $2, currency = $4 WHERE uid = $3 AND deleted_at IS NULL RETURNING uid, name, owner, start_over_date, currency, lang, archived_at, created_at",
             Self::get_table_name()
         );
         let row = sqlx::query_as::<_, ExpenseGroup>(&query)
             .bind(name)
             .bind(start_over_date)
             .bind(uid)
+            .bind(currency)
             .fetch_one(tx.as_mut())
             .await
             .map_err(|e| DatabaseError::from_sqlx_error(e, "updating expense group"))?;
         Ok(row)
     }
 
+    pub async fn set_lang(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+        lang: &str,
+    ) -> Result<ExpenseGroup, DatabaseError> {
+        let query = format!(
+            "UPDATE {} SET lang = $1 WHERE uid = $2 AND deleted_at IS NULL RETURNING uid, name, owner, start_over_date, currency, lang, archived_at, created_at",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, ExpenseGroup>(&query)
+            .bind(lang)
+            .bind(uid)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "setting expense group language"))?;
+        Ok(row)
+    }
+
+    // Archiving an archived group keeps its original `archived_at`
+    pub async fn set_archived(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+        archived: bool,
+    ) -> Result<ExpenseGroup, DatabaseError> {
+        let query = format!(
+            "UPDATE {} SET archived_at = CASE WHEN $1 THEN COALESCE(archived_at, now()) ELSE NULL END WHERE uid = $2 AND deleted_at IS NULL RETURNING uid, name, owner, start_over_date, currency, lang, archived_at, created_at",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, ExpenseGroup>(&query)
+            .bind(archived)
+            .bind(uid)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "archiving expense group"))?;
+        Ok(row)
+    }
+
+    pub async fn is_archived(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+    ) -> Result<bool, DatabaseError> {
+        let query = format!(
+            "SELECT archived_at IS NOT NULL FROM {} WHERE uid = $1 AND deleted_at IS NULL",
+            Self::get_table_name()
+        );
+        let archived = sqlx::query_scalar::<_, bool>(&query)
+            .bind(uid)
+            .fetch_optional(tx.as_mut())
+            .await
+            .map_err(|e| {
+                DatabaseError::from_sqlx_error(e, "checking whether expense group is archived")
+            })?;
+        Ok(archived.unwrap_or(false))
+    }
+
+    // The group's language, or the user's when the group has none
+    pub async fn find_lang(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+        user_uid: Uuid,
+    ) -> Result<Option<String>, DatabaseError> {
+        let query = format!(
+            "SELECT COALESCE(g.lang, u.lang) FROM {} g JOIN users u ON u.uid = $2 WHERE g.uid = $1",
+            Self::get_table_name()
+        );
+        let lang = sqlx::query_scalar::<_, Option<String>>(&query)
+            .bind(uid)
+            .bind(user_uid)
+            .fetch_optional(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "finding expense group language"))?;
+        Ok(lang.flatten())
+    }
+
+    // Hides the group everywhere; it can be restored until the purge job removes it
+    pub async fn soft_delete(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+    ) -> Result<(), DatabaseError> {
+        let query = format!(
+            "UPDATE {} SET deleted_at = now() WHERE uid = $1 AND deleted_at IS NULL RETURNING uid",
+            Self::get_table_name()
+        );
+        sqlx::query_scalar::<_, Uuid>(&query)
+            .bind(uid)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "soft deleting expense group"))?;
+        Ok(())
+    }
+
+    pub async fn get_deleted(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+    ) -> Result<ExpenseGroup, DatabaseError> {
+        let query = format!(
+            "SELECT uid, name, owner, start_over_date, currency, lang, archived_at, created_at FROM {} WHERE uid = $1 AND deleted_at IS NOT NULL",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, ExpenseGroup>(&query)
+            .bind(uid)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "getting deleted expense group"))?;
+        Ok(row)
+    }
+
+    pub async fn restore(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+    ) -> Result<ExpenseGroup, DatabaseError> {
+        let query = format!(
+            "UPDATE {} SET deleted_at = NULL WHERE uid = $1 AND deleted_at IS NOT NULL RETURNING uid, name, owner, start_over_date, currency, lang, archived_at, created_at",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, ExpenseGroup>(&query)
+            .bind(uid)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "restoring expense group"))?;
+        Ok(row)
+    }
+
+    pub async fn list_deleted(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<Vec<DeletedExpenseGroup>, DatabaseError> {
+        let query = format!(
+            "SELECT uid, owner, deleted_at FROM {} WHERE deleted_at IS NOT NULL ORDER BY deleted_at",
+            Self::get_table_name()
+        );
+        let rows = sqlx::query_as::<_, DeletedExpenseGroup>(&query)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "listing deleted expense groups"))?;
+        Ok(rows)
+    }
+
+    // Permanently removes the group together with everything that belongs to it
     pub async fn delete(
         tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
         uid: Uuid,
     ) -> Result<(), DatabaseError> {
+        for table in GROUP_DEPENDENT_TABLES {
+            sqlx::query(&format!("DELETE FROM {} WHERE group_uid = $1", table))
+                .bind(uid)
+                .execute(tx.as_mut())
+                .await
+                .map_err(|e| DatabaseError::from_sqlx_error(e, "deleting expense group data"))?;
+        }
         let query = format!("DELETE FROM {} WHERE uid = $1", Self::get_table_name());
         sqlx::query(&query)
             .bind(uid)
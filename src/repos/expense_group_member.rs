@@ -7,6 +7,37 @@ use uuid::Uuid;
 use crate::error::DatabaseError;
 use crate::repos::base::BaseRepo;
 
+/*
+ Roles are ordered by privilege so guards can compare with `>=`.
+ The group owner is stored on `expense_groups.owner`; an `owner` row in
+ group_members is never created through the API.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum GroupRole {
+    Member,
+    Admin,
+    Owner,
+}
+
+impl GroupRole {
+    pub fn parse(input: &str) -> Option<Self> {
+        match input.trim().to_lowercase().as_str() {
+            "member" => Some(Self::Member),
+            "admin" => Some(Self::Admin),
+            "owner" => Some(Self::Owner),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Member => "member",
+            Self::Admin => "admin",
+            Self::Owner => "owner",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
 pub struct GroupMember {
     pub id: Uuid,
@@ -51,6 +82,86 @@ impl GroupMemberRepo {
         Ok(rows)
     }
 
+    pub async fn list_by_group(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+    ) -> Result<Vec<GroupMember>, DatabaseError> {
+        let query = format!(
+            "SELECT id, group_uid, user_uid, role, created_at FROM {} WHERE group_uid = $1 ORDER BY created_at ASC",
+            Self::get_table_name()
+        );
+        let rows = sqlx::query_as::<_, GroupMember>(&query)
+            .bind(group_uid)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "listing group members by group"))?;
+        Ok(rows)
+    }
+
+    pub async fn find_by_group_and_user(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+        user_uid: Uuid,
+    ) -> Result<Option<GroupMember>, DatabaseError> {
+        let query = format!(
+            "SELECT id, group_uid, user_uid, role, created_at FROM {} WHERE group_uid = $1 AND user_uid = $2",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, GroupMember>(&query)
+            .bind(group_uid)
+            .bind(user_uid)
+            .fetch_optional(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "finding group member"))?;
+        Ok(row)
+    }
+
+    /*
+     The user's role in a live group in one query, None when they are not part of it.
+     Only `expense_groups.owner` makes someone the owner; unknown roles fall back to member.
+    */
+    pub async fn find_role(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+        user_uid: Uuid,
+    ) -> Result<Option<GroupRole>, DatabaseError> {
+        let query = format!(
+            "SELECT g.owner = $2 AS is_owner, gm.role FROM expense_groups g LEFT JOIN {} gm ON gm.group_uid = g.uid AND gm.user_uid = $2 WHERE g.uid = $1 AND g.deleted_at IS NULL",
+            Self::get_table_name()
+        );
+        let (is_owner, role) = sqlx::query_as::<_, (bool, Option<String>)>(&query)
+            .bind(group_uid)
+            .bind(user_uid)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "finding group role"))?;
+        if is_owner {
+            return Ok(Some(GroupRole::Owner));
+        }
+        Ok(role.map(|role| {
+            GroupRole::parse(&role)
+                .unwrap_or(GroupRole::Member)
+                .min(GroupRole::Admin)
+        }))
+    }
+
+    // The owner always counts as a member, whether or not they have a row here
+    pub async fn count_by_group(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+    ) -> Result<i64, DatabaseError> {
+        let query = format!(
+            "SELECT COUNT(*) + 1 FROM {} gm JOIN expense_groups g ON g.uid = gm.group_uid WHERE gm.group_uid = $1 AND gm.user_uid <> g.owner",
+            Self::get_table_name()
+        );
+        let count = sqlx::query_scalar::<_, i64>(&query)
+            .bind(group_uid)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "counting group members"))?;
+        Ok(count)
+    }
+
     pub async fn get(
         tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
         id: Uuid,
@@ -120,3 +231,16 @@ impl GroupMemberRepo {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_role_parse_and_order() {
+        assert_eq!(GroupRole::parse("Admin"), Some(GroupRole::Admin));
+        assert_eq!(GroupRole::parse("superuser"), None);
+        assert!(GroupRole::Owner > GroupRole::Admin);
+        assert!(GroupRole::Admin > GroupRole::Member);
+    }
+}
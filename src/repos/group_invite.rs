@@ -0,0 +1,200 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::DatabaseError;
+use crate::repos::base::BaseRepo;
+use crate::utils::pii;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct GroupInvite {
+    pub uid: Uuid,
+    pub group_uid: Uuid,
+    pub token: String,
+    pub role: String,
+    pub email: Option<String>,
+    pub invited_by: Uuid,
+    pub expires_at: DateTime<Utc>,
+    pub accepted_by: Option<Uuid>,
+    pub accepted_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl GroupInvite {
+    pub fn is_pending(&self, now: DateTime<Utc>) -> bool {
+        self.accepted_at.is_none() && self.expires_at > now
+    }
+
+    fn decrypted(mut self) -> Result<Self, DatabaseError> {
+        self.email = self.email.as_deref().map(pii::decrypt).transpose()?;
+        Ok(self)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateGroupInviteDbPayload {
+    pub group_uid: Uuid,
+    pub role: String,
+    pub email: Option<String>,
+    pub invited_by: Uuid,
+    pub expires_at: DateTime<Utc>,
+}
+
+pub struct GroupInviteRepo;
+
+impl BaseRepo for GroupInviteRepo {
+    fn get_table_name() -> &'static str {
+        "group_invites"
+    }
+}
+
+impl GroupInviteRepo {
+    // Two v4 UUIDs give 244 random bits, hex encoded so it is safe to put in a URL
+    fn generate_token() -> String {
+        format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+    }
+
+    pub async fn create(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        payload: CreateGroupInviteDbPayload,
+    ) -> Result<GroupInvite, DatabaseError> {
+        let uid = Uuid::new_v4();
+        let query = format!(
+            "INSERT INTO {} (uid, group_uid, token, role, email, email_hash, invited_by, expires_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING uid, group_uid, token, role, email, invited_by, expires_at, accepted_by, accepted_at, created_at",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, GroupInvite>(&query)
+            .bind(uid)
+            .bind(payload.group_uid)
+            .bind(Self::generate_token())
+            .bind(payload.role)
+            .bind(payload.email.as_deref().map(pii::encrypt))
+            .bind(payload.email.as_deref().and_then(pii::lookup_hash))
+            .bind(payload.invited_by)
+            .bind(payload.expires_at)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "creating group invite"))?;
+        row.decrypted()
+    }
+
+    // Only invites that can still be accepted
+    pub async fn list_pending_by_group(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+    ) -> Result<Vec<GroupInvite>, DatabaseError> {
+        let query = format!(
+            "SELECT uid, group_uid, token, role, email, invited_by, expires_at, accepted_by, accepted_at, created_at FROM {} WHERE group_uid = $1 AND accepted_at IS NULL AND expires_at > now() ORDER BY created_at DESC",
+            Self::get_table_name()
+        );
+        let rows = sqlx::query_as::<_, GroupInvite>(&query)
+            .bind(group_uid)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "listing group invites"))?;
+        rows.into_iter().map(GroupInvite::decrypted).collect()
+    }
+
+    pub async fn get(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+    ) -> Result<GroupInvite, DatabaseError> {
+        let query = format!(
+            "SELECT uid, group_uid, token, role, email, invited_by, expires_at, accepted_by, accepted_at, created_at FROM {} WHERE uid = $1",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, GroupInvite>(&query)
+            .bind(uid)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "getting group invite"))?;
+        row.decrypted()
+    }
+
+    // Locks the row so two concurrent accepts cannot both succeed
+    pub async fn get_by_token_for_update(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        token: &str,
+    ) -> Result<GroupInvite, DatabaseError> {
+        let query = format!(
+            "SELECT uid, group_uid, token, role, email, invited_by, expires_at, accepted_by, accepted_at, created_at FROM {} WHERE token = $1 FOR UPDATE",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, GroupInvite>(&query)
+            .bind(token)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "getting group invite by token"))?;
+        row.decrypted()
+    }
+
+    pub async fn mark_accepted(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+        accepted_by: Uuid,
+    ) -> Result<GroupInvite, DatabaseError> {
+        let query = format!(
+            "UPDATE {} SET accepted_by = $1, accepted_at = now() WHERE uid = $2 AND accepted_at IS NULL RETURNING uid, group_uid, token, role, email, invited_by, expires_at, accepted_by, accepted_at, created_at",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, GroupInvite>(&query)
+            .bind(accepted_by)
+            .bind(uid)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "accepting group invite"))?;
+        row.decrypted()
+    }
+
+    pub async fn delete(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+    ) -> Result<(), DatabaseError> {
+        let query = format!("DELETE FROM {} WHERE uid = $1", Self::get_table_name());
+        sqlx::query(&query)
+            .bind(uid)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "deleting group invite"))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn invite(expires_at: DateTime<Utc>, accepted_at: Option<DateTime<Utc>>) -> GroupInvite {
+        GroupInvite {
+            uid: Uuid::new_v4(),
+            group_uid: Uuid::new_v4(),
+            token: GroupInviteRepo::generate_token(),
+            role: "member".to_string(),
+            email: None,
+            invited_by: Uuid::new_v4(),
+            expires_at,
+            accepted_by: None,
+            accepted_at,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_is_pending() {
+        let now = Utc::now();
+        assert!(invite(now + Duration::hours(1), None).is_pending(now));
+        assert!(!invite(now - Duration::hours(1), None).is_pending(now));
+        assert!(!invite(now + Duration::hours(1), Some(now)).is_pending(now));
+    }
+
+    #[test]
+    fn test_generate_token() {
+        let token = GroupInviteRepo::generate_token();
+        assert_eq!(token.len(), 64);
+        assert!(token.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(token, GroupInviteRepo::generate_token());
+    }
+}
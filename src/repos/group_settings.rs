@@ -0,0 +1,161 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::DatabaseError;
+use crate::repos::base::BaseRepo;
+use crate::utils::{anomaly::AnomalySensitivity, period};
+
+pub const MAX_HISTORY_DAYS: i16 = 31;
+
+/*
+ Group preferences without a column of their own, stored as JSON in
+ `group_settings.settings`. Keys missing from the stored JSON take their default, so adding
+ a setting needs no migration.
+*/
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(default)]
+pub struct GroupSettings {
+    /// ISO weekday weeks start on (1-7, 1 is Monday), used by `/report week`
+    pub week_start: i16,
+    /// Days `/history` covers when sent without dates (1-31)
+    pub history_days: i16,
+    /// Plain chat messages like `Nasi Padang 25000` are recorded as expenses
+    pub natural_language_entries: bool,
+    /// How unusual a day's or an expense's spending in a category has to be before the chats
+    /// get an alert, `off`, `low`, `medium` or `high`
+    pub anomaly_sensitivity: AnomalySensitivity,
+}
+
+impl Default for GroupSettings {
+    fn default() -> Self {
+        Self {
+            week_start: 1,
+            history_days: 3,
+            natural_language_entries: false,
+            anomaly_sensitivity: AnomalySensitivity::Medium,
+        }
+    }
+}
+
+impl GroupSettings {
+    // First day of the week `date` is in
+    pub fn week_start_of(&self, date: NaiveDate) -> NaiveDate {
+        period::week_start_of(date, self.week_start)
+    }
+}
+
+pub struct GroupSettingsRepo;
+
+impl BaseRepo for GroupSettingsRepo {
+    fn get_table_name() -> &'static str {
+        "group_settings"
+    }
+}
+
+impl GroupSettingsRepo {
+    // Defaults for groups that never changed their settings
+    pub async fn get(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+    ) -> Result<GroupSettings, DatabaseError> {
+        let query = format!(
+            "SELECT settings FROM {} WHERE group_uid = $1",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_scalar::<_, serde_json::Value>(&query)
+            .bind(group_uid)
+            .fetch_optional(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "getting group settings"))?;
+        let Some(settings) = row else {
+            return Ok(GroupSettings::default());
+        };
+        serde_json::from_value(settings)
+            .map_err(|e| DatabaseError::TransactionError(format!("decoding group settings: {e}")))
+    }
+
+    // Settings of several groups at once, groups that never changed theirs are left out
+    pub async fn list_by_groups(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uids: &[Uuid],
+    ) -> Result<HashMap<Uuid, GroupSettings>, DatabaseError> {
+        let query = format!(
+            "SELECT group_uid, settings FROM {} WHERE group_uid = ANY($1)",
+            Self::get_table_name()
+        );
+        let rows = sqlx::query_as::<_, (Uuid, serde_json::Value)>(&query)
+            .bind(group_uids)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "listing group settings"))?;
+        rows.into_iter()
+            .map(|(group_uid, settings)| {
+                let settings = serde_json::from_value(settings).map_err(|e| {
+                    DatabaseError::TransactionError(format!("decoding group settings: {e}"))
+                })?;
+                Ok((group_uid, settings))
+            })
+            .collect()
+    }
+
+    pub async fn upsert(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+        settings: &GroupSettings,
+    ) -> Result<GroupSettings, DatabaseError> {
+        let value = serde_json::to_value(settings).map_err(|e| {
+            DatabaseError::TransactionError(format!("encoding group settings: {e}"))
+        })?;
+        let query = format!(
+            "INSERT INTO {} (group_uid, settings) VALUES ($1, $2) \
+            ON CONFLICT (group_uid) DO UPDATE SET settings = EXCLUDED.settings, updated_at = now() \
+            RETURNING settings",
+            Self::get_table_name()
+        );
+        let stored = sqlx::query_scalar::<_, serde_json::Value>(&query)
+            .bind(group_uid)
+            .bind(value)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "saving group settings"))?;
+        serde_json::from_value(stored)
+            .map_err(|e| DatabaseError::TransactionError(format!("decoding group settings: {e}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_missing_keys_take_defaults() {
+        let settings: GroupSettings =
+            serde_json::from_value(serde_json::json!({ "history_days": 7 })).unwrap();
+        assert_eq!(settings.history_days, 7);
+        assert_eq!(settings.week_start, 1);
+        assert!(!settings.natural_language_entries);
+        assert_eq!(settings.anomaly_sensitivity, AnomalySensitivity::Medium);
+    }
+
+    #[test]
+    fn test_week_start_of() {
+        let monday = GroupSettings::default();
+        // 2025-08-13 is a Wednesday
+        assert_eq!(monday.week_start_of(date(2025, 8, 13)), date(2025, 8, 11));
+        assert_eq!(monday.week_start_of(date(2025, 8, 17)), date(2025, 8, 11));
+
+        let sunday = GroupSettings {
+            week_start: 7,
+            ..GroupSettings::default()
+        };
+        assert_eq!(sunday.week_start_of(date(2025, 8, 13)), date(2025, 8, 10));
+        assert_eq!(sunday.week_start_of(date(2025, 8, 17)), date(2025, 8, 17));
+    }
+}
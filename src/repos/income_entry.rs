@@ -0,0 +1,153 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::DatabaseError;
+use crate::repos::base::BaseRepo;
+
+pub struct IncomeEntryRepo;
+
+impl BaseRepo for IncomeEntryRepo {
+    fn get_table_name() -> &'static str {
+        "income_entries"
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct IncomeEntry {
+    pub uid: Uuid,
+    pub amount: Decimal,
+    pub source: String,
+    pub created_by: String,
+
+    pub group_uid: Uuid,
+
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateIncomeEntryDbPayload {
+    pub amount: Decimal,
+    pub source: String,
+    pub group_uid: Uuid,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateIncomeEntryDbPayload {
+    pub amount: Option<Decimal>,
+    pub source: Option<String>,
+}
+
+impl IncomeEntryRepo {
+    pub async fn create(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        payload: CreateIncomeEntryDbPayload,
+    ) -> Result<IncomeEntry, DatabaseError> {
+        let uid = Uuid::new_v4();
+        let query = format!(
+            "INSERT INTO {} (uid, amount, source, group_uid, created_by) VALUES ($1, $2, $3, $4, $5) RETURNING uid, amount, source, created_by, group_uid, created_at, updated_at",
+            Self::get_table_name()
+        );
+        let rec = sqlx::query_as::<_, IncomeEntry>(&query)
+            .bind(uid)
+            .bind(payload.amount)
+            .bind(payload.source)
+            .bind(payload.group_uid)
+            .bind("system")
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "creating income entry"))?;
+        Ok(rec)
+    }
+
+    pub async fn list_by_group(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+    ) -> Result<Vec<IncomeEntry>, DatabaseError> {
+        let query = format!(
+            "SELECT uid, amount, source, created_by, group_uid, created_at, updated_at FROM {} WHERE group_uid = $1 ORDER BY created_at DESC",
+            Self::get_table_name()
+        );
+        let recs = sqlx::query_as::<_, IncomeEntry>(&query)
+            .bind(group_uid)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "listing income entries by group"))?;
+        Ok(recs)
+    }
+
+    pub async fn sum_by_group_in_range(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Decimal, DatabaseError> {
+        let query = format!(
+            "SELECT COALESCE(SUM(amount), 0) FROM {} WHERE group_uid = $1 AND created_at >= $2 AND created_at < $3",
+            Self::get_table_name()
+        );
+        let total = sqlx::query_scalar::<_, Decimal>(&query)
+            .bind(group_uid)
+            .bind(start)
+            .bind(end)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "summing income entries"))?;
+        Ok(total)
+    }
+
+    pub async fn get(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+    ) -> Result<IncomeEntry, DatabaseError> {
+        let query = format!(
+            "SELECT uid, amount, source, created_by, group_uid, created_at, updated_at FROM {} WHERE uid = $1",
+            Self::get_table_name()
+        );
+        let rec = sqlx::query_as::<_, IncomeEntry>(&query)
+            .bind(uid)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "getting income entry"))?;
+        Ok(rec)
+    }
+
+    pub async fn update(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+        payload: UpdateIncomeEntryDbPayload,
+    ) -> Result<IncomeEntry, DatabaseError> {
+        let current = Self::get(tx, uid).await?;
+        let amount = payload.amount.unwrap_or(current.amount);
+        let source = payload.source.unwrap_or(current.source);
+        let query = format!(
+            "UPDATE {} SET amount = $1, source = $2, updated_at = now() WHERE uid = $3 RETURNING uid, amount, source, created_by, group_uid, created_at, updated_at",
+            Self::get_table_name()
+        );
+        let rec = sqlx::query_as::<_, IncomeEntry>(&query)
+            .bind(amount)
+            .bind(source)
+            .bind(uid)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "updating income entry"))?;
+        Ok(rec)
+    }
+
+    pub async fn delete(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+    ) -> Result<(), DatabaseError> {
+        let query = format!("DELETE FROM {} WHERE uid = $1", Self::get_table_name());
+        sqlx::query(&query)
+            .bind(uid)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "deleting income entry"))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,278 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::DatabaseError;
+use crate::repos::base::BaseRepo;
+
+const JOB_COLUMNS: &str = "uid, kind, payload, status, attempts, max_attempts, run_at, locked_at, last_error, created_at, updated_at";
+
+pub const DEFAULT_MAX_ATTEMPTS: i32 = 5;
+
+const BASE_RETRY_DELAY_SECS: i64 = 30;
+const MAX_RETRY_DELAY_SECS: i64 = 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobStatus {
+    // Waiting for `run_at`, also after a failed attempt that is retried
+    Queued,
+    Running,
+    Succeeded,
+    // Failed `max_attempts` times, kept for inspection and never run again
+    Dead,
+}
+
+impl JobStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Queued => "queued",
+            Self::Running => "running",
+            Self::Succeeded => "succeeded",
+            Self::Dead => "dead",
+        }
+    }
+
+    pub fn parse(input: &str) -> Option<Self> {
+        match input.trim().to_lowercase().as_str() {
+            "queued" => Some(Self::Queued),
+            "running" => Some(Self::Running),
+            "succeeded" => Some(Self::Succeeded),
+            "dead" => Some(Self::Dead),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct Job {
+    pub uid: Uuid,
+    /// What the job does, e.g. `monthly_report`
+    pub kind: String,
+    #[schema(value_type = Object)]
+    pub payload: serde_json::Value,
+    /// One of `queued`, `running`, `succeeded`, `dead`
+    pub status: String,
+    /// Attempts started so far, including the running one
+    pub attempts: i32,
+    pub max_attempts: i32,
+    /// Earliest time the job runs, pushed back after each failed attempt
+    pub run_at: DateTime<Utc>,
+    /// When a worker claimed the job
+    pub locked_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/*
+ When a job that failed its `attempts`th attempt runs again, None once it is out of
+ attempts. The delay doubles with every attempt from 30 seconds up to an hour.
+*/
+pub fn next_attempt_at(
+    attempts: i32,
+    max_attempts: i32,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    if attempts >= max_attempts {
+        return None;
+    }
+    let exponent = attempts.clamp(1, 20) as u32 - 1;
+    let delay = BASE_RETRY_DELAY_SECS
+        .saturating_mul(1_i64 << exponent)
+        .min(MAX_RETRY_DELAY_SECS);
+    Some(now + Duration::seconds(delay))
+}
+
+pub struct JobRepo;
+
+impl BaseRepo for JobRepo {
+    fn get_table_name() -> &'static str {
+        "jobs"
+    }
+}
+
+impl JobRepo {
+    // Runs once the transaction commits, at `run_at` or right away
+    pub async fn enqueue(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        kind: &str,
+        payload: &serde_json::Value,
+        run_at: Option<DateTime<Utc>>,
+    ) -> Result<Job, DatabaseError> {
+        let query = format!(
+            "INSERT INTO {} (kind, payload, max_attempts, run_at) VALUES ($1, $2, $3, COALESCE($4, now())) RETURNING {JOB_COLUMNS}",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, Job>(&query)
+            .bind(kind)
+            .bind(payload)
+            .bind(DEFAULT_MAX_ATTEMPTS)
+            .bind(run_at)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "enqueueing job"))?;
+        Ok(row)
+    }
+
+    /*
+     Marks up to `limit` due jobs as running and counts an attempt for each. Jobs still
+     running since before `stale_before` belonged to a worker that died and are taken over.
+     Locked rows are skipped, so workers never claim the same job.
+    */
+    pub async fn claim_due(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        limit: i64,
+        stale_before: DateTime<Utc>,
+    ) -> Result<Vec<Job>, DatabaseError> {
+        let query = format!(
+            "UPDATE {table} SET status = 'running', attempts = attempts + 1, locked_at = now(), updated_at = now() \
+            WHERE uid IN (SELECT uid FROM {table} \
+                WHERE (status = 'queued' AND run_at <= now()) OR (status = 'running' AND locked_at < $2) \
+                ORDER BY run_at LIMIT $1 FOR UPDATE SKIP LOCKED) \
+            RETURNING {JOB_COLUMNS}",
+            table = Self::get_table_name()
+        );
+        let rows = sqlx::query_as::<_, Job>(&query)
+            .bind(limit)
+            .bind(stale_before)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "claiming due jobs"))?;
+        Ok(rows)
+    }
+
+    pub async fn mark_succeeded(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+    ) -> Result<(), DatabaseError> {
+        let query = format!(
+            "UPDATE {} SET status = 'succeeded', locked_at = NULL, updated_at = now() WHERE uid = $1",
+            Self::get_table_name()
+        );
+        sqlx::query(&query)
+            .bind(uid)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "marking job succeeded"))?;
+        Ok(())
+    }
+
+    // Queues the job for its next attempt, or moves it to `dead` when it has none left
+    pub async fn mark_failed(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        job: &Job,
+        error: &str,
+        now: DateTime<Utc>,
+    ) -> Result<JobStatus, DatabaseError> {
+        let retry_at = next_attempt_at(job.attempts, job.max_attempts, now);
+        let status = match retry_at {
+            Some(_) => JobStatus::Queued,
+            None => JobStatus::Dead,
+        };
+        let query = format!(
+            "UPDATE {} SET status = $2, run_at = COALESCE($3, run_at), last_error = $4, locked_at = NULL, updated_at = now() WHERE uid = $1",
+            Self::get_table_name()
+        );
+        sqlx::query(&query)
+            .bind(job.uid)
+            .bind(status.as_str())
+            .bind(retry_at)
+            .bind(error)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "marking job failed"))?;
+        Ok(status)
+    }
+
+    // Newest first, with the total matching the filters
+    pub async fn list(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        status: Option<JobStatus>,
+        kind: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<Job>, i64), DatabaseError> {
+        let status = status.map(|status| status.as_str());
+        let count_query = format!(
+            "SELECT COUNT(*) FROM {} WHERE ($1::text IS NULL OR status = $1) AND ($2::text IS NULL OR kind = $2)",
+            Self::get_table_name()
+        );
+        let total = sqlx::query_scalar::<_, i64>(&count_query)
+            .bind(status)
+            .bind(kind)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "counting jobs"))?;
+
+        let query = format!(
+            "SELECT {JOB_COLUMNS} FROM {} WHERE ($1::text IS NULL OR status = $1) AND ($2::text IS NULL OR kind = $2) \
+            ORDER BY created_at DESC LIMIT $3 OFFSET $4",
+            Self::get_table_name()
+        );
+        let rows = sqlx::query_as::<_, Job>(&query)
+            .bind(status)
+            .bind(kind)
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "listing jobs"))?;
+        Ok((rows, total))
+    }
+
+    // Succeeded jobs last updated before `before`, dead ones are kept until looked at
+    pub async fn delete_succeeded_before(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        before: DateTime<Utc>,
+    ) -> Result<u64, DatabaseError> {
+        let query = format!(
+            "DELETE FROM {} WHERE status = 'succeeded' AND updated_at < $1",
+            Self::get_table_name()
+        );
+        let result = sqlx::query(&query)
+            .bind(before)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "deleting succeeded jobs"))?;
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_attempt_backs_off() {
+        let now = Utc::now();
+        assert_eq!(
+            next_attempt_at(1, 5, now),
+            Some(now + Duration::seconds(30))
+        );
+        assert_eq!(
+            next_attempt_at(2, 5, now),
+            Some(now + Duration::seconds(60))
+        );
+        assert_eq!(
+            next_attempt_at(4, 5, now),
+            Some(now + Duration::seconds(240))
+        );
+        assert_eq!(next_attempt_at(12, 20, now), Some(now + Duration::hours(1)));
+    }
+
+    #[test]
+    fn test_next_attempt_none_when_out_of_attempts() {
+        let now = Utc::now();
+        assert_eq!(next_attempt_at(5, 5, now), None);
+        assert_eq!(next_attempt_at(6, 5, now), None);
+    }
+
+    #[test]
+    fn test_parse_status() {
+        assert_eq!(JobStatus::parse(" Dead "), Some(JobStatus::Dead));
+        assert_eq!(JobStatus::parse("queued"), Some(JobStatus::Queued));
+        assert_eq!(JobStatus::parse("failed"), None);
+    }
+}
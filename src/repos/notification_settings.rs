@@ -0,0 +1,241 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::DatabaseError;
+use crate::repos::base::BaseRepo;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestKind {
+    Daily,
+    Weekly,
+}
+
+impl DigestKind {
+    // Dates summarized by a digest sent on `today`, as [start, end)
+    pub fn range(&self, today: NaiveDate) -> (NaiveDate, NaiveDate) {
+        match self {
+            Self::Daily => (today, today + Duration::days(1)),
+            Self::Weekly => (today - Duration::days(7), today),
+        }
+    }
+
+    fn last_sent_column(&self) -> &'static str {
+        match self {
+            Self::Daily => "last_daily_sent_on",
+            Self::Weekly => "last_weekly_sent_on",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct NotificationSettings {
+    pub group_uid: Uuid,
+    pub daily_enabled: bool,
+    /// Hour of the day (UTC) the daily digest is sent
+    pub daily_hour: i16,
+    pub weekly_enabled: bool,
+    /// ISO weekday the weekly digest is sent on, 1 is Monday
+    pub weekly_day: i16,
+    /// Hour of the day (UTC) the weekly digest is sent
+    pub weekly_hour: i16,
+    pub last_daily_sent_on: Option<NaiveDate>,
+    pub last_weekly_sent_on: Option<NaiveDate>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl NotificationSettings {
+    // Used for groups that never changed their settings, mirrors the table defaults
+    pub fn default_for(group_uid: Uuid) -> Self {
+        Self {
+            group_uid,
+            daily_enabled: false,
+            daily_hour: 21,
+            weekly_enabled: false,
+            weekly_day: 1,
+            weekly_hour: 9,
+            last_daily_sent_on: None,
+            last_weekly_sent_on: None,
+            updated_at: Utc::now(),
+        }
+    }
+
+    /*
+     * Digests that should go out at `now` and were not sent yet today. A digest stays due
+     * for the rest of its day once its hour has passed, so a missed run catches up.
+     */
+    pub fn due_digests(&self, now: DateTime<Utc>) -> Vec<DigestKind> {
+        let today = now.date_naive();
+        let hour = now.hour() as i16;
+        let mut due = Vec::new();
+        if self.daily_enabled && hour >= self.daily_hour && self.last_daily_sent_on != Some(today) {
+            due.push(DigestKind::Daily);
+        }
+        if self.weekly_enabled
+            && today.weekday().number_from_monday() as i16 == self.weekly_day
+            && hour >= self.weekly_hour
+            && self.last_weekly_sent_on != Some(today)
+        {
+            due.push(DigestKind::Weekly);
+        }
+        due
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpsertNotificationSettingsDbPayload {
+    pub daily_enabled: bool,
+    pub daily_hour: i16,
+    pub weekly_enabled: bool,
+    pub weekly_day: i16,
+    pub weekly_hour: i16,
+}
+
+pub struct NotificationSettingsRepo;
+
+impl BaseRepo for NotificationSettingsRepo {
+    fn get_table_name() -> &'static str {
+        "notification_settings"
+    }
+}
+
+impl NotificationSettingsRepo {
+    pub async fn get(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+    ) -> Result<NotificationSettings, DatabaseError> {
+        let query = format!(
+            "SELECT group_uid, daily_enabled, daily_hour, weekly_enabled, weekly_day, weekly_hour, last_daily_sent_on, last_weekly_sent_on, updated_at FROM {} WHERE group_uid = $1",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, NotificationSettings>(&query)
+            .bind(group_uid)
+            .fetch_optional(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "getting notification settings"))?;
+        Ok(row.unwrap_or_else(|| NotificationSettings::default_for(group_uid)))
+    }
+
+    // Groups with at least one digest turned on
+    pub async fn list_enabled(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<Vec<NotificationSettings>, DatabaseError> {
+        let query = format!(
+            "SELECT group_uid, daily_enabled, daily_hour, weekly_enabled, weekly_day, weekly_hour, last_daily_sent_on, last_weekly_sent_on, updated_at FROM {} WHERE daily_enabled OR weekly_enabled",
+            Self::get_table_name()
+        );
+        let rows = sqlx::query_as::<_, NotificationSettings>(&query)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "listing notification settings"))?;
+        Ok(rows)
+    }
+
+    pub async fn upsert(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+        payload: UpsertNotificationSettingsDbPayload,
+    ) -> Result<NotificationSettings, DatabaseError> {
+        let query = format!(
+            "INSERT INTO {} (group_uid, daily_enabled, daily_hour, weekly_enabled, weekly_day, weekly_hour) VALUES ($1, $2, $3, $4, $5, $6) \
+            ON CONFLICT (group_uid) DO UPDATE SET daily_enabled = EXCLUDED.daily_enabled, daily_hour = EXCLUDED.daily_hour, weekly_enabled = EXCLUDED.weekly_enabled, weekly_day = EXCLUDED.weekly_day, weekly_hour = EXCLUDED.weekly_hour, updated_at = now() \
+            RETURNING group_uid, daily_enabled, daily_hour, weekly_enabled, weekly_day, weekly_hour, last_daily_sent_on, last_weekly_sent_on, updated_at",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, NotificationSettings>(&query)
+            .bind(group_uid)
+            .bind(payload.daily_enabled)
+            .bind(payload.daily_hour)
+            .bind(payload.weekly_enabled)
+            .bind(payload.weekly_day)
+            .bind(payload.weekly_hour)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "saving notification settings"))?;
+        Ok(row)
+    }
+
+    // Returns false when the digest was already sent on `date`
+    pub async fn mark_sent(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+        kind: DigestKind,
+        date: NaiveDate,
+    ) -> Result<bool, DatabaseError> {
+        let column = kind.last_sent_column();
+        let query = format!(
+            "UPDATE {} SET {column} = $2 WHERE group_uid = $1 AND {column} IS DISTINCT FROM $2",
+            Self::get_table_name()
+        );
+        let res = sqlx::query(&query)
+            .bind(group_uid)
+            .bind(date)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "marking digest as sent"))?;
+        Ok(res.rows_affected() > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(year: i32, month: u32, day: u32, hour: u32) -> DateTime<Utc> {
+        NaiveDate::from_ymd_opt(year, month, day)
+            .unwrap()
+            .and_hms_opt(hour, 0, 0)
+            .unwrap()
+            .and_utc()
+    }
+
+    #[test]
+    fn test_due_digests() {
+        let mut settings = NotificationSettings::default_for(Uuid::new_v4());
+        // 2025-09-01 is a Monday
+        assert!(settings.due_digests(at(2025, 9, 1, 21)).is_empty());
+
+        settings.daily_enabled = true;
+        settings.weekly_enabled = true;
+        assert_eq!(settings.due_digests(at(2025, 9, 1, 8)), vec![]);
+        assert_eq!(
+            settings.due_digests(at(2025, 9, 1, 9)),
+            vec![DigestKind::Weekly]
+        );
+        assert_eq!(
+            settings.due_digests(at(2025, 9, 1, 22)),
+            vec![DigestKind::Daily, DigestKind::Weekly]
+        );
+        assert_eq!(
+            settings.due_digests(at(2025, 9, 2, 21)),
+            vec![DigestKind::Daily]
+        );
+    }
+
+    #[test]
+    fn test_due_digests_sent_once_a_day() {
+        let mut settings = NotificationSettings::default_for(Uuid::new_v4());
+        settings.daily_enabled = true;
+        settings.last_daily_sent_on = NaiveDate::from_ymd_opt(2025, 9, 1);
+        assert!(settings.due_digests(at(2025, 9, 1, 23)).is_empty());
+        assert_eq!(
+            settings.due_digests(at(2025, 9, 2, 21)),
+            vec![DigestKind::Daily]
+        );
+    }
+
+    #[test]
+    fn test_digest_range() {
+        let today = NaiveDate::from_ymd_opt(2025, 9, 1).unwrap();
+        assert_eq!(
+            DigestKind::Daily.range(today),
+            (today, NaiveDate::from_ymd_opt(2025, 9, 2).unwrap())
+        );
+        assert_eq!(
+            DigestKind::Weekly.range(today),
+            (NaiveDate::from_ymd_opt(2025, 8, 25).unwrap(), today)
+        );
+    }
+}
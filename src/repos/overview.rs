@@ -0,0 +1,146 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::DatabaseError;
+use crate::utils::pii;
+
+// Spent in a group during its current cycle in one currency, envelopes left out like in /report
+#[derive(Debug, Clone, FromRow)]
+pub struct GroupPeriodTotal {
+    pub group_uid: Uuid,
+    pub currency: String,
+    pub total: Decimal,
+    pub entries: i64,
+}
+
+// Spent against a budget during its period in one currency, subcategories included
+#[derive(Debug, Clone, FromRow)]
+pub struct BudgetPeriodSpend {
+    pub budget_uid: Uuid,
+    pub category_name: String,
+    pub currency: String,
+    pub total: Decimal,
+}
+
+// An invite addressed to the user's email that can still be accepted
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct PendingInvite {
+    pub uid: Uuid,
+    pub group_uid: Uuid,
+    pub group_name: String,
+    pub role: String,
+    /// Accept with `POST /invites/{token}/accept`
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/*
+ Queries behind `GET /users/me/overview`. Each one covers every group of the user at once, the
+ periods are computed by the caller since they depend on each group's start_over_date, and
+ passed in as arrays.
+*/
+pub struct OverviewRepo;
+
+impl OverviewRepo {
+    // `periods` as (group_uid, start, end), groups without entries are left out
+    pub async fn group_period_totals(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        periods: &[(Uuid, DateTime<Utc>, DateTime<Utc>)],
+    ) -> Result<Vec<GroupPeriodTotal>, DatabaseError> {
+        let (group_uids, starts, ends) = unzip_periods(periods);
+        let rows = sqlx::query_as::<_, GroupPeriodTotal>(
+            "SELECT p.group_uid, e.currency, SUM(e.price) AS total, COUNT(*) AS entries \
+            FROM unnest($1::uuid[], $2::timestamptz[], $3::timestamptz[]) AS p(group_uid, starts_at, ends_at) \
+            JOIN expense_entries e ON e.group_uid = p.group_uid AND e.created_at >= p.starts_at AND e.created_at < p.ends_at \
+            WHERE e.deleted_at IS NULL AND e.envelope_uid IS NULL \
+            GROUP BY p.group_uid, e.currency",
+        )
+        .bind(group_uids)
+        .bind(starts)
+        .bind(ends)
+        .fetch_all(tx.as_mut())
+        .await
+        .map_err(|e| DatabaseError::from_sqlx_error(e, "summing group period totals"))?;
+        Ok(rows)
+    }
+
+    // `periods` as (budget_uid, start, end), budgets without spending are left out
+    pub async fn budget_period_spend(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        periods: &[(Uuid, DateTime<Utc>, DateTime<Utc>)],
+    ) -> Result<Vec<BudgetPeriodSpend>, DatabaseError> {
+        let (budget_uids, starts, ends) = unzip_periods(periods);
+        let rows = sqlx::query_as::<_, BudgetPeriodSpend>(
+            "SELECT p.budget_uid, c.name AS category_name, e.currency, SUM(e.price) AS total \
+            FROM unnest($1::uuid[], $2::timestamptz[], $3::timestamptz[]) AS p(budget_uid, starts_at, ends_at) \
+            JOIN budgets b ON b.uid = p.budget_uid \
+            JOIN categories c ON c.uid = b.category_uid \
+            JOIN expense_entries e ON e.group_uid = b.group_uid AND (e.category_uid = b.category_uid OR e.category_uid IN (SELECT uid FROM categories WHERE parent_uid = b.category_uid)) AND e.created_at >= p.starts_at AND e.created_at < p.ends_at \
+            WHERE e.deleted_at IS NULL AND e.envelope_uid IS NULL \
+            GROUP BY p.budget_uid, c.name, e.currency",
+        )
+        .bind(budget_uids)
+        .bind(starts)
+        .bind(ends)
+        .fetch_all(tx.as_mut())
+        .await
+        .map_err(|e| DatabaseError::from_sqlx_error(e, "summing budget period spend"))?;
+        Ok(rows)
+    }
+
+    // Members per group, groups without member rows are left out
+    pub async fn member_counts(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uids: &[Uuid],
+    ) -> Result<Vec<(Uuid, i64)>, DatabaseError> {
+        let rows = sqlx::query_as::<_, (Uuid, i64)>(
+            "SELECT group_uid, COUNT(*) FROM group_members WHERE group_uid = ANY($1) GROUP BY group_uid",
+        )
+        .bind(group_uids)
+        .fetch_all(tx.as_mut())
+        .await
+        .map_err(|e| DatabaseError::from_sqlx_error(e, "counting members of groups"))?;
+        Ok(rows)
+    }
+
+    // Invites for groups the user is not part of yet, soonest to expire first. Invite emails are
+    // stored lowercased, so the hash is taken of the lowercased address
+    pub async fn pending_invites(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        user_uid: Uuid,
+        email: &str,
+    ) -> Result<Vec<PendingInvite>, DatabaseError> {
+        let rows = sqlx::query_as::<_, PendingInvite>(
+            "SELECT i.uid, i.group_uid, g.name AS group_name, i.role, i.token, i.expires_at \
+            FROM group_invites i JOIN expense_groups g ON g.uid = i.group_uid AND g.deleted_at IS NULL \
+            WHERE (i.email_hash = $3 OR lower(i.email) = lower($2)) AND i.accepted_at IS NULL AND i.expires_at > now() \
+            AND g.owner <> $1 AND NOT EXISTS (SELECT 1 FROM group_members gm WHERE gm.group_uid = i.group_uid AND gm.user_uid = $1) \
+            ORDER BY i.expires_at",
+        )
+        .bind(user_uid)
+        .bind(email)
+        .bind(pii::lookup_hash(&email.to_lowercase()))
+        .fetch_all(tx.as_mut())
+        .await
+        .map_err(|e| DatabaseError::from_sqlx_error(e, "listing pending invites of user"))?;
+        Ok(rows)
+    }
+}
+
+fn unzip_periods(
+    periods: &[(Uuid, DateTime<Utc>, DateTime<Utc>)],
+) -> (Vec<Uuid>, Vec<DateTime<Utc>>, Vec<DateTime<Utc>>) {
+    let mut uids = Vec::with_capacity(periods.len());
+    let mut starts = Vec::with_capacity(periods.len());
+    let mut ends = Vec::with_capacity(periods.len());
+    for (uid, start, end) in periods {
+        uids.push(*uid);
+        starts.push(*start);
+        ends.push(*end);
+    }
+    (uids, starts, ends)
+}
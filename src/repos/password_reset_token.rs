@@ -0,0 +1,126 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::error::DatabaseError;
+use crate::repos::base::BaseRepo;
+use crate::utils::secret_token::{generate_token, hash_token};
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct PasswordResetToken {
+    pub uid: Uuid,
+    pub user_uid: Uuid,
+    pub token_hash: String,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl PasswordResetToken {
+    pub fn is_usable(&self, now: DateTime<Utc>) -> bool {
+        self.used_at.is_none() && self.expires_at > now
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreatePasswordResetTokenDbPayload {
+    pub user_uid: Uuid,
+    pub expires_at: DateTime<Utc>,
+}
+
+pub struct PasswordResetTokenRepo;
+
+impl BaseRepo for PasswordResetTokenRepo {
+    fn get_table_name() -> &'static str {
+        "password_reset_tokens"
+    }
+}
+
+impl PasswordResetTokenRepo {
+    // Returns the stored row together with the plain token, which is only available here
+    pub async fn create(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        payload: CreatePasswordResetTokenDbPayload,
+    ) -> Result<(PasswordResetToken, String), DatabaseError> {
+        let uid = Uuid::new_v4();
+        let token = generate_token();
+        let query = format!(
+            "INSERT INTO {} (uid, user_uid, token_hash, expires_at) VALUES ($1, $2, $3, $4) RETURNING uid, user_uid, token_hash, expires_at, used_at, created_at",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, PasswordResetToken>(&query)
+            .bind(uid)
+            .bind(payload.user_uid)
+            .bind(hash_token(&token))
+            .bind(payload.expires_at)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "creating password reset token"))?;
+        Ok((row, token))
+    }
+
+    // Locks the row so the same token cannot be used twice concurrently
+    pub async fn get_by_token_for_update(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        token: &str,
+    ) -> Result<Option<PasswordResetToken>, DatabaseError> {
+        let query = format!(
+            "SELECT uid, user_uid, token_hash, expires_at, used_at, created_at FROM {} WHERE token_hash = $1 FOR UPDATE",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, PasswordResetToken>(&query)
+            .bind(hash_token(token))
+            .fetch_optional(tx.as_mut())
+            .await
+            .map_err(|e| {
+                DatabaseError::from_sqlx_error(e, "getting password reset token by token")
+            })?;
+        Ok(row)
+    }
+
+    // Also invalidates any other outstanding tokens of the user
+    pub async fn mark_used(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        user_uid: Uuid,
+    ) -> Result<(), DatabaseError> {
+        let query = format!(
+            "UPDATE {} SET used_at = now() WHERE user_uid = $1 AND used_at IS NULL",
+            Self::get_table_name()
+        );
+        sqlx::query(&query)
+            .bind(user_uid)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "using password reset token"))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn reset_token(
+        expires_at: DateTime<Utc>,
+        used_at: Option<DateTime<Utc>>,
+    ) -> PasswordResetToken {
+        PasswordResetToken {
+            uid: Uuid::new_v4(),
+            user_uid: Uuid::new_v4(),
+            token_hash: hash_token("token"),
+            expires_at,
+            used_at,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_is_usable() {
+        let now = Utc::now();
+        assert!(reset_token(now + Duration::hours(1), None).is_usable(now));
+        assert!(!reset_token(now - Duration::hours(1), None).is_usable(now));
+        assert!(!reset_token(now + Duration::hours(1), Some(now)).is_usable(now));
+    }
+}
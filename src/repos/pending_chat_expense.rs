@@ -0,0 +1,63 @@
+use chrono::{DateTime, Duration, Utc};
+use uuid::Uuid;
+
+use crate::error::DatabaseError;
+use crate::repos::base::BaseRepo;
+
+/*
+ /expense messages held back because they would take a budget with a hard limit past its
+ amount, kept until the sender answers with /confirm. A sender has at most one per chat
+ binding, a newer message replaces the older one.
+*/
+pub struct PendingChatExpenseRepo;
+
+impl BaseRepo for PendingChatExpenseRepo {
+    fn get_table_name() -> &'static str {
+        "pending_chat_expenses"
+    }
+}
+
+impl PendingChatExpenseRepo {
+    pub async fn upsert(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        binding_id: Uuid,
+        p_user_id: &str,
+        raw_message: &str,
+    ) -> Result<(), DatabaseError> {
+        let query = format!(
+            "INSERT INTO {} (binding_id, p_user_id, raw_message) VALUES ($1, $2, $3) \
+            ON CONFLICT (binding_id, p_user_id) DO UPDATE SET raw_message = EXCLUDED.raw_message, created_at = now()",
+            Self::get_table_name()
+        );
+        sqlx::query(&query)
+            .bind(binding_id)
+            .bind(p_user_id)
+            .bind(raw_message)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "saving pending chat expense"))?;
+        Ok(())
+    }
+
+    // Removes the sender's pending message, returning it unless it is older than `max_age`
+    pub async fn take(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        binding_id: Uuid,
+        p_user_id: &str,
+        max_age: Duration,
+    ) -> Result<Option<String>, DatabaseError> {
+        let query = format!(
+            "DELETE FROM {} WHERE binding_id = $1 AND p_user_id = $2 RETURNING raw_message, created_at",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, (String, DateTime<Utc>)>(&query)
+            .bind(binding_id)
+            .bind(p_user_id)
+            .fetch_optional(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "taking pending chat expense"))?;
+        Ok(row
+            .filter(|(_, created_at)| *created_at > Utc::now() - max_age)
+            .map(|(raw_message, _)| raw_message))
+    }
+}
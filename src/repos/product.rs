@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::DatabaseError;
+use crate::repos::base::BaseRepo;
+use crate::repos::expense_entry::ExpenseEntry;
+
+const PRODUCT_COLUMNS: &str =
+    "group_uid, name, display_name, category_uid, typical_price, currency, use_count, last_used_at";
+
+/*
+ A product name used in a group, learned from the expenses recorded with it. The
+ category is the one it was last recorded with, the price averages the prices paid in
+ its currency.
+*/
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct Product {
+    pub group_uid: Uuid,
+    /// Normalized name, see `normalize_product_name`
+    pub name: String,
+    /// The name as it was last recorded
+    pub display_name: String,
+    pub category_uid: Option<Uuid>,
+    pub typical_price: Decimal,
+    pub currency: String,
+    pub use_count: i32,
+    pub last_used_at: DateTime<Utc>,
+}
+
+/*
+ Canonical form of a product name: trimmed, with single spaces and lowercase, so
+ "Nasi  Padang" and "nasi padang" are the same product. Matches the normalization of
+ the products migration.
+*/
+pub fn normalize_product_name(name: &str) -> String {
+    name.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+pub struct ProductRepo;
+
+impl BaseRepo for ProductRepo {
+    fn get_table_name() -> &'static str {
+        "products"
+    }
+}
+
+impl ProductRepo {
+    // Counts a use of the entry's product, learning its category and price
+    pub async fn learn(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        entry: &ExpenseEntry,
+    ) -> Result<(), DatabaseError> {
+        let name = normalize_product_name(&entry.product);
+        if name.is_empty() {
+            return Ok(());
+        }
+        let query = format!(
+            "INSERT INTO {table} (group_uid, name, display_name, category_uid, typical_price, currency) VALUES ($1, $2, $3, $4, $5, $6) \
+            ON CONFLICT (group_uid, name) DO UPDATE SET display_name = EXCLUDED.display_name, \
+            category_uid = COALESCE(EXCLUDED.category_uid, {table}.category_uid), \
+            typical_price = CASE WHEN {table}.currency = EXCLUDED.currency \
+                THEN round(({table}.typical_price * {table}.use_count + EXCLUDED.typical_price) / ({table}.use_count + 1), 2) \
+                ELSE EXCLUDED.typical_price END, \
+            currency = EXCLUDED.currency, use_count = {table}.use_count + 1, last_used_at = now()",
+            table = Self::get_table_name()
+        );
+        sqlx::query(&query)
+            .bind(entry.group_uid)
+            .bind(&name)
+            .bind(
+                entry
+                    .product
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            )
+            .bind(entry.category_uid)
+            .bind(entry.price)
+            .bind(&entry.currency)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "learning product"))?;
+        Ok(())
+    }
+
+    /*
+     Remembers the category an entry of the product was moved to, without counting a use.
+     Nothing happens for products not learned yet.
+    */
+    pub async fn learn_category(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+        product: &str,
+        category_uid: Uuid,
+    ) -> Result<(), DatabaseError> {
+        let query = format!(
+            "UPDATE {} SET category_uid = $3 WHERE group_uid = $1 AND name = $2",
+            Self::get_table_name()
+        );
+        sqlx::query(&query)
+            .bind(group_uid)
+            .bind(normalize_product_name(product))
+            .bind(category_uid)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "learning product category"))?;
+        Ok(())
+    }
+
+    /*
+     Products whose name contains `term`, those starting with it first, then the most
+     used and most recent ones.
+    */
+    pub async fn suggest(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+        term: &str,
+        limit: i64,
+    ) -> Result<Vec<Product>, DatabaseError> {
+        let query = format!(
+            "SELECT {PRODUCT_COLUMNS} FROM {} WHERE group_uid = $1 AND strpos(name, $2) > 0 \
+            ORDER BY starts_with(name, $2) DESC, use_count DESC, last_used_at DESC LIMIT $3",
+            Self::get_table_name()
+        );
+        let rows = sqlx::query_as::<_, Product>(&query)
+            .bind(group_uid)
+            .bind(normalize_product_name(term))
+            .bind(limit)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "suggesting products"))?;
+        Ok(rows)
+    }
+
+    // Learned category per normalized name, for the `products` that have one
+    pub async fn categories_by_name(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+        products: &[String],
+    ) -> Result<HashMap<String, Uuid>, DatabaseError> {
+        let names: Vec<String> = products
+            .iter()
+            .map(|product| normalize_product_name(product))
+            .collect();
+        let query = format!(
+            "SELECT name, category_uid FROM {} WHERE group_uid = $1 AND name = ANY($2) AND category_uid IS NOT NULL",
+            Self::get_table_name()
+        );
+        let rows = sqlx::query_as::<_, (String, Uuid)>(&query)
+            .bind(group_uid)
+            .bind(names)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "getting learned product categories"))?;
+        Ok(rows.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_product_name() {
+        assert_eq!(normalize_product_name("  Nasi   Padang "), "nasi padang");
+        assert_eq!(normalize_product_name("KOPI\tsusu"), "kopi susu");
+        assert_eq!(normalize_product_name("   "), "");
+    }
+}
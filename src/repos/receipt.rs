@@ -0,0 +1,86 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::DatabaseError;
+use crate::repos::base::BaseRepo;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct Receipt {
+    pub uid: Uuid,
+    pub expense_uid: Uuid,
+    // Name of the storage backend holding the file, e.g. "local" or "s3"
+    pub storage: String,
+    pub storage_key: String,
+    pub file_name: Option<String>,
+    pub content_type: String,
+    pub size_bytes: i64,
+    pub uploaded_by_user_uid: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpsertReceiptDbPayload {
+    pub expense_uid: Uuid,
+    pub storage: String,
+    pub storage_key: String,
+    pub file_name: Option<String>,
+    pub content_type: String,
+    pub size_bytes: i64,
+    pub uploaded_by_user_uid: Option<Uuid>,
+}
+
+pub struct ReceiptRepo;
+
+impl BaseRepo for ReceiptRepo {
+    fn get_table_name() -> &'static str {
+        "receipts"
+    }
+}
+
+impl ReceiptRepo {
+    pub async fn get_by_expense(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        expense_uid: Uuid,
+    ) -> Result<Option<Receipt>, DatabaseError> {
+        let query = format!(
+            "SELECT uid, expense_uid, storage, storage_key, file_name, content_type, size_bytes, uploaded_by_user_uid, created_at FROM {} WHERE expense_uid = $1",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, Receipt>(&query)
+            .bind(expense_uid)
+            .fetch_optional(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "getting receipt by expense"))?;
+        Ok(row)
+    }
+
+    // An entry has at most one receipt, uploading another one replaces it
+    pub async fn upsert(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        payload: UpsertReceiptDbPayload,
+    ) -> Result<Receipt, DatabaseError> {
+        let query = format!(
+            "INSERT INTO {} (uid, expense_uid, storage, storage_key, file_name, content_type, size_bytes, uploaded_by_user_uid) VALUES ($1, $2, $3, $4, $5, $6, $7, $8) \
+            ON CONFLICT (expense_uid) DO UPDATE SET storage = EXCLUDED.storage, storage_key = EXCLUDED.storage_key, file_name = EXCLUDED.file_name, \
+                content_type = EXCLUDED.content_type, size_bytes = EXCLUDED.size_bytes, uploaded_by_user_uid = EXCLUDED.uploaded_by_user_uid, created_at = now() \
+            RETURNING uid, expense_uid, storage, storage_key, file_name, content_type, size_bytes, uploaded_by_user_uid, created_at",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, Receipt>(&query)
+            .bind(Uuid::new_v4())
+            .bind(payload.expense_uid)
+            .bind(payload.storage)
+            .bind(payload.storage_key)
+            .bind(payload.file_name)
+            .bind(payload.content_type)
+            .bind(payload.size_bytes)
+            .bind(payload.uploaded_by_user_uid)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "saving receipt"))?;
+        Ok(row)
+    }
+}
@@ -0,0 +1,244 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::DatabaseError;
+use crate::repos::base::BaseRepo;
+use crate::utils::bank_statement::CandidateEntry;
+
+// Rows whose entry was deleted since count as unmatched again
+const RECONCILIATION_COLUMNS: &str = "r.uid, r.group_uid, r.file_name, r.format, r.currency, r.window_days, r.skipped_rows, r.created_by, r.created_at, \
+    (SELECT COUNT(*) FROM reconciliation_rows rr JOIN expense_entries e ON e.uid = rr.entry_uid AND e.deleted_at IS NULL WHERE rr.reconciliation_uid = r.uid) AS matched_rows, \
+    (SELECT COUNT(*) FROM reconciliation_rows rr LEFT JOIN expense_entries e ON e.uid = rr.entry_uid AND e.deleted_at IS NULL WHERE rr.reconciliation_uid = r.uid AND e.uid IS NULL) AS unmatched_rows";
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct Reconciliation {
+    pub uid: Uuid,
+    pub group_uid: Uuid,
+    pub file_name: Option<String>,
+    /// `csv` or `ofx`
+    pub format: String,
+    /// Currency of the statement, entries are matched and created in it
+    pub currency: String,
+    /// How many days apart a row and an entry may be dated and still match
+    pub window_days: i16,
+    /// Credits and rows that could not be read
+    pub skipped_rows: i32,
+    pub matched_rows: i64,
+    pub unmatched_rows: i64,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+// A debit on the statement, with the entry it was matched to or created as
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct ReconciliationRow {
+    pub uid: Uuid,
+    /// Line of the row in the uploaded file
+    pub line: i32,
+    pub posted_on: NaiveDate,
+    pub amount: Decimal,
+    pub description: String,
+    /// None while unmatched, or once the matched entry is deleted
+    pub entry_uid: Option<Uuid>,
+    pub entry_product: Option<String>,
+    pub entry_created_at: Option<DateTime<Utc>>,
+    /// The entry was created from this row rather than found
+    pub created_entry: bool,
+}
+
+#[derive(Debug)]
+pub struct CreateReconciliationDbPayload {
+    pub group_uid: Uuid,
+    pub file_name: Option<String>,
+    pub format: String,
+    pub currency: String,
+    pub window_days: i16,
+    pub skipped_rows: i32,
+    pub created_by: Uuid,
+}
+
+#[derive(Debug)]
+pub struct CreateReconciliationRowDbPayload {
+    pub line: i32,
+    pub posted_on: NaiveDate,
+    pub amount: Decimal,
+    pub description: String,
+    pub entry_uid: Option<Uuid>,
+}
+
+pub struct ReconciliationRepo;
+
+impl BaseRepo for ReconciliationRepo {
+    fn get_table_name() -> &'static str {
+        "reconciliations"
+    }
+}
+
+impl ReconciliationRepo {
+    pub async fn create(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        payload: CreateReconciliationDbPayload,
+        rows: Vec<CreateReconciliationRowDbPayload>,
+    ) -> Result<Reconciliation, DatabaseError> {
+        let query = format!(
+            "INSERT INTO {} (group_uid, file_name, format, currency, window_days, skipped_rows, created_by) \
+            VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING uid",
+            Self::get_table_name()
+        );
+        let uid = sqlx::query_scalar::<_, Uuid>(&query)
+            .bind(payload.group_uid)
+            .bind(payload.file_name)
+            .bind(payload.format)
+            .bind(payload.currency)
+            .bind(payload.window_days)
+            .bind(payload.skipped_rows)
+            .bind(payload.created_by)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "creating reconciliation"))?;
+
+        let mut lines = Vec::with_capacity(rows.len());
+        let mut dates = Vec::with_capacity(rows.len());
+        let mut amounts = Vec::with_capacity(rows.len());
+        let mut descriptions = Vec::with_capacity(rows.len());
+        let mut entry_uids = Vec::with_capacity(rows.len());
+        for row in rows {
+            lines.push(row.line);
+            dates.push(row.posted_on);
+            amounts.push(row.amount);
+            descriptions.push(row.description);
+            entry_uids.push(row.entry_uid);
+        }
+        sqlx::query(
+            "INSERT INTO reconciliation_rows (reconciliation_uid, line, posted_on, amount, description, entry_uid) \
+            SELECT $1, t.line, t.posted_on, t.amount, t.description, t.entry_uid \
+            FROM unnest($2::int[], $3::date[], $4::numeric[], $5::varchar[], $6::uuid[]) AS t(line, posted_on, amount, description, entry_uid)",
+        )
+        .bind(uid)
+        .bind(lines)
+        .bind(dates)
+        .bind(amounts)
+        .bind(descriptions)
+        .bind(entry_uids)
+        .execute(tx.as_mut())
+        .await
+        .map_err(|e| DatabaseError::from_sqlx_error(e, "creating reconciliation rows"))?;
+
+        Self::get(tx, uid).await
+    }
+
+    pub async fn get(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+    ) -> Result<Reconciliation, DatabaseError> {
+        let query = format!(
+            "SELECT {RECONCILIATION_COLUMNS} FROM {} r WHERE r.uid = $1",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, Reconciliation>(&query)
+            .bind(uid)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "getting reconciliation"))?;
+        Ok(row)
+    }
+
+    // Newest first
+    pub async fn list_by_group(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+    ) -> Result<Vec<Reconciliation>, DatabaseError> {
+        let query = format!(
+            "SELECT {RECONCILIATION_COLUMNS} FROM {} r WHERE r.group_uid = $1 ORDER BY r.created_at DESC",
+            Self::get_table_name()
+        );
+        let rows = sqlx::query_as::<_, Reconciliation>(&query)
+            .bind(group_uid)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "listing reconciliations"))?;
+        Ok(rows)
+    }
+
+    // In file order
+    pub async fn rows(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+    ) -> Result<Vec<ReconciliationRow>, DatabaseError> {
+        let rows = sqlx::query_as::<_, ReconciliationRow>(
+            "SELECT rr.uid, rr.line, rr.posted_on, rr.amount, rr.description, e.uid AS entry_uid, e.product AS entry_product, \
+            e.created_at AS entry_created_at, rr.created_entry AND e.uid IS NOT NULL AS created_entry \
+            FROM reconciliation_rows rr LEFT JOIN expense_entries e ON e.uid = rr.entry_uid AND e.deleted_at IS NULL \
+            WHERE rr.reconciliation_uid = $1 ORDER BY rr.line",
+        )
+        .bind(uid)
+        .fetch_all(tx.as_mut())
+        .await
+        .map_err(|e| DatabaseError::from_sqlx_error(e, "listing reconciliation rows"))?;
+        Ok(rows)
+    }
+
+    /*
+     Entries of the group recorded in `currency` between the two dates, inclusive, that a
+     statement row could be matched with
+    */
+    pub async fn candidate_entries(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+        currency: &str,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<CandidateEntry>, DatabaseError> {
+        let rows = sqlx::query_as::<_, (Uuid, Decimal, NaiveDate)>(
+            "SELECT uid, price, (created_at AT TIME ZONE 'UTC')::date FROM expense_entries \
+            WHERE group_uid = $1 AND currency = $2 AND deleted_at IS NULL \
+            AND (created_at AT TIME ZONE 'UTC')::date BETWEEN $3 AND $4 ORDER BY created_at",
+        )
+        .bind(group_uid)
+        .bind(currency)
+        .bind(from)
+        .bind(to)
+        .fetch_all(tx.as_mut())
+        .await
+        .map_err(|e| DatabaseError::from_sqlx_error(e, "listing reconciliation candidates"))?;
+        Ok(rows
+            .into_iter()
+            .map(|(uid, price, date)| CandidateEntry { uid, price, date })
+            .collect())
+    }
+
+    // Links a row to the entry just created from it
+    pub async fn set_created_entry(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        row_uid: Uuid,
+        entry_uid: Uuid,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query(
+            "UPDATE reconciliation_rows SET entry_uid = $2, created_entry = TRUE WHERE uid = $1",
+        )
+        .bind(row_uid)
+        .bind(entry_uid)
+        .execute(tx.as_mut())
+        .await
+        .map_err(|e| DatabaseError::from_sqlx_error(e, "linking reconciliation row"))?;
+        Ok(())
+    }
+
+    // Entries matched or created stay
+    pub async fn delete(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+    ) -> Result<(), DatabaseError> {
+        let query = format!("DELETE FROM {} WHERE uid = $1", Self::get_table_name());
+        sqlx::query(&query)
+            .bind(uid)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "deleting reconciliation"))?;
+        Ok(())
+    }
+}
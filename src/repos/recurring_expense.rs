@@ -0,0 +1,345 @@
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::DatabaseError;
+use crate::repos::base::BaseRepo;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RecurringCadence {
+    Monthly,
+    Weekly,
+}
+
+impl RecurringCadence {
+    // Accepts both the stored names and the Indonesian words used in chat
+    pub fn parse(input: &str) -> Option<Self> {
+        match input.trim().to_lowercase().as_str() {
+            "monthly" | "bulanan" => Some(Self::Monthly),
+            "weekly" | "mingguan" => Some(Self::Weekly),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Monthly => "monthly",
+            Self::Weekly => "weekly",
+        }
+    }
+
+    // Monthly runs on a day of month, weekly on an ISO weekday (1 = Monday)
+    pub fn is_valid_day(&self, day: i16) -> bool {
+        match self {
+            Self::Monthly => (1..=31).contains(&day),
+            Self::Weekly => (1..=7).contains(&day),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for RecurringCadence {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        RecurringCadence::parse(&s)
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid cadence: {}", s)))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct RecurringExpense {
+    pub uid: Uuid,
+    pub group_uid: Uuid,
+    pub product: String,
+    pub price: Decimal,
+    pub category_uid: Option<Uuid>,
+    pub cadence: String, // from enum via ::text
+    pub run_day: i16,
+    pub active: bool,
+    pub last_run_on: Option<NaiveDate>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl RecurringExpense {
+    /*
+     * Whether this item should be materialized on `date`.
+     * Monthly items scheduled past the end of a short month run on its last day,
+     * e.g. run_day 31 runs on 30th April and 28th/29th February.
+     */
+    pub fn is_due_on(&self, date: NaiveDate) -> bool {
+        if !self.active || self.last_run_on == Some(date) {
+            return false;
+        }
+
+        match RecurringCadence::parse(&self.cadence) {
+            Some(RecurringCadence::Monthly) => {
+                let last_day = last_day_of_month(date.year(), date.month());
+                date.day() == (self.run_day as u32).min(last_day)
+            }
+            Some(RecurringCadence::Weekly) => {
+                date.weekday().number_from_monday() == self.run_day as u32
+            }
+            None => false,
+        }
+    }
+}
+
+fn last_day_of_month(year: i32, month: u32) -> u32 {
+    let next_month = if month == 12 {
+        NaiveDate::from_ymd_opt(year + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(year, month + 1, 1)
+    }
+    .unwrap();
+    next_month.pred_opt().unwrap().day()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateRecurringExpenseDbPayload {
+    pub group_uid: Uuid,
+    pub product: String,
+    pub price: Decimal,
+    pub category_uid: Option<Uuid>,
+    pub cadence: RecurringCadence,
+    pub run_day: i16,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateRecurringExpenseDbPayload {
+    pub product: Option<String>,
+    pub price: Option<Decimal>,
+    pub category_uid: Option<Option<Uuid>>,
+    pub cadence: Option<RecurringCadence>,
+    pub run_day: Option<i16>,
+    pub active: Option<bool>,
+}
+
+pub struct RecurringExpenseRepo;
+
+impl BaseRepo for RecurringExpenseRepo {
+    fn get_table_name() -> &'static str {
+        "recurring_expenses"
+    }
+}
+
+impl RecurringExpenseRepo {
+    pub async fn create(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        payload: CreateRecurringExpenseDbPayload,
+    ) -> Result<RecurringExpense, DatabaseError> {
+        let uid = Uuid::new_v4();
+        let query = format!(
+            "INSERT INTO {} (uid, group_uid, product, price, category_uid, cadence, run_day) VALUES ($1, $2, $3, $4, $5, $6::recurring_cadence, $7) RETURNING uid, group_uid, product, price, category_uid, cadence::text AS cadence, run_day, active, last_run_on, created_at, updated_at",
+            Self::get_table_name()
+        );
+        let rec = sqlx::query_as::<_, RecurringExpense>(&query)
+            .bind(uid)
+            .bind(payload.group_uid)
+            .bind(payload.product)
+            .bind(payload.price)
+            .bind(payload.category_uid)
+            .bind(payload.cadence.as_str())
+            .bind(payload.run_day)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "creating recurring expense"))?;
+        Ok(rec)
+    }
+
+    pub async fn list_by_group(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+    ) -> Result<Vec<RecurringExpense>, DatabaseError> {
+        let query = format!(
+            "SELECT uid, group_uid, product, price, category_uid, cadence::text AS cadence, run_day, active, last_run_on, created_at, updated_at FROM {} WHERE group_uid = $1 ORDER BY created_at ASC",
+            Self::get_table_name()
+        );
+        let recs = sqlx::query_as::<_, RecurringExpense>(&query)
+            .bind(group_uid)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "listing recurring expenses by group"))?;
+        Ok(recs)
+    }
+
+    // Only groups that are neither deleted nor archived keep creating entries
+    pub async fn list_active(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<Vec<RecurringExpense>, DatabaseError> {
+        let query = format!(
+            "SELECT uid, group_uid, product, price, category_uid, cadence::text AS cadence, run_day, active, last_run_on, created_at, updated_at FROM {} WHERE active = TRUE AND group_uid IN (SELECT uid FROM expense_groups WHERE deleted_at IS NULL AND archived_at IS NULL)",
+            Self::get_table_name()
+        );
+        let recs = sqlx::query_as::<_, RecurringExpense>(&query)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "listing active recurring expenses"))?;
+        Ok(recs)
+    }
+
+    pub async fn count_by_group(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+    ) -> Result<i64, DatabaseError> {
+        let query = format!(
+            "SELECT COUNT(*) FROM {} WHERE group_uid = $1",
+            Self::get_table_name()
+        );
+        let count = sqlx::query_scalar::<_, i64>(&query)
+            .bind(group_uid)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "counting recurring expenses"))?;
+        Ok(count)
+    }
+
+    pub async fn get(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+    ) -> Result<RecurringExpense, DatabaseError> {
+        let query = format!(
+            "SELECT uid, group_uid, product, price, category_uid, cadence::text AS cadence, run_day, active, last_run_on, created_at, updated_at FROM {} WHERE uid = $1",
+            Self::get_table_name()
+        );
+        let rec = sqlx::query_as::<_, RecurringExpense>(&query)
+            .bind(uid)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "getting recurring expense"))?;
+        Ok(rec)
+    }
+
+    pub async fn update(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+        payload: UpdateRecurringExpenseDbPayload,
+    ) -> Result<RecurringExpense, DatabaseError> {
+        let current = Self::get(tx, uid).await?;
+        let product = payload.product.unwrap_or(current.product);
+        let price = payload.price.unwrap_or(current.price);
+        let category_uid = payload.category_uid.unwrap_or(current.category_uid);
+        let cadence = payload
+            .cadence
+            .map(|c| c.as_str().to_string())
+            .unwrap_or(current.cadence);
+        let run_day = payload.run_day.unwrap_or(current.run_day);
+        let active = payload.active.unwrap_or(current.active);
+        let query = format!(
+            "UPDATE {} SET product = $1, price = $2, category_uid = $3, cadence = $4::recurring_cadence, run_day = $5, active = $6, updated_at = now() WHERE uid = $7 RETURNING uid, group_uid, product, price, category_uid, cadence::text AS cadence, run_day, active, last_run_on, created_at, updated_at",
+            Self::get_table_name()
+        );
+        let rec = sqlx::query_as::<_, RecurringExpense>(&query)
+            .bind(product)
+            .bind(price)
+            .bind(category_uid)
+            .bind(cadence)
+            .bind(run_day)
+            .bind(active)
+            .bind(uid)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "updating recurring expense"))?;
+        Ok(rec)
+    }
+
+    pub async fn mark_run(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+        run_on: NaiveDate,
+    ) -> Result<(), DatabaseError> {
+        let query = format!(
+            "UPDATE {} SET last_run_on = $1, updated_at = now() WHERE uid = $2",
+            Self::get_table_name()
+        );
+        sqlx::query(&query)
+            .bind(run_on)
+            .bind(uid)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "marking recurring expense run"))?;
+        Ok(())
+    }
+
+    pub async fn delete(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+    ) -> Result<(), DatabaseError> {
+        let query = format!("DELETE FROM {} WHERE uid = $1", Self::get_table_name());
+        sqlx::query(&query)
+            .bind(uid)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "deleting recurring expense"))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn recurring(cadence: &str, run_day: i16) -> RecurringExpense {
+        RecurringExpense {
+            uid: Uuid::new_v4(),
+            group_uid: Uuid::new_v4(),
+            product: "Sewa".to_string(),
+            price: dec!(1_500_000),
+            category_uid: None,
+            cadence: cadence.to_string(),
+            run_day,
+            active: true,
+            last_run_on: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_monthly_is_due() {
+        let item = recurring("monthly", 5);
+        assert!(item.is_due_on(NaiveDate::from_ymd_opt(2025, 10, 5).unwrap()));
+        assert!(!item.is_due_on(NaiveDate::from_ymd_opt(2025, 10, 6).unwrap()));
+    }
+
+    #[test]
+    fn test_monthly_clamps_to_last_day() {
+        let item = recurring("monthly", 31);
+        assert!(item.is_due_on(NaiveDate::from_ymd_opt(2025, 2, 28).unwrap()));
+        assert!(item.is_due_on(NaiveDate::from_ymd_opt(2025, 4, 30).unwrap()));
+        assert!(!item.is_due_on(NaiveDate::from_ymd_opt(2025, 3, 30).unwrap()));
+    }
+
+    #[test]
+    fn test_weekly_is_due() {
+        // 2025-10-06 is a Monday
+        let item = recurring("weekly", 1);
+        assert!(item.is_due_on(NaiveDate::from_ymd_opt(2025, 10, 6).unwrap()));
+        assert!(!item.is_due_on(NaiveDate::from_ymd_opt(2025, 10, 7).unwrap()));
+    }
+
+    #[test]
+    fn test_not_due_twice_or_when_inactive() {
+        let date = NaiveDate::from_ymd_opt(2025, 10, 5).unwrap();
+        let mut item = recurring("monthly", 5);
+        item.last_run_on = Some(date);
+        assert!(!item.is_due_on(date));
+
+        let mut item = recurring("monthly", 5);
+        item.active = false;
+        assert!(!item.is_due_on(date));
+    }
+
+    #[test]
+    fn test_parse_cadence() {
+        assert_eq!(RecurringCadence::parse("Bulanan"), Some(RecurringCadence::Monthly));
+        assert_eq!(RecurringCadence::parse("weekly"), Some(RecurringCadence::Weekly));
+        assert_eq!(RecurringCadence::parse("daily"), None);
+    }
+}
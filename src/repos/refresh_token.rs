@@ -0,0 +1,234 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::DatabaseError;
+use crate::repos::base::BaseRepo;
+use crate::utils::secret_token::{generate_token, hash_token};
+
+const REFRESH_TOKEN_COLUMNS: &str = "uid, user_uid, session_uid, token_hash, user_agent, ip_address, signed_in_at, expires_at, revoked_at, created_at";
+
+// A web session. Access tokens carry its uid as `sid`, so revoking the row also rejects them
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct RefreshToken {
+    pub uid: Uuid,
+    pub user_uid: Uuid,
+    // Shared by every token rotated from the same sign-in
+    pub session_uid: Uuid,
+    pub token_hash: String,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub signed_in_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl RefreshToken {
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.revoked_at.is_none() && self.expires_at > now
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateRefreshTokenDbPayload {
+    pub user_uid: Uuid,
+    pub session_uid: Uuid,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub signed_in_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+// A signed in device, as listed to its user
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct Session {
+    /// Stays the same when the session's refresh token is rotated
+    pub uid: Uuid,
+    pub user_agent: Option<String>,
+    /// Address the session signed in from
+    pub ip_address: Option<String>,
+    pub signed_in_at: DateTime<Utc>,
+    /// When the session last renewed its access token
+    pub last_used_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    /// Whether this is the session making the request
+    pub current: bool,
+}
+
+pub struct RefreshTokenRepo;
+
+impl BaseRepo for RefreshTokenRepo {
+    fn get_table_name() -> &'static str {
+        "refresh_tokens"
+    }
+}
+
+impl RefreshTokenRepo {
+    // Returns the stored row together with the plain token, which is only available here
+    pub async fn create(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        payload: CreateRefreshTokenDbPayload,
+    ) -> Result<(RefreshToken, String), DatabaseError> {
+        let uid = Uuid::new_v4();
+        let token = generate_token();
+        let query = format!(
+            "INSERT INTO {} (uid, user_uid, session_uid, token_hash, user_agent, ip_address, signed_in_at, expires_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING {REFRESH_TOKEN_COLUMNS}",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, RefreshToken>(&query)
+            .bind(uid)
+            .bind(payload.user_uid)
+            .bind(payload.session_uid)
+            .bind(hash_token(&token))
+            .bind(payload.user_agent)
+            .bind(payload.ip_address)
+            .bind(payload.signed_in_at)
+            .bind(payload.expires_at)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "creating refresh token"))?;
+        Ok((row, token))
+    }
+
+    pub async fn get(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+    ) -> Result<RefreshToken, DatabaseError> {
+        let query = format!(
+            "SELECT {REFRESH_TOKEN_COLUMNS} FROM {} WHERE uid = $1",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, RefreshToken>(&query)
+            .bind(uid)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "getting refresh token"))?;
+        Ok(row)
+    }
+
+    // Locks the row so the same token cannot be rotated twice concurrently
+    pub async fn get_by_token_for_update(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        token: &str,
+    ) -> Result<Option<RefreshToken>, DatabaseError> {
+        let query = format!(
+            "SELECT {REFRESH_TOKEN_COLUMNS} FROM {} WHERE token_hash = $1 FOR UPDATE",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, RefreshToken>(&query)
+            .bind(hash_token(token))
+            .fetch_optional(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "getting refresh token by token"))?;
+        Ok(row)
+    }
+
+    pub async fn revoke(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+    ) -> Result<(), DatabaseError> {
+        let query = format!(
+            "UPDATE {} SET revoked_at = now() WHERE uid = $1 AND revoked_at IS NULL",
+            Self::get_table_name()
+        );
+        sqlx::query(&query)
+            .bind(uid)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "revoking refresh token"))?;
+        Ok(())
+    }
+
+    // Signs the user out everywhere, used when a rotated token is presented again
+    pub async fn revoke_all_for_user(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        user_uid: Uuid,
+    ) -> Result<u64, DatabaseError> {
+        let query = format!(
+            "UPDATE {} SET revoked_at = now() WHERE user_uid = $1 AND revoked_at IS NULL",
+            Self::get_table_name()
+        );
+        let res = sqlx::query(&query)
+            .bind(user_uid)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "revoking refresh tokens"))?;
+        Ok(res.rows_affected())
+    }
+
+    // Sessions with an active refresh token, `current` marks the one of the request
+    pub async fn list_sessions(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        user_uid: Uuid,
+        current_session_uid: Option<Uuid>,
+    ) -> Result<Vec<Session>, DatabaseError> {
+        let query = format!(
+            "SELECT session_uid AS uid, user_agent, ip_address, signed_in_at, created_at AS last_used_at, expires_at, COALESCE(session_uid = $2, false) AS current FROM {} WHERE user_uid = $1 AND revoked_at IS NULL AND expires_at > now() ORDER BY created_at DESC",
+            Self::get_table_name()
+        );
+        let rows = sqlx::query_as::<_, Session>(&query)
+            .bind(user_uid)
+            .bind(current_session_uid)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "listing sessions"))?;
+        Ok(rows)
+    }
+
+    /*
+     Signs one device out. Its tokens are deleted rather than revoked: a revoked refresh token
+     presented again counts as replayed and would sign the user out everywhere.
+    */
+    pub async fn delete_session(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        user_uid: Uuid,
+        session_uid: Uuid,
+    ) -> Result<(), DatabaseError> {
+        let query = format!(
+            "DELETE FROM {} WHERE user_uid = $1 AND session_uid = $2",
+            Self::get_table_name()
+        );
+        let res = sqlx::query(&query)
+            .bind(user_uid)
+            .bind(session_uid)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "deleting session"))?;
+        if res.rows_affected() == 0 {
+            return Err(Self::create_not_found_error("Session"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn refresh_token(expires_at: DateTime<Utc>, revoked_at: Option<DateTime<Utc>>) -> RefreshToken {
+        RefreshToken {
+            uid: Uuid::new_v4(),
+            user_uid: Uuid::new_v4(),
+            session_uid: Uuid::new_v4(),
+            token_hash: hash_token("token"),
+            user_agent: None,
+            ip_address: None,
+            signed_in_at: Utc::now(),
+            expires_at,
+            revoked_at,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_is_active() {
+        let now = Utc::now();
+        assert!(refresh_token(now + Duration::days(1), None).is_active(now));
+        assert!(!refresh_token(now - Duration::days(1), None).is_active(now));
+        assert!(!refresh_token(now + Duration::days(1), Some(now)).is_active(now));
+    }
+}
@@ -0,0 +1,270 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::DatabaseError;
+use crate::repos::base::BaseRepo;
+
+/*
+ A repayment of `amount` from `from_user_uid` to `to_user_uid`. It is recorded by the payer
+ and only counts once the receiving member confirmed it (`confirmed_at` is set).
+*/
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct Settlement {
+    pub uid: Uuid,
+    pub group_uid: Uuid,
+    pub from_user_uid: Uuid,
+    pub to_user_uid: Uuid,
+    pub amount: Decimal,
+    pub currency: String,
+    pub note: Option<String>,
+    pub confirmed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Settlement {
+    pub fn is_confirmed(&self) -> bool {
+        self.confirmed_at.is_some()
+    }
+}
+
+/*
+ Where a member stands with the group in one currency. Expenses are shared equally by the
+ owner and members, so a member who paid more than their share is owed the difference.
+ Confirmed settlements move money between members, unconfirmed ones don't count yet.
+*/
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct MemberBalance {
+    pub user_uid: Uuid,
+    pub currency: String,
+    // Expenses added by the member
+    pub paid: Decimal,
+    // Equal share of the expenses added by all members
+    pub share: Decimal,
+    // Settlements paid by the member minus the ones they received
+    pub settled: Decimal,
+    // Positive when the group owes the member, negative when the member owes the group
+    pub balance: Decimal,
+}
+
+impl MemberBalance {
+    // Balances of `members` in their order, `settlements` as (from, to, amount)
+    pub fn of(
+        members: &[Uuid],
+        paid: &HashMap<Uuid, Decimal>,
+        settlements: &[(Uuid, Uuid, Decimal)],
+        currency: &str,
+    ) -> Vec<Self> {
+        if members.is_empty() {
+            return Vec::new();
+        }
+        let total: Decimal = members.iter().filter_map(|uid| paid.get(uid)).sum();
+        let share = (total / Decimal::from(members.len())).round_dp(2);
+        members
+            .iter()
+            .map(|uid| {
+                let paid = paid.get(uid).copied().unwrap_or_default();
+                let settled = settlements
+                    .iter()
+                    .map(|(from, to, amount)| {
+                        if from == uid {
+                            *amount
+                        } else if to == uid {
+                            -*amount
+                        } else {
+                            Decimal::ZERO
+                        }
+                    })
+                    .sum::<Decimal>();
+                Self {
+                    user_uid: *uid,
+                    currency: currency.to_string(),
+                    paid,
+                    share,
+                    settled,
+                    balance: paid - share + settled,
+                }
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateSettlementDbPayload {
+    pub group_uid: Uuid,
+    pub from_user_uid: Uuid,
+    pub to_user_uid: Uuid,
+    pub amount: Decimal,
+    pub currency: String,
+    pub note: Option<String>,
+}
+
+pub struct SettlementRepo;
+
+impl BaseRepo for SettlementRepo {
+    fn get_table_name() -> &'static str {
+        "settlements"
+    }
+}
+
+impl SettlementRepo {
+    pub async fn list_by_group(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+    ) -> Result<Vec<Settlement>, DatabaseError> {
+        let query = format!(
+            "SELECT uid, group_uid, from_user_uid, to_user_uid, amount, currency, note, confirmed_at, created_at FROM {} WHERE group_uid = $1 ORDER BY created_at DESC",
+            Self::get_table_name()
+        );
+        let rows = sqlx::query_as::<_, Settlement>(&query)
+            .bind(group_uid)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "listing settlements by group"))?;
+        Ok(rows)
+    }
+
+    pub async fn get(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+    ) -> Result<Settlement, DatabaseError> {
+        let query = format!(
+            "SELECT uid, group_uid, from_user_uid, to_user_uid, amount, currency, note, confirmed_at, created_at FROM {} WHERE uid = $1",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, Settlement>(&query)
+            .bind(uid)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "getting settlement"))?;
+        Ok(row)
+    }
+
+    pub async fn create(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        payload: CreateSettlementDbPayload,
+    ) -> Result<Settlement, DatabaseError> {
+        let query = format!(
+            "INSERT INTO {} (uid, group_uid, from_user_uid, to_user_uid, amount, currency, note) VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING uid, group_uid, from_user_uid, to_user_uid, amount, currency, note, confirmed_at, created_at",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, Settlement>(&query)
+            .bind(Uuid::new_v4())
+            .bind(payload.group_uid)
+            .bind(payload.from_user_uid)
+            .bind(payload.to_user_uid)
+            .bind(payload.amount)
+            .bind(payload.currency)
+            .bind(payload.note)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "creating settlement"))?;
+        Ok(row)
+    }
+
+    pub async fn confirm(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+    ) -> Result<Settlement, DatabaseError> {
+        let query = format!(
+            "UPDATE {} SET confirmed_at = now() WHERE uid = $1 RETURNING uid, group_uid, from_user_uid, to_user_uid, amount, currency, note, confirmed_at, created_at",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, Settlement>(&query)
+            .bind(uid)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "confirming settlement"))?;
+        Ok(row)
+    }
+
+    /*
+     Balances of the owner and members in `currency`, the owner first. Expenses and
+     settlements in other currencies are left out.
+    */
+    pub async fn member_balances(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+        currency: &str,
+    ) -> Result<Vec<MemberBalance>, DatabaseError> {
+        let members = sqlx::query_scalar::<_, Uuid>(
+            "SELECT owner FROM expense_groups WHERE uid = $1 \
+            UNION ALL (SELECT gm.user_uid FROM group_members gm JOIN expense_groups g ON g.uid = gm.group_uid \
+            WHERE gm.group_uid = $1 AND gm.user_uid <> g.owner ORDER BY gm.created_at ASC)",
+        )
+        .bind(group_uid)
+        .fetch_all(tx.as_mut())
+        .await
+        .map_err(|e| DatabaseError::from_sqlx_error(e, "listing members for balances"))?;
+        let paid = sqlx::query_as::<_, (Uuid, Decimal)>(
+            "SELECT created_by_user_uid, SUM(price) FROM expense_entries \
+            WHERE group_uid = $1 AND currency = $2 AND deleted_at IS NULL AND created_by_user_uid IS NOT NULL \
+            GROUP BY created_by_user_uid",
+        )
+        .bind(group_uid)
+        .bind(currency)
+        .fetch_all(tx.as_mut())
+        .await
+        .map_err(|e| DatabaseError::from_sqlx_error(e, "summing expenses for balances"))?;
+        let query = format!(
+            "SELECT from_user_uid, to_user_uid, amount FROM {} WHERE group_uid = $1 AND currency = $2 AND confirmed_at IS NOT NULL",
+            Self::get_table_name()
+        );
+        let settlements = sqlx::query_as::<_, (Uuid, Uuid, Decimal)>(&query)
+            .bind(group_uid)
+            .bind(currency)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "listing settlements for balances"))?;
+        Ok(MemberBalance::of(
+            &members,
+            &paid.into_iter().collect(),
+            &settlements,
+            currency,
+        ))
+    }
+
+    // Only unconfirmed settlements can be withdrawn
+    pub async fn delete(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+    ) -> Result<(), DatabaseError> {
+        let query = format!("DELETE FROM {} WHERE uid = $1", Self::get_table_name());
+        sqlx::query(&query)
+            .bind(uid)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "deleting settlement"))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_member_balances() {
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let paid = HashMap::from([(a, dec!(90000)), (b, dec!(30000))]);
+
+        let balances = MemberBalance::of(&[a, b, c], &paid, &[], "IDR");
+        assert!(balances.iter().all(|balance| balance.share == dec!(40000)));
+        let by_member: Vec<_> = balances.iter().map(|balance| balance.balance).collect();
+        assert_eq!(by_member, vec![dec!(50000), dec!(-10000), dec!(-40000)]);
+
+        // c pays a back in full
+        let balances = MemberBalance::of(&[a, b, c], &paid, &[(c, a, dec!(40000))], "IDR");
+        let by_member: Vec<_> = balances.iter().map(|balance| balance.balance).collect();
+        assert_eq!(by_member, vec![dec!(10000), dec!(-10000), Decimal::ZERO]);
+        assert_eq!(balances[2].settled, dec!(40000));
+
+        assert!(MemberBalance::of(&[], &paid, &[], "IDR").is_empty());
+    }
+}
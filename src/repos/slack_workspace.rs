@@ -0,0 +1,50 @@
+use crate::error::DatabaseError;
+use crate::repos::base::BaseRepo;
+
+pub struct SlackWorkspaceRepo;
+
+impl BaseRepo for SlackWorkspaceRepo {
+    fn get_table_name() -> &'static str {
+        "slack_workspaces"
+    }
+}
+
+impl SlackWorkspaceRepo {
+    // Only written when the workspace moved to another channel
+    pub async fn set_channel(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        team_id: &str,
+        channel_id: &str,
+    ) -> Result<(), DatabaseError> {
+        let query = format!(
+            "INSERT INTO {table} (team_id, channel_id) VALUES ($1, $2) \
+            ON CONFLICT (team_id) DO UPDATE SET channel_id = EXCLUDED.channel_id, updated_at = now() \
+            WHERE {table}.channel_id <> EXCLUDED.channel_id",
+            table = Self::get_table_name()
+        );
+        sqlx::query(&query)
+            .bind(team_id)
+            .bind(channel_id)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "setting slack workspace channel"))?;
+        Ok(())
+    }
+
+    // None when the bot was never used in the workspace
+    pub async fn get_channel(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        team_id: &str,
+    ) -> Result<Option<String>, DatabaseError> {
+        let query = format!(
+            "SELECT channel_id FROM {} WHERE team_id = $1",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_scalar::<_, String>(&query)
+            .bind(team_id)
+            .fetch_optional(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "getting slack workspace channel"))?;
+        Ok(row)
+    }
+}
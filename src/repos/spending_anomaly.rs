@@ -0,0 +1,72 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::error::DatabaseError;
+use crate::repos::base::BaseRepo;
+
+// A categorized expense the unusual spending check looks at
+#[derive(Debug, Clone, FromRow)]
+pub struct CategorySpend {
+    pub entry_uid: Uuid,
+    pub category_uid: Uuid,
+    pub category: String,
+    pub product: String,
+    pub price: Decimal,
+    pub currency: String,
+    pub created_at: DateTime<Utc>,
+}
+
+pub struct SpendingAnomalyRepo;
+
+impl BaseRepo for SpendingAnomalyRepo {
+    fn get_table_name() -> &'static str {
+        "spending_anomaly_alerts"
+    }
+}
+
+impl SpendingAnomalyRepo {
+    // Entries of the group since `since`, oldest first. Envelopes are left out like in budgets
+    pub async fn list_spends_since(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<CategorySpend>, DatabaseError> {
+        let rows = sqlx::query_as::<_, CategorySpend>(
+            "SELECT e.uid AS entry_uid, e.category_uid, c.name AS category, e.product, e.price, e.currency, e.created_at \
+            FROM expense_entries e JOIN categories c ON c.uid = e.category_uid \
+            WHERE e.group_uid = $1 AND e.deleted_at IS NULL AND e.envelope_uid IS NULL AND e.created_at >= $2 \
+            ORDER BY e.created_at",
+        )
+        .bind(group_uid)
+        .bind(since)
+        .fetch_all(tx.as_mut())
+        .await
+        .map_err(|e| DatabaseError::from_sqlx_error(e, "listing spending for anomalies"))?;
+        Ok(rows)
+    }
+
+    // Returns false when the alert was already sent, `entry_uid` None stands for the day's total
+    pub async fn record_sent(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+        category_uid: Uuid,
+        day: NaiveDate,
+        entry_uid: Option<Uuid>,
+    ) -> Result<bool, DatabaseError> {
+        let query = format!(
+            "INSERT INTO {} (group_uid, category_uid, day, entry_uid) VALUES ($1, $2, $3, $4) ON CONFLICT DO NOTHING",
+            Self::get_table_name()
+        );
+        let res = sqlx::query(&query)
+            .bind(group_uid)
+            .bind(category_uid)
+            .bind(day)
+            .bind(entry_uid)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "recording spending anomaly alert"))?;
+        Ok(res.rows_affected() > 0)
+    }
+}
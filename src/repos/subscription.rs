@@ -192,6 +192,73 @@ impl SubscriptionRepo {
 
         Ok(rows)
     }
+
+    pub async fn get_by_provider_subscription(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        provider: &str,
+        provider_subscription_id: &str,
+    ) -> Result<Subscription, DatabaseError> {
+        let query = format!(
+            "SELECT id, user_uid, tier, status, current_period_start, current_period_end, cancel_at_period_end, created_at, updated_at FROM {} WHERE provider = $1 AND provider_subscription_id = $2",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, Subscription>(&query)
+            .bind(provider)
+            .bind(provider_subscription_id)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| {
+                DatabaseError::from_sqlx_error(e, "getting subscription by provider subscription")
+            })?;
+
+        Ok(row)
+    }
+
+    // Attaches a paid subscription from the payment provider and switches to its tier
+    pub async fn link_provider(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        id: Uuid,
+        tier: SubscriptionTier,
+        provider: &str,
+        provider_customer_id: &str,
+        provider_subscription_id: &str,
+    ) -> Result<Subscription, DatabaseError> {
+        let query = format!(
+            "UPDATE {} SET tier = $1, provider = $2, provider_customer_id = $3, provider_subscription_id = $4, cancel_at_period_end = FALSE WHERE id = $5 RETURNING id, user_uid, tier, status, current_period_start, current_period_end, cancel_at_period_end, created_at, updated_at",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, Subscription>(&query)
+            .bind(tier)
+            .bind(provider)
+            .bind(provider_customer_id)
+            .bind(provider_subscription_id)
+            .bind(id)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "linking subscription to provider"))?;
+
+        Ok(row)
+    }
+
+    // The paid subscription ended at the provider: back to free, the customer id is kept
+    pub async fn unlink_provider(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        id: Uuid,
+    ) -> Result<Subscription, DatabaseError> {
+        let query = format!(
+            "UPDATE {} SET tier = 'free', provider_subscription_id = NULL, current_period_start = NULL, current_period_end = NULL, cancel_at_period_end = FALSE WHERE id = $1 RETURNING id, user_uid, tier, status, current_period_start, current_period_end, cancel_at_period_end, created_at, updated_at",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, Subscription>(&query)
+            .bind(id)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| {
+                DatabaseError::from_sqlx_error(e, "unlinking subscription from provider")
+            })?;
+
+        Ok(row)
+    }
 }
 
 pub struct UserUsageRepo;
@@ -280,7 +347,8 @@ impl UserUsageRepo {
             r#"SELECT COUNT(*)
                FROM expense_entries e
                JOIN group_members gm ON e.group_uid = gm.group_uid
-               WHERE gm.user_uid = $1 AND e.created_at >= $2 AND e.created_at < $3"#,
+               JOIN expense_groups g ON g.uid = e.group_uid AND g.deleted_at IS NULL
+               WHERE gm.user_uid = $1 AND e.deleted_at IS NULL AND e.created_at >= $2 AND e.created_at < $3"#,
         )
         .bind(user_uid)
         .bind(period_start.and_hms_opt(0, 0, 0).unwrap().and_utc())
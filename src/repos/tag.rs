@@ -0,0 +1,249 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::DatabaseError;
+use crate::repos::base::BaseRepo;
+
+const MAX_TAG_LENGTH: usize = 50;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct Tag {
+    pub uid: Uuid,
+    pub group_uid: Uuid,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateTagDbPayload {
+    pub group_uid: Uuid,
+    pub name: String,
+}
+
+/*
+ Canonical form of a tag name: without the leading `#`, lowercase, made of letters,
+ digits, `_` and `-` only, so "#Bali-2025" and "bali-2025" are the same tag.
+ Returns None for names that cannot be a tag.
+*/
+pub fn normalize_tag_name(name: &str) -> Option<String> {
+    let name = name.trim();
+    let name = name.strip_prefix('#').unwrap_or(name).to_lowercase();
+    if name.is_empty()
+        || name.chars().count() > MAX_TAG_LENGTH
+        || !name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+    {
+        return None;
+    }
+    Some(name)
+}
+
+pub struct TagRepo;
+
+impl BaseRepo for TagRepo {
+    fn get_table_name() -> &'static str {
+        "tags"
+    }
+}
+
+impl TagRepo {
+    pub async fn list_by_group(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+    ) -> Result<Vec<Tag>, DatabaseError> {
+        let query = format!(
+            "SELECT uid, group_uid, name, created_at FROM {} WHERE group_uid = $1 ORDER BY name",
+            Self::get_table_name()
+        );
+        let rows = sqlx::query_as::<_, Tag>(&query)
+            .bind(group_uid)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "listing tags by group"))?;
+        Ok(rows)
+    }
+
+    pub async fn get(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+    ) -> Result<Tag, DatabaseError> {
+        let query = format!(
+            "SELECT uid, group_uid, name, created_at FROM {} WHERE uid = $1",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, Tag>(&query)
+            .bind(uid)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "getting tag"))?;
+        Ok(row)
+    }
+
+    // `name` must already be normalized
+    pub async fn find_by_name(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+        name: &str,
+    ) -> Result<Option<Tag>, DatabaseError> {
+        let query = format!(
+            "SELECT uid, group_uid, name, created_at FROM {} WHERE group_uid = $1 AND name = $2",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, Tag>(&query)
+            .bind(group_uid)
+            .bind(name)
+            .fetch_optional(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "finding tag by name"))?;
+        Ok(row)
+    }
+
+    pub async fn create(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        payload: CreateTagDbPayload,
+    ) -> Result<Tag, DatabaseError> {
+        let query = format!(
+            "INSERT INTO {} (uid, group_uid, name) VALUES ($1, $2, $3) RETURNING uid, group_uid, name, created_at",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, Tag>(&query)
+            .bind(Uuid::new_v4())
+            .bind(payload.group_uid)
+            .bind(payload.name)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "creating tag"))?;
+        Ok(row)
+    }
+
+    // Renames a tag, every entry carrying it follows
+    pub async fn rename(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+        name: String,
+    ) -> Result<Tag, DatabaseError> {
+        let query = format!(
+            "UPDATE {} SET name = $1 WHERE uid = $2 RETURNING uid, group_uid, name, created_at",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, Tag>(&query)
+            .bind(name)
+            .bind(uid)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "renaming tag"))?;
+        Ok(row)
+    }
+
+    // Entries lose the tag, the entries themselves are kept
+    pub async fn delete(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+    ) -> Result<(), DatabaseError> {
+        let query = format!("DELETE FROM {} WHERE uid = $1", Self::get_table_name());
+        sqlx::query(&query)
+            .bind(uid)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "deleting tag"))?;
+        Ok(())
+    }
+
+    // Tags of the group with the given (normalized) names, creating the missing ones
+    pub async fn get_or_create(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+        names: &[String],
+    ) -> Result<Vec<Tag>, DatabaseError> {
+        if names.is_empty() {
+            return Ok(Vec::new());
+        }
+        let uids: Vec<Uuid> = names.iter().map(|_| Uuid::new_v4()).collect();
+        let insert_query = format!(
+            "INSERT INTO {} (uid, group_uid, name) SELECT t.uid, $1, t.name FROM unnest($2::uuid[], $3::varchar[]) AS t(uid, name) ON CONFLICT (group_uid, name) DO NOTHING",
+            Self::get_table_name()
+        );
+        sqlx::query(&insert_query)
+            .bind(group_uid)
+            .bind(uids)
+            .bind(names)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "creating tags"))?;
+
+        let query = format!(
+            "SELECT uid, group_uid, name, created_at FROM {} WHERE group_uid = $1 AND name = ANY($2) ORDER BY name",
+            Self::get_table_name()
+        );
+        let rows = sqlx::query_as::<_, Tag>(&query)
+            .bind(group_uid)
+            .bind(names)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "getting tags by name"))?;
+        Ok(rows)
+    }
+
+    // Replaces the tags of an expense entry
+    pub async fn set_for_entry(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        expense_uid: Uuid,
+        tag_uids: &[Uuid],
+    ) -> Result<(), DatabaseError> {
+        sqlx::query("DELETE FROM expense_tags WHERE expense_uid = $1")
+            .bind(expense_uid)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "clearing expense entry tags"))?;
+        sqlx::query(
+            "INSERT INTO expense_tags (expense_uid, tag_uid) SELECT $1, tag_uid FROM unnest($2::uuid[]) AS tag_uid ON CONFLICT DO NOTHING",
+        )
+        .bind(expense_uid)
+        .bind(tag_uids)
+        .execute(tx.as_mut())
+        .await
+        .map_err(|e| DatabaseError::from_sqlx_error(e, "tagging expense entry"))?;
+        Ok(())
+    }
+
+    pub async fn list_names_for_entry(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        expense_uid: Uuid,
+    ) -> Result<Vec<String>, DatabaseError> {
+        let query = format!(
+            "SELECT t.name FROM {} t JOIN expense_tags et ON et.tag_uid = t.uid WHERE et.expense_uid = $1 ORDER BY t.name",
+            Self::get_table_name()
+        );
+        let names = sqlx::query_scalar::<_, String>(&query)
+            .bind(expense_uid)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "listing expense entry tags"))?;
+        Ok(names)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_tag_name() {
+        assert_eq!(
+            normalize_tag_name("#Vacation"),
+            Some("vacation".to_string())
+        );
+        assert_eq!(
+            normalize_tag_name(" bali-2025 "),
+            Some("bali-2025".to_string())
+        );
+        assert_eq!(normalize_tag_name("#"), None);
+        assert_eq!(normalize_tag_name("road trip"), None);
+        assert_eq!(normalize_tag_name("##double"), None);
+        assert_eq!(normalize_tag_name(&"a".repeat(51)), None);
+    }
+}
@@ -0,0 +1,129 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::DatabaseError;
+use crate::repos::base::BaseRepo;
+
+/*
+ A resource type where the user holds more than their tier allows, written by the tier
+ reconciliation. `group_uid` is None for per-user limits (`groups`), set for per-group ones.
+*/
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct TierViolation {
+    pub uid: Uuid,
+    pub user_uid: Uuid,
+    pub group_uid: Option<Uuid>,
+    pub resource_type: String,
+    pub current_count: i32,
+    pub limit_count: i32,
+    pub detected_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreateTierViolationDbPayload {
+    pub user_uid: Uuid,
+    pub group_uid: Option<Uuid>,
+    pub resource_type: String,
+    pub current_count: i32,
+    pub limit_count: i32,
+}
+
+pub struct TierViolationRepo;
+
+impl BaseRepo for TierViolationRepo {
+    fn get_table_name() -> &'static str {
+        "tier_violations"
+    }
+}
+
+impl TierViolationRepo {
+    pub async fn list(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<Vec<TierViolation>, DatabaseError> {
+        let query = format!(
+            "SELECT uid, user_uid, group_uid, resource_type, current_count, limit_count, detected_at FROM {} ORDER BY detected_at",
+            Self::get_table_name()
+        );
+        let rows = sqlx::query_as::<_, TierViolation>(&query)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "listing tier violations"))?;
+        Ok(rows)
+    }
+
+    pub async fn exists_for_group(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+        resource_type: &str,
+    ) -> Result<bool, DatabaseError> {
+        let query = format!(
+            "SELECT EXISTS (SELECT 1 FROM {} WHERE group_uid = $1 AND resource_type = $2)",
+            Self::get_table_name()
+        );
+        let exists = sqlx::query_scalar::<_, bool>(&query)
+            .bind(group_uid)
+            .bind(resource_type)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "checking tier violation"))?;
+        Ok(exists)
+    }
+
+    pub async fn create(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        payload: CreateTierViolationDbPayload,
+    ) -> Result<TierViolation, DatabaseError> {
+        let query = format!(
+            "INSERT INTO {} (uid, user_uid, group_uid, resource_type, current_count, limit_count) VALUES ($1, $2, $3, $4, $5, $6) RETURNING uid, user_uid, group_uid, resource_type, current_count, limit_count, detected_at",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, TierViolation>(&query)
+            .bind(Uuid::new_v4())
+            .bind(payload.user_uid)
+            .bind(payload.group_uid)
+            .bind(payload.resource_type)
+            .bind(payload.current_count)
+            .bind(payload.limit_count)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "creating tier violation"))?;
+        Ok(row)
+    }
+
+    // Keeps `detected_at`, the violation is the same one as long as it is not resolved
+    pub async fn update_counts(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+        current_count: i32,
+        limit_count: i32,
+    ) -> Result<(), DatabaseError> {
+        let query = format!(
+            "UPDATE {} SET current_count = $1, limit_count = $2 WHERE uid = $3",
+            Self::get_table_name()
+        );
+        sqlx::query(&query)
+            .bind(current_count)
+            .bind(limit_count)
+            .bind(uid)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "updating tier violation"))?;
+        Ok(())
+    }
+
+    pub async fn delete(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+    ) -> Result<(), DatabaseError> {
+        let query = format!("DELETE FROM {} WHERE uid = $1", Self::get_table_name());
+        sqlx::query(&query)
+            .bind(uid)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "deleting tier violation"))?;
+        Ok(())
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
@@ -6,12 +8,40 @@ use uuid::Uuid;
 
 use crate::error::DatabaseError;
 use crate::repos::base::BaseRepo;
+use crate::types::SubscriptionTier;
+use crate::utils::pii;
+
+// Platform-wide role, separate from the roles a user has inside groups
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UserRole {
+    User,
+    Admin,
+}
+
+impl UserRole {
+    pub fn parse(input: &str) -> Option<Self> {
+        match input.trim().to_lowercase().as_str() {
+            "user" => Some(Self::User),
+            "admin" => Some(Self::Admin),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::User => "user",
+            Self::Admin => "admin",
+        }
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
 pub struct User {
     pub uid: Uuid,
     pub email: String,
     pub phash: String,
+    // Language of the emails and chats of the user, see `lang::SUPPORTED_LANGS`
+    pub lang: Option<String>,
     pub created_at: DateTime<Utc>,
 }
 
@@ -25,12 +55,38 @@ pub struct CreateUserDbPayload {
 pub struct UpdateUserDbPayload {
     pub email: Option<String>,
     pub phash: Option<String>,
+    pub lang: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow, ToSchema)]
 pub struct UserRead {
     pub uid: Uuid,
     pub email: String,
+    pub lang: Option<String>,
+}
+
+// A user as listed in the admin API, with the tier of their active subscription
+#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct UserSummary {
+    pub uid: Uuid,
+    pub email: String,
+    pub role: String,
+    pub tier: Option<SubscriptionTier>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl User {
+    fn decrypted(mut self) -> Result<Self, DatabaseError> {
+        self.email = pii::decrypt(&self.email)?;
+        Ok(self)
+    }
+}
+
+impl UserRead {
+    fn decrypted(mut self) -> Result<Self, DatabaseError> {
+        self.email = pii::decrypt(&self.email)?;
+        Ok(self)
+    }
 }
 
 pub struct UserRepo;
@@ -46,14 +102,14 @@ impl UserRepo {
         tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
     ) -> Result<Vec<UserRead>, DatabaseError> {
         let query = format!(
-            "SELECT uid, email FROM {} ORDER BY created_at DESC",
+            "SELECT uid, email, lang FROM {} ORDER BY created_at DESC",
             Self::get_table_name()
         );
         let rows = sqlx::query_as::<_, UserRead>(&query)
             .fetch_all(tx.as_mut())
             .await
             .map_err(|e| DatabaseError::from_sqlx_error(e, "listing users"))?;
-        Ok(rows)
+        rows.into_iter().map(UserRead::decrypted).collect()
     }
 
     pub async fn get(
@@ -61,7 +117,7 @@ impl UserRepo {
         uid: Uuid,
     ) -> Result<UserRead, DatabaseError> {
         let query = format!(
-            "SELECT uid, email FROM {} WHERE uid = $1",
+            "SELECT uid, email, lang FROM {} WHERE uid = $1",
             Self::get_table_name()
         );
         let row = sqlx::query_as::<_, UserRead>(&query)
@@ -69,7 +125,7 @@ impl UserRepo {
             .fetch_one(tx.as_mut())
             .await
             .map_err(|e| DatabaseError::from_sqlx_error(e, "getting user"))?;
-        Ok(row)
+        row.decrypted()
     }
 
     pub async fn get_full(
@@ -77,7 +133,7 @@ impl UserRepo {
         uid: Uuid,
     ) -> Result<User, DatabaseError> {
         let query = format!(
-            "SELECT uid, email, phash, created_at FROM {} WHERE uid = $1",
+            "SELECT uid, email, phash, lang, created_at FROM {} WHERE uid = $1",
             Self::get_table_name()
         );
         let row = sqlx::query_as::<_, User>(&query)
@@ -85,23 +141,25 @@ impl UserRepo {
             .fetch_one(tx.as_mut())
             .await
             .map_err(|e| DatabaseError::from_sqlx_error(e, "getting full user"))?;
-        Ok(row)
+        row.decrypted()
     }
 
+    // By the lookup hash, or the email itself for rows not encrypted yet
     pub async fn get_by_email(
         tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
         email: &str,
     ) -> Result<User, DatabaseError> {
         let query = format!(
-            "SELECT uid, email, phash, created_at FROM {} WHERE email = $1",
+            "SELECT uid, email, phash, lang, created_at FROM {} WHERE email_hash = $2 OR email = $1",
             Self::get_table_name()
         );
         let row = sqlx::query_as::<_, User>(&query)
             .bind(email)
+            .bind(pii::lookup_hash(email))
             .fetch_one(tx.as_mut())
             .await
             .map_err(|e| DatabaseError::from_sqlx_error(e, "getting user by email"))?;
-        Ok(row)
+        row.decrypted()
     }
 
     pub async fn create(
@@ -110,17 +168,18 @@ impl UserRepo {
     ) -> Result<User, DatabaseError> {
         let uid = Uuid::new_v4();
         let query = format!(
-            "INSERT INTO {} (uid, email, phash) VALUES ($1, $2, $3) RETURNING uid, email, phash, created_at",
+            "INSERT INTO {} (uid, email, email_hash, phash) VALUES ($1, $2, $3, $4) RETURNING uid, email, phash, lang, created_at",
             Self::get_table_name()
         );
         let row = sqlx::query_as::<_, User>(&query)
             .bind(uid)
-            .bind(payload.email)
+            .bind(pii::encrypt(&payload.email))
+            .bind(pii::lookup_hash(&payload.email))
             .bind(payload.phash)
             .fetch_one(tx.as_mut())
             .await
             .map_err(|e| DatabaseError::from_sqlx_error(e, "creating user"))?;
-        Ok(row)
+        row.decrypted()
     }
 
     pub async fn update(
@@ -131,17 +190,139 @@ impl UserRepo {
         let current = Self::get_full(tx, uid).await?;
         let email = payload.email.unwrap_or(current.email);
         let phash = payload.phash.unwrap_or(current.phash);
+        let lang = payload.lang.or(current.lang);
         let query = format!(
-            "UPDATE {} SET email = $1, phash = $2 WHERE uid = $3 RETURNING uid, email",
+            "UPDATE {} SET email = $1, phash = $2, lang = $4, email_hash = $5 WHERE uid = $3 RETURNING uid, email, lang",
             Self::get_table_name()
         );
         let row = sqlx::query_as::<_, UserRead>(&query)
-            .bind(email)
+            .bind(pii::encrypt(&email))
             .bind(phash)
             .bind(uid)
+            .bind(lang)
+            .bind(pii::lookup_hash(&email))
             .fetch_one(tx.as_mut())
             .await
             .map_err(|e| DatabaseError::from_sqlx_error(e, "updating user"))?;
-        Ok(row)
+        row.decrypted()
+    }
+
+    // Unknown roles are treated as plain users
+    pub async fn get_role(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+    ) -> Result<UserRole, DatabaseError> {
+        let query = format!("SELECT role FROM {} WHERE uid = $1", Self::get_table_name());
+        let role = sqlx::query_scalar::<_, String>(&query)
+            .bind(uid)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "getting user role"))?;
+        Ok(UserRole::parse(&role).unwrap_or(UserRole::User))
+    }
+
+    // Emails of the users in `uids`, unknown uids are left out
+    pub async fn emails_by_uids(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uids: &[Uuid],
+    ) -> Result<HashMap<Uuid, String>, DatabaseError> {
+        let query = format!(
+            "SELECT uid, email FROM {} WHERE uid = ANY($1)",
+            Self::get_table_name()
+        );
+        let rows = sqlx::query_as::<_, (Uuid, String)>(&query)
+            .bind(uids)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "listing user emails"))?;
+        rows.into_iter()
+            .map(|(uid, email)| Ok((uid, pii::decrypt(&email)?)))
+            .collect()
+    }
+
+    /*
+     Users who added entries to the group and go by `name`, the part of their email before the
+     `@` in lower case. Matched here since the emails may be encrypted.
+    */
+    pub async fn list_creators_named(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+        name: &str,
+    ) -> Result<Vec<Uuid>, DatabaseError> {
+        let query = format!(
+            "SELECT uid, email FROM {} WHERE uid IN (SELECT created_by_user_uid FROM expense_entries WHERE group_uid = $1)",
+            Self::get_table_name()
+        );
+        let rows = sqlx::query_as::<_, (Uuid, String)>(&query)
+            .bind(group_uid)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "listing entry creators"))?;
+        let mut uids = Vec::new();
+        for (uid, email) in rows {
+            let email = pii::decrypt(&email)?;
+            if email.split('@').next().map(str::to_lowercase).as_deref() == Some(name) {
+                uids.push(uid);
+            }
+        }
+        Ok(uids)
+    }
+
+    /*
+     Users with exactly the email `search`, or every user without one, newest first, with the
+     total count. Encrypted emails can only be matched by their lookup hash, so there is no
+     partial or case-insensitive search.
+    */
+    pub async fn search(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        search: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<UserSummary>, i64), DatabaseError> {
+        let email = search.map(str::trim);
+        let hash = email.and_then(pii::lookup_hash);
+        let count_query = format!(
+            "SELECT COUNT(*) FROM {} WHERE ($1::text IS NULL OR email_hash = $2 OR email = $1)",
+            Self::get_table_name()
+        );
+        let total = sqlx::query_scalar::<_, i64>(&count_query)
+            .bind(email)
+            .bind(&hash)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "counting users"))?;
+
+        let query = format!(
+            "SELECT u.uid, u.email, u.role, s.tier, u.created_at FROM {} u LEFT JOIN subscriptions s ON s.user_uid = u.uid AND s.status = 'active' WHERE ($1::text IS NULL OR u.email_hash = $4 OR u.email = $1) ORDER BY u.created_at DESC LIMIT $2 OFFSET $3",
+            Self::get_table_name()
+        );
+        let rows = sqlx::query_as::<_, UserSummary>(&query)
+            .bind(email)
+            .bind(limit)
+            .bind(offset)
+            .bind(&hash)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "searching users"))?;
+        let rows = rows
+            .into_iter()
+            .map(|mut row| {
+                row.email = pii::decrypt(&row.email)?;
+                Ok(row)
+            })
+            .collect::<Result<_, DatabaseError>>()?;
+        Ok((rows, total))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_user_role() {
+        assert_eq!(UserRole::parse(" Admin "), Some(UserRole::Admin));
+        assert_eq!(UserRole::parse("user"), Some(UserRole::User));
+        assert_eq!(UserRole::parse("owner"), None);
     }
 }
@@ -0,0 +1,201 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::error::DatabaseError;
+use crate::repos::base::BaseRepo;
+use crate::utils::secret_token::{generate_token, hash_token};
+
+pub const RECOVERY_CODE_COUNT: usize = 8;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct UserMfa {
+    pub user_uid: Uuid,
+    pub secret: String,
+    // Stays empty until the first code is verified, so a half-finished enrollment never blocks login
+    pub enabled_at: Option<DateTime<Utc>>,
+    // TOTP time step of the last accepted code, used to reject replays
+    pub last_used_step: Option<i64>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl UserMfa {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled_at.is_some()
+    }
+}
+
+// Formatted as "xxxxx-xxxxx" for readability
+pub fn generate_recovery_code() -> String {
+    let token = generate_token();
+    format!("{}-{}", &token[..5], &token[5..10])
+}
+
+// Dashes, spaces and case are ignored when matching
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+pub struct UserMfaRepo;
+
+impl BaseRepo for UserMfaRepo {
+    fn get_table_name() -> &'static str {
+        "user_mfa"
+    }
+}
+
+impl UserMfaRepo {
+    pub async fn get(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        user_uid: Uuid,
+    ) -> Result<Option<UserMfa>, DatabaseError> {
+        let query = format!(
+            "SELECT user_uid, secret, enabled_at, last_used_step, created_at FROM {} WHERE user_uid = $1",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, UserMfa>(&query)
+            .bind(user_uid)
+            .fetch_optional(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "getting user mfa"))?;
+        Ok(row)
+    }
+
+    // Locks the row so the same code cannot be accepted twice concurrently
+    pub async fn get_for_update(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        user_uid: Uuid,
+    ) -> Result<Option<UserMfa>, DatabaseError> {
+        let query = format!(
+            "SELECT user_uid, secret, enabled_at, last_used_step, created_at FROM {} WHERE user_uid = $1 FOR UPDATE",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, UserMfa>(&query)
+            .bind(user_uid)
+            .fetch_optional(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "getting user mfa"))?;
+        Ok(row)
+    }
+
+    // Replaces any pending (not yet verified) enrollment with a fresh secret
+    pub async fn start_enrollment(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        user_uid: Uuid,
+        secret: &str,
+    ) -> Result<UserMfa, DatabaseError> {
+        let query = format!(
+            "INSERT INTO {0} (user_uid, secret) VALUES ($1, $2) \
+             ON CONFLICT (user_uid) DO UPDATE SET secret = EXCLUDED.secret, enabled_at = NULL, last_used_step = NULL, created_at = now() \
+             WHERE {0}.enabled_at IS NULL \
+             RETURNING user_uid, secret, enabled_at, last_used_step, created_at",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, UserMfa>(&query)
+            .bind(user_uid)
+            .bind(secret)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "starting mfa enrollment"))?;
+        Ok(row)
+    }
+
+    pub async fn enable(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        user_uid: Uuid,
+        step: i64,
+    ) -> Result<(), DatabaseError> {
+        let query = format!(
+            "UPDATE {} SET enabled_at = now(), last_used_step = $2 WHERE user_uid = $1",
+            Self::get_table_name()
+        );
+        sqlx::query(&query)
+            .bind(user_uid)
+            .bind(step)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "enabling mfa"))?;
+        Ok(())
+    }
+
+    pub async fn record_used_step(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        user_uid: Uuid,
+        step: i64,
+    ) -> Result<(), DatabaseError> {
+        let query = format!(
+            "UPDATE {} SET last_used_step = $2 WHERE user_uid = $1",
+            Self::get_table_name()
+        );
+        sqlx::query(&query)
+            .bind(user_uid)
+            .bind(step)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "recording mfa step"))?;
+        Ok(())
+    }
+
+    // Returns the plain codes, which are only available here
+    pub async fn replace_recovery_codes(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        user_uid: Uuid,
+    ) -> Result<Vec<String>, DatabaseError> {
+        sqlx::query("DELETE FROM user_mfa_recovery_codes WHERE user_uid = $1")
+            .bind(user_uid)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "deleting recovery codes"))?;
+
+        let codes = (0..RECOVERY_CODE_COUNT)
+            .map(|_| generate_recovery_code())
+            .collect::<Vec<_>>();
+        for code in &codes {
+            sqlx::query(
+                "INSERT INTO user_mfa_recovery_codes (uid, user_uid, code_hash) VALUES ($1, $2, $3)",
+            )
+            .bind(Uuid::new_v4())
+            .bind(user_uid)
+            .bind(hash_token(&normalize_recovery_code(code)))
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "creating recovery code"))?;
+        }
+        Ok(codes)
+    }
+
+    // Marks a matching unused code as used, false when there is none
+    pub async fn use_recovery_code(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        user_uid: Uuid,
+        code: &str,
+    ) -> Result<bool, DatabaseError> {
+        let res = sqlx::query(
+            "UPDATE user_mfa_recovery_codes SET used_at = now() WHERE user_uid = $1 AND code_hash = $2 AND used_at IS NULL",
+        )
+        .bind(user_uid)
+        .bind(hash_token(&normalize_recovery_code(code)))
+        .execute(tx.as_mut())
+        .await
+        .map_err(|e| DatabaseError::from_sqlx_error(e, "using recovery code"))?;
+        Ok(res.rows_affected() == 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recovery_code_format() {
+        let code = generate_recovery_code();
+        assert_eq!(code.len(), 11);
+        assert_eq!(code.chars().nth(5), Some('-'));
+        assert_eq!(normalize_recovery_code(&code).len(), 10);
+        assert_eq!(normalize_recovery_code(" AB12c-3dE45 "), "ab12c3de45");
+    }
+}
@@ -0,0 +1,299 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::DatabaseError;
+use crate::repos::base::BaseRepo;
+use crate::utils::secret_token::generate_token;
+
+const WEBHOOK_COLUMNS: &str =
+    "uid, group_uid, url, secret, events, active, created_by, created_at, updated_at";
+const DELIVERY_COLUMNS: &str = "uid, webhook_uid, event, payload, status, attempts, response_status, last_error, created_at, delivered_at";
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct Webhook {
+    pub uid: Uuid,
+    pub group_uid: Uuid,
+    pub url: String,
+    /// Only returned when the webhook is created
+    #[serde(skip_serializing, default)]
+    pub secret: String,
+    /// Events sent to `url`, e.g. `expense.created`
+    pub events: Vec<String>,
+    /// Inactive webhooks keep their settings but receive nothing
+    pub active: bool,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub struct CreateWebhookDbPayload {
+    pub group_uid: Uuid,
+    pub url: String,
+    pub events: Vec<String>,
+    pub created_by: Uuid,
+}
+
+#[derive(Debug, Default)]
+pub struct UpdateWebhookDbPayload {
+    pub url: Option<String>,
+    pub events: Option<Vec<String>>,
+    pub active: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeliveryStatus {
+    // Queued, or failed and waiting for a retry
+    Pending,
+    Succeeded,
+    // Out of attempts
+    Failed,
+}
+
+impl DeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Succeeded => "succeeded",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct WebhookDelivery {
+    pub uid: Uuid,
+    pub webhook_uid: Uuid,
+    pub event: String,
+    /// The body that was sent
+    #[schema(value_type = Object)]
+    pub payload: serde_json::Value,
+    /// One of `pending`, `succeeded`, `failed`
+    pub status: String,
+    pub attempts: i32,
+    /// HTTP status of the last attempt, none when the request itself failed
+    pub response_status: Option<i32>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+pub struct WebhookRepo;
+
+impl BaseRepo for WebhookRepo {
+    fn get_table_name() -> &'static str {
+        "webhooks"
+    }
+}
+
+impl WebhookRepo {
+    // The secret is generated here and only readable from the returned row
+    pub async fn create(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        payload: CreateWebhookDbPayload,
+    ) -> Result<Webhook, DatabaseError> {
+        let query = format!(
+            "INSERT INTO {} (group_uid, url, secret, events, created_by) VALUES ($1, $2, $3, $4, $5) RETURNING {WEBHOOK_COLUMNS}",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, Webhook>(&query)
+            .bind(payload.group_uid)
+            .bind(payload.url)
+            .bind(generate_token())
+            .bind(payload.events)
+            .bind(payload.created_by)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "creating webhook"))?;
+        Ok(row)
+    }
+
+    pub async fn get(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+    ) -> Result<Webhook, DatabaseError> {
+        let query = format!(
+            "SELECT {WEBHOOK_COLUMNS} FROM {} WHERE uid = $1",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, Webhook>(&query)
+            .bind(uid)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "getting webhook"))?;
+        Ok(row)
+    }
+
+    pub async fn list_by_group(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+    ) -> Result<Vec<Webhook>, DatabaseError> {
+        let query = format!(
+            "SELECT {WEBHOOK_COLUMNS} FROM {} WHERE group_uid = $1 ORDER BY created_at",
+            Self::get_table_name()
+        );
+        let rows = sqlx::query_as::<_, Webhook>(&query)
+            .bind(group_uid)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "listing webhooks"))?;
+        Ok(rows)
+    }
+
+    // Active webhooks of the group that asked for `event`
+    pub async fn list_subscribed(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_uid: Uuid,
+        event: &str,
+    ) -> Result<Vec<Webhook>, DatabaseError> {
+        let query = format!(
+            "SELECT {WEBHOOK_COLUMNS} FROM {} WHERE group_uid = $1 AND active AND $2 = ANY(events)",
+            Self::get_table_name()
+        );
+        let rows = sqlx::query_as::<_, Webhook>(&query)
+            .bind(group_uid)
+            .bind(event)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "listing subscribed webhooks"))?;
+        Ok(rows)
+    }
+
+    pub async fn update(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+        payload: UpdateWebhookDbPayload,
+    ) -> Result<Webhook, DatabaseError> {
+        let query = format!(
+            "UPDATE {} SET url = COALESCE($2, url), events = COALESCE($3, events), active = COALESCE($4, active), updated_at = now() WHERE uid = $1 RETURNING {WEBHOOK_COLUMNS}",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, Webhook>(&query)
+            .bind(uid)
+            .bind(payload.url)
+            .bind(payload.events)
+            .bind(payload.active)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "updating webhook"))?;
+        Ok(row)
+    }
+
+    // Its deliveries go with it
+    pub async fn delete(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+    ) -> Result<(), DatabaseError> {
+        let query = format!("DELETE FROM {} WHERE uid = $1", Self::get_table_name());
+        sqlx::query(&query)
+            .bind(uid)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "deleting webhook"))?;
+        Ok(())
+    }
+}
+
+pub struct WebhookDeliveryRepo;
+
+impl BaseRepo for WebhookDeliveryRepo {
+    fn get_table_name() -> &'static str {
+        "webhook_deliveries"
+    }
+}
+
+impl WebhookDeliveryRepo {
+    pub async fn create(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        webhook_uid: Uuid,
+        event: &str,
+        payload: &serde_json::Value,
+    ) -> Result<WebhookDelivery, DatabaseError> {
+        let query = format!(
+            "INSERT INTO {} (webhook_uid, event, payload) VALUES ($1, $2, $3) RETURNING {DELIVERY_COLUMNS}",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, WebhookDelivery>(&query)
+            .bind(webhook_uid)
+            .bind(event)
+            .bind(payload)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "creating webhook delivery"))?;
+        Ok(row)
+    }
+
+    pub async fn get(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+    ) -> Result<WebhookDelivery, DatabaseError> {
+        let query = format!(
+            "SELECT {DELIVERY_COLUMNS} FROM {} WHERE uid = $1",
+            Self::get_table_name()
+        );
+        let row = sqlx::query_as::<_, WebhookDelivery>(&query)
+            .bind(uid)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "getting webhook delivery"))?;
+        Ok(row)
+    }
+
+    // Counts the attempt and keeps what the endpoint answered
+    pub async fn record_attempt(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        uid: Uuid,
+        status: DeliveryStatus,
+        response_status: Option<i32>,
+        error: Option<&str>,
+    ) -> Result<(), DatabaseError> {
+        let query = format!(
+            "UPDATE {} SET status = $2, attempts = attempts + 1, response_status = $3, last_error = $4, \
+            delivered_at = CASE WHEN $2 = 'succeeded' THEN now() ELSE delivered_at END WHERE uid = $1",
+            Self::get_table_name()
+        );
+        sqlx::query(&query)
+            .bind(uid)
+            .bind(status.as_str())
+            .bind(response_status)
+            .bind(error)
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "recording webhook delivery attempt"))?;
+        Ok(())
+    }
+
+    // Newest first, with the total
+    pub async fn list_by_webhook(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        webhook_uid: Uuid,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<WebhookDelivery>, i64), DatabaseError> {
+        let count_query = format!(
+            "SELECT COUNT(*) FROM {} WHERE webhook_uid = $1",
+            Self::get_table_name()
+        );
+        let total = sqlx::query_scalar::<_, i64>(&count_query)
+            .bind(webhook_uid)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "counting webhook deliveries"))?;
+
+        let query = format!(
+            "SELECT {DELIVERY_COLUMNS} FROM {} WHERE webhook_uid = $1 ORDER BY created_at DESC LIMIT $2 OFFSET $3",
+            Self::get_table_name()
+        );
+        let rows = sqlx::query_as::<_, WebhookDelivery>(&query)
+            .bind(webhook_uid)
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| DatabaseError::from_sqlx_error(e, "listing webhook deliveries"))?;
+        Ok((rows, total))
+    }
+}
@@ -1,11 +1,37 @@
+pub mod activity;
+pub mod admin;
+pub mod api_keys;
+pub mod billing;
+pub mod budget_plans;
 pub mod budgets;
 pub mod categories;
 pub mod categories_aliases;
 pub mod chat_bind_requests;
 pub mod chat_bindings;
+pub mod chat_links;
+pub mod chat_relay;
+pub mod currencies;
+pub mod debts;
+pub mod email_ingest;
+pub mod envelopes;
 pub mod expense_entry;
 pub mod expense_groups;
+pub mod group_invites;
 pub mod group_members;
+pub mod group_settings;
 pub mod health;
+pub mod income_entry;
+pub mod metrics;
+pub mod notification_settings;
+pub mod overview;
+pub mod periods;
+pub mod products;
+pub mod reconciliations;
+pub mod recurring_expenses;
+pub mod sessions;
+pub mod settlements;
+pub mod stats;
+pub mod tags;
 pub mod users;
 pub mod version;
+pub mod webhooks;
@@ -0,0 +1,98 @@
+use axum::{
+    Json,
+    extract::{Extension, Path, Query, State},
+};
+use serde::Deserialize;
+use utoipa::IntoParams;
+use utoipa_axum::{router::OpenApiRouter, routes};
+use uuid::Uuid;
+
+use crate::{
+    auth::{AuthContext, group_guard::group_guard},
+    error::AppError,
+    types::{AppState, PaginatedResponse},
+    utils::{
+        activity::{ActivityEvent, group_activity},
+        cursor::Cursor,
+    },
+};
+
+pub fn router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new().routes(routes!(list))
+}
+
+const DEFAULT_ACTIVITY_PER_PAGE: u32 = 50;
+const MAX_ACTIVITY_PER_PAGE: u32 = 100;
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ListActivityQuery {
+    /// 1-based page number, defaults to 1
+    pub page: Option<u32>,
+    /// Page size, defaults to 50, at most 100
+    pub per_page: Option<u32>,
+    /// `next_cursor` of the previous page, replaces `page`
+    pub after: Option<String>,
+}
+
+/**
+ * Expenses added, edited or deleted, members joining or leaving and budget changes of the
+ * group in one feed, newest first. Any member can read it
+ */
+#[utoipa::path(
+    get,
+    path = "/groups/{group_uid}/activity",
+    params(("group_uid" = Uuid, Path), ListActivityQuery),
+    responses((status = 200, body = PaginatedResponse<ActivityEvent>)),
+    tag = "Activity",
+    operation_id = "listGroupActivity",
+    security(("bearerAuth" = []))
+)]
+pub async fn list(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(group_uid): Path<Uuid>,
+    Query(query): Query<ListActivityQuery>,
+) -> Result<Json<PaginatedResponse<ActivityEvent>>, AppError> {
+    let mut tx =
+        state.db_pool.begin().await.map_err(|e| {
+            AppError::from_sqlx_error(e, "beginning transaction for listing activity")
+        })?;
+    group_guard(&auth, group_uid, &mut tx, &state.membership_cache).await?;
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query
+        .per_page
+        .unwrap_or(DEFAULT_ACTIVITY_PER_PAGE)
+        .clamp(1, MAX_ACTIVITY_PER_PAGE);
+    let after = match query.after.as_deref() {
+        Some(after) => Some(
+            Cursor::decode(after)
+                .ok_or_else(|| AppError::BadRequest("Invalid cursor".to_string()))?,
+        ),
+        None => None,
+    };
+    let offset = match after {
+        Some(_) => 0,
+        None => (page as i64 - 1) * per_page as i64,
+    };
+    // One extra row tells whether there is a next page
+    let (mut items, total) =
+        group_activity(&mut tx, group_uid, per_page as i64 + 1, offset, after).await?;
+    let next_cursor = if items.len() > per_page as usize {
+        items.truncate(per_page as usize);
+        items
+            .last()
+            .map(|event| Cursor::new(event.created_at, event.uid).encode())
+    } else {
+        None
+    };
+    tx.commit()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "committing transaction for listing activity"))?;
+    Ok(Json(PaginatedResponse {
+        items,
+        total,
+        page,
+        per_page,
+        next_cursor,
+    }))
+}
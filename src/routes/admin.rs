@@ -0,0 +1,311 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+    response::IntoResponse,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use utoipa::{IntoParams, ToSchema};
+use utoipa_axum::{router::OpenApiRouter, routes};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    auth::admin_guard::AdminGuard,
+    backup,
+    error::{AppError, DatabaseError},
+    jobs::tier_reconciliation::{ReconcileSummary, TierReconciliationScheduler},
+    middleware::validated_json::ValidatedJson,
+    repos::{
+        admin::{AdminRepo, GroupInspection, PlatformStats, TierCount},
+        expense_group_member::{GroupMember, GroupMemberRepo},
+        job::{Job, JobRepo, JobStatus},
+        subscription::{
+            CreateSubscriptionDbPayload, Subscription, SubscriptionRepo,
+            UpdateSubscriptionDbPayload,
+        },
+        user::{UserRepo, UserSummary},
+    },
+    types::{AppState, PaginatedResponse, SubscriptionTier},
+};
+
+/*
+ Platform administration. Every handler takes `AdminGuard`, so callers without the
+ `admin` user role are rejected before anything is read.
+*/
+pub fn router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(list_users))
+        .routes(routes!(override_subscription))
+        .routes(routes!(stats))
+        .routes(routes!(get_group))
+        .routes(routes!(reconcile_tiers))
+        .routes(routes!(list_jobs))
+        .routes(routes!(download_backup))
+}
+
+const DEFAULT_PER_PAGE: u32 = 20;
+const MAX_PER_PAGE: u32 = 100;
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct AdminUserQuery {
+    /// The whole email address, matched exactly
+    pub q: Option<String>,
+    /// 1-based page number, defaults to 1
+    pub page: Option<u32>,
+    /// Page size, defaults to 20, at most 100
+    pub per_page: Option<u32>,
+}
+
+#[utoipa::path(get, path = "/admin/users", params(AdminUserQuery), responses((status = 200, body = PaginatedResponse<UserSummary>)), tag = "Admin", operation_id = "adminListUsers", security(("bearerAuth" = [])))]
+pub async fn list_users(
+    State(state): State<AppState>,
+    _admin: AdminGuard,
+    Query(query): Query<AdminUserQuery>,
+) -> Result<Json<PaginatedResponse<UserSummary>>, AppError> {
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query
+        .per_page
+        .unwrap_or(DEFAULT_PER_PAGE)
+        .clamp(1, MAX_PER_PAGE);
+    let search = query.q.as_deref().filter(|q| !q.trim().is_empty());
+    let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for listing users"))?;
+    let (items, total) = UserRepo::search(
+        &mut tx,
+        search,
+        per_page as i64,
+        (page as i64 - 1) * per_page as i64,
+    )
+    .await?;
+    tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for listing users"))?;
+    Ok(Json(PaginatedResponse {
+        items,
+        total,
+        page,
+        per_page,
+        next_cursor: None,
+    }))
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct OverrideSubscriptionPayload {
+    /// One of `free`, `personal`, `family`, `team`, `enterprise`
+    pub tier: String,
+    /// End of the granted period, None keeps it open-ended
+    pub current_period_end: Option<DateTime<Utc>>,
+}
+
+// Validates a tier name from a request body
+pub fn parse_tier(tier: &str) -> Result<SubscriptionTier, AppError> {
+    SubscriptionTier::parse(tier)
+        .ok_or_else(|| AppError::BadRequest(format!("Invalid tier: {}", tier.trim())))
+}
+
+// Replaces the tier of the user's active subscription, creating one when they have none
+#[utoipa::path(put, path = "/admin/users/{uid}/subscription", params(("uid" = Uuid, Path)), request_body = OverrideSubscriptionPayload, responses((status = 200, body = Subscription)), tag = "Admin", operation_id = "adminOverrideSubscription", security(("bearerAuth" = [])))]
+pub async fn override_subscription(
+    State(state): State<AppState>,
+    AdminGuard(auth): AdminGuard,
+    Path(uid): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<OverrideSubscriptionPayload>,
+) -> Result<Json<Subscription>, AppError> {
+    let tier = parse_tier(&payload.tier)?;
+    let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for overriding subscription"))?;
+    UserRepo::get(&mut tx, uid).await?;
+    let subscription = match SubscriptionRepo::get_by_user(&mut tx, uid).await {
+        Ok(current) => {
+            SubscriptionRepo::update(
+                &mut tx,
+                current.id,
+                UpdateSubscriptionDbPayload {
+                    tier: Some(tier),
+                    status: None,
+                    current_period_start: Some(Some(Utc::now())),
+                    current_period_end: Some(payload.current_period_end),
+                    cancel_at_period_end: Some(false),
+                },
+            )
+            .await?
+        }
+        Err(DatabaseError::NotFound(_)) => {
+            SubscriptionRepo::create(
+                &mut tx,
+                CreateSubscriptionDbPayload {
+                    user_uid: uid,
+                    tier,
+                    status: None,
+                    current_period_start: Some(Utc::now()),
+                    current_period_end: payload.current_period_end,
+                },
+            )
+            .await?
+        }
+        Err(e) => return Err(e.into()),
+    };
+    tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for overriding subscription"))?;
+    info!(
+        "Admin {} set the subscription of user {} to {}",
+        auth.user_uid,
+        uid,
+        subscription.get_tier().display_name()
+    );
+    Ok(Json(subscription))
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminStats {
+    #[serde(flatten)]
+    pub platform: PlatformStats,
+    pub users_by_tier: Vec<TierCount>,
+}
+
+#[utoipa::path(get, path = "/admin/stats", responses((status = 200, body = AdminStats)), tag = "Admin", operation_id = "adminGetStats", security(("bearerAuth" = [])))]
+pub async fn stats(
+    State(state): State<AppState>,
+    _admin: AdminGuard,
+) -> Result<Json<AdminStats>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for getting admin stats"))?;
+    let platform = AdminRepo::platform_stats(&mut tx).await?;
+    let users_by_tier = AdminRepo::users_by_tier(&mut tx).await?;
+    tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for getting admin stats"))?;
+    Ok(Json(AdminStats {
+        platform,
+        users_by_tier,
+    }))
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminGroupDetail {
+    pub group: GroupInspection,
+    /// Members added to the group, the owner is only listed on `group`
+    pub members: Vec<GroupMember>,
+}
+
+#[utoipa::path(get, path = "/admin/groups/{uid}", params(("uid" = Uuid, Path)), responses((status = 200, body = AdminGroupDetail)), tag = "Admin", operation_id = "adminGetGroup", security(("bearerAuth" = [])))]
+pub async fn get_group(
+    State(state): State<AppState>,
+    _admin: AdminGuard,
+    Path(uid): Path<Uuid>,
+) -> Result<Json<AdminGroupDetail>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for inspecting group"))?;
+    let group = AdminRepo::inspect_group(&mut tx, uid).await?;
+    let members = GroupMemberRepo::list_by_group(&mut tx, uid).await?;
+    tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for inspecting group"))?;
+    Ok(Json(AdminGroupDetail { group, members }))
+}
+
+// Runs the daily tier reconciliation now, e.g. right after downgrading a user
+#[utoipa::path(post, path = "/admin/reconcile-tiers", responses((status = 200, body = ReconcileSummary)), tag = "Admin", operation_id = "adminReconcileTiers", security(("bearerAuth" = [])))]
+pub async fn reconcile_tiers(
+    State(state): State<AppState>,
+    AdminGuard(auth): AdminGuard,
+) -> Result<Json<ReconcileSummary>, AppError> {
+    let summary = TierReconciliationScheduler::reconcile(
+        &state.db_pool,
+        state.email_sender.as_ref(),
+        &state.lang,
+    )
+    .await
+    .map_err(|e| AppError::Internal(anyhow::anyhow!(e)))?;
+    info!("Admin {} reconciled tier limits", auth.user_uid);
+    Ok(Json(summary))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct AdminJobQuery {
+    /// One of `queued`, `running`, `succeeded`, `dead`
+    pub status: Option<String>,
+    /// Job kind, e.g. `monthly_report`
+    pub kind: Option<String>,
+    /// 1-based page number, defaults to 1
+    pub page: Option<u32>,
+    /// Page size, defaults to 20, at most 100
+    pub per_page: Option<u32>,
+}
+
+// Background jobs, newest first. `status=dead` lists the ones that ran out of attempts
+#[utoipa::path(get, path = "/admin/jobs", params(AdminJobQuery), responses((status = 200, body = PaginatedResponse<Job>)), tag = "Admin", operation_id = "adminListJobs", security(("bearerAuth" = [])))]
+pub async fn list_jobs(
+    State(state): State<AppState>,
+    _admin: AdminGuard,
+    Query(query): Query<AdminJobQuery>,
+) -> Result<Json<PaginatedResponse<Job>>, AppError> {
+    let status = match query.status.as_deref() {
+        Some(status) => Some(JobStatus::parse(status).ok_or_else(|| {
+            AppError::BadRequest(format!("Invalid job status: {}", status.trim()))
+        })?),
+        None => None,
+    };
+    let kind = query
+        .kind
+        .as_deref()
+        .map(str::trim)
+        .filter(|k| !k.is_empty());
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query
+        .per_page
+        .unwrap_or(DEFAULT_PER_PAGE)
+        .clamp(1, MAX_PER_PAGE);
+    let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for listing jobs"))?;
+    let (items, total) = JobRepo::list(
+        &mut tx,
+        status,
+        kind,
+        per_page as i64,
+        (page as i64 - 1) * per_page as i64,
+    )
+    .await?;
+    tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for listing jobs"))?;
+    Ok(Json(PaginatedResponse {
+        items,
+        total,
+        page,
+        per_page,
+        next_cursor: None,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tier() {
+        assert_eq!(parse_tier(" Team ").unwrap(), SubscriptionTier::Team);
+        assert_eq!(parse_tier("free").unwrap(), SubscriptionTier::Free);
+        assert!(parse_tier("platinum").is_err());
+    }
+}
+
+/*
+ The whole database as a backup archive, the same as `cargo run --bin backup -- dump`. Restoring
+ replaces every account, the admin's included, so it is only done with the command.
+*/
+#[utoipa::path(get, path = "/admin/backup", responses((status = 200, description = "Gzip-compressed JSON archive", content_type = "application/gzip")), tag = "Admin", operation_id = "adminDownloadBackup", security(("bearerAuth" = [])))]
+pub async fn download_backup(
+    State(state): State<AppState>,
+    AdminGuard(auth): AdminGuard,
+) -> Result<impl IntoResponse, AppError> {
+    let archive = backup::dump(&state.db_pool).await?;
+    let bytes = archive.encode()?;
+    info!(
+        "Admin {} downloaded a backup of {} rows",
+        auth.user_uid,
+        archive.row_count()
+    );
+    Ok((
+        [
+            (CONTENT_TYPE, "application/gzip".to_string()),
+            (
+                CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"{}\"",
+                    backup::file_name(archive.created_at)
+                ),
+            ),
+        ],
+        bytes,
+    ))
+}
@@ -0,0 +1,123 @@
+use axum::{
+    Json,
+    extract::{Extension, Path, State},
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    auth::{AuthContext, reject_api_key_auth},
+    error::AppError,
+    middleware::validated_json::ValidatedJson,
+    repos::api_key::{ApiKey, ApiKeyRepo, ApiKeyScope, CreateApiKeyDbPayload},
+    types::AppState,
+};
+
+const MAX_API_KEYS_PER_USER: i64 = 20;
+const MAX_API_KEY_NAME_LEN: usize = 100;
+
+pub fn router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(list, create))
+        .routes(routes!(revoke))
+}
+
+#[derive(Debug, Deserialize, ToSchema, Validate)]
+pub struct CreateApiKeyPayload {
+    /// What the key is for, e.g. `Budget spreadsheet`
+    pub name: String,
+    /// `read_only` (default) or `read_write`
+    pub scope: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CreateApiKeyResponse {
+    pub api_key: ApiKey,
+    /// Sent as `X-Api-Key`. Shown only once, only its hash is stored
+    pub key: String,
+}
+
+#[utoipa::path(get, path = "/users/me/api-keys", responses((status = 200, body = [ApiKey])), tag = "Users", operation_id = "listApiKeys", security(("bearerAuth" = [])))]
+pub async fn list(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+) -> Result<Json<Vec<ApiKey>>, AppError> {
+    reject_api_key_auth(&auth)?;
+    let mut tx =
+        state.db_pool.begin().await.map_err(|e| {
+            AppError::from_sqlx_error(e, "beginning transaction for listing api keys")
+        })?;
+    let res = ApiKeyRepo::list_by_user(&mut tx, auth.user_uid).await?;
+    tx.commit()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "committing transaction for listing api keys"))?;
+    Ok(Json(res))
+}
+
+#[utoipa::path(post, path = "/users/me/api-keys", request_body = CreateApiKeyPayload, responses((status = 200, body = CreateApiKeyResponse)), tag = "Users", operation_id = "createApiKey", security(("bearerAuth" = [])))]
+pub async fn create(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    ValidatedJson(payload): ValidatedJson<CreateApiKeyPayload>,
+) -> Result<Json<CreateApiKeyResponse>, AppError> {
+    reject_api_key_auth(&auth)?;
+    let name = payload.name.trim();
+    if name.is_empty() || name.chars().count() > MAX_API_KEY_NAME_LEN {
+        return Err(AppError::BadRequest(format!(
+            "name must be between 1 and {} characters",
+            MAX_API_KEY_NAME_LEN
+        )));
+    }
+    let scope = match payload.scope.as_deref() {
+        None => ApiKeyScope::ReadOnly,
+        Some(scope) => ApiKeyScope::parse(scope).ok_or_else(|| {
+            AppError::BadRequest("scope must be read_only or read_write".to_string())
+        })?,
+    };
+
+    let mut tx =
+        state.db_pool.begin().await.map_err(|e| {
+            AppError::from_sqlx_error(e, "beginning transaction for creating api key")
+        })?;
+    if ApiKeyRepo::count_active_by_user(&mut tx, auth.user_uid).await? >= MAX_API_KEYS_PER_USER {
+        return Err(AppError::BadRequest(format!(
+            "At most {} active API keys, revoke one first",
+            MAX_API_KEYS_PER_USER
+        )));
+    }
+    let (api_key, key) = ApiKeyRepo::create(
+        &mut tx,
+        CreateApiKeyDbPayload {
+            user_uid: auth.user_uid,
+            name: name.to_string(),
+            scope,
+        },
+    )
+    .await?;
+    tx.commit()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "committing transaction for creating api key"))?;
+    Ok(Json(CreateApiKeyResponse { api_key, key }))
+}
+
+// Requests with the key fail right away
+#[utoipa::path(delete, path = "/users/me/api-keys/{uid}", params(("uid" = Uuid, Path)), responses((status = 200, body = ApiKey)), tag = "Users", operation_id = "revokeApiKey", security(("bearerAuth" = [])))]
+pub async fn revoke(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(uid): Path<Uuid>,
+) -> Result<Json<ApiKey>, AppError> {
+    reject_api_key_auth(&auth)?;
+    let mut tx =
+        state.db_pool.begin().await.map_err(|e| {
+            AppError::from_sqlx_error(e, "beginning transaction for revoking api key")
+        })?;
+    let res = ApiKeyRepo::revoke(&mut tx, uid, auth.user_uid).await?;
+    tx.commit()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "committing transaction for revoking api key"))?;
+    Ok(Json(res))
+}
@@ -0,0 +1,256 @@
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    body::Bytes,
+    extract::{Extension, State},
+    http::HeaderMap,
+};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+use validator::Validate;
+
+use crate::{
+    auth::{AuthContext, AuthSource},
+    billing::{BillingEvent, BillingEventKind, CheckoutRequest, PaymentProvider, WebhookError},
+    error::{AppError, DatabaseError},
+    middleware::validated_json::ValidatedJson,
+    repos::{
+        billing_event::BillingEventRepo,
+        subscription::{
+            CreateSubscriptionDbPayload, SubscriptionRepo, UpdateSubscriptionDbPayload,
+        },
+        user::UserRepo,
+    },
+    routes::admin::parse_tier,
+    types::{AppState, SubscriptionTier},
+};
+
+/*
+ Paid subscriptions. The user upgrades through the provider's hosted checkout, after which
+ the provider reports every change of the subscription to `/billing/webhook`:
+ - checkout completed: the user's subscription switches to the paid tier
+ - invoice paid: the paid period is extended
+ - cancellation changed: the subscription stays active until the period ends
+ - subscription ended: back to the free tier
+*/
+pub fn router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(create_checkout))
+        .routes(routes!(webhook))
+}
+
+fn payment_provider(state: &AppState) -> Result<Arc<dyn PaymentProvider + Send + Sync>, AppError> {
+    state
+        .payment_provider
+        .clone()
+        .ok_or_else(|| AppError::BadRequest("Billing is not configured".to_string()))
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct CheckoutPayload {
+    /// Paid tier to subscribe to: `personal`, `family`, `team` or `enterprise`
+    pub tier: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CheckoutResponse {
+    /// Hosted payment page to redirect the user to
+    pub url: String,
+}
+
+#[utoipa::path(post, path = "/billing/checkout", request_body = CheckoutPayload, responses((status = 200, body = CheckoutResponse)), tag = "Billing", operation_id = "createCheckout", security(("bearerAuth" = [])))]
+pub async fn create_checkout(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    ValidatedJson(payload): ValidatedJson<CheckoutPayload>,
+) -> Result<Json<CheckoutResponse>, AppError> {
+    if !matches!(auth.source, AuthSource::Web) {
+        return Err(AppError::Unauthorized(
+            "Checkout requires a web session".to_string(),
+        ));
+    }
+    let provider = payment_provider(&state)?;
+    let tier = parse_tier(&payload.tier)?;
+    if tier == SubscriptionTier::Free {
+        return Err(AppError::BadRequest(
+            "The free tier needs no checkout".to_string(),
+        ));
+    }
+    let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for creating checkout"))?;
+    let user = UserRepo::get(&mut tx, auth.user_uid).await?;
+    tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for creating checkout"))?;
+    let front_end_url = state.front_end_url.trim_end_matches('/');
+    let success_url = format!("{}/billing/success", front_end_url);
+    let cancel_url = format!("{}/billing/cancel", front_end_url);
+    let session = provider
+        .create_checkout_session(CheckoutRequest {
+            user_uid: user.uid,
+            email: &user.email,
+            tier: &tier,
+            success_url: &success_url,
+            cancel_url: &cancel_url,
+        })
+        .await
+        .map_err(|e| AppError::Internal(anyhow::anyhow!(e)))?;
+    info!(
+        "Checkout {} created for user {} ({})",
+        session.id,
+        user.uid,
+        tier.as_str()
+    );
+    Ok(Json(CheckoutResponse { url: session.url }))
+}
+
+// Public route: the provider authenticates with the webhook signature, not a token
+#[utoipa::path(post, path = "/billing/webhook", request_body = String, responses((status = 200, description = "Event applied or ignored"), (status = 401, description = "Invalid signature")), tag = "Billing", operation_id = "billingWebhook")]
+pub async fn webhook(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<(), AppError> {
+    let provider = payment_provider(&state)?;
+    let event = match provider.parse_webhook(&headers, &body) {
+        Ok(Some(event)) => event,
+        Ok(None) => return Ok(()),
+        Err(WebhookError::InvalidSignature) => {
+            return Err(AppError::Unauthorized(
+                "Invalid webhook signature".to_string(),
+            ));
+        }
+        Err(e) => return Err(AppError::BadRequest(e.to_string())),
+    };
+    let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for billing webhook"))?;
+    let is_new =
+        BillingEventRepo::record(&mut tx, provider.name(), &event.id, event_type(&event)).await?;
+    if !is_new {
+        info!("Billing event {} was already applied", event.id);
+        return Ok(());
+    }
+    apply_event(&mut tx, provider.name(), event).await?;
+    tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for billing webhook"))?;
+    Ok(())
+}
+
+fn event_type(event: &BillingEvent) -> &'static str {
+    match event.kind {
+        BillingEventKind::CheckoutCompleted { .. } => "checkout_completed",
+        BillingEventKind::InvoicePaid { .. } => "invoice_paid",
+        BillingEventKind::CancellationChanged { .. } => "cancellation_changed",
+        BillingEventKind::SubscriptionEnded { .. } => "subscription_ended",
+    }
+}
+
+/*
+ Events about a subscription we have not linked yet fail with 404 and roll back, so the
+ provider retries them: an invoice can be delivered before its checkout completed.
+*/
+async fn apply_event(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    provider: &str,
+    event: BillingEvent,
+) -> Result<(), AppError> {
+    match event.kind {
+        BillingEventKind::CheckoutCompleted {
+            user_uid,
+            tier,
+            customer_id,
+            subscription_id,
+        } => {
+            let current = match SubscriptionRepo::get_by_user(tx, user_uid).await {
+                Ok(current) => current,
+                Err(DatabaseError::NotFound(_)) => {
+                    SubscriptionRepo::create(
+                        tx,
+                        CreateSubscriptionDbPayload {
+                            user_uid,
+                            tier: SubscriptionTier::Free,
+                            status: None,
+                            current_period_start: None,
+                            current_period_end: None,
+                        },
+                    )
+                    .await?
+                }
+                Err(e) => return Err(e.into()),
+            };
+            SubscriptionRepo::link_provider(
+                tx,
+                current.id,
+                tier.clone(),
+                provider,
+                &customer_id,
+                &subscription_id,
+            )
+            .await?;
+            info!(
+                "User {} subscribed to {} ({})",
+                user_uid,
+                tier.as_str(),
+                subscription_id
+            );
+        }
+        BillingEventKind::InvoicePaid {
+            subscription_id,
+            period_start,
+            period_end,
+        } => {
+            let current =
+                SubscriptionRepo::get_by_provider_subscription(tx, provider, &subscription_id)
+                    .await?;
+            SubscriptionRepo::update(
+                tx,
+                current.id,
+                UpdateSubscriptionDbPayload {
+                    tier: None,
+                    status: None,
+                    current_period_start: Some(Some(period_start)),
+                    current_period_end: Some(Some(period_end)),
+                    cancel_at_period_end: None,
+                },
+            )
+            .await?;
+        }
+        BillingEventKind::CancellationChanged {
+            subscription_id,
+            cancel_at_period_end,
+        } => {
+            let current =
+                SubscriptionRepo::get_by_provider_subscription(tx, provider, &subscription_id)
+                    .await?;
+            SubscriptionRepo::update(
+                tx,
+                current.id,
+                UpdateSubscriptionDbPayload {
+                    tier: None,
+                    status: None,
+                    current_period_start: None,
+                    current_period_end: None,
+                    cancel_at_period_end: Some(cancel_at_period_end),
+                },
+            )
+            .await?;
+        }
+        BillingEventKind::SubscriptionEnded { subscription_id } => {
+            match SubscriptionRepo::get_by_provider_subscription(tx, provider, &subscription_id)
+                .await
+            {
+                Ok(current) => {
+                    SubscriptionRepo::unlink_provider(tx, current.id).await?;
+                    info!(
+                        "Subscription {} of user {} ended",
+                        subscription_id, current.user_uid
+                    );
+                }
+                // Nothing to downgrade, e.g. the checkout was never completed
+                Err(DatabaseError::NotFound(_)) => {
+                    warn!("Ended subscription {} is not linked", subscription_id);
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,575 @@
+use std::collections::{HashMap, HashSet};
+
+use axum::{
+    Json,
+    extract::{Extension, Path, State},
+};
+use chrono::{Datelike, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    auth::{
+        AuthContext,
+        group_guard::{group_guard, group_role_guard},
+    },
+    error::AppError,
+    middleware::{tier::check_tier_limit, validated_json::ValidatedJson},
+    repos::{
+        audit_log::{AuditActor, AuditChange, AuditEntity, AuditRepo},
+        budget::{
+            Budget, BudgetPeriodType, BudgetRepo, CreateBudgetDbPayload, UpdateBudgetDbPayload,
+        },
+        budget_plan::{BudgetPlan, BudgetPlanRepo, CreateBudgetPlanDbPayload, PlannedBudget},
+        category::CategoryRepo,
+        exchange_rate::ExchangeRateRepo,
+        expense_entry::ExpenseEntryRepo,
+        expense_group::{ExpenseGroup, ExpenseGroupRepo},
+        expense_group_member::GroupRole,
+        group_settings::GroupSettingsRepo,
+        subscription::SubscriptionRepo,
+    },
+    types::{AppState, DeleteResponse},
+    utils::period::BillingPeriod,
+};
+
+pub fn router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(list, create))
+        .routes(routes!(simulate))
+        .routes(routes!(get, delete_))
+        .routes(routes!(apply))
+}
+
+const MAX_PLAN_NAME_LENGTH: usize = 100;
+// Past cycles a simulation replays unless asked for another number
+const DEFAULT_SIMULATED_PERIODS: u32 = 6;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SimulatedPeriod {
+    pub period_start: NaiveDate,
+    /// Exclusive
+    pub period_end: NaiveDate,
+    pub spent: Decimal,
+    /// Left of the budget, negative when spending went over it
+    pub difference: Decimal,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SimulatedCategory {
+    pub category_uid: Uuid,
+    pub category_name: String,
+    /// Budget tracked today, null without one
+    pub current_budget: Option<Decimal>,
+    /// Budget the periods were replayed with, the planned one or else the current one
+    pub budget: Decimal,
+    pub average_spent: Decimal,
+    /// Periods in which spending went over `budget`
+    pub periods_over: usize,
+    /// Oldest first
+    pub periods: Vec<SimulatedPeriod>,
+}
+
+impl SimulatedCategory {
+    // Replays `spending` per period against `budget`
+    fn replay(
+        category_uid: Uuid,
+        category_name: String,
+        current_budget: Option<Decimal>,
+        budget: Decimal,
+        spending: Vec<(BillingPeriod, Decimal)>,
+    ) -> Self {
+        let total: Decimal = spending.iter().map(|(_, spent)| *spent).sum();
+        let average_spent = if spending.is_empty() {
+            Decimal::ZERO
+        } else {
+            (total / Decimal::from(spending.len())).round_dp(2)
+        };
+        let periods: Vec<SimulatedPeriod> = spending
+            .into_iter()
+            .map(|(period, spent)| SimulatedPeriod {
+                period_start: period.start,
+                period_end: period.end,
+                spent,
+                difference: budget - spent,
+            })
+            .collect();
+        Self {
+            category_uid,
+            category_name,
+            current_budget,
+            budget,
+            average_spent,
+            periods_over: periods
+                .iter()
+                .filter(|period| period.difference < Decimal::ZERO)
+                .count(),
+            periods,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BudgetSimulation {
+    /// Currency every amount is in, the group's currency
+    pub currency: String,
+    /// Cycles replayed, the ones before the current cycle since the group was created
+    pub periods: usize,
+    pub total_budget: Decimal,
+    /// Categories over budget in the most periods first
+    pub categories: Vec<SimulatedCategory>,
+    /// Currencies without a known exchange rate, their amounts are added unconverted
+    pub unconverted_currencies: Vec<String>,
+}
+
+/*
+ Replays the last `periods` cycles of the group against its current monthly budgets, with
+ `planned` replacing the budget of their categories. Spending in subcategories counts under their
+ parent's budget, entries assigned to an envelope are left out like in the regular cycle.
+*/
+async fn replay_budgets(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    group: &ExpenseGroup,
+    planned: &[PlannedBudget],
+    periods: u32,
+) -> Result<BudgetSimulation, AppError> {
+    let categories = check_planned_budgets(tx, group.uid, planned).await?;
+    let rates = ExchangeRateRepo::rate_table(tx).await?;
+    let today = Utc::now().date_naive();
+    let current = BillingPeriod::containing(today, group.start_over_date);
+    let created = group.created_at.date_naive();
+    let mut past: Vec<BillingPeriod> = (1..=periods)
+        .map(|n| current.nth_previous(n))
+        .filter(|period| period.end > created)
+        .collect();
+    past.reverse();
+
+    let mut unconverted_currencies: Vec<String> = Vec::new();
+    let mut budgets: HashMap<Uuid, (Option<Decimal>, Decimal)> = HashMap::new();
+    // Budgets over other periods don't compare with a cycle's spending
+    let monthly: Vec<Budget> = BudgetRepo::list_by_group(tx, group.uid)
+        .await?
+        .into_iter()
+        .filter(|budget| budget.period_type() == BudgetPeriodType::Monthly)
+        .collect();
+    let week_start = GroupSettingsRepo::get(tx, group.uid).await?.week_start;
+    let active = Budget::active_by_category(monthly, group.start_over_date, week_start, today);
+    for budget in active.into_values() {
+        let (amount, unconverted) =
+            rates.sum_in(&[(budget.currency.clone(), budget.amount)], &group.currency);
+        unconverted_currencies.extend(unconverted);
+        budgets.insert(budget.category_uid, (Some(amount), amount));
+    }
+    for budget in planned {
+        let entry = budgets
+            .entry(budget.category_uid)
+            .or_insert((None, budget.amount));
+        entry.1 = budget.amount;
+    }
+
+    let mut simulated = Vec::with_capacity(budgets.len());
+    for (category_uid, (current_budget, budget)) in budgets {
+        let mut spending = Vec::with_capacity(past.len());
+        for period in &past {
+            let totals = ExpenseEntryRepo::sum_by_category_in_range(
+                tx,
+                group.uid,
+                category_uid,
+                period.start_utc(),
+                period.end_utc(),
+            )
+            .await?;
+            let (spent, unconverted) = rates.sum_in(&totals, &group.currency);
+            unconverted_currencies.extend(unconverted);
+            spending.push((*period, spent));
+        }
+        simulated.push(SimulatedCategory::replay(
+            category_uid,
+            categories.get(&category_uid).cloned().unwrap_or_default(),
+            current_budget,
+            budget,
+            spending,
+        ));
+    }
+    simulated.sort_by(|a, b| {
+        b.periods_over
+            .cmp(&a.periods_over)
+            .then_with(|| a.category_name.cmp(&b.category_name))
+    });
+    unconverted_currencies.sort();
+    unconverted_currencies.dedup();
+
+    Ok(BudgetSimulation {
+        currency: group.currency.clone(),
+        periods: past.len(),
+        total_budget: simulated.iter().map(|category| category.budget).sum(),
+        categories: simulated,
+        unconverted_currencies,
+    })
+}
+
+/*
+ Rejects negative amounts, a category planned twice and categories of other groups. Returns
+ the names of the group's categories.
+*/
+async fn check_planned_budgets(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    group_uid: Uuid,
+    planned: &[PlannedBudget],
+) -> Result<HashMap<Uuid, String>, AppError> {
+    let categories: HashMap<Uuid, String> = CategoryRepo::list_by_group(tx, group_uid)
+        .await?
+        .into_iter()
+        .map(|category| (category.uid, category.name))
+        .collect();
+    let mut seen = HashSet::new();
+    for budget in planned {
+        if budget.amount < Decimal::ZERO {
+            return Err(AppError::BadRequest(
+                "Budget amounts must not be negative".to_string(),
+            ));
+        }
+        if !categories.contains_key(&budget.category_uid) {
+            return Err(AppError::BadRequest(
+                "Category does not belong to the group".to_string(),
+            ));
+        }
+        if !seen.insert(budget.category_uid) {
+            return Err(AppError::BadRequest(
+                "A category can only be planned once".to_string(),
+            ));
+        }
+    }
+    Ok(categories)
+}
+
+fn parse_name(name: &str) -> Result<String, AppError> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > MAX_PLAN_NAME_LENGTH {
+        return Err(AppError::BadRequest(format!(
+            "name must be between 1 and {} characters",
+            MAX_PLAN_NAME_LENGTH
+        )));
+    }
+    Ok(name.to_string())
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct SimulateBudgetPlanPayload {
+    /// Budgets to try, in the group's currency. Categories left out keep their current budget
+    pub budgets: Vec<PlannedBudget>,
+    /// Past cycles to replay, defaults to 6
+    #[validate(range(min = 1, max = 24))]
+    pub periods: Option<u32>,
+}
+
+// How past cycles would have fared with the planned budgets, before committing to them
+#[utoipa::path(post, path = "/groups/{group_uid}/budget-plans/simulate", params(("group_uid" = Uuid, Path)), request_body = SimulateBudgetPlanPayload, responses((status = 200, body = BudgetSimulation)), tag = "Budgets", operation_id = "simulateBudgetPlan", security(("bearerAuth" = [])))]
+pub async fn simulate(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(group_uid): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<SimulateBudgetPlanPayload>,
+) -> Result<Json<BudgetSimulation>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for simulating budget plan")
+    })?;
+    group_guard(&auth, group_uid, &mut tx, &state.membership_cache).await?;
+    let group = ExpenseGroupRepo::get(&mut tx, group_uid).await?;
+    let res = replay_budgets(
+        &mut tx,
+        &group,
+        &payload.budgets,
+        payload.periods.unwrap_or(DEFAULT_SIMULATED_PERIODS),
+    )
+    .await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for simulating budget plan")
+    })?;
+    Ok(Json(res))
+}
+
+#[utoipa::path(get, path = "/groups/{group_uid}/budget-plans", params(("group_uid" = Uuid, Path)), responses((status = 200, body = [BudgetPlan])), tag = "Budgets", operation_id = "listBudgetPlans", security(("bearerAuth" = [])))]
+pub async fn list(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(group_uid): Path<Uuid>,
+) -> Result<Json<Vec<BudgetPlan>>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for listing budget plans")
+    })?;
+    group_guard(&auth, group_uid, &mut tx, &state.membership_cache).await?;
+    let res = BudgetPlanRepo::list_by_group(&mut tx, group_uid).await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for listing budget plans")
+    })?;
+    Ok(Json(res))
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct CreateBudgetPlanPayload {
+    /// Unique within the group, e.g. "Tighter groceries"
+    pub name: String,
+    /// In the group's currency
+    pub budgets: Vec<PlannedBudget>,
+}
+
+#[utoipa::path(post, path = "/groups/{group_uid}/budget-plans", params(("group_uid" = Uuid, Path)), request_body = CreateBudgetPlanPayload, responses((status = 200, body = BudgetPlan)), tag = "Budgets", operation_id = "createBudgetPlan", security(("bearerAuth" = [])))]
+pub async fn create(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(group_uid): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<CreateBudgetPlanPayload>,
+) -> Result<Json<BudgetPlan>, AppError> {
+    let name = parse_name(&payload.name)?;
+    if payload.budgets.is_empty() {
+        return Err(AppError::BadRequest(
+            "A plan needs at least one budget".to_string(),
+        ));
+    }
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for creating budget plan")
+    })?;
+    group_guard(&auth, group_uid, &mut tx, &state.membership_cache).await?;
+    check_planned_budgets(&mut tx, group_uid, &payload.budgets).await?;
+    let created = BudgetPlanRepo::create(
+        &mut tx,
+        CreateBudgetPlanDbPayload {
+            group_uid,
+            name,
+            budgets: payload.budgets,
+        },
+    )
+    .await?;
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::BudgetPlan,
+        group_uid,
+        created.uid,
+        AuditChange::create(&created),
+    )
+    .await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for creating budget plan")
+    })?;
+    Ok(Json(created))
+}
+
+#[utoipa::path(get, path = "/budget-plans/{uid}", params(("uid" = Uuid, Path)), responses((status = 200, body = BudgetPlan)), tag = "Budgets", operation_id = "getBudgetPlan", security(("bearerAuth" = [])))]
+pub async fn get(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(uid): Path<Uuid>,
+) -> Result<Json<BudgetPlan>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for getting budget plan")
+    })?;
+    let plan = BudgetPlanRepo::get(&mut tx, uid).await?;
+    group_guard(&auth, plan.group_uid, &mut tx, &state.membership_cache).await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for getting budget plan")
+    })?;
+    Ok(Json(plan))
+}
+
+// Budgets the plan was applied as are kept
+#[utoipa::path(delete, path = "/budget-plans/{uid}", params(("uid" = Uuid, Path)), responses((status = 200, body = DeleteResponse)), tag = "Budgets", operation_id = "deleteBudgetPlan", security(("bearerAuth" = [])))]
+pub async fn delete_(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(uid): Path<Uuid>,
+) -> Result<Json<DeleteResponse>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for deleting budget plan")
+    })?;
+    let prev_rec = BudgetPlanRepo::get(&mut tx, uid).await?;
+    group_role_guard(
+        &auth,
+        prev_rec.group_uid,
+        &mut tx,
+        &state.membership_cache,
+        GroupRole::Admin,
+    )
+    .await?;
+    BudgetPlanRepo::delete(&mut tx, uid).await?;
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::BudgetPlan,
+        prev_rec.group_uid,
+        uid,
+        AuditChange::delete(&prev_rec),
+    )
+    .await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for deleting budget plan")
+    })?;
+    Ok(Json(DeleteResponse { success: true }))
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AppliedBudgetPlan {
+    /// The cycle the budgets are pinned to, as [start, end)
+    pub period_start: NaiveDate,
+    pub period_end: NaiveDate,
+    pub budgets: Vec<Budget>,
+}
+
+/*
+ Pins the plan's budgets to the group's next cycle, in the group's currency. Budgets already
+ pinned to that cycle are updated, the recurring budgets stay as they are and apply again
+ the cycle after. New budgets keep the hard limit of the category's current budget.
+*/
+#[utoipa::path(post, path = "/budget-plans/{uid}/apply", params(("uid" = Uuid, Path)), responses((status = 200, body = AppliedBudgetPlan)), tag = "Budgets", operation_id = "applyBudgetPlan", security(("bearerAuth" = [])))]
+pub async fn apply(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(uid): Path<Uuid>,
+) -> Result<Json<AppliedBudgetPlan>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for applying budget plan")
+    })?;
+    let plan = BudgetPlanRepo::get(&mut tx, uid).await?;
+    group_role_guard(
+        &auth,
+        plan.group_uid,
+        &mut tx,
+        &state.membership_cache,
+        GroupRole::Admin,
+    )
+    .await?;
+    let group = ExpenseGroupRepo::get(&mut tx, plan.group_uid).await?;
+    let today = Utc::now().date_naive();
+    let next = BillingPeriod::containing(today, group.start_over_date).next();
+    let (year, month) = (next.start.year(), next.start.month() as i32);
+
+    let existing = BudgetRepo::list_by_group(&mut tx, group.uid).await?;
+    let week_start = GroupSettingsRepo::get(&mut tx, group.uid).await?.week_start;
+    let active =
+        Budget::active_by_category(existing.clone(), group.start_over_date, week_start, today);
+    let subscription = SubscriptionRepo::get_by_user(&mut tx, group.owner).await?;
+    let actor = AuditActor::from_auth(&auth);
+
+    let mut budgets = Vec::with_capacity(plan.budgets.len());
+    for planned in plan.budgets {
+        let pinned = existing.iter().find(|budget| {
+            budget.category_uid == planned.category_uid
+                && budget.period_year == Some(year)
+                && budget.period_month == Some(month)
+        });
+        let (budget, change) = match pinned {
+            Some(prev_rec) => {
+                let updated = BudgetRepo::update(
+                    &mut tx,
+                    prev_rec.uid,
+                    UpdateBudgetDbPayload {
+                        amount: Some(planned.amount),
+                        currency: Some(group.currency.clone()),
+                        period_type: None,
+                        period_year: None,
+                        period_month: None,
+                        range_start: None,
+                        range_end: None,
+                        hard_limit: None,
+                    },
+                )
+                .await?;
+                let change = AuditChange::update(prev_rec, &updated);
+                (updated, change)
+            }
+            None => {
+                let current_budgets = BudgetRepo::count_by_group(&mut tx, group.uid).await?;
+                check_tier_limit(&subscription, "budgets_per_group", current_budgets as i32)?;
+                let created = BudgetRepo::create(
+                    &mut tx,
+                    CreateBudgetDbPayload {
+                        group_uid: group.uid,
+                        category_uid: planned.category_uid,
+                        amount: planned.amount,
+                        currency: Some(group.currency.clone()),
+                        period_type: BudgetPeriodType::Monthly,
+                        period_year: Some(year),
+                        period_month: Some(month),
+                        range_start: None,
+                        range_end: None,
+                        hard_limit: active
+                            .get(&planned.category_uid)
+                            .is_some_and(|budget| budget.hard_limit),
+                    },
+                )
+                .await?;
+                let change = AuditChange::create(&created);
+                (created, change)
+            }
+        };
+        AuditRepo::record(
+            &mut tx,
+            &actor,
+            AuditEntity::Budget,
+            group.uid,
+            budget.uid,
+            change,
+        )
+        .await?;
+        budgets.push(budget);
+    }
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for applying budget plan")
+    })?;
+    Ok(Json(AppliedBudgetPlan {
+        period_start: next.start,
+        period_end: next.end,
+        budgets,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_replay() {
+        let current = BillingPeriod::for_month(2025, 9, 1);
+        let spending = vec![
+            (current.nth_previous(3), dec!(900000)),
+            (current.nth_previous(2), dec!(1250000)),
+            (current.nth_previous(1), dec!(1000000)),
+        ];
+        let category = SimulatedCategory::replay(
+            Uuid::new_v4(),
+            "Makanan".to_string(),
+            Some(dec!(1500000)),
+            dec!(1000000),
+            spending,
+        );
+        assert_eq!(category.average_spent, dec!(1050000));
+        assert_eq!(category.periods_over, 1);
+        assert_eq!(
+            category.periods[0].period_start,
+            NaiveDate::from_ymd_opt(2025, 6, 1).unwrap()
+        );
+        assert_eq!(category.periods[0].difference, dec!(100000));
+        assert_eq!(category.periods[1].difference, dec!(-250000));
+        // Spending exactly the budget is not over it
+        assert_eq!(category.periods[2].difference, Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_replay_without_periods() {
+        let category = SimulatedCategory::replay(
+            Uuid::new_v4(),
+            "Makanan".to_string(),
+            None,
+            dec!(1000000),
+            Vec::new(),
+        );
+        assert_eq!(category.average_spent, Decimal::ZERO);
+        assert_eq!(category.periods_over, 0);
+    }
+}
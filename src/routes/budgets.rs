@@ -1,127 +1,495 @@
+use std::collections::HashMap;
+
 use axum::{
     Json,
     extract::{Extension, Path, State},
 };
-use serde::Deserialize;
+use chrono::{NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
 use uuid::Uuid;
+use validator::Validate;
 
 use crate::{
-    auth::{AuthContext, group_guard::group_guard},
+    auth::{
+        AuthContext,
+        group_guard::{group_guard, group_role_guard},
+    },
     error::AppError,
-    middleware::tier::check_tier_limit,
+    middleware::{
+        tier::check_tier_limit,
+        validated_json::{ValidatedJson, non_negative_amount},
+    },
     repos::{
-        budget::{Budget, BudgetRepo, CreateBudgetDbPayload, UpdateBudgetDbPayload},
+        audit_log::{AuditActor, AuditChange, AuditEntity, AuditRepo},
+        budget::{
+            Budget, BudgetPeriodType, BudgetRepo, CreateBudgetDbPayload, SpendProjection,
+            UpdateBudgetDbPayload,
+        },
+        budget_alert::{
+            BudgetAlertRepo, BudgetAlertSettings, HardLimitAction,
+            UpsertBudgetAlertSettingsDbPayload,
+        },
+        category::CategoryRepo,
+        exchange_rate::ExchangeRateRepo,
+        expense_entry::ExpenseEntryRepo,
+        expense_group::ExpenseGroupRepo,
+        expense_group_member::GroupRole,
+        group_settings::GroupSettingsRepo,
         subscription::SubscriptionRepo,
     },
+    routes::currencies::parse_currency,
     types::AppState,
+    utils::currency::RateTable,
 };
 
-pub fn router() -> axum::Router<AppState> {
-    axum::Router::new()
-        .route("/budgets", axum::routing::post(create))
-        .route("/budgets/group/{group_uid}", axum::routing::get(list))
-        .route(
-            "/budgets/{uid}",
-            axum::routing::get(get).put(update).delete(delete_),
+pub fn router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(list, create))
+        .routes(routes!(analytics))
+        .routes(routes!(get, update, delete_))
+        .routes(routes!(get_alert_settings, update_alert_settings))
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BudgetWithSpend {
+    #[serde(flatten)]
+    pub budget: Budget,
+    /// Spent in the budget's category during the period, in the budget's currency
+    pub spent: Decimal,
+    /// Negative once the budget is overspent
+    pub remaining: Decimal,
+    pub percentage_used: f64,
+    pub period_start: NaiveDate,
+    /// Exclusive
+    pub period_end: NaiveDate,
+    /// Currencies without an exchange rate to the budget's currency, counted unconverted
+    pub unconverted_currencies: Vec<String>,
+}
+
+async fn with_spend(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    budget: Budget,
+    start_over_date: i16,
+    week_start: i16,
+    rates: &RateTable,
+) -> Result<BudgetWithSpend, AppError> {
+    let (period_start, period_end) =
+        budget.period_range(start_over_date, week_start, Utc::now().date_naive());
+    let totals = ExpenseEntryRepo::sum_by_category_in_range(
+        tx,
+        budget.group_uid,
+        budget.category_uid,
+        period_start.and_hms_opt(0, 0, 0).unwrap().and_utc(),
+        period_end.and_hms_opt(0, 0, 0).unwrap().and_utc(),
+    )
+    .await?;
+    let (spent, unconverted_currencies) = rates.sum_in(&totals, &budget.currency);
+    Ok(BudgetWithSpend {
+        percentage_used: budget.percentage_used(spent),
+        remaining: budget.amount - spent,
+        budget,
+        spent,
+        period_start,
+        period_end,
+        unconverted_currencies,
+    })
+}
+
+fn parse_period_type(period_type: &str) -> Result<BudgetPeriodType, AppError> {
+    BudgetPeriodType::parse(period_type).ok_or_else(|| {
+        AppError::BadRequest(
+            "period_type must be 'weekly', 'monthly', 'quarterly', 'yearly' or 'custom'"
+                .to_string(),
         )
+    })
+}
+
+// The pin only applies to monthly budgets, the range only and always to custom ones
+fn check_period(
+    period_type: BudgetPeriodType,
+    pinned: bool,
+    range_start: Option<NaiveDate>,
+    range_end: Option<NaiveDate>,
+) -> Result<(), AppError> {
+    if pinned && period_type != BudgetPeriodType::Monthly {
+        return Err(AppError::BadRequest(
+            "period_year and period_month only apply to monthly budgets".to_string(),
+        ));
+    }
+    match (period_type, range_start, range_end) {
+        (BudgetPeriodType::Custom, Some(start), Some(end)) if end > start => Ok(()),
+        (BudgetPeriodType::Custom, ..) => Err(AppError::BadRequest(
+            "custom budgets need a range_end after range_start".to_string(),
+        )),
+        (_, None, None) => Ok(()),
+        _ => Err(AppError::BadRequest(
+            "range_start and range_end only apply to custom budgets".to_string(),
+        )),
+    }
 }
 
-#[utoipa::path(get, path = "/budgets/group/{group_uid}", params(("group_uid" = Uuid, Path)), responses((status = 200, body = [Budget])), tag = "Budgets", operation_id = "listBudgets", security(("bearerAuth" = [])))]
+#[utoipa::path(get, path = "/groups/{group_uid}/budgets", params(("group_uid" = Uuid, Path)), responses((status = 200, body = [BudgetWithSpend])), tag = "Budgets", operation_id = "listBudgets", security(("bearerAuth" = [])))]
 pub async fn list(
     State(state): State<AppState>,
     Path(group_uid): Path<Uuid>,
     Extension(auth): Extension<AuthContext>,
-) -> Result<Json<Vec<Budget>>, AppError> {
-    group_guard(&auth, group_uid, &state.db_pool).await?;
-    let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "Failed to begin transaction"))?;
-    let res = BudgetRepo::list_by_group(&mut tx, group_uid).await?;
-    tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "Failed to commit transaction"))?;
+) -> Result<Json<Vec<BudgetWithSpend>>, AppError> {
+    let mut tx =
+        state.db_pool.begin().await.map_err(|e| {
+            AppError::from_sqlx_error(e, "beginning transaction for listing budgets")
+        })?;
+    group_guard(&auth, group_uid, &mut tx, &state.membership_cache).await?;
+    let group = ExpenseGroupRepo::get(&mut tx, group_uid).await?;
+    let settings = GroupSettingsRepo::get(&mut tx, group_uid).await?;
+    let rates = ExchangeRateRepo::rate_table(&mut tx).await?;
+    let budgets = BudgetRepo::list_by_group(&mut tx, group_uid).await?;
+    let mut res = Vec::with_capacity(budgets.len());
+    for budget in budgets {
+        res.push(
+            with_spend(
+                &mut tx,
+                budget,
+                group.start_over_date,
+                settings.week_start,
+                &rates,
+            )
+            .await?,
+        );
+    }
+    tx.commit()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "committing transaction for listing budgets"))?;
     Ok(Json(res))
 }
 
-#[utoipa::path(get, path = "/budgets/{uid}", params(("uid" = Uuid, Path)), responses((status = 200, body = Budget)), tag = "Budgets", operation_id = "getBudget", security(("bearerAuth" = [])))]
+#[utoipa::path(get, path = "/budgets/{uid}", params(("uid" = Uuid, Path)), responses((status = 200, body = BudgetWithSpend)), tag = "Budgets", operation_id = "getBudget", security(("bearerAuth" = [])))]
 pub async fn get(
     State(state): State<AppState>,
     Path(uid): Path<Uuid>,
     Extension(auth): Extension<AuthContext>,
-) -> Result<Json<Budget>, AppError> {
-    let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for getting budget"))?;
-    let res = BudgetRepo::get(&mut tx, uid).await?;
-    group_guard(&auth, res.group_uid, &state.db_pool).await?;
-    tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for getting budget"))?;
+) -> Result<Json<BudgetWithSpend>, AppError> {
+    let mut tx =
+        state.db_pool.begin().await.map_err(|e| {
+            AppError::from_sqlx_error(e, "beginning transaction for getting budget")
+        })?;
+    let budget = BudgetRepo::get(&mut tx, uid).await?;
+    group_guard(&auth, budget.group_uid, &mut tx, &state.membership_cache).await?;
+    let group = ExpenseGroupRepo::get(&mut tx, budget.group_uid).await?;
+    let settings = GroupSettingsRepo::get(&mut tx, budget.group_uid).await?;
+    let rates = ExchangeRateRepo::rate_table(&mut tx).await?;
+    let res = with_spend(
+        &mut tx,
+        budget,
+        group.start_over_date,
+        settings.week_start,
+        &rates,
+    )
+    .await?;
+    tx.commit()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "committing transaction for getting budget"))?;
     Ok(Json(res))
 }
 
-#[derive(Deserialize, ToSchema)]
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BudgetAnalytics {
+    pub budget_uid: Uuid,
+    pub category_uid: Uuid,
+    pub category_name: String,
+    pub currency: String,
+    pub amount: Decimal,
+    pub spent: Decimal,
+    pub percentage_used: f64,
+    pub period_start: NaiveDate,
+    /// Exclusive
+    pub period_end: NaiveDate,
+    pub days_elapsed: i64,
+    pub days_in_period: i64,
+    /// Average spent per day so far in the period
+    pub daily_burn_rate: Decimal,
+    /// Spent by the end of the period if the burn rate holds
+    pub projected_total: Decimal,
+    pub projected_percentage: f64,
+    /// `on_track`, `at_risk` (projected to go over) or `over_budget`
+    pub status: String,
+}
+
+/*
+ Budget vs actual for the period each category's budget is in right now, one row per category.
+ A budget pinned to the current cycle or a custom range running today takes precedence over
+ the recurring one.
+*/
+#[utoipa::path(get, path = "/groups/{group_uid}/budgets/analytics", params(("group_uid" = Uuid, Path)), responses((status = 200, body = [BudgetAnalytics])), tag = "Budgets", operation_id = "getBudgetAnalytics", security(("bearerAuth" = [])))]
+pub async fn analytics(
+    State(state): State<AppState>,
+    Path(group_uid): Path<Uuid>,
+    Extension(auth): Extension<AuthContext>,
+) -> Result<Json<Vec<BudgetAnalytics>>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for getting budget analytics")
+    })?;
+    group_guard(&auth, group_uid, &mut tx, &state.membership_cache).await?;
+    let group = ExpenseGroupRepo::get(&mut tx, group_uid).await?;
+    let settings = GroupSettingsRepo::get(&mut tx, group_uid).await?;
+    let rates = ExchangeRateRepo::rate_table(&mut tx).await?;
+    let categories: HashMap<Uuid, String> = CategoryRepo::list_by_group(&mut tx, group_uid)
+        .await?
+        .into_iter()
+        .map(|category| (category.uid, category.name))
+        .collect();
+
+    let today = Utc::now().date_naive();
+    let budgets = BudgetRepo::list_by_group(&mut tx, group_uid).await?;
+    let active =
+        Budget::active_by_category(budgets, group.start_over_date, settings.week_start, today);
+
+    let mut res = Vec::with_capacity(active.len());
+    for budget in active.into_values() {
+        let category_name = categories
+            .get(&budget.category_uid)
+            .cloned()
+            .unwrap_or_default();
+        let tracked = with_spend(
+            &mut tx,
+            budget,
+            group.start_over_date,
+            settings.week_start,
+            &rates,
+        )
+        .await?;
+        let projection = SpendProjection::new(
+            tracked.spent,
+            tracked.period_start,
+            tracked.period_end,
+            today,
+        );
+        let projected_percentage = tracked.budget.percentage_used(projection.projected_total);
+        let status = if tracked.spent > tracked.budget.amount {
+            "over_budget"
+        } else if projection.projected_total > tracked.budget.amount {
+            "at_risk"
+        } else {
+            "on_track"
+        };
+        res.push(BudgetAnalytics {
+            budget_uid: tracked.budget.uid,
+            category_uid: tracked.budget.category_uid,
+            category_name,
+            currency: tracked.budget.currency,
+            amount: tracked.budget.amount,
+            spent: tracked.spent,
+            percentage_used: tracked.percentage_used,
+            period_start: tracked.period_start,
+            period_end: tracked.period_end,
+            days_elapsed: projection.days_elapsed,
+            days_in_period: projection.days_in_period,
+            daily_burn_rate: projection.daily_burn_rate,
+            projected_total: projection.projected_total,
+            projected_percentage,
+            status: status.to_string(),
+        });
+    }
+    // Categories heading furthest over budget first
+    res.sort_by(|a, b| b.projected_percentage.total_cmp(&a.projected_percentage));
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for getting budget analytics")
+    })?;
+    Ok(Json(res))
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
 pub struct CreateBudgetPayload {
-    pub group_uid: Uuid,
     pub category_uid: Uuid,
-    pub amount: f64,
+    #[validate(custom(function = "non_negative_amount"))]
+    pub amount: Decimal,
+    /// ISO 4217 code, defaults to the group's currency
+    pub currency: Option<String>,
+    /// `weekly`, `monthly` (default), `quarterly`, `yearly` or `custom`
+    pub period_type: Option<String>,
+    /// Pin a monthly budget to a single cycle, otherwise it applies to every cycle
     pub period_year: Option<i32>,
+    #[validate(range(min = 1, max = 12))]
     pub period_month: Option<i32>,
+    /// Range of a `custom` budget, required for it
+    pub range_start: Option<NaiveDate>,
+    /// Exclusive
+    pub range_end: Option<NaiveDate>,
+    /// Flag or hold back expenses that take spending past the amount, see `hard_limit_action`
+    /// of the budget alert settings
+    pub hard_limit: Option<bool>,
 }
 
-#[utoipa::path(post, path = "/budgets", request_body = CreateBudgetPayload, responses((status = 200, body = Budget)), tag = "Budgets", operation_id = "createBudget", security(("bearerAuth" = [])))]
+#[utoipa::path(post, path = "/groups/{group_uid}/budgets", params(("group_uid" = Uuid, Path)), request_body = CreateBudgetPayload, responses((status = 200, body = BudgetWithSpend)), tag = "Budgets", operation_id = "createBudget", security(("bearerAuth" = [])))]
 pub async fn create(
     State(state): State<AppState>,
     Extension(auth): Extension<AuthContext>,
-    Json(payload): Json<CreateBudgetPayload>,
-) -> Result<Json<Budget>, AppError> {
-    group_guard(&auth, payload.group_uid, &state.db_pool).await?;
-    let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for creating budget"))?;
+    Path(group_uid): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<CreateBudgetPayload>,
+) -> Result<Json<BudgetWithSpend>, AppError> {
+    let mut tx =
+        state.db_pool.begin().await.map_err(|e| {
+            AppError::from_sqlx_error(e, "beginning transaction for creating budget")
+        })?;
+    group_guard(&auth, group_uid, &mut tx, &state.membership_cache).await?;
+    let currency = parse_currency(payload.currency.as_deref())?;
+    if payload.period_year.is_some() != payload.period_month.is_some() {
+        return Err(AppError::BadRequest(
+            "period_year and period_month must be set together".to_string(),
+        ));
+    }
+    let period_type = match payload.period_type.as_deref() {
+        Some(period_type) => parse_period_type(period_type)?,
+        None => BudgetPeriodType::Monthly,
+    };
+    check_period(
+        period_type,
+        payload.period_month.is_some(),
+        payload.range_start,
+        payload.range_end,
+    )?;
 
-    // Get user's subscription
-    let subscription = SubscriptionRepo::get_by_user(&mut tx, auth.user_uid).await?;
+    let group = ExpenseGroupRepo::get(&mut tx, group_uid).await?;
+    let category = CategoryRepo::get(&mut tx, payload.category_uid).await?;
+    if category.group_uid != group_uid {
+        return Err(AppError::BadRequest(
+            "Category does not belong to the group".to_string(),
+        ));
+    }
 
-    // Check budget limit per group
-    let current_budgets = BudgetRepo::count_by_group(&mut tx, payload.group_uid).await?;
+    // Budget limit per group follows the owner's plan
+    let subscription = SubscriptionRepo::get_by_user(&mut tx, group.owner).await?;
+    let current_budgets = BudgetRepo::count_by_group(&mut tx, group_uid).await?;
     check_tier_limit(&subscription, "budgets_per_group", current_budgets as i32)?;
 
     let created = BudgetRepo::create(
         &mut tx,
         CreateBudgetDbPayload {
-            group_uid: payload.group_uid,
+            group_uid,
             category_uid: payload.category_uid,
             amount: payload.amount,
+            currency,
+            period_type,
             period_year: payload.period_year,
             period_month: payload.period_month,
+            range_start: payload.range_start,
+            range_end: payload.range_end,
+            hard_limit: payload.hard_limit.unwrap_or(false),
         },
     )
     .await?;
-    tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for creating budget"))?;
-    Ok(Json(created))
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::Budget,
+        group_uid,
+        created.uid,
+        AuditChange::create(&created),
+    )
+    .await?;
+    let settings = GroupSettingsRepo::get(&mut tx, group_uid).await?;
+    let rates = ExchangeRateRepo::rate_table(&mut tx).await?;
+    let res = with_spend(
+        &mut tx,
+        created,
+        group.start_over_date,
+        settings.week_start,
+        &rates,
+    )
+    .await?;
+    tx.commit()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "committing transaction for creating budget"))?;
+    Ok(Json(res))
 }
 
-#[derive(Deserialize, ToSchema)]
+#[derive(Deserialize, ToSchema, Validate)]
 pub struct UpdateBudgetPayload {
-    pub amount: Option<f64>,
+    #[validate(custom(function = "non_negative_amount"))]
+    pub amount: Option<Decimal>,
+    pub currency: Option<String>,
+    /// Switching away from `monthly` drops the pin, away from `custom` the range
+    pub period_type: Option<String>,
     pub period_year: Option<i32>,
+    #[validate(range(min = 1, max = 12))]
     pub period_month: Option<i32>,
+    pub range_start: Option<NaiveDate>,
+    pub range_end: Option<NaiveDate>,
+    pub hard_limit: Option<bool>,
 }
 
-#[utoipa::path(put, path = "/budgets/{uid}", params(("uid" = Uuid, Path)), request_body = UpdateBudgetPayload, responses((status = 200, body = Budget)), tag = "Budgets", operation_id = "updateBudget", security(("bearerAuth" = [])))]
+#[utoipa::path(put, path = "/budgets/{uid}", params(("uid" = Uuid, Path)), request_body = UpdateBudgetPayload, responses((status = 200, body = BudgetWithSpend)), tag = "Budgets", operation_id = "updateBudget", security(("bearerAuth" = [])))]
 pub async fn update(
     State(state): State<AppState>,
     Extension(auth): Extension<AuthContext>,
     Path(uid): Path<Uuid>,
-    Json(payload): Json<UpdateBudgetPayload>,
-) -> Result<Json<Budget>, AppError> {
-    let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for updating budget"))?;
+    ValidatedJson(payload): ValidatedJson<UpdateBudgetPayload>,
+) -> Result<Json<BudgetWithSpend>, AppError> {
+    let currency = parse_currency(payload.currency.as_deref())?;
+    let mut tx =
+        state.db_pool.begin().await.map_err(|e| {
+            AppError::from_sqlx_error(e, "beginning transaction for updating budget")
+        })?;
     let prev_rec = BudgetRepo::get(&mut tx, uid).await?;
-    group_guard(&auth, prev_rec.group_uid, &state.db_pool).await?;
+    group_guard(&auth, prev_rec.group_uid, &mut tx, &state.membership_cache).await?;
+    let period_type = match payload.period_type.as_deref() {
+        Some(period_type) => parse_period_type(period_type)?,
+        None => prev_rec.period_type(),
+    };
+    let (range_start, range_end) = match period_type {
+        BudgetPeriodType::Custom => (
+            payload.range_start.or(prev_rec.range_start),
+            payload.range_end.or(prev_rec.range_end),
+        ),
+        _ => (payload.range_start, payload.range_end),
+    };
+    check_period(
+        period_type,
+        payload.period_year.is_some() || payload.period_month.is_some(),
+        range_start,
+        range_end,
+    )?;
     let updated = BudgetRepo::update(
         &mut tx,
         uid,
         UpdateBudgetDbPayload {
             amount: payload.amount,
+            currency,
+            period_type: Some(period_type),
             period_year: payload.period_year,
             period_month: payload.period_month,
+            range_start,
+            range_end,
+            hard_limit: payload.hard_limit,
         },
     )
     .await?;
-    tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for updating budget"))?;
-    Ok(Json(updated))
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::Budget,
+        updated.group_uid,
+        updated.uid,
+        AuditChange::update(&prev_rec, &updated),
+    )
+    .await?;
+    let group = ExpenseGroupRepo::get(&mut tx, updated.group_uid).await?;
+    let settings = GroupSettingsRepo::get(&mut tx, updated.group_uid).await?;
+    let rates = ExchangeRateRepo::rate_table(&mut tx).await?;
+    let res = with_spend(
+        &mut tx,
+        updated,
+        group.start_over_date,
+        settings.week_start,
+        &rates,
+    )
+    .await?;
+    tx.commit()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "committing transaction for updating budget"))?;
+    Ok(Json(res))
 }
 
 #[utoipa::path(delete, path = "/budgets/{uid}", params(("uid" = Uuid, Path)), responses((status = 200, description = "Deleted")), tag = "Budgets", operation_id = "deleteBudget", security(("bearerAuth" = [])))]
@@ -130,10 +498,180 @@ pub async fn delete_(
     Path(uid): Path<Uuid>,
     Extension(auth): Extension<AuthContext>,
 ) -> Result<(), AppError> {
-    let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for deleting budget"))?;
+    let mut tx =
+        state.db_pool.begin().await.map_err(|e| {
+            AppError::from_sqlx_error(e, "beginning transaction for deleting budget")
+        })?;
     let budget = BudgetRepo::get(&mut tx, uid).await?;
-    group_guard(&auth, budget.group_uid, &state.db_pool).await?;
+    group_role_guard(
+        &auth,
+        budget.group_uid,
+        &mut tx,
+        &state.membership_cache,
+        GroupRole::Admin,
+    )
+    .await?;
     BudgetRepo::delete(&mut tx, uid).await?;
-    tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for deleting budget"))?;
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::Budget,
+        budget.group_uid,
+        uid,
+        AuditChange::delete(&budget),
+    )
+    .await?;
+    tx.commit()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "committing transaction for deleting budget"))?;
     Ok(())
 }
+
+#[utoipa::path(get, path = "/groups/{group_uid}/budget-alert-settings", params(("group_uid" = Uuid, Path)), responses((status = 200, body = BudgetAlertSettings)), tag = "Budgets", operation_id = "getBudgetAlertSettings", security(("bearerAuth" = [])))]
+pub async fn get_alert_settings(
+    State(state): State<AppState>,
+    Path(group_uid): Path<Uuid>,
+    Extension(auth): Extension<AuthContext>,
+) -> Result<Json<BudgetAlertSettings>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for getting budget alert settings")
+    })?;
+    group_guard(&auth, group_uid, &mut tx, &state.membership_cache).await?;
+    let res = BudgetAlertRepo::get_settings(&mut tx, group_uid).await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(
+            e,
+            "committing transaction for getting budget alert settings",
+        )
+    })?;
+    Ok(Json(res))
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema, Validate)]
+pub struct UpdateBudgetAlertSettingsPayload {
+    pub enabled: Option<bool>,
+    /// Percentage of the budget (1-99) that triggers the early warning
+    pub warning_percent: Option<i16>,
+    /// Also alert once spending goes over 100%
+    pub notify_exceeded: Option<bool>,
+    /// For budgets with `hard_limit`: `warn` records expenses going over with a warning,
+    /// `confirm` holds them back until confirmed
+    pub hard_limit_action: Option<String>,
+}
+
+#[utoipa::path(put, path = "/groups/{group_uid}/budget-alert-settings", params(("group_uid" = Uuid, Path)), request_body = UpdateBudgetAlertSettingsPayload, responses((status = 200, body = BudgetAlertSettings)), tag = "Budgets", operation_id = "updateBudgetAlertSettings", security(("bearerAuth" = [])))]
+pub async fn update_alert_settings(
+    State(state): State<AppState>,
+    Path(group_uid): Path<Uuid>,
+    Extension(auth): Extension<AuthContext>,
+    ValidatedJson(payload): ValidatedJson<UpdateBudgetAlertSettingsPayload>,
+) -> Result<Json<BudgetAlertSettings>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(
+            e,
+            "beginning transaction for updating budget alert settings",
+        )
+    })?;
+    group_role_guard(
+        &auth,
+        group_uid,
+        &mut tx,
+        &state.membership_cache,
+        GroupRole::Admin,
+    )
+    .await?;
+    let current = BudgetAlertRepo::get_settings(&mut tx, group_uid).await?;
+    let updated = BudgetAlertRepo::upsert_settings(
+        &mut tx,
+        group_uid,
+        merge_alert_settings(&current, &payload)?,
+    )
+    .await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(
+            e,
+            "committing transaction for updating budget alert settings",
+        )
+    })?;
+    Ok(Json(updated))
+}
+
+// Validates `payload` and fills what it leaves out from `current`
+pub(crate) fn merge_alert_settings(
+    current: &BudgetAlertSettings,
+    payload: &UpdateBudgetAlertSettingsPayload,
+) -> Result<UpsertBudgetAlertSettingsDbPayload, AppError> {
+    if payload
+        .warning_percent
+        .is_some_and(|percent| !(1..=99).contains(&percent))
+    {
+        return Err(AppError::BadRequest(
+            "warning_percent must be between 1 and 99".to_string(),
+        ));
+    }
+    let hard_limit_action = match payload.hard_limit_action.as_deref() {
+        Some(action) => HardLimitAction::parse(action).ok_or_else(|| {
+            AppError::BadRequest("hard_limit_action must be 'warn' or 'confirm'".to_string())
+        })?,
+        None => current.hard_limit_action(),
+    };
+    Ok(UpsertBudgetAlertSettingsDbPayload {
+        enabled: payload.enabled.unwrap_or(current.enabled),
+        warning_percent: payload.warning_percent.unwrap_or(current.warning_percent),
+        notify_exceeded: payload.notify_exceeded.unwrap_or(current.notify_exceeded),
+        hard_limit_action,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(year, month, day)
+    }
+
+    #[test]
+    fn test_check_period() {
+        assert!(check_period(BudgetPeriodType::Monthly, true, None, None).is_ok());
+        assert!(check_period(BudgetPeriodType::Yearly, false, None, None).is_ok());
+        assert!(check_period(BudgetPeriodType::Yearly, true, None, None).is_err());
+        assert!(
+            check_period(
+                BudgetPeriodType::Custom,
+                false,
+                date(2025, 12, 1),
+                date(2026, 1, 1)
+            )
+            .is_ok()
+        );
+        assert!(check_period(BudgetPeriodType::Custom, false, date(2025, 12, 1), None).is_err());
+        assert!(
+            check_period(
+                BudgetPeriodType::Custom,
+                false,
+                date(2025, 12, 1),
+                date(2025, 12, 1)
+            )
+            .is_err()
+        );
+        assert!(
+            check_period(
+                BudgetPeriodType::Weekly,
+                false,
+                date(2025, 12, 1),
+                date(2026, 1, 1)
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_period_type() {
+        assert_eq!(
+            parse_period_type("Quarterly").unwrap(),
+            BudgetPeriodType::Quarterly
+        );
+        assert!(parse_period_type("daily").is_err());
+    }
+}
@@ -1,31 +1,32 @@
 use axum::{
     Json,
-    extract::{Extension, Path, State},
+    extract::{Extension, Path, Query, State},
 };
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
+use utoipa_axum::{router::OpenApiRouter, routes};
 use uuid::Uuid;
 use validator::Validate;
 
 use crate::{
-    auth::{group_guard::group_guard, AuthContext},
+    auth::{group_guard::{group_guard, group_role_guard}, AuthContext},
     error::AppError,
-    middleware::tier::check_tier_limit,
+    middleware::{tier::{check_tier_limit, check_tier_violation}, validated_json::{ValidatedJson, category_icon, hex_color}},
     repos::{
+        audit_log::{AuditActor, AuditChange, AuditEntity, AuditRepo},
         category::{Category, CategoryRepo, CreateCategoryDbPayload, UpdateCategoryDbPayload},
+        expense_group_member::GroupRole,
         subscription::SubscriptionRepo,
     },
     types::AppState,
 };
 
-pub fn router() -> axum::Router<AppState> {
-    axum::Router::new()
-        .route("/groups/{group_uid}/categories", axum::routing::get(list))
-        .route("/categories", axum::routing::post(create))
-        .route(
-            "/categories/{uid}",
-            axum::routing::get(get).put(update),
-        )
+pub fn router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(list))
+        .routes(routes!(create))
+        .routes(routes!(get, update, delete_))
 }
 
 #[utoipa::path(
@@ -42,8 +43,8 @@ pub async fn list(
     State(state): State<AppState>,
     Path(group_uid): Path<Uuid>,
 ) -> Result<Json<Vec<Category>>, AppError> {
-    group_guard(&auth, group_uid, &state.db_pool).await?;
     let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for listing categories"))?;
+    group_guard(&auth, group_uid, &mut tx, &state.membership_cache).await?;
     let res = CategoryRepo::list_by_group(&mut tx, group_uid).await?;
     tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for listing categories"))?;
     Ok(Json(res))
@@ -57,7 +58,7 @@ pub async fn get(
 ) -> Result<Json<Category>, AppError> {
     let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for getting category"))?;
     let prev_category = CategoryRepo::get(&mut tx, uid).await?;
-    group_guard(&auth, prev_category.group_uid, &state.db_pool).await?;
+    group_guard(&auth, prev_category.group_uid, &mut tx, &state.membership_cache).await?;
     let res = CategoryRepo::get(&mut tx, uid).await?;
     tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for getting category"))?;
     Ok(Json(res))
@@ -72,6 +73,14 @@ pub struct CreateCategoryPayload {
     pub description: Option<String>,
     #[validate(length(min = 1, max = 100))]
     pub alias: Option<String>,
+    /// Top-level category of the same group to nest under, its totals include this one's spending
+    pub parent_uid: Option<Uuid>,
+    /// An emoji or a short code like `:coffee:`, picked from the name when omitted
+    #[validate(custom(function = "category_icon"))]
+    pub icon: Option<String>,
+    /// `#rrggbb`, picked from the name when omitted
+    #[validate(custom(function = "hex_color"))]
+    pub color: Option<String>,
 }
 
 #[utoipa::path(
@@ -86,12 +95,10 @@ pub struct CreateCategoryPayload {
 pub async fn create(
     State(state): State<AppState>,
     Extension(auth): Extension<AuthContext>,
-    Json(payload): Json<CreateCategoryPayload>,
+    ValidatedJson(payload): ValidatedJson<CreateCategoryPayload>,
 ) -> Result<Json<Category>, AppError> {
-    payload.validate()?;
-    group_guard(&auth, payload.group_uid, &state.db_pool).await?;
-
     let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for creating category"))?;
+    group_guard(&auth, payload.group_uid, &mut tx, &state.membership_cache).await?;
 
     // Get user's subscription
     let subscription = SubscriptionRepo::get_by_user(&mut tx, auth.user_uid).await?;
@@ -99,6 +106,7 @@ pub async fn create(
     // Check category limit per group
     let current_categories = CategoryRepo::count_by_group(&mut tx, payload.group_uid).await?;
     check_tier_limit(&subscription, "categories_per_group", current_categories as i32)?;
+    check_tier_violation(&mut tx, payload.group_uid, "categories_per_group").await?;
 
     let created = CategoryRepo::create(
         &mut tx,
@@ -106,9 +114,21 @@ pub async fn create(
             group_uid: payload.group_uid,
             name: payload.name,
             description: payload.description,
+            parent_uid: payload.parent_uid,
+            icon: payload.icon,
+            color: payload.color,
         },
     )
     .await?;
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::Category,
+        created.group_uid,
+        created.uid,
+        AuditChange::create(&created),
+    )
+    .await?;
     tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for creating category"))?;
     Ok(Json(created))
 }
@@ -121,20 +141,31 @@ pub struct UpdateCategoryPayload {
     pub description: Option<String>,
     #[validate(length(min = 1, max = 100))]
     pub alias: Option<String>,
+    /// Moves the category under another top-level category of the same group
+    pub parent_uid: Option<Uuid>,
+    /// Moves the category back to the top level, `parent_uid` is ignored when set
+    #[serde(default)]
+    pub clear_parent: bool,
+    #[validate(custom(function = "category_icon"))]
+    pub icon: Option<String>,
+    #[validate(custom(function = "hex_color"))]
+    pub color: Option<String>,
+    /// The category's `updated_at` as last read. When it changed since, nothing is updated and
+    /// `409 CONCURRENT_MODIFICATION` returns the current category as `details.current`
+    pub expected_updated_at: Option<DateTime<Utc>>,
 }
 
-#[utoipa::path(put, path = "/categories/{uid}", params(("uid" = Uuid, Path)), request_body = UpdateCategoryPayload, responses((status = 200, body = Category)), tag = "Categories", operation_id = "updateCategory", security(("bearerAuth" = [])))]
+#[utoipa::path(put, path = "/categories/{uid}", params(("uid" = Uuid, Path)), request_body = UpdateCategoryPayload, responses((status = 200, body = Category), (status = 409, description = "Changed by someone else since `expected_updated_at`")), tag = "Categories", operation_id = "updateCategory", security(("bearerAuth" = [])))]
 pub async fn update(
     State(state): State<AppState>,
     Extension(auth): Extension<AuthContext>,
     Path(uid): Path<Uuid>,
-    Json(payload): Json<UpdateCategoryPayload>,
+    ValidatedJson(payload): ValidatedJson<UpdateCategoryPayload>,
 ) -> Result<Json<Category>, AppError> {
-    payload.validate()?;
     let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for updating category"))?;
     let prev_category = CategoryRepo::get(&mut tx, uid).await?;
 
-    group_guard(&auth, prev_category.group_uid, &state.db_pool).await?;
+    group_guard(&auth, prev_category.group_uid, &mut tx, &state.membership_cache).await?;
 
     let updated = CategoryRepo::update(
         &mut tx,
@@ -142,24 +173,82 @@ pub async fn update(
         UpdateCategoryDbPayload {
             name: payload.name,
             description: payload.description,
+            parent_uid: if payload.clear_parent {
+                Some(None)
+            } else {
+                payload.parent_uid.map(Some)
+            },
+            icon: payload.icon,
+            color: payload.color,
+            expected_updated_at: payload.expected_updated_at,
         },
     )
     .await?;
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::Category,
+        updated.group_uid,
+        updated.uid,
+        AuditChange::update(&prev_category, &updated),
+    )
+    .await?;
     tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for updating category"))?;
     Ok(Json(updated))
 }
 
-// TODO: Not to be used until we implement cascading deletes
-#[utoipa::path(delete, path = "/categories/{uid}", params(("uid" = Uuid, Path)), responses((status = 200, description = "Deleted")), tag = "Categories", operation_id = "deleteCategory", security(("bearerAuth" = [])))]
+#[derive(Default, Serialize, Deserialize, IntoParams)]
+pub struct DeleteCategoryQuery {
+    /// Category in the same group that takes over the expenses; they become uncategorized when omitted
+    pub reassign_to: Option<Uuid>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DeleteCategoryResponse {
+    pub success: bool,
+    /// Number of expense entries moved to `reassign_to` or left uncategorized
+    pub affected_entries: u64,
+}
+
+#[utoipa::path(delete, path = "/categories/{uid}", params(("uid" = Uuid, Path), DeleteCategoryQuery), responses((status = 200, description = "Deleted", body = DeleteCategoryResponse)), tag = "Categories", operation_id = "deleteCategory", security(("bearerAuth" = [])))]
 pub async fn delete_(
     State(state): State<AppState>,
     Extension(auth): Extension<AuthContext>, 
-    Path(uid): Path<Uuid>
-) -> Result<(), AppError> {
+    Path(uid): Path<Uuid>,
+    Query(query): Query<DeleteCategoryQuery>,
+) -> Result<Json<DeleteCategoryResponse>, AppError> {
+    if query.reassign_to == Some(uid) {
+        return Err(AppError::BadRequest("Cannot reassign expenses to the deleted category".to_string()));
+    }
     let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for deleting category"))?;
     let prev_category = CategoryRepo::get(&mut tx, uid).await?;
-    group_guard(&auth, prev_category.group_uid, &state.db_pool).await?;
-    CategoryRepo::delete(&mut tx, uid).await?;
+    group_role_guard(
+        &auth,
+        prev_category.group_uid,
+        &mut tx,
+        &state.membership_cache,
+        GroupRole::Admin,
+    )
+    .await?;
+    if let Some(target_uid) = query.reassign_to {
+        let target = CategoryRepo::get(&mut tx, target_uid).await?;
+        if target.group_uid != prev_category.group_uid {
+            return Err(AppError::BadRequest("reassign_to must be a category in the same group".to_string()));
+        }
+    }
+    let affected_entries = CategoryRepo::delete_reassigning(&mut tx, uid, query.reassign_to).await?;
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::Category,
+        prev_category.group_uid,
+        uid,
+        AuditChange::delete(&prev_category),
+    )
+    .await?;
     tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for deleting category"))?;
-    Ok(())
+    Ok(Json(DeleteCategoryResponse {
+        success: true,
+        affected_entries,
+    }))
 }
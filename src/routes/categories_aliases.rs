@@ -4,32 +4,34 @@ use axum::{
 };
 use serde::Deserialize;
 use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
 use uuid::Uuid;
+use validator::Validate;
 
 use crate::{
-    auth::{AuthContext, group_guard::group_guard},
+    auth::{
+        AuthContext,
+        group_guard::{group_guard, group_role_guard},
+    },
     error::AppError,
+    middleware::validated_json::ValidatedJson,
     repos::{
+        audit_log::{AuditActor, AuditChange, AuditEntity, AuditRepo},
         category::CategoryRepo,
         category_alias::{
             CategoryAlias, CategoryAliasRepo, CreateCategoryAliasDbPayload,
             UpdateCategoryAliasDbPayload,
         },
+        expense_group_member::GroupRole,
     },
     types::AppState,
 };
 
-pub fn router() -> axum::Router<AppState> {
-    axum::Router::new()
-        .route("/categories-aliases", axum::routing::post(create))
-        .route(
-            "/categories-aliases/category/{category_uid}",
-            axum::routing::get(list),
-        )
-        .route(
-            "/categories-aliases/{alias_uid}",
-            axum::routing::put(update).delete(delete_),
-        )
+pub fn router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(create))
+        .routes(routes!(list))
+        .routes(routes!(update, delete_))
 }
 
 #[utoipa::path(
@@ -50,7 +52,7 @@ pub async fn list(
         AppError::from_sqlx_error(e, "beginning transaction for listing category aliases")
     })?;
     let category = CategoryRepo::get(&mut tx, category_uid).await?;
-    group_guard(&auth, category.group_uid, &state.db_pool).await?;
+    group_guard(&auth, category.group_uid, &mut tx, &state.membership_cache).await?;
     let res = CategoryAliasRepo::list_by_category(&mut tx, category_uid).await?;
     tx.commit().await.map_err(|e| {
         AppError::from_sqlx_error(e, "committing transaction for listing category aliases")
@@ -58,7 +60,7 @@ pub async fn list(
     Ok(Json(res))
 }
 
-#[derive(Deserialize, ToSchema)]
+#[derive(Deserialize, ToSchema, Validate)]
 pub struct CreateCategoryAliasPayload {
     pub group_uid: Uuid,
     pub alias: String,
@@ -69,12 +71,12 @@ pub struct CreateCategoryAliasPayload {
 pub async fn create(
     State(state): State<AppState>,
     Extension(auth): Extension<AuthContext>,
-    Json(payload): Json<CreateCategoryAliasPayload>,
+    ValidatedJson(payload): ValidatedJson<CreateCategoryAliasPayload>,
 ) -> Result<Json<CategoryAlias>, AppError> {
-    group_guard(&auth, payload.group_uid, &state.db_pool).await?;
     let mut tx = state.db_pool.begin().await.map_err(|e| {
         AppError::from_sqlx_error(e, "beginning transaction for creating category alias")
     })?;
+    group_guard(&auth, payload.group_uid, &mut tx, &state.membership_cache).await?;
     let created = CategoryAliasRepo::create(
         &mut tx,
         CreateCategoryAliasDbPayload {
@@ -84,13 +86,22 @@ pub async fn create(
         },
     )
     .await?;
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::CategoryAlias,
+        created.group_uid,
+        created.alias_uid,
+        AuditChange::create(&created),
+    )
+    .await?;
     tx.commit().await.map_err(|e| {
         AppError::from_sqlx_error(e, "committing transaction for creating category alias")
     })?;
     Ok(Json(created))
 }
 
-#[derive(Deserialize, ToSchema)]
+#[derive(Deserialize, ToSchema, Validate)]
 pub struct UpdateCategoryAliasPayload {
     pub alias: Option<String>,
     pub category_uid: Option<Uuid>,
@@ -101,13 +112,19 @@ pub async fn update(
     State(state): State<AppState>,
     Extension(auth): Extension<AuthContext>,
     Path(alias_uid): Path<Uuid>,
-    Json(payload): Json<UpdateCategoryAliasPayload>,
+    ValidatedJson(payload): ValidatedJson<UpdateCategoryAliasPayload>,
 ) -> Result<Json<CategoryAlias>, AppError> {
     let mut tx = state.db_pool.begin().await.map_err(|e| {
         AppError::from_sqlx_error(e, "beginning transaction for updating category alias")
     })?;
     let prev_alias = CategoryAliasRepo::get(&mut tx, alias_uid).await?;
-    group_guard(&auth, prev_alias.group_uid, &state.db_pool).await?;
+    group_guard(
+        &auth,
+        prev_alias.group_uid,
+        &mut tx,
+        &state.membership_cache,
+    )
+    .await?;
     let updated = CategoryAliasRepo::update(
         &mut tx,
         alias_uid,
@@ -117,6 +134,15 @@ pub async fn update(
         },
     )
     .await?;
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::CategoryAlias,
+        updated.group_uid,
+        alias_uid,
+        AuditChange::update(&prev_alias, &updated),
+    )
+    .await?;
     tx.commit().await.map_err(|e| {
         AppError::from_sqlx_error(e, "committing transaction for updating category alias")
     })?;
@@ -133,8 +159,24 @@ pub async fn delete_(
         AppError::from_sqlx_error(e, "beginning transaction for deleting category alias")
     })?;
     let prev_alias = CategoryAliasRepo::get(&mut tx, alias_uid).await?;
-    group_guard(&auth, prev_alias.group_uid, &state.db_pool).await?;
+    group_role_guard(
+        &auth,
+        prev_alias.group_uid,
+        &mut tx,
+        &state.membership_cache,
+        GroupRole::Admin,
+    )
+    .await?;
     CategoryAliasRepo::delete(&mut tx, alias_uid).await?;
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::CategoryAlias,
+        prev_alias.group_uid,
+        alias_uid,
+        AuditChange::delete(&prev_alias),
+    )
+    .await?;
     tx.commit().await.map_err(|e| {
         AppError::from_sqlx_error(e, "committing transaction for deleting category alias")
     })?;
@@ -4,23 +4,26 @@ use axum::{
 };
 use serde::Deserialize;
 use utoipa::{ ToSchema};
+use utoipa_axum::{router::OpenApiRouter, routes};
 use uuid::Uuid;
+use validator::Validate;
 
 use crate::{
     error::AppError,
+    middleware::validated_json::ValidatedJson,
     repos::chat_bind_request::{
         ChatBindRequest, ChatBindRequestRepo, CreateChatBindRequestDbPayload,
     },
     types::AppState,
 };
 
-pub fn router() -> axum::Router<AppState> {
-    axum::Router::new()
-        .route("/chat-bind-requests", axum::routing::post(create))
-        .route("/chat-bind-requests/{id}", axum::routing::get(get))
+pub fn router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(create))
+        .routes(routes!(get))
 }
 
-#[derive(Deserialize, ToSchema)]
+#[derive(Deserialize, ToSchema, Validate)]
 pub struct CreateChatBindRequestPayload {
     pub platform: String,
     pub p_uid: String,
@@ -32,7 +35,7 @@ pub struct CreateChatBindRequestPayload {
 #[utoipa::path(post, path = "/chat-bind-requests", request_body = CreateChatBindRequestPayload, responses((status = 200, body = ChatBindRequest)), tag = "Chat Bind Requests", operation_id = "createChatBindRequest", security(("bearerAuth" = [])))]
 pub async fn create(
     State(state): State<AppState>,
-    Json(payload): Json<CreateChatBindRequestPayload>,
+    ValidatedJson(payload): ValidatedJson<CreateChatBindRequestPayload>,
 ) -> Result<Json<ChatBindRequest>, AppError> {
     let mut tx = state.db_pool.begin().await.map_err(|e| {
         AppError::from_sqlx_error(e, "beginning transaction for creating chat bind request")
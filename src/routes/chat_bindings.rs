@@ -1,26 +1,41 @@
 use axum::{
     Json,
-    extract::{Extension, State},
+    extract::{Extension, Path, State},
 };
 use serde::Deserialize;
 use std::collections::HashMap;
 use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
 use uuid::Uuid;
+use validator::Validate;
 
 use crate::{
-    auth::{AuthContext, group_guard::group_guard},
+    auth::{
+        AuthContext,
+        group_guard::{group_guard, group_role_guard},
+    },
     error::AppError,
+    messengers::slack::{SLACK_FEATURE, SLACK_PLATFORM},
+    middleware::{tier::check_feature_access, validated_json::ValidatedJson},
     repos::{
         chat_bind_request::ChatBindRequestRepo,
-        chat_binding::{ChatBinding, ChatBindingRepo, CreateChatBindingDbPayload},
+        chat_binding::{
+            ChatBinding, ChatBindingRepo, CreateChatBindingDbPayload, UpdateChatBindingDbPayload,
+        },
         expense_group::ExpenseGroupRepo,
+        expense_group_member::GroupRole,
+        subscription::SubscriptionRepo,
         user::UserRepo,
     },
     types::AppState,
 };
 
-pub fn router() -> axum::Router<AppState> {
-    axum::Router::new().route("/chat-bindings/accept", axum::routing::post(accept))
+pub fn router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(accept))
+        .routes(routes!(list))
+        .routes(routes!(update))
+        .routes(routes!(revoke))
 }
 
 /*
@@ -30,10 +45,15 @@ Workflow:
 3) User logs in to web; server verifies request id+nonce and expiry; user selects expense group to bind.
 4) Server creates `ChatBinding { group_uid, platform, p_uid, status='active', bound_by=user_uid }`, marks the request used, and sends a welcome message in chat.
 
-accept should handle step 3 and 4.
+accept should handle step 3 and 4. A request can be accepted once, accepting it also
+invalidates the other open requests of the chat. Used and expired requests are deleted by
+`BindRequestCleanupScheduler`.
+
+Bound chats are managed by the group owner: list, revoke or re-activate them. The chat
+itself can detach with `/logout`.
  */
 
-#[derive(Deserialize, ToSchema)]
+#[derive(Deserialize, ToSchema, Validate)]
 pub struct AcceptChatBindingPayload {
     pub request_id: Uuid,
     pub nonce: String,
@@ -44,18 +64,22 @@ pub struct AcceptChatBindingPayload {
 pub async fn accept(
     State(state): State<AppState>,
     Extension(auth): Extension<AuthContext>,
-    Json(payload): Json<AcceptChatBindingPayload>,
+    ValidatedJson(payload): ValidatedJson<AcceptChatBindingPayload>,
 ) -> Result<Json<ChatBinding>, AppError> {
-    group_guard(&auth, payload.group_uid, &state.db_pool).await?;
-
     let mut tx = state.db_pool.begin().await.map_err(|e| {
         AppError::from_sqlx_error(e, "beginning transaction for accepting chat binding")
     })?;
-    let chat_bind_request = ChatBindRequestRepo::get(&mut tx, payload.request_id).await?;
-    // TODO: proper nonce handling (e.g. one-time use)
+    group_guard(&auth, payload.group_uid, &mut tx, &state.membership_cache).await?;
+    let chat_bind_request =
+        ChatBindRequestRepo::get_for_update(&mut tx, payload.request_id).await?;
     if chat_bind_request.nonce != payload.nonce {
         return Err(AppError::BadRequest("Invalid nonce".into()));
     }
+    if chat_bind_request.used_at.is_some() {
+        return Err(AppError::BadRequest(
+            "Chat bind request already used".into(),
+        ));
+    }
     if chat_bind_request.expires_at < chrono::Utc::now() {
         ChatBindRequestRepo::delete(&mut tx, payload.request_id).await?;
         tx.commit().await.map_err(|e| {
@@ -66,6 +90,23 @@ pub async fn accept(
     // Get user and group info for personalized message before committing
     let user = UserRepo::get(&mut tx, auth.user_uid).await?;
     let group = ExpenseGroupRepo::get(&mut tx, payload.group_uid).await?;
+    // Slack workspaces can only be bound to groups whose owner is on a plan with Slack
+    if chat_bind_request.platform == SLACK_PLATFORM {
+        let subscription = SubscriptionRepo::get_by_user(&mut tx, group.owner).await?;
+        check_feature_access(&subscription, SLACK_FEATURE)?;
+    }
+
+    let bound = ChatBindingRepo::list_active_by_chat(
+        &mut tx,
+        &chat_bind_request.platform,
+        &chat_bind_request.p_uid,
+    )
+    .await?;
+    if bound.iter().any(|b| b.group_uid == payload.group_uid) {
+        return Err(AppError::BadRequest(
+            "The chat is already bound to this group".into(),
+        ));
+    }
 
     let created = ChatBindingRepo::create(
         &mut tx,
@@ -78,13 +119,25 @@ pub async fn accept(
         },
     )
     .await?;
+    // The chat continues with the group it was just bound to, /switch goes back
+    let created = ChatBindingRepo::select(&mut tx, created.id).await?;
+    ChatBindRequestRepo::mark_used(
+        &mut tx,
+        &chat_bind_request.platform,
+        &chat_bind_request.p_uid,
+    )
+    .await?;
     tx.commit().await.map_err(|e| {
         AppError::from_sqlx_error(e, "committing transaction for creating chat binding")
     })?;
 
     // Send welcome message to the chat
     if let Some(messenger_manager) = &state.messenger_manager {
-        let mut welcome_message = state.lang.get_with_vars(
+        // Same language the chat's replies use
+        let lang = state
+            .lang
+            .preferred(group.lang.as_deref().or(user.lang.as_deref()));
+        let mut welcome_message = lang.get_with_vars(
             "MESSENGER__WELCOME_INTRO",
             HashMap::from([
                 ("name".to_string(), user.email.clone()),
@@ -94,30 +147,42 @@ pub async fn accept(
 
         welcome_message.push_str(&format!(
             "{}\n\n",
-            state.lang.get("MESSENGER__WELCOME_COMMAND_LIST_HEADER")
+            lang.get("MESSENGER__WELCOME_COMMAND_LIST_HEADER")
         ));
 
         // List all commands with their instructions
         let commands = vec![
             "MESSENGER__EXPENSE_SHORT_INSTRUCTION",
             "MESSENGER__EXPENSE_EDIT_SHORT_INSTRUCTION",
+            "MESSENGER__EXPENSE_DELETE_SHORT_INSTRUCTION",
+            "MESSENGER__CONFIRM_SHORT_INSTRUCTION",
+            "MESSENGER__UNDO_SHORT_INSTRUCTION",
+            "MESSENGER__CANCEL_SHORT_INSTRUCTION",
+            "MESSENGER__INCOME_SHORT_INSTRUCTION",
+            "MESSENGER__RECURRING_SHORT_INSTRUCTION",
+            "MESSENGER__TRIP_SHORT_INSTRUCTION",
             "MESSENGER__CATEGORY_SHORT_INSTRUCTION",
             "MESSENGER__CATEGORY_EDIT_SHORT_INSTRUCTION",
             "MESSENGER__HISTORY_SHORT_INSTRUCTION",
+            "MESSENGER__SEARCH_SHORT_INSTRUCTION",
             "MESSENGER__REPORT_SHORT_INSTRUCTION",
+            "MESSENGER__LINK_ME_SHORT_INSTRUCTION",
+            "MESSENGER__SWITCH_SHORT_INSTRUCTION",
+            "MESSENGER__LANG_SHORT_INSTRUCTION",
+            "MESSENGER__LOGOUT_SHORT_INSTRUCTION",
             "MESSENGER__HELP_SHORT_INSTRUCTION",
         ];
 
         for (index, key) in commands.iter().enumerate() {
-            welcome_message.push_str(&format!("{}. {}\n", index + 1, state.lang.get(key)));
+            welcome_message.push_str(&format!("{}. {}\n", index + 1, lang.get(key)));
         }
         welcome_message.push('\n');
 
         welcome_message.push_str(&format!(
             "{}\n\n",
-            state.lang.get("MESSENGER__WELCOME_CLOSING")
+            lang.get("MESSENGER__WELCOME_CLOSING")
         ));
-        welcome_message.push_str(&format!("{}", state.lang.get("MESSENGER__WELCOME_CTA")));
+        welcome_message.push_str(&format!("{}", lang.get("MESSENGER__WELCOME_CTA")));
 
         if let Err(e) = messenger_manager
             .send_message(&created.platform, &created.p_uid, &welcome_message)
@@ -130,89 +195,140 @@ pub async fn accept(
     Ok(Json(created))
 }
 
-// #[utoipa::path(get, path = "/chat-bindings", responses((status = 200, body = [ChatBinding])), tag = "Chat Bindings", operation_id = "listChatBindings", security(("bearerAuth" = [])))]
-// pub async fn list(State(state): State<AppState>) -> Result<Json<Vec<ChatBinding>>, AppError> {
-//     let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e))?;
-//     let res = ChatBindingRepo::list(&mut tx).await?;
-//     tx.commit().await.map_err(|e| AppError::from_sqlx_error(e))?;
-//     Ok(Json(res))
-// }
-
-// #[utoipa::path(get, path = "/chat-bindings/{id}", params(("id" = Uuid, Path)), responses((status = 200, body = ChatBinding)), tag = "Chat Bindings", operation_id = "getChatBinding", security(("bearerAuth" = [])))]
-// pub async fn get(
-//     State(state): State<AppState>,
-//     Path(id): Path<Uuid>,
-// ) -> Result<Json<ChatBinding>, AppError> {
-//     let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e))?;
-//     let res = ChatBindingRepo::get(&mut tx, id).await?;
-//     tx.commit().await.map_err(|e| AppError::from_sqlx_error(e))?;
-//     Ok(Json(res))
-// }
-
-// #[derive(Deserialize, ToSchema)]
-// pub struct CreateChatBindingPayload {
-//     pub group_uid: Uuid,
-//     pub platform: String,
-//     pub p_uid: String,
-//     pub status: Option<String>,
-//     pub bound_by: Uuid,
-// }
-
-// #[utoipa::path(post, path = "/chat-bindings", request_body = CreateChatBindingPayload, responses((status = 200, body = ChatBinding)), tag = "Chat Bindings", operation_id = "createChatBinding", security(("bearerAuth" = [])))]
-// pub async fn create(
-//     State(state): State<AppState>,
-//     Extension(auth): Extension<AuthContext>,
-//     Json(payload): Json<CreateChatBindingPayload>,
-// ) -> Result<Json<ChatBinding>, AppError> {
-//     if matches!(auth.source, AuthSource::Chat) && auth.group_uid != Some(payload.group_uid) {
-//         return Err(AppError::Unauthorized("Group scope mismatch".into()));
-//     }
-//     let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e))?;
-//     let created = ChatBindingRepo::create(
-//         &mut tx,
-//         CreateChatBindingDbPayload {
-//             group_uid: payload.group_uid,
-//             platform: payload.platform,
-//             p_uid: payload.p_uid,
-//             status: payload.status,
-//             bound_by: payload.bound_by,
-//         },
-//     )
-//     .await?;
-//     tx.commit().await.map_err(|e| AppError::from_sqlx_error(e))?;
-//     Ok(Json(created))
-// }
-
-// #[derive(Deserialize, ToSchema)]
-// pub struct UpdateChatBindingPayload {
-//     pub status: Option<String>,
-//     pub revoked_at: Option<Option<chrono::DateTime<chrono::Utc>>>,
-// }
-
-// #[utoipa::path(put, path = "/chat-bindings/{id}", params(("id" = Uuid, Path)), request_body = UpdateChatBindingPayload, responses((status = 200, body = ChatBinding)), tag = "Chat Bindings", operation_id = "updateChatBinding", security(("bearerAuth" = [])))]
-// pub async fn update(
-//     State(state): State<AppState>,
-//     Path(id): Path<Uuid>,
-//     Json(payload): Json<UpdateChatBindingPayload>,
-// ) -> Result<Json<ChatBinding>, AppError> {
-//     let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e))?;
-//     let updated = ChatBindingRepo::update(
-//         &mut tx,
-//         id,
-//         UpdateChatBindingDbPayload {
-//             status: payload.status,
-//             revoked_at: payload.revoked_at,
-//         },
-//     )
-//     .await?;
-//     tx.commit().await.map_err(|e| AppError::from_sqlx_error(e))?;
-//     Ok(Json(updated))
-// }
-
-// #[utoipa::path(delete, path = "/chat-bindings/{id}", params(("id" = Uuid, Path)), responses((status = 200, description = "Deleted")), tag = "Chat Bindings", operation_id = "deleteChatBinding", security(("bearerAuth" = [])))]
-// pub async fn delete_(State(state): State<AppState>, Path(id): Path<Uuid>) -> Result<(), AppError> {
-//     let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e))?;
-//     ChatBindingRepo::delete(&mut tx, id).await?;
-//     tx.commit().await.map_err(|e| AppError::from_sqlx_error(e))?;
-//     Ok(())
-// }
+#[utoipa::path(get, path = "/groups/{group_uid}/chat-bindings", params(("group_uid" = Uuid, Path)), responses((status = 200, body = [ChatBinding])), tag = "Chat Bindings", operation_id = "listChatBindings", security(("bearerAuth" = [])))]
+pub async fn list(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(group_uid): Path<Uuid>,
+) -> Result<Json<Vec<ChatBinding>>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for listing chat bindings")
+    })?;
+    group_role_guard(
+        &auth,
+        group_uid,
+        &mut tx,
+        &state.membership_cache,
+        GroupRole::Owner,
+    )
+    .await?;
+    let res = ChatBindingRepo::list_by_group(&mut tx, group_uid).await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for listing chat bindings")
+    })?;
+    Ok(Json(res))
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct UpdateChatBindingPayload {
+    /// `active` or `revoked`
+    pub status: String,
+}
+
+#[utoipa::path(put, path = "/chat-bindings/{id}", params(("id" = Uuid, Path)), request_body = UpdateChatBindingPayload, responses((status = 200, body = ChatBinding)), tag = "Chat Bindings", operation_id = "updateChatBinding", security(("bearerAuth" = [])))]
+pub async fn update(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<UpdateChatBindingPayload>,
+) -> Result<Json<ChatBinding>, AppError> {
+    if payload.status != "active" && payload.status != "revoked" {
+        return Err(AppError::BadRequest(format!(
+            "Invalid status: {}, expected active or revoked",
+            payload.status
+        )));
+    }
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for updating chat binding")
+    })?;
+    let prev_binding = ChatBindingRepo::get(&mut tx, id).await?;
+    group_role_guard(
+        &auth,
+        prev_binding.group_uid,
+        &mut tx,
+        &state.membership_cache,
+        GroupRole::Owner,
+    )
+    .await?;
+    let updated = if payload.status == "revoked" {
+        ChatBindingRepo::revoke(&mut tx, id).await?
+    } else if prev_binding.status == "active" {
+        prev_binding
+    } else {
+        let bound = ChatBindingRepo::list_active_by_chat(
+            &mut tx,
+            &prev_binding.platform,
+            &prev_binding.p_uid,
+        )
+        .await?;
+        // The chat may have been bound to the group again since
+        if bound.iter().any(|b| b.group_uid == prev_binding.group_uid) {
+            return Err(AppError::BadRequest(
+                "The chat is already bound to this group".to_string(),
+            ));
+        }
+        ChatBindingRepo::update(
+            &mut tx,
+            id,
+            UpdateChatBindingDbPayload {
+                status: Some(payload.status),
+                revoked_at: Some(None),
+            },
+        )
+        .await?;
+        // A re-activated binding only takes over a chat that has no other group selected
+        if bound.is_empty() {
+            ChatBindingRepo::select(&mut tx, id).await?
+        } else {
+            ChatBindingRepo::get(&mut tx, id).await?
+        }
+    };
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for updating chat binding")
+    })?;
+    Ok(Json(updated))
+}
+
+#[utoipa::path(post, path = "/chat-bindings/{id}/revoke", params(("id" = Uuid, Path)), responses((status = 200, body = ChatBinding)), tag = "Chat Bindings", operation_id = "revokeChatBinding", security(("bearerAuth" = [])))]
+pub async fn revoke(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ChatBinding>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for revoking chat binding")
+    })?;
+    let prev_binding = ChatBindingRepo::get(&mut tx, id).await?;
+    group_role_guard(
+        &auth,
+        prev_binding.group_uid,
+        &mut tx,
+        &state.membership_cache,
+        GroupRole::Owner,
+    )
+    .await?;
+    if prev_binding.status == "revoked" {
+        return Ok(Json(prev_binding));
+    }
+    let revoked = ChatBindingRepo::revoke(&mut tx, id).await?;
+    let group = ExpenseGroupRepo::get(&mut tx, revoked.group_uid).await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for revoking chat binding")
+    })?;
+
+    // Let the chat know why its commands stopped working
+    if let Some(messenger_manager) = &state.messenger_manager {
+        let message = state.lang.preferred(group.lang.as_deref()).get_with_vars(
+            "MESSENGER__BINDING_REVOKED",
+            HashMap::from([("group".to_string(), group.name)]),
+        );
+        if let Err(e) = messenger_manager
+            .send_message(&revoked.platform, &revoked.p_uid, &message)
+            .await
+        {
+            tracing::error!("Failed to send revoked binding message: {:?}", e);
+        }
+    }
+
+    Ok(Json(revoked))
+}
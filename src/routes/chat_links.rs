@@ -0,0 +1,99 @@
+use axum::{
+    Json,
+    extract::{Extension, Path, State},
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+use uuid::Uuid;
+
+use crate::{
+    auth::{AuthContext, AuthSource},
+    error::AppError,
+    repos::{
+        chat_link_code::ChatLinkCodeRepo,
+        chat_member_link::{ChatMemberLink, ChatMemberLinkRepo},
+    },
+    types::{AppState, DeleteResponse},
+};
+
+// Long enough to switch to the chat app and type the code
+const LINK_CODE_TTL_MINUTES: i64 = 10;
+
+/*
+Links a member's chat platform account to their user, so entries they send in a group chat
+are attributed to them:
+1) The member creates a code in the web app.
+2) They send `/link-me [code]` in the group chat, linking their platform user id.
+ */
+pub fn router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(list))
+        .routes(routes!(create_code))
+        .routes(routes!(delete_))
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ChatLinkCodeResponse {
+    /// Send `/link-me [code]` in the chat, the code works once
+    pub code: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[utoipa::path(post, path = "/chat-links/code", responses((status = 200, body = ChatLinkCodeResponse)), tag = "Chat Links", operation_id = "createChatLinkCode", security(("bearerAuth" = [])))]
+pub async fn create_code(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+) -> Result<Json<ChatLinkCodeResponse>, AppError> {
+    if !matches!(auth.source, AuthSource::Web) {
+        return Err(AppError::Unauthorized(
+            "Chat link codes require a web session".to_string(),
+        ));
+    }
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for creating chat link code")
+    })?;
+    let expires_at = Utc::now() + Duration::minutes(LINK_CODE_TTL_MINUTES);
+    let (_, code) = ChatLinkCodeRepo::create(&mut tx, auth.user_uid, expires_at).await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for creating chat link code")
+    })?;
+    Ok(Json(ChatLinkCodeResponse { code, expires_at }))
+}
+
+#[utoipa::path(get, path = "/chat-links", responses((status = 200, body = [ChatMemberLink])), tag = "Chat Links", operation_id = "listChatLinks", security(("bearerAuth" = [])))]
+pub async fn list(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+) -> Result<Json<Vec<ChatMemberLink>>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for listing chat links")
+    })?;
+    let res = ChatMemberLinkRepo::list_by_user(&mut tx, auth.user_uid).await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for listing chat links")
+    })?;
+    Ok(Json(res))
+}
+
+#[utoipa::path(delete, path = "/chat-links/{uid}", params(("uid" = Uuid, Path)), responses((status = 200, body = DeleteResponse)), tag = "Chat Links", operation_id = "deleteChatLink", security(("bearerAuth" = [])))]
+pub async fn delete_(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(uid): Path<Uuid>,
+) -> Result<Json<DeleteResponse>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for deleting chat link")
+    })?;
+    let link = ChatMemberLinkRepo::get(&mut tx, uid).await?;
+    // Someone else's link is reported as missing, not as forbidden
+    if link.user_uid != auth.user_uid {
+        return Err(AppError::NotFound("Chat link not found".to_string()));
+    }
+    ChatMemberLinkRepo::delete(&mut tx, uid).await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for deleting chat link")
+    })?;
+    Ok(Json(DeleteResponse { success: true }))
+}
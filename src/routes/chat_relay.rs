@@ -0,0 +1,114 @@
+use axum::{Json, body::Bytes, extract::State, http::HeaderMap};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use crate::{
+    auth::{RELAY_SIGNATURE_HEADER, verify_relay_signature},
+    commands::{base::ChatSender, dispatcher::CommandDispatcher},
+    error::AppError,
+    repos::chat_binding::ChatBindingRepo,
+    types::AppState,
+};
+
+// Slack is left out, its commands go through `/webhooks/slack` where the Team tier is checked
+const RELAY_PLATFORMS: [&str; 2] = ["telegram", "whatsapp"];
+
+/*
+ Chat messages forwarded by self-hosted bridges, e.g. a WhatsApp Web relay, which get the
+ reply to send back instead of the platform SDKs being built in. The body is signed with
+ `CHAT_RELAY_SECRET` like the relay requests of `auth_middleware`, but the chat does not
+ need to be bound: unbound chats get the same `/login` flow as the built-in messengers.
+*/
+pub fn router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new().routes(routes!(relay))
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ChatRelayPayload {
+    /// `telegram` or `whatsapp`
+    pub platform: String,
+    /// The chat on the platform, e.g. the WhatsApp number or group id
+    pub p_uid: String,
+    /// The message as typed, e.g. `/report`
+    pub text: String,
+    /// Who sent the message in group chats, defaults to `p_uid`
+    pub p_user_id: Option<String>,
+    /// Display name of the sender, defaults to `p_user_id`
+    pub sender_name: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ChatRelayResponse {
+    /// What to send back to the chat, none when nothing should be sent
+    pub text: Option<String>,
+}
+
+// Public route: the bridge authenticates with `X-Relay-Signature`, not a token
+#[utoipa::path(post, path = "/chat-relay", request_body = ChatRelayPayload, responses((status = 200, body = ChatRelayResponse), (status = 401, description = "Invalid signature")), tag = "Chat Relay", operation_id = "relayChatMessage")]
+pub async fn relay(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<ChatRelayResponse>, AppError> {
+    let signature = headers
+        .get(RELAY_SIGNATURE_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    if !verify_relay_signature(&state.chat_relay_secret, signature, &body) {
+        return Err(AppError::Unauthorized("Invalid relay signature".into()));
+    }
+    let payload: ChatRelayPayload = serde_json::from_slice(&body)
+        .map_err(|e| AppError::BadRequest(format!("Invalid relay payload: {}", e)))?;
+    if !RELAY_PLATFORMS.contains(&payload.platform.as_str()) {
+        return Err(AppError::BadRequest(format!(
+            "Unsupported platform: {}, expected one of {}",
+            payload.platform,
+            RELAY_PLATFORMS.join(", ")
+        )));
+    }
+    let p_uid = payload.p_uid.trim();
+    if p_uid.is_empty() {
+        return Err(AppError::BadRequest("p_uid is required".to_string()));
+    }
+    let p_user_id = payload.p_user_id.unwrap_or_else(|| p_uid.to_string());
+    let sender = ChatSender {
+        name: payload.sender_name.unwrap_or_else(|| p_user_id.clone()),
+        p_user_id,
+        linked_user_uid: None,
+    };
+
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for relaying chat message")
+    })?;
+    let binding = ChatBindingRepo::get_selected(&mut tx, &payload.platform, p_uid).await?;
+    let text = match binding {
+        Some(binding) => CommandDispatcher::dispatch(
+            &payload.text,
+            &binding,
+            &sender,
+            &state.chat_bind_url,
+            &mut tx,
+            &state.lang,
+            &state.rate_limiter,
+        )
+        .await
+        .map(|reply| reply.text),
+        None => Some(
+            CommandDispatcher::dispatch_unbound(
+                &payload.platform,
+                p_uid,
+                &payload.text,
+                &state.chat_bind_url,
+                &mut tx,
+                &state.lang,
+            )
+            .await
+            .map_err(AppError::Internal)?,
+        ),
+    };
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for relaying chat message")
+    })?;
+    Ok(Json(ChatRelayResponse { text }))
+}
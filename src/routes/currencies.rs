@@ -0,0 +1,61 @@
+use axum::{Json, extract::State};
+use serde::Serialize;
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use crate::{
+    error::AppError,
+    repos::exchange_rate::{ExchangeRate, ExchangeRateRepo},
+    types::AppState,
+    utils::currency::{SUPPORTED_CURRENCIES, normalize_currency},
+};
+
+pub fn router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(list))
+        .routes(routes!(list_rates))
+}
+
+// Validates an optional currency code from a request body
+pub fn parse_currency(code: Option<&str>) -> Result<Option<String>, AppError> {
+    code.map(|code| {
+        normalize_currency(code)
+            .ok_or_else(|| AppError::BadRequest(format!("Unsupported currency: {}", code.trim())))
+    })
+    .transpose()
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CurrencyInfo {
+    pub code: String,
+    pub symbol: String,
+    pub decimals: usize,
+}
+
+#[utoipa::path(get, path = "/currencies", responses((status = 200, body = [CurrencyInfo])), tag = "Currencies", operation_id = "listCurrencies", security(("bearerAuth" = [])))]
+pub async fn list() -> Json<Vec<CurrencyInfo>> {
+    Json(
+        SUPPORTED_CURRENCIES
+            .iter()
+            .map(|currency| CurrencyInfo {
+                code: currency.code.to_string(),
+                symbol: currency.symbol.trim().to_string(),
+                decimals: currency.decimals,
+            })
+            .collect(),
+    )
+}
+
+#[utoipa::path(get, path = "/exchange-rates", responses((status = 200, body = [ExchangeRate])), tag = "Currencies", operation_id = "listExchangeRates", security(("bearerAuth" = [])))]
+pub async fn list_rates(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<ExchangeRate>>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for listing exchange rates")
+    })?;
+    let res = ExchangeRateRepo::list(&mut tx).await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for listing exchange rates")
+    })?;
+    Ok(Json(res))
+}
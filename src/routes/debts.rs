@@ -0,0 +1,329 @@
+use axum::{
+    Json,
+    extract::{Extension, Path, Query, State},
+};
+use chrono::{NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+use utoipa_axum::{router::OpenApiRouter, routes};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    auth::{
+        AuthContext,
+        group_guard::{group_guard, group_role_guard},
+    },
+    error::AppError,
+    middleware::validated_json::ValidatedJson,
+    repos::{
+        audit_log::{AuditActor, AuditChange, AuditEntity, AuditRepo},
+        debt::{
+            CreateDebtDbPayload, Debt, DebtDirection, DebtRepo, OutstandingDebts,
+            UpdateDebtDbPayload,
+        },
+        exchange_rate::ExchangeRateRepo,
+        expense_group::ExpenseGroupRepo,
+        expense_group_member::GroupRole,
+    },
+    routes::currencies::parse_currency,
+    types::{AppState, DeleteResponse},
+};
+
+pub fn router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(list, create))
+        .routes(routes!(summary))
+        .routes(routes!(get, update, delete_))
+        .routes(routes!(settle))
+}
+
+/*
+Permissions:
+- every member can list, record, edit and settle the debts of the group
+- only admins can delete them, settled debts are kept for the record otherwise
+ */
+
+const MAX_PERSON_LENGTH: usize = 100;
+
+fn parse_person(person: &str) -> Result<String, AppError> {
+    let person = person.trim();
+    if person.is_empty() || person.chars().count() > MAX_PERSON_LENGTH {
+        return Err(AppError::BadRequest(format!(
+            "person must be between 1 and {} characters",
+            MAX_PERSON_LENGTH
+        )));
+    }
+    Ok(person.to_string())
+}
+
+fn validate_amount(amount: Decimal) -> Result<(), AppError> {
+    if amount <= Decimal::ZERO {
+        return Err(AppError::BadRequest(
+            "amount must be greater than zero".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ListDebtsQuery {
+    /// Also list debts that were paid back, defaults to false
+    pub include_settled: Option<bool>,
+}
+
+#[utoipa::path(get, path = "/groups/{group_uid}/debts", params(("group_uid" = Uuid, Path), ListDebtsQuery), responses((status = 200, body = [Debt])), tag = "Debts", operation_id = "listDebts", security(("bearerAuth" = [])))]
+pub async fn list(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(group_uid): Path<Uuid>,
+    Query(query): Query<ListDebtsQuery>,
+) -> Result<Json<Vec<Debt>>, AppError> {
+    let mut tx = state
+        .db_pool
+        .begin()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for listing debts"))?;
+    group_guard(&auth, group_uid, &mut tx, &state.membership_cache).await?;
+    let res =
+        DebtRepo::list_by_group(&mut tx, group_uid, query.include_settled.unwrap_or(false)).await?;
+    tx.commit()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "committing transaction for listing debts"))?;
+    Ok(Json(res))
+}
+
+// Open debts of the group summed in its currency
+#[utoipa::path(get, path = "/groups/{group_uid}/debts/summary", params(("group_uid" = Uuid, Path)), responses((status = 200, body = OutstandingDebts)), tag = "Debts", operation_id = "getDebtSummary", security(("bearerAuth" = [])))]
+pub async fn summary(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(group_uid): Path<Uuid>,
+) -> Result<Json<OutstandingDebts>, AppError> {
+    let mut tx =
+        state.db_pool.begin().await.map_err(|e| {
+            AppError::from_sqlx_error(e, "beginning transaction for summarizing debts")
+        })?;
+    group_guard(&auth, group_uid, &mut tx, &state.membership_cache).await?;
+    let group = ExpenseGroupRepo::get(&mut tx, group_uid).await?;
+    let rates = ExchangeRateRepo::rate_table(&mut tx).await?;
+    let debts = DebtRepo::list_by_group(&mut tx, group_uid, false).await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for summarizing debts")
+    })?;
+    Ok(Json(OutstandingDebts::of(
+        &debts,
+        &rates,
+        &group.currency,
+        Utc::now().date_naive(),
+    )))
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct CreateDebtPayload {
+    /// `lent` when someone owes the group, `borrowed` when the group owes them
+    pub direction: String,
+    /// Who the money was lent to or borrowed from, outside the group
+    pub person: String,
+    pub amount: Decimal,
+    /// ISO 4217 code, defaults to the group's currency
+    pub currency: Option<String>,
+    pub due_date: Option<NaiveDate>,
+    pub note: Option<String>,
+}
+
+#[utoipa::path(post, path = "/groups/{group_uid}/debts", params(("group_uid" = Uuid, Path)), request_body = CreateDebtPayload, responses((status = 200, body = Debt)), tag = "Debts", operation_id = "createDebt", security(("bearerAuth" = [])))]
+pub async fn create(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(group_uid): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<CreateDebtPayload>,
+) -> Result<Json<Debt>, AppError> {
+    let direction = DebtDirection::parse(&payload.direction).ok_or_else(|| {
+        AppError::BadRequest("direction must be either 'lent' or 'borrowed'".to_string())
+    })?;
+    let person = parse_person(&payload.person)?;
+    validate_amount(payload.amount)?;
+    let currency = parse_currency(payload.currency.as_deref())?;
+    let mut tx = state
+        .db_pool
+        .begin()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for creating debt"))?;
+    group_guard(&auth, group_uid, &mut tx, &state.membership_cache).await?;
+    let created = DebtRepo::create(
+        &mut tx,
+        CreateDebtDbPayload {
+            group_uid,
+            direction,
+            person,
+            amount: payload.amount,
+            currency,
+            due_date: payload.due_date,
+            note: payload.note,
+        },
+    )
+    .await?;
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::Debt,
+        group_uid,
+        created.uid,
+        AuditChange::create(&created),
+    )
+    .await?;
+    tx.commit()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "committing transaction for creating debt"))?;
+    Ok(Json(created))
+}
+
+#[utoipa::path(get, path = "/debts/{uid}", params(("uid" = Uuid, Path)), responses((status = 200, body = Debt)), tag = "Debts", operation_id = "getDebt", security(("bearerAuth" = [])))]
+pub async fn get(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(uid): Path<Uuid>,
+) -> Result<Json<Debt>, AppError> {
+    let mut tx = state
+        .db_pool
+        .begin()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for getting debt"))?;
+    let debt = DebtRepo::get(&mut tx, uid).await?;
+    group_guard(&auth, debt.group_uid, &mut tx, &state.membership_cache).await?;
+    tx.commit()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "committing transaction for getting debt"))?;
+    Ok(Json(debt))
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct UpdateDebtPayload {
+    pub person: Option<String>,
+    pub amount: Option<Decimal>,
+    pub currency: Option<String>,
+    /// A new due date is reminded of again
+    pub due_date: Option<NaiveDate>,
+    pub note: Option<String>,
+}
+
+#[utoipa::path(put, path = "/debts/{uid}", params(("uid" = Uuid, Path)), request_body = UpdateDebtPayload, responses((status = 200, body = Debt)), tag = "Debts", operation_id = "updateDebt", security(("bearerAuth" = [])))]
+pub async fn update(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(uid): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<UpdateDebtPayload>,
+) -> Result<Json<Debt>, AppError> {
+    let person = payload.person.as_deref().map(parse_person).transpose()?;
+    if let Some(amount) = payload.amount {
+        validate_amount(amount)?;
+    }
+    let currency = parse_currency(payload.currency.as_deref())?;
+    let mut tx = state
+        .db_pool
+        .begin()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for updating debt"))?;
+    let prev_rec = DebtRepo::get(&mut tx, uid).await?;
+    group_guard(&auth, prev_rec.group_uid, &mut tx, &state.membership_cache).await?;
+    let updated = DebtRepo::update(
+        &mut tx,
+        uid,
+        UpdateDebtDbPayload {
+            person,
+            amount: payload.amount,
+            currency,
+            due_date: payload.due_date,
+            note: payload.note,
+        },
+    )
+    .await?;
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::Debt,
+        updated.group_uid,
+        updated.uid,
+        AuditChange::update(&prev_rec, &updated),
+    )
+    .await?;
+    tx.commit()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "committing transaction for updating debt"))?;
+    Ok(Json(updated))
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct SettleDebtPayload {
+    /// False opens a debt settled by mistake again, defaults to true
+    pub settled: Option<bool>,
+}
+
+// Marks the debt as paid back, it stops counting as outstanding and is no longer reminded of
+#[utoipa::path(post, path = "/debts/{uid}/settle", params(("uid" = Uuid, Path)), request_body = SettleDebtPayload, responses((status = 200, body = Debt)), tag = "Debts", operation_id = "settleDebt", security(("bearerAuth" = [])))]
+pub async fn settle(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(uid): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<SettleDebtPayload>,
+) -> Result<Json<Debt>, AppError> {
+    let mut tx = state
+        .db_pool
+        .begin()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for settling debt"))?;
+    let prev_rec = DebtRepo::get(&mut tx, uid).await?;
+    group_guard(&auth, prev_rec.group_uid, &mut tx, &state.membership_cache).await?;
+    let updated = DebtRepo::set_settled(&mut tx, uid, payload.settled.unwrap_or(true)).await?;
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::Debt,
+        updated.group_uid,
+        updated.uid,
+        AuditChange::update(&prev_rec, &updated),
+    )
+    .await?;
+    tx.commit()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "committing transaction for settling debt"))?;
+    Ok(Json(updated))
+}
+
+#[utoipa::path(delete, path = "/debts/{uid}", params(("uid" = Uuid, Path)), responses((status = 200, body = DeleteResponse)), tag = "Debts", operation_id = "deleteDebt", security(("bearerAuth" = [])))]
+pub async fn delete_(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(uid): Path<Uuid>,
+) -> Result<Json<DeleteResponse>, AppError> {
+    let mut tx = state
+        .db_pool
+        .begin()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for deleting debt"))?;
+    let prev_rec = DebtRepo::get(&mut tx, uid).await?;
+    group_role_guard(
+        &auth,
+        prev_rec.group_uid,
+        &mut tx,
+        &state.membership_cache,
+        GroupRole::Admin,
+    )
+    .await?;
+    DebtRepo::delete(&mut tx, uid).await?;
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::Debt,
+        prev_rec.group_uid,
+        uid,
+        AuditChange::delete(&prev_rec),
+    )
+    .await?;
+    tx.commit()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "committing transaction for deleting debt"))?;
+    Ok(Json(DeleteResponse { success: true }))
+}
@@ -0,0 +1,352 @@
+use std::collections::HashMap;
+
+use axum::{
+    Json,
+    extract::{Extension, Multipart, Path, State},
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+use uuid::Uuid;
+
+use crate::{
+    auth::{
+        AuthContext,
+        group_guard::{group_guard, group_role_guard, group_write_guard},
+    },
+    error::AppError,
+    middleware::tier::check_feature_access,
+    repos::{
+        email_ingest_address::{EmailIngestAddress, EmailIngestAddressRepo},
+        expense_draft::{CreateExpenseDraftDbPayload, ExpenseDraft, ExpenseDraftRepo},
+        expense_group_member::GroupRole,
+        subscription::SubscriptionRepo,
+    },
+    types::{AppState, DeleteResponse},
+    utils::email_receipt,
+};
+
+// Older drafts have to be confirmed or discarded before more mail is accepted
+const MAX_DRAFTS_PER_GROUP: i64 = 100;
+const MAX_SUBJECT_LENGTH: usize = 255;
+const MAX_BODY_LENGTH: usize = 10_000;
+
+/*
+ Forwarded e-receipts. A group admin creates the group's ingest address, members forward
+ receipts to it, and the mail provider posts every message to `/ingest/email`. What can be read
+ from the receipt becomes a draft, which a member confirms by creating the entry with its
+ `draft_uid`, or discards.
+*/
+pub fn router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(get_address, rotate_address, delete_address))
+        .routes(routes!(ingest_email))
+        .routes(routes!(list_drafts))
+        .routes(routes!(delete_draft))
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct EmailIngestAddressResponse {
+    /// Where to forward receipts, none until EMAIL_INGEST_DOMAIN is configured on the server
+    pub address: Option<String>,
+    /// Local part of the address. Anyone who knows it can add drafts, rotate it if it leaks
+    pub token: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl EmailIngestAddressResponse {
+    fn new(address: EmailIngestAddress, domain: Option<&str>) -> Self {
+        Self {
+            address: domain.map(|domain| format!("{}@{}", address.token, domain)),
+            token: address.token,
+            created_at: address.created_at,
+        }
+    }
+}
+
+// Documents the multipart body, named like Mailgun's inbound routes post it
+#[derive(ToSchema)]
+#[schema(rename_all = "kebab-case")]
+#[allow(dead_code)]
+pub struct IngestEmailForm {
+    /// Comma separated recipients, one of them `<token>@EMAIL_INGEST_DOMAIN`
+    pub recipient: String,
+    /// Envelope sender
+    pub sender: Option<String>,
+    /// `From` header
+    pub from: Option<String>,
+    pub subject: Option<String>,
+    pub body_plain: Option<String>,
+    /// Used when there is no `body-plain`
+    pub body_html: Option<String>,
+    /// Unix time the message was received
+    pub timestamp: Option<String>,
+}
+
+#[utoipa::path(get, path = "/groups/{group_uid}/ingest-address", params(("group_uid" = Uuid, Path)), responses((status = 200, body = EmailIngestAddressResponse), (status = 404, description = "The group has no ingest address")), tag = "Email Ingest", operation_id = "getEmailIngestAddress", security(("bearerAuth" = [])))]
+pub async fn get_address(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(group_uid): Path<Uuid>,
+) -> Result<Json<EmailIngestAddressResponse>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for getting ingest address")
+    })?;
+    group_guard(&auth, group_uid, &mut tx, &state.membership_cache).await?;
+    let address = EmailIngestAddressRepo::get_by_group(&mut tx, group_uid)
+        .await?
+        .ok_or_else(|| AppError::NotFound("The group has no ingest address".to_string()))?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for getting ingest address")
+    })?;
+    Ok(Json(EmailIngestAddressResponse::new(
+        address,
+        state.email_ingest_domain.as_deref(),
+    )))
+}
+
+// Creates the address, or replaces it with a new one when the group has one
+#[utoipa::path(post, path = "/groups/{group_uid}/ingest-address", params(("group_uid" = Uuid, Path)), responses((status = 200, body = EmailIngestAddressResponse)), tag = "Email Ingest", operation_id = "rotateEmailIngestAddress", security(("bearerAuth" = [])))]
+pub async fn rotate_address(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(group_uid): Path<Uuid>,
+) -> Result<Json<EmailIngestAddressResponse>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for rotating ingest address")
+    })?;
+    group_role_guard(
+        &auth,
+        group_uid,
+        &mut tx,
+        &state.membership_cache,
+        GroupRole::Admin,
+    )
+    .await?;
+    let subscription = SubscriptionRepo::get_by_user(&mut tx, auth.user_uid).await?;
+    check_feature_access(&subscription, "receipts")?;
+    let address = EmailIngestAddressRepo::rotate(&mut tx, group_uid, auth.user_uid).await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for rotating ingest address")
+    })?;
+    Ok(Json(EmailIngestAddressResponse::new(
+        address,
+        state.email_ingest_domain.as_deref(),
+    )))
+}
+
+// Mail sent to the address is dropped from then on, drafts already received stay
+#[utoipa::path(delete, path = "/groups/{group_uid}/ingest-address", params(("group_uid" = Uuid, Path)), responses((status = 200, body = DeleteResponse)), tag = "Email Ingest", operation_id = "deleteEmailIngestAddress", security(("bearerAuth" = [])))]
+pub async fn delete_address(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(group_uid): Path<Uuid>,
+) -> Result<Json<DeleteResponse>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for deleting ingest address")
+    })?;
+    group_role_guard(
+        &auth,
+        group_uid,
+        &mut tx,
+        &state.membership_cache,
+        GroupRole::Admin,
+    )
+    .await?;
+    if !EmailIngestAddressRepo::delete(&mut tx, group_uid).await? {
+        return Err(AppError::NotFound(
+            "The group has no ingest address".to_string(),
+        ));
+    }
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for deleting ingest address")
+    })?;
+    Ok(Json(DeleteResponse { success: true }))
+}
+
+// Ingest tokens among the recipients, `<token>+anything@domain` included
+fn recipient_tokens(recipients: &str) -> Vec<String> {
+    recipients
+        .split(',')
+        .filter_map(|recipient| {
+            let address = recipient
+                .rsplit_once('<')
+                .map_or(recipient, |(_, address)| address)
+                .trim()
+                .trim_end_matches('>');
+            let (local, _) = address.split_once('@')?;
+            let token = local.split('+').next().unwrap_or(local).to_lowercase();
+            (token.len() == 64 && token.chars().all(|c| c.is_ascii_hexdigit())).then_some(token)
+        })
+        .collect()
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    text.trim().chars().take(max_chars).collect()
+}
+
+/*
+ Public route: the token in the recipient address is the credential. Mail to an unknown
+ address, or to a group with too many drafts, is accepted and dropped so the provider does not
+ retry it and tokens cannot be probed.
+*/
+#[utoipa::path(post, path = "/ingest/email", request_body(content = IngestEmailForm, content_type = "multipart/form-data"), responses((status = 200, description = "Message turned into a draft or dropped")), tag = "Email Ingest", operation_id = "ingestEmail")]
+pub async fn ingest_email(
+    State(state): State<AppState>,
+    mut multipart: Multipart,
+) -> Result<(), AppError> {
+    let mut fields: HashMap<String, String> = HashMap::new();
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| AppError::BadRequest(format!("Invalid multipart body: {}", e)))?
+    {
+        // Attachments are not read
+        let Some(name) = field.name().map(|name| name.to_string()) else {
+            continue;
+        };
+        if field.file_name().is_some() {
+            continue;
+        }
+        let value = field
+            .text()
+            .await
+            .map_err(|e| AppError::BadRequest(format!("Failed to read {}: {}", name, e)))?;
+        fields.insert(name, value);
+    }
+    let field = |name: &str| fields.get(name).map(String::as_str).unwrap_or_default();
+
+    let tokens = recipient_tokens(field("recipient"));
+    let mut tx =
+        state.db_pool.begin().await.map_err(|e| {
+            AppError::from_sqlx_error(e, "beginning transaction for ingesting email")
+        })?;
+    let mut group_uid = None;
+    for token in &tokens {
+        group_uid = EmailIngestAddressRepo::find_group_by_token(&mut tx, token).await?;
+        if group_uid.is_some() {
+            break;
+        }
+    }
+    let Some(group_uid) = group_uid else {
+        info!("Dropping email to unknown ingest address");
+        return Ok(());
+    };
+    if ExpenseDraftRepo::count_by_group(&mut tx, group_uid).await? >= MAX_DRAFTS_PER_GROUP {
+        warn!("Dropping email for group {}: too many drafts", group_uid);
+        return Ok(());
+    }
+
+    let from = match field("from") {
+        "" => field("sender"),
+        from => from,
+    };
+    let subject = truncate(field("subject"), MAX_SUBJECT_LENGTH);
+    let body = match field("body-plain") {
+        "" => email_receipt::html_to_text(field("body-html")),
+        body => body.to_string(),
+    };
+    let receipt = email_receipt::parse_receipt(from, &subject, &body);
+
+    // Receipts dated in the future are misread, the email's own time is closer
+    let now = Utc::now();
+    let received_at = field("timestamp")
+        .trim()
+        .parse::<i64>()
+        .ok()
+        .and_then(|secs| DateTime::from_timestamp(secs, 0))
+        .filter(|received_at| *received_at <= now)
+        .unwrap_or(now);
+    let spent_at = receipt
+        .date
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|date| date.and_utc())
+        .filter(|spent_at| *spent_at <= now)
+        .unwrap_or(received_at);
+
+    let draft = ExpenseDraftRepo::create(
+        &mut tx,
+        CreateExpenseDraftDbPayload {
+            group_uid,
+            source: "email".to_string(),
+            merchant: receipt.merchant,
+            price: receipt
+                .amount
+                .filter(|amount| amount.is_sign_positive() && !amount.is_zero()),
+            currency: receipt.currency,
+            spent_at,
+            sender: truncate(from, MAX_SUBJECT_LENGTH),
+            subject,
+            body: truncate(&body, MAX_BODY_LENGTH),
+        },
+    )
+    .await?;
+    tx.commit()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "committing transaction for ingesting email"))?;
+    info!(
+        "Created expense draft {} for group {}",
+        draft.uid, group_uid
+    );
+    Ok(())
+}
+
+#[utoipa::path(get, path = "/groups/{group_uid}/expense-drafts", params(("group_uid" = Uuid, Path)), responses((status = 200, body = [ExpenseDraft])), tag = "Email Ingest", operation_id = "listExpenseDrafts", security(("bearerAuth" = [])))]
+pub async fn list_drafts(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(group_uid): Path<Uuid>,
+) -> Result<Json<Vec<ExpenseDraft>>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for listing expense drafts")
+    })?;
+    group_guard(&auth, group_uid, &mut tx, &state.membership_cache).await?;
+    let res = ExpenseDraftRepo::list_by_group(&mut tx, group_uid).await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for listing expense drafts")
+    })?;
+    Ok(Json(res))
+}
+
+#[utoipa::path(delete, path = "/expense-drafts/{uid}", params(("uid" = Uuid, Path)), responses((status = 200, body = DeleteResponse)), tag = "Email Ingest", operation_id = "deleteExpenseDraft", security(("bearerAuth" = [])))]
+pub async fn delete_draft(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(uid): Path<Uuid>,
+) -> Result<Json<DeleteResponse>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for deleting expense draft")
+    })?;
+    let draft = ExpenseDraftRepo::get(&mut tx, uid).await?;
+    group_write_guard(&auth, draft.group_uid, &mut tx, &state.membership_cache).await?;
+    ExpenseDraftRepo::delete(&mut tx, uid).await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for deleting expense draft")
+    })?;
+    Ok(Json(DeleteResponse { success: true }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recipient_tokens() {
+        let token = "a".repeat(64);
+        assert_eq!(
+            recipient_tokens(&format!(
+                "Budi <budi@example.com>, \"Family\" <{}+grab@receipts.example.com>",
+                token.to_uppercase()
+            )),
+            vec![token.clone()]
+        );
+        assert_eq!(
+            recipient_tokens(&format!("{}@receipts.example.com", token)),
+            vec![token]
+        );
+        assert!(recipient_tokens("abc@receipts.example.com").is_empty());
+        assert!(recipient_tokens("").is_empty());
+    }
+}
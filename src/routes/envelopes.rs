@@ -0,0 +1,386 @@
+use std::collections::HashMap;
+
+use axum::{
+    Json,
+    extract::{Extension, Path, State},
+};
+use chrono::NaiveDate;
+use rust_decimal::{Decimal, prelude::ToPrimitive};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    auth::{
+        AuthContext,
+        group_guard::{group_guard, group_role_guard},
+    },
+    error::AppError,
+    middleware::validated_json::ValidatedJson,
+    repos::{
+        audit_log::{AuditActor, AuditChange, AuditEntity, AuditRepo},
+        envelope::{CreateEnvelopeDbPayload, Envelope, EnvelopeRepo, UpdateEnvelopeDbPayload},
+        exchange_rate::ExchangeRateRepo,
+        expense_group_member::GroupRole,
+    },
+    routes::currencies::parse_currency,
+    types::{AppState, DeleteResponse},
+    utils::currency::RateTable,
+};
+
+pub fn router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(list, create))
+        .routes(routes!(get, update, delete_))
+        .routes(routes!(stats))
+}
+
+const MAX_ENVELOPE_NAME_LENGTH: usize = 100;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct EnvelopeWithSpend {
+    #[serde(flatten)]
+    pub envelope: Envelope,
+    /// Spent in the envelope so far, in the envelope's currency
+    pub spent: Decimal,
+    /// Negative once the budget is overspent, null without a budget
+    pub remaining: Option<Decimal>,
+    /// Currencies without an exchange rate to the envelope's currency, counted unconverted
+    pub unconverted_currencies: Vec<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct EnvelopeCategoryStats {
+    /// Null for uncategorized entries
+    pub category_name: Option<String>,
+    pub spent: Decimal,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct EnvelopeStats {
+    #[serde(flatten)]
+    pub summary: EnvelopeWithSpend,
+    pub percentage_used: Option<f64>,
+    /// Largest category first
+    pub categories: Vec<EnvelopeCategoryStats>,
+}
+
+async fn with_spend(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    envelope: Envelope,
+    rates: &RateTable,
+) -> Result<(EnvelopeWithSpend, Vec<EnvelopeCategoryStats>), AppError> {
+    let totals = EnvelopeRepo::totals_by_category(tx, envelope.uid, None).await?;
+    let mut by_category: HashMap<Option<String>, Vec<(String, Decimal)>> = HashMap::new();
+    for (category, currency, amount) in totals {
+        by_category
+            .entry(category)
+            .or_default()
+            .push((currency, amount));
+    }
+
+    let mut spent = Decimal::ZERO;
+    let mut unconverted_currencies: Vec<String> = Vec::new();
+    let mut categories = Vec::with_capacity(by_category.len());
+    for (category_name, totals) in by_category {
+        let (category_spent, unconverted) = rates.sum_in(&totals, &envelope.currency);
+        spent += category_spent;
+        for currency in unconverted {
+            if !unconverted_currencies.contains(&currency) {
+                unconverted_currencies.push(currency);
+            }
+        }
+        categories.push(EnvelopeCategoryStats {
+            category_name,
+            spent: category_spent,
+        });
+    }
+    categories.sort_by(|a, b| b.spent.cmp(&a.spent));
+
+    Ok((
+        EnvelopeWithSpend {
+            remaining: envelope.budget.map(|budget| budget - spent),
+            envelope,
+            spent,
+            unconverted_currencies,
+        },
+        categories,
+    ))
+}
+
+fn parse_name(name: &str) -> Result<String, AppError> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > MAX_ENVELOPE_NAME_LENGTH {
+        return Err(AppError::BadRequest(format!(
+            "name must be between 1 and {} characters",
+            MAX_ENVELOPE_NAME_LENGTH
+        )));
+    }
+    Ok(name.to_string())
+}
+
+fn validate_budget_and_dates(
+    budget: Option<Decimal>,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+) -> Result<(), AppError> {
+    if budget.is_some_and(|budget| budget < Decimal::ZERO) {
+        return Err(AppError::BadRequest(
+            "budget must be a non-negative number".to_string(),
+        ));
+    }
+    if start_date > end_date {
+        return Err(AppError::BadRequest(
+            "start_date must not be after end_date".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+// Rejects envelopes of another group, so entries cannot be filed under them
+pub async fn check_envelope_in_group(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    group_uid: Uuid,
+    envelope_uid: Option<Uuid>,
+) -> Result<(), AppError> {
+    let Some(envelope_uid) = envelope_uid else {
+        return Ok(());
+    };
+    let envelope = EnvelopeRepo::get(tx, envelope_uid).await?;
+    if envelope.group_uid != group_uid {
+        return Err(AppError::BadRequest(
+            "Envelope does not belong to the group".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[utoipa::path(get, path = "/groups/{group_uid}/envelopes", params(("group_uid" = Uuid, Path)), responses((status = 200, body = [EnvelopeWithSpend])), tag = "Envelopes", operation_id = "listEnvelopes", security(("bearerAuth" = [])))]
+pub async fn list(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(group_uid): Path<Uuid>,
+) -> Result<Json<Vec<EnvelopeWithSpend>>, AppError> {
+    let mut tx =
+        state.db_pool.begin().await.map_err(|e| {
+            AppError::from_sqlx_error(e, "beginning transaction for listing envelopes")
+        })?;
+    group_guard(&auth, group_uid, &mut tx, &state.membership_cache).await?;
+    let rates = ExchangeRateRepo::rate_table(&mut tx).await?;
+    let envelopes = EnvelopeRepo::list_by_group(&mut tx, group_uid).await?;
+    let mut res = Vec::with_capacity(envelopes.len());
+    for envelope in envelopes {
+        let (summary, _) = with_spend(&mut tx, envelope, &rates).await?;
+        res.push(summary);
+    }
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for listing envelopes")
+    })?;
+    Ok(Json(res))
+}
+
+#[utoipa::path(get, path = "/envelopes/{uid}", params(("uid" = Uuid, Path)), responses((status = 200, body = EnvelopeWithSpend)), tag = "Envelopes", operation_id = "getEnvelope", security(("bearerAuth" = [])))]
+pub async fn get(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(uid): Path<Uuid>,
+) -> Result<Json<EnvelopeWithSpend>, AppError> {
+    let mut tx =
+        state.db_pool.begin().await.map_err(|e| {
+            AppError::from_sqlx_error(e, "beginning transaction for getting envelope")
+        })?;
+    let envelope = EnvelopeRepo::get(&mut tx, uid).await?;
+    group_guard(&auth, envelope.group_uid, &mut tx, &state.membership_cache).await?;
+    let rates = ExchangeRateRepo::rate_table(&mut tx).await?;
+    let (res, _) = with_spend(&mut tx, envelope, &rates).await?;
+    tx.commit()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "committing transaction for getting envelope"))?;
+    Ok(Json(res))
+}
+
+// Spending of the envelope broken down by category, against its own budget
+#[utoipa::path(get, path = "/envelopes/{uid}/stats", params(("uid" = Uuid, Path)), responses((status = 200, body = EnvelopeStats)), tag = "Envelopes", operation_id = "getEnvelopeStats", security(("bearerAuth" = [])))]
+pub async fn stats(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(uid): Path<Uuid>,
+) -> Result<Json<EnvelopeStats>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for getting envelope stats")
+    })?;
+    let envelope = EnvelopeRepo::get(&mut tx, uid).await?;
+    group_guard(&auth, envelope.group_uid, &mut tx, &state.membership_cache).await?;
+    let rates = ExchangeRateRepo::rate_table(&mut tx).await?;
+    let (summary, categories) = with_spend(&mut tx, envelope, &rates).await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for getting envelope stats")
+    })?;
+    let percentage_used = summary
+        .envelope
+        .budget
+        .filter(|budget| *budget > Decimal::ZERO)
+        .map(|budget| {
+            (summary.spent / budget * Decimal::ONE_HUNDRED)
+                .round_dp(2)
+                .to_f64()
+                .unwrap_or_default()
+        });
+    Ok(Json(EnvelopeStats {
+        summary,
+        percentage_used,
+        categories,
+    }))
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct CreateEnvelopePayload {
+    /// Unique within the group, e.g. "Bali trip"
+    pub name: String,
+    pub start_date: NaiveDate,
+    /// Inclusive
+    pub end_date: NaiveDate,
+    pub budget: Option<Decimal>,
+    /// ISO 4217 code, defaults to the group's currency
+    pub currency: Option<String>,
+}
+
+#[utoipa::path(post, path = "/groups/{group_uid}/envelopes", params(("group_uid" = Uuid, Path)), request_body = CreateEnvelopePayload, responses((status = 200, body = EnvelopeWithSpend)), tag = "Envelopes", operation_id = "createEnvelope", security(("bearerAuth" = [])))]
+pub async fn create(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(group_uid): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<CreateEnvelopePayload>,
+) -> Result<Json<EnvelopeWithSpend>, AppError> {
+    let mut tx =
+        state.db_pool.begin().await.map_err(|e| {
+            AppError::from_sqlx_error(e, "beginning transaction for creating envelope")
+        })?;
+    group_guard(&auth, group_uid, &mut tx, &state.membership_cache).await?;
+    let name = parse_name(&payload.name)?;
+    validate_budget_and_dates(payload.budget, payload.start_date, payload.end_date)?;
+    let currency = parse_currency(payload.currency.as_deref())?;
+    let created = EnvelopeRepo::create(
+        &mut tx,
+        CreateEnvelopeDbPayload {
+            group_uid,
+            name,
+            start_date: payload.start_date,
+            end_date: payload.end_date,
+            budget: payload.budget,
+            currency,
+        },
+    )
+    .await?;
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::Envelope,
+        group_uid,
+        created.uid,
+        AuditChange::create(&created),
+    )
+    .await?;
+    let rates = ExchangeRateRepo::rate_table(&mut tx).await?;
+    let (res, _) = with_spend(&mut tx, created, &rates).await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for creating envelope")
+    })?;
+    Ok(Json(res))
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct UpdateEnvelopePayload {
+    pub name: Option<String>,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    pub budget: Option<Decimal>,
+    pub currency: Option<String>,
+}
+
+#[utoipa::path(put, path = "/envelopes/{uid}", params(("uid" = Uuid, Path)), request_body = UpdateEnvelopePayload, responses((status = 200, body = EnvelopeWithSpend)), tag = "Envelopes", operation_id = "updateEnvelope", security(("bearerAuth" = [])))]
+pub async fn update(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(uid): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<UpdateEnvelopePayload>,
+) -> Result<Json<EnvelopeWithSpend>, AppError> {
+    let name = payload.name.as_deref().map(parse_name).transpose()?;
+    let currency = parse_currency(payload.currency.as_deref())?;
+    let mut tx =
+        state.db_pool.begin().await.map_err(|e| {
+            AppError::from_sqlx_error(e, "beginning transaction for updating envelope")
+        })?;
+    let prev_rec = EnvelopeRepo::get(&mut tx, uid).await?;
+    group_guard(&auth, prev_rec.group_uid, &mut tx, &state.membership_cache).await?;
+    validate_budget_and_dates(
+        payload.budget,
+        payload.start_date.unwrap_or(prev_rec.start_date),
+        payload.end_date.unwrap_or(prev_rec.end_date),
+    )?;
+    let updated = EnvelopeRepo::update(
+        &mut tx,
+        uid,
+        UpdateEnvelopeDbPayload {
+            name,
+            start_date: payload.start_date,
+            end_date: payload.end_date,
+            budget: payload.budget,
+            currency,
+        },
+    )
+    .await?;
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::Envelope,
+        updated.group_uid,
+        updated.uid,
+        AuditChange::update(&prev_rec, &updated),
+    )
+    .await?;
+    let rates = ExchangeRateRepo::rate_table(&mut tx).await?;
+    let (res, _) = with_spend(&mut tx, updated, &rates).await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for updating envelope")
+    })?;
+    Ok(Json(res))
+}
+
+// Entries of the envelope are kept and count towards the regular cycle again
+#[utoipa::path(delete, path = "/envelopes/{uid}", params(("uid" = Uuid, Path)), responses((status = 200, body = DeleteResponse)), tag = "Envelopes", operation_id = "deleteEnvelope", security(("bearerAuth" = [])))]
+pub async fn delete_(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(uid): Path<Uuid>,
+) -> Result<Json<DeleteResponse>, AppError> {
+    let mut tx =
+        state.db_pool.begin().await.map_err(|e| {
+            AppError::from_sqlx_error(e, "beginning transaction for deleting envelope")
+        })?;
+    let prev_rec = EnvelopeRepo::get(&mut tx, uid).await?;
+    group_role_guard(
+        &auth,
+        prev_rec.group_uid,
+        &mut tx,
+        &state.membership_cache,
+        GroupRole::Admin,
+    )
+    .await?;
+    EnvelopeRepo::delete(&mut tx, uid).await?;
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::Envelope,
+        prev_rec.group_uid,
+        uid,
+        AuditChange::delete(&prev_rec),
+    )
+    .await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for deleting envelope")
+    })?;
+    Ok(Json(DeleteResponse { success: true }))
+}
@@ -1,79 +1,255 @@
+use std::collections::HashMap;
+
 use axum::{
     Json,
-    extract::{Extension, Path, State},
+    extract::{Extension, Multipart, Path, Query, State},
+    http::header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+    response::IntoResponse,
 };
-use serde::Deserialize;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
 use serde_json;
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
+use utoipa_axum::{router::OpenApiRouter, routes};
 use uuid::Uuid;
+use validator::Validate;
 
 use crate::{
-    auth::{AuthContext, group_guard::group_guard},
-    error::AppError,
-    middleware::tier::check_tier_limit,
+    auth::{
+        AuthContext,
+        group_guard::{group_guard, group_write_guard},
+    },
+    error::{AppError, DatabaseError, ErrorCode},
+    middleware::{
+        tier::{check_feature_access, check_tier_limit},
+        validated_json::{ValidatedJson, positive_amount},
+    },
     repos::{
+        audit_log::{AuditActor, AuditChange, AuditEntity, AuditRepo},
+        budget_alert::{BudgetAlertRepo, HardLimitAction},
+        category::CategoryRepo,
+        category_alias::CategoryAliasRepo,
+        closed_period::ClosedPeriodRepo,
+        expense_draft::ExpenseDraftRepo,
         expense_entry::{
-            CreateExpenseEntryDbPayload, ExpenseEntry, ExpenseEntryRepo,
-            UpdateExpenseEntryDbPayload,
+            CreateExpenseEntryDbPayload, ExpenseEntry, ExpenseEntryListFilter, ExpenseEntryRepo,
+            ExpenseEntrySearchResult, ExpenseEntrySort, UpdateExpenseEntryDbPayload,
         },
-        subscription::SubscriptionRepo,
+        product::ProductRepo,
+        receipt::{Receipt, ReceiptRepo, UpsertReceiptDbPayload},
+        subscription::{SubscriptionRepo, UserUsageRepo},
+        tag::TagRepo,
     },
-    types::AppState,
+    routes::{
+        currencies::parse_currency, envelopes::check_envelope_in_group, tags::parse_tag_names,
+    },
+    types::{AppState, PaginatedResponse},
+    utils::{
+        budget_limit::{self, NewSpend},
+        cursor::Cursor,
+        expense_import::{self, ImportRowError},
+    },
+    webhooks::{self, WebhookEvent},
 };
 
-pub fn router() -> axum::Router<AppState> {
-    axum::Router::new()
-        .route(
-            "/expense-entries",
-            axum::routing::post(create_expense_entry),
-        )
-        .route(
-            "/groups/{group_uid}/expense-entries",
-            axum::routing::get(list_expense_entries),
-        )
-        .route(
-            "/{uid}",
-            axum::routing::get(get_expense_entry)
-                .put(update_expense_entry)
-                .delete(delete_expense_entry),
-        )
+pub fn router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(create_expense_entry))
+        .routes(routes!(create_expense_entries_batch))
+        .routes(routes!(upload_receipt, get_receipt))
+        .routes(routes!(list_expense_entries))
+        .routes(routes!(search_expense_entries))
+        .routes(routes!(import_expense_entries))
+        .routes(routes!(
+            get_expense_entry,
+            update_expense_entry,
+            delete_expense_entry
+        ))
+}
+
+const DEFAULT_PER_PAGE: u32 = 20;
+const MAX_PER_PAGE: u32 = 100;
+
+#[derive(Debug, Default, Serialize, Deserialize, IntoParams)]
+pub struct ListExpenseEntriesQuery {
+    /// 1-based page number, defaults to 1
+    pub page: Option<u32>,
+    /// Page size, defaults to 20, at most 100
+    pub per_page: Option<u32>,
+    /// Only entries created on or after this date (YYYY-MM-DD)
+    pub from: Option<NaiveDate>,
+    /// Only entries created on or before this date (YYYY-MM-DD)
+    pub to: Option<NaiveDate>,
+    pub category_uid: Option<Uuid>,
+    /// Only entries carrying this tag
+    pub tag_uid: Option<Uuid>,
+    /// Only entries assigned to this envelope
+    pub envelope_uid: Option<Uuid>,
+    pub min_price: Option<Decimal>,
+    pub max_price: Option<Decimal>,
+    /// One of `created_at`, `price`, `product`; prefix with `-` for descending. Defaults to `-created_at`
+    pub sort: Option<String>,
+    /// `next_cursor` of the previous page, replaces `page` and stays fast deep into large groups.
+    /// Only for the `created_at` sorts
+    pub after: Option<String>,
 }
 
-#[utoipa::path(get, path = "/groups/{group_uid}/expense-entries", responses((status = 200, body = [ExpenseEntry])), tag = "Expense Entries", operation_id = "listExpenseEntries", security(("bearerAuth" = [])))]
+impl ListExpenseEntriesQuery {
+    fn into_filter(self) -> Result<(ExpenseEntryListFilter, u32, u32), AppError> {
+        let page = self.page.unwrap_or(1).max(1);
+        let per_page = self
+            .per_page
+            .unwrap_or(DEFAULT_PER_PAGE)
+            .clamp(1, MAX_PER_PAGE);
+
+        let sort = match self.sort.as_deref() {
+            Some(sort) => ExpenseEntrySort::parse(sort)
+                .ok_or_else(|| AppError::BadRequest(format!("Invalid sort: {}", sort)))?,
+            None => ExpenseEntrySort::default(),
+        };
+
+        let after = match self.after.as_deref() {
+            Some(after) => {
+                if !sort.supports_cursor() {
+                    return Err(AppError::BadRequest(
+                        "`after` only works with the `created_at` sorts".to_string(),
+                    ));
+                }
+                Some(
+                    Cursor::decode(after)
+                        .ok_or_else(|| AppError::BadRequest("Invalid cursor".to_string()))?,
+                )
+            }
+            None => None,
+        };
+
+        if let (Some(from), Some(to)) = (self.from, self.to) {
+            if from > to {
+                return Err(AppError::BadRequest(
+                    "`from` must not be after `to`".to_string(),
+                ));
+            }
+        }
+
+        let filter = ExpenseEntryListFilter {
+            from: self.from.map(|d| d.and_hms_opt(0, 0, 0).unwrap().and_utc()),
+            // `to` is inclusive, so stop at the start of the following day
+            to: self.to.map(|d| {
+                (d + Duration::days(1))
+                    .and_hms_opt(0, 0, 0)
+                    .unwrap()
+                    .and_utc()
+            }),
+            category_uid: self.category_uid,
+            tag_uid: self.tag_uid,
+            envelope_uid: self.envelope_uid,
+            min_price: self.min_price,
+            max_price: self.max_price,
+            sort,
+            // One extra row tells whether there is a next page
+            limit: per_page as i64 + 1,
+            offset: match after {
+                Some(_) => 0,
+                None => (page as i64 - 1) * per_page as i64,
+            },
+            after,
+        };
+        Ok((filter, page, per_page))
+    }
+}
+
+#[utoipa::path(get, path = "/groups/{group_uid}/expense-entries", params(("group_uid" = Uuid, Path), ListExpenseEntriesQuery), responses((status = 200, body = PaginatedResponse<ExpenseEntry>)), tag = "Expense Entries", operation_id = "listExpenseEntries", security(("bearerAuth" = [])))]
 pub async fn list_expense_entries(
     State(state): State<AppState>,
     Extension(auth): Extension<AuthContext>,
     Path(group_uid): Path<Uuid>,
-) -> Result<Json<Vec<ExpenseEntry>>, AppError> {
-    group_guard(&auth, group_uid, &state.db_pool).await?;
+    Query(query): Query<ListExpenseEntriesQuery>,
+) -> Result<Json<PaginatedResponse<ExpenseEntry>>, AppError> {
     let mut tx = state.db_pool.begin().await.map_err(|e| {
         AppError::from_sqlx_error(e, "beginning transaction for listing expense entries")
     })?;
-    let res = ExpenseEntryRepo::list_by_group(&mut tx, group_uid).await?;
+    group_guard(&auth, group_uid, &mut tx, &state.membership_cache).await?;
+    let (filter, page, per_page) = query.into_filter()?;
+    let (mut items, total) =
+        ExpenseEntryRepo::list_by_group_filtered(&mut tx, group_uid, &filter).await?;
+    let next_cursor = if items.len() > per_page as usize {
+        items.truncate(per_page as usize);
+        items
+            .last()
+            .filter(|_| filter.sort.supports_cursor())
+            .map(|entry| Cursor::new(entry.created_at, entry.uid).encode())
+    } else {
+        None
+    };
     tx.commit().await.map_err(|e| {
         AppError::from_sqlx_error(e, "committing transaction for listing expense entries")
     })?;
-    Ok(Json(res))
+    Ok(Json(PaginatedResponse {
+        items,
+        total,
+        page,
+        per_page,
+        next_cursor,
+    }))
 }
 
-#[derive(Debug, Deserialize, ToSchema)]
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
 pub struct CreateExpenseEntryPayload {
-    pub price: f64,
+    /// Greater than 0
+    #[validate(custom(function = "positive_amount"))]
+    pub price: Decimal,
+    /// ISO 4217 code, defaults to the group's currency
+    pub currency: Option<String>,
+    /// 1 to 255 characters
+    #[validate(length(min = 1, max = MAX_PRODUCT_LENGTH))]
     pub product: String,
     pub group_uid: Uuid,
     pub category_uid: Option<Uuid>,
+    /// Tag names, created in the group when they do not exist yet
+    pub tags: Option<Vec<String>>,
+    /// When the expense was made, defaults to now. Set it to log an earlier purchase in its own period
+    pub created_at: Option<DateTime<Utc>>,
+    /// Free text, at most 1000 characters
+    pub note: Option<String>,
+    /// Envelope (e.g. a trip) of the same group the entry counts against instead of the regular cycle
+    pub envelope_uid: Option<Uuid>,
+    /// Record the entry even though it goes over a budget with a hard limit in a group whose
+    /// `hard_limit_action` is `confirm`. Ignored by the batch endpoint
+    pub confirm_over_limit: Option<bool>,
+    /// Draft of the same group this entry confirms, it is removed once the entry is created.
+    /// Ignored by the batch endpoint
+    pub draft_uid: Option<Uuid>,
 }
 
+// Leeway for clients whose clock is a little ahead
+const MAX_CLOCK_SKEW_MINUTES: i64 = 5;
+
+const MAX_NOTE_LENGTH: usize = 1000;
+
+const MAX_PRODUCT_LENGTH: u64 = 255;
+
 #[utoipa::path(post, path = "/expense-entries", request_body = CreateExpenseEntryPayload, responses((status = 200, body = serde_json::Value)), tag = "Expense Entries", operation_id = "createExpenseEntry", security(("bearerAuth" = [])))]
 pub async fn create_expense_entry(
     State(state): State<AppState>,
     Extension(auth): Extension<AuthContext>,
-    Json(payload): Json<CreateExpenseEntryPayload>,
+    ValidatedJson(payload): ValidatedJson<CreateExpenseEntryPayload>,
 ) -> Result<Json<serde_json::Value>, AppError> {
-    group_guard(&auth, payload.group_uid, &state.db_pool).await?;
     let mut tx = state.db_pool.begin().await.map_err(|e| {
         AppError::from_sqlx_error(e, "beginning transaction for creating expense entry")
     })?;
+    group_write_guard(&auth, payload.group_uid, &mut tx, &state.membership_cache).await?;
+    let currency = parse_currency(payload.currency.as_deref())?;
+    let tag_names = parse_tag_names(payload.tags.as_deref().unwrap_or_default())?;
+    check_created_at(payload.created_at)?;
+    let note = parse_note(payload.note)?;
+    check_envelope_in_group(&mut tx, payload.group_uid, payload.envelope_uid).await?;
+    if let Some(draft_uid) = payload.draft_uid {
+        let draft = ExpenseDraftRepo::get(&mut tx, draft_uid).await?;
+        if draft.group_uid != payload.group_uid {
+            return Err(AppError::NotFound("Expense draft not found".to_string()));
+        }
+    }
 
     // Get user's subscription
     let subscription = SubscriptionRepo::get_by_user(&mut tx, auth.user_uid).await?;
@@ -88,16 +264,69 @@ pub async fn create_expense_entry(
         usage_payload.total_expenses,
     )?;
 
+    // Entries in an envelope do not count against the group's budgets
+    let breaches = match (payload.category_uid, payload.envelope_uid) {
+        (Some(category_uid), None) => {
+            let spend = NewSpend {
+                category_uid,
+                amount: payload.price,
+                currency: currency.clone(),
+                date: payload.created_at.unwrap_or_else(Utc::now).date_naive(),
+            };
+            budget_limit::find_breaches(&mut tx, payload.group_uid, &[spend]).await?
+        }
+        _ => Vec::new(),
+    };
+    if !breaches.is_empty() && !payload.confirm_over_limit.unwrap_or(false) {
+        let settings = BudgetAlertRepo::get_settings(&mut tx, payload.group_uid).await?;
+        if settings.hard_limit_action() == HardLimitAction::Confirm {
+            return Err(AppError::coded(
+                ErrorCode::BudgetHardLimitExceeded,
+                "The entry goes over a budget with a hard limit, send it again with confirm_over_limit to record it",
+            )
+            .with_details(serde_json::json!({ "breaches": breaches })));
+        }
+    }
+
     let created = ExpenseEntryRepo::create_expense_entry(
         &mut tx,
         CreateExpenseEntryDbPayload {
             price: payload.price,
+            currency,
             product: payload.product,
             group_uid: payload.group_uid,
             category_uid: payload.category_uid,
+            created_by: auth.user_uid.to_string(),
+            created_by_user_uid: Some(auth.user_uid),
+            created_at: payload.created_at,
+            note,
+            envelope_uid: payload.envelope_uid,
         },
     )
     .await?;
+    ProductRepo::learn(&mut tx, &created).await?;
+    if !tag_names.is_empty() {
+        tag_entry(&mut tx, created.group_uid, created.uid, &tag_names).await?;
+    }
+    if let Some(draft_uid) = payload.draft_uid {
+        ExpenseDraftRepo::delete(&mut tx, draft_uid).await?;
+    }
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::ExpenseEntry,
+        created.group_uid,
+        created.uid,
+        AuditChange::create(&created),
+    )
+    .await?;
+    webhooks::dispatch(
+        &mut tx,
+        created.group_uid,
+        WebhookEvent::ExpenseCreated,
+        &created,
+    )
+    .await?;
 
     // Check if near limit and include upgrade warning in response
     let limits = subscription.get_tier().limits();
@@ -123,12 +352,226 @@ pub async fn create_expense_entry(
         );
     }
 
+    if !breaches.is_empty() {
+        let warning = serde_json::to_value(&breaches).unwrap();
+        if let serde_json::Value::Object(ref mut map) = response_data {
+            map.insert("budget_limit_warning".to_string(), warning);
+        }
+    }
+
     tx.commit().await.map_err(|e| {
         AppError::from_sqlx_error(e, "committing transaction for creating expense entry")
     })?;
     Ok(Json(response_data))
 }
 
+// Trimmed note, None when blank
+fn parse_note(note: Option<String>) -> Result<Option<String>, AppError> {
+    let Some(note) = note.map(|note| note.trim().to_string()) else {
+        return Ok(None);
+    };
+    if note.chars().count() > MAX_NOTE_LENGTH {
+        return Err(AppError::BadRequest(format!(
+            "note must be at most {} characters",
+            MAX_NOTE_LENGTH
+        )));
+    }
+    Ok(Some(note).filter(|note| !note.is_empty()))
+}
+
+fn check_created_at(created_at: Option<DateTime<Utc>>) -> Result<(), AppError> {
+    if created_at.is_some_and(|created_at| {
+        created_at > Utc::now() + Duration::minutes(MAX_CLOCK_SKEW_MINUTES)
+    }) {
+        return Err(AppError::BadRequest(
+            "created_at cannot be in the future".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+const MAX_BATCH_ENTRIES: u64 = 100;
+
+#[derive(Debug, Deserialize, ToSchema, Validate)]
+pub struct CreateExpenseEntriesBatchPayload {
+    /// At most 100 entries, each shaped like a single create
+    #[validate(length(min = 1, max = MAX_BATCH_ENTRIES))]
+    pub entries: Vec<CreateExpenseEntryPayload>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ExpenseEntryBatchResult {
+    /// Position of the entry in the request
+    pub index: usize,
+    /// Set when the entry was created
+    pub uid: Option<Uuid>,
+    /// Set when the entry failed validation and was skipped
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CreateExpenseEntriesBatchResponse {
+    pub created: usize,
+    pub results: Vec<ExpenseEntryBatchResult>,
+}
+
+// Error text for a per-item result, without the `bad request:` prefix
+fn batch_error_message(err: AppError) -> String {
+    match err {
+        AppError::BadRequest(msg) | AppError::NotFound(msg) => msg,
+        other => other.to_string(),
+    }
+}
+
+#[utoipa::path(post, path = "/expense-entries/batch", request_body = CreateExpenseEntriesBatchPayload, responses((status = 200, body = CreateExpenseEntriesBatchResponse)), tag = "Expense Entries", operation_id = "createExpenseEntriesBatch", security(("bearerAuth" = [])))]
+pub async fn create_expense_entries_batch(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    ValidatedJson(payload): ValidatedJson<CreateExpenseEntriesBatchPayload>,
+) -> Result<Json<CreateExpenseEntriesBatchResponse>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(
+            e,
+            "beginning transaction for creating expense entries batch",
+        )
+    })?;
+    // Access is checked once per group, a group the caller cannot write to fails the whole batch
+    let mut group_uids: Vec<Uuid> = payload.entries.iter().map(|e| e.group_uid).collect();
+    group_uids.sort();
+    group_uids.dedup();
+    for group_uid in group_uids {
+        group_write_guard(&auth, group_uid, &mut tx, &state.membership_cache).await?;
+    }
+
+    let mut results = Vec::with_capacity(payload.entries.len());
+    let mut valid = Vec::new();
+    for (index, entry) in payload.entries.into_iter().enumerate() {
+        // Entries are not validated with the batch, one failing its rules is only skipped
+        let parsed = entry.validate().map_err(AppError::from).and_then(|_| {
+            check_created_at(entry.created_at)?;
+            let currency = parse_currency(entry.currency.as_deref())?;
+            let tag_names = parse_tag_names(entry.tags.as_deref().unwrap_or_default())?;
+            let note = parse_note(entry.note)?;
+            Ok((currency, tag_names, note))
+        });
+        match parsed {
+            Ok((currency, tag_names, note)) => {
+                results.push(ExpenseEntryBatchResult {
+                    index,
+                    uid: None,
+                    error: None,
+                });
+                valid.push((
+                    index,
+                    CreateExpenseEntryDbPayload {
+                        price: entry.price,
+                        currency,
+                        product: entry.product,
+                        group_uid: entry.group_uid,
+                        category_uid: entry.category_uid,
+                        created_by: auth.user_uid.to_string(),
+                        created_by_user_uid: Some(auth.user_uid),
+                        created_at: entry.created_at,
+                        note,
+                        envelope_uid: entry.envelope_uid,
+                    },
+                    tag_names,
+                ));
+            }
+            Err(err) => results.push(ExpenseEntryBatchResult {
+                index,
+                uid: None,
+                error: Some(batch_error_message(err)),
+            }),
+        }
+    }
+
+    if valid.is_empty() {
+        return Ok(Json(CreateExpenseEntriesBatchResponse {
+            created: 0,
+            results,
+        }));
+    }
+
+    // One limit check for the whole batch, as if the entries were created one by one
+    let subscription = SubscriptionRepo::get_by_user(&mut tx, auth.user_uid).await?;
+    let usage_payload = UserUsageRepo::calculate_current_usage(&mut tx, auth.user_uid).await?;
+    check_tier_limit(
+        &subscription,
+        "expenses_per_month",
+        usage_payload.total_expenses + valid.len() as i32 - 1,
+    )?;
+
+    let actor = AuditActor::from_auth(&auth);
+    let mut created_count = 0;
+    for (index, db_payload, tag_names) in valid {
+        if let Err(err) =
+            check_envelope_in_group(&mut tx, db_payload.group_uid, db_payload.envelope_uid).await
+        {
+            results[index].error = Some(batch_error_message(err));
+            continue;
+        }
+        let created = match ExpenseEntryRepo::create_expense_entry(&mut tx, db_payload).await {
+            Ok(created) => created,
+            // Checked before the insert, the rest of the batch can still go in
+            Err(err @ DatabaseError::PeriodClosed(_)) => {
+                results[index].error = Some(err.to_string());
+                continue;
+            }
+            Err(err) => return Err(err.into()),
+        };
+        ProductRepo::learn(&mut tx, &created).await?;
+        if !tag_names.is_empty() {
+            tag_entry(&mut tx, created.group_uid, created.uid, &tag_names).await?;
+        }
+        AuditRepo::record(
+            &mut tx,
+            &actor,
+            AuditEntity::ExpenseEntry,
+            created.group_uid,
+            created.uid,
+            AuditChange::create(&created),
+        )
+        .await?;
+        webhooks::dispatch(
+            &mut tx,
+            created.group_uid,
+            WebhookEvent::ExpenseCreated,
+            &created,
+        )
+        .await?;
+        results[index].uid = Some(created.uid);
+        created_count += 1;
+    }
+
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(
+            e,
+            "committing transaction for creating expense entries batch",
+        )
+    })?;
+    Ok(Json(CreateExpenseEntriesBatchResponse {
+        created: created_count,
+        results,
+    }))
+}
+
+// Replaces the entry's tags with the given (normalized) names
+async fn tag_entry(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    group_uid: Uuid,
+    expense_uid: Uuid,
+    tag_names: &[String],
+) -> Result<(), AppError> {
+    let tag_uids: Vec<Uuid> = TagRepo::get_or_create(tx, group_uid, tag_names)
+        .await?
+        .into_iter()
+        .map(|tag| tag.uid)
+        .collect();
+    TagRepo::set_for_entry(tx, expense_uid, &tag_uids).await?;
+    Ok(())
+}
+
 #[utoipa::path(get, path = "/expense-entries/{uid}", params(("uid" = Uuid, Path)), responses((status = 200, body = ExpenseEntry)), tag = "Expense Entries", operation_id = "getExpenseEntry", security(("bearerAuth" = [])))]
 pub async fn get_expense_entry(
     State(state): State<AppState>,
@@ -139,42 +582,89 @@ pub async fn get_expense_entry(
         AppError::from_sqlx_error(e, "beginning transaction for getting expense entry")
     })?;
     let rec = ExpenseEntryRepo::get(&mut tx, uid).await?;
-    group_guard(&auth, rec.group_uid, &state.db_pool).await?;
+    group_guard(&auth, rec.group_uid, &mut tx, &state.membership_cache).await?;
     tx.commit().await.map_err(|e| {
         AppError::from_sqlx_error(e, "committing transaction for getting expense entry")
     })?;
     Ok(Json(rec))
 }
 
-#[derive(Debug, Deserialize, ToSchema)]
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema, Validate)]
 pub struct UpdateExpenseEntryPayload {
-    pub price: Option<f64>,
+    #[validate(custom(function = "positive_amount"))]
+    pub price: Option<Decimal>,
+    pub currency: Option<String>,
+    #[validate(length(min = 1, max = MAX_PRODUCT_LENGTH))]
     pub product: Option<String>,
     pub category_uid: Option<Uuid>,
+    /// Replaces the entry's tags when set, an empty list removes them all
+    pub tags: Option<Vec<String>>,
+    /// Replaces the note when set, an empty string removes it
+    pub note: Option<String>,
+    /// Moves the entry to this envelope of the same group
+    pub envelope_uid: Option<Uuid>,
+    /// The entry's `updated_at` as last read. When it changed since, nothing is updated and
+    /// `409 CONCURRENT_MODIFICATION` returns the current entry as `details.current`
+    pub expected_updated_at: Option<DateTime<Utc>>,
 }
 
-#[utoipa::path(put, path = "/expense-entries/{uid}", params(("uid" = Uuid, Path)), request_body = UpdateExpenseEntryPayload, responses((status = 200, body = ExpenseEntry)), tag = "Expense Entries", operation_id = "updateExpenseEntry", security(("bearerAuth" = [])))]
+#[utoipa::path(put, path = "/expense-entries/{uid}", params(("uid" = Uuid, Path)), request_body = UpdateExpenseEntryPayload, responses((status = 200, body = ExpenseEntry), (status = 409, description = "Changed by someone else since `expected_updated_at`")), tag = "Expense Entries", operation_id = "updateExpenseEntry", security(("bearerAuth" = [])))]
 pub async fn update_expense_entry(
     State(state): State<AppState>,
     Extension(auth): Extension<AuthContext>,
     Path(uid): Path<Uuid>,
-    Json(payload): Json<UpdateExpenseEntryPayload>,
+    ValidatedJson(payload): ValidatedJson<UpdateExpenseEntryPayload>,
 ) -> Result<Json<ExpenseEntry>, AppError> {
+    let currency = parse_currency(payload.currency.as_deref())?;
+    let tag_names = payload.tags.as_deref().map(parse_tag_names).transpose()?;
+    // An empty string survives parsing so the repo knows to clear the note
+    let note = match payload.note {
+        Some(note) => Some(parse_note(Some(note))?.unwrap_or_default()),
+        None => None,
+    };
     let mut tx = state.db_pool.begin().await.map_err(|e| {
         AppError::from_sqlx_error(e, "beginning transaction for updating expense entry")
     })?;
     let prev_rec = ExpenseEntryRepo::get(&mut tx, uid).await?;
-    group_guard(&auth, prev_rec.group_uid, &state.db_pool).await?;
+    group_guard(&auth, prev_rec.group_uid, &mut tx, &state.membership_cache).await?;
+    check_envelope_in_group(&mut tx, prev_rec.group_uid, payload.envelope_uid).await?;
     let updated = ExpenseEntryRepo::update(
         &mut tx,
         uid,
         UpdateExpenseEntryDbPayload {
             price: payload.price,
+            currency,
             product: payload.product,
             category_uid: payload.category_uid,
+            note,
+            envelope_uid: payload.envelope_uid,
+            expected_updated_at: payload.expected_updated_at,
         },
     )
     .await?;
+    if let Some(category_uid) = payload.category_uid {
+        ProductRepo::learn_category(&mut tx, updated.group_uid, &updated.product, category_uid)
+            .await?;
+    }
+    if let Some(tag_names) = tag_names {
+        tag_entry(&mut tx, updated.group_uid, updated.uid, &tag_names).await?;
+    }
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::ExpenseEntry,
+        updated.group_uid,
+        updated.uid,
+        AuditChange::update(&prev_rec, &updated),
+    )
+    .await?;
+    webhooks::dispatch(
+        &mut tx,
+        updated.group_uid,
+        WebhookEvent::ExpenseUpdated,
+        &updated,
+    )
+    .await?;
     tx.commit().await.map_err(|e| {
         AppError::from_sqlx_error(e, "committing transaction for updating expense entry")
     })?;
@@ -191,10 +681,356 @@ pub async fn delete_expense_entry(
         AppError::from_sqlx_error(e, "beginning transaction for deleting expense entry")
     })?;
     let prev_rec = ExpenseEntryRepo::get(&mut tx, uid).await?;
-    group_guard(&auth, prev_rec.group_uid, &state.db_pool).await?;
-    ExpenseEntryRepo::delete(&mut tx, uid).await?;
+    group_guard(&auth, prev_rec.group_uid, &mut tx, &state.membership_cache).await?;
+    ExpenseEntryRepo::soft_delete(&mut tx, uid).await?;
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::ExpenseEntry,
+        prev_rec.group_uid,
+        uid,
+        AuditChange::delete(&prev_rec),
+    )
+    .await?;
+    webhooks::dispatch(
+        &mut tx,
+        prev_rec.group_uid,
+        WebhookEvent::ExpenseDeleted,
+        &prev_rec,
+    )
+    .await?;
     tx.commit().await.map_err(|e| {
         AppError::from_sqlx_error(e, "committing transaction for deleting expense entry")
     })?;
     Ok(())
 }
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ImportExpenseEntriesQuery {
+    /// Validate the file and report per-row errors without inserting anything
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+// Documents the multipart body; the handler reads the `file` field directly
+#[derive(ToSchema)]
+#[allow(dead_code)]
+pub struct ImportExpenseEntriesForm {
+    /// CSV or XLSX file with a header row (product, price, optional category and date)
+    #[schema(value_type = String, format = Binary)]
+    pub file: Vec<u8>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ImportExpenseEntriesResponse {
+    pub dry_run: bool,
+    pub total_rows: usize,
+    pub valid_rows: usize,
+    pub imported: usize,
+    pub errors: Vec<ImportRowError>,
+}
+
+const DEFAULT_SEARCH_LIMIT: u32 = 20;
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct SearchExpenseEntriesQuery {
+    /// Search term matched against the product name
+    pub q: String,
+    /// Maximum number of results, defaults to 20, at most 100
+    pub limit: Option<u32>,
+}
+
+#[utoipa::path(get, path = "/groups/{group_uid}/expense-entries/search", params(("group_uid" = Uuid, Path), SearchExpenseEntriesQuery), responses((status = 200, body = [ExpenseEntrySearchResult])), tag = "Expense Entries", operation_id = "searchExpenseEntries", security(("bearerAuth" = [])))]
+pub async fn search_expense_entries(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(group_uid): Path<Uuid>,
+    Query(query): Query<SearchExpenseEntriesQuery>,
+) -> Result<Json<Vec<ExpenseEntrySearchResult>>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for searching expense entries")
+    })?;
+    group_guard(&auth, group_uid, &mut tx, &state.membership_cache).await?;
+    let term = query.q.trim();
+    if term.is_empty() {
+        return Err(AppError::BadRequest("q must not be empty".to_string()));
+    }
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_SEARCH_LIMIT)
+        .clamp(1, MAX_PER_PAGE);
+    let res = ExpenseEntryRepo::search_by_group(&mut tx, group_uid, term, limit as i64).await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for searching expense entries")
+    })?;
+    Ok(Json(res))
+}
+
+#[utoipa::path(post, path = "/groups/{group_uid}/expense-entries/import", params(("group_uid" = Uuid, Path), ImportExpenseEntriesQuery), request_body(content = ImportExpenseEntriesForm, content_type = "multipart/form-data"), responses((status = 200, body = ImportExpenseEntriesResponse)), tag = "Expense Entries", operation_id = "importExpenseEntries", security(("bearerAuth" = [])))]
+pub async fn import_expense_entries(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(group_uid): Path<Uuid>,
+    Query(query): Query<ImportExpenseEntriesQuery>,
+    mut multipart: Multipart,
+) -> Result<Json<ImportExpenseEntriesResponse>, AppError> {
+    let mut table = None;
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| AppError::BadRequest(format!("Invalid multipart body: {}", e)))?
+    {
+        if field.name() != Some("file") {
+            continue;
+        }
+        let is_xlsx = field
+            .file_name()
+            .is_some_and(|name| name.to_lowercase().ends_with(".xlsx"));
+        let bytes = field
+            .bytes()
+            .await
+            .map_err(|e| AppError::BadRequest(format!("Failed to read file: {}", e)))?;
+        let parsed = if is_xlsx {
+            expense_import::read_xlsx(&bytes)
+        } else {
+            expense_import::read_csv(&bytes)
+        };
+        table = Some(parsed.map_err(|e| AppError::BadRequest(e.to_string()))?);
+    }
+
+    let Some(table) = table else {
+        return Err(AppError::BadRequest(
+            "Missing `file` field in multipart body".to_string(),
+        ));
+    };
+    let (rows, mut errors) =
+        expense_import::parse_rows(&table).map_err(|e| AppError::BadRequest(e.to_string()))?;
+
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for importing expense entries")
+    })?;
+    group_write_guard(&auth, group_uid, &mut tx, &state.membership_cache).await?;
+
+    // Resolve category names and aliases the same way chat commands do
+    let mut category_map: HashMap<String, Uuid> = HashMap::new();
+    for category in CategoryRepo::list_by_group(&mut tx, group_uid).await? {
+        category_map.insert(category.name.to_lowercase(), category.uid);
+    }
+    for alias in CategoryAliasRepo::list_by_group(&mut tx, group_uid).await? {
+        category_map.insert(alias.alias.to_lowercase(), alias.category_uid);
+    }
+
+    let mut payloads = Vec::new();
+    for row in rows {
+        let category_uid = match &row.category {
+            Some(name) => match category_map.get(&name.to_lowercase()) {
+                Some(uid) => Some(*uid),
+                None => {
+                    errors.push(ImportRowError {
+                        row: row.row,
+                        message: format!("Unknown category: {}", name),
+                    });
+                    continue;
+                }
+            },
+            None => None,
+        };
+        payloads.push(CreateExpenseEntryDbPayload {
+            price: row.price,
+            currency: None,
+            product: row.product,
+            group_uid,
+            category_uid,
+            created_by: auth.user_uid.to_string(),
+            created_by_user_uid: Some(auth.user_uid),
+            created_at: row.created_at,
+            note: None,
+            envelope_uid: None,
+        });
+    }
+    errors.sort_by_key(|e| e.row);
+
+    let mut response = ImportExpenseEntriesResponse {
+        dry_run: query.dry_run,
+        total_rows: payloads.len() + errors.len(),
+        valid_rows: payloads.len(),
+        imported: 0,
+        errors,
+    };
+
+    // All-or-nothing: a file with any invalid row is not imported
+    if query.dry_run || !response.errors.is_empty() || payloads.is_empty() {
+        return Ok(Json(response));
+    }
+
+    let subscription = SubscriptionRepo::get_by_user(&mut tx, auth.user_uid).await?;
+    let usage_payload = UserUsageRepo::calculate_current_usage(&mut tx, auth.user_uid).await?;
+    check_tier_limit(
+        &subscription,
+        "expenses_per_month",
+        usage_payload.total_expenses + payloads.len() as i32 - 1,
+    )?;
+
+    let actor = AuditActor::from_auth(&auth);
+    for payload in payloads {
+        let created = ExpenseEntryRepo::create_expense_entry(&mut tx, payload).await?;
+        ProductRepo::learn(&mut tx, &created).await?;
+        AuditRepo::record(
+            &mut tx,
+            &actor,
+            AuditEntity::ExpenseEntry,
+            group_uid,
+            created.uid,
+            AuditChange::create(&created),
+        )
+        .await?;
+        webhooks::dispatch(&mut tx, group_uid, WebhookEvent::ExpenseCreated, &created).await?;
+        response.imported += 1;
+    }
+
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for importing expense entries")
+    })?;
+    Ok(Json(response))
+}
+
+const MAX_RECEIPT_BYTES: usize = 5 * 1024 * 1024;
+const RECEIPT_CONTENT_TYPES: &[&str] =
+    &["image/jpeg", "image/png", "image/webp", "application/pdf"];
+
+// Documents the multipart body; the handler reads the `file` field directly
+#[derive(ToSchema)]
+#[allow(dead_code)]
+pub struct UploadReceiptForm {
+    /// JPEG, PNG, WebP or PDF, at most 5 MB
+    #[schema(value_type = String, format = Binary)]
+    pub file: Vec<u8>,
+}
+
+#[utoipa::path(post, path = "/expense-entries/{uid}/receipt", params(("uid" = Uuid, Path)), request_body(content = UploadReceiptForm, content_type = "multipart/form-data"), responses((status = 200, body = Receipt)), tag = "Expense Entries", operation_id = "uploadExpenseReceipt", security(("bearerAuth" = [])))]
+pub async fn upload_receipt(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(uid): Path<Uuid>,
+    mut multipart: Multipart,
+) -> Result<Json<Receipt>, AppError> {
+    let mut tx =
+        state.db_pool.begin().await.map_err(|e| {
+            AppError::from_sqlx_error(e, "beginning transaction for uploading receipt")
+        })?;
+    let entry = ExpenseEntryRepo::get(&mut tx, uid).await?;
+    group_guard(&auth, entry.group_uid, &mut tx, &state.membership_cache).await?;
+    // The receipt is part of the entry, it is locked along with it
+    ClosedPeriodRepo::ensure_entry_open(&mut tx, uid).await?;
+    let subscription = SubscriptionRepo::get_by_user(&mut tx, auth.user_uid).await?;
+    check_feature_access(&subscription, "receipts")?;
+
+    let mut upload = None;
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| AppError::BadRequest(format!("Invalid multipart body: {}", e)))?
+    {
+        if field.name() != Some("file") {
+            continue;
+        }
+        let file_name = field.file_name().map(|name| name.to_string());
+        let content_type = field.content_type().unwrap_or_default().to_string();
+        let bytes = field
+            .bytes()
+            .await
+            .map_err(|e| AppError::BadRequest(format!("Failed to read file: {}", e)))?;
+        upload = Some((file_name, content_type, bytes));
+    }
+    let Some((file_name, content_type, bytes)) = upload else {
+        return Err(AppError::BadRequest(
+            "Missing `file` field in multipart body".to_string(),
+        ));
+    };
+    if !RECEIPT_CONTENT_TYPES.contains(&content_type.as_str()) {
+        return Err(AppError::BadRequest(format!(
+            "Unsupported receipt type {:?}, expected JPEG, PNG, WebP or PDF",
+            content_type
+        )));
+    }
+    if bytes.is_empty() || bytes.len() > MAX_RECEIPT_BYTES {
+        return Err(AppError::BadRequest(format!(
+            "Receipt must be between 1 byte and {} MB",
+            MAX_RECEIPT_BYTES / (1024 * 1024)
+        )));
+    }
+
+    let previous = ReceiptRepo::get_by_expense(&mut tx, uid).await?;
+    let storage_key = format!("receipts/{}/{}", entry.group_uid, Uuid::new_v4());
+    let size_bytes = bytes.len() as i64;
+    state
+        .file_storage
+        .put(&storage_key, &content_type, bytes.to_vec())
+        .await
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to store receipt: {}", e)))?;
+    let receipt = ReceiptRepo::upsert(
+        &mut tx,
+        UpsertReceiptDbPayload {
+            expense_uid: uid,
+            storage: state.file_storage.name().to_string(),
+            storage_key,
+            file_name,
+            content_type,
+            size_bytes,
+            uploaded_by_user_uid: Some(auth.user_uid),
+        },
+    )
+    .await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for uploading receipt")
+    })?;
+
+    // The replaced file is only removed once the new one is recorded
+    if let Some(previous) = previous.filter(|p| p.storage == state.file_storage.name()) {
+        if let Err(e) = state.file_storage.delete(&previous.storage_key).await {
+            tracing::warn!(
+                "Failed to delete replaced receipt {}: {}",
+                previous.storage_key,
+                e
+            );
+        }
+    }
+    Ok(Json(receipt))
+}
+
+#[utoipa::path(get, path = "/expense-entries/{uid}/receipt", params(("uid" = Uuid, Path)), responses((status = 200, description = "The receipt file", content_type = "application/octet-stream")), tag = "Expense Entries", operation_id = "getExpenseReceipt", security(("bearerAuth" = [])))]
+pub async fn get_receipt(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(uid): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut tx =
+        state.db_pool.begin().await.map_err(|e| {
+            AppError::from_sqlx_error(e, "beginning transaction for getting receipt")
+        })?;
+    let entry = ExpenseEntryRepo::get(&mut tx, uid).await?;
+    group_guard(&auth, entry.group_uid, &mut tx, &state.membership_cache).await?;
+    let receipt = ReceiptRepo::get_by_expense(&mut tx, uid)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Receipt not found".to_string()))?;
+    tx.commit()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "committing transaction for getting receipt"))?;
+
+    let bytes = state
+        .file_storage
+        .get(&receipt.storage_key)
+        .await
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to read receipt: {}", e)))?;
+    let disposition = match &receipt.file_name {
+        Some(name) => format!("inline; filename=\"{}\"", name.replace('"', "")),
+        None => "inline".to_string(),
+    };
+    Ok((
+        [
+            (CONTENT_TYPE, receipt.content_type),
+            (CONTENT_DISPOSITION, disposition),
+        ],
+        bytes,
+    ))
+}
@@ -1,34 +1,40 @@
 use axum::{
-    extract::{Path, State}, Extension, Json
+    extract::{Path, Query, State}, Extension, Json
 };
 use serde::Deserialize;
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
+use utoipa_axum::{router::OpenApiRouter, routes};
 use uuid::Uuid;
 use validator::Validate;
 
 use crate::{
-    auth::{ group_guard::group_guard, AuthContext}, error::AppError,
-    middleware::tier::check_tier_limit,
+    auth::{ group_guard::{group_guard, group_role_guard}, AuthContext, AuthSource}, error::{AppError, DatabaseError, ErrorCode},
+    middleware::{tier::check_tier_limit, validated_json::ValidatedJson},
     repos::{
+        audit_log::{AuditActor, AuditChange, AuditEntity, AuditLog, AuditRepo},
         expense_group::{
          CreateExpenseGroupDbPayload, ExpenseGroup, ExpenseGroupRepo, UpdateExpenseGroupDbPayload
         },
+        expense_group_member::GroupRole,
         subscription::SubscriptionRepo,
     },
-    types::{AppState, DeleteResponse}
+    routes::currencies::parse_currency,
+    types::{AppState, DeleteResponse, PaginatedResponse},
+    utils::{currency::DEFAULT_CURRENCY, cursor::Cursor},
 };
 
-pub fn router() -> axum::Router<AppState> {
-    axum::Router::new()
-        .route("/expense-groups", axum::routing::get(list).post(create))
-        .route(
-            "/expense-groups/{uid}",
-            axum::routing::get(get).put(update).delete(delete_),
-        )
+pub fn router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(list, create))
+        .routes(routes!(get, update, delete_))
+        .routes(routes!(restore))
+        .routes(routes!(archive))
+        .routes(routes!(unarchive))
+        .routes(routes!(list_audit_logs))
 }
 
 /**
- * Get all expense groups for the authenticated user
+ * Get all expense groups the authenticated user owns or is a member of
  */
 #[utoipa::path(
     get, 
@@ -46,7 +52,7 @@ pub async fn list(State(state): State<AppState>,
         .begin()
         .await
         .map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for listing expense groups"))?;
-    let res = ExpenseGroupRepo::get_all_by_owner(&mut tx, auth.user_uid).await?;
+    let res = ExpenseGroupRepo::get_all_by_member(&mut tx, auth.user_uid).await?;
     tx.commit()
         .await
         .map_err(|e| AppError::from_sqlx_error(e, "committing transaction for listing expense groups"))?;
@@ -67,12 +73,12 @@ pub async fn get(
     Path(uid): Path<Uuid>,
     Extension(auth): Extension<AuthContext>,
 ) -> Result<Json<ExpenseGroup>, AppError> {
-    group_guard(&auth, uid, &state.db_pool).await?;
     let mut tx = state
         .db_pool
         .begin()
         .await
         .map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for getting expense group"))?;
+    group_guard(&auth, uid, &mut tx, &state.membership_cache).await?;
     let res = ExpenseGroupRepo::get(&mut tx, uid).await?;
     tx.commit()
         .await
@@ -85,6 +91,8 @@ pub struct CreateExpenseGroupPayload {
     pub name: String,
     #[validate(range(min = 1, max = 28))]
     pub start_over_date: i16,
+    /// ISO 4217 code, defaults to IDR
+    pub currency: Option<String>,
 }
 
 #[derive(Deserialize, serde::Serialize, ToSchema, Validate)]
@@ -92,6 +100,7 @@ pub struct UpdateExpenseGroupPayload {
     pub name: Option<String>,
     #[validate(range(min = 1, max = 28))]
     pub start_over_date: Option<i16>,
+    pub currency: Option<String>,
 }
 
 // TODO: infer owner from auth context
@@ -107,9 +116,10 @@ pub struct UpdateExpenseGroupPayload {
 pub async fn create(
     State(state): State<AppState>,
     Extension(auth): Extension<AuthContext>,
-    Json(payload): Json<CreateExpenseGroupPayload>,
+    ValidatedJson(payload): ValidatedJson<CreateExpenseGroupPayload>,
 ) -> Result<Json<ExpenseGroup>, AppError> {
-    payload.validate().map_err(|e| AppError::BadRequest(e.to_string()))?;
+    let currency = parse_currency(payload.currency.as_deref())?
+        .unwrap_or_else(|| DEFAULT_CURRENCY.to_string());
 
     let mut tx = state
         .db_pool
@@ -130,9 +140,19 @@ pub async fn create(
             name: payload.name,
             owner: auth.user_uid, // Use authenticated user as owner
             start_over_date: payload.start_over_date,
+            currency,
         },
     )
     .await?;
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::ExpenseGroup,
+        created.uid,
+        created.uid,
+        AuditChange::create(&created),
+    )
+    .await?;
     tx.commit()
         .await
         .map_err(|e| AppError::from_sqlx_error(e, "committing transaction for creating expense group"))?;
@@ -153,33 +173,43 @@ pub async fn update(
     State(state): State<AppState>,
     Extension(auth): Extension<AuthContext>,
     Path(uid): Path<Uuid>,
-    Json(payload): Json<UpdateExpenseGroupPayload>,
+    ValidatedJson(payload): ValidatedJson<UpdateExpenseGroupPayload>,
 ) -> Result<Json<ExpenseGroup>, AppError> {
-    payload.validate().map_err(|e| AppError::BadRequest(e.to_string()))?;
-    group_guard(&auth, uid, &state.db_pool).await?;
+    let currency = parse_currency(payload.currency.as_deref())?;
     let mut tx = state
         .db_pool
         .begin()
         .await
         .map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for updating expense group"))?;
+    group_role_guard(&auth, uid, &mut tx, &state.membership_cache, GroupRole::Admin).await?;
+    let prev_rec = ExpenseGroupRepo::get(&mut tx, uid).await?;
     let updated = ExpenseGroupRepo::update(
         &mut tx,
         uid,
         UpdateExpenseGroupDbPayload {
             name: payload.name,
             start_over_date: payload.start_over_date,
+            currency,
         },
     )
     .await?;
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::ExpenseGroup,
+        uid,
+        uid,
+        AuditChange::update(&prev_rec, &updated),
+    )
+    .await?;
     tx.commit()
         .await
         .map_err(|e| AppError::from_sqlx_error(e, "committing transaction for updating expense group"))?;
     Ok(Json(updated))
 }
 
-
-// TODO: change into soft delete
-// should we fail if there are expenses in the group?
+// Soft delete: the group disappears everywhere but can be restored until the purge job removes it
+// TODO: should we fail if there are expenses in the group?
 #[utoipa::path(
     delete, 
     path = "/expense-groups/{uid}", 
@@ -194,17 +224,243 @@ pub async fn delete_(
     Path(uid): Path<Uuid>,
     Extension(auth): Extension<AuthContext>,
 ) -> Result<Json<DeleteResponse>, AppError> {
-    group_guard(&auth, uid, &state.db_pool).await?;
     let mut tx = state
         .db_pool
         .begin()
         .await
         .map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for deleting expense group"))?;
-    ExpenseGroupRepo::delete(&mut tx, uid).await?;
+    group_role_guard(&auth, uid, &mut tx, &state.membership_cache, GroupRole::Owner).await?;
+    let prev_rec = ExpenseGroupRepo::get(&mut tx, uid).await?;
+    ExpenseGroupRepo::soft_delete(&mut tx, uid).await?;
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::ExpenseGroup,
+        uid,
+        uid,
+        AuditChange::delete(&prev_rec),
+    )
+    .await?;
     tx.commit()
         .await
         .map_err(|e| AppError::from_sqlx_error(e, "committing transaction for deleting expense group"))?;
+    state.membership_cache.invalidate_group(uid);
     Ok(Json(DeleteResponse {
         success: true,
     }))
 }
+
+#[utoipa::path(
+    post,
+    path = "/expense-groups/{uid}/restore",
+    params(("uid" = Uuid, Path)),
+    responses((status = 200, body = ExpenseGroup), (status = 404, description = "No deleted group with this uid")),
+    tag = "Expense Groups",
+    operation_id = "restoreExpenseGroup",
+    security(("bearerAuth" = []))
+)]
+pub async fn restore(
+    State(state): State<AppState>,
+    Path(uid): Path<Uuid>,
+    Extension(auth): Extension<AuthContext>,
+) -> Result<Json<ExpenseGroup>, AppError> {
+    let mut tx = state
+        .db_pool
+        .begin()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for restoring expense group"))?;
+    // The role guards only see live groups, so ownership is checked directly
+    let group = match ExpenseGroupRepo::get_deleted(&mut tx, uid).await {
+        Ok(group) => group,
+        Err(DatabaseError::NotFound(_)) => {
+            return Err(AppError::coded(ErrorCode::GroupNotFound, "No deleted group to restore"));
+        }
+        Err(e) => return Err(e.into()),
+    };
+    if group.owner != auth.user_uid || matches!(auth.source, AuthSource::Chat) {
+        return Err(AppError::coded(ErrorCode::GroupRoleRequired, "Requires owner role in the group"));
+    }
+
+    // Restoring counts against the group limit like creating a new group
+    let subscription = SubscriptionRepo::get_by_user(&mut tx, auth.user_uid).await?;
+    let current_groups = ExpenseGroupRepo::count_by_owner(&mut tx, auth.user_uid).await?;
+    check_tier_limit(&subscription, "groups", current_groups as i32)?;
+
+    let restored = ExpenseGroupRepo::restore(&mut tx, uid).await?;
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::ExpenseGroup,
+        uid,
+        uid,
+        AuditChange::restore(&restored),
+    )
+    .await?;
+    tx.commit()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "committing transaction for restoring expense group"))?;
+    Ok(Json(restored))
+}
+
+/**
+ * Closes the group: it stays readable but takes no new entries or chat commands, and no
+ * longer counts against the owner's group limit. Owner only
+ */
+#[utoipa::path(
+    post,
+    path = "/expense-groups/{uid}/archive",
+    params(("uid" = Uuid, Path)),
+    responses((status = 200, body = ExpenseGroup)),
+    tag = "Expense Groups",
+    operation_id = "archiveExpenseGroup",
+    security(("bearerAuth" = []))
+)]
+pub async fn archive(
+    State(state): State<AppState>,
+    Path(uid): Path<Uuid>,
+    Extension(auth): Extension<AuthContext>,
+) -> Result<Json<ExpenseGroup>, AppError> {
+    let mut tx = state
+        .db_pool
+        .begin()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for archiving expense group"))?;
+    group_role_guard(&auth, uid, &mut tx, &state.membership_cache, GroupRole::Owner).await?;
+    let prev_rec = ExpenseGroupRepo::get(&mut tx, uid).await?;
+    if prev_rec.archived_at.is_some() {
+        return Ok(Json(prev_rec));
+    }
+    let archived = ExpenseGroupRepo::set_archived(&mut tx, uid, true).await?;
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::ExpenseGroup,
+        uid,
+        uid,
+        AuditChange::update(&prev_rec, &archived),
+    )
+    .await?;
+    tx.commit()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "committing transaction for archiving expense group"))?;
+    Ok(Json(archived))
+}
+
+/**
+ * Reopens an archived group. Counts against the owner's group limit again. Owner only
+ */
+#[utoipa::path(
+    post,
+    path = "/expense-groups/{uid}/unarchive",
+    params(("uid" = Uuid, Path)),
+    responses((status = 200, body = ExpenseGroup)),
+    tag = "Expense Groups",
+    operation_id = "unarchiveExpenseGroup",
+    security(("bearerAuth" = []))
+)]
+pub async fn unarchive(
+    State(state): State<AppState>,
+    Path(uid): Path<Uuid>,
+    Extension(auth): Extension<AuthContext>,
+) -> Result<Json<ExpenseGroup>, AppError> {
+    let mut tx = state
+        .db_pool
+        .begin()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for unarchiving expense group"))?;
+    group_role_guard(&auth, uid, &mut tx, &state.membership_cache, GroupRole::Owner).await?;
+    let prev_rec = ExpenseGroupRepo::get(&mut tx, uid).await?;
+    if prev_rec.archived_at.is_none() {
+        return Ok(Json(prev_rec));
+    }
+
+    // Unarchiving counts against the group limit like creating a new group
+    let subscription = SubscriptionRepo::get_by_user(&mut tx, prev_rec.owner).await?;
+    let current_groups = ExpenseGroupRepo::count_by_owner(&mut tx, prev_rec.owner).await?;
+    check_tier_limit(&subscription, "groups", current_groups as i32)?;
+
+    let unarchived = ExpenseGroupRepo::set_archived(&mut tx, uid, false).await?;
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::ExpenseGroup,
+        uid,
+        uid,
+        AuditChange::update(&prev_rec, &unarchived),
+    )
+    .await?;
+    tx.commit()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "committing transaction for unarchiving expense group"))?;
+    Ok(Json(unarchived))
+}
+
+const DEFAULT_AUDIT_LOGS_PER_PAGE: u32 = 50;
+const MAX_AUDIT_LOGS_PER_PAGE: u32 = 100;
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ListAuditLogsQuery {
+    /// 1-based page number, defaults to 1
+    pub page: Option<u32>,
+    /// Page size, defaults to 50, at most 100
+    pub per_page: Option<u32>,
+    /// `next_cursor` of the previous page, replaces `page` and stays fast on long histories
+    pub after: Option<String>,
+}
+
+/**
+ * Changes made to the group and everything in it, newest first. Owner only
+ */
+#[utoipa::path(
+    get,
+    path = "/expense-groups/{uid}/audit-logs",
+    params(("uid" = Uuid, Path), ListAuditLogsQuery),
+    responses((status = 200, body = PaginatedResponse<AuditLog>)),
+    tag = "Expense Groups",
+    operation_id = "listExpenseGroupAuditLogs",
+    security(("bearerAuth" = []))
+)]
+pub async fn list_audit_logs(
+    State(state): State<AppState>,
+    Path(uid): Path<Uuid>,
+    Extension(auth): Extension<AuthContext>,
+    Query(query): Query<ListAuditLogsQuery>,
+) -> Result<Json<PaginatedResponse<AuditLog>>, AppError> {
+    let mut tx = state
+        .db_pool
+        .begin()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for listing audit logs"))?;
+    group_role_guard(&auth, uid, &mut tx, &state.membership_cache, GroupRole::Owner).await?;
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query
+        .per_page
+        .unwrap_or(DEFAULT_AUDIT_LOGS_PER_PAGE)
+        .clamp(1, MAX_AUDIT_LOGS_PER_PAGE);
+    let after = match query.after.as_deref() {
+        Some(after) => Some(Cursor::decode(after).ok_or_else(|| AppError::BadRequest("Invalid cursor".to_string()))?),
+        None => None,
+    };
+    let offset = match after {
+        Some(_) => 0,
+        None => (page as i64 - 1) * per_page as i64,
+    };
+    // One extra row tells whether there is a next page
+    let (mut items, total) = AuditRepo::list_by_group(&mut tx, uid, per_page as i64 + 1, offset, after).await?;
+    let next_cursor = if items.len() > per_page as usize {
+        items.truncate(per_page as usize);
+        items.last().map(|log| Cursor::new(log.created_at, log.uid).encode())
+    } else {
+        None
+    };
+    tx.commit()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "committing transaction for listing audit logs"))?;
+    Ok(Json(PaginatedResponse {
+        items,
+        total,
+        page,
+        per_page,
+        next_cursor,
+    }))
+}
@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+
+use axum::{
+    Json,
+    extract::{Extension, Path, State},
+};
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    auth::{AuthContext, group_guard::group_role_guard},
+    error::AppError,
+    middleware::{tier::check_tier_limit, validated_json::ValidatedJson},
+    repos::{
+        audit_log::{AuditActor, AuditChange, AuditEntity, AuditRepo},
+        chat_binding::ChatBindingRepo,
+        expense_group::{ExpenseGroup, ExpenseGroupRepo},
+        expense_group_member::{
+            CreateGroupMemberDbPayload, GroupMember, GroupMemberRepo, GroupRole,
+        },
+        group_invite::{CreateGroupInviteDbPayload, GroupInvite, GroupInviteRepo},
+        subscription::SubscriptionRepo,
+        user::UserRepo,
+    },
+    types::{AppState, DeleteResponse},
+};
+
+const DEFAULT_INVITE_TTL_HOURS: i64 = 72;
+const MAX_INVITE_TTL_HOURS: i64 = 24 * 30;
+
+pub fn router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(list, create))
+        .routes(routes!(delete_))
+        .routes(routes!(accept))
+}
+
+fn invite_link(front_end_url: &str, token: &str) -> String {
+    format!("{}/invites/{}", front_end_url.trim_end_matches('/'), token)
+}
+
+#[derive(Debug, Deserialize, ToSchema, Validate)]
+pub struct CreateGroupInvitePayload {
+    /// `member` (default) or `admin`
+    pub role: Option<String>,
+    /// Restrict the invite to the user with this email
+    pub email: Option<String>,
+    /// Hours until the link expires, defaults to 72, at most 720
+    pub expires_in_hours: Option<i64>,
+    /// Also post the link to every chat bound to the group
+    #[serde(default)]
+    pub send_to_chat: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct GroupInviteResponse {
+    pub invite: GroupInvite,
+    pub link: String,
+    /// Number of chats the link was posted to
+    pub delivered_to_chats: usize,
+}
+
+#[utoipa::path(get, path = "/expense-groups/{uid}/invites", params(("uid" = Uuid, Path)), responses((status = 200, body = [GroupInvite])), tag = "Group Invites", operation_id = "listGroupInvites", security(("bearerAuth" = [])))]
+pub async fn list(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(uid): Path<Uuid>,
+) -> Result<Json<Vec<GroupInvite>>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for listing group invites")
+    })?;
+    group_role_guard(
+        &auth,
+        uid,
+        &mut tx,
+        &state.membership_cache,
+        GroupRole::Admin,
+    )
+    .await?;
+    let res = GroupInviteRepo::list_pending_by_group(&mut tx, uid).await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for listing group invites")
+    })?;
+    Ok(Json(res))
+}
+
+#[utoipa::path(post, path = "/expense-groups/{uid}/invites", params(("uid" = Uuid, Path)), request_body = CreateGroupInvitePayload, responses((status = 200, body = GroupInviteResponse)), tag = "Group Invites", operation_id = "createGroupInvite", security(("bearerAuth" = [])))]
+pub async fn create(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(uid): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<CreateGroupInvitePayload>,
+) -> Result<Json<GroupInviteResponse>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for creating group invite")
+    })?;
+    let caller_role = group_role_guard(
+        &auth,
+        uid,
+        &mut tx,
+        &state.membership_cache,
+        GroupRole::Admin,
+    )
+    .await?;
+    let role = match payload.role.as_deref().map(GroupRole::parse) {
+        None => GroupRole::Member,
+        Some(Some(role)) if role != GroupRole::Owner => role,
+        _ => {
+            return Err(AppError::BadRequest(
+                "role must be either 'member' or 'admin'".to_string(),
+            ));
+        }
+    };
+    if role == GroupRole::Admin && caller_role != GroupRole::Owner {
+        return Err(AppError::Unauthorized(
+            "Only the owner can invite admins".into(),
+        ));
+    }
+    let ttl_hours = payload.expires_in_hours.unwrap_or(DEFAULT_INVITE_TTL_HOURS);
+    if !(1..=MAX_INVITE_TTL_HOURS).contains(&ttl_hours) {
+        return Err(AppError::BadRequest(format!(
+            "expires_in_hours must be between 1 and {}",
+            MAX_INVITE_TTL_HOURS
+        )));
+    }
+    let email = payload
+        .email
+        .map(|email| email.trim().to_lowercase())
+        .filter(|email| !email.is_empty());
+    let group = ExpenseGroupRepo::get(&mut tx, uid).await?;
+
+    // Fail early instead of handing out a link that can never be accepted
+    let subscription = SubscriptionRepo::get_by_user(&mut tx, group.owner).await?;
+    let current_members = GroupMemberRepo::count_by_group(&mut tx, uid).await?;
+    check_tier_limit(&subscription, "members_per_group", current_members as i32)?;
+
+    let invite = GroupInviteRepo::create(
+        &mut tx,
+        CreateGroupInviteDbPayload {
+            group_uid: uid,
+            role: role.as_str().to_string(),
+            email,
+            invited_by: auth.user_uid,
+            expires_at: Utc::now() + Duration::hours(ttl_hours),
+        },
+    )
+    .await?;
+    let inviter = UserRepo::get(&mut tx, auth.user_uid).await?;
+    let bindings = if payload.send_to_chat {
+        ChatBindingRepo::list(&mut tx).await?
+    } else {
+        Vec::new()
+    };
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for creating group invite")
+    })?;
+
+    let link = invite_link(&state.front_end_url, &invite.token);
+    let mut delivered_to_chats = 0;
+    if let Some(messenger_manager) = &state.messenger_manager {
+        let message = invite_message(&state, &group, &inviter.email, &invite, &link);
+        for binding in bindings
+            .iter()
+            .filter(|b| b.group_uid == uid && b.status == "active")
+        {
+            match messenger_manager
+                .send_message(&binding.platform, &binding.p_uid, &message)
+                .await
+            {
+                Ok(_) => delivered_to_chats += 1,
+                Err(e) => tracing::error!("Failed to send group invite to chat: {:?}", e),
+            }
+        }
+    }
+
+    Ok(Json(GroupInviteResponse {
+        invite,
+        link,
+        delivered_to_chats,
+    }))
+}
+
+fn invite_message(
+    state: &AppState,
+    group: &ExpenseGroup,
+    inviter: &str,
+    invite: &GroupInvite,
+    link: &str,
+) -> String {
+    state.lang.get_with_vars(
+        "MESSENGER__GROUP_INVITE",
+        HashMap::from([
+            ("inviter".to_string(), inviter.to_string()),
+            ("group".to_string(), group.name.clone()),
+            (
+                "expires_at".to_string(),
+                invite.expires_at.format("%Y-%m-%d %H:%M UTC").to_string(),
+            ),
+            ("link".to_string(), link.to_string()),
+        ]),
+    )
+}
+
+#[utoipa::path(delete, path = "/expense-groups/{uid}/invites/{invite_uid}", params(("uid" = Uuid, Path), ("invite_uid" = Uuid, Path)), responses((status = 200, description = "Revoked", body = DeleteResponse)), tag = "Group Invites", operation_id = "deleteGroupInvite", security(("bearerAuth" = [])))]
+pub async fn delete_(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path((uid, invite_uid)): Path<(Uuid, Uuid)>,
+) -> Result<Json<DeleteResponse>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for deleting group invite")
+    })?;
+    group_role_guard(
+        &auth,
+        uid,
+        &mut tx,
+        &state.membership_cache,
+        GroupRole::Admin,
+    )
+    .await?;
+    let invite = GroupInviteRepo::get(&mut tx, invite_uid).await?;
+    if invite.group_uid != uid {
+        return Err(AppError::NotFound("Group invite not found".to_string()));
+    }
+    GroupInviteRepo::delete(&mut tx, invite_uid).await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for deleting group invite")
+    })?;
+    Ok(Json(DeleteResponse { success: true }))
+}
+
+#[utoipa::path(post, path = "/invites/{token}/accept", params(("token" = String, Path)), responses((status = 200, body = GroupMember)), tag = "Group Invites", operation_id = "acceptGroupInvite", security(("bearerAuth" = [])))]
+pub async fn accept(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(token): Path<String>,
+) -> Result<Json<GroupMember>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for accepting group invite")
+    })?;
+    let invite = GroupInviteRepo::get_by_token_for_update(&mut tx, &token).await?;
+    if !invite.is_pending(Utc::now()) {
+        return Err(AppError::BadRequest(
+            "Invite has expired or was already used".to_string(),
+        ));
+    }
+
+    let user = UserRepo::get(&mut tx, auth.user_uid).await?;
+    if let Some(email) = &invite.email {
+        if !email.eq_ignore_ascii_case(&user.email) {
+            return Err(AppError::Unauthorized(
+                "Invite was issued for a different email".into(),
+            ));
+        }
+    }
+
+    let group = ExpenseGroupRepo::get(&mut tx, invite.group_uid).await?;
+    if user.uid == group.owner
+        || GroupMemberRepo::find_by_group_and_user(&mut tx, group.uid, user.uid)
+            .await?
+            .is_some()
+    {
+        return Err(AppError::BadRequest(
+            "User is already a member of the group".to_string(),
+        ));
+    }
+
+    let subscription = SubscriptionRepo::get_by_user(&mut tx, group.owner).await?;
+    let current_members = GroupMemberRepo::count_by_group(&mut tx, group.uid).await?;
+    check_tier_limit(&subscription, "members_per_group", current_members as i32)?;
+
+    let member = GroupMemberRepo::create(
+        &mut tx,
+        CreateGroupMemberDbPayload {
+            group_uid: group.uid,
+            user_uid: user.uid,
+            role: invite.role.clone(),
+        },
+    )
+    .await?;
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::GroupMember,
+        group.uid,
+        member.id,
+        AuditChange::create(&member),
+    )
+    .await?;
+    GroupInviteRepo::mark_accepted(&mut tx, invite.uid, user.uid).await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for accepting group invite")
+    })?;
+    state.membership_cache.invalidate(user.uid, group.uid);
+    Ok(Json(member))
+}
@@ -4,108 +4,243 @@ use axum::{
 };
 use serde::Deserialize;
 use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
 use uuid::Uuid;
+use validator::Validate;
 
 use crate::{
-    auth::{AuthContext, AuthSource},
+    auth::{
+        AuthContext,
+        group_guard::{group_guard, group_role_guard},
+    },
     error::AppError,
-    repos::expense_group_member::{
-        CreateGroupMemberDbPayload, GroupMember, GroupMemberRepo, UpdateGroupMemberDbPayload,
+    middleware::{tier::check_tier_limit, validated_json::ValidatedJson},
+    repos::{
+        audit_log::{AuditActor, AuditChange, AuditEntity, AuditRepo},
+        expense_group::ExpenseGroupRepo,
+        expense_group_member::{
+            CreateGroupMemberDbPayload, GroupMember, GroupMemberRepo, GroupRole,
+            UpdateGroupMemberDbPayload,
+        },
+        subscription::SubscriptionRepo,
+        user::UserRepo,
     },
-    types::AppState,
+    types::{AppState, DeleteResponse},
 };
 
-pub fn router() -> axum::Router<AppState> {
-    axum::Router::new()
-        .route("/group-members", axum::routing::get(list).post(create))
-        .route(
-            "/group-members/{id}",
-            axum::routing::get(get).put(update).delete(delete_),
-        )
+pub fn router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(list, create))
+        .routes(routes!(update, delete_))
 }
 
 /*
-Before activating these routes, make sure to:
-1. What can users do with group members? (e.g., only admins can add/remove members)
-2. Ensure proper authentication and authorization checks are in place.
-3. What can group members see and do? (e.g., can they see other members, their roles, etc.)
+Permissions:
+- every member can list the members of the group
+- owner and admins can add members and remove plain members
+- only the owner can add/remove admins or change roles
+- anyone can remove themselves (leave the group), except the owner
  */
 
-#[utoipa::path(get, path = "/group-members", responses((status = 200, body = [GroupMember])), tag = "Group Members", operation_id = "listGroupMembers", security(("bearerAuth" = [])))]
-pub async fn list(State(state): State<AppState>) -> Result<Json<Vec<GroupMember>>, AppError> {
-    let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for listing group members"))?;
-    let res = GroupMemberRepo::list(&mut tx).await?;
-    tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for listing group members"))?;
-    Ok(Json(res))
+// Roles that can be granted through the API; ownership lives on the group itself
+fn parse_assignable_role(role: Option<&str>) -> Result<GroupRole, AppError> {
+    match role.map(GroupRole::parse) {
+        None => Ok(GroupRole::Member),
+        Some(Some(role)) if role != GroupRole::Owner => Ok(role),
+        _ => Err(AppError::BadRequest(
+            "role must be either 'member' or 'admin'".to_string(),
+        )),
+    }
 }
 
-#[utoipa::path(get, path = "/group-members/{id}", params(("id" = Uuid, Path)), responses((status = 200, body = GroupMember)), tag = "Group Members", operation_id = "getGroupMember", security(("bearerAuth" = [])))]
-pub async fn get(
+#[utoipa::path(get, path = "/expense-groups/{uid}/members", params(("uid" = Uuid, Path)), responses((status = 200, body = [GroupMember])), tag = "Group Members", operation_id = "listGroupMembers", security(("bearerAuth" = [])))]
+pub async fn list(
     State(state): State<AppState>,
-    Path(id): Path<Uuid>,
-) -> Result<Json<GroupMember>, AppError> {
-    let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for getting group member"))?;
-    let res = GroupMemberRepo::get(&mut tx, id).await?;
-    tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for getting group member"))?;
+    Extension(auth): Extension<AuthContext>,
+    Path(uid): Path<Uuid>,
+) -> Result<Json<Vec<GroupMember>>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for listing group members")
+    })?;
+    group_guard(&auth, uid, &mut tx, &state.membership_cache).await?;
+    let res = GroupMemberRepo::list_by_group(&mut tx, uid).await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for listing group members")
+    })?;
     Ok(Json(res))
 }
 
-#[derive(Deserialize, ToSchema)]
+#[derive(Deserialize, ToSchema, Validate)]
 pub struct CreateGroupMemberPayload {
-    pub group_uid: Uuid,
     pub user_uid: Uuid,
-    pub role: String,
+    /// `member` (default) or `admin`
+    pub role: Option<String>,
 }
 
-#[utoipa::path(post, path = "/group-members", request_body = CreateGroupMemberPayload, responses((status = 200, body = GroupMember)), tag = "Group Members", operation_id = "createGroupMember", security(("bearerAuth" = [])))]
+#[utoipa::path(post, path = "/expense-groups/{uid}/members", params(("uid" = Uuid, Path)), request_body = CreateGroupMemberPayload, responses((status = 200, body = GroupMember)), tag = "Group Members", operation_id = "createGroupMember", security(("bearerAuth" = [])))]
 pub async fn create(
     State(state): State<AppState>,
     Extension(auth): Extension<AuthContext>,
-    Json(payload): Json<CreateGroupMemberPayload>,
+    Path(uid): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<CreateGroupMemberPayload>,
 ) -> Result<Json<GroupMember>, AppError> {
-    if matches!(auth.source, AuthSource::Chat) && auth.group_uid != Some(payload.group_uid) {
-        return Err(AppError::Unauthorized("Group scope mismatch".into()));
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for creating group member")
+    })?;
+    let caller_role = group_role_guard(
+        &auth,
+        uid,
+        &mut tx,
+        &state.membership_cache,
+        GroupRole::Admin,
+    )
+    .await?;
+    let role = parse_assignable_role(payload.role.as_deref())?;
+    if role == GroupRole::Admin && caller_role != GroupRole::Owner {
+        return Err(AppError::Unauthorized(
+            "Only the owner can add admins".into(),
+        ));
+    }
+
+    let group = ExpenseGroupRepo::get(&mut tx, uid).await?;
+    UserRepo::get(&mut tx, payload.user_uid).await?;
+    if payload.user_uid == group.owner
+        || GroupMemberRepo::find_by_group_and_user(&mut tx, uid, payload.user_uid)
+            .await?
+            .is_some()
+    {
+        return Err(AppError::BadRequest(
+            "User is already a member of the group".to_string(),
+        ));
     }
-    let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for creating group member"))?;
+
+    // The member limit follows the owner's plan, not the caller's
+    let subscription = SubscriptionRepo::get_by_user(&mut tx, group.owner).await?;
+    let current_members = GroupMemberRepo::count_by_group(&mut tx, uid).await?;
+    check_tier_limit(&subscription, "members_per_group", current_members as i32)?;
+
     let created = GroupMemberRepo::create(
         &mut tx,
         CreateGroupMemberDbPayload {
-            group_uid: payload.group_uid,
+            group_uid: uid,
             user_uid: payload.user_uid,
-            role: payload.role,
+            role: role.as_str().to_string(),
         },
     )
     .await?;
-    tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for creating group member"))?;
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::GroupMember,
+        uid,
+        created.id,
+        AuditChange::create(&created),
+    )
+    .await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for creating group member")
+    })?;
+    state.membership_cache.invalidate(payload.user_uid, uid);
     Ok(Json(created))
 }
 
-#[derive(Deserialize, ToSchema)]
+#[derive(Deserialize, ToSchema, Validate)]
 pub struct UpdateGroupMemberPayload {
-    pub role: Option<String>,
+    /// `member` or `admin`
+    pub role: String,
 }
 
-#[utoipa::path(put, path = "/group-members/{id}", params(("id" = Uuid, Path)), request_body = UpdateGroupMemberPayload, responses((status = 200, body = GroupMember)), tag = "Group Members", operation_id = "updateGroupMember", security(("bearerAuth" = [])))]
+#[utoipa::path(put, path = "/expense-groups/{uid}/members/{user_uid}", params(("uid" = Uuid, Path), ("user_uid" = Uuid, Path)), request_body = UpdateGroupMemberPayload, responses((status = 200, body = GroupMember)), tag = "Group Members", operation_id = "updateGroupMember", security(("bearerAuth" = [])))]
 pub async fn update(
     State(state): State<AppState>,
-    Path(id): Path<Uuid>,
-    Json(payload): Json<UpdateGroupMemberPayload>,
+    Extension(auth): Extension<AuthContext>,
+    Path((uid, user_uid)): Path<(Uuid, Uuid)>,
+    ValidatedJson(payload): ValidatedJson<UpdateGroupMemberPayload>,
 ) -> Result<Json<GroupMember>, AppError> {
-    let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for updating group member"))?;
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for updating group member")
+    })?;
+    group_role_guard(
+        &auth,
+        uid,
+        &mut tx,
+        &state.membership_cache,
+        GroupRole::Owner,
+    )
+    .await?;
+    let role = parse_assignable_role(Some(&payload.role))?;
+    let member = GroupMemberRepo::find_by_group_and_user(&mut tx, uid, user_uid)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Group member not found".to_string()))?;
     let updated = GroupMemberRepo::update(
         &mut tx,
-        id,
-        UpdateGroupMemberDbPayload { role: payload.role },
+        member.id,
+        UpdateGroupMemberDbPayload {
+            role: Some(role.as_str().to_string()),
+        },
+    )
+    .await?;
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::GroupMember,
+        uid,
+        member.id,
+        AuditChange::update(&member, &updated),
     )
     .await?;
-    tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for updating group member"))?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for updating group member")
+    })?;
+    state.membership_cache.invalidate(user_uid, uid);
     Ok(Json(updated))
 }
 
-#[utoipa::path(delete, path = "/group-members/{id}", params(("id" = Uuid, Path)), responses((status = 200, description = "Deleted")), tag = "Group Members", operation_id = "deleteGroupMember", security(("bearerAuth" = [])))]
-pub async fn delete_(State(state): State<AppState>, Path(id): Path<Uuid>) -> Result<(), AppError> {
-    let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for deleting group member"))?;
-    GroupMemberRepo::delete(&mut tx, id).await?;
-    tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for deleting group member"))?;
-    Ok(())
+#[utoipa::path(delete, path = "/expense-groups/{uid}/members/{user_uid}", params(("uid" = Uuid, Path), ("user_uid" = Uuid, Path)), responses((status = 200, description = "Deleted", body = DeleteResponse)), tag = "Group Members", operation_id = "deleteGroupMember", security(("bearerAuth" = [])))]
+pub async fn delete_(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path((uid, user_uid)): Path<(Uuid, Uuid)>,
+) -> Result<Json<DeleteResponse>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for deleting group member")
+    })?;
+    let caller_role = group_role_guard(
+        &auth,
+        uid,
+        &mut tx,
+        &state.membership_cache,
+        GroupRole::Member,
+    )
+    .await?;
+    let member = GroupMemberRepo::find_by_group_and_user(&mut tx, uid, user_uid)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Group member not found".to_string()))?;
+    let member_role = GroupRole::parse(&member.role).unwrap_or(GroupRole::Member);
+
+    let leaving = user_uid == auth.user_uid && caller_role != GroupRole::Owner;
+    let allowed = leaving
+        || caller_role == GroupRole::Owner
+        || (caller_role == GroupRole::Admin && member_role == GroupRole::Member);
+    if !allowed {
+        return Err(AppError::Unauthorized(
+            "Not allowed to remove this member".into(),
+        ));
+    }
+
+    GroupMemberRepo::delete(&mut tx, member.id).await?;
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::GroupMember,
+        uid,
+        member.id,
+        AuditChange::delete(&member),
+    )
+    .await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for deleting group member")
+    })?;
+    state.membership_cache.invalidate(user_uid, uid);
+    Ok(Json(DeleteResponse { success: true }))
 }
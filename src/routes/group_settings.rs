@@ -0,0 +1,189 @@
+use axum::{
+    Json,
+    extract::{Extension, Path, State},
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    auth::{
+        AuthContext,
+        group_guard::{group_guard, group_role_guard},
+    },
+    error::AppError,
+    middleware::validated_json::ValidatedJson,
+    repos::{
+        audit_log::{AuditActor, AuditChange, AuditEntity, AuditRepo},
+        budget_alert::{BudgetAlertRepo, BudgetAlertSettings},
+        expense_group::{ExpenseGroupRepo, UpdateExpenseGroupDbPayload},
+        expense_group_member::GroupRole,
+        group_settings::{GroupSettings, GroupSettingsRepo, MAX_HISTORY_DAYS},
+        notification_settings::{NotificationSettings, NotificationSettingsRepo},
+    },
+    routes::{
+        budgets::{UpdateBudgetAlertSettingsPayload, merge_alert_settings},
+        currencies::parse_currency,
+        notification_settings::{UpdateNotificationSettingsPayload, merge_settings},
+    },
+    types::AppState,
+    utils::anomaly::AnomalySensitivity,
+};
+
+pub fn router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new().routes(routes!(get, update))
+}
+
+/*
+ Every setting of a group in one place. Currency, digest schedule and budget alerts are
+ stored where they always were and can still be changed through their own endpoints.
+*/
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct GroupSettingsResponse {
+    /// Default currency of the group, same as `ExpenseGroup.currency`
+    pub currency: String,
+    #[serde(flatten)]
+    pub settings: GroupSettings,
+    /// When reports are delivered to the group's chats
+    pub notifications: NotificationSettings,
+    pub budget_alerts: BudgetAlertSettings,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema, Validate)]
+pub struct UpdateGroupSettingsPayload {
+    /// ISO 4217 code
+    pub currency: Option<String>,
+    /// ISO weekday (1-7, 1 is Monday) weeks start on
+    pub week_start: Option<i16>,
+    /// Days `/history` covers when sent without dates (1-31)
+    pub history_days: Option<i16>,
+    pub natural_language_entries: Option<bool>,
+    pub anomaly_sensitivity: Option<AnomalySensitivity>,
+    pub notifications: Option<UpdateNotificationSettingsPayload>,
+    pub budget_alerts: Option<UpdateBudgetAlertSettingsPayload>,
+}
+
+async fn load(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    group_uid: Uuid,
+) -> Result<GroupSettingsResponse, AppError> {
+    let group = ExpenseGroupRepo::get(tx, group_uid).await?;
+    Ok(GroupSettingsResponse {
+        currency: group.currency,
+        settings: GroupSettingsRepo::get(tx, group_uid).await?,
+        notifications: NotificationSettingsRepo::get(tx, group_uid).await?,
+        budget_alerts: BudgetAlertRepo::get_settings(tx, group_uid).await?,
+    })
+}
+
+#[utoipa::path(get, path = "/expense-groups/{uid}/settings", params(("uid" = Uuid, Path)), responses((status = 200, body = GroupSettingsResponse)), tag = "Expense Groups", operation_id = "getGroupSettings", security(("bearerAuth" = [])))]
+pub async fn get(
+    State(state): State<AppState>,
+    Path(uid): Path<Uuid>,
+    Extension(auth): Extension<AuthContext>,
+) -> Result<Json<GroupSettingsResponse>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for getting group settings")
+    })?;
+    group_guard(&auth, uid, &mut tx, &state.membership_cache).await?;
+    let res = load(&mut tx, uid).await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for getting group settings")
+    })?;
+    Ok(Json(res))
+}
+
+// Only the given fields change. Admins and owners only
+#[utoipa::path(put, path = "/expense-groups/{uid}/settings", params(("uid" = Uuid, Path)), request_body = UpdateGroupSettingsPayload, responses((status = 200, body = GroupSettingsResponse)), tag = "Expense Groups", operation_id = "updateGroupSettings", security(("bearerAuth" = [])))]
+pub async fn update(
+    State(state): State<AppState>,
+    Path(uid): Path<Uuid>,
+    Extension(auth): Extension<AuthContext>,
+    ValidatedJson(payload): ValidatedJson<UpdateGroupSettingsPayload>,
+) -> Result<Json<GroupSettingsResponse>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for updating group settings")
+    })?;
+    group_role_guard(
+        &auth,
+        uid,
+        &mut tx,
+        &state.membership_cache,
+        GroupRole::Admin,
+    )
+    .await?;
+    if payload
+        .week_start
+        .is_some_and(|day| !(1..=7).contains(&day))
+    {
+        return Err(AppError::BadRequest(
+            "week_start must be between 1 and 7".to_string(),
+        ));
+    }
+    if payload
+        .history_days
+        .is_some_and(|days| !(1..=MAX_HISTORY_DAYS).contains(&days))
+    {
+        return Err(AppError::BadRequest(format!(
+            "history_days must be between 1 and {}",
+            MAX_HISTORY_DAYS
+        )));
+    }
+    let currency = parse_currency(payload.currency.as_deref())?;
+    if let Some(currency) = currency {
+        let prev_group = ExpenseGroupRepo::get(&mut tx, uid).await?;
+        let updated_group = ExpenseGroupRepo::update(
+            &mut tx,
+            uid,
+            UpdateExpenseGroupDbPayload {
+                name: None,
+                start_over_date: None,
+                currency: Some(currency),
+            },
+        )
+        .await?;
+        AuditRepo::record(
+            &mut tx,
+            &AuditActor::from_auth(&auth),
+            AuditEntity::ExpenseGroup,
+            uid,
+            uid,
+            AuditChange::update(&prev_group, &updated_group),
+        )
+        .await?;
+    }
+
+    let current = GroupSettingsRepo::get(&mut tx, uid).await?;
+    let settings = GroupSettings {
+        week_start: payload.week_start.unwrap_or(current.week_start),
+        history_days: payload.history_days.unwrap_or(current.history_days),
+        natural_language_entries: payload
+            .natural_language_entries
+            .unwrap_or(current.natural_language_entries),
+        anomaly_sensitivity: payload
+            .anomaly_sensitivity
+            .unwrap_or(current.anomaly_sensitivity),
+    };
+    if settings != current {
+        GroupSettingsRepo::upsert(&mut tx, uid, &settings).await?;
+    }
+
+    if let Some(notifications) = &payload.notifications {
+        let current = NotificationSettingsRepo::get(&mut tx, uid).await?;
+        let merged = merge_settings(&current, notifications)?;
+        NotificationSettingsRepo::upsert(&mut tx, uid, merged).await?;
+    }
+    if let Some(budget_alerts) = &payload.budget_alerts {
+        let current = BudgetAlertRepo::get_settings(&mut tx, uid).await?;
+        let merged = merge_alert_settings(&current, budget_alerts)?;
+        BudgetAlertRepo::upsert_settings(&mut tx, uid, merged).await?;
+    }
+
+    let res = load(&mut tx, uid).await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for updating group settings")
+    })?;
+    Ok(Json(res))
+}
@@ -0,0 +1,219 @@
+use axum::{
+    Json,
+    extract::{Extension, Path, State},
+};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    auth::{
+        AuthContext,
+        group_guard::{group_guard, group_write_guard},
+    },
+    error::AppError,
+    middleware::validated_json::{ValidatedJson, positive_amount},
+    repos::{
+        audit_log::{AuditActor, AuditChange, AuditEntity, AuditRepo},
+        income_entry::{
+            CreateIncomeEntryDbPayload, IncomeEntry, IncomeEntryRepo, UpdateIncomeEntryDbPayload,
+        },
+    },
+    types::AppState,
+};
+
+pub fn router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(create_income_entry))
+        .routes(routes!(list_income_entries))
+        .routes(routes!(
+            get_income_entry,
+            update_income_entry,
+            delete_income_entry
+        ))
+}
+
+#[utoipa::path(get, path = "/groups/{group_uid}/income-entries", params(("group_uid" = Uuid, Path)), responses((status = 200, body = [IncomeEntry])), tag = "Income Entries", operation_id = "listIncomeEntries", security(("bearerAuth" = [])))]
+pub async fn list_income_entries(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(group_uid): Path<Uuid>,
+) -> Result<Json<Vec<IncomeEntry>>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for listing income entries")
+    })?;
+    group_guard(&auth, group_uid, &mut tx, &state.membership_cache).await?;
+    let res = IncomeEntryRepo::list_by_group(&mut tx, group_uid).await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for listing income entries")
+    })?;
+    Ok(Json(res))
+}
+
+const MAX_SOURCE_LENGTH: u64 = 255;
+
+#[derive(Debug, Deserialize, ToSchema, Validate)]
+pub struct CreateIncomeEntryPayload {
+    /// Greater than 0
+    #[validate(custom(function = "positive_amount"))]
+    pub amount: Decimal,
+    /// 1 to 255 characters
+    #[validate(length(min = 1, max = MAX_SOURCE_LENGTH))]
+    pub source: String,
+    pub group_uid: Uuid,
+}
+
+#[utoipa::path(post, path = "/income-entries", request_body = CreateIncomeEntryPayload, responses((status = 200, body = IncomeEntry)), tag = "Income Entries", operation_id = "createIncomeEntry", security(("bearerAuth" = [])))]
+pub async fn create_income_entry(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    ValidatedJson(payload): ValidatedJson<CreateIncomeEntryPayload>,
+) -> Result<Json<IncomeEntry>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for creating income entry")
+    })?;
+    group_write_guard(&auth, payload.group_uid, &mut tx, &state.membership_cache).await?;
+    let created = IncomeEntryRepo::create(
+        &mut tx,
+        CreateIncomeEntryDbPayload {
+            amount: payload.amount,
+            source: payload.source,
+            group_uid: payload.group_uid,
+        },
+    )
+    .await?;
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::IncomeEntry,
+        created.group_uid,
+        created.uid,
+        AuditChange::create(&created),
+    )
+    .await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for creating income entry")
+    })?;
+    Ok(Json(created))
+}
+
+#[utoipa::path(get, path = "/income-entries/{uid}", params(("uid" = Uuid, Path)), responses((status = 200, body = IncomeEntry)), tag = "Income Entries", operation_id = "getIncomeEntry", security(("bearerAuth" = [])))]
+pub async fn get_income_entry(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(uid): Path<Uuid>,
+) -> Result<Json<IncomeEntry>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for getting income entry")
+    })?;
+    let rec = IncomeEntryRepo::get(&mut tx, uid).await?;
+    group_guard(&auth, rec.group_uid, &mut tx, &state.membership_cache).await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for getting income entry")
+    })?;
+    Ok(Json(rec))
+}
+
+#[derive(Debug, Deserialize, ToSchema, Validate)]
+pub struct UpdateIncomeEntryPayload {
+    /// Greater than 0
+    #[validate(custom(function = "positive_amount"))]
+    pub amount: Option<Decimal>,
+    /// 1 to 255 characters
+    #[validate(length(min = 1, max = MAX_SOURCE_LENGTH))]
+    pub source: Option<String>,
+}
+
+#[utoipa::path(put, path = "/income-entries/{uid}", params(("uid" = Uuid, Path)), request_body = UpdateIncomeEntryPayload, responses((status = 200, body = IncomeEntry)), tag = "Income Entries", operation_id = "updateIncomeEntry", security(("bearerAuth" = [])))]
+pub async fn update_income_entry(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(uid): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<UpdateIncomeEntryPayload>,
+) -> Result<Json<IncomeEntry>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for updating income entry")
+    })?;
+    let prev_rec = IncomeEntryRepo::get(&mut tx, uid).await?;
+    group_guard(&auth, prev_rec.group_uid, &mut tx, &state.membership_cache).await?;
+    let updated = IncomeEntryRepo::update(
+        &mut tx,
+        uid,
+        UpdateIncomeEntryDbPayload {
+            amount: payload.amount,
+            source: payload.source,
+        },
+    )
+    .await?;
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::IncomeEntry,
+        updated.group_uid,
+        updated.uid,
+        AuditChange::update(&prev_rec, &updated),
+    )
+    .await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for updating income entry")
+    })?;
+    Ok(Json(updated))
+}
+
+#[utoipa::path(delete, path = "/income-entries/{uid}", params(("uid" = Uuid, Path)), responses((status = 200, description = "Deleted")), tag = "Income Entries", operation_id = "deleteIncomeEntry", security(("bearerAuth" = [])))]
+pub async fn delete_income_entry(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(uid): Path<Uuid>,
+) -> Result<(), AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for deleting income entry")
+    })?;
+    let prev_rec = IncomeEntryRepo::get(&mut tx, uid).await?;
+    group_guard(&auth, prev_rec.group_uid, &mut tx, &state.membership_cache).await?;
+    IncomeEntryRepo::delete(&mut tx, uid).await?;
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::IncomeEntry,
+        prev_rec.group_uid,
+        uid,
+        AuditChange::delete(&prev_rec),
+    )
+    .await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for deleting income entry")
+    })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_payload_validation() {
+        let create = |amount, source: &str| CreateIncomeEntryPayload {
+            amount,
+            source: source.to_string(),
+            group_uid: Uuid::new_v4(),
+        };
+        assert!(create(dec!(5000000), "Gaji").validate().is_ok());
+        assert!(create(dec!(0), "Gaji").validate().is_err());
+        assert!(create(dec!(-1), "Gaji").validate().is_err());
+        assert!(create(dec!(5000000), "").validate().is_err());
+
+        let update = |amount, source: Option<&str>| UpdateIncomeEntryPayload {
+            amount,
+            source: source.map(str::to_string),
+        };
+        assert!(update(None, None).validate().is_ok());
+        assert!(update(Some(dec!(1)), Some("Bonus")).validate().is_ok());
+        assert!(update(Some(dec!(0)), None).validate().is_err());
+        assert!(update(None, Some("")).validate().is_err());
+    }
+}
@@ -0,0 +1,13 @@
+use axum::{extract::State, http::header::CONTENT_TYPE, response::IntoResponse};
+
+use crate::{telemetry, types::AppState};
+
+// Not behind the user auth, `metrics_auth_middleware` asks for METRICS_TOKEN instead
+#[utoipa::path(get, path = "/metrics", responses((status = 200, description = "Metrics in the Prometheus text format", content_type = "text/plain")), tag = "System", operation_id = "getMetrics")]
+pub async fn metrics(State(state): State<AppState>) -> impl IntoResponse {
+    telemetry::record_db_pool(&state.db_pool);
+    (
+        [(CONTENT_TYPE, "text/plain; version=0.0.4")],
+        telemetry::handle().render(),
+    )
+}
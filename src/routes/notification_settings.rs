@@ -0,0 +1,97 @@
+use axum::{
+    Json,
+    extract::{Extension, Path, State},
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    auth::{
+        AuthContext,
+        group_guard::{group_guard, group_role_guard},
+    },
+    error::AppError,
+    middleware::validated_json::ValidatedJson,
+    repos::{
+        expense_group_member::GroupRole,
+        notification_settings::{
+            NotificationSettings, NotificationSettingsRepo, UpsertNotificationSettingsDbPayload,
+        },
+    },
+    types::AppState,
+};
+
+pub fn router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(get, update))
+}
+
+#[utoipa::path(get, path = "/groups/{group_uid}/notification-settings", params(("group_uid" = Uuid, Path)), responses((status = 200, body = NotificationSettings)), tag = "Expense Groups", operation_id = "getNotificationSettings", security(("bearerAuth" = [])))]
+pub async fn get(
+    State(state): State<AppState>,
+    Path(group_uid): Path<Uuid>,
+    Extension(auth): Extension<AuthContext>,
+) -> Result<Json<NotificationSettings>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for getting notification settings"))?;
+    group_guard(&auth, group_uid, &mut tx, &state.membership_cache).await?;
+    let res = NotificationSettingsRepo::get(&mut tx, group_uid).await?;
+    tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for getting notification settings"))?;
+    Ok(Json(res))
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema, Validate)]
+pub struct UpdateNotificationSettingsPayload {
+    pub daily_enabled: Option<bool>,
+    /// Hour of the day (0-23, UTC) the daily digest is sent
+    pub daily_hour: Option<i16>,
+    pub weekly_enabled: Option<bool>,
+    /// ISO weekday (1-7, 1 is Monday) the weekly digest is sent on
+    pub weekly_day: Option<i16>,
+    /// Hour of the day (0-23, UTC) the weekly digest is sent
+    pub weekly_hour: Option<i16>,
+}
+
+#[utoipa::path(put, path = "/groups/{group_uid}/notification-settings", params(("group_uid" = Uuid, Path)), request_body = UpdateNotificationSettingsPayload, responses((status = 200, body = NotificationSettings)), tag = "Expense Groups", operation_id = "updateNotificationSettings", security(("bearerAuth" = [])))]
+pub async fn update(
+    State(state): State<AppState>,
+    Path(group_uid): Path<Uuid>,
+    Extension(auth): Extension<AuthContext>,
+    ValidatedJson(payload): ValidatedJson<UpdateNotificationSettingsPayload>,
+) -> Result<Json<NotificationSettings>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for updating notification settings"))?;
+    group_role_guard(&auth, group_uid, &mut tx, &state.membership_cache, GroupRole::Admin).await?;
+    let current = NotificationSettingsRepo::get(&mut tx, group_uid).await?;
+    let updated =
+        NotificationSettingsRepo::upsert(&mut tx, group_uid, merge_settings(&current, &payload)?)
+            .await?;
+    tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for updating notification settings"))?;
+    Ok(Json(updated))
+}
+
+// Validates `payload` and fills what it leaves out from `current`
+pub(crate) fn merge_settings(
+    current: &NotificationSettings,
+    payload: &UpdateNotificationSettingsPayload,
+) -> Result<UpsertNotificationSettingsDbPayload, AppError> {
+    let valid_hour = |hour: Option<i16>| hour.is_none_or(|hour| (0..=23).contains(&hour));
+    if !valid_hour(payload.daily_hour) || !valid_hour(payload.weekly_hour) {
+        return Err(AppError::BadRequest(
+            "daily_hour and weekly_hour must be between 0 and 23".to_string(),
+        ));
+    }
+    if payload.weekly_day.is_some_and(|day| !(1..=7).contains(&day)) {
+        return Err(AppError::BadRequest(
+            "weekly_day must be between 1 and 7".to_string(),
+        ));
+    }
+    Ok(UpsertNotificationSettingsDbPayload {
+        daily_enabled: payload.daily_enabled.unwrap_or(current.daily_enabled),
+        daily_hour: payload.daily_hour.unwrap_or(current.daily_hour),
+        weekly_enabled: payload.weekly_enabled.unwrap_or(current.weekly_enabled),
+        weekly_day: payload.weekly_day.unwrap_or(current.weekly_day),
+        weekly_hour: payload.weekly_hour.unwrap_or(current.weekly_hour),
+    })
+}
@@ -0,0 +1,342 @@
+use std::collections::HashMap;
+
+use axum::{
+    Json,
+    extract::{Extension, State},
+};
+use chrono::{NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+use uuid::Uuid;
+
+use crate::{
+    auth::AuthContext,
+    error::{AppError, DatabaseError},
+    repos::{
+        budget::{Budget, BudgetRepo},
+        budget_alert::{BudgetAlertRepo, BudgetAlertSettings},
+        exchange_rate::ExchangeRateRepo,
+        expense_group::{ExpenseGroup, ExpenseGroupRepo},
+        group_settings::{GroupSettings, GroupSettingsRepo},
+        overview::{BudgetPeriodSpend, OverviewRepo, PendingInvite},
+        subscription::{SubscriptionRepo, UserUsageRepo},
+        user::UserRepo,
+    },
+    types::{AppState, SubscriptionTier},
+    utils::{currency::RateTable, period::BillingPeriod},
+};
+
+pub fn router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new().routes(routes!(get_overview))
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct GroupOverview {
+    pub group: ExpenseGroup,
+    /// The group's current billing cycle, as [start, end)
+    pub period_start: NaiveDate,
+    pub period_end: NaiveDate,
+    /// Spent this cycle in the group's currency, envelopes left out
+    pub total_spent: Decimal,
+    pub entries_count: i64,
+    pub members_count: i64,
+    /// Currencies without a known exchange rate, their amounts are added unconverted
+    pub unconverted_currencies: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BudgetAlertSummary {
+    pub budget_uid: Uuid,
+    pub group_uid: Uuid,
+    pub category_uid: Uuid,
+    pub category_name: String,
+    pub amount: Decimal,
+    pub currency: String,
+    /// Spent this period in the budget's currency
+    pub spent: Decimal,
+    pub percentage_used: f64,
+    /// Highest threshold reached, 100 once the budget is exceeded
+    pub threshold: i16,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UsageLimit {
+    pub used: i64,
+    /// -1 for unlimited
+    pub limit: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TierUsage {
+    pub tier: SubscriptionTier,
+    /// Groups owned by the user, archived ones left out
+    pub groups: UsageLimit,
+    /// Entries this calendar month across the user's groups
+    pub expenses_this_month: UsageLimit,
+    pub max_members_per_group: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UserOverview {
+    /// Newest group first
+    pub groups: Vec<GroupOverview>,
+    /// Budgets past their group's warning threshold, fullest first
+    pub budget_alerts: Vec<BudgetAlertSummary>,
+    pub pending_invites: Vec<PendingInvite>,
+    pub usage: TierUsage,
+}
+
+// None when the spend doesn't reach any of the thresholds enabled in `settings`
+fn budget_alert(
+    budget: &Budget,
+    settings: &BudgetAlertSettings,
+    spend: &[&BudgetPeriodSpend],
+    rates: &RateTable,
+) -> Option<BudgetAlertSummary> {
+    let category_name = spend.first()?.category_name.clone();
+    let totals: Vec<(String, Decimal)> = spend
+        .iter()
+        .map(|row| (row.currency.clone(), row.total))
+        .collect();
+    let (spent, _) = rates.sum_in(&totals, &budget.currency);
+    let percentage_used = budget.percentage_used(spent);
+    let threshold = settings
+        .crossed_thresholds(percentage_used)
+        .into_iter()
+        .max()?;
+    Some(BudgetAlertSummary {
+        budget_uid: budget.uid,
+        group_uid: budget.group_uid,
+        category_uid: budget.category_uid,
+        category_name,
+        amount: budget.amount,
+        currency: budget.currency.clone(),
+        spent: spent.round_dp(2),
+        percentage_used,
+        threshold,
+    })
+}
+
+/*
+ Everything the web dashboard shows on load in one call: the user's groups with what was spent
+ this cycle, the budgets that need attention, invites waiting for the user and how much of the
+ plan is used. Each part is a single aggregated query over all groups.
+*/
+#[utoipa::path(get, path = "/users/me/overview", responses((status = 200, body = UserOverview)), tag = "Users", operation_id = "getMyOverview", security(("bearerAuth" = [])))]
+pub async fn get_overview(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+) -> Result<Json<UserOverview>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for getting user overview")
+    })?;
+    let today = Utc::now().date_naive();
+    let user = UserRepo::get(&mut tx, auth.user_uid).await?;
+    let groups = ExpenseGroupRepo::get_all_by_member(&mut tx, auth.user_uid).await?;
+    let group_uids: Vec<Uuid> = groups.iter().map(|group| group.uid).collect();
+    let periods: HashMap<Uuid, BillingPeriod> = groups
+        .iter()
+        .map(|group| {
+            (
+                group.uid,
+                BillingPeriod::containing(today, group.start_over_date),
+            )
+        })
+        .collect();
+
+    let group_periods: Vec<_> = periods
+        .iter()
+        .map(|(group_uid, period)| (*group_uid, period.start_utc(), period.end_utc()))
+        .collect();
+    let group_totals = OverviewRepo::group_period_totals(&mut tx, &group_periods).await?;
+    let member_counts: HashMap<Uuid, i64> = OverviewRepo::member_counts(&mut tx, &group_uids)
+        .await?
+        .into_iter()
+        .collect();
+
+    // Only the budget each category is tracked against today can raise an alert. Periods
+    // start on each group's (start_over_date, week_start)
+    let settings = GroupSettingsRepo::list_by_groups(&mut tx, &group_uids).await?;
+    let period_starts: HashMap<Uuid, (i16, i16)> = groups
+        .iter()
+        .map(|group| {
+            let week_start = settings
+                .get(&group.uid)
+                .map_or(GroupSettings::default().week_start, |settings| {
+                    settings.week_start
+                });
+            (group.uid, (group.start_over_date, week_start))
+        })
+        .collect();
+    let mut budgets_by_group: HashMap<Uuid, Vec<Budget>> = HashMap::new();
+    for budget in BudgetRepo::list_by_groups(&mut tx, &group_uids).await? {
+        budgets_by_group
+            .entry(budget.group_uid)
+            .or_default()
+            .push(budget);
+    }
+    let budgets: Vec<Budget> = budgets_by_group
+        .into_iter()
+        .flat_map(|(group_uid, budgets)| {
+            let (start_over_date, week_start) = period_starts[&group_uid];
+            Budget::active_by_category(budgets, start_over_date, week_start, today).into_values()
+        })
+        .collect();
+    let budget_periods: Vec<_> = budgets
+        .iter()
+        .map(|budget| {
+            let (start_over_date, week_start) = period_starts[&budget.group_uid];
+            let (start, end) = budget.period_range(start_over_date, week_start, today);
+            (
+                budget.uid,
+                start.and_hms_opt(0, 0, 0).unwrap().and_utc(),
+                end.and_hms_opt(0, 0, 0).unwrap().and_utc(),
+            )
+        })
+        .collect();
+    let budget_spend = OverviewRepo::budget_period_spend(&mut tx, &budget_periods).await?;
+    let alert_settings: HashMap<Uuid, BudgetAlertSettings> =
+        BudgetAlertRepo::list_settings(&mut tx, &group_uids)
+            .await?
+            .into_iter()
+            .map(|settings| (settings.group_uid, settings))
+            .collect();
+
+    let pending_invites =
+        OverviewRepo::pending_invites(&mut tx, auth.user_uid, &user.email).await?;
+
+    let tier = match SubscriptionRepo::get_by_user(&mut tx, auth.user_uid).await {
+        Ok(subscription) => subscription.get_tier(),
+        Err(DatabaseError::NotFound(_)) => SubscriptionTier::Free,
+        Err(e) => return Err(e.into()),
+    };
+    let owned_groups = ExpenseGroupRepo::count_by_owner(&mut tx, auth.user_uid).await?;
+    let usage = UserUsageRepo::calculate_current_usage(&mut tx, auth.user_uid).await?;
+    let rates = ExchangeRateRepo::rate_table(&mut tx).await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for getting user overview")
+    })?;
+
+    let groups = groups
+        .into_iter()
+        .map(|group| {
+            let period = &periods[&group.uid];
+            let rows: Vec<_> = group_totals
+                .iter()
+                .filter(|row| row.group_uid == group.uid)
+                .collect();
+            let totals: Vec<(String, Decimal)> = rows
+                .iter()
+                .map(|row| (row.currency.clone(), row.total))
+                .collect();
+            let (total_spent, unconverted_currencies) = rates.sum_in(&totals, &group.currency);
+            GroupOverview {
+                period_start: period.start,
+                period_end: period.end,
+                total_spent: total_spent.round_dp(2),
+                entries_count: rows.iter().map(|row| row.entries).sum(),
+                members_count: member_counts.get(&group.uid).copied().unwrap_or_default(),
+                unconverted_currencies,
+                group,
+            }
+        })
+        .collect();
+
+    let mut budget_alerts: Vec<BudgetAlertSummary> = budgets
+        .iter()
+        .filter_map(|budget| {
+            let spend: Vec<&BudgetPeriodSpend> = budget_spend
+                .iter()
+                .filter(|row| row.budget_uid == budget.uid)
+                .collect();
+            budget_alert(budget, &alert_settings[&budget.group_uid], &spend, &rates)
+        })
+        .collect();
+    budget_alerts.sort_by(|a, b| b.percentage_used.total_cmp(&a.percentage_used));
+
+    let limits = tier.limits();
+    Ok(Json(UserOverview {
+        groups,
+        budget_alerts,
+        pending_invites,
+        usage: TierUsage {
+            groups: UsageLimit {
+                used: owned_groups,
+                limit: limits.max_groups,
+            },
+            expenses_this_month: UsageLimit {
+                used: usage.total_expenses as i64,
+                limit: limits.max_expenses_per_month,
+            },
+            max_members_per_group: limits.max_members_per_group,
+            tier,
+        },
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repos::budget_alert::EXCEEDED_THRESHOLD;
+    use rust_decimal_macros::dec;
+
+    fn budget(amount: Decimal) -> Budget {
+        Budget {
+            uid: Uuid::new_v4(),
+            group_uid: Uuid::new_v4(),
+            category_uid: Uuid::new_v4(),
+            amount,
+            currency: "IDR".to_string(),
+            period_type: "monthly".to_string(),
+            period_year: None,
+            period_month: None,
+            range_start: None,
+            range_end: None,
+            hard_limit: false,
+        }
+    }
+
+    fn spend(budget: &Budget, currency: &str, total: Decimal) -> BudgetPeriodSpend {
+        BudgetPeriodSpend {
+            budget_uid: budget.uid,
+            category_name: "Food".to_string(),
+            currency: currency.to_string(),
+            total,
+        }
+    }
+
+    #[test]
+    fn test_budget_alert_thresholds() {
+        let budget = budget(dec!(1000));
+        let settings = BudgetAlertSettings::default_for(budget.group_uid);
+        let rates = RateTable::new(vec![("USD".to_string(), "IDR".to_string(), dec!(10))]);
+
+        let below = spend(&budget, "IDR", dec!(500));
+        assert!(budget_alert(&budget, &settings, &[&below], &rates).is_none());
+
+        let warning = spend(&budget, "USD", dec!(85));
+        let alert = budget_alert(&budget, &settings, &[&warning], &rates).unwrap();
+        assert_eq!(alert.spent, dec!(850));
+        assert_eq!(alert.threshold, 80);
+        assert_eq!(alert.category_name, "Food");
+
+        let over = spend(&budget, "IDR", dec!(300));
+        let alert = budget_alert(&budget, &settings, &[&warning, &over], &rates).unwrap();
+        assert_eq!(alert.threshold, EXCEEDED_THRESHOLD);
+        assert_eq!(alert.percentage_used, 115.0);
+    }
+
+    #[test]
+    fn test_budget_alert_disabled_or_unspent() {
+        let budget = budget(dec!(1000));
+        let rates = RateTable::new(Vec::new());
+        let mut settings = BudgetAlertSettings::default_for(budget.group_uid);
+        assert!(budget_alert(&budget, &settings, &[], &rates).is_none());
+
+        settings.enabled = false;
+        let over = spend(&budget, "IDR", dec!(2000));
+        assert!(budget_alert(&budget, &settings, &[&over], &rates).is_none());
+    }
+}
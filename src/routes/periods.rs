@@ -0,0 +1,170 @@
+use axum::{
+    Json,
+    extract::{Extension, Path, State},
+};
+use chrono::{Datelike, NaiveDate, Utc};
+use utoipa_axum::{router::OpenApiRouter, routes};
+use uuid::Uuid;
+
+use crate::{
+    auth::{
+        AuthContext,
+        group_guard::{group_guard, group_role_guard},
+    },
+    error::AppError,
+    repos::{
+        audit_log::{AuditActor, AuditChange, AuditEntity, AuditRepo},
+        closed_period::{ClosedPeriod, ClosedPeriodRepo, CreateClosedPeriodDbPayload},
+        expense_group::ExpenseGroupRepo,
+        expense_group_member::GroupRole,
+    },
+    types::AppState,
+    utils::period::BillingPeriod,
+};
+
+/*
+ Closing a period locks the expense entries created in that billing cycle: editing, deleting
+ or restoring them fails with `409 PERIOD_CLOSED`, whether it comes from the API or a chat.
+ Admins close periods, only owners reopen them.
+*/
+pub fn router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(list_closed))
+        .routes(routes!(close))
+        .routes(routes!(reopen))
+}
+
+// `YYYY-MM` as in `/report`, the cycle starting in that month
+fn parse_period(period: &str) -> Result<(i32, u32), AppError> {
+    let date =
+        NaiveDate::parse_from_str(&format!("{}-01", period.trim()), "%Y-%m-%d").map_err(|_| {
+            AppError::BadRequest(format!("Invalid period {}, expected YYYY-MM", period))
+        })?;
+    Ok((date.year(), date.month()))
+}
+
+#[utoipa::path(get, path = "/groups/{group_uid}/periods/closed", params(("group_uid" = Uuid, Path)), responses((status = 200, body = [ClosedPeriod])), tag = "Expense Groups", operation_id = "listClosedPeriods", security(("bearerAuth" = [])))]
+pub async fn list_closed(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(group_uid): Path<Uuid>,
+) -> Result<Json<Vec<ClosedPeriod>>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for listing closed periods")
+    })?;
+    group_guard(&auth, group_uid, &mut tx, &state.membership_cache).await?;
+    let res = ClosedPeriodRepo::list_by_group(&mut tx, group_uid).await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for listing closed periods")
+    })?;
+    Ok(Json(res))
+}
+
+// Only cycles that have ended can be closed. Admins and owners only
+#[utoipa::path(post, path = "/groups/{group_uid}/periods/{period}/close", params(("group_uid" = Uuid, Path), ("period" = String, Path, description = "YYYY-MM")), responses((status = 200, body = ClosedPeriod), (status = 400, description = "Invalid, ongoing or already closed period")), tag = "Expense Groups", operation_id = "closePeriod", security(("bearerAuth" = [])))]
+pub async fn close(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path((group_uid, period)): Path<(Uuid, String)>,
+) -> Result<Json<ClosedPeriod>, AppError> {
+    let (year, month) = parse_period(&period)?;
+    let mut tx =
+        state.db_pool.begin().await.map_err(|e| {
+            AppError::from_sqlx_error(e, "beginning transaction for closing period")
+        })?;
+    group_role_guard(
+        &auth,
+        group_uid,
+        &mut tx,
+        &state.membership_cache,
+        GroupRole::Admin,
+    )
+    .await?;
+    let group = ExpenseGroupRepo::get(&mut tx, group_uid).await?;
+    let cycle = BillingPeriod::for_month(year, month, group.start_over_date);
+    if cycle.end_utc() > Utc::now() {
+        return Err(AppError::BadRequest(format!(
+            "Period {} has not ended yet, it runs until {}",
+            period,
+            cycle.end.format("%Y-%m-%d")
+        )));
+    }
+    let closed = ClosedPeriodRepo::close(
+        &mut tx,
+        CreateClosedPeriodDbPayload {
+            group_uid,
+            period_year: year,
+            period_month: month as i32,
+            starts_at: cycle.start_utc(),
+            ends_at: cycle.end_utc(),
+            closed_by: Some(auth.user_uid),
+        },
+    )
+    .await?
+    .ok_or_else(|| AppError::BadRequest(format!("Period {} is already closed", period)))?;
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::ClosedPeriod,
+        group_uid,
+        group_uid,
+        AuditChange::create(&closed),
+    )
+    .await?;
+    tx.commit()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "committing transaction for closing period"))?;
+    Ok(Json(closed))
+}
+
+// Unlocks the period's entries again. Owner only
+#[utoipa::path(post, path = "/groups/{group_uid}/periods/{period}/reopen", params(("group_uid" = Uuid, Path), ("period" = String, Path, description = "YYYY-MM")), responses((status = 200, body = ClosedPeriod, description = "The period as it was closed"), (status = 404, description = "Period not closed")), tag = "Expense Groups", operation_id = "reopenPeriod", security(("bearerAuth" = [])))]
+pub async fn reopen(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path((group_uid, period)): Path<(Uuid, String)>,
+) -> Result<Json<ClosedPeriod>, AppError> {
+    let (year, month) = parse_period(&period)?;
+    let mut tx =
+        state.db_pool.begin().await.map_err(|e| {
+            AppError::from_sqlx_error(e, "beginning transaction for reopening period")
+        })?;
+    group_role_guard(
+        &auth,
+        group_uid,
+        &mut tx,
+        &state.membership_cache,
+        GroupRole::Owner,
+    )
+    .await?;
+    let reopened = ClosedPeriodRepo::reopen(&mut tx, group_uid, year, month as i32)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Period {} is not closed", period)))?;
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::ClosedPeriod,
+        group_uid,
+        group_uid,
+        AuditChange::delete(&reopened),
+    )
+    .await?;
+    tx.commit()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "committing transaction for reopening period"))?;
+    Ok(Json(reopened))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_period() {
+        assert_eq!(parse_period("2025-09").unwrap(), (2025, 9));
+        assert_eq!(parse_period(" 2025-12 ").unwrap(), (2025, 12));
+        assert!(parse_period("2025-13").is_err());
+        assert!(parse_period("2025-09-01").is_err());
+        assert!(parse_period("september").is_err());
+    }
+}
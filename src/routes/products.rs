@@ -0,0 +1,56 @@
+use axum::{
+    Json,
+    extract::{Extension, Path, Query, State},
+};
+use serde::{Deserialize, Serialize};
+use utoipa::IntoParams;
+use utoipa_axum::{router::OpenApiRouter, routes};
+use uuid::Uuid;
+
+use crate::{
+    auth::{AuthContext, group_guard::group_guard},
+    error::AppError,
+    repos::product::{Product, ProductRepo},
+    types::AppState,
+};
+
+pub fn router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new().routes(routes!(suggest))
+}
+
+const DEFAULT_SUGGEST_LIMIT: u32 = 10;
+const MAX_SUGGEST_LIMIT: u32 = 50;
+
+#[derive(Debug, Serialize, Deserialize, IntoParams)]
+pub struct SuggestProductsQuery {
+    /// Part of the product name typed so far, case-insensitive
+    pub q: String,
+    /// Maximum number of suggestions, defaults to 10, at most 50
+    pub limit: Option<u32>,
+}
+
+#[utoipa::path(get, path = "/groups/{group_uid}/products/suggest", params(("group_uid" = Uuid, Path), SuggestProductsQuery), responses((status = 200, body = [Product])), tag = "Products", operation_id = "suggestProducts", security(("bearerAuth" = [])))]
+pub async fn suggest(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(group_uid): Path<Uuid>,
+    Query(query): Query<SuggestProductsQuery>,
+) -> Result<Json<Vec<Product>>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for suggesting products")
+    })?;
+    group_guard(&auth, group_uid, &mut tx, &state.membership_cache).await?;
+    let term = query.q.trim();
+    if term.is_empty() {
+        return Err(AppError::BadRequest("q must not be empty".to_string()));
+    }
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_SUGGEST_LIMIT)
+        .clamp(1, MAX_SUGGEST_LIMIT);
+    let res = ProductRepo::suggest(&mut tx, group_uid, term, limit as i64).await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for suggesting products")
+    })?;
+    Ok(Json(res))
+}
@@ -0,0 +1,418 @@
+use std::collections::HashSet;
+
+use axum::{
+    Json,
+    extract::{Extension, Multipart, Path, Query, State},
+};
+use chrono::Duration;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use utoipa_axum::{router::OpenApiRouter, routes};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    auth::{
+        AuthContext,
+        group_guard::{group_guard, group_write_guard},
+    },
+    error::AppError,
+    middleware::{tier::check_tier_limit, validated_json::ValidatedJson},
+    repos::{
+        audit_log::{AuditActor, AuditChange, AuditEntity, AuditRepo},
+        category::CategoryRepo,
+        expense_entry::{CreateExpenseEntryDbPayload, ExpenseEntry, ExpenseEntryRepo},
+        expense_group::ExpenseGroupRepo,
+        product::{ProductRepo, normalize_product_name},
+        reconciliation::{
+            CreateReconciliationDbPayload, CreateReconciliationRowDbPayload, Reconciliation,
+            ReconciliationRepo, ReconciliationRow,
+        },
+        subscription::{SubscriptionRepo, UserUsageRepo},
+    },
+    routes::currencies::parse_currency,
+    types::{AppState, DeleteResponse},
+    utils::{
+        bank_statement::{self, StatementFormat},
+        expense_import::ImportRowError,
+    },
+    webhooks::{self, WebhookEvent},
+};
+
+const DEFAULT_WINDOW_DAYS: i16 = 3;
+const MAX_WINDOW_DAYS: i16 = 14;
+
+/*
+ Reconciling a bank statement: the uploaded debits are matched with the group's entries of the
+ same amount, recorded around the same date. What is left unmatched is likely missing from the
+ group, and can be added as entries in one go.
+*/
+pub fn router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(create, list))
+        .routes(routes!(get, delete_))
+        .routes(routes!(create_entries))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct CreateReconciliationQuery {
+    /// Currency of the statement, defaults to the one an OFX file declares, then to the group's
+    pub currency: Option<String>,
+    /// How many days apart a row and an entry may be dated and still match, 3 by default, at most 14
+    pub window_days: Option<i16>,
+}
+
+// Documents the multipart body; the handler reads the `file` field directly
+#[derive(ToSchema)]
+#[allow(dead_code)]
+pub struct ReconciliationStatementForm {
+    /// CSV or OFX export of the account, see `utils::bank_statement` for the columns read
+    #[schema(value_type = String, format = Binary)]
+    pub file: Vec<u8>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReconciliationDetail {
+    pub reconciliation: Reconciliation,
+    /// Rows paid for by an entry of the group
+    pub matched: Vec<ReconciliationRow>,
+    /// Rows without an entry yet
+    pub unmatched: Vec<ReconciliationRow>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CreateReconciliationResponse {
+    #[serde(flatten)]
+    pub detail: ReconciliationDetail,
+    /// Rows of the file that could not be read, they are not part of the reconciliation
+    pub errors: Vec<ImportRowError>,
+}
+
+#[derive(Debug, Deserialize, ToSchema, Validate)]
+pub struct CreateReconciliationEntriesPayload {
+    /// Unmatched rows to add, all of them when omitted
+    #[validate(length(min = 1))]
+    pub row_uids: Option<Vec<Uuid>>,
+    /// Category of the new entries, otherwise the one their description was last recorded with
+    pub category_uid: Option<Uuid>,
+}
+
+async fn get_in_group(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    group_uid: Uuid,
+    reconciliation_uid: Uuid,
+) -> Result<Reconciliation, AppError> {
+    let reconciliation = ReconciliationRepo::get(tx, reconciliation_uid).await?;
+    if reconciliation.group_uid != group_uid {
+        return Err(AppError::NotFound("Reconciliation not found".to_string()));
+    }
+    Ok(reconciliation)
+}
+
+async fn load_detail(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    reconciliation: Reconciliation,
+) -> Result<ReconciliationDetail, AppError> {
+    let (matched, unmatched) = ReconciliationRepo::rows(tx, reconciliation.uid)
+        .await?
+        .into_iter()
+        .partition(|row| row.entry_uid.is_some());
+    Ok(ReconciliationDetail {
+        reconciliation,
+        matched,
+        unmatched,
+    })
+}
+
+// Nothing is added to the group: the statement and its matches are kept for review
+#[utoipa::path(post, path = "/groups/{group_uid}/reconciliations", params(("group_uid" = Uuid, Path), CreateReconciliationQuery), request_body(content = ReconciliationStatementForm, content_type = "multipart/form-data"), responses((status = 200, body = CreateReconciliationResponse)), tag = "Reconciliations", operation_id = "createReconciliation", security(("bearerAuth" = [])))]
+pub async fn create(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(group_uid): Path<Uuid>,
+    Query(query): Query<CreateReconciliationQuery>,
+    mut multipart: Multipart,
+) -> Result<Json<CreateReconciliationResponse>, AppError> {
+    let window_days = query.window_days.unwrap_or(DEFAULT_WINDOW_DAYS);
+    if !(0..=MAX_WINDOW_DAYS).contains(&window_days) {
+        return Err(AppError::BadRequest(format!(
+            "window_days must be between 0 and {}",
+            MAX_WINDOW_DAYS
+        )));
+    }
+    let query_currency = parse_currency(query.currency.as_deref())?;
+
+    let mut upload = None;
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| AppError::BadRequest(format!("Invalid multipart body: {}", e)))?
+    {
+        if field.name() != Some("file") {
+            continue;
+        }
+        let file_name = field.file_name().map(|name| name.to_string());
+        let bytes = field
+            .bytes()
+            .await
+            .map_err(|e| AppError::BadRequest(format!("Failed to read file: {}", e)))?;
+        upload = Some((file_name, bytes));
+    }
+    let Some((file_name, bytes)) = upload else {
+        return Err(AppError::BadRequest(
+            "Missing `file` field in multipart body".to_string(),
+        ));
+    };
+    let format = StatementFormat::detect(file_name.as_deref(), &bytes);
+    let statement =
+        bank_statement::parse(format, &bytes).map_err(|e| AppError::BadRequest(e.to_string()))?;
+    if statement.rows.is_empty() {
+        return Err(AppError::BadRequest(
+            "The statement has no debits to reconcile".to_string(),
+        ));
+    }
+    let statement_currency = match query_currency {
+        Some(currency) => Some(currency),
+        None => parse_currency(statement.currency.as_deref())?,
+    };
+
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for creating reconciliation")
+    })?;
+    group_write_guard(&auth, group_uid, &mut tx, &state.membership_cache).await?;
+    let currency = match statement_currency {
+        Some(currency) => currency,
+        None => ExpenseGroupRepo::get(&mut tx, group_uid).await?.currency,
+    };
+
+    // Rows are never empty here
+    let window = Duration::days(window_days.into());
+    let first = statement.rows.iter().map(|row| row.date).min().unwrap();
+    let last = statement.rows.iter().map(|row| row.date).max().unwrap();
+    let candidates = ReconciliationRepo::candidate_entries(
+        &mut tx,
+        group_uid,
+        &currency,
+        first - window,
+        last + window,
+    )
+    .await?;
+    let matches = bank_statement::match_rows(&statement.rows, &candidates, window_days.into());
+
+    let rows = statement
+        .rows
+        .into_iter()
+        .zip(matches)
+        .map(|(row, entry_uid)| CreateReconciliationRowDbPayload {
+            line: row.line as i32,
+            posted_on: row.date,
+            amount: row.amount,
+            description: row.description,
+            entry_uid,
+        })
+        .collect();
+    let reconciliation = ReconciliationRepo::create(
+        &mut tx,
+        CreateReconciliationDbPayload {
+            group_uid,
+            file_name,
+            format: format.as_str().to_string(),
+            currency,
+            window_days,
+            skipped_rows: (statement.skipped + statement.errors.len()) as i32,
+            created_by: auth.user_uid,
+        },
+        rows,
+    )
+    .await?;
+    let detail = load_detail(&mut tx, reconciliation).await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for creating reconciliation")
+    })?;
+    Ok(Json(CreateReconciliationResponse {
+        detail,
+        errors: statement.errors,
+    }))
+}
+
+// Newest first, with how many rows are matched
+#[utoipa::path(get, path = "/groups/{group_uid}/reconciliations", params(("group_uid" = Uuid, Path)), responses((status = 200, body = [Reconciliation])), tag = "Reconciliations", operation_id = "listReconciliations", security(("bearerAuth" = [])))]
+pub async fn list(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(group_uid): Path<Uuid>,
+) -> Result<Json<Vec<Reconciliation>>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for listing reconciliations")
+    })?;
+    group_guard(&auth, group_uid, &mut tx, &state.membership_cache).await?;
+    let res = ReconciliationRepo::list_by_group(&mut tx, group_uid).await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for listing reconciliations")
+    })?;
+    Ok(Json(res))
+}
+
+// Matches are read again, a row whose entry was deleted since is unmatched
+#[utoipa::path(get, path = "/groups/{group_uid}/reconciliations/{reconciliation_uid}", params(("group_uid" = Uuid, Path), ("reconciliation_uid" = Uuid, Path)), responses((status = 200, body = ReconciliationDetail)), tag = "Reconciliations", operation_id = "getReconciliation", security(("bearerAuth" = [])))]
+pub async fn get(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path((group_uid, reconciliation_uid)): Path<(Uuid, Uuid)>,
+) -> Result<Json<ReconciliationDetail>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for getting reconciliation")
+    })?;
+    group_guard(&auth, group_uid, &mut tx, &state.membership_cache).await?;
+    let reconciliation = get_in_group(&mut tx, group_uid, reconciliation_uid).await?;
+    let detail = load_detail(&mut tx, reconciliation).await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for getting reconciliation")
+    })?;
+    Ok(Json(detail))
+}
+
+// Entries matched with or created from the statement stay
+#[utoipa::path(delete, path = "/groups/{group_uid}/reconciliations/{reconciliation_uid}", params(("group_uid" = Uuid, Path), ("reconciliation_uid" = Uuid, Path)), responses((status = 200, body = DeleteResponse)), tag = "Reconciliations", operation_id = "deleteReconciliation", security(("bearerAuth" = [])))]
+pub async fn delete_(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path((group_uid, reconciliation_uid)): Path<(Uuid, Uuid)>,
+) -> Result<Json<DeleteResponse>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for deleting reconciliation")
+    })?;
+    group_write_guard(&auth, group_uid, &mut tx, &state.membership_cache).await?;
+    get_in_group(&mut tx, group_uid, reconciliation_uid).await?;
+    ReconciliationRepo::delete(&mut tx, reconciliation_uid).await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for deleting reconciliation")
+    })?;
+    Ok(Json(DeleteResponse { success: true }))
+}
+
+/*
+ Adds unmatched rows to the group as entries, dated and priced as on the statement and named
+ after their description. All or nothing: rows already matched or unknown are rejected.
+*/
+#[utoipa::path(post, path = "/groups/{group_uid}/reconciliations/{reconciliation_uid}/entries", params(("group_uid" = Uuid, Path), ("reconciliation_uid" = Uuid, Path)), request_body = CreateReconciliationEntriesPayload, responses((status = 200, body = [ExpenseEntry])), tag = "Reconciliations", operation_id = "createReconciliationEntries", security(("bearerAuth" = [])))]
+pub async fn create_entries(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path((group_uid, reconciliation_uid)): Path<(Uuid, Uuid)>,
+    ValidatedJson(payload): ValidatedJson<CreateReconciliationEntriesPayload>,
+) -> Result<Json<Vec<ExpenseEntry>>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(
+            e,
+            "beginning transaction for creating reconciliation entries",
+        )
+    })?;
+    group_write_guard(&auth, group_uid, &mut tx, &state.membership_cache).await?;
+    let reconciliation = get_in_group(&mut tx, group_uid, reconciliation_uid).await?;
+    if let Some(category_uid) = payload.category_uid {
+        let category = CategoryRepo::get(&mut tx, category_uid).await?;
+        if category.group_uid != group_uid {
+            return Err(AppError::BadRequest(
+                "Category does not belong to the group".to_string(),
+            ));
+        }
+    }
+
+    let unmatched: Vec<ReconciliationRow> = ReconciliationRepo::rows(&mut tx, reconciliation_uid)
+        .await?
+        .into_iter()
+        .filter(|row| row.entry_uid.is_none())
+        .collect();
+    let rows = match payload.row_uids {
+        Some(row_uids) => {
+            let unmatched_uids: HashSet<Uuid> = unmatched.iter().map(|row| row.uid).collect();
+            if let Some(uid) = row_uids.iter().find(|uid| !unmatched_uids.contains(uid)) {
+                return Err(AppError::BadRequest(format!(
+                    "Row {} is not an unmatched row of the reconciliation",
+                    uid
+                )));
+            }
+            let row_uids: HashSet<Uuid> = row_uids.into_iter().collect();
+            unmatched
+                .into_iter()
+                .filter(|row| row_uids.contains(&row.uid))
+                .collect()
+        }
+        None => unmatched,
+    };
+    if rows.is_empty() {
+        return Ok(Json(Vec::new()));
+    }
+    if let Some(row) = rows.iter().find(|row| row.amount <= Decimal::ZERO) {
+        return Err(AppError::BadRequest(format!(
+            "Row {} has no amount to record",
+            row.uid
+        )));
+    }
+
+    let subscription = SubscriptionRepo::get_by_user(&mut tx, auth.user_uid).await?;
+    let usage_payload = UserUsageRepo::calculate_current_usage(&mut tx, auth.user_uid).await?;
+    check_tier_limit(
+        &subscription,
+        "expenses_per_month",
+        usage_payload.total_expenses + rows.len() as i32 - 1,
+    )?;
+
+    let descriptions: Vec<String> = rows.iter().map(|row| row.description.clone()).collect();
+    let learned_categories = match payload.category_uid {
+        Some(_) => Default::default(),
+        None => ProductRepo::categories_by_name(&mut tx, group_uid, &descriptions).await?,
+    };
+
+    let actor = AuditActor::from_auth(&auth);
+    let mut created_entries = Vec::with_capacity(rows.len());
+    for row in rows {
+        let category_uid = payload.category_uid.or_else(|| {
+            learned_categories
+                .get(&normalize_product_name(&row.description))
+                .copied()
+        });
+        let created = ExpenseEntryRepo::create_expense_entry(
+            &mut tx,
+            CreateExpenseEntryDbPayload {
+                price: row.amount,
+                currency: Some(reconciliation.currency.clone()),
+                product: row.description,
+                group_uid,
+                category_uid,
+                created_by: auth.user_uid.to_string(),
+                created_by_user_uid: Some(auth.user_uid),
+                created_at: row
+                    .posted_on
+                    .and_hms_opt(0, 0, 0)
+                    .map(|date| date.and_utc()),
+                note: None,
+                envelope_uid: None,
+            },
+        )
+        .await?;
+        ReconciliationRepo::set_created_entry(&mut tx, row.uid, created.uid).await?;
+        ProductRepo::learn(&mut tx, &created).await?;
+        AuditRepo::record(
+            &mut tx,
+            &actor,
+            AuditEntity::ExpenseEntry,
+            group_uid,
+            created.uid,
+            AuditChange::create(&created),
+        )
+        .await?;
+        webhooks::dispatch(&mut tx, group_uid, WebhookEvent::ExpenseCreated, &created).await?;
+        created_entries.push(created);
+    }
+
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(
+            e,
+            "committing transaction for creating reconciliation entries",
+        )
+    })?;
+    Ok(Json(created_entries))
+}
@@ -0,0 +1,224 @@
+use axum::{
+    Json,
+    extract::{Extension, Path, State},
+};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    auth::{
+        AuthContext,
+        group_guard::{group_guard, group_role_guard, group_write_guard},
+    },
+    error::AppError,
+    middleware::validated_json::ValidatedJson,
+    repos::{
+        audit_log::{AuditActor, AuditChange, AuditEntity, AuditRepo},
+        expense_group_member::GroupRole,
+        recurring_expense::{
+            CreateRecurringExpenseDbPayload, RecurringCadence, RecurringExpense,
+            RecurringExpenseRepo, UpdateRecurringExpenseDbPayload,
+        },
+    },
+    types::AppState,
+};
+
+pub fn router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(create))
+        .routes(routes!(list))
+        .routes(routes!(get, update, delete_))
+}
+
+fn parse_schedule(cadence: &str, run_day: i16) -> Result<RecurringCadence, AppError> {
+    let cadence = RecurringCadence::parse(cadence).ok_or_else(|| {
+        AppError::BadRequest("cadence must be either 'monthly' or 'weekly'".to_string())
+    })?;
+    if !cadence.is_valid_day(run_day) {
+        return Err(AppError::BadRequest(format!(
+            "run_day {} is not valid for {} cadence",
+            run_day,
+            cadence.as_str()
+        )));
+    }
+    Ok(cadence)
+}
+
+#[utoipa::path(get, path = "/groups/{group_uid}/recurring-expenses", params(("group_uid" = Uuid, Path)), responses((status = 200, body = [RecurringExpense])), tag = "Recurring Expenses", operation_id = "listRecurringExpenses", security(("bearerAuth" = [])))]
+pub async fn list(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(group_uid): Path<Uuid>,
+) -> Result<Json<Vec<RecurringExpense>>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for listing recurring expenses")
+    })?;
+    group_guard(&auth, group_uid, &mut tx, &state.membership_cache).await?;
+    let res = RecurringExpenseRepo::list_by_group(&mut tx, group_uid).await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for listing recurring expenses")
+    })?;
+    Ok(Json(res))
+}
+
+#[derive(Debug, Deserialize, ToSchema, Validate)]
+pub struct CreateRecurringExpensePayload {
+    pub group_uid: Uuid,
+    pub product: String,
+    pub price: Decimal,
+    pub category_uid: Option<Uuid>,
+    /// `monthly` or `weekly`
+    pub cadence: String,
+    /// Day of month (1-31) for monthly, ISO weekday (1 = Monday) for weekly
+    pub run_day: i16,
+}
+
+#[utoipa::path(post, path = "/recurring-expenses", request_body = CreateRecurringExpensePayload, responses((status = 200, body = RecurringExpense)), tag = "Recurring Expenses", operation_id = "createRecurringExpense", security(("bearerAuth" = [])))]
+pub async fn create(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    ValidatedJson(payload): ValidatedJson<CreateRecurringExpensePayload>,
+) -> Result<Json<RecurringExpense>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for creating recurring expense")
+    })?;
+    group_write_guard(&auth, payload.group_uid, &mut tx, &state.membership_cache).await?;
+    let cadence = parse_schedule(&payload.cadence, payload.run_day)?;
+    let created = RecurringExpenseRepo::create(
+        &mut tx,
+        CreateRecurringExpenseDbPayload {
+            group_uid: payload.group_uid,
+            product: payload.product,
+            price: payload.price,
+            category_uid: payload.category_uid,
+            cadence,
+            run_day: payload.run_day,
+        },
+    )
+    .await?;
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::RecurringExpense,
+        created.group_uid,
+        created.uid,
+        AuditChange::create(&created),
+    )
+    .await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for creating recurring expense")
+    })?;
+    Ok(Json(created))
+}
+
+#[utoipa::path(get, path = "/recurring-expenses/{uid}", params(("uid" = Uuid, Path)), responses((status = 200, body = RecurringExpense)), tag = "Recurring Expenses", operation_id = "getRecurringExpense", security(("bearerAuth" = [])))]
+pub async fn get(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(uid): Path<Uuid>,
+) -> Result<Json<RecurringExpense>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for getting recurring expense")
+    })?;
+    let rec = RecurringExpenseRepo::get(&mut tx, uid).await?;
+    group_guard(&auth, rec.group_uid, &mut tx, &state.membership_cache).await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for getting recurring expense")
+    })?;
+    Ok(Json(rec))
+}
+
+#[derive(Debug, Deserialize, ToSchema, Validate)]
+pub struct UpdateRecurringExpensePayload {
+    pub product: Option<String>,
+    pub price: Option<Decimal>,
+    pub category_uid: Option<Uuid>,
+    pub cadence: Option<String>,
+    pub run_day: Option<i16>,
+    pub active: Option<bool>,
+}
+
+#[utoipa::path(put, path = "/recurring-expenses/{uid}", params(("uid" = Uuid, Path)), request_body = UpdateRecurringExpensePayload, responses((status = 200, body = RecurringExpense)), tag = "Recurring Expenses", operation_id = "updateRecurringExpense", security(("bearerAuth" = [])))]
+pub async fn update(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(uid): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<UpdateRecurringExpensePayload>,
+) -> Result<Json<RecurringExpense>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for updating recurring expense")
+    })?;
+    let prev_rec = RecurringExpenseRepo::get(&mut tx, uid).await?;
+    group_guard(&auth, prev_rec.group_uid, &mut tx, &state.membership_cache).await?;
+
+    // Validate the resulting schedule, not just the fields that changed
+    let cadence = parse_schedule(
+        payload.cadence.as_deref().unwrap_or(&prev_rec.cadence),
+        payload.run_day.unwrap_or(prev_rec.run_day),
+    )?;
+
+    let updated = RecurringExpenseRepo::update(
+        &mut tx,
+        uid,
+        UpdateRecurringExpenseDbPayload {
+            product: payload.product,
+            price: payload.price,
+            category_uid: payload.category_uid.map(Some),
+            cadence: Some(cadence),
+            run_day: payload.run_day,
+            active: payload.active,
+        },
+    )
+    .await?;
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::RecurringExpense,
+        updated.group_uid,
+        updated.uid,
+        AuditChange::update(&prev_rec, &updated),
+    )
+    .await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for updating recurring expense")
+    })?;
+    Ok(Json(updated))
+}
+
+#[utoipa::path(delete, path = "/recurring-expenses/{uid}", params(("uid" = Uuid, Path)), responses((status = 200, description = "Deleted")), tag = "Recurring Expenses", operation_id = "deleteRecurringExpense", security(("bearerAuth" = [])))]
+pub async fn delete_(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(uid): Path<Uuid>,
+) -> Result<(), AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for deleting recurring expense")
+    })?;
+    let prev_rec = RecurringExpenseRepo::get(&mut tx, uid).await?;
+    group_role_guard(
+        &auth,
+        prev_rec.group_uid,
+        &mut tx,
+        &state.membership_cache,
+        GroupRole::Admin,
+    )
+    .await?;
+    RecurringExpenseRepo::delete(&mut tx, uid).await?;
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::RecurringExpense,
+        prev_rec.group_uid,
+        uid,
+        AuditChange::delete(&prev_rec),
+    )
+    .await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for deleting recurring expense")
+    })?;
+    Ok(())
+}
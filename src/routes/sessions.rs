@@ -0,0 +1,56 @@
+use axum::{
+    Json,
+    extract::{Extension, Path, State},
+};
+use utoipa_axum::{router::OpenApiRouter, routes};
+use uuid::Uuid;
+
+use crate::{
+    auth::{AuthContext, reject_api_key_auth},
+    error::AppError,
+    repos::refresh_token::{RefreshTokenRepo, Session},
+    types::AppState,
+};
+
+pub fn router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(list))
+        .routes(routes!(revoke))
+}
+
+// Devices the user is signed in on, the most recently used first
+#[utoipa::path(get, path = "/users/me/sessions", responses((status = 200, body = [Session])), tag = "Users", operation_id = "listSessions", security(("bearerAuth" = [])))]
+pub async fn list(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+) -> Result<Json<Vec<Session>>, AppError> {
+    reject_api_key_auth(&auth)?;
+    let mut tx =
+        state.db_pool.begin().await.map_err(|e| {
+            AppError::from_sqlx_error(e, "beginning transaction for listing sessions")
+        })?;
+    let res = RefreshTokenRepo::list_sessions(&mut tx, auth.user_uid, auth.session_uid).await?;
+    tx.commit()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "committing transaction for listing sessions"))?;
+    Ok(Json(res))
+}
+
+// Signs the device out, its refresh token and access tokens fail right away
+#[utoipa::path(delete, path = "/users/me/sessions/{uid}", params(("uid" = Uuid, Path)), responses((status = 200, description = "Signed out"), (status = 404, description = "Not found")), tag = "Users", operation_id = "revokeSession", security(("bearerAuth" = [])))]
+pub async fn revoke(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(uid): Path<Uuid>,
+) -> Result<(), AppError> {
+    reject_api_key_auth(&auth)?;
+    let mut tx =
+        state.db_pool.begin().await.map_err(|e| {
+            AppError::from_sqlx_error(e, "beginning transaction for revoking session")
+        })?;
+    RefreshTokenRepo::delete_session(&mut tx, auth.user_uid, uid).await?;
+    tx.commit()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "committing transaction for revoking session"))?;
+    Ok(())
+}
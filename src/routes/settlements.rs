@@ -0,0 +1,220 @@
+use axum::{
+    Json,
+    extract::{Extension, Path, State},
+};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    auth::{AuthContext, group_guard::group_guard},
+    error::AppError,
+    middleware::validated_json::ValidatedJson,
+    repos::{
+        audit_log::{AuditActor, AuditChange, AuditEntity, AuditRepo},
+        expense_group::ExpenseGroupRepo,
+        expense_group_member::GroupMemberRepo,
+        settlement::{CreateSettlementDbPayload, MemberBalance, Settlement, SettlementRepo},
+    },
+    routes::currencies::parse_currency,
+    types::{AppState, DeleteResponse},
+};
+
+pub fn router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(list, create))
+        .routes(routes!(delete_))
+        .routes(routes!(confirm))
+        .routes(routes!(balances))
+}
+
+/*
+Permissions:
+- every member can list the settlements and balances of the group
+- a member records a settlement as the payer, towards another member
+- only the receiving member can confirm it
+- only the payer can withdraw it, as long as it is not confirmed
+ */
+
+fn validate_settlement(from: Uuid, to: Uuid, amount: Decimal) -> Result<(), AppError> {
+    if from == to {
+        return Err(AppError::BadRequest(
+            "Cannot settle with yourself".to_string(),
+        ));
+    }
+    if amount <= Decimal::ZERO {
+        return Err(AppError::BadRequest(
+            "amount must be greater than zero".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[utoipa::path(get, path = "/groups/{group_uid}/settlements", params(("group_uid" = Uuid, Path)), responses((status = 200, body = [Settlement])), tag = "Settlements", operation_id = "listSettlements", security(("bearerAuth" = [])))]
+pub async fn list(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(group_uid): Path<Uuid>,
+) -> Result<Json<Vec<Settlement>>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for listing settlements"))?;
+    group_guard(&auth, group_uid, &mut tx, &state.membership_cache).await?;
+    let res = SettlementRepo::list_by_group(&mut tx, group_uid).await?;
+    tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for listing settlements"))?;
+    Ok(Json(res))
+}
+
+// Where every member stands in the group's currency, confirmed settlements included
+#[utoipa::path(get, path = "/groups/{group_uid}/balances", params(("group_uid" = Uuid, Path)), responses((status = 200, body = [MemberBalance])), tag = "Settlements", operation_id = "listMemberBalances", security(("bearerAuth" = [])))]
+pub async fn balances(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(group_uid): Path<Uuid>,
+) -> Result<Json<Vec<MemberBalance>>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for listing balances"))?;
+    group_guard(&auth, group_uid, &mut tx, &state.membership_cache).await?;
+    let group = ExpenseGroupRepo::get(&mut tx, group_uid).await?;
+    let res = SettlementRepo::member_balances(&mut tx, group_uid, &group.currency).await?;
+    tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for listing balances"))?;
+    Ok(Json(res))
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct CreateSettlementPayload {
+    /// Member who received the money, the caller is the payer
+    pub to_user_uid: Uuid,
+    pub amount: Decimal,
+    /// ISO 4217 code, defaults to the group's currency
+    pub currency: Option<String>,
+    pub note: Option<String>,
+}
+
+#[utoipa::path(post, path = "/groups/{group_uid}/settlements", params(("group_uid" = Uuid, Path)), request_body = CreateSettlementPayload, responses((status = 200, body = Settlement)), tag = "Settlements", operation_id = "createSettlement", security(("bearerAuth" = [])))]
+pub async fn create(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(group_uid): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<CreateSettlementPayload>,
+) -> Result<Json<Settlement>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for creating settlement"))?;
+    group_guard(&auth, group_uid, &mut tx, &state.membership_cache).await?;
+    validate_settlement(auth.user_uid, payload.to_user_uid, payload.amount)?;
+    let currency = parse_currency(payload.currency.as_deref())?;
+    let group = ExpenseGroupRepo::get(&mut tx, group_uid).await?;
+    let is_member = group.owner == payload.to_user_uid
+        || GroupMemberRepo::find_by_group_and_user(&mut tx, group_uid, payload.to_user_uid)
+            .await?
+            .is_some();
+    if !is_member {
+        return Err(AppError::BadRequest(
+            "to_user_uid must be a member of the group".to_string(),
+        ));
+    }
+    let created = SettlementRepo::create(
+        &mut tx,
+        CreateSettlementDbPayload {
+            group_uid,
+            from_user_uid: auth.user_uid,
+            to_user_uid: payload.to_user_uid,
+            amount: payload.amount,
+            currency: currency.unwrap_or(group.currency),
+            note: payload.note,
+        },
+    )
+    .await?;
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::Settlement,
+        created.group_uid,
+        created.uid,
+        AuditChange::create(&created),
+    )
+    .await?;
+    tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for creating settlement"))?;
+    Ok(Json(created))
+}
+
+#[utoipa::path(post, path = "/settlements/{uid}/confirm", params(("uid" = Uuid, Path)), responses((status = 200, body = Settlement)), tag = "Settlements", operation_id = "confirmSettlement", security(("bearerAuth" = [])))]
+pub async fn confirm(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(uid): Path<Uuid>,
+) -> Result<Json<Settlement>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for confirming settlement"))?;
+    let prev_settlement = SettlementRepo::get(&mut tx, uid).await?;
+    group_guard(&auth, prev_settlement.group_uid, &mut tx, &state.membership_cache).await?;
+    if prev_settlement.to_user_uid != auth.user_uid {
+        return Err(AppError::Unauthorized(
+            "Only the receiving member can confirm a settlement".to_string(),
+        ));
+    }
+    if prev_settlement.is_confirmed() {
+        return Err(AppError::BadRequest(
+            "Settlement is already confirmed".to_string(),
+        ));
+    }
+    let confirmed = SettlementRepo::confirm(&mut tx, uid).await?;
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::Settlement,
+        confirmed.group_uid,
+        confirmed.uid,
+        AuditChange::update(&prev_settlement, &confirmed),
+    )
+    .await?;
+    tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for confirming settlement"))?;
+    Ok(Json(confirmed))
+}
+
+#[utoipa::path(delete, path = "/settlements/{uid}", params(("uid" = Uuid, Path)), responses((status = 200, body = DeleteResponse)), tag = "Settlements", operation_id = "deleteSettlement", security(("bearerAuth" = [])))]
+pub async fn delete_(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(uid): Path<Uuid>,
+) -> Result<Json<DeleteResponse>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for deleting settlement"))?;
+    let prev_settlement = SettlementRepo::get(&mut tx, uid).await?;
+    group_guard(&auth, prev_settlement.group_uid, &mut tx, &state.membership_cache).await?;
+    if prev_settlement.from_user_uid != auth.user_uid {
+        return Err(AppError::Unauthorized(
+            "Only the paying member can withdraw a settlement".to_string(),
+        ));
+    }
+    if prev_settlement.is_confirmed() {
+        return Err(AppError::BadRequest(
+            "A confirmed settlement cannot be withdrawn".to_string(),
+        ));
+    }
+    SettlementRepo::delete(&mut tx, uid).await?;
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::Settlement,
+        prev_settlement.group_uid,
+        uid,
+        AuditChange::delete(&prev_settlement),
+    )
+    .await?;
+    tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for deleting settlement"))?;
+    Ok(Json(DeleteResponse { success: true }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_validate_settlement() {
+        let from = Uuid::new_v4();
+        let to = Uuid::new_v4();
+        assert!(validate_settlement(from, to, dec!(50000)).is_ok());
+        assert!(validate_settlement(from, from, dec!(50000)).is_err());
+        assert!(validate_settlement(from, to, Decimal::ZERO).is_err());
+        assert!(validate_settlement(from, to, dec!(-1)).is_err());
+    }
+}
@@ -0,0 +1,360 @@
+use std::collections::HashMap;
+
+use axum::{
+    Json,
+    extract::{Extension, Path, Query, State},
+};
+use chrono::{Duration, Months, NaiveDate, Utc};
+use rust_decimal::{Decimal, prelude::ToPrimitive};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use utoipa_axum::{router::OpenApiRouter, routes};
+use uuid::Uuid;
+
+use crate::{
+    auth::{AuthContext, group_guard::group_guard},
+    error::AppError,
+    repos::{
+        exchange_rate::ExchangeRateRepo,
+        expense_entry::{ExpenseEntryRepo, StatsGranularity},
+        expense_group::ExpenseGroupRepo,
+        tag::TagRepo,
+    },
+    types::AppState,
+    utils::{
+        currency::RateTable,
+        forecast::{GroupForecast, group_forecast},
+    },
+};
+
+pub fn router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(get))
+        .routes(routes!(forecast))
+}
+
+// Longest range a single request may cover, keeps daily buckets chartable
+const MAX_RANGE_DAYS: i64 = 731;
+const TOP_PRODUCTS: i64 = 10;
+
+#[derive(Debug, Default, Serialize, Deserialize, IntoParams)]
+pub struct StatsQuery {
+    /// One of `day`, `week` (starting Monday) or `month`. Defaults to `day`
+    pub granularity: Option<String>,
+    /// First day included (YYYY-MM-DD), defaults to 30 days, 12 weeks or 12 months before `to`
+    pub from: Option<NaiveDate>,
+    /// Last day included (YYYY-MM-DD), defaults to today
+    pub to: Option<NaiveDate>,
+    /// Only entries carrying this tag
+    pub tag_uid: Option<Uuid>,
+}
+
+impl StatsQuery {
+    fn resolve(
+        self,
+        today: NaiveDate,
+    ) -> Result<(StatsGranularity, NaiveDate, NaiveDate), AppError> {
+        let granularity = match self.granularity.as_deref() {
+            Some(granularity) => StatsGranularity::parse(granularity).ok_or_else(|| {
+                AppError::BadRequest(format!("Invalid granularity: {}", granularity))
+            })?,
+            None => StatsGranularity::default(),
+        };
+        let to = self.to.unwrap_or(today);
+        let from = self.from.unwrap_or_else(|| match granularity {
+            StatsGranularity::Day => to - Duration::days(29),
+            StatsGranularity::Week => granularity.truncate(to) - Duration::weeks(11),
+            StatsGranularity::Month => granularity.truncate(to) - Months::new(11),
+        });
+        if from > to {
+            return Err(AppError::BadRequest(
+                "`from` must not be after `to`".to_string(),
+            ));
+        }
+        if (to - from).num_days() >= MAX_RANGE_DAYS {
+            return Err(AppError::BadRequest(format!(
+                "The range can cover at most {} days",
+                MAX_RANGE_DAYS
+            )));
+        }
+        Ok((granularity, from, to))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct StatsBucket {
+    /// First day of the bucket, buckets without entries are included with zero totals
+    pub start: NaiveDate,
+    pub total: Decimal,
+    pub count: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CategoryStats {
+    /// None for uncategorized entries
+    pub category_name: Option<String>,
+    pub total: Decimal,
+    pub percentage: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ProductStats {
+    pub product: String,
+    pub count: i64,
+    pub total: Decimal,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct GroupStats {
+    /// Currency every amount is reported in, the group's currency
+    pub currency: String,
+    pub granularity: String,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub total: Decimal,
+    pub count: i64,
+    pub average_transaction: Decimal,
+    pub buckets: Vec<StatsBucket>,
+    /// Largest first
+    pub categories: Vec<CategoryStats>,
+    /// Most frequently bought first
+    pub top_products: Vec<ProductStats>,
+    /// Currencies without a known exchange rate, their amounts are added unconverted
+    pub unconverted_currencies: Vec<String>,
+}
+
+/*
+ Spending of a group between `from` and `to` (inclusive) for the web dashboard: totals per
+ time bucket, per category and for the most bought products, all aggregated in SQL and
+ converted to the group's currency.
+*/
+#[utoipa::path(get, path = "/groups/{group_uid}/stats", params(("group_uid" = Uuid, Path), StatsQuery), responses((status = 200, body = GroupStats)), tag = "Expense Entries", operation_id = "getGroupStats", security(("bearerAuth" = [])))]
+pub async fn get(
+    State(state): State<AppState>,
+    Path(group_uid): Path<Uuid>,
+    Extension(auth): Extension<AuthContext>,
+    Query(query): Query<StatsQuery>,
+) -> Result<Json<GroupStats>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for getting group stats"))?;
+    group_guard(&auth, group_uid, &mut tx, &state.membership_cache).await?;
+    let tag_uid = query.tag_uid;
+    let (granularity, from, to) = query.resolve(Utc::now().date_naive())?;
+    let start = from.and_hms_opt(0, 0, 0).unwrap().and_utc();
+    // `to` is inclusive, so stop at the start of the following day
+    let end = (to + Duration::days(1))
+        .and_hms_opt(0, 0, 0)
+        .unwrap()
+        .and_utc();
+    let group = ExpenseGroupRepo::get(&mut tx, group_uid).await?;
+    let rates = ExchangeRateRepo::rate_table(&mut tx).await?;
+    if let Some(tag_uid) = tag_uid {
+        let tag = TagRepo::get(&mut tx, tag_uid).await?;
+        if tag.group_uid != group_uid {
+            return Err(AppError::BadRequest(
+                "tag_uid must be a tag of this group".to_string(),
+            ));
+        }
+    }
+    let bucket_totals =
+        ExpenseEntryRepo::totals_by_bucket(&mut tx, group_uid, tag_uid, granularity, start, end)
+            .await?;
+    let category_totals = ExpenseEntryRepo::totals_by_category_in_range(
+        &mut tx, group_uid, None, tag_uid, true, start, end,
+    )
+    .await?;
+    let product_totals = ExpenseEntryRepo::top_products_in_range(
+        &mut tx,
+        group_uid,
+        tag_uid,
+        start,
+        end,
+        TOP_PRODUCTS,
+    )
+    .await?;
+    tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for getting group stats"))?;
+
+    let mut converter = Converter::new(&rates, &group.currency);
+
+    let mut buckets: Vec<StatsBucket> = granularity
+        .bucket_starts(from, to)
+        .into_iter()
+        .map(|start| StatsBucket {
+            start,
+            total: Decimal::ZERO,
+            count: 0,
+        })
+        .collect();
+    for (bucket_start, currency, amount, count) in bucket_totals {
+        if let Some(bucket) = buckets
+            .iter_mut()
+            .find(|bucket| bucket.start == bucket_start)
+        {
+            bucket.total += converter.convert(amount, &currency);
+            bucket.count += count;
+        }
+    }
+    let total: Decimal = buckets.iter().map(|bucket| bucket.total).sum();
+    let count: i64 = buckets.iter().map(|bucket| bucket.count).sum();
+    let average_transaction = if count > 0 {
+        (total / Decimal::from(count)).round_dp(2)
+    } else {
+        Decimal::ZERO
+    };
+
+    let mut category_sums: HashMap<Option<String>, Decimal> = HashMap::new();
+    for (category, currency, amount) in category_totals {
+        *category_sums.entry(category).or_default() += converter.convert(amount, &currency);
+    }
+    let mut categories: Vec<CategoryStats> = category_sums
+        .into_iter()
+        .map(|(category_name, category_total)| CategoryStats {
+            category_name,
+            percentage: percentage_of(category_total, total),
+            total: category_total,
+        })
+        .collect();
+    categories.sort_by(|a, b| b.total.cmp(&a.total));
+
+    // Rows come ordered by how often the product was bought, one row per currency
+    let mut top_products: Vec<ProductStats> = Vec::new();
+    for (product, currency, amount, product_count) in product_totals {
+        let amount = converter.convert(amount, &currency);
+        match top_products.iter_mut().find(|p| p.product == product) {
+            Some(existing) => {
+                existing.total += amount;
+                existing.count += product_count;
+            }
+            None => top_products.push(ProductStats {
+                product,
+                count: product_count,
+                total: amount,
+            }),
+        }
+    }
+
+    Ok(Json(GroupStats {
+        currency: group.currency.clone(),
+        granularity: granularity.as_str().to_string(),
+        from,
+        to,
+        total,
+        count,
+        average_transaction,
+        buckets,
+        categories,
+        top_products,
+        unconverted_currencies: converter.unconverted,
+    }))
+}
+
+/*
+ Where the group's spending is heading by the end of the current cycle, in total and per
+ category, with the range it is likely to end up in. See `utils::forecast` for the model.
+*/
+#[utoipa::path(get, path = "/groups/{group_uid}/forecast", params(("group_uid" = Uuid, Path)), responses((status = 200, body = GroupForecast)), tag = "Expense Entries", operation_id = "getGroupForecast", security(("bearerAuth" = [])))]
+pub async fn forecast(
+    State(state): State<AppState>,
+    Path(group_uid): Path<Uuid>,
+    Extension(auth): Extension<AuthContext>,
+) -> Result<Json<GroupForecast>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for getting group forecast")
+    })?;
+    group_guard(&auth, group_uid, &mut tx, &state.membership_cache).await?;
+    let group = ExpenseGroupRepo::get(&mut tx, group_uid).await?;
+    let forecast = group_forecast(&mut tx, &group, Utc::now().date_naive()).await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for getting group forecast")
+    })?;
+    Ok(Json(forecast))
+}
+
+// Converts into the report currency and remembers currencies without a known rate
+struct Converter<'a> {
+    rates: &'a RateTable,
+    to: &'a str,
+    unconverted: Vec<String>,
+}
+
+impl<'a> Converter<'a> {
+    fn new(rates: &'a RateTable, to: &'a str) -> Self {
+        Self {
+            rates,
+            to,
+            unconverted: Vec::new(),
+        }
+    }
+
+    fn convert(&mut self, amount: Decimal, currency: &str) -> Decimal {
+        match self.rates.convert(amount, currency, self.to) {
+            Some(converted) => converted,
+            None => {
+                if !self.unconverted.iter().any(|c| c == currency) {
+                    self.unconverted.push(currency.to_string());
+                }
+                amount
+            }
+        }
+    }
+}
+
+fn percentage_of(amount: Decimal, total: Decimal) -> f64 {
+    if total.is_zero() {
+        return 0.0;
+    }
+    (amount / total * Decimal::ONE_HUNDRED)
+        .to_f64()
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn query(
+        granularity: Option<&str>,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> StatsQuery {
+        StatsQuery {
+            granularity: granularity.map(|g| g.to_string()),
+            from,
+            to,
+            tag_uid: None,
+        }
+    }
+
+    #[test]
+    fn test_resolve_defaults() {
+        let today = date(2025, 9, 17);
+        let (granularity, from, to) = query(None, None, None).resolve(today).unwrap();
+        assert_eq!(granularity, StatsGranularity::Day);
+        assert_eq!((from, to), (date(2025, 8, 19), today));
+
+        let (_, from, _) = query(Some("week"), None, None).resolve(today).unwrap();
+        assert_eq!(from, date(2025, 6, 30));
+
+        let (_, from, _) = query(Some("month"), None, None).resolve(today).unwrap();
+        assert_eq!(from, date(2024, 10, 1));
+    }
+
+    #[test]
+    fn test_resolve_rejects_invalid_ranges() {
+        let today = date(2025, 9, 17);
+        assert!(query(Some("year"), None, None).resolve(today).is_err());
+        assert!(
+            query(None, Some(date(2025, 9, 18)), Some(today))
+                .resolve(today)
+                .is_err()
+        );
+        assert!(
+            query(None, Some(date(2020, 1, 1)), Some(today))
+                .resolve(today)
+                .is_err()
+        );
+    }
+}
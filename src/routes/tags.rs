@@ -0,0 +1,157 @@
+use axum::{
+    Json,
+    extract::{Extension, Path, State},
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    auth::{
+        AuthContext,
+        group_guard::{group_guard, group_role_guard},
+    },
+    error::AppError,
+    middleware::validated_json::ValidatedJson,
+    repos::{
+        audit_log::{AuditActor, AuditChange, AuditEntity, AuditRepo},
+        expense_group_member::GroupRole,
+        tag::{CreateTagDbPayload, Tag, TagRepo, normalize_tag_name},
+    },
+    types::{AppState, DeleteResponse},
+};
+
+pub fn router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(list, create))
+        .routes(routes!(update, delete_))
+}
+
+fn parse_tag_name(name: &str) -> Result<String, AppError> {
+    normalize_tag_name(name)
+        .ok_or_else(|| AppError::BadRequest(format!("Invalid tag: {}", name.trim())))
+}
+
+// Normalizes tag names from a request body, duplicates are dropped
+pub fn parse_tag_names(names: &[String]) -> Result<Vec<String>, AppError> {
+    let mut parsed: Vec<String> = Vec::with_capacity(names.len());
+    for name in names {
+        let tag = parse_tag_name(name)?;
+        if !parsed.contains(&tag) {
+            parsed.push(tag);
+        }
+    }
+    Ok(parsed)
+}
+
+#[utoipa::path(get, path = "/groups/{group_uid}/tags", params(("group_uid" = Uuid, Path)), responses((status = 200, body = [Tag])), tag = "Tags", operation_id = "listTags", security(("bearerAuth" = [])))]
+pub async fn list(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(group_uid): Path<Uuid>,
+) -> Result<Json<Vec<Tag>>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for listing tags"))?;
+    group_guard(&auth, group_uid, &mut tx, &state.membership_cache).await?;
+    let res = TagRepo::list_by_group(&mut tx, group_uid).await?;
+    tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for listing tags"))?;
+    Ok(Json(res))
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Validate)]
+pub struct TagPayload {
+    /// Letters, digits, `_` and `-`, stored lowercase; a leading `#` is ignored
+    pub name: String,
+}
+
+#[utoipa::path(post, path = "/groups/{group_uid}/tags", params(("group_uid" = Uuid, Path)), request_body = TagPayload, responses((status = 200, body = Tag)), tag = "Tags", operation_id = "createTag", security(("bearerAuth" = [])))]
+pub async fn create(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(group_uid): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<TagPayload>,
+) -> Result<Json<Tag>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for creating tag"))?;
+    group_guard(&auth, group_uid, &mut tx, &state.membership_cache).await?;
+    let name = parse_tag_name(&payload.name)?;
+    let created = TagRepo::create(&mut tx, CreateTagDbPayload { group_uid, name }).await?;
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::Tag,
+        created.group_uid,
+        created.uid,
+        AuditChange::create(&created),
+    )
+    .await?;
+    tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for creating tag"))?;
+    Ok(Json(created))
+}
+
+#[utoipa::path(put, path = "/tags/{uid}", params(("uid" = Uuid, Path)), request_body = TagPayload, responses((status = 200, body = Tag)), tag = "Tags", operation_id = "updateTag", security(("bearerAuth" = [])))]
+pub async fn update(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(uid): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<TagPayload>,
+) -> Result<Json<Tag>, AppError> {
+    let name = parse_tag_name(&payload.name)?;
+    let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for updating tag"))?;
+    let prev_tag = TagRepo::get(&mut tx, uid).await?;
+    group_guard(&auth, prev_tag.group_uid, &mut tx, &state.membership_cache).await?;
+    let updated = TagRepo::rename(&mut tx, uid, name).await?;
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::Tag,
+        updated.group_uid,
+        updated.uid,
+        AuditChange::update(&prev_tag, &updated),
+    )
+    .await?;
+    tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for updating tag"))?;
+    Ok(Json(updated))
+}
+
+#[utoipa::path(delete, path = "/tags/{uid}", params(("uid" = Uuid, Path)), responses((status = 200, body = DeleteResponse)), tag = "Tags", operation_id = "deleteTag", security(("bearerAuth" = [])))]
+pub async fn delete_(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(uid): Path<Uuid>,
+) -> Result<Json<DeleteResponse>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for deleting tag"))?;
+    let prev_tag = TagRepo::get(&mut tx, uid).await?;
+    group_role_guard(&auth, prev_tag.group_uid, &mut tx, &state.membership_cache, GroupRole::Admin).await?;
+    TagRepo::delete(&mut tx, uid).await?;
+    AuditRepo::record(
+        &mut tx,
+        &AuditActor::from_auth(&auth),
+        AuditEntity::Tag,
+        prev_tag.group_uid,
+        uid,
+        AuditChange::delete(&prev_tag),
+    )
+    .await?;
+    tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for deleting tag"))?;
+    Ok(Json(DeleteResponse { success: true }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tag_names() {
+        let names = vec![
+            "#Vacation".to_string(),
+            "bali".to_string(),
+            "vacation".to_string(),
+        ];
+        assert_eq!(
+            parse_tag_names(&names).unwrap(),
+            vec!["vacation".to_string(), "bali".to_string()]
+        );
+        assert!(parse_tag_names(&["road trip".to_string()]).is_err());
+    }
+}
@@ -5,47 +5,35 @@ use argon2::{
     password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString, rand_core::OsRng},
 };
 use axum::{
-    extract::{Path, State}, Extension, Json
+    extract::{Path, State}, http::{header::USER_AGENT, HeaderMap}, Extension, Json
 };
+use std::collections::HashMap;
 use serde::Deserialize;
 use tracing::info;
 use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
 use uuid::Uuid;
 use validator::Validate;
 
 use crate::{
-    auth::AuthContext, error::AppError, repos::{
-        expense_group::{CreateExpenseGroupDbPayload, ExpenseGroupRepo}, subscription::{CreateSubscriptionDbPayload, SubscriptionRepo}, user::{CreateUserDbPayload, UserRead, UserRepo}
-    }, types::{AppState, SubscriptionTier}
+    auth::{jwt::JwtKeys, reject_api_key_auth, totp, AuthContext, ACCESS_TOKEN_TTL_SECONDS, REFRESH_TOKEN_TTL_DAYS}, error::{AppError, DatabaseError}, lang, repos::{
+        expense_group::{CreateExpenseGroupDbPayload, ExpenseGroupRepo}, password_reset_token::{CreatePasswordResetTokenDbPayload, PasswordResetTokenRepo}, refresh_token::{CreateRefreshTokenDbPayload, RefreshTokenRepo}, subscription::{CreateSubscriptionDbPayload, SubscriptionRepo}, user::{CreateUserDbPayload, UserRead, UserRepo, UserRole}, user_mfa::UserMfaRepo
+    }, types::{AppState, SubscriptionTier}, utils::currency::DEFAULT_CURRENCY,
+    middleware::{client_ip::ClientIp, validated_json::ValidatedJson},
 };
 
-pub fn router() -> axum::Router<AppState> {
-    axum::Router::new()
-        // .route("/users", axum::routing::get(list_users))
-        .route(
-            "/users/{uid}",
-            axum::routing::put(update_user),
-        )
-        .route("/users/me", axum::routing::get(get_me)) // alias for get_user
-        .route("/auth/register", axum::routing::post(create_user))
-        .route("/auth/login", axum::routing::post(login_user))
-    
-}
-
-// TODO: restrict to admin users only
-#[utoipa::path(
-    get, 
-    path = "/users", 
-    responses((status = 200, body = [UserRead])), 
-    tag = "Users", 
-    operation_id = "listUsers", 
-    security(("bearerAuth" = []))
-)]
-pub async fn list_users(State(state): State<AppState>) -> Result<Json<Vec<UserRead>>, AppError> {
-    let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for listing users"))?;
-    let res = UserRepo::list(&mut tx).await?;
-    tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for listing users"))?;
-    Ok(Json(res))
+pub fn router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(update_user))
+        .routes(routes!(get_me, update_me))
+        .routes(routes!(enroll_mfa))
+        .routes(routes!(verify_mfa))
+        .routes(routes!(create_user))
+        .routes(routes!(login_user))
+        .routes(routes!(refresh_session))
+        .routes(routes!(logout))
+        .routes(routes!(forgot_password))
+        .routes(routes!(reset_password))
 }
 
 #[derive(Debug, Deserialize, serde::Serialize, ToSchema, Validate)]
@@ -59,9 +47,10 @@ pub struct CreateUserPayload {
 #[utoipa::path(post, path = "/auth/register", request_body = CreateUserPayload, responses((status = 200, body = UserRead)), tag = "Users", operation_id = "createUser")]
 pub async fn create_user(
     State(state): State<AppState>,
-    Json(payload): Json<CreateUserPayload>,
+    headers: HeaderMap,
+    client_ip: Option<Extension<ClientIp>>,
+    ValidatedJson(payload): ValidatedJson<CreateUserPayload>,
 ) -> Result<Json<LoginResponse>, AppError> {
-    payload.validate().map_err(|e| AppError::BadRequest(e.to_string()))?;
     let salt = SaltString::generate(&mut OsRng);
     let phash = argon2::Argon2::default()
         .hash_password(payload.password.as_bytes(), &salt)
@@ -84,6 +73,7 @@ pub async fn create_user(
             name: "Default".to_string(),
             owner: user.uid,
             start_over_date: 1,
+            currency: DEFAULT_CURRENCY.to_string(),
         },
     )
     .await?;
@@ -116,19 +106,18 @@ pub async fn create_user(
         },
     ).await?;
 
+    // Issue JWT for web clients
+    let tokens = issue_session(&mut tx, new_session(user.uid, &headers, client_ip), &state.jwt_keys).await?;
 
     tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for creating user"))?;
 
-    // Issue JWT for web clients
-    let token = crate::auth::encode_web_jwt(user.uid, &state.jwt_secret, 60 * 60 * 24 * 7)
-        .map_err(AppError::Internal)?;
-
     info!("Created new user: {}", user.email);
     Ok(Json(LoginResponse {
-        token,
+        tokens,
         user: UserRead {
             uid: user.uid,
             email: user.email,
+            lang: user.lang,
         },
     }))
 }
@@ -155,23 +144,39 @@ pub async fn get_me(
     Ok(Json(user))
 }
 
-// TODO: restrict to admin users or the user themselves
 #[derive(Deserialize, ToSchema, Validate)]
 pub struct UpdateUserPayload {
     #[validate(email)]
     pub email: Option<String>,
     #[validate(length(min = 8))]
     pub password: Option<String>,
+    /// Language of emails and chat replies, `id` or `en`
+    pub lang: Option<String>,
 }
 
 #[utoipa::path(put, path = "/users/{uid}", params(("uid" = Uuid, Path)), request_body = UpdateUserPayload, responses((status = 200, body = UserRead)), tag = "Users", operation_id = "updateUser", security(("bearerAuth" = [])))]
 pub async fn update_user(
     State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
     Path(uid): Path<Uuid>,
-    Json(payload): Json<UpdateUserPayload>,
+    ValidatedJson(payload): ValidatedJson<UpdateUserPayload>,
 ) -> Result<Json<UserRead>, AppError> {
-    payload.validate().map_err(|e| AppError::BadRequest(e.to_string()))?;
+    // Also covers PUT /users/me, a key must not be able to take over the account
+    reject_api_key_auth(&auth)?;
+    if let Some(code) = payload.lang.as_deref().filter(|code| !lang::is_supported(code)) {
+        return Err(AppError::BadRequest(format!(
+            "Unsupported language: {}, expected one of {}",
+            code,
+            lang::SUPPORTED_LANGS.join(", ")
+        )));
+    }
     let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for updating user"))?;
+    // Users may update themselves, admins may update anyone
+    if auth.user_uid != uid
+        && UserRepo::get_role(&mut tx, auth.user_uid).await? != UserRole::Admin
+    {
+        return Err(AppError::Unauthorized("Cannot update another user".into()));
+    }
     let new_phash = match &payload.password {
         Some(pw) => {
             let salt = SaltString::generate(&mut OsRng);
@@ -184,41 +189,114 @@ pub async fn update_user(
         }
         None => None,
     };
+    let password_changed = new_phash.is_some();
     let updated_user = UserRepo::update(
         &mut tx,
         uid,
         crate::repos::user::UpdateUserDbPayload {
             email: payload.email,
             phash: new_phash,
+            lang: payload.lang,
         },
     )
     .await?;
+    // Like a reset, a new password signs every device out
+    if password_changed {
+        RefreshTokenRepo::revoke_all_for_user(&mut tx, uid).await?;
+    }
     tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for updating user"))?;
     Ok(Json(updated_user))
 }
 
-#[derive(Deserialize, serde::Serialize, ToSchema)]
+#[utoipa::path(put, path = "/users/me", request_body = UpdateUserPayload, responses((status = 200, body = UserRead)), tag = "Users", operation_id = "updateMe", security(("bearerAuth" = [])))]
+pub async fn update_me(
+    state: State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    payload: ValidatedJson<UpdateUserPayload>,
+) -> Result<Json<UserRead>, AppError> {
+    let uid = auth.user_uid;
+    update_user(state, Extension(auth), Path(uid), payload).await
+}
+
+#[derive(Deserialize, serde::Serialize, ToSchema, Validate)]
 pub struct LoginUserPayload {
     pub email: String,
     pub password: String,
+    /// Required when two-factor authentication is enabled, unless `recovery_code` is given
+    #[serde(default)]
+    pub totp_code: Option<String>,
+    /// Single-use fallback for a lost authenticator
+    #[serde(default)]
+    pub recovery_code: Option<String>,
 }
 
-#[derive(serde::Serialize, ToSchema)]
-pub struct LoginResponse {
+#[derive(Deserialize, serde::Serialize, ToSchema)]
+pub struct SessionTokens {
+    /// Short-lived access token, sent as `Authorization: Bearer <token>`
     pub token: String,
+    /// Exchanged at /auth/refresh for a new token pair, single use
+    pub refresh_token: String,
+    /// Seconds until `token` expires
+    pub expires_in: u64,
+}
+
+#[derive(Deserialize, serde::Serialize, ToSchema)]
+pub struct LoginResponse {
+    #[serde(flatten)]
+    pub tokens: SessionTokens,
     pub user: UserRead,
 }
 
+const MAX_USER_AGENT_LEN: usize = 512;
+
+// A session signed in by the request, described by its user agent and address for the session list
+fn new_session(
+    user_uid: Uuid,
+    headers: &HeaderMap,
+    client_ip: Option<Extension<ClientIp>>,
+) -> CreateRefreshTokenDbPayload {
+    let now = chrono::Utc::now();
+    CreateRefreshTokenDbPayload {
+        user_uid,
+        session_uid: Uuid::new_v4(),
+        user_agent: headers
+            .get(USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(|user_agent| user_agent.chars().take(MAX_USER_AGENT_LEN).collect()),
+        ip_address: client_ip.map(|Extension(ClientIp(ip))| ip.to_string()),
+        signed_in_at: now,
+        expires_at: now + chrono::Duration::days(REFRESH_TOKEN_TTL_DAYS),
+    }
+}
+
+// Issues the session's next token pair: a refresh token row plus an access token bound to it
+async fn issue_session(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    payload: CreateRefreshTokenDbPayload,
+    jwt_keys: &JwtKeys,
+) -> Result<SessionTokens, AppError> {
+    let user_uid = payload.user_uid;
+    let (session, refresh_token) = RefreshTokenRepo::create(tx, payload).await?;
+    let token = crate::auth::encode_session_jwt(user_uid, session.uid, jwt_keys, ACCESS_TOKEN_TTL_SECONDS)
+        .map_err(AppError::Internal)?;
+    Ok(SessionTokens {
+        token,
+        refresh_token,
+        expires_in: ACCESS_TOKEN_TTL_SECONDS,
+    })
+}
+
 #[utoipa::path(post, path = "/auth/login", request_body = LoginUserPayload, responses((status = 200, body = LoginResponse), (status = 401, description = "Unauthorized")), tag = "Users", operation_id = "loginUser")]
 pub async fn login_user(
     State(state): State<AppState>,
-    Json(payload): Json<LoginUserPayload>,
+    headers: HeaderMap,
+    client_ip: Option<Extension<ClientIp>>,
+    ValidatedJson(payload): ValidatedJson<LoginUserPayload>,
 ) -> Result<Json<LoginResponse>, AppError> {
     let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for user login"))?;
     let user = UserRepo::get_by_email(&mut tx, &payload.email)
         .await
         .map_err(|_| AppError::Unauthorized("Invalid email or password".into()))?;
-    tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for user login"))?;
 
     let phash =
         PasswordHash::new(&user.phash).map_err(|e| AppError::Internal(anyhow::anyhow!(e)))?;
@@ -229,16 +307,258 @@ pub async fn login_user(
     {
         return Err(AppError::Unauthorized("Invalid email or password".into()));
     }
+    verify_second_factor(
+        &mut tx,
+        user.uid,
+        payload.totp_code.as_deref(),
+        payload.recovery_code.as_deref(),
+    )
+    .await?;
 
     // Issue JWT for web clients
-    let token = crate::auth::encode_web_jwt(user.uid, &state.jwt_secret, 60 * 60 * 24 * 7)
-        .map_err(AppError::Internal)?;
+    let tokens = issue_session(&mut tx, new_session(user.uid, &headers, client_ip), &state.jwt_keys).await?;
+    tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for user login"))?;
 
     Ok(Json(LoginResponse {
-        token,
+        tokens,
         user: UserRead {
             uid: user.uid,
             email: user.email,
+            lang: user.lang,
         },
     }))
 }
+
+// No-op unless the user finished 2FA enrollment; otherwise a TOTP or recovery code is required
+async fn verify_second_factor(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    user_uid: Uuid,
+    totp_code: Option<&str>,
+    recovery_code: Option<&str>,
+) -> Result<(), AppError> {
+    let Some(mfa) = UserMfaRepo::get_for_update(tx, user_uid)
+        .await?
+        .filter(|mfa| mfa.is_enabled())
+    else {
+        return Ok(());
+    };
+
+    if let Some(code) = totp_code {
+        let now = chrono::Utc::now().timestamp() as u64;
+        let step = totp::verify_code(&mfa.secret, code, now, mfa.last_used_step.map(|step| step as u64))
+            .ok_or_else(|| AppError::Unauthorized("Invalid two-factor code".into()))?;
+        UserMfaRepo::record_used_step(tx, user_uid, step as i64).await?;
+        return Ok(());
+    }
+    if let Some(code) = recovery_code {
+        if UserMfaRepo::use_recovery_code(tx, user_uid, code).await? {
+            return Ok(());
+        }
+        return Err(AppError::Unauthorized("Invalid recovery code".into()));
+    }
+    Err(AppError::Unauthorized("Two-factor code required".into()))
+}
+
+#[derive(Deserialize, serde::Serialize, ToSchema, Validate)]
+pub struct RefreshTokenPayload {
+    pub refresh_token: String,
+}
+
+#[utoipa::path(post, path = "/auth/refresh", request_body = RefreshTokenPayload, responses((status = 200, body = SessionTokens), (status = 401, description = "Unauthorized")), tag = "Users", operation_id = "refreshSession")]
+pub async fn refresh_session(
+    State(state): State<AppState>,
+    ValidatedJson(payload): ValidatedJson<RefreshTokenPayload>,
+) -> Result<Json<SessionTokens>, AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for refreshing session"))?;
+    let current = RefreshTokenRepo::get_by_token_for_update(&mut tx, &payload.refresh_token)
+        .await?
+        .ok_or_else(|| AppError::Unauthorized("Invalid refresh token".into()))?;
+
+    if current.revoked_at.is_some() {
+        // Refresh tokens are single use, a replayed one has leaked so end every session of the user
+        RefreshTokenRepo::revoke_all_for_user(&mut tx, current.user_uid).await?;
+        tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for refreshing session"))?;
+        return Err(AppError::Unauthorized("Refresh token has been revoked".into()));
+    }
+    if !current.is_active(chrono::Utc::now()) {
+        return Err(AppError::Unauthorized("Refresh token has expired".into()));
+    }
+
+    // Rotate: the old token (and access tokens bound to it) stop working, the session goes on
+    RefreshTokenRepo::revoke(&mut tx, current.uid).await?;
+    let next = CreateRefreshTokenDbPayload {
+        user_uid: current.user_uid,
+        session_uid: current.session_uid,
+        user_agent: current.user_agent,
+        ip_address: current.ip_address,
+        signed_in_at: current.signed_in_at,
+        expires_at: chrono::Utc::now() + chrono::Duration::days(REFRESH_TOKEN_TTL_DAYS),
+    };
+    let tokens = issue_session(&mut tx, next, &state.jwt_keys).await?;
+    tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for refreshing session"))?;
+    Ok(Json(tokens))
+}
+
+#[utoipa::path(post, path = "/auth/logout", request_body = RefreshTokenPayload, responses((status = 200, description = "Logged out")), tag = "Users", operation_id = "logoutUser")]
+pub async fn logout(
+    State(state): State<AppState>,
+    ValidatedJson(payload): ValidatedJson<RefreshTokenPayload>,
+) -> Result<(), AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for logging out"))?;
+    // Unknown or already revoked tokens are ignored so logging out is idempotent
+    if let Some(session) = RefreshTokenRepo::get_by_token_for_update(&mut tx, &payload.refresh_token).await? {
+        RefreshTokenRepo::revoke(&mut tx, session.uid).await?;
+    }
+    tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for logging out"))?;
+    Ok(())
+}
+
+const PASSWORD_RESET_TTL_MINUTES: i64 = 60;
+
+#[derive(Debug, Deserialize, serde::Serialize, ToSchema, Validate)]
+pub struct ForgotPasswordPayload {
+    #[validate(email)]
+    pub email: String,
+}
+
+#[utoipa::path(post, path = "/auth/forgot-password", request_body = ForgotPasswordPayload, responses((status = 200, description = "Reset link sent if the email is registered")), tag = "Users", operation_id = "forgotPassword")]
+pub async fn forgot_password(
+    State(state): State<AppState>,
+    ValidatedJson(payload): ValidatedJson<ForgotPasswordPayload>,
+) -> Result<(), AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for requesting password reset"))?;
+    // Unknown emails get the same response so the endpoint cannot be used to probe for accounts
+    let user = match UserRepo::get_by_email(&mut tx, &payload.email).await {
+        Ok(user) => user,
+        Err(DatabaseError::NotFound(_)) => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    let (_, token) = PasswordResetTokenRepo::create(
+        &mut tx,
+        CreatePasswordResetTokenDbPayload {
+            user_uid: user.uid,
+            expires_at: chrono::Utc::now() + chrono::Duration::minutes(PASSWORD_RESET_TTL_MINUTES),
+        },
+    )
+    .await?;
+    tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for requesting password reset"))?;
+
+    let lang = state.lang.preferred(user.lang.as_deref());
+    let subject = lang.get("EMAIL__PASSWORD_RESET_SUBJECT");
+    let body = lang.get_with_vars(
+        "EMAIL__PASSWORD_RESET_BODY",
+        HashMap::from([
+            ("link".to_string(), format!("{}/reset-password?token={}", state.front_end_url, token)),
+            ("minutes".to_string(), PASSWORD_RESET_TTL_MINUTES.to_string()),
+        ]),
+    );
+    if let Err(e) = state.email_sender.send(&user.email, &subject, &body).await {
+        tracing::error!("Failed to send password reset email: {:?}", e);
+    }
+    Ok(())
+}
+
+#[derive(Debug, Deserialize, serde::Serialize, ToSchema, Validate)]
+pub struct ResetPasswordPayload {
+    pub token: String,
+    #[validate(length(min = 8))]
+    pub password: String,
+}
+
+#[utoipa::path(post, path = "/auth/reset-password", request_body = ResetPasswordPayload, responses((status = 200, description = "Password updated"), (status = 400, description = "Invalid or expired token")), tag = "Users", operation_id = "resetPassword")]
+pub async fn reset_password(
+    State(state): State<AppState>,
+    ValidatedJson(payload): ValidatedJson<ResetPasswordPayload>,
+) -> Result<(), AppError> {
+    let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for resetting password"))?;
+    let reset = PasswordResetTokenRepo::get_by_token_for_update(&mut tx, &payload.token)
+        .await?
+        .filter(|reset| reset.is_usable(chrono::Utc::now()))
+        .ok_or_else(|| AppError::BadRequest("Invalid or expired reset token".into()))?;
+
+    let salt = SaltString::generate(&mut OsRng);
+    let phash = argon2::Argon2::default()
+        .hash_password(payload.password.as_bytes(), &salt)
+        .map_err(|e| AppError::Internal(anyhow::anyhow!(e)))?
+        .to_string();
+    UserRepo::update(
+        &mut tx,
+        reset.user_uid,
+        crate::repos::user::UpdateUserDbPayload {
+            email: None,
+            phash: Some(phash),
+            lang: None,
+        },
+    )
+    .await?;
+    PasswordResetTokenRepo::mark_used(&mut tx, reset.user_uid).await?;
+    // Whoever knew the old password should not stay signed in
+    RefreshTokenRepo::revoke_all_for_user(&mut tx, reset.user_uid).await?;
+    tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for resetting password"))?;
+    Ok(())
+}
+
+const TOTP_ISSUER: &str = "Expense Tracker";
+
+#[derive(serde::Serialize, ToSchema)]
+pub struct MfaEnrollResponse {
+    /// Base32 secret for manual entry in an authenticator app
+    pub secret: String,
+    /// Same secret as an otpauth:// URI, usually rendered as a QR code
+    pub otpauth_uri: String,
+}
+
+#[utoipa::path(post, path = "/users/me/2fa/enroll", responses((status = 200, body = MfaEnrollResponse), (status = 400, description = "Already enabled")), tag = "Users", operation_id = "enrollMfa", security(("bearerAuth" = [])))]
+pub async fn enroll_mfa(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+) -> Result<Json<MfaEnrollResponse>, AppError> {
+    reject_api_key_auth(&auth)?;
+    let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for enrolling mfa"))?;
+    if UserMfaRepo::get_for_update(&mut tx, auth.user_uid).await?.is_some_and(|mfa| mfa.is_enabled()) {
+        return Err(AppError::BadRequest("Two-factor authentication is already enabled".into()));
+    }
+    let user = UserRepo::get(&mut tx, auth.user_uid).await?;
+    let mfa = UserMfaRepo::start_enrollment(&mut tx, auth.user_uid, &totp::generate_secret()).await?;
+    tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for enrolling mfa"))?;
+
+    Ok(Json(MfaEnrollResponse {
+        otpauth_uri: totp::otpauth_uri(TOTP_ISSUER, &user.email, &mfa.secret),
+        secret: mfa.secret,
+    }))
+}
+
+#[derive(Deserialize, serde::Serialize, ToSchema, Validate)]
+pub struct MfaVerifyPayload {
+    pub code: String,
+}
+
+#[derive(serde::Serialize, ToSchema)]
+pub struct MfaRecoveryCodesResponse {
+    /// Shown once, each can replace a TOTP code for a single login
+    pub recovery_codes: Vec<String>,
+}
+
+#[utoipa::path(post, path = "/users/me/2fa/verify", request_body = MfaVerifyPayload, responses((status = 200, body = MfaRecoveryCodesResponse), (status = 400, description = "Invalid code")), tag = "Users", operation_id = "verifyMfa", security(("bearerAuth" = [])))]
+pub async fn verify_mfa(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    ValidatedJson(payload): ValidatedJson<MfaVerifyPayload>,
+) -> Result<Json<MfaRecoveryCodesResponse>, AppError> {
+    reject_api_key_auth(&auth)?;
+    let mut tx = state.db_pool.begin().await.map_err(|e| AppError::from_sqlx_error(e, "beginning transaction for verifying mfa"))?;
+    let mfa = UserMfaRepo::get_for_update(&mut tx, auth.user_uid)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("Two-factor enrollment has not been started".into()))?;
+    if mfa.is_enabled() {
+        return Err(AppError::BadRequest("Two-factor authentication is already enabled".into()));
+    }
+    let now = chrono::Utc::now().timestamp() as u64;
+    let step = totp::verify_code(&mfa.secret, &payload.code, now, None)
+        .ok_or_else(|| AppError::BadRequest("Invalid two-factor code".into()))?;
+
+    UserMfaRepo::enable(&mut tx, auth.user_uid, step as i64).await?;
+    let recovery_codes = UserMfaRepo::replace_recovery_codes(&mut tx, auth.user_uid).await?;
+    tx.commit().await.map_err(|e| AppError::from_sqlx_error(e, "committing transaction for verifying mfa"))?;
+    Ok(Json(MfaRecoveryCodesResponse { recovery_codes }))
+}
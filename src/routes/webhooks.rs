@@ -0,0 +1,319 @@
+use axum::{
+    Json,
+    extract::{Extension, Path, Query, State},
+};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use utoipa_axum::{router::OpenApiRouter, routes};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    auth::{AuthContext, group_guard::group_role_guard},
+    error::AppError,
+    middleware::validated_json::ValidatedJson,
+    repos::{
+        expense_group_member::GroupRole,
+        webhook::{
+            CreateWebhookDbPayload, UpdateWebhookDbPayload, Webhook, WebhookDelivery,
+            WebhookDeliveryRepo, WebhookRepo,
+        },
+    },
+    types::{AppState, DeleteResponse, PaginatedResponse},
+    webhooks::WebhookEvent,
+};
+
+const MAX_WEBHOOKS_PER_GROUP: usize = 10;
+const DEFAULT_PER_PAGE: u32 = 20;
+const MAX_PER_PAGE: u32 = 100;
+
+pub fn router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(list, create))
+        .routes(routes!(update, delete_))
+        .routes(routes!(list_deliveries))
+}
+
+#[derive(Debug, Deserialize, ToSchema, Validate)]
+pub struct CreateWebhookPayload {
+    /// http(s) URL receiving the POST requests
+    pub url: String,
+    /// Any of `expense.created`, `expense.updated`, `expense.deleted`, `budget.exceeded`
+    pub events: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema, Validate)]
+pub struct UpdateWebhookPayload {
+    pub url: Option<String>,
+    pub events: Option<Vec<String>>,
+    pub active: Option<bool>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CreateWebhookResponse {
+    pub webhook: Webhook,
+    /// Signs every payload, see `X-Webhook-Signature`. Shown only once
+    pub secret: String,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct WebhookDeliveryQuery {
+    /// 1-based page number, defaults to 1
+    pub page: Option<u32>,
+    /// Page size, defaults to 20, at most 100
+    pub per_page: Option<u32>,
+}
+
+fn parse_url(url: &str) -> Result<String, AppError> {
+    let url = url.trim();
+    match reqwest::Url::parse(url) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") && parsed.has_host() => {
+            Ok(url.to_string())
+        }
+        _ => Err(AppError::BadRequest(format!(
+            "Invalid webhook URL: {}",
+            url
+        ))),
+    }
+}
+
+// Deduplicated, in the order given
+fn parse_events(events: &[String]) -> Result<Vec<String>, AppError> {
+    if events.is_empty() {
+        return Err(AppError::BadRequest(
+            "A webhook needs at least one event".to_string(),
+        ));
+    }
+    let mut parsed: Vec<String> = Vec::new();
+    for event in events {
+        let event = WebhookEvent::parse(event).ok_or_else(|| {
+            AppError::BadRequest(format!("Unknown webhook event: {}", event.trim()))
+        })?;
+        if !parsed.iter().any(|e| e == event.as_str()) {
+            parsed.push(event.as_str().to_string());
+        }
+    }
+    Ok(parsed)
+}
+
+// The webhook, when it belongs to the group
+async fn get_in_group(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    group_uid: Uuid,
+    webhook_uid: Uuid,
+) -> Result<Webhook, AppError> {
+    let webhook = WebhookRepo::get(tx, webhook_uid).await?;
+    if webhook.group_uid != group_uid {
+        return Err(AppError::NotFound("Webhook not found".to_string()));
+    }
+    Ok(webhook)
+}
+
+// Webhooks can send the group's data anywhere, so all of these are for admins and owners
+#[utoipa::path(get, path = "/groups/{group_uid}/webhooks", params(("group_uid" = Uuid, Path)), responses((status = 200, body = [Webhook])), tag = "Webhooks", operation_id = "listWebhooks", security(("bearerAuth" = [])))]
+pub async fn list(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(group_uid): Path<Uuid>,
+) -> Result<Json<Vec<Webhook>>, AppError> {
+    let mut tx =
+        state.db_pool.begin().await.map_err(|e| {
+            AppError::from_sqlx_error(e, "beginning transaction for listing webhooks")
+        })?;
+    group_role_guard(
+        &auth,
+        group_uid,
+        &mut tx,
+        &state.membership_cache,
+        GroupRole::Admin,
+    )
+    .await?;
+    let res = WebhookRepo::list_by_group(&mut tx, group_uid).await?;
+    tx.commit()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "committing transaction for listing webhooks"))?;
+    Ok(Json(res))
+}
+
+#[utoipa::path(post, path = "/groups/{group_uid}/webhooks", params(("group_uid" = Uuid, Path)), request_body = CreateWebhookPayload, responses((status = 200, body = CreateWebhookResponse)), tag = "Webhooks", operation_id = "createWebhook", security(("bearerAuth" = [])))]
+pub async fn create(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(group_uid): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<CreateWebhookPayload>,
+) -> Result<Json<CreateWebhookResponse>, AppError> {
+    let url = parse_url(&payload.url)?;
+    let events = parse_events(&payload.events)?;
+    let mut tx =
+        state.db_pool.begin().await.map_err(|e| {
+            AppError::from_sqlx_error(e, "beginning transaction for creating webhook")
+        })?;
+    group_role_guard(
+        &auth,
+        group_uid,
+        &mut tx,
+        &state.membership_cache,
+        GroupRole::Admin,
+    )
+    .await?;
+    if WebhookRepo::list_by_group(&mut tx, group_uid).await?.len() >= MAX_WEBHOOKS_PER_GROUP {
+        return Err(AppError::BadRequest(format!(
+            "A group can have at most {} webhooks",
+            MAX_WEBHOOKS_PER_GROUP
+        )));
+    }
+    let webhook = WebhookRepo::create(
+        &mut tx,
+        CreateWebhookDbPayload {
+            group_uid,
+            url,
+            events,
+            created_by: auth.user_uid,
+        },
+    )
+    .await?;
+    tx.commit()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "committing transaction for creating webhook"))?;
+    Ok(Json(CreateWebhookResponse {
+        secret: webhook.secret.clone(),
+        webhook,
+    }))
+}
+
+// Only the given fields change. Deliveries still queued go to the new URL, or are dropped once inactive
+#[utoipa::path(put, path = "/groups/{group_uid}/webhooks/{webhook_uid}", params(("group_uid" = Uuid, Path), ("webhook_uid" = Uuid, Path)), request_body = UpdateWebhookPayload, responses((status = 200, body = Webhook)), tag = "Webhooks", operation_id = "updateWebhook", security(("bearerAuth" = [])))]
+pub async fn update(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path((group_uid, webhook_uid)): Path<(Uuid, Uuid)>,
+    ValidatedJson(payload): ValidatedJson<UpdateWebhookPayload>,
+) -> Result<Json<Webhook>, AppError> {
+    let url = payload.url.as_deref().map(parse_url).transpose()?;
+    let events = payload.events.as_deref().map(parse_events).transpose()?;
+    let mut tx =
+        state.db_pool.begin().await.map_err(|e| {
+            AppError::from_sqlx_error(e, "beginning transaction for updating webhook")
+        })?;
+    group_role_guard(
+        &auth,
+        group_uid,
+        &mut tx,
+        &state.membership_cache,
+        GroupRole::Admin,
+    )
+    .await?;
+    get_in_group(&mut tx, group_uid, webhook_uid).await?;
+    let updated = WebhookRepo::update(
+        &mut tx,
+        webhook_uid,
+        UpdateWebhookDbPayload {
+            url,
+            events,
+            active: payload.active,
+        },
+    )
+    .await?;
+    tx.commit()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "committing transaction for updating webhook"))?;
+    Ok(Json(updated))
+}
+
+#[utoipa::path(delete, path = "/groups/{group_uid}/webhooks/{webhook_uid}", params(("group_uid" = Uuid, Path), ("webhook_uid" = Uuid, Path)), responses((status = 200, body = DeleteResponse)), tag = "Webhooks", operation_id = "deleteWebhook", security(("bearerAuth" = [])))]
+pub async fn delete_(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path((group_uid, webhook_uid)): Path<(Uuid, Uuid)>,
+) -> Result<Json<DeleteResponse>, AppError> {
+    let mut tx =
+        state.db_pool.begin().await.map_err(|e| {
+            AppError::from_sqlx_error(e, "beginning transaction for deleting webhook")
+        })?;
+    group_role_guard(
+        &auth,
+        group_uid,
+        &mut tx,
+        &state.membership_cache,
+        GroupRole::Admin,
+    )
+    .await?;
+    get_in_group(&mut tx, group_uid, webhook_uid).await?;
+    WebhookRepo::delete(&mut tx, webhook_uid).await?;
+    tx.commit()
+        .await
+        .map_err(|e| AppError::from_sqlx_error(e, "committing transaction for deleting webhook"))?;
+    Ok(Json(DeleteResponse { success: true }))
+}
+
+// Newest first, with the payload sent and what the endpoint answered to the last attempt
+#[utoipa::path(get, path = "/groups/{group_uid}/webhooks/{webhook_uid}/deliveries", params(("group_uid" = Uuid, Path), ("webhook_uid" = Uuid, Path), WebhookDeliveryQuery), responses((status = 200, body = PaginatedResponse<WebhookDelivery>)), tag = "Webhooks", operation_id = "listWebhookDeliveries", security(("bearerAuth" = [])))]
+pub async fn list_deliveries(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path((group_uid, webhook_uid)): Path<(Uuid, Uuid)>,
+    Query(query): Query<WebhookDeliveryQuery>,
+) -> Result<Json<PaginatedResponse<WebhookDelivery>>, AppError> {
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query
+        .per_page
+        .unwrap_or(DEFAULT_PER_PAGE)
+        .clamp(1, MAX_PER_PAGE);
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "beginning transaction for listing webhook deliveries")
+    })?;
+    group_role_guard(
+        &auth,
+        group_uid,
+        &mut tx,
+        &state.membership_cache,
+        GroupRole::Admin,
+    )
+    .await?;
+    get_in_group(&mut tx, group_uid, webhook_uid).await?;
+    let (items, total) = WebhookDeliveryRepo::list_by_webhook(
+        &mut tx,
+        webhook_uid,
+        per_page as i64,
+        (page as i64 - 1) * per_page as i64,
+    )
+    .await?;
+    tx.commit().await.map_err(|e| {
+        AppError::from_sqlx_error(e, "committing transaction for listing webhook deliveries")
+    })?;
+    Ok(Json(PaginatedResponse {
+        items,
+        total,
+        page,
+        per_page,
+        next_cursor: None,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_url() {
+        assert!(parse_url(" https://hooks.example.com/expenses ").is_ok());
+        assert!(parse_url("http://n8n.local:5678/webhook/abc").is_ok());
+        assert!(parse_url("ftp://example.com").is_err());
+        assert!(parse_url("not a url").is_err());
+    }
+
+    #[test]
+    fn test_parse_events() {
+        let events = vec![
+            "expense.created".to_string(),
+            " budget.exceeded".to_string(),
+            "expense.created".to_string(),
+        ];
+        assert_eq!(
+            parse_events(&events).unwrap(),
+            vec!["expense.created", "budget.exceeded"]
+        );
+        assert!(parse_events(&[]).is_err());
+        assert!(parse_events(&["expense.archived".to_string()]).is_err());
+    }
+}
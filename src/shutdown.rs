@@ -0,0 +1,145 @@
+use std::future::Future;
+use std::time::Duration;
+
+use tokio_cron_scheduler::JobScheduler;
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::{TaskTracker, task_tracker::TaskTrackerToken};
+
+/*
+ Coordinates shutting down the messengers and schedulers with the HTTP server. Their work is
+ tracked, so `drain` can wait for what already started (a scheduled job holding a transaction,
+ a chat message being handled) while nothing new starts once shutdown is triggered.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct Shutdown {
+    token: CancellationToken,
+    tasks: TaskTracker,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn trigger(&self) {
+        self.token.cancel();
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    pub async fn triggered(&self) {
+        self.token.cancelled().await;
+    }
+
+    // Spawns a task `drain` waits for, it has to stop by itself once shutdown is triggered
+    pub fn spawn<F>(&self, task: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.tasks.spawn(task);
+    }
+
+    // Held by a scheduled job while it runs, None once shutdown is triggered so it does not start
+    pub fn track(&self) -> Option<TaskTrackerToken> {
+        (!self.is_triggered()).then(|| self.tasks.token())
+    }
+
+    // Stops the scheduler from starting jobs once shutdown is triggered
+    pub fn stop_with(&self, mut scheduler: JobScheduler) {
+        let shutdown = self.clone();
+        tokio::spawn(async move {
+            shutdown.triggered().await;
+            if let Err(e) = scheduler.shutdown().await {
+                tracing::error!("Failed to stop scheduler: {:?}", e);
+            }
+        });
+    }
+
+    /*
+     Resolves on the first SIGTERM (sent by docker, kubernetes and systemd on stop), SIGINT or
+     Ctrl+C, after triggering shutdown. Schedulers stop starting jobs right away; the rest is
+     drained once the HTTP server stopped.
+    */
+    pub async fn on_signal(self) {
+        let signal = wait_for_signal().await;
+        tracing::info!("{} received, starting graceful shutdown", signal);
+        self.trigger();
+    }
+
+    // Waits at most `timeout` for the tracked work, returns false when some was still running
+    pub async fn drain(&self, timeout: Duration) -> bool {
+        self.trigger();
+        self.tasks.close();
+        tokio::time::timeout(timeout, self.tasks.wait())
+            .await
+            .is_ok()
+    }
+}
+
+#[cfg(unix)]
+async fn wait_for_signal() -> &'static str {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    // A signal that cannot be listened for never arrives, the others still shut down
+    async fn recv(kind: SignalKind, name: &'static str) -> &'static str {
+        match signal(kind) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("Failed to listen for {}: {}", name, e);
+                std::future::pending::<()>().await;
+            }
+        }
+        name
+    }
+
+    tokio::select! {
+        name = recv(SignalKind::terminate(), "SIGTERM") => name,
+        name = recv(SignalKind::interrupt(), "SIGINT") => name,
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_signal() -> &'static str {
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        tracing::error!("Failed to listen for Ctrl+C: {}", e);
+        std::future::pending::<()>().await;
+    }
+    "Ctrl+C"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_drain_waits_for_running_jobs() {
+        let shutdown = Shutdown::new();
+        let running = shutdown.track().unwrap();
+        let (done, finished) = tokio::sync::oneshot::channel();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            drop(running);
+            let _ = done.send(());
+        });
+
+        assert!(shutdown.drain(Duration::from_secs(5)).await);
+        assert!(finished.await.is_ok());
+        assert!(shutdown.track().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_drain_gives_up_after_timeout() {
+        let shutdown = Shutdown::new();
+        let inner = shutdown.clone();
+        shutdown.spawn(async move {
+            let _running = inner.track();
+            std::future::pending::<()>().await;
+        });
+
+        assert!(!shutdown.drain(Duration::from_millis(50)).await);
+    }
+}
@@ -0,0 +1,38 @@
+pub mod local;
+pub mod s3;
+
+use async_trait::async_trait;
+
+use crate::config::Config;
+
+// Where uploaded files such as receipts are kept, keys are `/` separated paths
+#[async_trait]
+pub trait FileStorage {
+    // Stored in `receipts.storage`
+    fn name(&self) -> &'static str;
+
+    async fn put(
+        &self,
+        key: &str,
+        content_type: &str,
+        bytes: Vec<u8>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>>;
+
+    async fn delete(&self, key: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+}
+
+// Picks the S3-compatible bucket when configured, otherwise files go to a local directory
+pub fn file_storage_from_config(config: &Config) -> Box<dyn FileStorage + Send + Sync> {
+    match s3::S3FileStorage::from_config(config) {
+        Some(storage) => Box::new(storage),
+        None => {
+            tracing::info!(
+                "S3_BUCKET not set, uploads are stored in {}",
+                config.storage_dir
+            );
+            Box::new(local::LocalFileStorage::new(&config.storage_dir))
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use std::path::{Component, Path, PathBuf};
+
+use async_trait::async_trait;
+
+use super::FileStorage;
+
+pub struct LocalFileStorage {
+    root: PathBuf,
+}
+
+impl LocalFileStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    // Keys are generated by us, still refuse anything that could leave the root
+    fn path(&self, key: &str) -> Result<PathBuf, Box<dyn std::error::Error + Send + Sync>> {
+        let relative = Path::new(key);
+        if key.is_empty()
+            || !relative
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Err(format!("Invalid storage key: {}", key).into());
+        }
+        Ok(self.root.join(relative))
+    }
+}
+
+#[async_trait]
+impl FileStorage for LocalFileStorage {
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    async fn put(
+        &self,
+        key: &str,
+        _content_type: &str,
+        bytes: Vec<u8>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(path, bytes).await?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(tokio::fs::read(self.path(key)?).await?)
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        match tokio::fs::remove_file(self.path(key)?).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_stays_in_root() {
+        let storage = LocalFileStorage::new("/data/uploads");
+        assert_eq!(
+            storage.path("receipts/a/b.jpg").unwrap(),
+            PathBuf::from("/data/uploads/receipts/a/b.jpg")
+        );
+        assert!(storage.path("../etc/passwd").is_err());
+        assert!(storage.path("/etc/passwd").is_err());
+        assert!(storage.path("").is_err());
+    }
+}
@@ -1,25 +1,153 @@
-use teloxide::{prelude::*, types::ChatId};
-use tracing_subscriber::Layer;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
 
+use teloxide::{RequestError, prelude::*, types::ChatId};
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+use tracing::span::{Attributes, Id};
+use tracing_subscriber::{Layer, registry::LookupSpan};
+
+use crate::commands::dispatcher::MAX_RESPONSE_LENGTH;
+use crate::messengers::paginator::paginate;
+
+// Log lines waiting to be sent, more are dropped instead of slowing down the logging code
+const QUEUE_CAPACITY: usize = 1000;
+// Lines logged within this window after the first one are sent together
+const BATCH_WINDOW: Duration = Duration::from_secs(2);
+// Telegram allows about 20 messages a minute in a group
+const MIN_SEND_INTERVAL: Duration = Duration::from_secs(3);
+// A burst of logs should not fill the log chat for minutes, the rest is counted as dropped
+const MAX_MESSAGES_PER_BATCH: usize = 3;
+
+/*
+ Forwards warnings and errors to a Telegram chat. Events are only queued here; a background
+ task batches them into few messages, merges repeated lines and keeps to Telegram's rate
+ limits. When the queue is full, lines are dropped and the next message says how many.
+*/
 pub struct TelegramLogger {
-    bot: Bot,
-    chat_id: ChatId,
+    queue: mpsc::Sender<String>,
+    dropped: Arc<AtomicU64>,
 }
 
 impl TelegramLogger {
+    // Spawns the sending task, so it must be created inside the tokio runtime
     pub fn new(token: String, chat_id: i64) -> Self {
-        Self {
-            bot: Bot::new(token),
-            chat_id: ChatId(chat_id),
+        let (queue, lines) = mpsc::channel(QUEUE_CAPACITY);
+        let dropped = Arc::new(AtomicU64::new(0));
+        tokio::spawn(send_batches(
+            Bot::new(token),
+            ChatId(chat_id),
+            lines,
+            dropped.clone(),
+        ));
+        Self { queue, dropped }
+    }
+
+    fn push(&self, line: String) {
+        if self.queue.try_send(line).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+async fn send_batches(
+    bot: Bot,
+    chat_id: ChatId,
+    mut lines: mpsc::Receiver<String>,
+    dropped: Arc<AtomicU64>,
+) {
+    let mut last_sent: Option<Instant> = None;
+    while let Some(first) = lines.recv().await {
+        let mut batch = vec![first];
+        let deadline = Instant::now() + BATCH_WINDOW;
+        while let Ok(Some(line)) = tokio::time::timeout_at(deadline, lines.recv()).await {
+            batch.push(line);
+        }
+
+        let mut messages = batch_messages(&batch, dropped.swap(0, Ordering::Relaxed));
+        if messages.len() > MAX_MESSAGES_PER_BATCH {
+            let omitted: usize = messages[MAX_MESSAGES_PER_BATCH..]
+                .iter()
+                .map(|message| message.split("\n\n").count())
+                .sum();
+            dropped.fetch_add(omitted as u64, Ordering::Relaxed);
+            messages.truncate(MAX_MESSAGES_PER_BATCH);
+        }
+        for message in messages {
+            if let Some(last_sent) = last_sent {
+                tokio::time::sleep_until(last_sent + MIN_SEND_INTERVAL).await;
+            }
+            send(&bot, chat_id, &message).await;
+            last_sent = Some(Instant::now());
+        }
+    }
+}
+
+// Not logged through tracing, a failing log chat would feed itself
+async fn send(bot: &Bot, chat_id: ChatId, message: &str) {
+    match bot.send_message(chat_id, message).await {
+        Ok(_) => {}
+        Err(RequestError::RetryAfter(seconds)) => {
+            tokio::time::sleep(seconds.duration()).await;
+            if let Err(e) = bot.send_message(chat_id, message).await {
+                eprintln!("Failed to send log to Telegram: {:?}", e);
+            }
         }
+        Err(e) => eprintln!("Failed to send log to Telegram: {:?}", e),
     }
 }
 
+/*
+ The messages for a batch of log lines. Repeated lines are sent once with their count, in the
+ order they were first logged, and lines are kept whole unless one alone is too long.
+*/
+fn batch_messages(lines: &[String], dropped: u64) -> Vec<String> {
+    let mut counted: Vec<(&str, usize)> = Vec::new();
+    for line in lines {
+        match counted.iter_mut().find(|(seen, _)| *seen == line.as_str()) {
+            Some((_, count)) => *count += 1,
+            None => counted.push((line, 1)),
+        }
+    }
+
+    let mut entries: Vec<String> = counted
+        .into_iter()
+        .map(|(line, count)| match count {
+            1 => line.to_string(),
+            _ => format!("{}\n(repeated {} times)", line, count),
+        })
+        .collect();
+    if dropped > 0 {
+        entries.push(format!(
+            "{} log lines were dropped, the log chat could not keep up",
+            dropped
+        ));
+    }
+    paginate(&entries.join("\n\n"), MAX_RESPONSE_LENGTH)
+}
+
+// Fields of a span, kept to add the request id, chat, ... to the events logged inside it
+struct SpanFields(String);
+
 impl<S> Layer<S> for TelegramLogger
 where
-    S: tracing::Subscriber,
+    S: tracing::Subscriber + for<'a> LookupSpan<'a>,
 {
-    fn on_event(&self, event: &tracing::Event<'_>, _ctx: tracing_subscriber::layer::Context<'_, S>) {
+    fn on_new_span(
+        &self,
+        attrs: &Attributes<'_>,
+        id: &Id,
+        ctx: tracing_subscriber::layer::Context<'_, S>,
+    ) {
+        let mut visitor = StringVisitor::new();
+        attrs.record(&mut visitor);
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanFields(visitor.0));
+        }
+    }
+
+    fn on_event(&self, event: &tracing::Event<'_>, ctx: tracing_subscriber::layer::Context<'_, S>) {
         let level = event.metadata().level();
         if *level == tracing::Level::WARN || *level == tracing::Level::ERROR {
             let mut message = format!("{}: ", level);
@@ -28,14 +156,19 @@ where
             event.record(&mut visitor);
             message.push_str(&visitor.0);
 
-            let bot = self.bot.clone();
-            let chat_id = self.chat_id;
-
-            tokio::spawn(async move {
-                if let Err(e) = bot.send_message(chat_id, &message).await {
-                    eprintln!("Failed to send log to Telegram: {:?}", e);
+            if let Some(scope) = ctx.event_scope(event) {
+                for span in scope.from_root() {
+                    let extensions = span.extensions();
+                    if let Some(SpanFields(fields)) = extensions
+                        .get::<SpanFields>()
+                        .filter(|fields| !fields.0.is_empty())
+                    {
+                        message.push_str(&format!("\n{}: {}", span.name(), fields));
+                    }
                 }
-            });
+            }
+
+            self.push(message);
         }
     }
 }
@@ -70,4 +203,42 @@ impl tracing::field::Visit for StringVisitor {
             self.0.push_str(&format!("{}={}", field.name(), value));
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_messages_merges_repeats() {
+        let lines = [
+            "ERROR: db down".to_string(),
+            "WARN: slow".to_string(),
+            "ERROR: db down".to_string(),
+        ];
+        assert_eq!(
+            batch_messages(&lines, 0),
+            vec!["ERROR: db down\n(repeated 2 times)\n\nWARN: slow"]
+        );
+    }
+
+    #[test]
+    fn test_batch_messages_reports_dropped_lines() {
+        let messages = batch_messages(&["ERROR: db down".to_string()], 5);
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].ends_with("5 log lines were dropped, the log chat could not keep up"));
+    }
+
+    #[test]
+    fn test_batch_messages_splits_long_batches() {
+        let lines: Vec<String> = (0..100)
+            .map(|i| format!("ERROR: {} {}", i, "x".repeat(100)))
+            .collect();
+        let messages = batch_messages(&lines, 0);
+        assert!(messages.len() > 1);
+        assert!(
+            messages
+                .iter()
+                .all(|message| message.chars().count() <= MAX_RESPONSE_LENGTH)
+        );
+    }
+}
@@ -0,0 +1,79 @@
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use metrics::{counter, gauge, histogram};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+use sqlx::PgPool;
+
+// Seconds, from a cached read up to a PDF report with charts
+const DURATION_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+// Histograms are only drained by the upkeep, not by the scrapes
+const UPKEEP_INTERVAL: Duration = Duration::from_secs(5);
+
+static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+
+// Installs the Prometheus recorder on first use, anything recorded before that is dropped
+pub fn handle() -> &'static PrometheusHandle {
+    HANDLE.get_or_init(|| {
+        PrometheusBuilder::new()
+            .set_buckets_for_metric(
+                Matcher::Suffix("duration_seconds".to_string()),
+                DURATION_BUCKETS,
+            )
+            .expect("Invalid histogram buckets")
+            .install_recorder()
+            .expect("Failed to install metrics recorder")
+    })
+}
+
+pub fn spawn_upkeep() {
+    let handle = handle();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(UPKEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+            handle.run_upkeep();
+        }
+    });
+}
+
+// `route` is the matched route template, not the path, so ids do not end up in labels
+pub fn record_request(method: &str, route: &str, status: u16, started: Instant) {
+    let labels = [
+        ("method", method.to_string()),
+        ("route", route.to_string()),
+        ("status", status.to_string()),
+    ];
+    counter!("http_requests_total", &labels).increment(1);
+    histogram!("http_request_duration_seconds", &labels).record(started.elapsed());
+}
+
+pub fn record_chat_command(platform: &str, command: &str, succeeded: bool) {
+    let labels = [
+        ("platform", platform.to_string()),
+        ("command", command.to_string()),
+    ];
+    counter!("chat_commands_total", &labels).increment(1);
+    if !succeeded {
+        counter!("chat_command_failures_total", &labels).increment(1);
+    }
+}
+
+// `report` names what was generated, e.g. the monthly report data or a rendered PDF
+pub fn record_report(report: &'static str, started: Instant) {
+    histogram!("report_generation_duration_seconds", "report" => report).record(started.elapsed());
+}
+
+// Read when scraped, the pool has no hooks to update them as connections come and go
+pub fn record_db_pool(pool: &PgPool) {
+    gauge!("db_pool_connections").set(pool.size() as f64);
+    gauge!("db_pool_idle_connections").set(pool.num_idle() as f64);
+    gauge!("db_pool_max_connections").set(pool.options().get_max_connections() as f64);
+}
+
+// Queries that gave up waiting for a connection, a sign DB_MAX_CONNECTIONS is too low
+pub fn record_db_pool_timeout() {
+    counter!("db_pool_acquire_timeouts_total").increment(1);
+}
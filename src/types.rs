@@ -45,6 +45,10 @@ impl SubscriptionTier {
                 export_data: false,
                 priority_support: false,
                 custom_categories: false,
+                receipts: false,
+                slack_integration: false,
+                api_requests_per_minute: 60,
+                chat_commands_per_minute: 10,
             },
             SubscriptionTier::Personal => TierLimits {
                 max_groups: 2,
@@ -57,6 +61,10 @@ impl SubscriptionTier {
                 export_data: true,
                 priority_support: false,
                 custom_categories: true,
+                receipts: true,
+                slack_integration: false,
+                api_requests_per_minute: 120,
+                chat_commands_per_minute: 20,
             },
             SubscriptionTier::Family => TierLimits {
                 max_groups: 3,
@@ -69,6 +77,10 @@ impl SubscriptionTier {
                 export_data: true,
                 priority_support: false,
                 custom_categories: true,
+                receipts: true,
+                slack_integration: false,
+                api_requests_per_minute: 240,
+                chat_commands_per_minute: 30,
             },
             SubscriptionTier::Team => TierLimits {
                 max_groups: 10,
@@ -81,6 +93,10 @@ impl SubscriptionTier {
                 export_data: true,
                 priority_support: true,
                 custom_categories: true,
+                receipts: true,
+                slack_integration: true,
+                api_requests_per_minute: 600,
+                chat_commands_per_minute: 60,
             },
             SubscriptionTier::Enterprise => TierLimits {
                 max_groups: -1,               // Unlimited
@@ -93,6 +109,10 @@ impl SubscriptionTier {
                 export_data: true,
                 priority_support: true,
                 custom_categories: true,
+                receipts: true,
+                slack_integration: true,
+                api_requests_per_minute: -1,  // Unlimited
+                chat_commands_per_minute: -1, // Unlimited
             },
         }
     }
@@ -107,6 +127,28 @@ impl SubscriptionTier {
         }
     }
 
+    // Unlike `From<&str>`, unknown names are rejected instead of falling back to free
+    pub fn parse(input: &str) -> Option<Self> {
+        match input.trim().to_lowercase().as_str() {
+            "free" => Some(SubscriptionTier::Free),
+            "personal" => Some(SubscriptionTier::Personal),
+            "family" => Some(SubscriptionTier::Family),
+            "team" => Some(SubscriptionTier::Team),
+            "enterprise" => Some(SubscriptionTier::Enterprise),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SubscriptionTier::Free => "free",
+            SubscriptionTier::Personal => "personal",
+            SubscriptionTier::Family => "family",
+            SubscriptionTier::Team => "team",
+            SubscriptionTier::Enterprise => "enterprise",
+        }
+    }
+
     pub fn display_name(&self) -> &'static str {
         match self {
             SubscriptionTier::Free => "Free",
@@ -130,6 +172,12 @@ pub struct TierLimits {
     pub export_data: bool,
     pub priority_support: bool,
     pub custom_categories: bool,
+    pub receipts: bool,
+    // Chats bound from Slack workspaces
+    pub slack_integration: bool,
+    // Token bucket sizes, also the number of tokens refilled per minute
+    pub api_requests_per_minute: i32,
+    pub chat_commands_per_minute: i32,
 }
 
 impl TierLimits {
@@ -207,20 +255,48 @@ use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use utoipa::ToSchema;
 
-use crate::{lang::Lang, messengers::MessengerManager};
+use crate::{
+    auth::{jwt::JwtKeys, membership_cache::MembershipCache},
+    billing::PaymentProvider,
+    email::EmailSender,
+    lang::Lang,
+    messengers::MessengerManager,
+    middleware::rate_limit::RateLimiter,
+    storage::FileStorage,
+};
 
 #[derive(Clone)]
 pub struct AppState {
     pub db_pool: sqlx::PgPool,
     pub version: String,
-    pub jwt_secret: String,
+    pub jwt_keys: Arc<JwtKeys>,
     pub chat_relay_secret: String,
     pub front_end_url: String,
+    // Page of the web app where chats are bound, linked in the `/login` reply
+    pub chat_bind_url: String,
+    // Domain receiving forwarded receipts, group addresses are `<token>@<domain>`
+    pub email_ingest_domain: Option<String>,
     pub lang: Lang,
     pub messenger_manager: Option<Arc<MessengerManager>>,
+    pub email_sender: Arc<dyn EmailSender + Send + Sync>,
+    pub payment_provider: Option<Arc<dyn PaymentProvider + Send + Sync>>,
+    pub file_storage: Arc<dyn FileStorage + Send + Sync>,
+    pub rate_limiter: Arc<RateLimiter>,
+    pub membership_cache: Arc<MembershipCache>,
 }
 
-#[derive(Serialize, ToSchema)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct DeleteResponse {
     pub success: bool,
 }
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct PaginatedResponse<T: ToSchema> {
+    pub items: Vec<T>,
+    pub total: i64,
+    pub page: u32,
+    pub per_page: u32,
+    /// Opaque cursor to pass as `after` for the next page, on lists that support it. Absent on the last page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
@@ -1 +1,14 @@
+pub mod activity;
+pub mod anomaly;
+pub mod bank_statement;
+pub mod budget_limit;
+pub mod category_style;
+pub mod currency;
+pub mod cursor;
+pub mod email_receipt;
+pub mod expense_import;
+pub mod forecast;
 pub mod parse_price;
+pub mod period;
+pub mod pii;
+pub mod secret_token;
@@ -0,0 +1,361 @@
+/*
+The group's activity feed: expenses added, edited and deleted, members joining and leaving,
+and budget changes, newest first. It is read from the audit log, so changes from the web, the
+chats and the API all show up, each described from the snapshot its log kept.
+*/
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use serde_json::Value;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::DatabaseError;
+use crate::repos::{
+    audit_log::{AuditEntity, AuditLog, AuditRepo},
+    category::CategoryRepo,
+    user::UserRepo,
+};
+use crate::utils::cursor::Cursor;
+
+// Entities whose changes make up the feed
+pub const FEED_ENTITIES: [AuditEntity; 3] = [
+    AuditEntity::ExpenseEntry,
+    AuditEntity::GroupMember,
+    AuditEntity::Budget,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActivityKind {
+    ExpenseAdded,
+    ExpenseEdited,
+    ExpenseDeleted,
+    ExpenseRestored,
+    MemberJoined,
+    MemberRoleChanged,
+    MemberLeft,
+    BudgetAdded,
+    BudgetChanged,
+    BudgetRemoved,
+}
+
+impl ActivityKind {
+    // Kind of an audit log's `entity_type` and `action`, None outside the feed
+    pub fn of(entity_type: &str, action: &str) -> Option<Self> {
+        match (entity_type, action) {
+            ("expense_entry", "create") => Some(Self::ExpenseAdded),
+            ("expense_entry", "update") => Some(Self::ExpenseEdited),
+            ("expense_entry", "delete") => Some(Self::ExpenseDeleted),
+            ("expense_entry", "restore") => Some(Self::ExpenseRestored),
+            ("group_member", "create" | "restore") => Some(Self::MemberJoined),
+            ("group_member", "update") => Some(Self::MemberRoleChanged),
+            ("group_member", "delete") => Some(Self::MemberLeft),
+            ("budget", "create" | "restore") => Some(Self::BudgetAdded),
+            ("budget", "update") => Some(Self::BudgetChanged),
+            ("budget", "delete") => Some(Self::BudgetRemoved),
+            _ => None,
+        }
+    }
+
+    pub fn parse(input: &str) -> Option<Self> {
+        match input {
+            "expense_added" => Some(Self::ExpenseAdded),
+            "expense_edited" => Some(Self::ExpenseEdited),
+            "expense_deleted" => Some(Self::ExpenseDeleted),
+            "expense_restored" => Some(Self::ExpenseRestored),
+            "member_joined" => Some(Self::MemberJoined),
+            "member_role_changed" => Some(Self::MemberRoleChanged),
+            "member_left" => Some(Self::MemberLeft),
+            "budget_added" => Some(Self::BudgetAdded),
+            "budget_changed" => Some(Self::BudgetChanged),
+            "budget_removed" => Some(Self::BudgetRemoved),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ExpenseAdded => "expense_added",
+            Self::ExpenseEdited => "expense_edited",
+            Self::ExpenseDeleted => "expense_deleted",
+            Self::ExpenseRestored => "expense_restored",
+            Self::MemberJoined => "member_joined",
+            Self::MemberRoleChanged => "member_role_changed",
+            Self::MemberLeft => "member_left",
+            Self::BudgetAdded => "budget_added",
+            Self::BudgetChanged => "budget_changed",
+            Self::BudgetRemoved => "budget_removed",
+        }
+    }
+
+    fn entity(&self) -> AuditEntity {
+        match self {
+            Self::ExpenseAdded
+            | Self::ExpenseEdited
+            | Self::ExpenseDeleted
+            | Self::ExpenseRestored => AuditEntity::ExpenseEntry,
+            Self::MemberJoined | Self::MemberRoleChanged | Self::MemberLeft => {
+                AuditEntity::GroupMember
+            }
+            Self::BudgetAdded | Self::BudgetChanged | Self::BudgetRemoved => AuditEntity::Budget,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct ActivityEvent {
+    /// Uid of the audit log the event comes from
+    pub uid: Uuid,
+    /// `expense_added`, `expense_edited`, `expense_deleted`, `expense_restored`,
+    /// `member_joined`, `member_role_changed`, `member_left`, `budget_added`, `budget_changed`
+    /// or `budget_removed`
+    pub kind: String,
+    /// The expense entry, group member or budget
+    pub entity_uid: Uuid,
+    pub actor_user_uid: Option<Uuid>,
+    /// Name of the chat sender, or the part of the user's email before the `@`
+    pub actor_name: Option<String>,
+    /// `web`, `chat` for the relay API, or the chat platform
+    pub source: String,
+    /// Product of the expense, name of the member or category of the budget
+    pub subject: Option<String>,
+    /// Price of the expense or amount of the budget
+    pub amount: Option<Decimal>,
+    pub currency: Option<String>,
+    /// Amount before an edit that changed it
+    pub previous_amount: Option<Decimal>,
+    /// Role of the member
+    pub role: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ActivityEvent {
+    /*
+     Event of a log, described from its latest snapshot. `names` are the display names of users
+     by uid, `categories` the category names of the group. None for logs outside the feed.
+    */
+    pub fn from_log(
+        log: &AuditLog,
+        names: &HashMap<Uuid, String>,
+        categories: &HashMap<Uuid, String>,
+    ) -> Option<Self> {
+        let kind = ActivityKind::of(&log.entity_type, &log.action)?;
+        let snapshot = log.after.as_ref().or(log.before.as_ref());
+        let amount_field = match kind.entity() {
+            AuditEntity::ExpenseEntry => Some("price"),
+            AuditEntity::Budget => Some("amount"),
+            _ => None,
+        };
+        let amount = amount_field.and_then(|field| decimal_of(snapshot, field));
+        let previous_amount = match (log.before.as_ref(), log.after.as_ref()) {
+            (Some(before), Some(_)) => amount_field
+                .and_then(|field| decimal_of(Some(before), field))
+                .filter(|previous| Some(*previous) != amount),
+            _ => None,
+        };
+        let subject = match kind.entity() {
+            AuditEntity::ExpenseEntry => string_of(snapshot, "product"),
+            AuditEntity::GroupMember => {
+                uid_of(snapshot, "user_uid").and_then(|uid| names.get(&uid).cloned())
+            }
+            _ => uid_of(snapshot, "category_uid").and_then(|uid| categories.get(&uid).cloned()),
+        };
+        let actor_name = log
+            .actor_name
+            .clone()
+            .or_else(|| log.actor_user_uid.and_then(|uid| names.get(&uid).cloned()));
+
+        Some(Self {
+            uid: log.uid,
+            kind: kind.as_str().to_string(),
+            entity_uid: log.entity_uid,
+            actor_user_uid: log.actor_user_uid,
+            actor_name,
+            source: log.source.clone(),
+            subject,
+            amount,
+            currency: amount.and_then(|_| string_of(snapshot, "currency")),
+            previous_amount,
+            role: string_of(snapshot, "role"),
+            created_at: log.created_at,
+        })
+    }
+}
+
+fn string_of(snapshot: Option<&Value>, field: &str) -> Option<String> {
+    snapshot?
+        .get(field)?
+        .as_str()
+        .map(|value| value.to_string())
+}
+
+fn uid_of(snapshot: Option<&Value>, field: &str) -> Option<Uuid> {
+    Uuid::parse_str(snapshot?.get(field)?.as_str()?).ok()
+}
+
+fn decimal_of(snapshot: Option<&Value>, field: &str) -> Option<Decimal> {
+    serde_json::from_value(snapshot?.get(field)?.clone()).ok()
+}
+
+/*
+ A page of the group's feed, newest first, with the total count of events. With `after` the
+ page starts below that cursor instead of at `offset`, like the audit log listing.
+*/
+pub async fn group_activity(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    group_uid: Uuid,
+    limit: i64,
+    offset: i64,
+    after: Option<Cursor>,
+) -> Result<(Vec<ActivityEvent>, i64), DatabaseError> {
+    let (logs, total) =
+        AuditRepo::list_by_group_and_entities(tx, group_uid, &FEED_ENTITIES, limit, offset, after)
+            .await?;
+
+    // Actors and members are named after their email, decrypted once per user
+    let mut user_uids: Vec<Uuid> = logs
+        .iter()
+        .flat_map(|log| {
+            let member = (log.entity_type == AuditEntity::GroupMember.as_str())
+                .then(|| uid_of(log.after.as_ref().or(log.before.as_ref()), "user_uid"))
+                .flatten();
+            [log.actor_user_uid, member]
+        })
+        .flatten()
+        .collect();
+    user_uids.sort();
+    user_uids.dedup();
+    let names: HashMap<Uuid, String> = UserRepo::emails_by_uids(tx, &user_uids)
+        .await?
+        .into_iter()
+        .filter_map(|(uid, email)| Some((uid, email.split('@').next()?.to_string())))
+        .collect();
+    let categories: HashMap<Uuid, String> = CategoryRepo::list_by_group(tx, group_uid)
+        .await?
+        .into_iter()
+        .map(|category| (category.uid, category.name))
+        .collect();
+
+    let events = logs
+        .iter()
+        .filter_map(|log| ActivityEvent::from_log(log, &names, &categories))
+        .collect();
+    Ok((events, total))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+    use serde_json::json;
+
+    fn log(
+        entity_type: &str,
+        action: &str,
+        before: Option<Value>,
+        after: Option<Value>,
+    ) -> AuditLog {
+        AuditLog {
+            uid: Uuid::new_v4(),
+            group_uid: Uuid::new_v4(),
+            actor_user_uid: None,
+            actor_name: Some("Siti".to_string()),
+            source: "telegram".to_string(),
+            entity_type: entity_type.to_string(),
+            entity_uid: Uuid::new_v4(),
+            action: action.to_string(),
+            before,
+            after,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_kind_of() {
+        assert_eq!(
+            ActivityKind::of("expense_entry", "update"),
+            Some(ActivityKind::ExpenseEdited)
+        );
+        assert_eq!(
+            ActivityKind::of("group_member", "create"),
+            Some(ActivityKind::MemberJoined)
+        );
+        assert_eq!(
+            ActivityKind::of("budget", "delete"),
+            Some(ActivityKind::BudgetRemoved)
+        );
+        assert_eq!(ActivityKind::of("category", "create"), None);
+    }
+
+    #[test]
+    fn test_expense_edit() {
+        let log = log(
+            "expense_entry",
+            "update",
+            Some(json!({ "product": "Kopi", "price": 20000, "currency": "IDR" })),
+            Some(json!({ "product": "Kopi susu", "price": 25000, "currency": "IDR" })),
+        );
+        let event = ActivityEvent::from_log(&log, &HashMap::new(), &HashMap::new()).unwrap();
+        assert_eq!(event.kind, "expense_edited");
+        assert_eq!(
+            ActivityKind::parse(&event.kind),
+            Some(ActivityKind::ExpenseEdited)
+        );
+        assert_eq!(event.actor_name.as_deref(), Some("Siti"));
+        assert_eq!(event.subject.as_deref(), Some("Kopi susu"));
+        assert_eq!(event.amount, Some(dec!(25000)));
+        assert_eq!(event.currency.as_deref(), Some("IDR"));
+        assert_eq!(event.previous_amount, Some(dec!(20000)));
+    }
+
+    #[test]
+    fn test_expense_delete_keeps_snapshot() {
+        let log = log(
+            "expense_entry",
+            "delete",
+            Some(json!({ "product": "Kopi", "price": 20000, "currency": "IDR" })),
+            None,
+        );
+        let event = ActivityEvent::from_log(&log, &HashMap::new(), &HashMap::new()).unwrap();
+        assert_eq!(event.kind, "expense_deleted");
+        assert_eq!(event.subject.as_deref(), Some("Kopi"));
+        assert_eq!(event.amount, Some(dec!(20000)));
+        assert_eq!(event.previous_amount, None);
+    }
+
+    #[test]
+    fn test_member_and_budget_names() {
+        let user_uid = Uuid::new_v4();
+        let category_uid = Uuid::new_v4();
+        let names = HashMap::from([(user_uid, "budi".to_string())]);
+        let categories = HashMap::from([(category_uid, "Makanan".to_string())]);
+
+        let mut joined = log(
+            "group_member",
+            "create",
+            None,
+            Some(json!({ "user_uid": user_uid, "role": "member" })),
+        );
+        joined.actor_name = None;
+        joined.actor_user_uid = Some(user_uid);
+        let event = ActivityEvent::from_log(&joined, &names, &categories).unwrap();
+        assert_eq!(event.kind, "member_joined");
+        assert_eq!(event.actor_name.as_deref(), Some("budi"));
+        assert_eq!(event.subject.as_deref(), Some("budi"));
+        assert_eq!(event.role.as_deref(), Some("member"));
+        assert_eq!(event.amount, None);
+
+        let budget = log(
+            "budget",
+            "update",
+            Some(json!({ "category_uid": category_uid, "amount": 500000, "currency": "IDR" })),
+            Some(json!({ "category_uid": category_uid, "amount": 500000, "currency": "IDR" })),
+        );
+        let event = ActivityEvent::from_log(&budget, &names, &categories).unwrap();
+        assert_eq!(event.kind, "budget_changed");
+        assert_eq!(event.subject.as_deref(), Some("Makanan"));
+        assert_eq!(event.previous_amount, None);
+    }
+}
@@ -0,0 +1,174 @@
+/*
+Unusual spending. An amount stands out when it is far above the median of what was spent
+before, measured in median absolute deviations (the modified z-score), and is also a multiple
+of that median. Both have to hold: the first ignores categories that swing a lot anyway, the
+second ignores small changes in categories that barely move at all.
+*/
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+// Past amounts needed before anything counts as unusual
+pub const MIN_BASELINE_SAMPLES: usize = 5;
+// Makes the MAD comparable to a standard deviation for normally distributed amounts
+const MAD_SCALE: f64 = 0.6745;
+
+// How unusual spending has to be before the group's chats get an alert
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AnomalySensitivity {
+    Off,
+    Low,
+    #[default]
+    Medium,
+    High,
+}
+
+impl AnomalySensitivity {
+    // (modified z-score, multiple of the median) an amount has to reach
+    fn thresholds(&self) -> Option<(f64, f64)> {
+        match self {
+            Self::Off => None,
+            Self::Low => Some((5.0, 4.0)),
+            Self::Medium => Some((3.5, 3.0)),
+            Self::High => Some((2.5, 2.0)),
+        }
+    }
+}
+
+// What is usual for a category: the median of its past amounts and their spread around it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Baseline {
+    pub median: f64,
+    pub mad: f64,
+}
+
+impl Baseline {
+    // None with fewer than `MIN_BASELINE_SAMPLES` amounts
+    pub fn from_samples(samples: &[f64]) -> Option<Self> {
+        if samples.len() < MIN_BASELINE_SAMPLES {
+            return None;
+        }
+        let median = median_of(samples.to_vec());
+        let deviations = samples
+            .iter()
+            .map(|sample| (sample - median).abs())
+            .collect();
+        Some(Self {
+            median,
+            mad: median_of(deviations),
+        })
+    }
+
+    // How many times the median `amount` is, when that is unusual at `sensitivity`
+    pub fn outlier_ratio(&self, amount: f64, sensitivity: AnomalySensitivity) -> Option<f64> {
+        let (min_score, min_ratio) = sensitivity.thresholds()?;
+        if self.median <= 0.0 {
+            return None;
+        }
+        let ratio = amount / self.median;
+        if ratio < min_ratio {
+            return None;
+        }
+        // Steady spending has no spread, the multiple alone decides then
+        if self.mad > 0.0 && MAD_SCALE * (amount - self.median) / self.mad < min_score {
+            return None;
+        }
+        Some(ratio)
+    }
+}
+
+fn median_of(mut samples: Vec<f64>) -> f64 {
+    samples.sort_by(f64::total_cmp);
+    let mid = samples.len() / 2;
+    if samples.len().is_multiple_of(2) {
+        (samples[mid - 1] + samples[mid]) / 2.0
+    } else {
+        samples[mid]
+    }
+}
+
+// `4` or `2.5`, as in "4× the usual"
+pub fn format_ratio(ratio: f64) -> String {
+    if ratio >= 10.0 {
+        return format!("{:.0}", ratio);
+    }
+    let rounded = format!("{:.1}", ratio);
+    rounded.trim_end_matches(".0").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_baseline() {
+        let baseline = Baseline::from_samples(&[10.0, 12.0, 11.0, 9.0, 10.0, 13.0, 10.0]).unwrap();
+        assert_eq!(
+            baseline,
+            Baseline {
+                median: 10.0,
+                mad: 1.0
+            }
+        );
+        let even = Baseline::from_samples(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]).unwrap();
+        assert_eq!(even.median, 3.5);
+        assert_eq!(even.mad, 1.5);
+        assert_eq!(Baseline::from_samples(&[10.0, 10.0, 10.0, 10.0]), None);
+    }
+
+    #[test]
+    fn test_outlier_ratio() {
+        let baseline = Baseline::from_samples(&[10.0, 12.0, 11.0, 9.0, 10.0, 13.0, 10.0]).unwrap();
+        assert_eq!(
+            baseline.outlier_ratio(40.0, AnomalySensitivity::Medium),
+            Some(4.0)
+        );
+        assert_eq!(
+            baseline.outlier_ratio(25.0, AnomalySensitivity::Medium),
+            None
+        );
+        assert_eq!(
+            baseline.outlier_ratio(25.0, AnomalySensitivity::High),
+            Some(2.5)
+        );
+        assert_eq!(baseline.outlier_ratio(35.0, AnomalySensitivity::Low), None);
+        assert_eq!(baseline.outlier_ratio(400.0, AnomalySensitivity::Off), None);
+    }
+
+    #[test]
+    fn test_outlier_ratio_needs_a_high_score() {
+        // Amounts that swing a lot make a triple of the median nothing special
+        let baseline = Baseline::from_samples(&[2.0, 10.0, 30.0, 5.0, 10.0, 25.0]).unwrap();
+        assert_eq!(
+            baseline.outlier_ratio(30.0, AnomalySensitivity::Medium),
+            None
+        );
+        assert!(
+            baseline
+                .outlier_ratio(100.0, AnomalySensitivity::Medium)
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn test_outlier_ratio_steady_baseline() {
+        let baseline = Baseline::from_samples(&[10.0; 6]).unwrap();
+        assert_eq!(baseline.mad, 0.0);
+        assert_eq!(
+            baseline.outlier_ratio(30.0, AnomalySensitivity::Medium),
+            Some(3.0)
+        );
+        assert_eq!(
+            baseline.outlier_ratio(29.0, AnomalySensitivity::Medium),
+            None
+        );
+    }
+
+    #[test]
+    fn test_format_ratio() {
+        assert_eq!(format_ratio(4.0), "4");
+        assert_eq!(format_ratio(2.54), "2.5");
+        assert_eq!(format_ratio(2.96), "3");
+        assert_eq!(format_ratio(12.4), "12");
+    }
+}
@@ -0,0 +1,496 @@
+/*
+Bank statements for reconciliation, exported by the bank as CSV or OFX.
+
+Only money going out is kept, as a positive amount; deposits and other credits are counted and
+skipped. CSV files need a header row, columns are matched case-insensitively:
+- date: date | tanggal | transaction date | tanggal transaksi | posting date | value date
+- description: description | keterangan | memo | details | narrative | payee | uraian
+- either a signed amount: amount | jumlah | nominal | mutasi, negative or suffixed with
+  DB / DR for debits and CR for credits (as in BCA exports),
+- or separate columns: debit | debet | withdrawal | money out and credit | kredit | deposit | money in
+
+Dates accept YYYY-MM-DD, DD/MM/YYYY, DD-MM-YYYY, DD MMM YYYY and MM/DD/YYYY when the day
+comes second, optionally followed by a time.
+*/
+use std::sync::LazyLock;
+
+use anyhow::Result;
+use chrono::NaiveDate;
+use regex::Regex;
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+use crate::utils::{
+    expense_import::{ImportRowError, read_csv},
+    parse_price::parse_amount,
+};
+
+const MAX_DESCRIPTION_LENGTH: usize = 255;
+
+static OFX_TRANSACTION_START: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)<STMTTRN>").unwrap());
+
+// OFX 1.x is SGML, a transaction may only end where the next one or the list starts
+static OFX_TRANSACTION_END: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)</STMTTRN>|</BANKTRANLIST>").unwrap());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatementFormat {
+    Csv,
+    Ofx,
+}
+
+impl StatementFormat {
+    // OFX by extension (.ofx, or Quicken's .qfx) or by its header, CSV otherwise
+    pub fn detect(file_name: Option<&str>, bytes: &[u8]) -> Self {
+        let name = file_name.unwrap_or_default().to_lowercase();
+        let head = String::from_utf8_lossy(&bytes[..bytes.len().min(1024)]).to_uppercase();
+        if name.ends_with(".ofx")
+            || name.ends_with(".qfx")
+            || head.contains("OFXHEADER")
+            || head.contains("<OFX>")
+        {
+            Self::Ofx
+        } else {
+            Self::Csv
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Ofx => "ofx",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatementRow {
+    // 1-based: the row in a CSV file, the header being row 1, or the transaction in an OFX file
+    pub line: usize,
+    pub date: NaiveDate,
+    // Money out, always positive
+    pub amount: Decimal,
+    pub description: String,
+}
+
+#[derive(Debug, Default)]
+pub struct ParsedStatement {
+    pub rows: Vec<StatementRow>,
+    // Credits and zero amounts
+    pub skipped: usize,
+    pub errors: Vec<ImportRowError>,
+    // Currency declared by the file, OFX only
+    pub currency: Option<String>,
+}
+
+pub fn parse(format: StatementFormat, bytes: &[u8]) -> Result<ParsedStatement> {
+    match format {
+        StatementFormat::Csv => parse_csv(&read_csv(bytes)?),
+        StatementFormat::Ofx => parse_ofx(&String::from_utf8_lossy(bytes)),
+    }
+}
+
+#[derive(Debug, Default)]
+struct ColumnMap {
+    date: Option<usize>,
+    description: Option<usize>,
+    amount: Option<usize>,
+    debit: Option<usize>,
+    credit: Option<usize>,
+}
+
+impl ColumnMap {
+    fn from_header(header: &[String]) -> Result<Self> {
+        let mut map = ColumnMap::default();
+        for (index, name) in header.iter().enumerate() {
+            let column = match name.trim().to_lowercase().as_str() {
+                "date" | "tanggal" | "transaction date" | "tanggal transaksi" | "posting date"
+                | "posted date" | "value date" | "tgl" => &mut map.date,
+                "description"
+                | "keterangan"
+                | "memo"
+                | "details"
+                | "narrative"
+                | "payee"
+                | "uraian"
+                | "transaction description" => &mut map.description,
+                "amount" | "jumlah" | "nominal" | "mutasi" => &mut map.amount,
+                "debit" | "debet" | "withdrawal" | "withdrawals" | "money out" | "paid out" => {
+                    &mut map.debit
+                }
+                "credit" | "kredit" | "deposit" | "deposits" | "money in" | "paid in" => {
+                    &mut map.credit
+                }
+                _ => continue,
+            };
+            // The first matching column wins, e.g. "Date" over a later "Value Date"
+            column.get_or_insert(index);
+        }
+        if map.date.is_none() || (map.amount.is_none() && map.debit.is_none()) {
+            return Err(anyhow::anyhow!(
+                "Header must contain a date and an amount or debit column"
+            ));
+        }
+        Ok(map)
+    }
+}
+
+fn parse_csv(table: &[Vec<String>]) -> Result<ParsedStatement> {
+    let Some((header, rows)) = table.split_first() else {
+        return Err(anyhow::anyhow!("File is empty"));
+    };
+    let columns = ColumnMap::from_header(header)?;
+    let cell = |row: &Vec<String>, index: Option<usize>| -> Option<String> {
+        index
+            .and_then(|i| row.get(i))
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+
+    let mut statement = ParsedStatement::default();
+    for (offset, row) in rows.iter().enumerate() {
+        let line = offset + 2;
+        if row.iter().all(|value| value.trim().is_empty()) {
+            continue;
+        }
+        let error = |message: String| ImportRowError { row: line, message };
+
+        let Some(date) = cell(row, columns.date) else {
+            statement.errors.push(error("Missing date".to_string()));
+            continue;
+        };
+        let Some(date) = parse_date(&date) else {
+            statement
+                .errors
+                .push(error(format!("Invalid date: {}", date)));
+            continue;
+        };
+
+        // Negative is money out
+        let signed = match (cell(row, columns.amount), cell(row, columns.debit)) {
+            (Some(amount), _) => parse_signed_amount(&amount).ok_or(amount),
+            (None, Some(debit)) => parse_signed_amount(&debit)
+                .map(|amount| -amount.abs())
+                .ok_or(debit),
+            (None, None) => match cell(row, columns.credit) {
+                Some(credit) => parse_signed_amount(&credit)
+                    .map(|amount| amount.abs())
+                    .ok_or(credit),
+                None => {
+                    statement.errors.push(error("Missing amount".to_string()));
+                    continue;
+                }
+            },
+        };
+        let signed = match signed {
+            Ok(signed) => signed,
+            Err(amount) => {
+                statement
+                    .errors
+                    .push(error(format!("Invalid amount: {}", amount)));
+                continue;
+            }
+        };
+        if !signed.is_sign_negative() || signed.is_zero() {
+            statement.skipped += 1;
+            continue;
+        }
+        statement.rows.push(StatementRow {
+            line,
+            date,
+            amount: signed.abs(),
+            description: description(cell(row, columns.description).as_deref()),
+        });
+    }
+    Ok(statement)
+}
+
+fn parse_ofx(text: &str) -> Result<ParsedStatement> {
+    if !text.to_uppercase().contains("<OFX>") {
+        return Err(anyhow::anyhow!("Invalid OFX: missing <OFX> element"));
+    }
+    let mut statement = ParsedStatement {
+        currency: ofx_value(text, "CURDEF").map(|code| code.to_uppercase()),
+        ..Default::default()
+    };
+    for (index, transaction) in OFX_TRANSACTION_START.split(text).skip(1).enumerate() {
+        let line = index + 1;
+        let transaction = match OFX_TRANSACTION_END.find(transaction) {
+            Some(end) => &transaction[..end.start()],
+            None => transaction,
+        };
+        let error = |message: String| ImportRowError { row: line, message };
+
+        // YYYYMMDD, optionally followed by the time and zone
+        let posted = ofx_value(transaction, "DTPOSTED").unwrap_or_default();
+        let Some(date) = posted
+            .get(..8)
+            .and_then(|date| NaiveDate::parse_from_str(date, "%Y%m%d").ok())
+        else {
+            statement
+                .errors
+                .push(error(format!("Invalid date: {}", posted)));
+            continue;
+        };
+        let amount = ofx_value(transaction, "TRNAMT").unwrap_or_default();
+        let Some(signed) = parse_signed_amount(&amount) else {
+            statement
+                .errors
+                .push(error(format!("Invalid amount: {}", amount)));
+            continue;
+        };
+        if !signed.is_sign_negative() || signed.is_zero() {
+            statement.skipped += 1;
+            continue;
+        }
+        let name = ofx_value(transaction, "NAME").or_else(|| ofx_value(transaction, "MEMO"));
+        statement.rows.push(StatementRow {
+            line,
+            date,
+            amount: signed.abs(),
+            description: description(name.as_deref()),
+        });
+    }
+    Ok(statement)
+}
+
+// Value of `<TAG>value`, closed or not since OFX 1.x is SGML
+fn ofx_value(text: &str, tag: &str) -> Option<String> {
+    let pattern = format!(r"(?i)<{}>\s*([^<\r\n]+)", regex::escape(tag));
+    let value = Regex::new(&pattern).ok()?.captures(text)?[1]
+        .trim()
+        .to_string();
+    Some(value).filter(|value| !value.is_empty())
+}
+
+fn description(text: Option<&str>) -> String {
+    let text = text
+        .map(|text| text.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|text| !text.is_empty())
+        .unwrap_or_else(|| "Bank transaction".to_string());
+    text.chars().take(MAX_DESCRIPTION_LENGTH).collect()
+}
+
+/*
+ `-50,000.00`, `(50.000)`, `50,000.00 DB` and `Rp 50.000 DR` are all -50000, `50,000.00 CR`
+ and `+50.000` are 50000
+*/
+fn parse_signed_amount(input: &str) -> Option<Decimal> {
+    let mut text = input.trim().to_uppercase();
+    let mut negative = false;
+    if let Some(inner) = text
+        .strip_prefix('(')
+        .and_then(|text| text.strip_suffix(')'))
+    {
+        negative = true;
+        text = inner.trim().to_string();
+    }
+    for (suffix, debit) in [("DB", true), ("DR", true), ("CR", false)] {
+        if let Some(rest) = text.strip_suffix(suffix)
+            && rest.ends_with(|c: char| c.is_ascii_digit() || c.is_whitespace())
+        {
+            negative |= debit;
+            text = rest.trim().to_string();
+            break;
+        }
+    }
+    let text = text
+        .trim_start_matches("RP.")
+        .trim_start_matches("RP")
+        .trim();
+    let text = match text.strip_prefix('-') {
+        Some(rest) => {
+            negative = true;
+            rest
+        }
+        None => text.strip_prefix('+').unwrap_or(text),
+    };
+    let amount = parse_amount(text.trim())?;
+    Some(if negative { -amount } else { amount })
+}
+
+fn parse_date(input: &str) -> Option<NaiveDate> {
+    [
+        "%Y-%m-%d", "%d/%m/%Y", "%d-%m-%Y", "%d %b %Y", "%d %B %Y", "%m/%d/%Y",
+    ]
+    .iter()
+    .find_map(|format| NaiveDate::parse_and_remainder(input.trim(), format).ok())
+    .filter(|(_, rest)| rest.is_empty() || rest.starts_with([' ', 'T']))
+    .map(|(date, _)| date)
+}
+
+// An expense entry a statement row can be matched with
+#[derive(Debug, Clone)]
+pub struct CandidateEntry {
+    pub uid: Uuid,
+    pub price: Decimal,
+    pub date: NaiveDate,
+}
+
+/*
+ The entry each row pays for: the same amount at most `window_days` apart, the closest date
+ winning. Every entry is matched at most once, rows are taken by date so an earlier payment
+ doesn't take the entry of a later one.
+*/
+pub fn match_rows(
+    rows: &[StatementRow],
+    entries: &[CandidateEntry],
+    window_days: i64,
+) -> Vec<Option<Uuid>> {
+    let mut order: Vec<usize> = (0..rows.len()).collect();
+    order.sort_by_key(|&index| (rows[index].date, rows[index].line));
+    let mut used = vec![false; entries.len()];
+    let mut matches = vec![None; rows.len()];
+    for index in order {
+        let row = &rows[index];
+        let best = entries
+            .iter()
+            .enumerate()
+            .filter(|(candidate, entry)| {
+                !used[*candidate]
+                    && entry.price == row.amount
+                    && (entry.date - row.date).num_days().abs() <= window_days
+            })
+            .min_by_key(|(_, entry)| (entry.date - row.date).num_days().abs());
+        if let Some((candidate, entry)) = best {
+            used[candidate] = true;
+            matches[index] = Some(entry.uid);
+        }
+    }
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_parse_csv_signed_amounts() {
+        let csv = "Tanggal Transaksi,Keterangan,Cabang,Jumlah,Saldo
+03/11/2025,TRSF E-BANKING GRAB,0000,\"43,000.00 DB\",\"1,000,000.00\"
+04/11/2025,SETORAN,0000,\"500,000.00 CR\",\"1,500,000.00\"
+2025-11-05,Indomaret,,-25.500,
+bad,Kopi,,-10000,
+";
+        let statement = parse(StatementFormat::Csv, csv.as_bytes()).unwrap();
+        assert_eq!(
+            statement.rows,
+            vec![
+                StatementRow {
+                    line: 2,
+                    date: date(2025, 11, 3),
+                    amount: dec!(43000),
+                    description: "TRSF E-BANKING GRAB".to_string(),
+                },
+                StatementRow {
+                    line: 4,
+                    date: date(2025, 11, 5),
+                    amount: dec!(25500),
+                    description: "Indomaret".to_string(),
+                },
+            ]
+        );
+        assert_eq!(statement.skipped, 1);
+        assert_eq!(statement.errors.len(), 1);
+        assert_eq!(statement.errors[0].row, 5);
+    }
+
+    #[test]
+    fn test_parse_csv_debit_credit_columns() {
+        let csv =
+            "Date,Description,Debit,Credit\n11/25/2025,Coffee,4.50,\n11/26/2025,Salary,,2000.00\n";
+        let statement = parse(StatementFormat::Csv, csv.as_bytes()).unwrap();
+        assert_eq!(statement.rows.len(), 1);
+        assert_eq!(statement.rows[0].date, date(2025, 11, 25));
+        assert_eq!(statement.rows[0].amount, dec!(4.50));
+        assert_eq!(statement.skipped, 1);
+
+        assert!(parse(StatementFormat::Csv, b"Description,Balance\nCoffee,10\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_ofx() {
+        let ofx = "OFXHEADER:100
+DATA:OFXSGML
+
+<OFX><BANKMSGSRSV1><STMTTRNRS><STMTRS>
+<CURDEF>idr
+<BANKTRANLIST>
+<STMTTRN><TRNTYPE>DEBIT<DTPOSTED>20251103120000[+7:WIB]<TRNAMT>-43000.00<FITID>1<NAME>GRAB*RIDE
+<STMTTRN><TRNTYPE>CREDIT<DTPOSTED>20251104<TRNAMT>500000.00<FITID>2<NAME>SALARY
+<STMTTRN><TRNTYPE>DEBIT<DTPOSTED>20251105<TRNAMT>-25500<FITID>3<MEMO>Indomaret</STMTTRN>
+</BANKTRANLIST>
+</STMTRS></STMTTRNRS></BANKMSGSRSV1></OFX>";
+        assert_eq!(
+            StatementFormat::detect(Some("statement.txt"), ofx.as_bytes()),
+            StatementFormat::Ofx
+        );
+        let statement = parse(StatementFormat::Ofx, ofx.as_bytes()).unwrap();
+        assert_eq!(statement.currency.as_deref(), Some("IDR"));
+        assert_eq!(statement.skipped, 1);
+        assert_eq!(statement.rows.len(), 2);
+        assert_eq!(statement.rows[0].date, date(2025, 11, 3));
+        assert_eq!(statement.rows[0].amount, dec!(43000));
+        assert_eq!(statement.rows[0].description, "GRAB*RIDE");
+        assert_eq!(statement.rows[1].line, 3);
+        assert_eq!(statement.rows[1].description, "Indomaret");
+
+        assert!(parse(StatementFormat::Ofx, b"not ofx").is_err());
+    }
+
+    #[test]
+    fn test_parse_signed_amount() {
+        assert_eq!(parse_signed_amount("-50,000.00"), Some(dec!(-50000)));
+        assert_eq!(parse_signed_amount("(50.000)"), Some(dec!(-50000)));
+        assert_eq!(parse_signed_amount("50,000.00 DB"), Some(dec!(-50000)));
+        assert_eq!(parse_signed_amount("Rp 50.000 DR"), Some(dec!(-50000)));
+        assert_eq!(parse_signed_amount("50,000.00 CR"), Some(dec!(50000)));
+        assert_eq!(parse_signed_amount("+50.000"), Some(dec!(50000)));
+        assert_eq!(parse_signed_amount("abc"), None);
+    }
+
+    #[test]
+    fn test_match_rows() {
+        let row = |line: usize, day: u32, amount: Decimal| StatementRow {
+            line,
+            date: date(2025, 11, day),
+            amount,
+            description: String::new(),
+        };
+        let entry = |day: u32, price: Decimal| CandidateEntry {
+            uid: Uuid::new_v4(),
+            price,
+            date: date(2025, 11, day),
+        };
+        let entries = vec![
+            entry(2, dec!(43000)),
+            entry(10, dec!(43000)),
+            entry(5, dec!(25500)),
+            entry(20, dec!(99000)),
+        ];
+        let rows = vec![
+            row(2, 11, dec!(43000)),
+            row(3, 3, dec!(43000)),
+            row(4, 5, dec!(25500)),
+            row(5, 5, dec!(25500)),
+            row(6, 28, dec!(99000)),
+        ];
+        let matches = match_rows(&rows, &entries, 3);
+        assert_eq!(
+            matches,
+            vec![
+                Some(entries[1].uid),
+                Some(entries[0].uid),
+                Some(entries[2].uid),
+                None,
+                None,
+            ]
+        );
+    }
+}
@@ -0,0 +1,202 @@
+/*
+Hard budget limits. A budget with `hard_limit` set is not only reported on afterwards: an
+expense taking spending in its category past the amount is flagged when it is recorded, or
+held back until confirmed, depending on the group's `hard_limit_action`.
+*/
+use std::collections::HashMap;
+
+use chrono::{NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::DatabaseError;
+use crate::repos::{
+    budget::{Budget, BudgetRepo},
+    category::CategoryRepo,
+    exchange_rate::ExchangeRateRepo,
+    expense_entry::ExpenseEntryRepo,
+    expense_group::ExpenseGroupRepo,
+    group_settings::GroupSettingsRepo,
+};
+
+// An expense about to be recorded in a category, outside of any envelope
+#[derive(Debug, Clone)]
+pub struct NewSpend {
+    pub category_uid: Uuid,
+    pub amount: Decimal,
+    // Defaults to the group's currency
+    pub currency: Option<String>,
+    pub date: NaiveDate,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct HardLimitBreach {
+    pub budget_uid: Uuid,
+    pub category_uid: Uuid,
+    pub amount: Decimal,
+    pub currency: String,
+    /// Spent in the budget's period once the new expenses are recorded, in the budget's currency
+    pub spent: Decimal,
+}
+
+/*
+ Budgets with a hard limit that `spends` would take, or keep, past their amount. Only the
+ budget each category is tracked against today counts, and only spends dated within its
+ period. Spends in a subcategory count against the parent's budget too. Call it before the
+ spends are recorded.
+*/
+pub async fn find_breaches(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    group_uid: Uuid,
+    spends: &[NewSpend],
+) -> Result<Vec<HardLimitBreach>, DatabaseError> {
+    if spends.is_empty() {
+        return Ok(Vec::new());
+    }
+    let budgets = BudgetRepo::list_by_group(tx, group_uid).await?;
+    if !budgets.iter().any(|budget| budget.hard_limit) {
+        return Ok(Vec::new());
+    }
+
+    let group = ExpenseGroupRepo::get(tx, group_uid).await?;
+    let week_start = GroupSettingsRepo::get(tx, group_uid).await?.week_start;
+    let rates = ExchangeRateRepo::rate_table(tx).await?;
+    let parents: HashMap<Uuid, Uuid> = CategoryRepo::list_by_group(tx, group_uid)
+        .await?
+        .into_iter()
+        .filter_map(|category| Some((category.uid, category.parent_uid?)))
+        .collect();
+    let today = Utc::now().date_naive();
+    let mut active: Vec<Budget> =
+        Budget::active_by_category(budgets, group.start_over_date, week_start, today)
+            .into_values()
+            .filter(|budget| budget.hard_limit)
+            .collect();
+    active.sort_by_key(|budget| budget.category_uid);
+
+    let mut breaches = Vec::new();
+    for budget in active {
+        let (period_start, period_end) =
+            budget.period_range(group.start_over_date, week_start, today);
+        let adding = spends_in_period(
+            spends,
+            &budget,
+            &parents,
+            period_start,
+            period_end,
+            &group.currency,
+        );
+        if adding.is_empty() {
+            continue;
+        }
+        let mut totals = ExpenseEntryRepo::sum_by_category_in_range(
+            tx,
+            group_uid,
+            budget.category_uid,
+            period_start.and_hms_opt(0, 0, 0).unwrap().and_utc(),
+            period_end.and_hms_opt(0, 0, 0).unwrap().and_utc(),
+        )
+        .await?;
+        totals.extend(adding);
+        let (spent, _) = rates.sum_in(&totals, &budget.currency);
+        if spent > budget.amount {
+            breaches.push(HardLimitBreach {
+                budget_uid: budget.uid,
+                category_uid: budget.category_uid,
+                amount: budget.amount,
+                currency: budget.currency,
+                spent,
+            });
+        }
+    }
+    Ok(breaches)
+}
+
+/*
+ Currency and amount of the spends counting against `budget` in [period_start, period_end),
+ `parents` maps subcategories to their parent
+*/
+fn spends_in_period(
+    spends: &[NewSpend],
+    budget: &Budget,
+    parents: &HashMap<Uuid, Uuid>,
+    period_start: NaiveDate,
+    period_end: NaiveDate,
+    group_currency: &str,
+) -> Vec<(String, Decimal)> {
+    spends
+        .iter()
+        .filter(|spend| {
+            spend.category_uid == budget.category_uid
+                || parents.get(&spend.category_uid) == Some(&budget.category_uid)
+        })
+        .filter(|spend| (period_start..period_end).contains(&spend.date))
+        .map(|spend| {
+            let currency = spend.currency.as_deref().unwrap_or(group_currency);
+            (currency.to_string(), spend.amount)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_spends_in_period() {
+        let budget = Budget {
+            uid: Uuid::new_v4(),
+            group_uid: Uuid::new_v4(),
+            category_uid: Uuid::new_v4(),
+            amount: dec!(100000),
+            currency: "IDR".to_string(),
+            period_type: "monthly".to_string(),
+            period_year: None,
+            period_month: None,
+            range_start: None,
+            range_end: None,
+            hard_limit: true,
+        };
+        let spend = |category_uid: Uuid, currency: Option<&str>, date: NaiveDate| NewSpend {
+            category_uid,
+            amount: dec!(5000),
+            currency: currency.map(str::to_string),
+            date,
+        };
+        let subcategory = Uuid::new_v4();
+        let parents = HashMap::from([(subcategory, budget.category_uid)]);
+        let spends = vec![
+            spend(budget.category_uid, None, date(2025, 3, 1)),
+            spend(budget.category_uid, Some("USD"), date(2025, 3, 31)),
+            // Another category
+            spend(Uuid::new_v4(), None, date(2025, 3, 10)),
+            // Backdated into the previous cycle
+            spend(budget.category_uid, None, date(2025, 2, 28)),
+            // Subcategory, counted against the parent's budget
+            spend(subcategory, Some("SGD"), date(2025, 3, 15)),
+        ];
+
+        assert_eq!(
+            spends_in_period(
+                &spends,
+                &budget,
+                &parents,
+                date(2025, 3, 1),
+                date(2025, 4, 1),
+                "IDR"
+            ),
+            vec![
+                ("IDR".to_string(), dec!(5000)),
+                ("USD".to_string(), dec!(5000)),
+                ("SGD".to_string(), dec!(5000)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,115 @@
+/*
+ Icon and color a category is drawn with on dashboards and in reports. Categories created
+ without one get the style of the template matching their name, in English or Indonesian,
+ or the fallback. Keep the templates in sync with the backfill in the
+ `add_category_icons` migration.
+*/
+
+pub const DEFAULT_ICON: &str = "🏷️";
+pub const DEFAULT_COLOR: &str = "#64748b";
+
+// (names, icon, color)
+const TEMPLATES: &[(&[&str], &str, &str)] = &[
+    (&["food", "makanan", "makan"], "🍔", "#f97316"),
+    (&["drinks", "minuman", "coffee", "kopi"], "☕", "#92400e"),
+    (
+        &["groceries", "belanja bulanan", "sembako"],
+        "🛒",
+        "#16a34a",
+    ),
+    (
+        &["transport", "transportation", "transportasi"],
+        "🚗",
+        "#2563eb",
+    ),
+    (&["shopping", "belanja"], "🛍️", "#db2777"),
+    (&["entertainment", "hiburan"], "🎬", "#7c3aed"),
+    (&["bills", "utilities", "tagihan"], "💡", "#eab308"),
+    (&["health", "kesehatan"], "💊", "#dc2626"),
+    (&["education", "pendidikan"], "📚", "#0891b2"),
+    (&["home", "housing", "rumah"], "🏠", "#65a30d"),
+    (&["travel", "liburan"], "✈️", "#0ea5e9"),
+    (&["gifts", "hadiah"], "🎁", "#e11d48"),
+];
+
+// (icon, color) for a new category called `name`
+pub fn default_style(name: &str) -> (&'static str, &'static str) {
+    let name = name.trim().to_lowercase();
+    TEMPLATES
+        .iter()
+        .find(|(names, _, _)| names.contains(&name.as_str()))
+        .map(|(_, icon, color)| (*icon, *color))
+        .unwrap_or((DEFAULT_ICON, DEFAULT_COLOR))
+}
+
+/*
+ A single emoji, possibly built from several code points (skin tones, flags, ZWJ
+ sequences), or a short code like `:coffee:`
+*/
+pub fn is_valid_icon(icon: &str) -> bool {
+    if let Some(code) = icon
+        .strip_prefix(':')
+        .and_then(|icon| icon.strip_suffix(':'))
+    {
+        return (1..=30).contains(&code.len())
+            && code
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "_+-".contains(c));
+    }
+    (1..=8).contains(&icon.chars().count())
+        && icon
+            .chars()
+            .all(|c| !c.is_ascii() && !c.is_whitespace() && !c.is_control())
+}
+
+// `#rrggbb`, either case
+pub fn is_valid_color(color: &str) -> bool {
+    rgb(color).is_some()
+}
+
+pub fn rgb(color: &str) -> Option<(u8, u8, u8)> {
+    let hex = color.strip_prefix('#')?;
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |index: usize| u8::from_str_radix(&hex[index..index + 2], 16).ok();
+    Some((channel(0)?, channel(2)?, channel(4)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_style() {
+        assert_eq!(default_style("Makanan"), ("🍔", "#f97316"));
+        assert_eq!(default_style(" food "), ("🍔", "#f97316"));
+        assert_eq!(default_style("Transportasi"), ("🚗", "#2563eb"));
+        assert_eq!(default_style("Hobi"), (DEFAULT_ICON, DEFAULT_COLOR));
+    }
+
+    #[test]
+    fn test_is_valid_icon() {
+        assert!(is_valid_icon("🍔"));
+        assert!(is_valid_icon("👍🏽"));
+        assert!(is_valid_icon("👨‍👩‍👧"));
+        assert!(is_valid_icon(":coffee:"));
+        assert!(is_valid_icon(":+1:"));
+        assert!(!is_valid_icon(""));
+        assert!(!is_valid_icon("::"));
+        assert!(!is_valid_icon("coffee"));
+        assert!(!is_valid_icon(":Coffee Cup:"));
+        assert!(!is_valid_icon("🍔 food"));
+        assert!(!is_valid_icon("🍔🍔🍔🍔🍔🍔🍔🍔🍔"));
+    }
+
+    #[test]
+    fn test_rgb() {
+        assert_eq!(rgb("#f97316"), Some((249, 115, 22)));
+        assert_eq!(rgb("#F97316"), Some((249, 115, 22)));
+        assert!(is_valid_color("#000000"));
+        assert!(!is_valid_color("f97316"));
+        assert!(!is_valid_color("#fff"));
+        assert!(!is_valid_color("#gggggg"));
+    }
+}
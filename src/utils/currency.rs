@@ -0,0 +1,215 @@
+use rust_decimal::{Decimal, RoundingStrategy};
+use std::collections::HashMap;
+
+pub const DEFAULT_CURRENCY: &str = "IDR";
+
+#[derive(Debug, PartialEq)]
+pub struct Currency {
+    pub code: &'static str,
+    pub symbol: &'static str,
+    pub decimals: usize,
+    pub thousands_separator: char,
+    pub decimal_separator: char,
+}
+
+pub const SUPPORTED_CURRENCIES: &[Currency] = &[
+    Currency {
+        code: "IDR",
+        symbol: "Rp. ",
+        decimals: 0,
+        thousands_separator: '.',
+        decimal_separator: ',',
+    },
+    Currency {
+        code: "USD",
+        symbol: "$",
+        decimals: 2,
+        thousands_separator: ',',
+        decimal_separator: '.',
+    },
+    Currency {
+        code: "EUR",
+        symbol: "€",
+        decimals: 2,
+        thousands_separator: '.',
+        decimal_separator: ',',
+    },
+    Currency {
+        code: "SGD",
+        symbol: "S$",
+        decimals: 2,
+        thousands_separator: ',',
+        decimal_separator: '.',
+    },
+    Currency {
+        code: "MYR",
+        symbol: "RM",
+        decimals: 2,
+        thousands_separator: ',',
+        decimal_separator: '.',
+    },
+    Currency {
+        code: "JPY",
+        symbol: "¥",
+        decimals: 0,
+        thousands_separator: ',',
+        decimal_separator: '.',
+    },
+];
+
+impl Currency {
+    // Case-insensitive lookup by ISO 4217 code
+    pub fn find(code: &str) -> Option<&'static Currency> {
+        let code = code.trim();
+        SUPPORTED_CURRENCIES
+            .iter()
+            .find(|currency| currency.code.eq_ignore_ascii_case(code))
+    }
+
+    /*
+     Formats `amount` with the currency's symbol and separators, e.g.
+     IDR 1234567 -> Rp. 1.234.567
+     USD 1234.5  -> $1,234.50
+    */
+    pub fn format(&self, amount: Decimal) -> String {
+        let rounded = amount
+            .abs()
+            .round_dp_with_strategy(self.decimals as u32, RoundingStrategy::MidpointAwayFromZero);
+        let formatted = format!("{:.*}", self.decimals, rounded);
+        let (whole, fraction) = match formatted.split_once('.') {
+            Some((whole, fraction)) => (whole, Some(fraction)),
+            None => (formatted.as_str(), None),
+        };
+
+        let mut grouped = String::new();
+        for (index, digit) in whole.chars().enumerate() {
+            if index > 0 && (whole.len() - index) % 3 == 0 {
+                grouped.push(self.thousands_separator);
+            }
+            grouped.push(digit);
+        }
+        if let Some(fraction) = fraction {
+            grouped.push(self.decimal_separator);
+            grouped.push_str(fraction);
+        }
+
+        let sign = if amount.is_sign_negative() && !rounded.is_zero() {
+            "-"
+        } else {
+            ""
+        };
+        format!("{}{}{}", sign, self.symbol, grouped)
+    }
+}
+
+// Validates a user supplied currency code and returns it in canonical (upper) case
+pub fn normalize_currency(code: &str) -> Option<String> {
+    Currency::find(code).map(|currency| currency.code.to_string())
+}
+
+/*
+ Exchange rates keyed by (base, quote), loaded from the `exchange_rates` table.
+ When only the opposite pair is known its inverse is used.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct RateTable {
+    rates: HashMap<(String, String), Decimal>,
+}
+
+impl RateTable {
+    pub fn new(rates: impl IntoIterator<Item = (String, String, Decimal)>) -> Self {
+        Self {
+            rates: rates
+                .into_iter()
+                .map(|(base, quote, rate)| ((base.to_uppercase(), quote.to_uppercase()), rate))
+                .collect(),
+        }
+    }
+
+    pub fn rate(&self, from: &str, to: &str) -> Option<Decimal> {
+        let (from, to) = (from.to_uppercase(), to.to_uppercase());
+        if from == to {
+            return Some(Decimal::ONE);
+        }
+        if let Some(rate) = self.rates.get(&(from.clone(), to.clone())) {
+            return Some(*rate);
+        }
+        self.rates
+            .get(&(to, from))
+            .filter(|rate| rate.is_sign_positive() && !rate.is_zero())
+            .map(|rate| Decimal::ONE / rate)
+    }
+
+    pub fn convert(&self, amount: Decimal, from: &str, to: &str) -> Option<Decimal> {
+        self.rate(from, to).map(|rate| amount * rate)
+    }
+
+    /*
+     Sums per-currency totals into `to`. Totals without a known rate are added as is
+     and their currency is returned so callers can tell the user the sum is approximate.
+    */
+    pub fn sum_in(&self, totals: &[(String, Decimal)], to: &str) -> (Decimal, Vec<String>) {
+        let mut sum = Decimal::ZERO;
+        let mut unconverted = Vec::new();
+        for (currency, amount) in totals {
+            match self.convert(*amount, currency, to) {
+                Some(converted) => sum += converted,
+                None => {
+                    sum += *amount;
+                    unconverted.push(currency.clone());
+                }
+            }
+        }
+        (sum, unconverted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_format() {
+        let idr = Currency::find("idr").unwrap();
+        assert_eq!(idr.format(dec!(1234567)), "Rp. 1.234.567");
+        assert_eq!(idr.format(dec!(0)), "Rp. 0");
+        assert_eq!(idr.format(dec!(-5000)), "-Rp. 5.000");
+
+        let usd = Currency::find("USD").unwrap();
+        assert_eq!(usd.format(dec!(1234.5)), "$1,234.50");
+        assert_eq!(usd.format(dec!(999.999)), "$1,000.00");
+
+        let eur = Currency::find("EUR").unwrap();
+        assert_eq!(eur.format(dec!(1234567.891)), "€1.234.567,89");
+    }
+
+    #[test]
+    fn test_normalize_currency() {
+        assert_eq!(normalize_currency(" usd "), Some("USD".to_string()));
+        assert_eq!(normalize_currency("XYZ"), None);
+    }
+
+    #[test]
+    fn test_rate_table() {
+        let rates = RateTable::new(vec![("USD".to_string(), "IDR".to_string(), dec!(16000))]);
+        assert_eq!(rates.convert(dec!(2), "USD", "IDR"), Some(dec!(32000)));
+        assert_eq!(rates.convert(dec!(32000), "IDR", "USD"), Some(dec!(2)));
+        assert_eq!(rates.convert(dec!(10), "IDR", "IDR"), Some(dec!(10)));
+        assert_eq!(rates.convert(dec!(10), "EUR", "IDR"), None);
+    }
+
+    #[test]
+    fn test_sum_in() {
+        let rates = RateTable::new(vec![("USD".to_string(), "IDR".to_string(), dec!(16000))]);
+        let totals = vec![
+            ("IDR".to_string(), dec!(50000)),
+            ("USD".to_string(), dec!(1.5)),
+            ("EUR".to_string(), dec!(10)),
+        ];
+        assert_eq!(
+            rates.sum_in(&totals, "IDR"),
+            (dec!(74010), vec!["EUR".to_string()])
+        );
+    }
+}
@@ -0,0 +1,67 @@
+/*
+ Keyset pagination cursors for lists ordered by `(created_at, uid)`. The position of the last
+ item is sent as unpadded base64 of `created_at,uid` so clients pass it back as `?after=`
+ without depending on what is inside.
+*/
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, SecondsFormat, Utc};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cursor {
+    pub created_at: DateTime<Utc>,
+    pub uid: Uuid,
+}
+
+impl Cursor {
+    pub fn new(created_at: DateTime<Utc>, uid: Uuid) -> Self {
+        Self { created_at, uid }
+    }
+
+    pub fn encode(&self) -> String {
+        let raw = format!(
+            "{},{}",
+            self.created_at.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+            self.uid
+        );
+        URL_SAFE_NO_PAD.encode(raw)
+    }
+
+    pub fn decode(input: &str) -> Option<Self> {
+        let bytes = URL_SAFE_NO_PAD.decode(input.trim()).ok()?;
+        let raw = String::from_utf8(bytes).ok()?;
+        let (created_at, uid) = raw.split_once(',')?;
+        Some(Self {
+            created_at: DateTime::parse_from_rfc3339(created_at)
+                .ok()?
+                .with_timezone(&Utc),
+            uid: Uuid::parse_str(uid).ok()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_round_trip() {
+        // Postgres keeps microseconds, they must survive the trip
+        let created_at = Utc.timestamp_micros(1_760_000_000_123_456).unwrap();
+        let cursor = Cursor::new(created_at, Uuid::new_v4());
+        let encoded = cursor.encode();
+        assert!(!encoded.contains(','));
+        assert_eq!(Cursor::decode(&encoded), Some(cursor));
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        assert_eq!(Cursor::decode("not a cursor"), None);
+        assert_eq!(Cursor::decode(&URL_SAFE_NO_PAD.encode("2025-10-01")), None);
+        assert_eq!(
+            Cursor::decode(&URL_SAFE_NO_PAD.encode("yesterday,not-a-uuid")),
+            None
+        );
+    }
+}
@@ -0,0 +1,318 @@
+/*
+ Best-effort reading of forwarded e-receipts (ride hailing, marketplaces, restaurants) into
+ the parts of an expense: who was paid, how much and when. Receipts have no common format,
+ so every field is optional and the result is only a draft for a member to check.
+*/
+use std::sync::LazyLock;
+
+use chrono::{DateTime, NaiveDate};
+use regex::Regex;
+use rust_decimal::Decimal;
+
+use crate::utils::parse_price::parse_amount;
+
+const MAX_MERCHANT_LENGTH: usize = 255;
+
+// An amount with an optional currency before or after it, e.g. `Rp 150.000`, `$12.50`, `45,00 EUR`
+static AMOUNT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)(?:(?P<pre>rp\.?|idr|us\$|usd|s\$|sgd|rm|myr|eur|€|jpy|¥|\$)\s*)?(?P<num>\d(?:[\d.,]*\d)?)(?:\s*(?P<post>idr|usd|sgd|myr|eur|jpy)\b)?",
+    )
+    .unwrap()
+});
+
+// Lines naming the total, the most specific ones first
+static TOTAL_LABELS: LazyLock<[Regex; 3]> = LazyLock::new(|| {
+    [
+        Regex::new(r"(?i)\b(grand total|total bayar|total pembayaran|total payment|amount paid|total paid)\b").unwrap(),
+        Regex::new(r"(?i)\btotal\b").unwrap(),
+        Regex::new(r"(?i)\b(jumlah|amount)\b").unwrap(),
+    ]
+});
+
+static SUBTOTAL: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)\bsub[\s-]*total").unwrap());
+
+static MERCHANT_LINE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)^\s*(merchant|merchant name|store|toko|nama toko|restaurant|restoran)\s*:\s*(.+)$",
+    )
+    .unwrap()
+});
+
+// The header block mail clients put above a forwarded message
+static FORWARDED_FROM: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)^\s*(from|dari)\s*:\s*(.+)$").unwrap());
+
+static DATE_LINE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)^\s*(date|tanggal|sent|transaction date|tanggal transaksi)\s*:\s*(.+)$")
+        .unwrap()
+});
+
+static HTML_BREAK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)<br\s*/?>|</(p|div|tr|li|h\d)>").unwrap());
+
+static HTML_TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?s)<[^>]*>").unwrap());
+
+#[derive(Debug, Default, PartialEq)]
+pub struct ParsedReceipt {
+    pub merchant: Option<String>,
+    pub amount: Option<Decimal>,
+    // ISO 4217 code when the amount came with a currency
+    pub currency: Option<String>,
+    pub date: Option<NaiveDate>,
+}
+
+/*
+ `from` is the sender of the email, which for a forwarded receipt is the member who forwarded
+ it, so the merchant is taken from a `Merchant:` line or the forwarded header first.
+*/
+pub fn parse_receipt(from: &str, subject: &str, body: &str) -> ParsedReceipt {
+    let (amount, currency) = match find_total(body).or_else(|| largest_amount(subject)) {
+        Some((amount, currency)) => (Some(amount), currency),
+        None => (None, None),
+    };
+    ParsedReceipt {
+        merchant: find_merchant(from, body),
+        amount,
+        currency,
+        date: body
+            .lines()
+            .filter_map(|line| DATE_LINE.captures(line))
+            .find_map(|caps| parse_date(&caps[2])),
+    }
+}
+
+// Plain text of an HTML email body, for receipts sent without a text part
+pub fn html_to_text(html: &str) -> String {
+    let text = HTML_BREAK.replace_all(html, "\n");
+    let text = HTML_TAG.replace_all(&text, " ");
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'");
+    text.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/*
+ The amount on the last line carrying the most specific total label; tabular receipts put
+ it on the line below the label
+*/
+fn find_total(body: &str) -> Option<(Decimal, Option<String>)> {
+    let lines: Vec<&str> = body.lines().collect();
+    for label in TOTAL_LABELS.iter() {
+        let found = lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| label.is_match(line) && !SUBTOTAL.is_match(line))
+            .filter_map(|(index, line)| {
+                last_amount(&label.replace_all(line, ""))
+                    .or_else(|| lines.get(index + 1).and_then(|next| last_amount(next)))
+            })
+            .next_back();
+        if found.is_some() {
+            return found;
+        }
+    }
+    None
+}
+
+fn amounts(text: &str) -> impl Iterator<Item = (Decimal, Option<String>)> + '_ {
+    AMOUNT.captures_iter(text).filter_map(|caps| {
+        let amount = parse_amount(&caps["num"])?;
+        let currency = caps
+            .name("pre")
+            .or_else(|| caps.name("post"))
+            .and_then(|marker| currency_code(marker.as_str()));
+        Some((amount, currency))
+    })
+}
+
+fn last_amount(text: &str) -> Option<(Decimal, Option<String>)> {
+    amounts(text).last()
+}
+
+// Only amounts with a currency, a subject like "Order #12345" has no price in it
+fn largest_amount(text: &str) -> Option<(Decimal, Option<String>)> {
+    amounts(text)
+        .filter(|(_, currency)| currency.is_some())
+        .max_by_key(|(amount, _)| *amount)
+}
+
+fn currency_code(marker: &str) -> Option<String> {
+    let code = match marker.to_lowercase().trim_end_matches('.') {
+        "rp" | "idr" => "IDR",
+        "$" | "us$" | "usd" => "USD",
+        "s$" | "sgd" => "SGD",
+        "rm" | "myr" => "MYR",
+        "€" | "eur" => "EUR",
+        "¥" | "jpy" => "JPY",
+        _ => return None,
+    };
+    Some(code.to_string())
+}
+
+fn find_merchant(from: &str, body: &str) -> Option<String> {
+    let lines = || body.lines();
+    lines()
+        .find_map(|line| {
+            MERCHANT_LINE
+                .captures(line)
+                .map(|caps| caps[2].trim().to_string())
+        })
+        .or_else(|| {
+            lines()
+                .filter_map(|line| FORWARDED_FROM.captures(line))
+                .find_map(|caps| sender_name(&caps[2]))
+        })
+        .or_else(|| sender_name(from))
+        .filter(|merchant| !merchant.is_empty())
+        .map(|merchant| merchant.chars().take(MAX_MERCHANT_LENGTH).collect())
+}
+
+/*
+ The display name of `"Grab" <no-reply@grab.com>`, or the name of the domain when there is
+ none: `no-reply@mail.tokopedia.co.id` is Tokopedia
+*/
+fn sender_name(sender: &str) -> Option<String> {
+    let sender = sender.trim();
+    if let Some((name, _)) = sender.split_once('<') {
+        let name = name.trim().trim_matches('"').trim();
+        if !name.is_empty() {
+            return Some(name.to_string());
+        }
+    }
+    let address = sender
+        .trim_start_matches(|c| c != '<')
+        .trim_matches(['<', '>']);
+    let address = if address.is_empty() { sender } else { address };
+    let (_, domain) = address.split_once('@')?;
+    let name = domain
+        .trim()
+        .to_lowercase()
+        .split('.')
+        .rfind(|label| {
+            !matches!(
+                *label,
+                "com" | "co" | "id" | "net" | "org" | "io" | "sg" | "my" | "app" | "mail" | "email"
+            )
+        })?
+        .to_string();
+    let mut chars = name.chars();
+    let first = chars.next()?;
+    Some(first.to_uppercase().chain(chars).collect())
+}
+
+fn parse_date(text: &str) -> Option<NaiveDate> {
+    let text = text.trim();
+    if let Ok(date) = DateTime::parse_from_rfc2822(text) {
+        return Some(date.date_naive());
+    }
+    // Gmail's forwarded header: "Mon, Nov 3, 2025 at 10:15 AM"
+    let text = text.split(" at ").next().unwrap_or(text).trim();
+    let text = match text.split_once(", ") {
+        Some((weekday, rest)) if weekday.len() <= 9 && weekday.chars().all(char::is_alphabetic) => {
+            rest
+        }
+        _ => text,
+    };
+    [
+        "%Y-%m-%d",
+        "%d/%m/%Y",
+        "%d-%m-%Y",
+        "%d %b %Y",
+        "%d %B %Y",
+        "%b %d, %Y",
+        "%B %d, %Y",
+    ]
+    .iter()
+    .find_map(|format| NaiveDate::parse_and_remainder(text, format).ok())
+    .map(|(date, _)| date)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_parse_forwarded_receipt() {
+        let body = "---------- Forwarded message ---------\n\
+            From: Grab <no-reply@grab.com>\n\
+            Date: Mon, Nov 3, 2025 at 10:15 AM\n\
+            Subject: Your Grab E-Receipt\n\
+            \n\
+            Subtotal Rp 48.000\n\
+            Discount -Rp 5.000\n\
+            Total Paid Rp 43.000\n";
+        assert_eq!(
+            parse_receipt("Budi <budi@example.com>", "Fwd: Your Grab E-Receipt", body),
+            ParsedReceipt {
+                merchant: Some("Grab".to_string()),
+                amount: Some(dec!(43000)),
+                currency: Some("IDR".to_string()),
+                date: Some(date(2025, 11, 3)),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_direct_receipt() {
+        let body = "Merchant: Kopi Kenangan\n\
+            Tanggal: 03/11/2025 08:12\n\
+            Total\n\
+            $12.50\n";
+        let receipt = parse_receipt("\"Receipts\" <receipts@pos.example>", "", body);
+        assert_eq!(receipt.merchant.as_deref(), Some("Kopi Kenangan"));
+        assert_eq!(receipt.amount, Some(dec!(12.50)));
+        assert_eq!(receipt.currency.as_deref(), Some("USD"));
+        assert_eq!(receipt.date, Some(date(2025, 11, 3)));
+    }
+
+    #[test]
+    fn test_parse_receipt_falls_back_to_sender_and_subject() {
+        let receipt = parse_receipt(
+            "no-reply@mail.tokopedia.co.id",
+            "Pembayaran Rp 150.000 untuk pesanan #12345 berhasil",
+            "Terima kasih",
+        );
+        assert_eq!(receipt.merchant.as_deref(), Some("Tokopedia"));
+        assert_eq!(receipt.amount, Some(dec!(150000)));
+        assert_eq!(receipt.date, None);
+
+        assert_eq!(
+            parse_receipt("", "Order #12345", ""),
+            ParsedReceipt::default()
+        );
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(
+            parse_date("Mon, 3 Nov 2025 10:15:00 +0700"),
+            Some(date(2025, 11, 3))
+        );
+        assert_eq!(parse_date("2025-11-03"), Some(date(2025, 11, 3)));
+        assert_eq!(
+            parse_date("3 November 2025, 10:15"),
+            Some(date(2025, 11, 3))
+        );
+        assert_eq!(parse_date("yesterday"), None);
+    }
+
+    #[test]
+    fn test_html_to_text() {
+        let html = "<table><tr><td>Total</td><td>Rp&nbsp;43.000</td></tr></table><p>Thanks &amp; see you</p>";
+        assert_eq!(html_to_text(html), "Total Rp 43.000\nThanks & see you");
+    }
+}
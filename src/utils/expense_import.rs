@@ -0,0 +1,255 @@
+/*
+Spreadsheet import for expense entries.
+
+The first row must be a header. Columns are matched case-insensitively:
+- product: product | name | item | nama
+- price: price | amount | harga | jumlah
+- category (optional): category | kategori
+- date (optional): date | created_at | tanggal
+
+Dates accept YYYY-MM-DD, DD/MM/YYYY, DD-MM-YYYY or RFC 3339.
+*/
+use std::io::Cursor;
+
+use anyhow::Result;
+use calamine::{Data, DataType, Reader, Xlsx, open_workbook_from_rs};
+use chrono::{DateTime, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::utils::parse_price::parse_price;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportRow {
+    // 1-based row number in the sheet, the header being row 1
+    pub row: usize,
+    pub product: String,
+    pub price: Decimal,
+    pub category: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ImportRowError {
+    pub row: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Default)]
+struct ColumnMap {
+    product: Option<usize>,
+    price: Option<usize>,
+    category: Option<usize>,
+    date: Option<usize>,
+}
+
+impl ColumnMap {
+    fn from_header(header: &[String]) -> Result<Self> {
+        let mut map = ColumnMap::default();
+        for (index, name) in header.iter().enumerate() {
+            match name.trim().to_lowercase().as_str() {
+                "product" | "name" | "item" | "nama" => map.product = Some(index),
+                "price" | "amount" | "harga" | "jumlah" => map.price = Some(index),
+                "category" | "kategori" => map.category = Some(index),
+                "date" | "created_at" | "tanggal" => map.date = Some(index),
+                _ => {}
+            }
+        }
+
+        if map.product.is_none() || map.price.is_none() {
+            return Err(anyhow::anyhow!(
+                "Header must contain a product and a price column"
+            ));
+        }
+        Ok(map)
+    }
+}
+
+pub fn read_csv(bytes: &[u8]) -> Result<Vec<Vec<String>>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(bytes);
+
+    let mut table = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| anyhow::anyhow!("Invalid CSV: {}", e))?;
+        table.push(record.iter().map(|cell| cell.to_string()).collect());
+    }
+    Ok(table)
+}
+
+pub fn read_xlsx(bytes: &[u8]) -> Result<Vec<Vec<String>>> {
+    let mut workbook: Xlsx<_> = open_workbook_from_rs(Cursor::new(bytes))
+        .map_err(|e| anyhow::anyhow!("Invalid XLSX: {}", e))?;
+    let range = workbook
+        .worksheet_range_at(0)
+        .ok_or_else(|| anyhow::anyhow!("XLSX file has no worksheet"))?
+        .map_err(|e| anyhow::anyhow!("Invalid XLSX: {}", e))?;
+
+    let table = range
+        .rows()
+        .map(|row| {
+            row.iter()
+                .map(|cell| match cell {
+                    Data::DateTime(_) | Data::DateTimeIso(_) => cell
+                        .as_datetime()
+                        .map(|dt| dt.format("%Y-%m-%d").to_string())
+                        .unwrap_or_else(|| cell.to_string()),
+                    _ => cell.to_string(),
+                })
+                .collect()
+        })
+        .collect();
+    Ok(table)
+}
+
+fn parse_date(input: &str) -> Result<DateTime<Utc>> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(input) {
+        return Ok(dt.with_timezone(&Utc));
+    }
+    for format in ["%Y-%m-%d", "%d/%m/%Y", "%d-%m-%Y"] {
+        if let Ok(date) = NaiveDate::parse_from_str(input, format) {
+            return Ok(date.and_hms_opt(0, 0, 0).unwrap().and_utc());
+        }
+    }
+    Err(anyhow::anyhow!("Invalid date: {}", input))
+}
+
+/*
+ Validates every data row, collecting all errors instead of stopping at the first one
+ so a dry run can report the whole file at once.
+*/
+pub fn parse_rows(table: &[Vec<String>]) -> Result<(Vec<ImportRow>, Vec<ImportRowError>)> {
+    let Some((header, rows)) = table.split_first() else {
+        return Err(anyhow::anyhow!("File is empty"));
+    };
+    let columns = ColumnMap::from_header(header)?;
+
+    let cell = |row: &Vec<String>, index: Option<usize>| -> Option<String> {
+        index
+            .and_then(|i| row.get(i))
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+
+    let mut valid = Vec::new();
+    let mut errors = Vec::new();
+
+    for (offset, row) in rows.iter().enumerate() {
+        let row_number = offset + 2;
+        if row.iter().all(|value| value.trim().is_empty()) {
+            continue;
+        }
+
+        let Some(product) = cell(row, columns.product) else {
+            errors.push(ImportRowError {
+                row: row_number,
+                message: "Missing product".to_string(),
+            });
+            continue;
+        };
+
+        let price = match cell(row, columns.price).map(|value| parse_price(&value)) {
+            Some(Ok(price)) => price,
+            Some(Err(e)) => {
+                errors.push(ImportRowError {
+                    row: row_number,
+                    message: e.to_string(),
+                });
+                continue;
+            }
+            None => {
+                errors.push(ImportRowError {
+                    row: row_number,
+                    message: "Missing price".to_string(),
+                });
+                continue;
+            }
+        };
+
+        let created_at = match cell(row, columns.date).map(|value| parse_date(&value)) {
+            Some(Ok(date)) => Some(date),
+            Some(Err(e)) => {
+                errors.push(ImportRowError {
+                    row: row_number,
+                    message: e.to_string(),
+                });
+                continue;
+            }
+            None => None,
+        };
+
+        valid.push(ImportRow {
+            row: row_number,
+            product,
+            price,
+            category: cell(row, columns.category),
+            created_at,
+        });
+    }
+
+    Ok((valid, errors))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parse_csv_rows() {
+        let csv = "Date,Product,Price,Category
+2025-09-01,Nasi Padang,\"10.000\",Makanan
+02/09/2025,Warteg,15000,
+,,,
+2025-09-03,,5000,Makanan
+2025-09-04,Kopi,abc,Minuman
+bad-date,Teh,3000,Minuman
+";
+        let table = read_csv(csv.as_bytes()).unwrap();
+        let (rows, errors) = parse_rows(&table).unwrap();
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].row, 2);
+        assert_eq!(rows[0].product, "Nasi Padang");
+        assert_eq!(rows[0].price, dec!(10000));
+        assert_eq!(rows[0].category.as_deref(), Some("Makanan"));
+        assert_eq!(
+            rows[0].created_at.unwrap().date_naive(),
+            NaiveDate::from_ymd_opt(2025, 9, 1).unwrap()
+        );
+        assert_eq!(rows[1].category, None);
+        assert_eq!(
+            rows[1].created_at.unwrap().date_naive(),
+            NaiveDate::from_ymd_opt(2025, 9, 2).unwrap()
+        );
+
+        let error_rows: Vec<usize> = errors.iter().map(|e| e.row).collect();
+        assert_eq!(error_rows, vec![5, 6, 7]);
+    }
+
+    #[test]
+    fn test_parse_rows_requires_header_columns() {
+        let table = read_csv("Date,Category\n2025-09-01,Makanan\n".as_bytes()).unwrap();
+        assert!(parse_rows(&table).is_err());
+    }
+
+    #[test]
+    fn test_parse_rows_empty_file() {
+        assert!(parse_rows(&[]).is_err());
+    }
+
+    #[test]
+    fn test_parse_rows_indonesian_header() {
+        let table = read_csv("nama,harga,kategori\nBakso,Rp. 20.000,Makanan\n".as_bytes()).unwrap();
+        let (rows, errors) = parse_rows(&table).unwrap();
+
+        assert!(errors.is_empty());
+        assert_eq!(rows[0].product, "Bakso");
+        assert_eq!(rows[0].price, dec!(20000));
+        assert_eq!(rows[0].created_at, None);
+    }
+}
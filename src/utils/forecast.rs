@@ -0,0 +1,287 @@
+/*
+Spending forecasts for the running billing cycle. Daily spending of up to `LOOKBACK_DAYS`
+days before today is smoothed exponentially, recent days weighing most, and the smoothed
+daily amount is assumed for every day left in the cycle. The bounds come from how far single
+days strayed from the smoothed amount before them, widening with the days left.
+*/
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use rust_decimal::{Decimal, prelude::ToPrimitive};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::DatabaseError;
+use crate::repos::{
+    exchange_rate::ExchangeRateRepo, expense_entry::ExpenseEntryRepo, expense_group::ExpenseGroup,
+};
+use crate::utils::period::BillingPeriod;
+
+// Days of spending before today the run rate is taken from
+pub const LOOKBACK_DAYS: i64 = 90;
+// Share of the smoothed amount each new day makes up
+const SMOOTHING: f64 = 0.1;
+// Share of outcomes expected between the bounds, and its two-sided z-score
+pub const CONFIDENCE: f64 = 0.8;
+const CONFIDENCE_Z: f64 = 1.2816;
+
+// Smoothed daily spending and the typical error of a day's amount next to it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RunRate {
+    pub daily: f64,
+    pub deviation: f64,
+}
+
+impl RunRate {
+    /*
+     Days oldest first, days without spending included as zero. Smoothing starts from the
+     mean so a single large day at the start does not linger. None without any days.
+    */
+    pub fn smoothed(days: &[f64]) -> Option<Self> {
+        if days.is_empty() {
+            return None;
+        }
+        let mut level = days.iter().sum::<f64>() / days.len() as f64;
+        let mut squared_errors = 0.0;
+        for amount in days {
+            let error = amount - level;
+            squared_errors += error * error;
+            level += SMOOTHING * error;
+        }
+        Some(Self {
+            daily: level,
+            deviation: (squared_errors / days.len() as f64).sqrt(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Projection {
+    /// Spent so far in the cycle, today included
+    pub spent: Decimal,
+    /// Smoothed daily spending assumed for the rest of the cycle
+    pub daily_rate: Decimal,
+    /// Spent by the end of the cycle if the daily rate holds
+    pub projected_total: Decimal,
+    /// `confidence` of outcomes are expected between the bounds, never below `spent`
+    pub lower_bound: Decimal,
+    pub upper_bound: Decimal,
+}
+
+impl Projection {
+    // `history` is the daily spending before today, oldest first
+    pub fn new(spent: Decimal, history: &[Decimal], days_remaining: i64) -> Self {
+        let days: Vec<f64> = history
+            .iter()
+            .map(|amount| amount.to_f64().unwrap_or_default())
+            .collect();
+        // Nothing to go by yet, the cycle ends where it is
+        let Some(rate) = RunRate::smoothed(&days) else {
+            return Self {
+                spent,
+                daily_rate: Decimal::ZERO,
+                projected_total: spent,
+                lower_bound: spent,
+                upper_bound: spent,
+            };
+        };
+        let remaining = days_remaining.max(0) as f64;
+        let expected = rate.daily * remaining;
+        // Days are taken as independent, so the spread grows with the root of the days left
+        let spread = CONFIDENCE_Z * rate.deviation * remaining.sqrt();
+        Self {
+            spent,
+            daily_rate: to_amount(rate.daily),
+            projected_total: spent + to_amount(expected),
+            lower_bound: spent + to_amount((expected - spread).max(0.0)),
+            upper_bound: spent + to_amount(expected + spread),
+        }
+    }
+}
+
+fn to_amount(value: f64) -> Decimal {
+    Decimal::try_from(value).unwrap_or_default().round_dp(2)
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CategoryForecast {
+    /// None for uncategorized entries, subcategories are forecast under their parent
+    pub category_uid: Option<Uuid>,
+    pub category_name: Option<String>,
+    #[serde(flatten)]
+    pub projection: Projection,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct GroupForecast {
+    /// Currency every amount is reported in, the group's currency
+    pub currency: String,
+    /// The group's current billing cycle, as [start, end)
+    pub period_start: NaiveDate,
+    pub period_end: NaiveDate,
+    /// Days of the cycle that have started, today included
+    pub days_elapsed: i64,
+    pub days_in_period: i64,
+    /// Share of outcomes expected between the bounds
+    pub confidence: f64,
+    #[serde(flatten)]
+    pub projection: Projection,
+    /// Largest projected total first, categories with nothing to project are left out
+    pub categories: Vec<CategoryForecast>,
+    /// Currencies without a known exchange rate, their amounts are added unconverted
+    pub unconverted_currencies: Vec<String>,
+}
+
+// Spending of one category, or of the whole group, in the group's currency
+#[derive(Default)]
+struct Series {
+    name: Option<String>,
+    spent: Decimal,
+    history: Vec<Decimal>,
+}
+
+/*
+ Forecast of the group's cycle containing `today`, in total and per category. Entries assigned
+ to an envelope are left out like in the regular cycle, and history only goes back to the day
+ the group was created.
+*/
+pub async fn group_forecast(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    group: &ExpenseGroup,
+    today: NaiveDate,
+) -> Result<GroupForecast, DatabaseError> {
+    let period = BillingPeriod::containing(today, group.start_over_date);
+    let history_start =
+        (today - Duration::days(LOOKBACK_DAYS)).max(group.created_at.date_naive().min(today));
+    let history_days = (today - history_start).num_days() as usize;
+    let days_elapsed = (today - period.start).num_days() + 1;
+
+    let rates = ExchangeRateRepo::rate_table(tx).await?;
+    let rows = ExpenseEntryRepo::totals_by_day_and_category(
+        tx,
+        group.uid,
+        midnight(history_start.min(period.start)),
+        midnight(today + Duration::days(1)),
+    )
+    .await?;
+
+    let mut unconverted_currencies: Vec<String> = Vec::new();
+    let mut total = Series {
+        history: vec![Decimal::ZERO; history_days],
+        ..Default::default()
+    };
+    let mut categories: HashMap<Option<Uuid>, Series> = HashMap::new();
+    for (day, category_uid, category_name, currency, amount) in rows {
+        let amount = match rates.convert(amount, &currency, &group.currency) {
+            Some(converted) => converted,
+            None => {
+                if !unconverted_currencies.contains(&currency) {
+                    unconverted_currencies.push(currency);
+                }
+                amount
+            }
+        };
+        let category = categories.entry(category_uid).or_insert_with(|| Series {
+            name: category_name,
+            spent: Decimal::ZERO,
+            history: vec![Decimal::ZERO; history_days],
+        });
+        for series in [&mut total, category] {
+            if day >= period.start {
+                series.spent += amount;
+            }
+            if day >= history_start && day < today {
+                series.history[(day - history_start).num_days() as usize] += amount;
+            }
+        }
+    }
+
+    let days_remaining = period.num_days() - days_elapsed;
+    let mut categories: Vec<CategoryForecast> = categories
+        .into_iter()
+        .map(|(category_uid, series)| CategoryForecast {
+            category_uid,
+            category_name: series.name,
+            projection: Projection::new(series.spent, &series.history, days_remaining),
+        })
+        .filter(|category| !category.projection.projected_total.is_zero())
+        .collect();
+    categories.sort_by(|a, b| {
+        b.projection
+            .projected_total
+            .cmp(&a.projection.projected_total)
+    });
+
+    Ok(GroupForecast {
+        currency: group.currency.clone(),
+        period_start: period.start,
+        period_end: period.end,
+        days_elapsed,
+        days_in_period: period.num_days(),
+        confidence: CONFIDENCE,
+        projection: Projection::new(total.spent, &total.history, days_remaining),
+        categories,
+        unconverted_currencies,
+    })
+}
+
+fn midnight(date: NaiveDate) -> DateTime<Utc> {
+    date.and_hms_opt(0, 0, 0).unwrap().and_utc()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_run_rate() {
+        let steady = RunRate::smoothed(&[100.0; 10]).unwrap();
+        assert_eq!(steady.daily, 100.0);
+        assert_eq!(steady.deviation, 0.0);
+
+        // Recent days weigh more than older ones
+        let mut days = vec![100.0; 20];
+        days.extend([200.0; 10]);
+        let rate = RunRate::smoothed(&days).unwrap();
+        let mean = days.iter().sum::<f64>() / days.len() as f64;
+        assert!(rate.daily > mean && rate.daily < 200.0);
+        assert!(rate.deviation > 0.0);
+
+        assert_eq!(RunRate::smoothed(&[]), None);
+    }
+
+    #[test]
+    fn test_projection() {
+        let history = vec![dec!(50000); 30];
+        let projection = Projection::new(dec!(200000), &history, 20);
+        assert_eq!(projection.daily_rate, dec!(50000));
+        assert_eq!(projection.projected_total, dec!(1200000));
+        assert_eq!(projection.lower_bound, dec!(1200000));
+        assert_eq!(projection.upper_bound, dec!(1200000));
+
+        // Nothing left of the cycle
+        let projection = Projection::new(dec!(200000), &history, 0);
+        assert_eq!(projection.projected_total, dec!(200000));
+    }
+
+    #[test]
+    fn test_projection_bounds() {
+        let history: Vec<Decimal> = (0..30)
+            .map(|day| if day % 2 == 0 { dec!(0) } else { dec!(100000) })
+            .collect();
+        let projection = Projection::new(dec!(100000), &history, 10);
+        assert!(projection.lower_bound < projection.projected_total);
+        assert!(projection.upper_bound > projection.projected_total);
+        assert!(projection.lower_bound >= projection.spent);
+    }
+
+    #[test]
+    fn test_projection_without_history() {
+        let projection = Projection::new(dec!(75000), &[], 25);
+        assert_eq!(projection.daily_rate, Decimal::ZERO);
+        assert_eq!(projection.projected_total, dec!(75000));
+        assert_eq!(projection.upper_bound, dec!(75000));
+    }
+}
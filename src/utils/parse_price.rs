@@ -10,9 +10,14 @@ Rp. 1.234.567
 Rp.1.234.567
 Rp 5000
 */
+use std::str::FromStr;
+
 use anyhow::Result;
+use rust_decimal::{Decimal, RoundingStrategy};
+
+use crate::utils::currency::Currency;
 
-pub fn parse_price(input: &str) -> Result<f64> {
+pub fn parse_price(input: &str) -> Result<Decimal> {
     let input = input.trim();
     let input = input.replace('.', "").replace(',', "");
     // Remove "Rp" prefix if exists
@@ -22,20 +27,45 @@ pub fn parse_price(input: &str) -> Result<f64> {
         input
     };
     // Remove dots and commas
-    // Parse to f64
-    let price: f64 = input
-        .parse()
+    // Parse to Decimal
+    let price = Decimal::from_str(&input)
         .map_err(|_| anyhow::anyhow!("Failed to parse price: {}", input))?;
-    if price < 0.0 {
+    if price.is_sign_negative() && !price.is_zero() {
         return Err(anyhow::anyhow!("Price cannot be negative: {}", input));
     }
     Ok(price)
 }
 
+/*
+ Digits grouped with `.` or `,`, in either convention. The last separator is the decimal one
+ when one or two digits follow it, the others group thousands: 150.000 and 150,000 are both
+ 150000, 1.234,56 and 1,234.56 are 1234.56
+*/
+pub fn parse_amount(num: &str) -> Option<Decimal> {
+    let num = num.trim();
+    if num.is_empty() || !num.chars().all(|c| c.is_ascii_digit() || ".,".contains(c)) {
+        return None;
+    }
+    let (whole, fraction) = match num.rfind(['.', ',']) {
+        Some(index) if (1..=2).contains(&(num.len() - index - 1)) => {
+            (&num[..index], Some(&num[index + 1..]))
+        }
+        _ => (num, None),
+    };
+    let mut digits: String = whole.chars().filter(char::is_ascii_digit).collect();
+    if let Some(fraction) = fraction {
+        digits.push('.');
+        digits.push_str(fraction);
+    }
+    Decimal::from_str(&digits).ok()
+}
+
 // Format price to string with dot as thousand separator
 // 10000 -> 10.000
-pub fn format_price(price: f64) -> String {
-    let mut price_str = format!("{:.0}", price);
+pub fn format_price(price: Decimal) -> String {
+    let mut price_str = price
+        .round_dp_with_strategy(0, RoundingStrategy::MidpointAwayFromZero)
+        .to_string();
     let mut result = String::new();
     while price_str.len() > 3 {
         let len = price_str.len();
@@ -49,26 +79,35 @@ pub fn format_price(price: f64) -> String {
     result
 }
 
+// Format price with the currency symbol, e.g. ("IDR", 10000) -> Rp. 10.000
+pub fn format_price_in(price: Decimal, currency: &str) -> String {
+    match Currency::find(currency) {
+        Some(currency) => currency.format(price),
+        None => format!("{} {}", currency, format_price(price)),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rust_decimal_macros::dec;
 
     #[test]
     fn test_parse_price() {
         let cases = vec![
-            ("10000", 10000.0),
-            ("10.000", 10000.0),
-            ("Rp 10.000", 10000.0),
-            ("Rp10.000", 10000.0),
-            ("Rp 10,000", 10000.0),
-            ("Rp10,000", 10000.0),
-            ("  Rp  1.234.567  ", 1234567.0),
-            ("0", 0.0),
-            ("  5000  ", 5000.0),
-            ("Rp. 5000", 5000.0),
-            ("Rp.1.234.567", 1234567.0),
-            ("Rp. 1,234,567", 1234567.0),
-            ("Rp 1,234,567", 1234567.0),
+            ("10000", dec!(10000)),
+            ("10.000", dec!(10000)),
+            ("Rp 10.000", dec!(10000)),
+            ("Rp10.000", dec!(10000)),
+            ("Rp 10,000", dec!(10000)),
+            ("Rp10,000", dec!(10000)),
+            ("  Rp  1.234.567  ", dec!(1234567)),
+            ("0", dec!(0)),
+            ("  5000  ", dec!(5000)),
+            ("Rp. 5000", dec!(5000)),
+            ("Rp.1.234.567", dec!(1234567)),
+            ("Rp. 1,234,567", dec!(1234567)),
+            ("Rp 1,234,567", dec!(1234567)),
         ];
         for (input, expected) in cases {
             let result = parse_price(input).unwrap();
@@ -84,18 +123,38 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_amount() {
+        assert_eq!(parse_amount("150.000"), Some(dec!(150000)));
+        assert_eq!(parse_amount("150,000"), Some(dec!(150000)));
+        assert_eq!(parse_amount("1.234,56"), Some(dec!(1234.56)));
+        assert_eq!(parse_amount("1,234.56"), Some(dec!(1234.56)));
+        assert_eq!(parse_amount("12.5"), Some(dec!(12.5)));
+        assert_eq!(parse_amount("7"), Some(dec!(7)));
+        assert_eq!(parse_amount("-7"), None);
+        assert_eq!(parse_amount(""), None);
+    }
+
     #[test]
     fn test_format_price() {
         let cases = vec![
-            (10000.0, "10.000"),
-            (1234567.0, "1.234.567"),
-            (0.0, "0"),
-            (5000.0, "5.000"),
-            (100.0, "100"),
+            (dec!(10000), "10.000"),
+            (dec!(1234567), "1.234.567"),
+            (dec!(0), "0"),
+            (dec!(5000), "5.000"),
+            (dec!(100), "100"),
+            (dec!(1500.5), "1.501"),
         ];
         for (input, expected) in cases {
             let result = format_price(input);
             assert_eq!(result, expected, "Failed on input: {}", input);
         }
     }
+
+    #[test]
+    fn test_format_price_in() {
+        assert_eq!(format_price_in(dec!(10000), "IDR"), "Rp. 10.000");
+        assert_eq!(format_price_in(dec!(12.5), "USD"), "$12.50");
+        assert_eq!(format_price_in(dec!(10000), "XYZ"), "XYZ 10.000");
+    }
 }
@@ -0,0 +1,225 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+
+/*
+ A billing cycle of an expense group, as [start, end). A cycle starts on the group's
+ `start_over_date` and lasts until the same day next month; days past the end of a
+ month are clamped, so a start over date of 31 means the last day of short months.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BillingPeriod {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    start_over_date: i16,
+}
+
+impl BillingPeriod {
+    // The cycle that starts in the given month
+    pub fn for_month(year: i32, month: u32, start_over_date: i16) -> Self {
+        let (next_year, next_month) = next_month(year, month);
+        Self {
+            start: cycle_start(year, month, start_over_date),
+            end: cycle_start(next_year, next_month, start_over_date),
+            start_over_date,
+        }
+    }
+
+    pub fn containing(date: NaiveDate, start_over_date: i16) -> Self {
+        let (year, month) = if date >= cycle_start(date.year(), date.month(), start_over_date) {
+            (date.year(), date.month())
+        } else {
+            previous_month(date.year(), date.month())
+        };
+        Self::for_month(year, month, start_over_date)
+    }
+
+    pub fn current(start_over_date: i16) -> Self {
+        Self::containing(Utc::now().date_naive(), start_over_date)
+    }
+
+    // The cycle `n` periods before this one, `nth_previous(0)` being this one
+    pub fn nth_previous(&self, n: u32) -> Self {
+        let (mut year, mut month) = (self.start.year(), self.start.month());
+        for _ in 0..n {
+            (year, month) = previous_month(year, month);
+        }
+        Self::for_month(year, month, self.start_over_date)
+    }
+
+    pub fn previous(&self) -> Self {
+        self.nth_previous(1)
+    }
+
+    pub fn next(&self) -> Self {
+        let (year, month) = next_month(self.start.year(), self.start.month());
+        Self::for_month(year, month, self.start_over_date)
+    }
+
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.start <= date && date < self.end
+    }
+
+    pub fn num_days(&self) -> i64 {
+        (self.end - self.start).num_days()
+    }
+
+    pub fn start_utc(&self) -> DateTime<Utc> {
+        self.start.and_hms_opt(0, 0, 0).unwrap().and_utc()
+    }
+
+    pub fn end_utc(&self) -> DateTime<Utc> {
+        self.end.and_hms_opt(0, 0, 0).unwrap().and_utc()
+    }
+}
+
+/*
+ The quarter or year of cycles `date` is in, as [start, end), `months` being 3 or 12.
+ Quarters are the three cycles starting in January, April, July and October, years the
+ twelve starting in January.
+*/
+pub fn cycles_containing(
+    date: NaiveDate,
+    start_over_date: i16,
+    months: u32,
+) -> (NaiveDate, NaiveDate) {
+    let cycle = BillingPeriod::containing(date, start_over_date);
+    let year = cycle.start.year();
+    let first = (cycle.start.month() - 1) / months * months + 1;
+    let (mut end_year, mut end_month) = (year, first);
+    for _ in 0..months {
+        (end_year, end_month) = next_month(end_year, end_month);
+    }
+    (
+        cycle_start(year, first, start_over_date),
+        cycle_start(end_year, end_month, start_over_date),
+    )
+}
+
+// First day of the week `date` is in, weeks starting on the ISO weekday `week_start`
+pub fn week_start_of(date: NaiveDate, week_start: i16) -> NaiveDate {
+    let offset = (date.weekday().number_from_monday() as i64 - week_start as i64).rem_euclid(7);
+    date - Duration::days(offset)
+}
+
+fn cycle_start(year: i32, month: u32, start_over_date: i16) -> NaiveDate {
+    let day = start_over_date.clamp(1, 31) as u32;
+    (1..=day)
+        .rev()
+        .find_map(|day| NaiveDate::from_ymd_opt(year, month, day))
+        .unwrap()
+}
+
+fn previous_month(year: i32, month: u32) -> (i32, u32) {
+    if month == 1 {
+        (year - 1, 12)
+    } else {
+        (year, month - 1)
+    }
+}
+
+fn next_month(year: i32, month: u32) -> (i32, u32) {
+    if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_containing() {
+        let period = BillingPeriod::containing(date(2025, 3, 25), 25);
+        assert_eq!(
+            (period.start, period.end),
+            (date(2025, 3, 25), date(2025, 4, 25))
+        );
+
+        let period = BillingPeriod::containing(date(2025, 3, 24), 25);
+        assert_eq!(
+            (period.start, period.end),
+            (date(2025, 2, 25), date(2025, 3, 25))
+        );
+
+        let period = BillingPeriod::containing(date(2025, 1, 10), 25);
+        assert_eq!(
+            (period.start, period.end),
+            (date(2024, 12, 25), date(2025, 1, 25))
+        );
+    }
+
+    #[test]
+    fn test_clamps_to_month_length() {
+        let period = BillingPeriod::containing(date(2024, 2, 29), 31);
+        assert_eq!(
+            (period.start, period.end),
+            (date(2024, 2, 29), date(2024, 3, 31))
+        );
+
+        let period = BillingPeriod::containing(date(2025, 2, 27), 31);
+        assert_eq!(
+            (period.start, period.end),
+            (date(2025, 1, 31), date(2025, 2, 28))
+        );
+        assert_eq!(period.num_days(), 28);
+    }
+
+    #[test]
+    fn test_previous_and_next() {
+        let period = BillingPeriod::for_month(2025, 1, 1);
+        assert_eq!(period.previous(), BillingPeriod::for_month(2024, 12, 1));
+        assert_eq!(period.next(), BillingPeriod::for_month(2025, 2, 1));
+        assert_eq!(period.nth_previous(0), period);
+        assert_eq!(
+            period.nth_previous(13),
+            BillingPeriod::for_month(2023, 12, 1)
+        );
+        assert_eq!(period.previous().end, period.start);
+    }
+
+    #[test]
+    fn test_cycles_containing() {
+        assert_eq!(
+            cycles_containing(date(2025, 5, 20), 1, 3),
+            (date(2025, 4, 1), date(2025, 7, 1))
+        );
+        // The cycle starting on 25 December is the last of the year
+        assert_eq!(
+            cycles_containing(date(2025, 1, 10), 25, 3),
+            (date(2024, 10, 25), date(2025, 1, 25))
+        );
+        assert_eq!(
+            cycles_containing(date(2025, 1, 10), 25, 12),
+            (date(2024, 1, 25), date(2025, 1, 25))
+        );
+        assert_eq!(
+            cycles_containing(date(2025, 12, 31), 1, 12),
+            (date(2025, 1, 1), date(2026, 1, 1))
+        );
+    }
+
+    #[test]
+    fn test_week_start_of() {
+        // 13 August 2025 is a Wednesday
+        assert_eq!(week_start_of(date(2025, 8, 13), 1), date(2025, 8, 11));
+        assert_eq!(week_start_of(date(2025, 8, 13), 7), date(2025, 8, 10));
+        assert_eq!(week_start_of(date(2025, 8, 17), 7), date(2025, 8, 17));
+    }
+
+    #[test]
+    fn test_contains_is_half_open() {
+        let period = BillingPeriod::for_month(2025, 6, 15);
+        assert!(period.contains(date(2025, 6, 15)));
+        assert!(period.contains(date(2025, 7, 14)));
+        assert!(!period.contains(date(2025, 7, 15)));
+        assert_eq!(
+            period.end_utc(),
+            date(2025, 7, 15).and_hms_opt(0, 0, 0).unwrap().and_utc()
+        );
+    }
+}
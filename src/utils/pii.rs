@@ -0,0 +1,172 @@
+/*
+ Application-level encryption of personal data: user and invite emails, the platform user ids
+ of linked chat members and the chat ids of bound chats. Values are sealed with AES-256-GCM
+ under PII_ENCRYPTION_KEY and stored as `enc:v1:<base64 of nonce and ciphertext>` in their
+ usual column. Plaintext written before the key was set reads back as is until the
+ `encrypt_pii` binary rewrites it.
+
+ The random nonce makes equal values encrypt differently, so lookups go through a keyed
+ HMAC-SHA256 of the value stored next to it (`email_hash`, `p_user_id_hash`, `p_uid_hash`)
+ instead.
+*/
+use std::sync::OnceLock;
+
+use aes_gcm::{
+    Aes256Gcm, Key, Nonce,
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use thiserror::Error;
+
+// Marks encrypted values, anything else is plaintext
+pub const PREFIX: &str = "enc:v1:";
+const NONCE_LEN: usize = 12;
+// Lookup hashes get their own key, derived so there is still a single secret to manage
+const HASH_KEY_CONTEXT: &[u8] = b"expense-tracker pii lookup hash";
+
+static CIPHER: OnceLock<PiiCipher> = OnceLock::new();
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug, Error)]
+pub enum PiiError {
+    #[error("encrypted value cannot be read without PII_ENCRYPTION_KEY")]
+    MissingKey,
+    #[error("encrypted value is malformed or was sealed with another key")]
+    Invalid,
+}
+
+pub struct PiiCipher {
+    aead: Aes256Gcm,
+    hash_key: Vec<u8>,
+}
+
+impl PiiCipher {
+    pub fn new(key: &[u8; 32]) -> Self {
+        let mut mac =
+            <HmacSha256 as Mac>::new_from_slice(key).expect("HMAC accepts keys of any length");
+        mac.update(HASH_KEY_CONTEXT);
+        Self {
+            aead: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key)),
+            hash_key: mac.finalize().into_bytes().to_vec(),
+        }
+    }
+
+    pub fn encrypt(&self, value: &str) -> String {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        // AES-GCM only refuses plaintexts of more than 64 GiB
+        let ciphertext = self
+            .aead
+            .encrypt(&nonce, value.as_bytes())
+            .expect("PII value too long to encrypt");
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        format!("{}{}", PREFIX, STANDARD.encode(sealed))
+    }
+
+    // Plaintext is returned as is
+    pub fn decrypt(&self, value: &str) -> Result<String, PiiError> {
+        let Some(encoded) = value.strip_prefix(PREFIX) else {
+            return Ok(value.to_string());
+        };
+        let sealed = STANDARD.decode(encoded).map_err(|_| PiiError::Invalid)?;
+        if sealed.len() < NONCE_LEN {
+            return Err(PiiError::Invalid);
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let plaintext = self
+            .aead
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| PiiError::Invalid)?;
+        String::from_utf8(plaintext).map_err(|_| PiiError::Invalid)
+    }
+
+    // Hex HMAC of the exact value, equal values always hash alike
+    pub fn lookup_hash(&self, value: &str) -> String {
+        let mut mac = <HmacSha256 as Mac>::new_from_slice(&self.hash_key)
+            .expect("HMAC accepts keys of any length");
+        mac.update(value.as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+}
+
+// Set once at startup from PII_ENCRYPTION_KEY, values are stored in plaintext otherwise
+pub fn install(cipher: PiiCipher) {
+    if CIPHER.set(cipher).is_err() {
+        tracing::warn!("PII cipher already installed, keeping the first one");
+    }
+}
+
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(PREFIX)
+}
+
+pub fn encrypt(value: &str) -> String {
+    match CIPHER.get() {
+        Some(cipher) => cipher.encrypt(value),
+        None => value.to_string(),
+    }
+}
+
+pub fn decrypt(value: &str) -> Result<String, PiiError> {
+    if !is_encrypted(value) {
+        return Ok(value.to_string());
+    }
+    CIPHER.get().ok_or(PiiError::MissingKey)?.decrypt(value)
+}
+
+// None without a key, lookups then match the plaintext column alone
+pub fn lookup_hash(value: &str) -> Option<String> {
+    CIPHER.get().map(|cipher| cipher.lookup_hash(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let cipher = PiiCipher::new(&[7; 32]);
+        let sealed = cipher.encrypt("budi@example.com");
+        assert!(is_encrypted(&sealed));
+        assert!(!sealed.contains("budi"));
+        // A fresh nonce every time
+        assert_ne!(sealed, cipher.encrypt("budi@example.com"));
+        assert_eq!(cipher.decrypt(&sealed).unwrap(), "budi@example.com");
+        // Rows written before the key was set
+        assert_eq!(
+            cipher.decrypt("siti@example.com").unwrap(),
+            "siti@example.com"
+        );
+    }
+
+    #[test]
+    fn test_decrypt_with_other_key() {
+        let sealed = PiiCipher::new(&[7; 32]).encrypt("budi@example.com");
+        let other = PiiCipher::new(&[8; 32]);
+        assert!(matches!(other.decrypt(&sealed), Err(PiiError::Invalid)));
+        assert!(matches!(
+            other.decrypt("enc:v1:not base64"),
+            Err(PiiError::Invalid)
+        ));
+        assert!(matches!(
+            other.decrypt("enc:v1:AAAA"),
+            Err(PiiError::Invalid)
+        ));
+    }
+
+    #[test]
+    fn test_lookup_hash() {
+        let cipher = PiiCipher::new(&[7; 32]);
+        let hash = cipher.lookup_hash("budi@example.com");
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash, cipher.lookup_hash("budi@example.com"));
+        assert_ne!(hash, cipher.lookup_hash("siti@example.com"));
+        assert_ne!(
+            hash,
+            PiiCipher::new(&[8; 32]).lookup_hash("budi@example.com")
+        );
+    }
+}
@@ -0,0 +1,36 @@
+/*
+ Opaque tokens handed to users (refresh tokens, password reset links). Only the SHA-256
+ hash is stored so a leaked table cannot be replayed.
+*/
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+// Two v4 UUIDs give 244 random bits, hex encoded so it is safe to put in a URL
+pub fn generate_token() -> String {
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_token() {
+        let token = generate_token();
+        assert_eq!(token.len(), 64);
+        assert!(token.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(token, generate_token());
+    }
+
+    #[test]
+    fn test_hash_token() {
+        let hash = hash_token("token");
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash, hash_token("token"));
+        assert_ne!(hash, hash_token("other"));
+    }
+}
@@ -0,0 +1,228 @@
+/*
+ Outbound webhooks: groups register URLs that receive a signed JSON POST when their expenses
+ change or a budget is exceeded. Events are recorded as deliveries and sent by the job queue,
+ which retries failed ones.
+*/
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::error::DatabaseError;
+use crate::jobs::{JobQueue, JobTask};
+use crate::repos::job::DEFAULT_MAX_ATTEMPTS;
+use crate::repos::webhook::{DeliveryStatus, WebhookDeliveryRepo, WebhookRepo};
+
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+pub const EVENT_HEADER: &str = "X-Webhook-Event";
+
+const DELIVERY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+// Enough of the response body to tell what went wrong
+const MAX_ERROR_LEN: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookEvent {
+    ExpenseCreated,
+    ExpenseUpdated,
+    ExpenseDeleted,
+    // A budget went over 100% in its current period
+    BudgetExceeded,
+}
+
+impl WebhookEvent {
+    pub const ALL: [Self; 4] = [
+        Self::ExpenseCreated,
+        Self::ExpenseUpdated,
+        Self::ExpenseDeleted,
+        Self::BudgetExceeded,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ExpenseCreated => "expense.created",
+            Self::ExpenseUpdated => "expense.updated",
+            Self::ExpenseDeleted => "expense.deleted",
+            Self::BudgetExceeded => "budget.exceeded",
+        }
+    }
+
+    pub fn parse(input: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|event| event.as_str() == input.trim())
+    }
+}
+
+/*
+ Queues a delivery of `event` to every active webhook of the group that subscribed to it.
+ Call it in the transaction making the change, so nothing is sent when it rolls back.
+*/
+pub async fn dispatch<T: Serialize>(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    group_uid: Uuid,
+    event: WebhookEvent,
+    data: &T,
+) -> Result<(), DatabaseError> {
+    let webhooks = WebhookRepo::list_subscribed(tx, group_uid, event.as_str()).await?;
+    if webhooks.is_empty() {
+        return Ok(());
+    }
+    let data = serde_json::to_value(data)
+        .map_err(|e| DatabaseError::TransactionError(format!("encoding webhook data: {e}")))?;
+    for webhook in webhooks {
+        let delivery_uid = Uuid::new_v4();
+        let payload = serde_json::json!({
+            "id": delivery_uid,
+            "event": event.as_str(),
+            "group_uid": group_uid,
+            "created_at": Utc::now(),
+            "data": data,
+        });
+        let delivery =
+            WebhookDeliveryRepo::create(tx, webhook.uid, event.as_str(), &payload).await?;
+        JobQueue::enqueue(
+            tx,
+            &JobTask::WebhookDelivery {
+                delivery_uid: delivery.uid,
+            },
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/*
+ Value of `X-Webhook-Signature`, in Stripe's format: `t=<unix time>,v1=<hex>` where v1 is the
+ HMAC-SHA256 of `<unix time>.<body>` keyed with the webhook's secret
+*/
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    format!(
+        "t={},v1={}",
+        timestamp,
+        hex::encode(mac.finalize().into_bytes())
+    )
+}
+
+#[derive(Clone)]
+pub struct WebhookSender {
+    client: reqwest::Client,
+}
+
+impl Default for WebhookSender {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WebhookSender {
+    pub fn new() -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(DELIVERY_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+
+    /*
+     Sends a queued delivery and records the outcome. Fails on anything but a 2xx answer so
+     the job is retried; deliveries of deleted or deactivated webhooks are dropped.
+    */
+    pub async fn deliver(
+        &self,
+        db_pool: &PgPool,
+        delivery_uid: Uuid,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut tx = db_pool.begin().await?;
+        let delivery = match WebhookDeliveryRepo::get(&mut tx, delivery_uid).await {
+            Ok(delivery) => delivery,
+            Err(DatabaseError::NotFound(_)) => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        let webhook = WebhookRepo::get(&mut tx, delivery.webhook_uid).await?;
+        tx.commit().await?;
+        if !webhook.active {
+            return Ok(());
+        }
+
+        let body = serde_json::to_vec(&delivery.payload)?;
+        let signature = sign(&webhook.secret, Utc::now().timestamp(), &body);
+        let result = self
+            .client
+            .post(&webhook.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(SIGNATURE_HEADER, signature)
+            .header(EVENT_HEADER, &delivery.event)
+            .body(body)
+            .send()
+            .await;
+        let (response_status, error) = match result {
+            Ok(response) if response.status().is_success() => {
+                (Some(response.status().as_u16() as i32), None)
+            }
+            Ok(response) => {
+                let status = response.status();
+                let body = response.text().await.unwrap_or_default();
+                let error = format!("{} returned {}: {}", webhook.url, status, body);
+                (
+                    Some(status.as_u16() as i32),
+                    Some(error.chars().take(MAX_ERROR_LEN).collect::<String>()),
+                )
+            }
+            Err(e) => (None, Some(format!("sending to {}: {}", webhook.url, e))),
+        };
+
+        // Each job attempt is one delivery attempt, the last one marks the delivery failed
+        let status = match &error {
+            None => DeliveryStatus::Succeeded,
+            Some(_) if delivery.attempts + 1 >= DEFAULT_MAX_ATTEMPTS => DeliveryStatus::Failed,
+            Some(_) => DeliveryStatus::Pending,
+        };
+        let mut tx = db_pool.begin().await?;
+        WebhookDeliveryRepo::record_attempt(
+            &mut tx,
+            delivery.uid,
+            status,
+            response_status,
+            error.as_deref(),
+        )
+        .await?;
+        tx.commit().await?;
+        match error {
+            Some(error) => Err(error.into()),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_event() {
+        for event in WebhookEvent::ALL {
+            assert_eq!(WebhookEvent::parse(event.as_str()), Some(event));
+        }
+        assert_eq!(WebhookEvent::parse("expense.archived"), None);
+    }
+
+    #[test]
+    fn test_sign() {
+        let signature = sign("secret", 1_700_000_000, b"{}");
+        let (timestamp, v1) = signature.split_once(',').unwrap();
+        assert_eq!(timestamp, "t=1700000000");
+        let v1 = v1.strip_prefix("v1=").unwrap();
+        assert_eq!(v1.len(), 64);
+        assert_eq!(signature, sign("secret", 1_700_000_000, b"{}"));
+        assert_ne!(signature, sign("other", 1_700_000_000, b"{}"));
+        assert_ne!(signature, sign("secret", 1_700_000_001, b"{}"));
+    }
+}
@@ -0,0 +1,142 @@
+/*
+ Every test gets a database of its own, so the suite runs in parallel and leaves no rows behind.
+ The databases are copies of a template that is migrated once per set of migrations, copying is
+ much quicker than running them all again, and are dropped along with their TestDb.
+*/
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    ops::Deref,
+    str::FromStr,
+};
+
+use anyhow::Result;
+use sqlx::{
+    Connection, Executor, PgConnection, PgPool,
+    postgres::{PgConnectOptions, PgPoolOptions},
+};
+use uuid::Uuid;
+
+const DATABASE_PREFIX: &str = "expense_tracker_test_";
+
+// Key of the advisory lock held while the template is built
+const TEMPLATE_LOCK: i64 = 0x7465_7374_6462;
+
+pub struct TestDb {
+    pub pool: PgPool,
+    name: String,
+    admin_options: PgConnectOptions,
+}
+
+impl TestDb {
+    // The database of `database_url` itself is only used to create and drop the test ones
+    pub async fn create(database_url: &str) -> Result<Self> {
+        let admin_options = PgConnectOptions::from_str(database_url)?;
+        let template = ensure_template(&admin_options).await?;
+
+        let name = format!("{DATABASE_PREFIX}{}", Uuid::new_v4().simple());
+        let mut admin = PgConnection::connect_with(&admin_options).await?;
+        admin
+            .execute(format!(r#"CREATE DATABASE "{name}" TEMPLATE "{template}""#).as_str())
+            .await?;
+        admin.close().await?;
+
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect_with(admin_options.clone().database(&name))
+            .await?;
+        Ok(Self {
+            pool,
+            name,
+            admin_options,
+        })
+    }
+}
+
+impl Deref for TestDb {
+    type Target = PgPool;
+
+    fn deref(&self) -> &PgPool {
+        &self.pool
+    }
+}
+
+// Drop can't await, a thread with a runtime of its own drops the database
+impl Drop for TestDb {
+    fn drop(&mut self) {
+        let name = self.name.clone();
+        let options = self.admin_options.clone();
+        let dropped = std::thread::spawn(move || -> Result<()> {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()?;
+            runtime.block_on(async {
+                let mut admin = PgConnection::connect_with(&options).await?;
+                // FORCE ends the connections the pool still has open
+                admin
+                    .execute(format!(r#"DROP DATABASE IF EXISTS "{name}" WITH (FORCE)"#).as_str())
+                    .await?;
+                admin.close().await?;
+                Ok(())
+            })
+        })
+        .join();
+        if let Ok(Err(e)) = dropped {
+            eprintln!("Failed to drop test database {}: {:?}", self.name, e);
+        }
+    }
+}
+
+// Named after the migrations, adding or changing one builds a new template
+async fn ensure_template(admin_options: &PgConnectOptions) -> Result<String> {
+    let migrator = sqlx::migrate!("./migrations");
+    let mut hasher = DefaultHasher::new();
+    for migration in migrator.iter() {
+        migration.version.hash(&mut hasher);
+        migration.checksum.hash(&mut hasher);
+    }
+    let name = format!("{DATABASE_PREFIX}template_{:016x}", hasher.finish());
+
+    let mut admin = PgConnection::connect_with(admin_options).await?;
+    // Test binaries run in parallel too, the first one builds the template while the rest wait
+    sqlx::query("SELECT pg_advisory_lock($1)")
+        .bind(TEMPLATE_LOCK)
+        .execute(&mut admin)
+        .await?;
+    let exists: bool =
+        sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM pg_database WHERE datname = $1)")
+            .bind(&name)
+            .fetch_one(&mut admin)
+            .await?;
+    if !exists {
+        let stale: Vec<String> = sqlx::query_scalar(
+            "SELECT datname FROM pg_database WHERE datname LIKE $1 AND datname <> $2",
+        )
+        .bind(format!("{DATABASE_PREFIX}template\\_%"))
+        .bind(&name)
+        .fetch_all(&mut admin)
+        .await?;
+        for stale in stale {
+            admin
+                .execute(format!(r#"DROP DATABASE IF EXISTS "{stale}""#).as_str())
+                .await?;
+        }
+
+        // Migrated under another name first, a failed run never leaves a half migrated template
+        let building = format!("{name}_building");
+        admin
+            .execute(format!(r#"CREATE DATABASE "{building}""#).as_str())
+            .await?;
+        let pool = PgPoolOptions::new()
+            .max_connections(1)
+            .connect_with(admin_options.clone().database(&building))
+            .await?;
+        migrator.run(&pool).await?;
+        pool.close().await;
+        admin
+            .execute(format!(r#"ALTER DATABASE "{building}" RENAME TO "{name}""#).as_str())
+            .await?;
+    }
+    // Closing the connection releases the lock
+    admin.close().await?;
+    Ok(name)
+}
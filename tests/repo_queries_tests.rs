@@ -0,0 +1,1170 @@
+/*
+ Runs every query of the most used repos against the migrated schema. The repos build their
+ SQL with `format!`, so a renamed column or a wrong cast only shows up when the query runs;
+ these tests run each one once and roll back, so schema drift fails CI instead of a request.
+*/
+mod common;
+
+use anyhow::Result;
+use chrono::{Duration, NaiveDate, Utc};
+use common::TestDb;
+use expense_tracker::{
+    backup,
+    error::DatabaseError,
+    repos::{
+        audit_log::{AuditActor, AuditChange, AuditEntity, AuditRepo},
+        budget::{BudgetPeriodType, BudgetRepo, CreateBudgetDbPayload, UpdateBudgetDbPayload},
+        budget_alert::BudgetAlertRepo,
+        budget_plan::{BudgetPlanRepo, CreateBudgetPlanDbPayload, PlannedBudget},
+        category::{CategoryRepo, CreateCategoryDbPayload, UpdateCategoryDbPayload},
+        closed_period::{ClosedPeriodRepo, CreateClosedPeriodDbPayload},
+        debt::{CreateDebtDbPayload, DebtDirection, DebtRepo, UpdateDebtDbPayload},
+        email_ingest_address::EmailIngestAddressRepo,
+        expense_draft::{CreateExpenseDraftDbPayload, ExpenseDraftRepo},
+        expense_entry::{
+            CreateExpenseEntryDbPayload, ExpenseEntryListFilter, ExpenseEntryRepo,
+            ExpenseEntrySort, StatsGranularity, UpdateExpenseEntryDbPayload,
+        },
+        expense_group::{CreateExpenseGroupDbPayload, ExpenseGroupRepo},
+        expense_group_member::{CreateGroupMemberDbPayload, GroupMemberRepo},
+        overview::OverviewRepo,
+        reconciliation::{
+            CreateReconciliationDbPayload, CreateReconciliationRowDbPayload, ReconciliationRepo,
+        },
+        settlement::{CreateSettlementDbPayload, SettlementRepo},
+        subscription::{
+            CreateSubscriptionDbPayload, SubscriptionRepo, UpdateSubscriptionDbPayload,
+            UserUsageRepo,
+        },
+        user::{CreateUserDbPayload, UserRepo},
+    },
+    types::SubscriptionTier,
+    utils::{activity::group_activity, category_style, cursor::Cursor},
+};
+use rust_decimal_macros::dec;
+use sqlx::{Postgres, Transaction};
+use uuid::Uuid;
+
+async fn ensure_db_pool() -> Result<Option<TestDb>> {
+    let url = match std::env::var("DATABASE_URL") {
+        Ok(v) => v,
+        Err(_) => {
+            eprintln!("Skipping repo query tests: DATABASE_URL not set");
+            return Ok(None);
+        }
+    };
+    // A migrated database of the test's own, dropped when it ends
+    Ok(Some(TestDb::create(&url).await?))
+}
+
+// A user owning a group with one category, returns (user, group, category)
+async fn seed(tx: &mut Transaction<'_, Postgres>) -> Result<(Uuid, Uuid, Uuid)> {
+    let user = UserRepo::create(
+        tx,
+        CreateUserDbPayload {
+            email: format!("queries+{}@example.com", Uuid::new_v4()),
+            phash: "hash".into(),
+        },
+    )
+    .await?;
+    let group = ExpenseGroupRepo::create(
+        tx,
+        CreateExpenseGroupDbPayload {
+            name: "Queries".into(),
+            owner: user.uid,
+            start_over_date: 1,
+            currency: "IDR".to_string(),
+        },
+    )
+    .await?;
+    let category = CategoryRepo::create(
+        tx,
+        CreateCategoryDbPayload {
+            group_uid: group.uid,
+            name: "Groceries".into(),
+            description: None,
+            parent_uid: None,
+            icon: None,
+            color: None,
+        },
+    )
+    .await?;
+    Ok((user.uid, group.uid, category.uid))
+}
+
+#[tokio::test]
+async fn expense_entry_repo_queries() -> Result<()> {
+    let Some(pool) = ensure_db_pool().await? else {
+        return Ok(());
+    };
+    let mut tx = pool.begin().await?;
+    let (user_uid, group_uid, category_uid) = seed(&mut tx).await?;
+    let now = Utc::now();
+    let (start, end) = (now - Duration::days(30), now + Duration::days(1));
+
+    let entry = ExpenseEntryRepo::create_expense_entry(
+        &mut tx,
+        CreateExpenseEntryDbPayload {
+            price: dec!(12500),
+            currency: None,
+            product: "Kopi Susu".into(),
+            group_uid,
+            category_uid: Some(category_uid),
+            created_by: "test".into(),
+            created_by_user_uid: Some(user_uid),
+            created_at: None,
+            note: Some("with ice".into()),
+            envelope_uid: None,
+        },
+    )
+    .await?;
+    assert_eq!(entry.currency, "IDR");
+
+    ExpenseEntryRepo::list(&mut tx).await?;
+    assert_eq!(
+        ExpenseEntryRepo::list_by_group(&mut tx, group_uid)
+            .await?
+            .len(),
+        1
+    );
+    for sort in [
+        ExpenseEntrySort::CreatedAtDesc,
+        ExpenseEntrySort::CreatedAtAsc,
+        ExpenseEntrySort::PriceDesc,
+        ExpenseEntrySort::PriceAsc,
+        ExpenseEntrySort::ProductAsc,
+        ExpenseEntrySort::ProductDesc,
+    ] {
+        let filter = ExpenseEntryListFilter {
+            from: Some(start),
+            to: Some(end),
+            category_uid: Some(category_uid),
+            tag_uid: None,
+            envelope_uid: None,
+            min_price: Some(dec!(1)),
+            max_price: Some(dec!(100000)),
+            sort,
+            limit: 10,
+            offset: 0,
+            after: None,
+        };
+        let (entries, total) =
+            ExpenseEntryRepo::list_by_group_filtered(&mut tx, group_uid, &filter).await?;
+        assert_eq!((entries.len(), total), (1, 1));
+
+        // Nothing comes after the only entry, the total still counts it
+        let filter = ExpenseEntryListFilter {
+            after: Some(Cursor::new(entry.created_at, entry.uid)),
+            ..filter
+        };
+        let (entries, total) =
+            ExpenseEntryRepo::list_by_group_filtered(&mut tx, group_uid, &filter).await?;
+        let expected = if sort.supports_cursor() { 0 } else { 1 };
+        assert_eq!((entries.len(), total), (expected, 1));
+    }
+    ExpenseEntryRepo::search_by_group(&mut tx, group_uid, "kopi", 10).await?;
+
+    let totals =
+        ExpenseEntryRepo::sum_by_category_in_range(&mut tx, group_uid, category_uid, start, end)
+            .await?;
+    assert_eq!(totals, vec![("IDR".to_string(), dec!(12500))]);
+    ExpenseEntryRepo::totals_by_category_in_range(
+        &mut tx,
+        group_uid,
+        Some(user_uid),
+        None,
+        false,
+        start,
+        end,
+    )
+    .await?;
+    let days = ExpenseEntryRepo::totals_by_day_and_category(&mut tx, group_uid, start, end).await?;
+    assert_eq!(
+        days,
+        vec![(
+            entry.created_at.date_naive(),
+            Some(category_uid),
+            Some("Groceries".to_string()),
+            "IDR".to_string(),
+            dec!(12500)
+        )]
+    );
+    let windows =
+        ExpenseEntryRepo::sum_by_windows(&mut tx, group_uid, None, &[(start, end)]).await?;
+    assert_eq!(windows, vec![dec!(12500)]);
+    for granularity in [
+        StatsGranularity::Day,
+        StatsGranularity::Week,
+        StatsGranularity::Month,
+    ] {
+        ExpenseEntryRepo::totals_by_bucket(&mut tx, group_uid, None, granularity, start, end)
+            .await?;
+    }
+    ExpenseEntryRepo::top_products_in_range(&mut tx, group_uid, None, start, end, 5).await?;
+
+    let fetched = ExpenseEntryRepo::get(&mut tx, entry.uid).await?;
+    let updated = ExpenseEntryRepo::update(
+        &mut tx,
+        entry.uid,
+        UpdateExpenseEntryDbPayload {
+            price: Some(dec!(15000)),
+            currency: None,
+            product: None,
+            category_uid: None,
+            note: Some(String::new()),
+            envelope_uid: None,
+            expected_updated_at: Some(fetched.updated_at),
+        },
+    )
+    .await?;
+    assert_eq!(updated.note, None);
+    ExpenseEntryRepo::overwrite(&mut tx, &fetched).await?;
+    ExpenseEntryRepo::soft_delete(&mut tx, entry.uid).await?;
+    ExpenseEntryRepo::restore(&mut tx, entry.uid).await?;
+    ExpenseEntryRepo::count_expired(&mut tx, group_uid, now).await?;
+    ExpenseEntryRepo::delete_expired(&mut tx, group_uid, start).await?;
+    ExpenseEntryRepo::purge_deleted(&mut tx, group_uid, now).await?;
+    ExpenseEntryRepo::delete(&mut tx, entry.uid).await?;
+
+    drop(tx);
+    Ok(())
+}
+
+#[tokio::test]
+async fn category_repo_queries() -> Result<()> {
+    let Some(pool) = ensure_db_pool().await? else {
+        return Ok(());
+    };
+    let mut tx = pool.begin().await?;
+    let (_, group_uid, category_uid) = seed(&mut tx).await?;
+
+    CategoryRepo::list(&mut tx).await?;
+    assert_eq!(
+        CategoryRepo::list_by_group(&mut tx, group_uid).await?.len(),
+        1
+    );
+    assert_eq!(CategoryRepo::count_by_group(&mut tx, group_uid).await?, 1);
+    let category = CategoryRepo::get(&mut tx, category_uid).await?;
+    let updated = CategoryRepo::update(
+        &mut tx,
+        category_uid,
+        UpdateCategoryDbPayload {
+            name: Some("Supermarket".into()),
+            description: Some("weekly".into()),
+            parent_uid: None,
+            icon: Some("🛒".into()),
+            color: Some("#16A34A".into()),
+            expected_updated_at: None,
+        },
+    )
+    .await?;
+    assert_eq!(
+        (updated.icon.as_str(), updated.color.as_str()),
+        ("🛒", "#16a34a")
+    );
+    let stale = CategoryRepo::update(
+        &mut tx,
+        category_uid,
+        UpdateCategoryDbPayload {
+            name: Some("Groceries".into()),
+            description: None,
+            parent_uid: None,
+            icon: None,
+            color: None,
+            expected_updated_at: Some(category.updated_at - Duration::seconds(1)),
+        },
+    )
+    .await;
+    assert!(matches!(
+        stale,
+        Err(DatabaseError::ConcurrentModification(ref current)) if current["name"] == "Supermarket"
+    ));
+    let found = CategoryRepo::find_by_name_or_alias(&mut tx, group_uid, "Supermarket").await?;
+    assert_eq!(found.map(|c| c.uid), Some(category_uid));
+
+    let other = CategoryRepo::create(
+        &mut tx,
+        CreateCategoryDbPayload {
+            group_uid,
+            name: "Other".into(),
+            description: None,
+            parent_uid: None,
+            icon: None,
+            color: None,
+        },
+    )
+    .await?;
+    assert_eq!(
+        (other.icon.as_str(), other.color.as_str()),
+        (category_style::DEFAULT_ICON, category_style::DEFAULT_COLOR)
+    );
+    let child = CategoryRepo::create(
+        &mut tx,
+        CreateCategoryDbPayload {
+            group_uid,
+            name: "Snacks".into(),
+            description: None,
+            parent_uid: Some(category_uid),
+            icon: None,
+            color: None,
+        },
+    )
+    .await?;
+    assert_eq!(child.parent_uid, Some(category_uid));
+    let nested = CategoryRepo::create(
+        &mut tx,
+        CreateCategoryDbPayload {
+            group_uid,
+            name: "Chips".into(),
+            description: None,
+            parent_uid: Some(child.uid),
+            icon: None,
+            color: None,
+        },
+    )
+    .await;
+    assert!(matches!(nested, Err(DatabaseError::ConstraintViolation(_))));
+    let moved = CategoryRepo::update(
+        &mut tx,
+        category_uid,
+        UpdateCategoryDbPayload {
+            name: None,
+            description: None,
+            parent_uid: Some(Some(other.uid)),
+            icon: None,
+            color: None,
+            expected_updated_at: None,
+        },
+    )
+    .await;
+    assert!(matches!(moved, Err(DatabaseError::ConstraintViolation(_))));
+    CategoryRepo::delete_reassigning(&mut tx, category_uid, Some(other.uid)).await?;
+    assert_eq!(
+        CategoryRepo::get(&mut tx, child.uid).await?.parent_uid,
+        None
+    );
+    CategoryRepo::delete(&mut tx, child.uid).await?;
+    CategoryRepo::delete(&mut tx, other.uid).await?;
+
+    drop(tx);
+    Ok(())
+}
+
+#[tokio::test]
+async fn budget_repo_queries() -> Result<()> {
+    let Some(pool) = ensure_db_pool().await? else {
+        return Ok(());
+    };
+    let mut tx = pool.begin().await?;
+    let (_, group_uid, category_uid) = seed(&mut tx).await?;
+
+    let budget = BudgetRepo::create(
+        &mut tx,
+        CreateBudgetDbPayload {
+            group_uid,
+            category_uid,
+            amount: dec!(500000),
+            currency: None,
+            period_type: BudgetPeriodType::Monthly,
+            period_year: None,
+            period_month: None,
+            range_start: None,
+            range_end: None,
+            hard_limit: false,
+        },
+    )
+    .await?;
+    assert_eq!(budget.currency, "IDR");
+
+    BudgetRepo::list(&mut tx).await?;
+    assert_eq!(
+        BudgetRepo::list_by_group(&mut tx, group_uid).await?.len(),
+        1
+    );
+    assert_eq!(
+        BudgetRepo::list_by_groups(&mut tx, &[group_uid])
+            .await?
+            .len(),
+        1
+    );
+    let found = BudgetRepo::get_by_group_and_category(&mut tx, group_uid, category_uid).await?;
+    assert_eq!(found.map(|b| b.uid), Some(budget.uid));
+    assert_eq!(BudgetRepo::count_by_group(&mut tx, group_uid).await?, 1);
+    BudgetRepo::get(&mut tx, budget.uid).await?;
+    let updated = BudgetRepo::update(
+        &mut tx,
+        budget.uid,
+        UpdateBudgetDbPayload {
+            amount: Some(dec!(750000)),
+            currency: None,
+            period_type: None,
+            period_year: Some(2025),
+            period_month: Some(10),
+            range_start: None,
+            range_end: None,
+            hard_limit: Some(true),
+        },
+    )
+    .await?;
+    assert!(updated.hard_limit);
+    // Switching to yearly drops the monthly pin
+    let yearly = BudgetRepo::update(
+        &mut tx,
+        budget.uid,
+        UpdateBudgetDbPayload {
+            amount: None,
+            currency: None,
+            period_type: Some(BudgetPeriodType::Yearly),
+            period_year: None,
+            period_month: None,
+            range_start: None,
+            range_end: None,
+            hard_limit: None,
+        },
+    )
+    .await?;
+    assert_eq!(yearly.period_type, "yearly");
+    assert_eq!(yearly.period_month, None);
+    BudgetRepo::delete(&mut tx, budget.uid).await?;
+
+    let custom = BudgetRepo::create(
+        &mut tx,
+        CreateBudgetDbPayload {
+            group_uid,
+            category_uid,
+            amount: dec!(5000000),
+            currency: None,
+            period_type: BudgetPeriodType::Custom,
+            period_year: None,
+            period_month: None,
+            range_start: NaiveDate::from_ymd_opt(2025, 12, 1),
+            range_end: NaiveDate::from_ymd_opt(2026, 1, 1),
+            hard_limit: false,
+        },
+    )
+    .await?;
+    assert_eq!(custom.period_type(), BudgetPeriodType::Custom);
+    assert_eq!(custom.range_end, NaiveDate::from_ymd_opt(2026, 1, 1));
+    BudgetRepo::delete(&mut tx, custom.uid).await?;
+
+    let plan = BudgetPlanRepo::create(
+        &mut tx,
+        CreateBudgetPlanDbPayload {
+            group_uid,
+            name: "Tighter groceries".to_string(),
+            budgets: vec![PlannedBudget {
+                category_uid,
+                amount: dec!(400000),
+            }],
+        },
+    )
+    .await?;
+    let plans = BudgetPlanRepo::list_by_group(&mut tx, group_uid).await?;
+    assert_eq!(plans.len(), 1);
+    assert_eq!(plans[0].budgets, plan.budgets);
+    assert_eq!(
+        BudgetPlanRepo::get(&mut tx, plan.uid).await?.budgets.len(),
+        1
+    );
+    BudgetPlanRepo::delete(&mut tx, plan.uid).await?;
+
+    drop(tx);
+    Ok(())
+}
+
+#[tokio::test]
+async fn subscription_repo_queries() -> Result<()> {
+    let Some(pool) = ensure_db_pool().await? else {
+        return Ok(());
+    };
+    let mut tx = pool.begin().await?;
+    let (user_uid, _, _) = seed(&mut tx).await?;
+    let now = Utc::now();
+
+    let subscription = SubscriptionRepo::create(
+        &mut tx,
+        CreateSubscriptionDbPayload {
+            user_uid,
+            tier: SubscriptionTier::Personal,
+            status: Some("active".to_string()),
+            current_period_start: Some(now),
+            current_period_end: Some(now + Duration::days(30)),
+        },
+    )
+    .await?;
+
+    SubscriptionRepo::get_by_user(&mut tx, user_uid).await?;
+    SubscriptionRepo::get(&mut tx, subscription.id).await?;
+    SubscriptionRepo::list(&mut tx).await?;
+    SubscriptionRepo::update(
+        &mut tx,
+        subscription.id,
+        UpdateSubscriptionDbPayload {
+            tier: Some(SubscriptionTier::Family),
+            status: None,
+            current_period_start: None,
+            current_period_end: Some(None),
+            cancel_at_period_end: Some(true),
+        },
+    )
+    .await?;
+
+    let provider_subscription_id = format!("sub_{}", Uuid::new_v4());
+    SubscriptionRepo::link_provider(
+        &mut tx,
+        subscription.id,
+        SubscriptionTier::Team,
+        "stripe",
+        "cus_queries",
+        &provider_subscription_id,
+    )
+    .await?;
+    let linked = SubscriptionRepo::get_by_provider_subscription(
+        &mut tx,
+        "stripe",
+        &provider_subscription_id,
+    )
+    .await?;
+    assert_eq!(linked.id, subscription.id);
+    SubscriptionRepo::unlink_provider(&mut tx, subscription.id).await?;
+
+    let usage = UserUsageRepo::calculate_current_usage(&mut tx, user_uid).await?;
+    UserUsageRepo::create_or_update(&mut tx, usage).await?;
+    UserUsageRepo::get_current_usage(&mut tx, user_uid).await?;
+
+    assert!(matches!(
+        SubscriptionRepo::get(&mut tx, Uuid::new_v4()).await,
+        Err(DatabaseError::NotFound(_))
+    ));
+
+    drop(tx);
+    Ok(())
+}
+
+#[tokio::test]
+async fn settlement_repo_queries() -> Result<()> {
+    let Some(pool) = ensure_db_pool().await? else {
+        return Ok(());
+    };
+    let mut tx = pool.begin().await?;
+    let (owner_uid, group_uid, _) = seed(&mut tx).await?;
+    let member = UserRepo::create(
+        &mut tx,
+        CreateUserDbPayload {
+            email: format!("queries+{}@example.com", Uuid::new_v4()),
+            phash: "hash".into(),
+        },
+    )
+    .await?;
+    GroupMemberRepo::create(
+        &mut tx,
+        CreateGroupMemberDbPayload {
+            group_uid,
+            user_uid: member.uid,
+            role: "member".into(),
+        },
+    )
+    .await?;
+    ExpenseEntryRepo::create_expense_entry(
+        &mut tx,
+        CreateExpenseEntryDbPayload {
+            price: dec!(100000),
+            currency: None,
+            product: "Listrik".into(),
+            group_uid,
+            category_uid: None,
+            created_by: "test".into(),
+            created_by_user_uid: Some(owner_uid),
+            created_at: None,
+            note: None,
+            envelope_uid: None,
+        },
+    )
+    .await?;
+
+    let settlement = SettlementRepo::create(
+        &mut tx,
+        CreateSettlementDbPayload {
+            group_uid,
+            from_user_uid: member.uid,
+            to_user_uid: owner_uid,
+            amount: dec!(30000),
+            currency: "IDR".into(),
+            note: None,
+        },
+    )
+    .await?;
+    // Unconfirmed settlements don't count yet
+    let balances = SettlementRepo::member_balances(&mut tx, group_uid, "IDR").await?;
+    let by_member: Vec<_> = balances.iter().map(|b| (b.user_uid, b.balance)).collect();
+    assert_eq!(
+        by_member,
+        vec![(owner_uid, dec!(50000)), (member.uid, dec!(-50000))]
+    );
+
+    SettlementRepo::confirm(&mut tx, settlement.uid).await?;
+    let balances = SettlementRepo::member_balances(&mut tx, group_uid, "IDR").await?;
+    let by_member: Vec<_> = balances.iter().map(|b| (b.user_uid, b.balance)).collect();
+    assert_eq!(
+        by_member,
+        vec![(owner_uid, dec!(20000)), (member.uid, dec!(-20000))]
+    );
+    assert!(
+        SettlementRepo::member_balances(&mut tx, group_uid, "USD")
+            .await?
+            .iter()
+            .all(|b| b.balance.is_zero())
+    );
+
+    drop(tx);
+    Ok(())
+}
+
+#[tokio::test]
+async fn closed_period_repo_queries() -> Result<()> {
+    let Some(pool) = ensure_db_pool().await? else {
+        return Ok(());
+    };
+    let mut tx = pool.begin().await?;
+    let (user_uid, group_uid, category_uid) = seed(&mut tx).await?;
+    let now = Utc::now();
+    let (start, end) = (now - Duration::days(40), now - Duration::days(10));
+
+    let entry = ExpenseEntryRepo::create_expense_entry(
+        &mut tx,
+        CreateExpenseEntryDbPayload {
+            price: dec!(20000),
+            currency: None,
+            product: "Bakso".into(),
+            group_uid,
+            category_uid: Some(category_uid),
+            created_by: "test".into(),
+            created_by_user_uid: Some(user_uid),
+            created_at: Some(now - Duration::days(20)),
+            note: None,
+            envelope_uid: None,
+        },
+    )
+    .await?;
+    let closed = ClosedPeriodRepo::close(
+        &mut tx,
+        CreateClosedPeriodDbPayload {
+            group_uid,
+            period_year: 2025,
+            period_month: 9,
+            starts_at: start,
+            ends_at: end,
+            closed_by: Some(user_uid),
+        },
+    )
+    .await?;
+    assert_eq!(closed.map(|p| p.label()), Some("2025-09".to_string()));
+    let again = ClosedPeriodRepo::close(
+        &mut tx,
+        CreateClosedPeriodDbPayload {
+            group_uid,
+            period_year: 2025,
+            period_month: 9,
+            starts_at: start,
+            ends_at: end,
+            closed_by: None,
+        },
+    )
+    .await?;
+    assert!(again.is_none());
+    assert_eq!(
+        ClosedPeriodRepo::list_by_group(&mut tx, group_uid)
+            .await?
+            .len(),
+        1
+    );
+    assert!(
+        ClosedPeriodRepo::find_covering(&mut tx, group_uid, start, end)
+            .await?
+            .is_some()
+    );
+
+    assert!(matches!(
+        ExpenseEntryRepo::soft_delete(&mut tx, entry.uid).await,
+        Err(DatabaseError::PeriodClosed(period)) if period == "2025-09"
+    ));
+    // Deleting the category would move the locked entry to another category
+    assert!(matches!(
+        CategoryRepo::delete_reassigning(&mut tx, category_uid, None).await,
+        Err(DatabaseError::PeriodClosed(period)) if period == "2025-09"
+    ));
+    let backdated = |created_at| CreateExpenseEntryDbPayload {
+        price: dec!(15000),
+        currency: None,
+        product: "Es teh".into(),
+        group_uid,
+        category_uid: None,
+        created_by: "test".into(),
+        created_by_user_uid: Some(user_uid),
+        created_at: Some(created_at),
+        note: None,
+        envelope_uid: None,
+    };
+    assert!(matches!(
+        ExpenseEntryRepo::create_expense_entry(&mut tx, backdated(now - Duration::days(15))).await,
+        Err(DatabaseError::PeriodClosed(period)) if period == "2025-09"
+    ));
+    ExpenseEntryRepo::create_expense_entry(&mut tx, backdated(now - Duration::days(5))).await?;
+    assert!(
+        ClosedPeriodRepo::reopen(&mut tx, group_uid, 2025, 9)
+            .await?
+            .is_some()
+    );
+    ExpenseEntryRepo::soft_delete(&mut tx, entry.uid).await?;
+    assert_eq!(
+        CategoryRepo::delete_reassigning(&mut tx, category_uid, None).await?,
+        1
+    );
+
+    drop(tx);
+    Ok(())
+}
+
+#[tokio::test]
+async fn debt_repo_queries() -> Result<()> {
+    let Some(pool) = ensure_db_pool().await? else {
+        return Ok(());
+    };
+    let mut tx = pool.begin().await?;
+    let (_, group_uid, _) = seed(&mut tx).await?;
+    let today = Utc::now().date_naive();
+
+    let debt = DebtRepo::create(
+        &mut tx,
+        CreateDebtDbPayload {
+            group_uid,
+            direction: DebtDirection::Lent,
+            person: "Andi".into(),
+            amount: dec!(500000),
+            currency: None,
+            due_date: Some(today + Duration::days(10)),
+            note: None,
+        },
+    )
+    .await?;
+    assert_eq!(debt.direction(), DebtDirection::Lent);
+    assert_eq!(debt.currency, "IDR");
+    assert!(
+        DebtRepo::list_due_for_reminder(&mut tx, today + Duration::days(3))
+            .await?
+            .is_empty()
+    );
+
+    let updated = DebtRepo::update(
+        &mut tx,
+        debt.uid,
+        UpdateDebtDbPayload {
+            person: None,
+            amount: Some(dec!(450000)),
+            currency: None,
+            due_date: Some(today + Duration::days(2)),
+            note: Some("Pinjam untuk servis motor".into()),
+        },
+    )
+    .await?;
+    assert_eq!(updated.amount, dec!(450000));
+    let due = DebtRepo::list_due_for_reminder(&mut tx, today + Duration::days(3)).await?;
+    assert_eq!(
+        due.iter().map(|d| d.uid).collect::<Vec<_>>(),
+        vec![debt.uid]
+    );
+    DebtRepo::mark_reminded(&mut tx, &[debt.uid]).await?;
+    assert!(
+        DebtRepo::list_due_for_reminder(&mut tx, today + Duration::days(3))
+            .await?
+            .is_empty()
+    );
+
+    let settled = DebtRepo::set_settled(&mut tx, debt.uid, true).await?;
+    assert!(settled.is_settled());
+    assert!(
+        DebtRepo::list_by_group(&mut tx, group_uid, false)
+            .await?
+            .is_empty()
+    );
+    assert_eq!(
+        DebtRepo::list_by_group(&mut tx, group_uid, true)
+            .await?
+            .len(),
+        1
+    );
+    assert!(
+        !DebtRepo::set_settled(&mut tx, debt.uid, false)
+            .await?
+            .is_settled()
+    );
+    DebtRepo::delete(&mut tx, debt.uid).await?;
+    assert!(DebtRepo::get(&mut tx, debt.uid).await.is_err());
+
+    drop(tx);
+    Ok(())
+}
+
+#[tokio::test]
+async fn overview_repo_queries() -> Result<()> {
+    let Some(pool) = ensure_db_pool().await? else {
+        return Ok(());
+    };
+    let mut tx = pool.begin().await?;
+    let (user_uid, group_uid, category_uid) = seed(&mut tx).await?;
+    let now = Utc::now();
+    let (start, end) = (now - Duration::days(1), now + Duration::days(1));
+
+    ExpenseEntryRepo::create_expense_entry(
+        &mut tx,
+        CreateExpenseEntryDbPayload {
+            price: dec!(20000),
+            currency: None,
+            product: "Bakso".into(),
+            group_uid,
+            category_uid: Some(category_uid),
+            created_by: "test".into(),
+            created_by_user_uid: Some(user_uid),
+            created_at: None,
+            note: None,
+            envelope_uid: None,
+        },
+    )
+    .await?;
+    let budget = BudgetRepo::create(
+        &mut tx,
+        CreateBudgetDbPayload {
+            group_uid,
+            category_uid,
+            amount: dec!(25000),
+            currency: None,
+            period_type: BudgetPeriodType::Monthly,
+            period_year: None,
+            period_month: None,
+            range_start: None,
+            range_end: None,
+            hard_limit: false,
+        },
+    )
+    .await?;
+
+    let totals = OverviewRepo::group_period_totals(&mut tx, &[(group_uid, start, end)]).await?;
+    assert_eq!(totals.len(), 1);
+    assert_eq!(totals[0].total, dec!(20000));
+    assert_eq!(totals[0].entries, 1);
+    let spend = OverviewRepo::budget_period_spend(&mut tx, &[(budget.uid, start, end)]).await?;
+    assert_eq!(spend.len(), 1);
+    assert_eq!(spend[0].category_name, "Groceries");
+    OverviewRepo::member_counts(&mut tx, &[group_uid]).await?;
+    assert!(
+        OverviewRepo::pending_invites(&mut tx, user_uid, "nobody@example.com")
+            .await?
+            .is_empty()
+    );
+    let settings = BudgetAlertRepo::list_settings(&mut tx, &[group_uid]).await?;
+    assert_eq!(settings.len(), 1);
+    assert!(settings[0].enabled);
+
+    drop(tx);
+    Ok(())
+}
+
+#[tokio::test]
+async fn activity_feed_queries() -> Result<()> {
+    let Some(pool) = ensure_db_pool().await? else {
+        return Ok(());
+    };
+    let mut tx = pool.begin().await?;
+    let (user_uid, group_uid, category_uid) = seed(&mut tx).await?;
+    let actor = AuditActor {
+        user_uid: Some(user_uid),
+        name: None,
+        source: "web".into(),
+    };
+
+    let entry = ExpenseEntryRepo::create_expense_entry(
+        &mut tx,
+        CreateExpenseEntryDbPayload {
+            price: dec!(20000),
+            currency: None,
+            product: "Bakso".into(),
+            group_uid,
+            category_uid: Some(category_uid),
+            created_by: "test".into(),
+            created_by_user_uid: Some(user_uid),
+            created_at: None,
+            note: None,
+            envelope_uid: None,
+        },
+    )
+    .await?;
+    AuditRepo::record(
+        &mut tx,
+        &actor,
+        AuditEntity::ExpenseEntry,
+        group_uid,
+        entry.uid,
+        AuditChange::create(&entry),
+    )
+    .await?;
+    // Not part of the feed
+    let category = CategoryRepo::get(&mut tx, category_uid).await?;
+    AuditRepo::record(
+        &mut tx,
+        &actor,
+        AuditEntity::Category,
+        group_uid,
+        category_uid,
+        AuditChange::create(&category),
+    )
+    .await?;
+    let budget = BudgetRepo::create(
+        &mut tx,
+        CreateBudgetDbPayload {
+            group_uid,
+            category_uid,
+            amount: dec!(25000),
+            currency: None,
+            period_type: BudgetPeriodType::Monthly,
+            period_year: None,
+            period_month: None,
+            range_start: None,
+            range_end: None,
+            hard_limit: false,
+        },
+    )
+    .await?;
+    AuditRepo::record(
+        &mut tx,
+        &actor,
+        AuditEntity::Budget,
+        group_uid,
+        budget.uid,
+        AuditChange::create(&budget),
+    )
+    .await?;
+
+    let entities = [AuditEntity::ExpenseEntry, AuditEntity::Budget];
+    let (logs, total) =
+        AuditRepo::list_by_group_and_entities(&mut tx, group_uid, &entities, 10, 0, None).await?;
+    assert_eq!(total, 2);
+    assert_eq!(logs.len(), 2);
+    let (first, _) =
+        AuditRepo::list_by_group_and_entities(&mut tx, group_uid, &entities, 1, 0, None).await?;
+    let after = Cursor::new(first[0].created_at, first[0].uid);
+    let (rest, _) =
+        AuditRepo::list_by_group_and_entities(&mut tx, group_uid, &entities, 10, 0, Some(after))
+            .await?;
+    assert_eq!(rest.len(), 1);
+    assert_ne!(rest[0].uid, first[0].uid);
+
+    let emails = UserRepo::emails_by_uids(&mut tx, &[user_uid, Uuid::new_v4()]).await?;
+    assert_eq!(emails.len(), 1);
+    assert!(emails[&user_uid].starts_with("queries+"));
+
+    let (events, total) = group_activity(&mut tx, group_uid, 10, 0, None).await?;
+    assert_eq!(total, 2);
+    let expense = events
+        .iter()
+        .find(|event| event.kind == "expense_added")
+        .expect("expense event");
+    assert_eq!(expense.subject.as_deref(), Some("Bakso"));
+    assert_eq!(expense.amount, Some(dec!(20000)));
+    assert_eq!(
+        expense.actor_name.as_deref(),
+        emails[&user_uid].split('@').next()
+    );
+    let budget = events
+        .iter()
+        .find(|event| event.kind == "budget_added")
+        .expect("budget event");
+    assert_eq!(budget.subject.as_deref(), Some("Groceries"));
+
+    drop(tx);
+    Ok(())
+}
+
+#[tokio::test]
+async fn email_ingest_repo_queries() -> Result<()> {
+    let Some(pool) = ensure_db_pool().await? else {
+        return Ok(());
+    };
+    let mut tx = pool.begin().await?;
+    let (user_uid, group_uid, _) = seed(&mut tx).await?;
+
+    assert!(
+        EmailIngestAddressRepo::get_by_group(&mut tx, group_uid)
+            .await?
+            .is_none()
+    );
+    let address = EmailIngestAddressRepo::rotate(&mut tx, group_uid, user_uid).await?;
+    assert_eq!(
+        EmailIngestAddressRepo::find_group_by_token(&mut tx, &address.token).await?,
+        Some(group_uid)
+    );
+    let rotated = EmailIngestAddressRepo::rotate(&mut tx, group_uid, user_uid).await?;
+    assert_ne!(rotated.token, address.token);
+    assert!(
+        EmailIngestAddressRepo::find_group_by_token(&mut tx, &address.token)
+            .await?
+            .is_none()
+    );
+
+    let draft = ExpenseDraftRepo::create(
+        &mut tx,
+        CreateExpenseDraftDbPayload {
+            group_uid,
+            source: "email".into(),
+            merchant: Some("Grab".into()),
+            price: Some(dec!(43000)),
+            currency: Some("IDR".into()),
+            spent_at: Utc::now(),
+            sender: "budi@example.com".into(),
+            subject: "Your Grab E-Receipt".into(),
+            body: "Total Paid Rp 43.000".into(),
+        },
+    )
+    .await?;
+    assert_eq!(
+        ExpenseDraftRepo::get(&mut tx, draft.uid).await?.price,
+        Some(dec!(43000))
+    );
+    assert_eq!(
+        ExpenseDraftRepo::list_by_group(&mut tx, group_uid)
+            .await?
+            .len(),
+        1
+    );
+    assert_eq!(
+        ExpenseDraftRepo::count_by_group(&mut tx, group_uid).await?,
+        1
+    );
+    ExpenseDraftRepo::delete(&mut tx, draft.uid).await?;
+    assert!(matches!(
+        ExpenseDraftRepo::get(&mut tx, draft.uid).await,
+        Err(DatabaseError::NotFound(_))
+    ));
+
+    assert!(EmailIngestAddressRepo::delete(&mut tx, group_uid).await?);
+    assert!(!EmailIngestAddressRepo::delete(&mut tx, group_uid).await?);
+
+    drop(tx);
+    Ok(())
+}
+
+#[tokio::test]
+async fn reconciliation_repo_queries() -> Result<()> {
+    let Some(pool) = ensure_db_pool().await? else {
+        return Ok(());
+    };
+    let mut tx = pool.begin().await?;
+    let (user_uid, group_uid, category_uid) = seed(&mut tx).await?;
+
+    let today = Utc::now().date_naive();
+    let entry = ExpenseEntryRepo::create_expense_entry(
+        &mut tx,
+        CreateExpenseEntryDbPayload {
+            price: dec!(43000),
+            currency: None,
+            product: "Grab".into(),
+            group_uid,
+            category_uid: Some(category_uid),
+            created_by: "test".into(),
+            created_by_user_uid: Some(user_uid),
+            created_at: None,
+            note: None,
+            envelope_uid: None,
+        },
+    )
+    .await?;
+    let candidates = ReconciliationRepo::candidate_entries(
+        &mut tx,
+        group_uid,
+        "IDR",
+        today - Duration::days(3),
+        today + Duration::days(3),
+    )
+    .await?;
+    assert_eq!(candidates.len(), 1);
+    assert_eq!(candidates[0].uid, entry.uid);
+
+    let reconciliation = ReconciliationRepo::create(
+        &mut tx,
+        CreateReconciliationDbPayload {
+            group_uid,
+            file_name: Some("statement.csv".into()),
+            format: "csv".into(),
+            currency: "IDR".into(),
+            window_days: 3,
+            skipped_rows: 1,
+            created_by: user_uid,
+        },
+        vec![
+            CreateReconciliationRowDbPayload {
+                line: 1,
+                posted_on: today,
+                amount: dec!(43000),
+                description: "GRAB*RIDE".into(),
+                entry_uid: Some(entry.uid),
+            },
+            CreateReconciliationRowDbPayload {
+                line: 2,
+                posted_on: today,
+                amount: dec!(25500),
+                description: "Indomaret".into(),
+                entry_uid: None,
+            },
+        ],
+    )
+    .await?;
+    assert_eq!(reconciliation.matched_rows, 1);
+    assert_eq!(reconciliation.unmatched_rows, 1);
+    assert_eq!(
+        ReconciliationRepo::list_by_group(&mut tx, group_uid)
+            .await?
+            .len(),
+        1
+    );
+
+    let rows = ReconciliationRepo::rows(&mut tx, reconciliation.uid).await?;
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].entry_product.as_deref(), Some("Grab"));
+    assert!(rows[1].entry_uid.is_none());
+    ReconciliationRepo::set_created_entry(&mut tx, rows[1].uid, entry.uid).await?;
+    let rows = ReconciliationRepo::rows(&mut tx, reconciliation.uid).await?;
+    assert!(rows[1].created_entry);
+
+    ReconciliationRepo::delete(&mut tx, reconciliation.uid).await?;
+    assert!(matches!(
+        ReconciliationRepo::get(&mut tx, reconciliation.uid).await,
+        Err(DatabaseError::NotFound(_))
+    ));
+
+    drop(tx);
+    Ok(())
+}
+
+#[tokio::test]
+async fn backup_dump_queries() -> Result<()> {
+    let Some(pool) = ensure_db_pool().await? else {
+        return Ok(());
+    };
+
+    let archive = backup::dump(&pool).await?;
+    assert!(archive.schema_version > 0);
+    let names: Vec<&str> = archive
+        .tables
+        .iter()
+        .map(|table| table.name.as_str())
+        .collect();
+    assert!(!names.contains(&"_sqlx_migrations"));
+    let position = |name: &str| names.iter().position(|table| *table == name).unwrap();
+    assert!(position("users") < position("expense_groups"));
+    assert!(position("expense_groups") < position("expense_entries"));
+    let users = &archive.tables[position("users")];
+    assert!(users.columns.iter().any(|column| column.name == "email"));
+
+    let decoded = backup::Archive::decode(&archive.encode()?)?;
+    assert_eq!(decoded.row_count(), archive.row_count());
+    Ok(())
+}
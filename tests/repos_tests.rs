@@ -1,20 +1,27 @@
+mod common;
+
 use anyhow::Result;
+use common::TestDb;
 use expense_tracker::middleware::tier::check_tier_limit;
 use expense_tracker::types::SubscriptionTier;
 use expense_tracker::{
-    db::make_db_pool,
     repos::{
-        budget::{BudgetRepo, CreateBudgetDbPayload},
+        budget::{BudgetPeriodType, BudgetRepo, CreateBudgetDbPayload},
         category::{CategoryRepo, CreateCategoryDbPayload, UpdateCategoryDbPayload},
         expense_group::{CreateExpenseGroupDbPayload, ExpenseGroupRepo},
+        income_entry::{CreateIncomeEntryDbPayload, IncomeEntryRepo, UpdateIncomeEntryDbPayload},
+        job::{JobRepo, JobStatus},
         subscription::{CreateSubscriptionDbPayload, SubscriptionRepo},
         user::{CreateUserDbPayload, UpdateUserDbPayload, UserRepo},
+        webhook::{CreateWebhookDbPayload, DeliveryStatus, WebhookDeliveryRepo, WebhookRepo},
     },
+    webhooks::{self, WebhookEvent},
 };
-use sqlx::PgPool;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
 use uuid::Uuid;
 
-async fn ensure_db_pool() -> Result<Option<PgPool>> {
+async fn ensure_db_pool() -> Result<Option<TestDb>> {
     let url = match std::env::var("DATABASE_URL") {
         Ok(v) => v,
         Err(_) => {
@@ -22,10 +29,8 @@ async fn ensure_db_pool() -> Result<Option<PgPool>> {
             return Ok(None);
         }
     };
-    let pool = make_db_pool(&url).await?;
-    // Run migrations to ensure schema exists
-    sqlx::migrate!("./migrations").run(&pool).await?;
-    Ok(Some(pool))
+    // A migrated database of the test's own, dropped when it ends
+    Ok(Some(TestDb::create(&url).await?))
 }
 
 #[tokio::test]
@@ -59,6 +64,7 @@ async fn user_repo_crud_smoke() -> Result<()> {
         UpdateUserDbPayload {
             email: Some(new_email.clone()),
             phash: None,
+            lang: None,
         },
     )
     .await?;
@@ -91,6 +97,7 @@ async fn category_repo_crud_smoke() -> Result<()> {
             name: "Test Group".into(),
             owner: owner.uid,
             start_over_date: 1,
+            currency: "IDR".to_string(),
         },
     )
     .await?;
@@ -102,6 +109,9 @@ async fn category_repo_crud_smoke() -> Result<()> {
             group_uid: group.uid,
             name: "Groceries".into(),
             description: Some("food".into()),
+            parent_uid: None,
+            icon: None,
+            color: None,
         },
     )
     .await?;
@@ -118,6 +128,10 @@ async fn category_repo_crud_smoke() -> Result<()> {
         UpdateCategoryDbPayload {
             name: Some("Supermarket".into()),
             description: None,
+            parent_uid: None,
+            icon: None,
+            color: None,
+            expected_updated_at: None,
         },
     )
     .await?;
@@ -153,6 +167,7 @@ async fn category_repo_list_and_count() -> Result<()> {
             name: "Test Group 1".into(),
             owner: owner.uid,
             start_over_date: 1,
+            currency: "IDR".to_string(),
         },
     )
     .await?;
@@ -162,6 +177,7 @@ async fn category_repo_list_and_count() -> Result<()> {
             name: "Test Group 2".into(),
             owner: owner.uid,
             start_over_date: 1,
+            currency: "IDR".to_string(),
         },
     )
     .await?;
@@ -173,6 +189,9 @@ async fn category_repo_list_and_count() -> Result<()> {
             group_uid: group1.uid,
             name: "Groceries".into(),
             description: Some("food".into()),
+            parent_uid: None,
+            icon: None,
+            color: None,
         },
     )
     .await?;
@@ -182,6 +201,9 @@ async fn category_repo_list_and_count() -> Result<()> {
             group_uid: group1.uid,
             name: "Transport".into(),
             description: None,
+            parent_uid: None,
+            icon: None,
+            color: None,
         },
     )
     .await?;
@@ -191,6 +213,9 @@ async fn category_repo_list_and_count() -> Result<()> {
             group_uid: group2.uid,
             name: "Entertainment".into(),
             description: Some("fun".into()),
+            parent_uid: None,
+            icon: None,
+            color: None,
         },
     )
     .await?;
@@ -228,6 +253,7 @@ async fn category_repo_list_and_count() -> Result<()> {
             name: "Empty Group".into(),
             owner: owner.uid,
             start_over_date: 1,
+            currency: "IDR".to_string(),
         },
     )
     .await?;
@@ -276,6 +302,7 @@ async fn tier_limits_enforcement_test() -> Result<()> {
             name: "Test Group 1".into(),
             owner: user.uid,
             start_over_date: 1,
+            currency: "IDR".to_string(),
         },
     )
     .await?;
@@ -291,6 +318,9 @@ async fn tier_limits_enforcement_test() -> Result<()> {
                 group_uid: group1.uid,
                 name: format!("Category {}", i),
                 description: None,
+                parent_uid: None,
+                icon: None,
+                color: None,
             },
         )
         .await?;
@@ -304,6 +334,9 @@ async fn tier_limits_enforcement_test() -> Result<()> {
             group_uid: group1.uid,
             name: "Budget Test Category".into(),
             description: None,
+            parent_uid: None,
+            icon: None,
+            color: None,
         },
     )
     .await?;
@@ -314,9 +347,14 @@ async fn tier_limits_enforcement_test() -> Result<()> {
             CreateBudgetDbPayload {
                 group_uid: group1.uid,
                 category_uid: category.uid,
-                amount: 100.0 * i as f64,
+                amount: Decimal::from(100 * i),
+                currency: None,
+                period_type: BudgetPeriodType::Monthly,
                 period_year: None,
                 period_month: None,
+                range_start: None,
+                range_end: None,
+                hard_limit: false,
             },
         )
         .await?;
@@ -369,6 +407,7 @@ async fn expense_group_repo_crud() -> Result<()> {
             name: group_name.into(),
             owner: user.uid,
             start_over_date: 1,
+            currency: "IDR".to_string(),
         },
     )
     .await?;
@@ -399,6 +438,7 @@ async fn expense_group_repo_crud() -> Result<()> {
         expense_tracker::repos::expense_group::UpdateExpenseGroupDbPayload {
             name: Some(new_name.into()),
             start_over_date: None,
+            currency: None,
         },
     )
     .await?;
@@ -411,6 +451,16 @@ async fn expense_group_repo_crud() -> Result<()> {
     let our_group = all_groups.iter().find(|g| g.uid == created.uid).unwrap();
     assert_eq!(our_group.name, new_name);
 
+    // Test soft delete and restore
+    ExpenseGroupRepo::soft_delete(&mut tx, created.uid).await?;
+    assert!(ExpenseGroupRepo::get(&mut tx, created.uid).await.is_err());
+    assert_eq!(ExpenseGroupRepo::count_by_owner(&mut tx, user.uid).await?, 0);
+    let deleted = ExpenseGroupRepo::list_deleted(&mut tx).await?;
+    assert!(deleted.iter().any(|g| g.uid == created.uid));
+    let restored = ExpenseGroupRepo::restore(&mut tx, created.uid).await?;
+    assert_eq!(restored.name, new_name);
+    assert!(ExpenseGroupRepo::restore(&mut tx, created.uid).await.is_err());
+
     // Test delete
     ExpenseGroupRepo::delete(&mut tx, created.uid).await?;
 
@@ -460,6 +510,7 @@ async fn expense_group_repo_multiple_owners() -> Result<()> {
             name: "User1 Group".into(),
             owner: user1.uid,
             start_over_date: 1,
+            currency: "IDR".to_string(),
         },
     )
     .await?;
@@ -470,6 +521,7 @@ async fn expense_group_repo_multiple_owners() -> Result<()> {
             name: "User2 Group".into(),
             owner: user2.uid,
             start_over_date: 1,
+            currency: "IDR".to_string(),
         },
     )
     .await?;
@@ -494,3 +546,224 @@ async fn expense_group_repo_multiple_owners() -> Result<()> {
     drop(tx);
     Ok(())
 }
+
+#[tokio::test]
+async fn income_entry_repo_crud_and_sum() -> Result<()> {
+    let Some(pool) = ensure_db_pool().await? else {
+        return Ok(());
+    };
+    let mut tx = pool.begin().await?;
+
+    // prerequisites: user and group
+    let owner = UserRepo::create(
+        &mut tx,
+        CreateUserDbPayload {
+            email: format!("income+{}@example.com", Uuid::new_v4()),
+            phash: "hash".into(),
+        },
+    )
+    .await?;
+    let group = ExpenseGroupRepo::create(
+        &mut tx,
+        CreateExpenseGroupDbPayload {
+            name: "Income Group".into(),
+            owner: owner.uid,
+            start_over_date: 1,
+            currency: "IDR".to_string(),
+        },
+    )
+    .await?;
+
+    let salary = IncomeEntryRepo::create(
+        &mut tx,
+        CreateIncomeEntryDbPayload {
+            amount: dec!(10_000_000),
+            source: "Gaji".into(),
+            group_uid: group.uid,
+        },
+    )
+    .await?;
+    IncomeEntryRepo::create(
+        &mut tx,
+        CreateIncomeEntryDbPayload {
+            amount: dec!(500_000),
+            source: "Transfer".into(),
+            group_uid: group.uid,
+        },
+    )
+    .await?;
+
+    let listed = IncomeEntryRepo::list_by_group(&mut tx, group.uid).await?;
+    assert_eq!(listed.len(), 2);
+
+    let updated = IncomeEntryRepo::update(
+        &mut tx,
+        salary.uid,
+        UpdateIncomeEntryDbPayload {
+            amount: Some(dec!(12_000_000)),
+            source: None,
+        },
+    )
+    .await?;
+    assert_eq!(updated.amount, dec!(12_000_000));
+    assert_eq!(updated.source, "Gaji");
+
+    let now = chrono::Utc::now();
+    let total = IncomeEntryRepo::sum_by_group_in_range(
+        &mut tx,
+        group.uid,
+        now - chrono::Duration::days(1),
+        now + chrono::Duration::days(1),
+    )
+    .await?;
+    assert_eq!(total, dec!(12_500_000));
+
+    IncomeEntryRepo::delete(&mut tx, salary.uid).await?;
+    assert!(IncomeEntryRepo::get(&mut tx, salary.uid).await.is_err());
+
+    // rollback test data implicitly by dropping tx
+    drop(tx);
+    Ok(())
+}
+
+#[tokio::test]
+async fn job_repo_retries_then_dead_letters() -> Result<()> {
+    let Some(pool) = ensure_db_pool().await? else {
+        return Ok(());
+    };
+    let mut tx = pool.begin().await?;
+
+    // Earlier queued jobs of other runs are claimed first, give this one its own kind
+    let kind = format!("test_{}", Uuid::new_v4());
+    let queued = JobRepo::enqueue(
+        &mut tx,
+        &kind,
+        &serde_json::json!({ "n": 1 }),
+        Some(chrono::Utc::now() - chrono::Duration::days(365)),
+    )
+    .await?;
+    assert_eq!(queued.status, "queued");
+    assert_eq!(queued.attempts, 0);
+
+    let claimed = JobRepo::claim_due(
+        &mut tx,
+        1,
+        chrono::Utc::now() - chrono::Duration::minutes(15),
+    )
+    .await?;
+    let job = claimed.into_iter().next().expect("a due job");
+    assert_eq!(job.uid, queued.uid);
+    assert_eq!(job.status, "running");
+    assert_eq!(job.attempts, 1);
+
+    // Failed but with attempts left: queued again for later
+    let now = chrono::Utc::now();
+    assert_eq!(
+        JobRepo::mark_failed(&mut tx, &job, "boom", now).await?,
+        JobStatus::Queued
+    );
+    let (listed, total) =
+        JobRepo::list(&mut tx, Some(JobStatus::Queued), Some(&kind), 10, 0).await?;
+    assert_eq!(total, 1);
+    assert!(listed[0].run_at > now);
+    assert_eq!(listed[0].last_error.as_deref(), Some("boom"));
+
+    // Out of attempts: dead
+    let exhausted = expense_tracker::repos::job::Job {
+        attempts: job.max_attempts,
+        ..job
+    };
+    assert_eq!(
+        JobRepo::mark_failed(&mut tx, &exhausted, "boom", now).await?,
+        JobStatus::Dead
+    );
+    let (_, dead) = JobRepo::list(&mut tx, Some(JobStatus::Dead), Some(&kind), 10, 0).await?;
+    assert_eq!(dead, 1);
+
+    // rollback test data implicitly by dropping tx
+    drop(tx);
+    Ok(())
+}
+
+#[tokio::test]
+async fn webhook_dispatch_queues_subscribed_deliveries() -> Result<()> {
+    let Some(pool) = ensure_db_pool().await? else {
+        return Ok(());
+    };
+    let mut tx = pool.begin().await?;
+
+    let owner = UserRepo::create(
+        &mut tx,
+        CreateUserDbPayload {
+            email: format!("webhook+{}@example.com", Uuid::new_v4()),
+            phash: "hash".into(),
+        },
+    )
+    .await?;
+    let group = ExpenseGroupRepo::create(
+        &mut tx,
+        CreateExpenseGroupDbPayload {
+            name: "Webhook Group".into(),
+            owner: owner.uid,
+            start_over_date: 1,
+            currency: "IDR".to_string(),
+        },
+    )
+    .await?;
+    let webhook = WebhookRepo::create(
+        &mut tx,
+        CreateWebhookDbPayload {
+            group_uid: group.uid,
+            url: "https://hooks.example.com/expenses".to_string(),
+            events: vec![WebhookEvent::ExpenseCreated.as_str().to_string()],
+            created_by: owner.uid,
+        },
+    )
+    .await?;
+    assert_eq!(webhook.secret.len(), 64);
+
+    let entry = serde_json::json!({ "product": "Kopi" });
+    webhooks::dispatch(&mut tx, group.uid, WebhookEvent::ExpenseCreated, &entry).await?;
+    // Not subscribed
+    webhooks::dispatch(&mut tx, group.uid, WebhookEvent::ExpenseDeleted, &entry).await?;
+
+    let (deliveries, total) =
+        WebhookDeliveryRepo::list_by_webhook(&mut tx, webhook.uid, 10, 0).await?;
+    assert_eq!(total, 1);
+    let delivery = &deliveries[0];
+    assert_eq!(delivery.event, "expense.created");
+    assert_eq!(delivery.status, "pending");
+    assert_eq!(delivery.payload["group_uid"], group.uid.to_string());
+    assert_eq!(delivery.payload["data"], entry);
+
+    WebhookDeliveryRepo::record_attempt(
+        &mut tx,
+        delivery.uid,
+        DeliveryStatus::Succeeded,
+        Some(204),
+        None,
+    )
+    .await?;
+    let delivered = WebhookDeliveryRepo::get(&mut tx, delivery.uid).await?;
+    assert_eq!(delivered.status, "succeeded");
+    assert_eq!(delivered.attempts, 1);
+    assert!(delivered.delivered_at.is_some());
+
+    // Inactive webhooks receive nothing
+    WebhookRepo::update(
+        &mut tx,
+        webhook.uid,
+        expense_tracker::repos::webhook::UpdateWebhookDbPayload {
+            active: Some(false),
+            ..Default::default()
+        },
+    )
+    .await?;
+    webhooks::dispatch(&mut tx, group.uid, WebhookEvent::ExpenseCreated, &entry).await?;
+    let (_, total) = WebhookDeliveryRepo::list_by_webhook(&mut tx, webhook.uid, 10, 0).await?;
+    assert_eq!(total, 1);
+
+    // rollback test data implicitly by dropping tx
+    drop(tx);
+    Ok(())
+}